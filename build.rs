@@ -8,7 +8,7 @@ use std::path::{Path, PathBuf};
 use builders::{
     build_apps, build_drivers, build_module, build_newlib, build_service, build_user_libs,
     build_utils, copy_newlib_libs, create_ext2_image, create_initfs_image, default_modules,
-    parse_service_index, setup_fs_layout,
+    embed_kernel_symbols, parse_service_index, setup_fs_layout,
 };
 
 const BUSYBOX_URL: &str = "https://busybox.net/downloads/binaries/1.35.0-x86_64-linux-musl/busybox";
@@ -29,9 +29,19 @@ fn build_kernel(manifest_dir: &PathBuf, fs_dir: &PathBuf, profile: &str) {
     cmd.env("MOCHIOS_BUILDING_KERNEL", "1");
     cmd.env("CARGO_TARGET_DIR", &kernel_target_dir);
     cmd.args(["build", "-Z", "build-std=core,alloc"]);
+    // 外側のビルドの RUSTFLAGS が src/core/.cargo/config.toml の rustflags
+    // （フレームポインタ）を上書きしないようにする
+    cmd.env_remove("RUSTFLAGS");
+    cmd.env_remove("CARGO_ENCODED_RUSTFLAGS");
     if profile == "release" {
         cmd.arg("--release");
     }
+    // MOCHIOS_KERNEL_FEATURES=ktest のようにカーネルのフィーチャを指定する
+    if let Ok(features) = env::var("MOCHIOS_KERNEL_FEATURES") {
+        if !features.trim().is_empty() {
+            cmd.args(["--features", features.trim()]);
+        }
+    }
     let status = cmd.status();
     match status {
         Ok(s) if s.success() => {}
@@ -59,6 +69,10 @@ fn build_kernel(manifest_dir: &PathBuf, fs_dir: &PathBuf, profile: &str) {
     fs::copy(&kernel_bin, &dest)
         .unwrap_or_else(|e| panic!("failed to copy kernel ELF to {}: {}", dest.display(), e));
     println!("Kernel ELF copied to {}", dest.display());
+    match embed_kernel_symbols(&dest) {
+        Ok(count) => println!("Embedded {} kernel symbols", count),
+        Err(e) => println!("cargo:warning=kernel symbols not embedded: {}", e),
+    }
 }
 
 fn is_elf_binary(path: &Path) -> Result<bool, String> {
@@ -361,6 +375,10 @@ fn main() {
     println!("cargo:rerun-if-env-changed=PROFILE");
     println!("cargo:rerun-if-env-changed=TARGET");
     println!("cargo:rerun-if-env-changed=CARGO_TARGET_DIR");
+    println!("cargo:rerun-if-env-changed=MOCHIOS_KERNEL_FEATURES");
+    println!("cargo:rerun-if-env-changed=START_TEST_APP");
+    println!("cargo:rerun-if-env-changed=START_FUZZER");
+    println!("cargo:rerun-if-env-changed=MOCHIOS_FUZZ_ARGS");
 
     // カーネルビルドの再帰呼び出しの場合はプレースホルダーだけ作成して終了する
     // (initfs は埋め込まず、ブートローダーが実行時にロードして BootInfo で渡す)
@@ -505,6 +523,10 @@ fn main() {
     let services_base_dir = manifest_dir.join("src/services");
 
     for service in &services {
+        // dir の無いエントリはマニフェストだけ（ビルドしない）
+        if service.dir.is_empty() {
+            continue;
+        }
         let output_dir = if service.fs_type == "initfs" {
             &ramfs_dir
         } else {
@@ -579,28 +601,13 @@ fn main() {
             e
         ),
     }
-    // services.index に基づき、initfs 以外の autostart サービス一覧を生成 (Config/services.list)
-    let mut services_autostart_entries: Vec<String> = Vec::new();
-    for svc in &services {
-        if svc.autostart {
-            if svc.fs_type != "initfs" {
-                services_autostart_entries.push(format!("/system/services/{}.service", svc.name));
-            } else {
-                // 場合によっては initfs に autostart=true が設定されていることがある。
-                // 開発者に分かるようにビルド時警告を出す。
-                println!("cargo:warning=Autostart service '{}' skipped for services.list because fs='initfs'. If you want it on ATA, set fs = 'ata' in src/services/index.toml", svc.name);
-            }
-        }
-    }
-    let services_autostart_path = fs_dir.join("config").join("services.list");
-    match fs::write(
-        &services_autostart_path,
-        services_autostart_entries.join("\n"),
-    ) {
-        Ok(_) => println!("Generated {}", services_autostart_path.display()),
+    // core.service が実行時に読むサービスマニフェスト (config/services.toml)
+    let services_manifest_path = fs_dir.join("config").join("services.toml");
+    match fs::copy(&index_path, &services_manifest_path) {
+        Ok(_) => println!("Copied {}", services_manifest_path.display()),
         Err(e) => panic!(
             "Failed to write critical config {}: {}",
-            services_autostart_path.display(),
+            services_manifest_path.display(),
             e
         ),
     }
@@ -0,0 +1,153 @@
+use std::fs;
+use std::path::Path;
+
+/// カーネル側の表の形式（src/core/backtrace/ksyms.rs と合わせる）
+const MAGIC: &[u8; 4] = b"KSYM";
+const HEADER_SIZE: usize = 16;
+const ENTRY_SIZE: usize = 16;
+
+const SHT_SYMTAB: u32 = 2;
+const STT_FUNC: u8 = 2;
+
+struct Section {
+    name: u32,
+    kind: u32,
+    offset: usize,
+    size: usize,
+    link: u32,
+}
+
+fn u16_at(data: &[u8], off: usize) -> Result<u16, String> {
+    data.get(off..off + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| format!("ELF truncated at {:#x}", off))
+}
+
+fn u32_at(data: &[u8], off: usize) -> Result<u32, String> {
+    data.get(off..off + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| format!("ELF truncated at {:#x}", off))
+}
+
+fn u64_at(data: &[u8], off: usize) -> Result<u64, String> {
+    data.get(off..off + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| format!("ELF truncated at {:#x}", off))
+}
+
+fn c_str(data: &[u8], off: usize) -> &[u8] {
+    let rest = data.get(off..).unwrap_or(&[]);
+    let len = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+    &rest[..len]
+}
+
+fn sections(elf: &[u8]) -> Result<Vec<Section>, String> {
+    if elf.len() < 64 || elf[..4] != [0x7F, b'E', b'L', b'F'] || elf[4] != 2 {
+        return Err("not an ELF64 file".to_string());
+    }
+    let shoff = u64_at(elf, 0x28)? as usize;
+    let shentsize = u16_at(elf, 0x3A)? as usize;
+    let shnum = u16_at(elf, 0x3C)? as usize;
+    (0..shnum)
+        .map(|i| {
+            let base = shoff + i * shentsize;
+            Ok(Section {
+                name: u32_at(elf, base)?,
+                kind: u32_at(elf, base + 4)?,
+                offset: u64_at(elf, base + 0x18)? as usize,
+                size: u64_at(elf, base + 0x20)? as usize,
+                link: u32_at(elf, base + 0x28)?,
+            })
+        })
+        .collect()
+}
+
+/// カーネル ELF の `.symtab` から関数シンボルを集め、`.ksyms` セクションに書き込む
+///
+/// バックトレースで関数名を引くための表。名前はマングルされたまま格納し、
+/// カーネル側で表示時に戻す。セクションに収まらない分は捨てる。
+/// 書き込んだシンボル数を返す。
+pub fn embed_kernel_symbols(elf_path: &Path) -> Result<usize, String> {
+    let mut elf =
+        fs::read(elf_path).map_err(|e| format!("Failed to read {}: {}", elf_path.display(), e))?;
+    let sections = sections(&elf)?;
+    let shstrndx = u16_at(&elf, 0x3E)? as usize;
+    let shstrtab = sections
+        .get(shstrndx)
+        .ok_or("section name table not found")?;
+    let ksyms = sections
+        .iter()
+        .find(|s| c_str(&elf, shstrtab.offset + s.name as usize) == b".ksyms")
+        .ok_or(".ksyms section not found")?;
+    let symtab = sections
+        .iter()
+        .find(|s| s.kind == SHT_SYMTAB)
+        .ok_or("kernel ELF has no .symtab (stripped?)")?;
+    let strtab = sections
+        .get(symtab.link as usize)
+        .ok_or("symbol string table not found")?;
+
+    let mut funcs: Vec<(u64, u32, Vec<u8>)> = Vec::new();
+    for i in 0..symtab.size / 24 {
+        let base = symtab.offset + i * 24;
+        let name = u32_at(&elf, base)?;
+        let info = *elf.get(base + 4).ok_or("ELF truncated in .symtab")?;
+        let shndx = u16_at(&elf, base + 6)?;
+        let value = u64_at(&elf, base + 8)?;
+        let size = u64_at(&elf, base + 16)?;
+        if info & 0xf != STT_FUNC || value == 0 || shndx == 0 {
+            continue;
+        }
+        let name = c_str(&elf, strtab.offset + name as usize);
+        if name.is_empty() {
+            continue;
+        }
+        funcs.push((value, size.min(u32::MAX as u64) as u32, name.to_vec()));
+    }
+    funcs.sort_by_key(|f| f.0);
+    funcs.dedup_by_key(|f| f.0);
+
+    // 入るだけ詰める（エントリと名前の両方が収まる数を求める）
+    let capacity = ksyms.size;
+    let mut count = 0;
+    let mut names_len = 0;
+    for (_, _, name) in &funcs {
+        let need = HEADER_SIZE + (count + 1) * ENTRY_SIZE + names_len + name.len() + 1;
+        if need > capacity {
+            println!(
+                "cargo:warning=.ksyms is full: embedded {} of {} kernel symbols",
+                count,
+                funcs.len()
+            );
+            break;
+        }
+        count += 1;
+        names_len += name.len() + 1;
+    }
+
+    let strtab_off = HEADER_SIZE + count * ENTRY_SIZE;
+    let mut table = Vec::with_capacity(capacity);
+    table.extend_from_slice(MAGIC);
+    table.extend_from_slice(&(count as u32).to_le_bytes());
+    table.extend_from_slice(&(strtab_off as u32).to_le_bytes());
+    table.extend_from_slice(&(names_len as u32).to_le_bytes());
+    let mut name_off = 0u32;
+    for (addr, size, name) in &funcs[..count] {
+        table.extend_from_slice(&addr.to_le_bytes());
+        table.extend_from_slice(&size.to_le_bytes());
+        table.extend_from_slice(&name_off.to_le_bytes());
+        name_off += name.len() as u32 + 1;
+    }
+    for (_, _, name) in &funcs[..count] {
+        table.extend_from_slice(name);
+        table.push(0);
+    }
+    table.resize(capacity, 0);
+
+    elf.get_mut(ksyms.offset..ksyms.offset + capacity)
+        .ok_or(".ksyms has no file contents")?
+        .copy_from_slice(&table);
+    fs::write(elf_path, &elf)
+        .map_err(|e| format!("Failed to write {}: {}", elf_path.display(), e))?;
+    Ok(count)
+}
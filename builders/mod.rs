@@ -1,6 +1,7 @@
 pub mod apps;
 pub mod drivers;
 pub mod fs_image;
+pub mod ksyms;
 pub mod modules;
 pub mod newlib;
 pub mod services;
@@ -9,6 +10,7 @@ pub mod utils;
 pub use apps::{build_apps, build_utils};
 pub use drivers::build_drivers;
 pub use fs_image::{copy_newlib_libs, create_ext2_image, create_initfs_image, setup_fs_layout};
+pub use ksyms::embed_kernel_symbols;
 pub use modules::{build_module, default_modules};
 pub use newlib::{build_newlib, build_user_libs};
 pub use services::{build_service, parse_service_index};
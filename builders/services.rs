@@ -196,11 +196,25 @@ pub fn build_service(
         println!("  Using target from .cargo/config.toml");
     }
 
-    if service.name == "core" {
+    // テストスイートの起動は START_TEST_APP=true（または 1）のビルドだけ
+    let run_tests = std::env::var("START_TEST_APP")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if service.name == "core" && run_tests {
         cmd.arg("--features").arg("run_tests");
         println!("  Enabling run_tests feature for core.service");
     }
 
+    // ファザーの起動は START_FUZZER=true（または 1）のビルドだけ
+    // 引数は MOCHIOS_FUZZ_ARGS で渡す（core.service にビルド時に埋め込まれる）
+    let run_fuzzer = std::env::var("START_FUZZER")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if service.name == "core" && run_fuzzer {
+        cmd.arg("--features").arg("run_fuzzer");
+        println!("  Enabling run_fuzzer feature for core.service");
+    }
+
     let output = cmd
         .current_dir(&service_dir)
         .output()
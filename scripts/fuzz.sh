@@ -0,0 +1,109 @@
+#!/bin/bash
+#
+# システムコールファザーを QEMU 上で走らせ、カーネルパニックとハングを見張る
+#
+#   scripts/fuzz.sh [SEED] [ITERATIONS]
+#
+# fuzzer.app の中ではカーネルごと止まる問題を検出できないので、ここでシリアル出力を見る。
+# - `!!! KERNEL PANIC !!!` が出た: パニック
+# - `!!! HARD LOCKUP !!!` が出た: NMI ウォッチドッグが検出したハードロックアップ
+# - FUZZ_HANG_SECS 秒（既定 120）出力が止まった: カーネルのハング
+# どちらも最後の `fuzz: progress N` 行から再現用の引数を組み立て、
+# target/fuzz/<seed>-<N>.txt に残す。再現は
+#   START_FUZZER=1 MOCHIOS_FUZZ_ARGS="<ファイルの fuzzer 行の引数>" HEADLESS=1 cargo run
+# で行う。
+
+set -e
+
+SCRIPT_DIR="$(cd "$(dirname "$0")" && pwd)"
+ROOT_DIR="$(cd "$SCRIPT_DIR/.." && pwd)"
+OUT_DIR="$ROOT_DIR/target/fuzz"
+
+SEED="${1:-$(date +%s)}"
+ITERATIONS="${2:-100000}"
+HANG_SECS="${FUZZ_HANG_SECS:-120}"
+# fuzzer.app の PROGRESS_EVERY と同じ
+PROGRESS_EVERY=256
+
+mkdir -p "$OUT_DIR"
+LOG="$OUT_DIR/$SEED.log"
+
+export START_FUZZER=1
+export HEADLESS=1
+export MOCHIOS_FUZZ_ARGS="--seed $SEED --iterations $ITERATIONS --keep-going"
+
+echo "fuzz.sh: seed=$SEED iterations=$ITERATIONS (log: $LOG)"
+cd "$ROOT_DIR"
+cargo build
+
+: > "$LOG"
+cargo run > "$LOG" 2>&1 &
+RUN_PID=$!
+
+# QEMU ごと止める（cargo run の子プロセスも含む）
+stop_qemu() {
+    pkill -P "$RUN_PID" 2>/dev/null || true
+    pkill -f "qemu-system-x86_64.*mochiOS.img" 2>/dev/null || true
+    wait "$RUN_PID" 2>/dev/null || true
+}
+
+# 再現手順を残す（引数: 種類）
+record() {
+    local kind="$1"
+    local progress
+    progress=$(grep -a -o 'fuzz: progress [0-9]*' "$LOG" | tail -1 | awk '{print $3}')
+    progress="${progress:-0}"
+    local file="$OUT_DIR/$SEED-$progress.txt"
+    {
+        echo "$kind after iteration $progress"
+        echo "fuzzer --seed $SEED --start $progress --iterations $((PROGRESS_EVERY * 2)) --trace-from $progress"
+        echo
+        tail -n 50 "$LOG"
+    } > "$file"
+    echo "fuzz.sh: $kind (seed=$SEED, after iteration $progress)"
+    echo "fuzz.sh: reproducer written to $file"
+}
+
+last_size=-1
+idle=0
+while kill -0 "$RUN_PID" 2>/dev/null; do
+    sleep 1
+    if grep -a -q '!!! KERNEL PANIC !!!' "$LOG"; then
+        # パニックメッセージを出し切るまで少し待つ
+        sleep 2
+        stop_qemu
+        record "KERNEL PANIC"
+        exit 1
+    fi
+    if grep -a -q '!!! HARD LOCKUP !!!' "$LOG"; then
+        sleep 2
+        stop_qemu
+        record "HARD LOCKUP"
+        exit 1
+    fi
+    size=$(stat -c%s "$LOG")
+    if [ "$size" -eq "$last_size" ]; then
+        idle=$((idle + 1))
+    else
+        idle=0
+        last_size=$size
+    fi
+    if [ "$idle" -ge "$HANG_SECS" ]; then
+        stop_qemu
+        record "KERNEL HANG"
+        exit 1
+    fi
+done
+
+set +e
+wait "$RUN_PID"
+status=$?
+set -e
+
+# fuzzer.app 自身が見つけたもの（クラッシュ・ハング）
+grep -a 'fuzz: ' "$LOG" | grep -a -v 'fuzz: progress' || true
+case "$status" in
+    0) echo "fuzz.sh: no findings" ;;
+    *) echo "fuzz.sh: fuzzer reported findings (exit $status)" ;;
+esac
+exit "$status"
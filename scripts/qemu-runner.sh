@@ -68,12 +68,34 @@ mcopy -i "$ESP_IMG" "$BOOTX64_SRC" ::/EFI/BOOT/BOOTX64.EFI
 [ -f "$INITFS_IMG" ] && mcopy -i "$ESP_IMG" "$INITFS_IMG" ::/system/initfs.img
 [ -f "$ROOTFS_IMG" ] && mcopy -i "$ESP_IMG" "$ROOTFS_IMG" ::/system/rootfs.ext2
 
+# MOCHIOS_CMDLINE を起動オプションとして渡す（例: MOCHIOS_CMDLINE=kgdb=wait）
+if [ -n "$MOCHIOS_CMDLINE" ]; then
+    CMDLINE_TXT="$TARGET_DIR/cmdline.txt"
+    printf '%s\n' "$MOCHIOS_CMDLINE" > "$CMDLINE_TXT"
+    mcopy -i "$ESP_IMG" "$CMDLINE_TXT" ::/system/cmdline.txt
+fi
+
 KVM_ARGS=()
 if [ -e /dev/kvm ] && [ -r /dev/kvm ]; then
     KVM_ARGS=(-enable-kvm -cpu host,migratable=no,+invtsc)
 fi
 
-exec qemu-system-x86_64 \
+# GDBSERVER_PORT を指定すると COM2 を TCP に出す（gdb から target remote :<port>）
+# gdbserver.app とカーネルの kgdb はどちらも COM2 を使う
+DEBUG_SERIAL_ARGS=()
+if [ -n "$GDBSERVER_PORT" ]; then
+    DEBUG_SERIAL_ARGS=(-serial "tcp::$GDBSERVER_PORT,server,nowait")
+fi
+
+# isa-debug-exit: ktest ビルドのカーネルがテスト結果を書き込んで QEMU を終了させる
+# HEADLESS=1 で画面を出さずに実行する（CI などでシリアル出力だけを見る場合）
+DISPLAY_ARGS=(-vga std)
+if [ -n "$HEADLESS" ]; then
+    DISPLAY_ARGS=(-vga std -display none)
+fi
+
+set +e
+qemu-system-x86_64 \
     "${KVM_ARGS[@]}" \
     -bios "$OVMF" \
     -drive format=raw,file="$ESP_IMG",index=0,media=disk \
@@ -84,7 +106,20 @@ exec qemu-system-x86_64 \
     -device usb-tablet,bus=xhci.0 \
     -netdev user,id=net0 \
     -device virtio-net-pci,netdev=net0 \
+    -device isa-debug-exit,iobase=0xf4,iosize=0x04 \
     -m 512M \
     -no-reboot \
     -serial stdio \
-    -vga std
+    "${DEBUG_SERIAL_ARGS[@]}" \
+    "${DISPLAY_ARGS[@]}"
+status=$?
+set -e
+
+# isa-debug-exit は (値 << 1) | 1 で終了する:
+# 0x10 → 33（成功）、0x11 → 35（失敗）、0x12 → 37（既知の失敗だけが残った）
+case "$status" in
+    33) exit 0 ;;
+    35) exit 1 ;;
+    37) exit 2 ;;
+    *) exit "$status" ;;
+esac
@@ -0,0 +1,303 @@
+//! fuzzer: システムコールのファザー
+//!
+//! ```text
+//! fuzzer [--seed N] [--start N] [--iterations N] [--trace-from N]
+//!        [--hang-ms N] [--keep-going] [--record DIR]
+//! ```
+//!
+//! 壊れたポインタ・境界値の長さ・不正な FD を組み合わせた呼び出しを、fork した子プロセス
+//! （ワーカー）で延々と実行する。呼び出し列はシードと反復番号だけで決まるので、
+//! 同じシードで実行し直せば同じ列が再現する。
+//!
+//! 親はウォッチドッグとして、ワーカーが各呼び出しの直前に送るハートビートを見張る。
+//!
+//! - ワーカーがシグナルで落ちた: その呼び出しを報告する（カーネルは EFAULT などを返すべき）
+//! - `--hang-ms` の間ハートビートが来ない: 待つのが正常な呼び出し（read や poll）なら
+//!   ワーカーを作り直して次の反復から続け、そうでなければハングとして報告する
+//! - SIGKILL でもワーカーが終わらない: カーネル内で固まったとして報告し、終了コード 2 で終わる
+//!
+//! カーネルパニックはこのプロセスでは検出できないので、`fuzz: progress` 行を定期的に出し、
+//! ホスト側（`scripts/fuzz.sh`）がシリアル出力から再現手順を組み立てる。
+//!
+//! 見つかったものはそれぞれ `fuzz: repro ...` 行（この引数で再実行すれば再現する）として出力し、
+//! `--record` を指定すればそのディレクトリにもファイルとして残す。
+
+mod plan;
+mod rng;
+mod worker;
+
+use std::time::{Duration, Instant};
+use swiftlib::task::{self, WNOHANG};
+use swiftlib::{ipc, signal, time};
+
+/// 既定の反復回数
+const DEFAULT_ITERATIONS: u64 = 100_000;
+/// 既定のハング判定時間
+const DEFAULT_HANG_MS: u64 = 5000;
+/// `fuzz: progress` 行を出す間隔（反復数）
+const PROGRESS_EVERY: u64 = 256;
+/// SIGKILL を送ってから終了を待つ時間
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+pub struct Options {
+    pub seed: u64,
+    pub start: u64,
+    /// この反復の手前で終える
+    pub end: u64,
+    /// この反復以降の呼び出しと戻り値をすべて出力する
+    pub trace_from: Option<u64>,
+    hang: Duration,
+    keep_going: bool,
+    record: Option<String>,
+}
+
+/// ワーカー 1 回分の結末
+enum Outcome {
+    Finished,
+    /// 待つのが正常な呼び出しで止まっていた
+    Blocked((u64, usize)),
+    Crashed {
+        at: Option<(u64, usize)>,
+        signal: i32,
+    },
+    Hung {
+        at: Option<(u64, usize)>,
+    },
+    Unkillable {
+        at: Option<(u64, usize)>,
+    },
+    Failed(i32),
+}
+
+fn usage() -> ! {
+    eprintln!("usage: fuzzer [--seed N] [--start N] [--iterations N] [--trace-from N]");
+    eprintln!("              [--hang-ms N] [--keep-going] [--record DIR]");
+    task::exit(2);
+}
+
+fn parse_args() -> Options {
+    let argv: Vec<String> = std::env::args().collect();
+    let mut seed = None;
+    let mut start = 0;
+    let mut iterations = DEFAULT_ITERATIONS;
+    let mut trace_from = None;
+    let mut hang_ms = DEFAULT_HANG_MS;
+    let mut keep_going = false;
+    let mut record = None;
+
+    let mut rest = argv.iter().skip(1);
+    while let Some(flag) = rest.next() {
+        if flag == "--keep-going" {
+            keep_going = true;
+            continue;
+        }
+        let Some(value) = rest.next() else { usage() };
+        if flag == "--record" {
+            record = Some(value.clone());
+            continue;
+        }
+        let Some(number) = parse_number(value) else {
+            usage()
+        };
+        match flag.as_str() {
+            "--seed" => seed = Some(number),
+            "--start" => start = number,
+            "--iterations" => iterations = number,
+            "--trace-from" => trace_from = Some(number),
+            "--hang-ms" => hang_ms = number,
+            _ => usage(),
+        }
+    }
+
+    Options {
+        seed: seed.unwrap_or_else(time::get_ticks),
+        start,
+        end: start.saturating_add(iterations),
+        trace_from,
+        hang: Duration::from_millis(hang_ms),
+        keep_going,
+        record,
+    }
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn describe(seed: u64, at: Option<(u64, usize)>) -> String {
+    match at {
+        Some((iter, step)) => {
+            let calls = plan::plan(seed, iter);
+            match calls.get(step) {
+                Some(call) => format!("{}.{} {}", iter, step, call),
+                None => format!("{}.{}", iter, step),
+            }
+        }
+        None => "worker setup".to_string(),
+    }
+}
+
+/// ワーカーの終了を待つ（`limit` を過ぎたら None）
+fn reap_within(pid: i64, limit: Duration) -> Option<task::WaitResult> {
+    let start = Instant::now();
+    while start.elapsed() < limit {
+        match task::wait4(pid, WNOHANG) {
+            Ok(result) if result.pid == 0 => time::sleep_ms(10),
+            Ok(result) => return Some(result),
+            Err(_) => return None,
+        }
+    }
+    None
+}
+
+/// ワーカーを見張り、終わるか止まるまで待つ
+fn watch(opts: &Options, pid: i64, progress: &mut u64) -> Outcome {
+    let mut buf = [0u8; 4128];
+    let mut at: Option<(u64, usize)> = None;
+    let mut last_beat = Instant::now();
+    loop {
+        // ハートビートとワーカーの標準出力の転送が混ざって届く
+        loop {
+            let (sender, len) = ipc::ipc_recv(&mut buf);
+            if (sender, len) == (0, 0) {
+                break;
+            }
+            if len != 24 || buf[..8] != worker::HEARTBEAT_MAGIC.to_le_bytes() {
+                continue;
+            }
+            let iter = u64::from_le_bytes(buf[8..16].try_into().unwrap_or_default());
+            let step = u64::from_le_bytes(buf[16..24].try_into().unwrap_or_default());
+            at = Some((iter, step as usize));
+            last_beat = Instant::now();
+            if iter >= *progress + PROGRESS_EVERY {
+                *progress = iter - iter % PROGRESS_EVERY;
+                println!("fuzz: progress {} seed={}", *progress, opts.seed);
+            }
+        }
+
+        match task::wait4(pid, WNOHANG) {
+            Ok(result) if result.pid == 0 => {}
+            Ok(result) if result.exited() => {
+                return match result.exit_status() {
+                    0 => Outcome::Finished,
+                    code => Outcome::Failed(code),
+                };
+            }
+            Ok(result) => {
+                return Outcome::Crashed {
+                    at,
+                    signal: result.status & 0x7f,
+                };
+            }
+            Err(errno) => return Outcome::Failed(errno as i32),
+        }
+
+        if last_beat.elapsed() >= opts.hang {
+            let blocking = at.filter(|&(iter, step)| {
+                plan::plan(opts.seed, iter)
+                    .get(step)
+                    .is_some_and(|call| call.spec.blocking)
+            });
+            let _ = signal::kill(pid, signal::SIGKILL);
+            if reap_within(pid, KILL_GRACE).is_none() {
+                return Outcome::Unkillable { at };
+            }
+            return match blocking {
+                Some(iter) => Outcome::Blocked(iter),
+                None => Outcome::Hung { at },
+            };
+        }
+        time::sleep_ms(1);
+    }
+}
+
+/// 見つかったものを報告し、再現手順を残す
+fn report(opts: &Options, kind: &str, at: Option<(u64, usize)>) {
+    let iter = at.map(|(iter, _)| iter).unwrap_or(opts.start);
+    // 最初から同じ列を流し直し、問題の反復だけ詳しく出す
+    let repro = format!(
+        "--seed {} --iterations {} --trace-from {}",
+        opts.seed,
+        iter + 1,
+        iter
+    );
+    println!("fuzz: {} at {}", kind, describe(opts.seed, at));
+    println!("fuzz: repro {}", repro);
+
+    if let Some(dir) = &opts.record {
+        let path = format!("{}/{}-{}.txt", dir, opts.seed, iter);
+        let body = format!(
+            "{} at {}\nfuzzer {}\n",
+            kind,
+            describe(opts.seed, at),
+            repro
+        );
+        if let Err(err) = std::fs::write(&path, body) {
+            println!("fuzz: failed to record {}: {}", path, err);
+        }
+    }
+}
+
+fn main() {
+    let opts = parse_args();
+    let parent_tid = task::gettid();
+    println!(
+        "fuzz: seed={} iterations {}..{} ({} syscalls)",
+        opts.seed,
+        opts.start,
+        opts.end,
+        plan::SPECS.len()
+    );
+
+    let mut next = opts.start;
+    let mut progress = opts.start;
+    let mut findings = 0;
+    while next < opts.end {
+        let pid = task::fork();
+        if pid == 0 {
+            std::process::exit(worker::run(&opts, parent_tid, next));
+        }
+        if pid < 0 {
+            println!("fuzz: fork failed: {}", pid);
+            task::exit(2);
+        }
+
+        match watch(&opts, pid, &mut progress) {
+            Outcome::Finished => break,
+            Outcome::Blocked(at) => {
+                println!(
+                    "fuzz: {} blocked, restarting worker",
+                    describe(opts.seed, Some(at))
+                );
+                next = at.0 + 1;
+                continue;
+            }
+            Outcome::Crashed { at, signal } => {
+                report(&opts, &format!("CRASH (signal {})", signal), at);
+                next = at.map(|(iter, _)| iter + 1).unwrap_or(opts.end);
+            }
+            Outcome::Hung { at } => {
+                report(&opts, "HANG", at);
+                next = at.map(|(iter, _)| iter + 1).unwrap_or(opts.end);
+            }
+            Outcome::Unkillable { at } => {
+                report(&opts, "STUCK (worker survived SIGKILL)", at);
+                task::exit(2);
+            }
+            Outcome::Failed(code) => {
+                println!("fuzz: worker failed with {}", code);
+                task::exit(2);
+            }
+        }
+        findings += 1;
+        if !opts.keep_going {
+            break;
+        }
+    }
+
+    println!("fuzz: done seed={} findings={}", opts.seed, findings);
+    task::exit(if findings == 0 { 0 } else { 1 });
+}
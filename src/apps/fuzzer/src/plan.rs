@@ -0,0 +1,404 @@
+//! 呼び出し列の生成
+//!
+//! 1 回の反復は、ランダムに選んだシステムコール 1 つか、あらかじめ決めた手順
+//! （open → read → close など）に沿った短い列。引数は境界値・壊れたポインタ・
+//! 不正な FD などの候補から選ぶ。生成は `(シード, 反復番号)` だけで決まり、
+//! 実行結果には依存しない（前の呼び出しの戻り値は `Value::Ret` として実行時に解決する）。
+
+use std::fmt;
+use swiftlib::sys::SyscallNumber;
+
+use crate::rng::Rng;
+
+/// スクラッチ領域の大きさ（直後に未マップのガードページを置く）
+pub const SCRATCH_SIZE: u64 = 4 * 4096;
+
+/// 引数の種類（候補の選び方）
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Fd,
+    /// 任意のポインタ（自分のテキストも含む）
+    Ptr,
+    /// マッピングを変更するアドレス（範囲が自分のコード・スタック・ヒープに届かないものだけ）
+    Region,
+    Path,
+    Len,
+    Int,
+    Flags,
+}
+
+/// ファジング対象のシステムコール
+pub struct Spec {
+    pub nr: SyscallNumber,
+    pub name: &'static str,
+    pub args: &'static [Kind],
+    /// 正常でも長く待つことがある（ハングとは見なさない）
+    pub blocking: bool,
+}
+
+macro_rules! spec {
+    ($nr:ident, $name:literal, [$($kind:ident),*]) => {
+        spec!($nr, $name, [$($kind),*], false)
+    };
+    ($nr:ident, $name:literal, [$($kind:ident),*], $blocking:expr) => {
+        Spec {
+            nr: SyscallNumber::$nr,
+            name: $name,
+            args: &[$(Kind::$kind),*],
+            blocking: $blocking,
+        }
+    };
+}
+
+/// 対象のシステムコール
+///
+/// プロセスの終了・exec・シグナルハンドラ・brk・他プロセスへの IPC や kill など、
+/// ファザー自身や他のプロセスを壊すものは含めない。
+pub const SPECS: &[Spec] = &[
+    spec!(Read, "read", [Fd, Ptr, Len], true),
+    spec!(Write, "write", [Fd, Ptr, Len]),
+    spec!(Readv, "readv", [Fd, Ptr, Int], true),
+    spec!(Writev, "writev", [Fd, Ptr, Int]),
+    spec!(Open, "open", [Path, Flags]),
+    spec!(Openat, "openat", [Fd, Path, Flags, Int]),
+    spec!(Close, "close", [Fd]),
+    spec!(Stat, "stat", [Path, Ptr]),
+    spec!(Fstat, "fstat", [Fd, Ptr]),
+    spec!(Lstat, "lstat", [Path, Ptr]),
+    spec!(Newfstatat, "newfstatat", [Fd, Path, Ptr, Flags]),
+    spec!(Lseek, "lseek", [Fd, Int, Int]),
+    spec!(Getdents64, "getdents64", [Fd, Ptr, Len]),
+    spec!(Readdir, "readdir", [Path, Ptr, Len]),
+    spec!(Getcwd, "getcwd", [Ptr, Len]),
+    spec!(Chdir, "chdir", [Path]),
+    spec!(Readlink, "readlink", [Path, Ptr, Len]),
+    spec!(Readlinkat, "readlinkat", [Fd, Path, Ptr, Len]),
+    spec!(Access, "access", [Path, Int]),
+    spec!(Faccessat, "faccessat", [Fd, Path, Int, Flags]),
+    spec!(Statfs, "statfs", [Path, Ptr]),
+    spec!(Ftruncate, "ftruncate", [Fd, Len]),
+    spec!(Fsync, "fsync", [Fd]),
+    spec!(Pipe2, "pipe2", [Ptr, Flags]),
+    spec!(Dup, "dup", [Fd]),
+    spec!(Dup2, "dup2", [Fd, Fd]),
+    spec!(Fcntl, "fcntl", [Fd, Int, Int]),
+    spec!(Ioctl, "ioctl", [Fd, Int, Ptr]),
+    spec!(Poll, "poll", [Ptr, Int, Int], true),
+    spec!(Ppoll, "ppoll", [Ptr, Int, Ptr, Ptr, Len], true),
+    spec!(Select, "select", [Int, Ptr, Ptr, Ptr, Ptr], true),
+    spec!(Pselect6, "pselect6", [Int, Ptr, Ptr, Ptr, Ptr, Ptr], true),
+    spec!(Mmap, "mmap", [Region, Len, Int, Flags, Fd]),
+    spec!(Munmap, "munmap", [Region, Len]),
+    spec!(Mprotect, "mprotect", [Region, Len, Int]),
+    spec!(Futex, "futex", [Ptr, Int, Int, Int], true),
+    spec!(Nanosleep, "nanosleep", [Ptr, Ptr], true),
+    spec!(ClockGettime, "clock_gettime", [Int, Ptr]),
+    spec!(Uname, "uname", [Ptr]),
+    spec!(Getrandom, "getrandom", [Ptr, Len, Flags]),
+    spec!(Getrlimit, "getrlimit", [Int, Ptr]),
+    spec!(RtSigprocmask, "rt_sigprocmask", [Int, Ptr, Ptr, Len]),
+    spec!(Sigaltstack, "sigaltstack", [Ptr, Ptr]),
+    spec!(
+        RtSigtimedwait,
+        "rt_sigtimedwait",
+        [Ptr, Ptr, Ptr, Len],
+        true
+    ),
+    spec!(Signalfd4, "signalfd4", [Fd, Ptr, Len, Flags]),
+    spec!(Wait, "wait4", [Int, Ptr, Int, Ptr], true),
+    spec!(Waitid, "waitid", [Int, Int, Ptr, Int, Ptr], true),
+    spec!(Log, "log", [Ptr, Len, Int]),
+    spec!(Syslog, "syslog", [Int, Ptr, Len]),
+    spec!(ExecFromBuffer, "exec_from_buffer", [Ptr, Len]),
+];
+
+fn spec(nr: SyscallNumber) -> &'static Spec {
+    SPECS
+        .iter()
+        .find(|s| s.nr as u64 == nr as u64)
+        .expect("spec for template syscall")
+}
+
+/// 引数の値（ポインタはファザーのアドレス配置に依らない形で持つ）
+#[derive(Clone, Copy)]
+pub enum Value {
+    Raw(u64),
+    /// スクラッチ領域の先頭からのオフセット
+    Scratch(u64),
+    /// 自分のテキスト（読み取り専用）
+    Text,
+    /// `PATHS` の添字
+    Path(usize),
+    /// 同じ反復の `n` 番目の呼び出しの戻り値
+    Ret(usize),
+    /// ファザー自身が持つ i32 の置き場 `n` のアドレス（pipe2 の出力先）
+    Slot(usize),
+    /// 置き場 `n` の値（pipe2 が書いた FD）
+    Load(usize),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Value::Raw(v) if v < 0x1000 => write!(f, "{}", v),
+            Value::Raw(v) if (v as i64) < 0 && (v as i64) > -0x1000 => write!(f, "{}", v as i64),
+            Value::Raw(v) => write!(f, "{:#x}", v),
+            Value::Scratch(off) => write!(f, "scratch+{:#x}", off),
+            Value::Text => f.write_str("text"),
+            Value::Path(i) => write!(f, "{:?}", display_path(PATHS[i])),
+            Value::Ret(n) => write!(f, "${}", n),
+            Value::Slot(n) => write!(f, "&slot[{}]", n),
+            Value::Load(n) => write!(f, "slot[{}]", n),
+        }
+    }
+}
+
+/// 候補のパス（NUL 終端）
+pub const PATHS: &[&[u8]] = &[
+    b"/\0",
+    b"\0",
+    b".\0",
+    b"..\0",
+    b"//\0",
+    b"/nonexistent\0",
+    b"/applications\0",
+    b"/applications/fuzzer.app/entry.elf\0",
+    b"/applications/fuzzer.app/entry.elf/x\0",
+    b"/system/../../../..\0",
+    b"/dev/tty\0",
+    b"/proc/self/status\0",
+    b"/fuzz.tmp\0",
+    LONG_PATH,
+];
+
+/// PATH_MAX を超える長さのパス
+const LONG_PATH: &[u8] = &{
+    let mut path = [b'a'; 4200];
+    path[0] = b'/';
+    path[4199] = 0;
+    path
+};
+
+fn display_path(path: &[u8]) -> String {
+    let text = String::from_utf8_lossy(&path[..path.len() - 1]);
+    if text.len() > 32 {
+        format!("{}...({} bytes)", &text[..16], text.len())
+    } else {
+        text.into_owned()
+    }
+}
+
+/// 1 回の呼び出し
+pub struct Call {
+    pub spec: &'static Spec,
+    pub args: Vec<Value>,
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.spec.name)?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", arg)?;
+        }
+        f.write_str(")")
+    }
+}
+
+fn fd(rng: &mut Rng) -> Value {
+    // 0〜2 は選ばない（標準入力は待ち続け、標準出力は親への IPC になる）
+    if rng.one_in(4) {
+        Value::Raw(*rng.pick(&[u64::MAX, 200, 1023, 1024, 0x7fff_ffff, 0x8000_0000, 1 << 32]))
+    } else {
+        Value::Raw(3 + rng.below(10))
+    }
+}
+
+fn pointer(rng: &mut Rng) -> Value {
+    match rng.below(12) {
+        0 => Value::Raw(0),
+        1 => Value::Raw(*rng.pick(&[1, 0xfff, 0x1000])),
+        2 => Value::Scratch(0),
+        3 => Value::Scratch(rng.below(SCRATCH_SIZE)),
+        // ガードページにまたがる
+        4 => Value::Scratch(SCRATCH_SIZE - 1 - rng.below(16)),
+        5 => Value::Scratch(SCRATCH_SIZE),
+        6 => Value::Text,
+        7 => Value::Raw(*rng.pick(&[0x7fff_ffff_f000, 0x7fff_ffff_fff8, 0x7fff_ffff_ffff])),
+        // 非正規アドレスとカーネル空間
+        8 => Value::Raw(*rng.pick(&[
+            0x0000_8000_0000_0000,
+            0xffff_7fff_ffff_ffff,
+            0xffff_8000_0000_0000,
+            0xffff_ffff_8000_0000,
+            u64::MAX,
+        ])),
+        9 => unmapped(rng),
+        _ => Value::Scratch(rng.below(SCRATCH_SIZE / 4096) * 4096),
+    }
+}
+
+/// 未マップのユーザー空間（スタックとヒープより上）
+fn unmapped(rng: &mut Rng) -> Value {
+    Value::Raw(0x1_0000_0000 + (rng.below(0x7000_0000) << 12))
+}
+
+fn region(rng: &mut Rng) -> Value {
+    match rng.below(4) {
+        0 => Value::Scratch(rng.below(SCRATCH_SIZE / 4096) * 4096),
+        1 => Value::Scratch(1 + rng.below(4095)),
+        2 => unmapped(rng),
+        _ => Value::Raw(*rng.pick(&[
+            0x7fff_ffff_f000,
+            0x0000_8000_0000_0000,
+            0xffff_8000_0000_0000,
+            0xffff_ffff_ffff_f000,
+        ])),
+    }
+}
+
+fn len(rng: &mut Rng) -> Value {
+    if rng.one_in(3) {
+        return Value::Raw(rng.below(8192));
+    }
+    Value::Raw(*rng.pick(&[
+        0,
+        1,
+        7,
+        8,
+        16,
+        64,
+        4095,
+        4096,
+        4097,
+        SCRATCH_SIZE,
+        SCRATCH_SIZE + 1,
+        0x10000,
+        1 << 31,
+        1 << 32,
+        i64::MAX as u64,
+        u64::MAX,
+    ]))
+}
+
+fn int(rng: &mut Rng) -> Value {
+    match rng.below(4) {
+        0 => Value::Raw(rng.below(64)),
+        1 => Value::Raw(rng.next()),
+        _ => Value::Raw(*rng.pick(&[
+            0,
+            1,
+            2,
+            3,
+            u64::MAX,
+            i32::MIN as i64 as u64,
+            i32::MAX as u64,
+            u32::MAX as u64,
+            0x8000_0000,
+            i64::MIN as u64,
+        ])),
+    }
+}
+
+fn flags(rng: &mut Rng) -> Value {
+    match rng.below(4) {
+        0 => Value::Raw(0),
+        1 => Value::Raw(1 << rng.below(64)),
+        2 => Value::Raw(rng.next() & 0xffff_ffff),
+        _ => Value::Raw(rng.next()),
+    }
+}
+
+fn arg(rng: &mut Rng, kind: Kind) -> Value {
+    match kind {
+        Kind::Fd => fd(rng),
+        Kind::Ptr => pointer(rng),
+        Kind::Region => region(rng),
+        Kind::Path if rng.one_in(4) => pointer(rng),
+        Kind::Path => Value::Path(rng.below(PATHS.len() as u64) as usize),
+        Kind::Len => len(rng),
+        Kind::Int => int(rng),
+        Kind::Flags => flags(rng),
+    }
+}
+
+fn random_call(rng: &mut Rng, spec: &'static Spec) -> Call {
+    let mut args: Vec<Value> = spec.args.iter().map(|&kind| arg(rng, kind)).collect();
+    // スクラッチ領域を対象にした変更はガードページまでに収める
+    if let Some(Value::Scratch(off)) = args.first() {
+        if matches!(spec.args.first(), Some(Kind::Region)) {
+            args[1] = Value::Raw(rng.below(SCRATCH_SIZE + 4096 - off + 1));
+        }
+    }
+    Call { spec, args }
+}
+
+/// `spec` の引数をランダムに埋め、`fixed` の位置だけ差し替える
+fn call_with(rng: &mut Rng, nr: SyscallNumber, fixed: &[(usize, Value)]) -> Call {
+    let mut call = random_call(rng, spec(nr));
+    for &(index, value) in fixed {
+        call.args[index] = value;
+    }
+    call
+}
+
+/// 手順に沿った列（前の戻り値を次の引数に使う）
+fn template(rng: &mut Rng) -> Vec<Call> {
+    use SyscallNumber::*;
+    match rng.below(4) {
+        // ファイルを開いて読んで閉じる
+        0 => vec![
+            call_with(rng, Open, &[]),
+            call_with(rng, Lseek, &[(0, Value::Ret(0))]),
+            call_with(rng, Read, &[(0, Value::Ret(0))]),
+            call_with(rng, Getdents64, &[(0, Value::Ret(0))]),
+            call_with(rng, Fstat, &[(0, Value::Ret(0))]),
+            call_with(rng, Close, &[(0, Value::Ret(0))]),
+        ],
+        // 確保 → 保護変更 → カーネルに書かせる → 解放 → 解放後に書かせる
+        1 => {
+            // 確保した範囲の外（スタックなど）に届かないよう長さは揃える
+            let len = Value::Raw(*rng.pick(&[1, 4096, 8192, SCRATCH_SIZE]));
+            let mapping = Value::Ret(0);
+            vec![
+                call_with(
+                    rng,
+                    Mmap,
+                    &[(0, Value::Raw(0)), (1, len), (3, Value::Raw(0x22))],
+                ),
+                call_with(rng, Mprotect, &[(0, mapping), (1, len)]),
+                call_with(rng, Getrandom, &[(0, mapping), (1, len)]),
+                call_with(rng, Munmap, &[(0, mapping), (1, len)]),
+                call_with(rng, Getrandom, &[(0, mapping), (1, Value::Raw(8))]),
+            ]
+        }
+        // パイプを作って書いて読む
+        2 => vec![
+            call_with(rng, Pipe2, &[(0, Value::Slot(0))]),
+            call_with(rng, Write, &[(0, Value::Load(1))]),
+            call_with(rng, Read, &[(0, Value::Load(0))]),
+            call_with(rng, Close, &[(0, Value::Load(1))]),
+            call_with(rng, Close, &[(0, Value::Load(0))]),
+        ],
+        // 複製した FD をいじって閉じる
+        _ => vec![
+            call_with(rng, Dup, &[]),
+            call_with(rng, Dup2, &[(0, Value::Ret(0))]),
+            call_with(rng, Fcntl, &[(0, Value::Ret(0))]),
+            call_with(rng, Close, &[(0, Value::Ret(0))]),
+        ],
+    }
+}
+
+/// 反復 `iter` で実行する呼び出し列
+pub fn plan(seed: u64, iter: u64) -> Vec<Call> {
+    let mut rng = Rng::for_iteration(seed, iter);
+    if rng.one_in(4) {
+        template(&mut rng)
+    } else {
+        let spec = rng.pick(SPECS);
+        vec![random_call(&mut rng, spec)]
+    }
+}
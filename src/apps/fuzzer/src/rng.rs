@@ -0,0 +1,37 @@
+//! 決定的な乱数（SplitMix64）
+//!
+//! 各反復の乱数列は `(シード, 反復番号)` だけで決まるので、
+//! 途中の反復だけを取り出して再生できる。
+
+pub struct Rng(u64);
+
+impl Rng {
+    /// 反復 `iter` 用の乱数列
+    pub fn for_iteration(seed: u64, iter: u64) -> Self {
+        let mut rng = Self(seed ^ iter.wrapping_mul(0xA076_1D64_78BD_642F));
+        rng.next();
+        rng
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// `0..n` の一様乱数（`n` は 0 以外）
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    /// `1 / n` の確率で真
+    pub fn one_in(&mut self, n: u64) -> bool {
+        self.below(n) == 0
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
@@ -0,0 +1,142 @@
+//! 呼び出し列を実際に実行する子プロセス
+//!
+//! 親（ウォッチドッグ）とは IPC で繋がり、各呼び出しの直前に `(反復, 手順)` を送る。
+//! 親はそれを見て、止まった・落ちた呼び出しを特定する。
+
+use std::sync::atomic::{AtomicI32, Ordering};
+use swiftlib::sys::SyscallNumber;
+use swiftlib::{ipc, signal, time};
+
+use crate::plan::{self, Call, Value, PATHS, SCRATCH_SIZE};
+use crate::Options;
+
+/// ハートビートの先頭に付ける印（標準出力の転送と区別する）
+pub const HEARTBEAT_MAGIC: u64 = u64::from_le_bytes(*b"FUZZBEAT");
+
+/// ハートビートを送り直す回数の上限（親がいなくなっていたら諦める）
+const HEARTBEAT_RETRIES: u32 = 200;
+
+/// pipe2 などが書き込む置き場（`Value::Slot`）
+static SLOTS: [AtomicI32; 2] = [AtomicI32::new(-1), AtomicI32::new(-1)];
+
+/// 6 引数のシステムコール（戻り値は変換しない）
+fn raw_syscall(nr: u64, args: [u64; 6]) -> u64 {
+    let ret: u64;
+    // SAFETY: 何が起きてもカーネル側で検証される（それを確かめるのが目的）
+    unsafe {
+        core::arch::asm!(
+            "int 0x80",
+            inlateout("rax") nr => ret,
+            in("rdi") args[0],
+            in("rsi") args[1],
+            in("rdx") args[2],
+            in("r10") args[3],
+            in("r8") args[4],
+            in("r9") args[5],
+            options(nostack),
+        );
+    }
+    ret
+}
+
+/// ファジング用の領域: `SCRATCH_SIZE` バイトの読み書き可能なページと、その直後の未マップのページ
+fn map_scratch() -> Option<u64> {
+    let total = SCRATCH_SIZE + 4096;
+    // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+    let addr = raw_syscall(
+        SyscallNumber::Mmap as u64,
+        [0, total, 0x3, 0x22, u64::MAX, 0],
+    );
+    if (addr as i64) <= 0 {
+        return None;
+    }
+    if raw_syscall(
+        SyscallNumber::Munmap as u64,
+        [addr + SCRATCH_SIZE, 4096, 0, 0, 0, 0],
+    ) != 0
+    {
+        return None;
+    }
+    Some(addr)
+}
+
+struct Worker {
+    scratch: u64,
+    text: u64,
+}
+
+impl Worker {
+    fn resolve(&self, value: Value, results: &[u64]) -> u64 {
+        match value {
+            Value::Raw(v) => v,
+            Value::Scratch(off) => self.scratch + off,
+            Value::Text => self.text,
+            Value::Path(i) => PATHS[i].as_ptr() as u64,
+            Value::Ret(n) => results.get(n).copied().unwrap_or(u64::MAX),
+            Value::Slot(n) => SLOTS[n].as_ptr() as u64,
+            Value::Load(n) => SLOTS[n].load(Ordering::SeqCst) as i64 as u64,
+        }
+    }
+
+    fn execute(&self, call: &Call, results: &[u64]) -> u64 {
+        let mut args = [0u64; 6];
+        for (slot, &value) in args.iter_mut().zip(&call.args) {
+            *slot = self.resolve(value, results);
+        }
+        raw_syscall(call.spec.nr as u64, args)
+    }
+}
+
+/// 親に `(反復, 手順)` を知らせる（メールボックスが一杯なら空くまで待つ）
+fn heartbeat(parent_tid: u64, iter: u64, step: usize) {
+    let mut msg = [0u8; 24];
+    msg[..8].copy_from_slice(&HEARTBEAT_MAGIC.to_le_bytes());
+    msg[8..16].copy_from_slice(&iter.to_le_bytes());
+    msg[16..].copy_from_slice(&(step as u64).to_le_bytes());
+    for _ in 0..HEARTBEAT_RETRIES {
+        if ipc::ipc_send(parent_tid, &msg) == 0 {
+            return;
+        }
+        time::sleep_ms(1);
+    }
+}
+
+/// 戻り値（負の errno は 10 進で）
+fn format_ret(ret: u64) -> String {
+    match ret as i64 {
+        errno @ -4095..=-1 => errno.to_string(),
+        _ => format!("{:#x}", ret),
+    }
+}
+
+/// 反復 `start` から `opts.end` の手前までを実行して終了コードを返す
+pub fn run(opts: &Options, parent_tid: u64, start: u64) -> i32 {
+    let Some(scratch) = map_scratch() else {
+        println!("fuzz: failed to map scratch area");
+        return 2;
+    };
+    // 読み手のいないパイプへの書き込みで終わらないようにする
+    let ignore = signal::SigAction::disposition(signal::SIG_IGN);
+    let _ = signal::sigaction(signal::SIGPIPE, Some(&ignore));
+
+    let worker = Worker {
+        scratch,
+        text: run as *const () as u64,
+    };
+    for iter in start..opts.end {
+        let calls = plan::plan(opts.seed, iter);
+        let mut results = Vec::with_capacity(calls.len());
+        for (step, call) in calls.iter().enumerate() {
+            heartbeat(parent_tid, iter, step);
+            if opts.trace_from.is_some_and(|from| iter >= from) {
+                println!("fuzz: {}.{} {}", iter, step, call);
+            }
+            let ret = worker.execute(call, &results);
+            if opts.trace_from.is_some_and(|from| iter >= from) {
+                println!("fuzz: {}.{} = {}", iter, step, format_ret(ret));
+            }
+            results.push(ret);
+        }
+    }
+    0
+}
@@ -0,0 +1,485 @@
+//! gdbserver: シリアルポート越しにユーザープログラムをデバッグする
+//!
+//! ```text
+//! gdbserver [-p <port>] <program> [args...]   プログラムを起動して先頭で止める
+//! gdbserver [-p <port>] --attach <pid>        実行中のプロセスにアタッチする
+//! ```
+//!
+//! ホスト側では QEMU の 2 本目のシリアル（`GDBSERVER_PORT=1234` で qemu-runner.sh が TCP に出す）へ
+//! `target remote :1234` で接続する。ポートは既定で COM2 (0x2F8)。
+
+mod packet;
+mod serial;
+
+use packet::{decode_hex, encode_hex, parse_hex, Connection, Incoming};
+use swiftlib::ptrace::{self, UserRegs};
+use swiftlib::signal::{SIGINT, SIGTRAP};
+use swiftlib::task::{self, WNOHANG};
+
+/// ソフトウェアブレークポイント（int3）
+const INT3: u8 = 0xCC;
+
+/// g パケットで送るレジスタ数（rax..gs。x87/SSE は送らない）
+const GDB_REG_COUNT: usize = 24;
+/// 先頭 17 個（rax..rip）は 8 バイト、残り（eflags, cs, ss, ds, es, fs, gs）は 4 バイト
+const GDB_WIDE_REGS: usize = 17;
+
+struct Breakpoint {
+    addr: u64,
+    saved: u8,
+}
+
+/// ターゲットが止まった理由
+#[derive(Clone, Copy)]
+enum Stop {
+    Signal(u8),
+    Exited(u8),
+}
+
+impl Stop {
+    fn reply(self) -> String {
+        match self {
+            Stop::Signal(sig) => format!("S{:02x}", sig),
+            Stop::Exited(code) => format!("W{:02x}", code),
+        }
+    }
+}
+
+struct Target {
+    pid: u64,
+    attached: bool,
+    breakpoints: Vec<Breakpoint>,
+    last_stop: Stop,
+}
+
+/// gdb の amd64 レジスタ番号 → user_regs_struct のフィールド
+fn gdb_reg(regs: &mut UserRegs, n: usize) -> Option<&mut u64> {
+    Some(match n {
+        0 => &mut regs.rax,
+        1 => &mut regs.rbx,
+        2 => &mut regs.rcx,
+        3 => &mut regs.rdx,
+        4 => &mut regs.rsi,
+        5 => &mut regs.rdi,
+        6 => &mut regs.rbp,
+        7 => &mut regs.rsp,
+        8 => &mut regs.r8,
+        9 => &mut regs.r9,
+        10 => &mut regs.r10,
+        11 => &mut regs.r11,
+        12 => &mut regs.r12,
+        13 => &mut regs.r13,
+        14 => &mut regs.r14,
+        15 => &mut regs.r15,
+        16 => &mut regs.rip,
+        17 => &mut regs.eflags,
+        18 => &mut regs.cs,
+        19 => &mut regs.ss,
+        20 => &mut regs.ds,
+        21 => &mut regs.es,
+        22 => &mut regs.fs,
+        23 => &mut regs.gs,
+        _ => return None,
+    })
+}
+
+fn reg_width(n: usize) -> usize {
+    if n < GDB_WIDE_REGS {
+        8
+    } else {
+        4
+    }
+}
+
+impl Target {
+    fn regs(&self) -> Option<UserRegs> {
+        ptrace::get_regs(self.pid).ok()
+    }
+
+    fn encode_regs(&self) -> Option<String> {
+        let mut regs = self.regs()?;
+        let mut out = String::new();
+        for n in 0..GDB_REG_COUNT {
+            let value = *gdb_reg(&mut regs, n)?;
+            out.push_str(&encode_hex(&value.to_le_bytes()[..reg_width(n)]));
+        }
+        Some(out)
+    }
+
+    fn write_regs(&self, hex: &[u8]) -> bool {
+        let (Some(bytes), Some(mut regs)) = (decode_hex(hex), self.regs()) else {
+            return false;
+        };
+        let mut off = 0;
+        for n in 0..GDB_REG_COUNT {
+            let width = reg_width(n);
+            let Some(chunk) = bytes.get(off..off + width) else {
+                break;
+            };
+            let mut raw = [0u8; 8];
+            raw[..width].copy_from_slice(chunk);
+            if let Some(slot) = gdb_reg(&mut regs, n) {
+                *slot = u64::from_le_bytes(raw);
+            }
+            off += width;
+        }
+        ptrace::set_regs(self.pid, &regs).is_ok()
+    }
+
+    fn read_reg(&self, n: usize) -> Option<String> {
+        let mut regs = self.regs()?;
+        let value = *gdb_reg(&mut regs, n)?;
+        Some(encode_hex(&value.to_le_bytes()[..reg_width(n)]))
+    }
+
+    fn write_reg(&self, n: usize, hex: &[u8]) -> bool {
+        let (Some(bytes), Some(mut regs)) = (decode_hex(hex), self.regs()) else {
+            return false;
+        };
+        let mut raw = [0u8; 8];
+        let width = reg_width(n).min(bytes.len());
+        raw[..width].copy_from_slice(&bytes[..width]);
+        match gdb_reg(&mut regs, n) {
+            Some(slot) => *slot = u64::from_le_bytes(raw),
+            None => return false,
+        }
+        ptrace::set_regs(self.pid, &regs).is_ok()
+    }
+
+    /// メモリを読む（差し込んだ int3 は元のバイトに戻して見せる）
+    fn read_memory(&self, addr: u64, len: usize) -> Option<Vec<u8>> {
+        let start = addr & !7;
+        let end = addr.checked_add(len as u64)?;
+        let mut raw = Vec::with_capacity(len + 16);
+        let mut word_addr = start;
+        while word_addr < end {
+            raw.extend_from_slice(&ptrace::peek_data(self.pid, word_addr).ok()?.to_le_bytes());
+            word_addr += 8;
+        }
+        let skip = (addr - start) as usize;
+        let mut out = raw[skip..skip + len].to_vec();
+        for bp in &self.breakpoints {
+            if bp.addr >= addr && bp.addr < end {
+                out[(bp.addr - addr) as usize] = bp.saved;
+            }
+        }
+        Some(out)
+    }
+
+    /// メモリを書く（int3 を差し込んだ位置は保存済みの元バイトを書き換える）
+    fn write_memory(&mut self, addr: u64, data: &[u8]) -> bool {
+        for (i, &byte) in data.iter().enumerate() {
+            let at = addr + i as u64;
+            if let Some(bp) = self.breakpoints.iter_mut().find(|bp| bp.addr == at) {
+                bp.saved = byte;
+            } else if !self.poke_byte(at, byte) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn peek_byte(&self, addr: u64) -> Option<u8> {
+        let word = ptrace::peek_data(self.pid, addr & !7).ok()?;
+        Some(word.to_le_bytes()[(addr & 7) as usize])
+    }
+
+    fn poke_byte(&self, addr: u64, byte: u8) -> bool {
+        let Ok(word) = ptrace::peek_data(self.pid, addr & !7) else {
+            return false;
+        };
+        let mut bytes = word.to_le_bytes();
+        bytes[(addr & 7) as usize] = byte;
+        ptrace::poke_data(self.pid, addr & !7, u64::from_le_bytes(bytes)).is_ok()
+    }
+
+    fn insert_breakpoint(&mut self, addr: u64) -> bool {
+        if self.breakpoints.iter().any(|bp| bp.addr == addr) {
+            return true;
+        }
+        let Some(saved) = self.peek_byte(addr) else {
+            return false;
+        };
+        if !self.poke_byte(addr, INT3) {
+            return false;
+        }
+        self.breakpoints.push(Breakpoint { addr, saved });
+        true
+    }
+
+    fn remove_breakpoint(&mut self, addr: u64) -> bool {
+        let Some(idx) = self.breakpoints.iter().position(|bp| bp.addr == addr) else {
+            return true;
+        };
+        let bp = self.breakpoints.remove(idx);
+        self.poke_byte(bp.addr, bp.saved)
+    }
+
+    fn remove_all_breakpoints(&mut self) {
+        for bp in core::mem::take(&mut self.breakpoints) {
+            self.poke_byte(bp.addr, bp.saved);
+        }
+    }
+
+    /// 再開して次に止まるまで待つ（待っている間の Ctrl-C は SIGINT にする）
+    fn resume(&mut self, conn: &Connection, step: bool, sig: u8) -> Stop {
+        let ok = if step {
+            ptrace::singlestep(self.pid, sig as u32)
+        } else {
+            ptrace::cont(self.pid, sig as u32)
+        };
+        if ok.is_err() {
+            return self.last_stop;
+        }
+        let stop = self.wait(conn);
+        self.last_stop = stop;
+        stop
+    }
+
+    fn wait(&self, conn: &Connection) -> Stop {
+        loop {
+            match task::wait4(self.pid as i64, WNOHANG) {
+                Ok(r) if r.pid == 0 => {
+                    if conn.poll_interrupt() {
+                        let _ = swiftlib::signal::kill(self.pid as i64, SIGINT);
+                    }
+                    task::yield_now();
+                }
+                Ok(r) if r.stopped() => {
+                    let sig = r.stop_signal() as u8;
+                    if sig == SIGTRAP as u8 {
+                        self.rewind_breakpoint();
+                    }
+                    return Stop::Signal(sig);
+                }
+                Ok(r) => return Stop::Exited(r.exit_status() as u8),
+                Err(_) => return Stop::Exited(0),
+            }
+        }
+    }
+
+    /// int3 を踏んで止まったなら RIP をブレークポイントの位置へ戻す
+    fn rewind_breakpoint(&self) {
+        let Some(mut regs) = self.regs() else {
+            return;
+        };
+        let hit = regs.rip.wrapping_sub(1);
+        if self.breakpoints.iter().any(|bp| bp.addr == hit) {
+            regs.rip = hit;
+            let _ = ptrace::set_regs(self.pid, &regs);
+        }
+    }
+}
+
+/// `addr,len` を分解する
+fn parse_addr_len(args: &[u8]) -> Option<(u64, usize)> {
+    let comma = args.iter().position(|&b| b == b',')?;
+    let addr = parse_hex(&args[..comma])?;
+    let len = parse_hex(&args[comma + 1..])? as usize;
+    Some((addr, len))
+}
+
+/// `c[addr]` / `s[addr]` / `Csig[;addr]` / `Ssig[;addr]` の引数
+fn parse_resume(args: &[u8], with_signal: bool) -> (u8, Option<u64>) {
+    if !with_signal {
+        return (0, parse_hex(args));
+    }
+    let mut parts = args.splitn(2, |&b| b == b';');
+    let sig = parts.next().and_then(parse_hex).unwrap_or(0) as u8;
+    (sig, parts.next().and_then(parse_hex))
+}
+
+enum Next {
+    Reply(String),
+    /// 返信してからセッションを終える
+    Quit(String),
+    /// 返信しない（k）
+    Exit,
+}
+
+fn handle(target: &mut Target, conn: &Connection, packet: &[u8]) -> Next {
+    let Some((&cmd, args)) = packet.split_first() else {
+        return Next::Reply(String::new());
+    };
+    let ok = |b: bool| Next::Reply(if b { "OK".into() } else { "E01".into() });
+    match cmd {
+        b'?' => Next::Reply(target.last_stop.reply()),
+        b'g' => Next::Reply(target.encode_regs().unwrap_or_else(|| "E01".into())),
+        b'G' => ok(target.write_regs(args)),
+        b'p' => match parse_hex(args).and_then(|n| target.read_reg(n as usize)) {
+            Some(hex) => Next::Reply(hex),
+            None => Next::Reply("E01".into()),
+        },
+        b'P' => {
+            let mut parts = args.splitn(2, |&b| b == b'=');
+            let n = parts.next().and_then(parse_hex);
+            match (n, parts.next()) {
+                (Some(n), Some(hex)) => ok(target.write_reg(n as usize, hex)),
+                _ => Next::Reply("E01".into()),
+            }
+        }
+        b'm' => match parse_addr_len(args).and_then(|(a, l)| target.read_memory(a, l)) {
+            Some(bytes) => Next::Reply(encode_hex(&bytes)),
+            None => Next::Reply("E01".into()),
+        },
+        b'M' => {
+            let colon = args.iter().position(|&b| b == b':');
+            let parsed = colon
+                .and_then(|c| Some((parse_addr_len(&args[..c])?, decode_hex(&args[c + 1..])?)));
+            match parsed {
+                Some(((addr, len), data)) if data.len() == len => {
+                    ok(target.write_memory(addr, &data))
+                }
+                _ => Next::Reply("E01".into()),
+            }
+        }
+        b'c' | b's' | b'C' | b'S' => {
+            let (sig, addr) = parse_resume(args, cmd == b'C' || cmd == b'S');
+            if let Some(addr) = addr {
+                if let Some(mut regs) = target.regs() {
+                    regs.rip = addr;
+                    let _ = ptrace::set_regs(target.pid, &regs);
+                }
+            }
+            let stop = target.resume(conn, cmd == b's' || cmd == b'S', sig);
+            match stop {
+                Stop::Exited(_) => Next::Quit(stop.reply()),
+                Stop::Signal(_) => Next::Reply(stop.reply()),
+            }
+        }
+        b'Z' | b'z' if args.first() == Some(&b'0') => {
+            let addr = args
+                .get(2..)
+                .and_then(|rest| rest.split(|&b| b == b',').next())
+                .and_then(parse_hex);
+            match addr {
+                Some(addr) if cmd == b'Z' => ok(target.insert_breakpoint(addr)),
+                Some(addr) => ok(target.remove_breakpoint(addr)),
+                None => Next::Reply("E01".into()),
+            }
+        }
+        b'k' => {
+            let _ = ptrace::kill(target.pid);
+            Next::Exit
+        }
+        b'D' => {
+            target.remove_all_breakpoints();
+            let _ = ptrace::detach(target.pid, 0);
+            Next::Quit("OK".into())
+        }
+        b'H' | b'T' => Next::Reply("OK".into()),
+        b'q' if args.starts_with(b"Supported") => Next::Reply("PacketSize=1000".into()),
+        b'q' if args.starts_with(b"Attached") => {
+            Next::Reply(if target.attached { "1" } else { "0" }.into())
+        }
+        b'q' if args.starts_with(b"C") && args.len() == 1 => {
+            Next::Reply(format!("QC{:x}", target.pid))
+        }
+        // vCont などその他は未対応（空応答で gdb が c / s に切り替える）
+        _ => Next::Reply(String::new()),
+    }
+}
+
+/// プログラムを fork + execve で起動し、execve 直後の停止まで待つ
+fn launch(program: &str, args: &[&str]) -> Result<u64, String> {
+    let pid = task::fork();
+    if pid < 0 {
+        return Err("fork failed".into());
+    }
+    if pid == 0 {
+        if ptrace::traceme().is_err() {
+            task::exit(126);
+        }
+        let err = swiftlib::process::execve(program, args);
+        eprintln!("gdbserver: execve {} failed ({})", program, err);
+        task::exit(127);
+    }
+    let pid = pid as u64;
+    match task::wait4(pid as i64, 0) {
+        Ok(r) if r.stopped() => {}
+        _ => return Err(format!("{} did not stop after execve", program)),
+    }
+    let _ = ptrace::set_options(pid, ptrace::PTRACE_O_EXITKILL);
+    Ok(pid)
+}
+
+/// 実行中のプロセスにアタッチし、SIGSTOP での停止まで待つ
+fn attach(pid: u64) -> Result<u64, String> {
+    ptrace::attach(pid).map_err(|e| format!("attach {} failed ({})", pid, e as i64))?;
+    match task::wait4(pid as i64, 0) {
+        Ok(r) if r.stopped() => Ok(pid),
+        _ => Err(format!("{} did not stop after attach", pid)),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: gdbserver [-p <port>] <program> [args...]");
+    eprintln!("       gdbserver [-p <port>] --attach <pid>");
+    task::exit(2);
+}
+
+fn main() {
+    let argv: Vec<String> = std::env::args().collect();
+    let mut rest = &argv[1.min(argv.len())..];
+    let mut port = serial::DEFAULT_BASE;
+    if rest.first().map(String::as_str) == Some("-p") {
+        let Some(value) = rest.get(1) else { usage() };
+        let digits = value.trim_start_matches("0x");
+        port = match u16::from_str_radix(digits, 16) {
+            Ok(p) => p,
+            Err(_) => usage(),
+        };
+        rest = &rest[2..];
+    }
+
+    let (result, attached) = match rest.first().map(String::as_str) {
+        Some("--attach") => match rest.get(1).and_then(|s| s.parse::<u64>().ok()) {
+            Some(pid) => (attach(pid), true),
+            None => usage(),
+        },
+        Some(program) => {
+            let args: Vec<&str> = rest[1..].iter().map(String::as_str).collect();
+            (launch(program, &args), false)
+        }
+        None => usage(),
+    };
+    let pid = match result {
+        Ok(pid) => pid,
+        Err(msg) => {
+            eprintln!("gdbserver: {}", msg);
+            task::exit(1);
+        }
+    };
+
+    println!(
+        "gdbserver: debugging pid {} on serial port {:#x}",
+        pid, port
+    );
+    let conn = Connection::new(serial::Serial::new(port));
+    let mut target = Target {
+        pid,
+        attached,
+        breakpoints: Vec::new(),
+        last_stop: Stop::Signal(SIGTRAP as u8),
+    };
+
+    loop {
+        let packet = match conn.receive() {
+            Incoming::Packet(p) => p,
+            // 止まっている間の Ctrl-C は現在の停止をそのまま報告する
+            Incoming::Interrupt => {
+                conn.send_str(&target.last_stop.reply());
+                continue;
+            }
+        };
+        match handle(&mut target, &conn, &packet) {
+            Next::Reply(reply) => conn.send_str(&reply),
+            Next::Quit(reply) => {
+                conn.send_str(&reply);
+                break;
+            }
+            Next::Exit => break,
+        }
+    }
+    println!("gdbserver: session for pid {} ended", pid);
+}
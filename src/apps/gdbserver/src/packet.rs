@@ -0,0 +1,136 @@
+//! GDB リモートシリアルプロトコルのパケット入出力
+//!
+//! `$<data>#<checksum>` の枠と `+`/`-` の確認応答だけを扱う。
+//! 実行中のターゲットへの割り込み（0x03）はパケット外で届く。
+
+use crate::serial::Serial;
+use std::cell::Cell;
+
+/// 受信した 1 単位
+pub enum Incoming {
+    Packet(Vec<u8>),
+    /// Ctrl-C
+    Interrupt,
+}
+
+pub struct Connection {
+    serial: Serial,
+    /// 確認応答を待っている間に読んでしまったバイト
+    pushback: Cell<Option<u8>>,
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+impl Connection {
+    pub fn new(serial: Serial) -> Self {
+        Self {
+            serial,
+            pushback: Cell::new(None),
+        }
+    }
+
+    fn read(&self) -> u8 {
+        self.pushback.take().unwrap_or_else(|| self.serial.read())
+    }
+
+    /// 割り込みバイトだけを覗く（ターゲット実行中に使う）
+    pub fn poll_interrupt(&self) -> bool {
+        match self.pushback.take().or_else(|| self.serial.try_read()) {
+            Some(0x03) => true,
+            Some(other) => {
+                self.pushback.set(Some(other));
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// パケットか割り込みを 1 つ受け取る（チェックサム不一致は `-` を返して読み直す）
+    pub fn receive(&self) -> Incoming {
+        loop {
+            match self.read() {
+                0x03 => return Incoming::Interrupt,
+                b'$' => {}
+                _ => continue,
+            }
+            let mut data = Vec::new();
+            let mut sum: u8 = 0;
+            loop {
+                let b = self.serial.read();
+                if b == b'#' {
+                    break;
+                }
+                sum = sum.wrapping_add(b);
+                data.push(b);
+            }
+            let hi = hex_digit(self.serial.read());
+            let lo = hex_digit(self.serial.read());
+            match (hi, lo) {
+                (Some(hi), Some(lo)) if (hi << 4 | lo) == sum => {
+                    self.serial.write(b'+');
+                    return Incoming::Packet(data);
+                }
+                _ => self.serial.write(b'-'),
+            }
+        }
+    }
+
+    /// パケットを送り、`+` が返るまで再送する
+    pub fn send(&self, data: &[u8]) {
+        let sum = data.iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+        let trailer = format!("#{:02x}", sum);
+        loop {
+            self.serial.write(b'$');
+            self.serial.write_all(data);
+            self.serial.write_all(trailer.as_bytes());
+            match self.read() {
+                b'+' => return,
+                b'-' => continue,
+                // 確認応答の代わりに次の要求が来た場合は届いたものとみなす
+                other => {
+                    self.pushback.set(Some(other));
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn send_str(&self, s: &str) {
+        self.send(s.as_bytes());
+    }
+}
+
+/// 16 進文字列をバイト列へ
+pub fn decode_hex(s: &[u8]) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    s.chunks(2)
+        .map(|pair| Some(hex_digit(pair[0])? << 4 | hex_digit(pair[1])?))
+        .collect()
+}
+
+/// バイト列を 16 進文字列へ
+pub fn encode_hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        out.push_str(&format!("{:02x}", b));
+    }
+    out
+}
+
+/// 16 進の数値（`addr,len` の各要素など）
+pub fn parse_hex(s: &[u8]) -> Option<u64> {
+    if s.is_empty() || s.len() > 16 {
+        return None;
+    }
+    s.iter()
+        .try_fold(0u64, |acc, &b| Some(acc << 4 | hex_digit(b)? as u64))
+}
@@ -0,0 +1,69 @@
+//! 16550 UART（ポート I/O）
+//!
+//! 受信は割り込みを使わずポーリングする。ポートアクセスには
+//! ケーパビリティのポート範囲が必要（gdbserver.app は Service 権限で起動される）。
+
+use swiftlib::port::{inb, outb};
+
+/// 既定のポート（COM2。COM1 はカーネルログが使う）
+pub const DEFAULT_BASE: u16 = 0x2F8;
+
+const DATA: u16 = 0;
+const IER: u16 = 1;
+const FCR: u16 = 2;
+const LCR: u16 = 3;
+const MCR: u16 = 4;
+const LSR: u16 = 5;
+
+const LSR_DATA_READY: u8 = 0x01;
+const LSR_THR_EMPTY: u8 = 0x20;
+
+pub struct Serial {
+    base: u16,
+}
+
+impl Serial {
+    /// 115200 baud, 8N1, FIFO 有効で初期化する
+    pub fn new(base: u16) -> Self {
+        outb(base + IER, 0x00);
+        outb(base + LCR, 0x80);
+        outb(base + DATA, 0x01);
+        outb(base + IER, 0x00);
+        outb(base + LCR, 0x03);
+        outb(base + FCR, 0xC7);
+        outb(base + MCR, 0x0B);
+        Self { base }
+    }
+
+    /// 受信データがあれば 1 バイト返す
+    pub fn try_read(&self) -> Option<u8> {
+        if inb(self.base + LSR) & LSR_DATA_READY != 0 {
+            Some(inb(self.base + DATA))
+        } else {
+            None
+        }
+    }
+
+    /// 1 バイト受信するまで待つ
+    pub fn read(&self) -> u8 {
+        loop {
+            if let Some(b) = self.try_read() {
+                return b;
+            }
+            swiftlib::task::yield_now();
+        }
+    }
+
+    pub fn write(&self, byte: u8) {
+        while inb(self.base + LSR) & LSR_THR_EMPTY == 0 {
+            core::hint::spin_loop();
+        }
+        outb(self.base + DATA, byte);
+    }
+
+    pub fn write_all(&self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write(b);
+        }
+    }
+}
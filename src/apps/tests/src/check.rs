@@ -0,0 +1,44 @@
+//! テスト本体で使うアサーション（失敗すると `Err(理由)` で早期リターンする）
+
+/// 条件が偽なら失敗
+macro_rules! check {
+    ($cond:expr) => {
+        check!($cond, "assertion failed: {}", stringify!($cond))
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            return Err(format!("line {}: {}", line!(), format!($($arg)+)));
+        }
+    };
+}
+
+/// 2 つの値が等しくなければ失敗
+macro_rules! check_eq {
+    ($left:expr, $right:expr) => {
+        match (&$left, &$right) {
+            (left, right) => check!(
+                *left == *right,
+                "{} == {} (left: {:?}, right: {:?})",
+                stringify!($left),
+                stringify!($right),
+                left,
+                right
+            ),
+        }
+    };
+}
+
+/// システムコールの戻り値が `-errno` でなければ失敗
+macro_rules! check_errno {
+    ($ret:expr, $errno:ident) => {
+        match $ret as i64 {
+            ret => check!(
+                ret == -$crate::sys::$errno,
+                "{} returned {} (expected -{})",
+                stringify!($ret),
+                ret,
+                stringify!($errno)
+            ),
+        }
+    };
+}
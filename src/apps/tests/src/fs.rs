@@ -0,0 +1,256 @@
+//! open / read / write / lseek / dup / dup2 / getdents64
+
+use crate::sys::{self, SyscallNumber};
+use crate::{Test, TestResult};
+
+/// 中身が分かっている読み取り専用ファイル（このアプリ自身）
+const SELF_ELF: &str = "/applications/tests.app/entry.elf";
+
+pub const TESTS: &[Test] = &[
+    Test::new("fs: open nonexistent path is ENOENT", open_enoent),
+    Test::new("fs: read/write on unused fd is EBADF", rw_ebadf),
+    Test::new("fs: read into NULL is EFAULT", read_efault),
+    Test::new("fs: double close is EBADF", double_close),
+    Test::new("fs: lseek returns new offset", lseek_offsets),
+    Test::new("fs: lseek to negative offset is EINVAL", lseek_negative),
+    Test::new("fs: lseek with bad whence is EINVAL", lseek_bad_whence),
+    Test::new("fs: lseek on unused fd is EBADF", lseek_ebadf),
+    Test::todo(
+        "fs: read honours lseek offset",
+        read_after_lseek,
+        "remote reads ignore the fd offset",
+    ),
+    Test::todo(
+        "fs: lseek on pipe is ESPIPE",
+        lseek_pipe,
+        "pipes are seekable as empty files",
+    ),
+    Test::new("fs: dup returns lowest free fd", dup_lowest),
+    Test::new("fs: dup2 onto itself returns fd", dup2_same),
+    Test::new("fs: dup2 from unused fd is EBADF", dup2_ebadf),
+    Test::new("fs: dup2 redirects writes", dup2_redirect),
+    Test::new("fs: getdents64 lists . and ..", getdents_dots),
+    Test::new("fs: getdents64 on unused fd is EBADF", getdents_ebadf),
+    Test::new(
+        "fs: getdents64 with zero length is EINVAL",
+        getdents_zero_len,
+    ),
+    Test::todo(
+        "fs: getdents64 on regular file is ENOTDIR",
+        getdents_enotdir,
+        "getdents64 does not check the fd type",
+    ),
+    Test::todo(
+        "fs: getdents64 with too small buffer is EINVAL",
+        getdents_small_buffer,
+        "returns 0 instead of EINVAL",
+    ),
+];
+
+fn open_enoent() -> TestResult {
+    check_errno!(sys::open("/no/such/file", sys::O_RDONLY), ENOENT);
+    Ok(())
+}
+
+fn rw_ebadf() -> TestResult {
+    let mut buf = [0u8; 4];
+    check_errno!(sys::read(sys::UNUSED_FD, &mut buf), EBADF);
+    check_errno!(sys::write(sys::UNUSED_FD, b"x"), EBADF);
+    Ok(())
+}
+
+fn read_efault() -> TestResult {
+    let fd = sys::open(SELF_ELF, sys::O_RDONLY);
+    check!(fd >= 0, "open returned {}", fd);
+    check_errno!(sys::sys3(SyscallNumber::Read, fd as u64, 0, 4), EFAULT);
+    sys::close(fd);
+    Ok(())
+}
+
+fn double_close() -> TestResult {
+    let fd = sys::open(SELF_ELF, sys::O_RDONLY);
+    check!(fd >= 0, "open returned {}", fd);
+    check_eq!(sys::close(fd), 0);
+    check_errno!(sys::close(fd), EBADF);
+    Ok(())
+}
+
+fn lseek_offsets() -> TestResult {
+    let fd = sys::open(SELF_ELF, sys::O_RDONLY);
+    check!(fd >= 0, "open returned {}", fd);
+    check_eq!(sys::lseek(fd, 3, sys::SEEK_SET), 3);
+    check_eq!(sys::lseek(fd, 2, sys::SEEK_CUR), 5);
+    let end = sys::lseek(fd, 0, sys::SEEK_END);
+    check!(end > 5, "SEEK_END returned {}", end);
+    check_eq!(sys::lseek(fd, -1, sys::SEEK_END), end - 1);
+    sys::close(fd);
+    Ok(())
+}
+
+fn lseek_negative() -> TestResult {
+    let fd = sys::open(SELF_ELF, sys::O_RDONLY);
+    check!(fd >= 0, "open returned {}", fd);
+    check_errno!(sys::lseek(fd, -1, sys::SEEK_SET), EINVAL);
+    sys::close(fd);
+    Ok(())
+}
+
+fn lseek_bad_whence() -> TestResult {
+    let fd = sys::open(SELF_ELF, sys::O_RDONLY);
+    check!(fd >= 0, "open returned {}", fd);
+    check_errno!(sys::lseek(fd, 0, 9), EINVAL);
+    sys::close(fd);
+    Ok(())
+}
+
+fn lseek_ebadf() -> TestResult {
+    check_errno!(sys::lseek(sys::UNUSED_FD, 0, sys::SEEK_SET), EBADF);
+    Ok(())
+}
+
+fn read_after_lseek() -> TestResult {
+    let fd = sys::open(SELF_ELF, sys::O_RDONLY);
+    check!(fd >= 0, "open returned {}", fd);
+    check_eq!(sys::lseek(fd, 1, sys::SEEK_SET), 1);
+    let mut buf = [0u8; 3];
+    check_eq!(sys::read(fd, &mut buf), 3);
+    sys::close(fd);
+    check_eq!(&buf, b"ELF");
+    Ok(())
+}
+
+fn lseek_pipe() -> TestResult {
+    let (rd, wr) = sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))?;
+    let ret = sys::lseek(rd, 0, sys::SEEK_CUR);
+    sys::close(rd);
+    sys::close(wr);
+    check_errno!(ret, ESPIPE);
+    Ok(())
+}
+
+fn dup_lowest() -> TestResult {
+    let (rd, wr) = sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))?;
+    check_eq!(sys::close(rd), 0);
+    let fd = sys::sys1(SyscallNumber::Dup, wr as u64);
+    check_eq!(fd, rd);
+    sys::close(fd);
+    sys::close(wr);
+    Ok(())
+}
+
+fn dup2_same() -> TestResult {
+    let (rd, wr) = sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))?;
+    check_eq!(sys::sys2(SyscallNumber::Dup2, rd as u64, rd as u64), rd);
+    sys::close(rd);
+    sys::close(wr);
+    Ok(())
+}
+
+fn dup2_ebadf() -> TestResult {
+    let (rd, wr) = sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))?;
+    let ret = sys::sys2(SyscallNumber::Dup2, sys::UNUSED_FD as u64, rd as u64);
+    sys::close(rd);
+    sys::close(wr);
+    check_errno!(ret, EBADF);
+    Ok(())
+}
+
+fn dup2_redirect() -> TestResult {
+    let (rd1, wr1) = sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))?;
+    let (rd2, wr2) = sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))?;
+    // wr1 を 2 本目のパイプの書き込み端に差し替える
+    check_eq!(sys::sys2(SyscallNumber::Dup2, wr2 as u64, wr1 as u64), wr1);
+    check_eq!(sys::write(wr1, b"moved"), 5);
+    let mut buf = [0u8; 5];
+    check_eq!(sys::read(rd2, &mut buf), 5);
+    check_eq!(&buf, b"moved");
+    for fd in [rd1, wr1, rd2, wr2] {
+        sys::close(fd);
+    }
+    Ok(())
+}
+
+/// linux_dirent64 を先頭から読む: `(名前, d_reclen)` の列
+fn parse_dirents(buf: &[u8]) -> Vec<(String, usize)> {
+    let mut entries = Vec::new();
+    let mut off = 0;
+    while off + 19 < buf.len() {
+        let reclen = u16::from_ne_bytes([buf[off + 16], buf[off + 17]]) as usize;
+        if reclen == 0 || off + reclen > buf.len() {
+            break;
+        }
+        let name = &buf[off + 19..off + reclen];
+        let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        entries.push((String::from_utf8_lossy(&name[..end]).into_owned(), reclen));
+        off += reclen;
+    }
+    entries
+}
+
+fn getdents(fd: i64, buf: &mut [u8]) -> i64 {
+    sys::sys3(
+        SyscallNumber::Getdents64,
+        fd as u64,
+        buf.as_mut_ptr() as u64,
+        buf.len() as u64,
+    )
+}
+
+fn getdents_dots() -> TestResult {
+    let fd = sys::open("/", sys::O_RDONLY | sys::O_DIRECTORY);
+    check!(fd >= 0, "open returned {}", fd);
+    let mut buf = [0u8; 1024];
+    let n = getdents(fd, &mut buf);
+    check!(n > 0, "getdents64 returned {}", n);
+    let entries = parse_dirents(&buf[..n as usize]);
+    check!(entries.len() >= 2, "only {} entries", entries.len());
+    check_eq!(entries[0].0, ".");
+    check_eq!(entries[1].0, "..");
+    for (name, reclen) in &entries {
+        check!(reclen % 8 == 0, "{}: d_reclen {} not aligned", name, reclen);
+    }
+    // 読み切ったら 0
+    let mut rounds = 0;
+    while getdents(fd, &mut buf) > 0 {
+        rounds += 1;
+        check!(rounds < 64, "getdents64 never reached the end");
+    }
+    check_eq!(getdents(fd, &mut buf), 0);
+    sys::close(fd);
+    Ok(())
+}
+
+fn getdents_ebadf() -> TestResult {
+    let mut buf = [0u8; 256];
+    check_errno!(getdents(sys::UNUSED_FD, &mut buf), EBADF);
+    Ok(())
+}
+
+fn getdents_zero_len() -> TestResult {
+    let fd = sys::open("/", sys::O_RDONLY | sys::O_DIRECTORY);
+    check!(fd >= 0, "open returned {}", fd);
+    let ret = getdents(fd, &mut []);
+    sys::close(fd);
+    check_errno!(ret, EINVAL);
+    Ok(())
+}
+
+fn getdents_enotdir() -> TestResult {
+    let fd = sys::open(SELF_ELF, sys::O_RDONLY);
+    check!(fd >= 0, "open returned {}", fd);
+    let mut buf = [0u8; 256];
+    let ret = getdents(fd, &mut buf);
+    sys::close(fd);
+    check_errno!(ret, ENOTDIR);
+    Ok(())
+}
+
+fn getdents_small_buffer() -> TestResult {
+    let fd = sys::open("/", sys::O_RDONLY | sys::O_DIRECTORY);
+    check!(fd >= 0, "open returned {}", fd);
+    let mut buf = [0u8; 8];
+    let ret = getdents(fd, &mut buf);
+    sys::close(fd);
+    check_errno!(ret, EINVAL);
+    Ok(())
+}
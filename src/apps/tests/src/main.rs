@@ -1,191 +1,180 @@
-use core::ffi::c_char;
-use swiftlib::fs;
-
-extern "C" {
-    fn printf(fmt: *const c_char, ...) -> i32;
-}
-
-// テスト結果の統計
-struct TestStats {
-    passed: usize,
-    failed: usize,
-    total: usize,
+//! mochiOS ユーザー空間テストスイート
+//!
+//! POSIX システムコールの振る舞い（戻り値と errno）を確かめる。結果は TAP で
+//! 標準出力（シリアル）に出し、TODO 以外の失敗が 1 つでもあれば終了コード 1 で終わる。
+//! テストビルドでは core.service がこのアプリを起動し、終了コードで QEMU を終了させる。
+//!
+//! 各テストは fork した子プロセスで走らせるので、クラッシュやハングしても
+//! そのテストの失敗として報告して次に進む。
+//!
+//! 引数に文字列を渡すと、名前にそれを含むテストだけを実行する。
+
+use std::time::{Duration, Instant};
+use swiftlib::{ipc, signal, time};
+
+#[macro_use]
+mod check;
+mod sys;
+
+mod fs;
+mod memory;
+mod pipe;
+mod process;
+mod signals;
+
+/// テストの結果（失敗時は理由）
+pub type TestResult = Result<(), String>;
+
+/// テスト 1 件
+pub struct Test {
+    pub name: &'static str,
+    pub run: fn() -> TestResult,
+    /// まだカーネルが満たしていない振る舞い（失敗しても全体の結果に数えない）
+    pub todo: Option<&'static str>,
 }
 
-impl TestStats {
-    const fn new() -> Self {
+impl Test {
+    pub const fn new(name: &'static str, run: fn() -> TestResult) -> Self {
         Self {
-            passed: 0,
-            failed: 0,
-            total: 0,
-        }
-    }
-
-    fn run_test(&mut self, name: &str, test_fn: fn() -> bool) {
-        self.total += 1;
-        unsafe {
-            printf(b"[TEST] Running: %s ... \0".as_ptr() as *const c_char, name.as_ptr());
-        }
-        
-        if test_fn() {
-            self.passed += 1;
-            unsafe {
-                printf(b"OK\n\0".as_ptr() as *const c_char);
-            }
-        } else {
-            self.failed += 1;
-            unsafe {
-                printf(b"FAILED\n\0".as_ptr() as *const c_char);
-            }
+            name,
+            run,
+            todo: None,
         }
     }
 
-    fn summary(&self) {
-        unsafe {
-            printf(b"\n========================================\n\0".as_ptr() as *const c_char);
-            printf(b"Test Results:\n\0".as_ptr() as *const c_char);
-            printf(b"  Total:  %d\n\0".as_ptr() as *const c_char, self.total);
-            printf(b"  Passed: %d\n\0".as_ptr() as *const c_char, self.passed);
-            printf(b"  Failed: %d\n\0".as_ptr() as *const c_char, self.failed);
-            printf(b"========================================\n\0".as_ptr() as *const c_char);
+    pub const fn todo(name: &'static str, run: fn() -> TestResult, reason: &'static str) -> Self {
+        Self {
+            name,
+            run,
+            todo: Some(reason),
         }
     }
 }
 
-// ========== テスト関数 ==========
-
-fn test_basic_arithmetic() -> bool {
-    let a = 2 + 2;
-    a == 4
-}
+/// 1 件あたりの制限時間
+const TEST_TIMEOUT: Duration = Duration::from_secs(5);
 
-fn test_string_compare() -> bool {
-    let s1 = b"hello";
-    let s2 = b"hello";
-    s1 == s2
-}
+/// execve テストが自分自身を起動し直すときの引数
+pub const EXIT_WITH_ARG: &str = "--exit-with";
 
-fn test_argc_argv() -> bool {
-    true
+enum Outcome {
+    Passed,
+    Failed,
+    Signaled(i32),
+    TimedOut,
 }
 
-fn test_mkdir() -> bool {
-    unsafe {
-        printf(b"\n  Creating directory '/testdir' ... \0".as_ptr() as *const c_char);
-    }
-    
-    let result = fs::mkdir("/testdir\0", 0o755);
-    
-    unsafe {
-        if result == 0 || result == u64::MAX - 5 { // SUCCESS or ENOSYS
-            printf(b"(syscall returned %llu) \0".as_ptr() as *const c_char, result);
-            true
-        } else {
-            printf(b"failed with error %llu \0".as_ptr() as *const c_char, result);
-            false
-        }
-    }
+/// 子プロセスの標準出力は IPC で親（このプロセス）にも届くので、溜めずに捨てる
+fn drain_ipc() {
+    // カーネルの最大メッセージ長
+    let mut buf = [0u8; 4128];
+    while ipc::ipc_recv(&mut buf) != (0, 0) {}
 }
 
-fn test_rmdir() -> bool {
-    unsafe {
-        printf(b"\n  Removing directory '/testdir' ... \0".as_ptr() as *const c_char);
-    }
-    
-    let result = fs::rmdir("/testdir\0");
-    
-    unsafe {
-        if result == 0 || result == u64::MAX - 5 { // SUCCESS or ENOSYS
-            printf(b"(syscall returned %llu) \0".as_ptr() as *const c_char, result);
-            true
-        } else {
-            printf(b"failed with error %llu \0".as_ptr() as *const c_char, result);
-            false
+fn run_isolated(test: &Test) -> Outcome {
+    let run = test.run;
+    let name = test.name;
+    let pid = sys::spawn(|| match run() {
+        Ok(()) => 0,
+        Err(message) => {
+            println!("# {}: {}", name, message);
+            1
         }
+    });
+    if pid < 0 {
+        // fork が使えなければその場で走らせる
+        return match run() {
+            Ok(()) => Outcome::Passed,
+            Err(message) => {
+                println!("# {}: {}", name, message);
+                Outcome::Failed
+            }
+        };
     }
-}
 
-fn test_chdir() -> bool {
-    unsafe {
-        printf(b"\n  Changing directory to '/' ... \0".as_ptr() as *const c_char);
-    }
-    
-    let result = fs::chdir("/\0");
-    
-    unsafe {
-        if result == 0 || result == u64::MAX - 5 { // SUCCESS or ENOSYS
-            printf(b"(syscall returned %llu) \0".as_ptr() as *const c_char, result);
-            true
-        } else {
-            printf(b"failed with error %llu \0".as_ptr() as *const c_char, result);
-            false
+    let start = Instant::now();
+    loop {
+        drain_ipc();
+        match sys::wait4(pid, sys::WNOHANG) {
+            Ok((0, _)) => {}
+            Ok((_, status)) => {
+                return match (sys::exit_code(status), sys::term_signal(status)) {
+                    (Some(0), _) => Outcome::Passed,
+                    (_, Some(sig)) => Outcome::Signaled(sig),
+                    _ => Outcome::Failed,
+                };
+            }
+            Err(_) => return Outcome::Failed,
         }
-    }
-}
-
-fn test_readdir() -> bool {
-    unsafe {
-        printf(b"\n  Reading directory (fd=3) ... \0".as_ptr() as *const c_char);
-    }
-    
-    let mut buf = [0u8; 512];
-    let result = fs::readdir(3, &mut buf);
-    
-    unsafe {
-        if result == u64::MAX - 5 { // ENOSYS is expected for now
-            printf(b"(syscall returned ENOSYS) \0".as_ptr() as *const c_char);
-            true
-        } else if result == u64::MAX - 3 { // EBADF
-            printf(b"(syscall returned EBADF, expected) \0".as_ptr() as *const c_char);
-            true
-        } else {
-            printf(b"read %llu bytes \0".as_ptr() as *const c_char, result);
-            result == 0 || result > 0
+        if start.elapsed() >= TEST_TIMEOUT {
+            let _ = signal::kill(pid, signal::SIGKILL);
+            let _ = sys::wait4(pid, 0);
+            return Outcome::TimedOut;
         }
+        time::sleep_ms(10);
     }
 }
 
-// ========== メイン関数 ==========
 fn main() {
-    let argc = std::env::args().count() as i32;
-    let args: Vec<std::ffi::CString> = std::env::args()
-        .map(|a| std::ffi::CString::new(a).unwrap_or_default())
-        .collect();
-    let argv_ptrs: Vec<*const u8> = args.iter().map(|a| a.as_ptr() as *const u8).collect();
-    let argv: *const *const u8 = argv_ptrs.as_ptr();
-    unsafe {
-        printf(b"\n========================================\n\0".as_ptr() as *const c_char);
-        printf(b"mochiOS Test Suite\n\0".as_ptr() as *const c_char);
-        printf(b"========================================\n\n\0".as_ptr() as *const c_char);
-
-        printf(b"Test invocation:\n\0".as_ptr() as *const c_char);
-        printf(b"  argc: %d\n\0".as_ptr() as *const c_char, argc);
-        for i in 0..argc {
-            let arg_ptr = *argv.offset(i as isize);
-            printf(b"  argv[%d]: %s\n\0".as_ptr() as *const c_char, i, arg_ptr);
-        }
-        printf(b"\n\0".as_ptr() as *const c_char);
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some(EXIT_WITH_ARG) {
+        let code = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
+        std::process::exit(code);
     }
+    let filter = args.get(1).cloned().unwrap_or_default();
+
+    let suites: [&[Test]; 5] = [
+        fs::TESTS,
+        pipe::TESTS,
+        process::TESTS,
+        signals::TESTS,
+        memory::TESTS,
+    ];
+    let tests: Vec<&Test> = suites
+        .iter()
+        .flat_map(|suite| suite.iter())
+        .filter(|t| t.name.contains(filter.as_str()))
+        .collect();
 
-    let mut stats = TestStats::new();
-
-    // 基本テストを実行
-    stats.run_test("basic_arithmetic\0", test_basic_arithmetic);
-    stats.run_test("string_compare\0", test_string_compare);
-    stats.run_test("argc_argv\0", test_argc_argv);
-    
-    // ファイルシステムテストを実行
-    unsafe {
-        printf(b"\n--- Filesystem Tests ---\n\0".as_ptr() as *const c_char);
+    println!("TAP version 13");
+    println!("1..{}", tests.len());
+
+    let (mut passed, mut failed, mut todo) = (0, 0, 0);
+    for (index, test) in tests.iter().enumerate() {
+        let number = index + 1;
+        let outcome = run_isolated(test);
+        let ok = matches!(outcome, Outcome::Passed);
+        let directive = test
+            .todo
+            .map(|reason| format!(" # TODO {}", reason))
+            .unwrap_or_default();
+        println!(
+            "{} {} - {}{}",
+            if ok { "ok" } else { "not ok" },
+            number,
+            test.name,
+            directive
+        );
+        match outcome {
+            Outcome::Signaled(sig) => println!("  # killed by signal {}", sig),
+            Outcome::TimedOut => println!("  # timed out after {:?}", TEST_TIMEOUT),
+            Outcome::Passed | Outcome::Failed => {}
+        }
+        match (ok, test.todo.is_some()) {
+            (true, _) => passed += 1,
+            (false, true) => todo += 1,
+            (false, false) => failed += 1,
+        }
     }
-    stats.run_test("mkdir\0", test_mkdir);
-    stats.run_test("chdir\0", test_chdir);
-    stats.run_test("readdir\0", test_readdir);
-    stats.run_test("rmdir\0", test_rmdir);
-
-    // 結果を表示
-    stats.summary();
 
-    // 失敗があれば終了コード1を返す
-    std::process::exit(if stats.failed > 0 { 1 } else { 0 });
+    println!("# pass {}", passed);
+    println!("# fail {}", failed);
+    println!("# todo {}", todo);
+    // 既知の失敗（TODO）だけが残っている場合は 2 で終わり、全成功と区別できるようにする
+    let code = match (failed, todo) {
+        (0, 0) => 0,
+        (0, _) => 2,
+        _ => 1,
+    };
+    std::process::exit(code);
 }
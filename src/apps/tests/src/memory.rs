@@ -0,0 +1,126 @@
+//! mmap / munmap / futex
+
+use crate::sys::{self, SyscallNumber};
+use crate::{Test, TestResult};
+
+const PAGE_SIZE: u64 = 4096;
+
+pub const TESTS: &[Test] = &[
+    Test::new(
+        "mmap: anonymous mapping is zeroed and writable",
+        mmap_anonymous,
+    ),
+    Test::new("mmap: zero length is EINVAL", mmap_zero_len),
+    Test::new("munmap: access after unmap raises SIGSEGV", munmap_segv),
+    Test::new("munmap: zero length is EINVAL", munmap_zero_len),
+    Test::todo(
+        "munmap: unaligned address is EINVAL",
+        munmap_unaligned,
+        "the address is rounded down to a page",
+    ),
+    Test::new("futex: WAIT with stale value is EAGAIN", futex_stale),
+    Test::new("futex: WAKE without waiters returns 0", futex_wake_none),
+    Test::new("futex: WAIT on NULL is EFAULT", futex_efault),
+    Test::new("futex: unknown op is ENOSYS", futex_bad_op),
+];
+
+fn mmap(len: u64) -> i64 {
+    sys::sys5(
+        SyscallNumber::Mmap,
+        0,
+        len,
+        sys::PROT_READ | sys::PROT_WRITE,
+        sys::MAP_PRIVATE | sys::MAP_ANONYMOUS,
+        u64::MAX,
+    )
+}
+
+fn munmap(addr: u64, len: u64) -> i64 {
+    sys::sys2(SyscallNumber::Munmap, addr, len)
+}
+
+fn mmap_anonymous() -> TestResult {
+    let len = 2 * PAGE_SIZE;
+    let addr = mmap(len);
+    check!(addr > 0, "mmap returned {}", addr);
+    check_eq!(addr as u64 % PAGE_SIZE, 0);
+    // SAFETY: 直前に確保した len バイトの領域
+    let bytes = unsafe { std::slice::from_raw_parts_mut(addr as *mut u8, len as usize) };
+    check!(bytes.iter().all(|&b| b == 0), "mapping is not zeroed");
+    bytes.fill(0xa5);
+    check!(
+        bytes.iter().all(|&b| b == 0xa5),
+        "mapping did not keep writes"
+    );
+    check_eq!(munmap(addr as u64, len), 0);
+    Ok(())
+}
+
+fn mmap_zero_len() -> TestResult {
+    check_errno!(mmap(0), EINVAL);
+    Ok(())
+}
+
+fn munmap_segv() -> TestResult {
+    let pid = sys::spawn(|| {
+        let addr = mmap(PAGE_SIZE);
+        if addr <= 0 || munmap(addr as u64, PAGE_SIZE) != 0 {
+            return 2;
+        }
+        // SAFETY: 解放済みのページに触れて SIGSEGV を起こす（子プロセス内）
+        unsafe { (addr as *mut u8).write_volatile(1) };
+        0
+    });
+    check!(pid > 0, "fork returned {}", pid);
+    let (_, status) = sys::wait4(pid, 0).map_err(|e| format!("wait4 returned {}", e))?;
+    check_eq!(sys::term_signal(status), Some(swiftlib::signal::SIGSEGV));
+    Ok(())
+}
+
+fn munmap_zero_len() -> TestResult {
+    let addr = mmap(PAGE_SIZE);
+    check!(addr > 0, "mmap returned {}", addr);
+    let ret = munmap(addr as u64, 0);
+    munmap(addr as u64, PAGE_SIZE);
+    check_errno!(ret, EINVAL);
+    Ok(())
+}
+
+fn munmap_unaligned() -> TestResult {
+    let addr = mmap(PAGE_SIZE);
+    check!(addr > 0, "mmap returned {}", addr);
+    let ret = munmap(addr as u64 + 1, PAGE_SIZE - 1);
+    munmap(addr as u64, PAGE_SIZE);
+    check_errno!(ret, EINVAL);
+    Ok(())
+}
+
+fn futex(uaddr: u64, op: u64, val: u64) -> i64 {
+    sys::sys4(SyscallNumber::Futex, uaddr, op, val, 0)
+}
+
+fn futex_stale() -> TestResult {
+    let word = 1u32;
+    check_errno!(
+        futex(&word as *const u32 as u64, sys::FUTEX_WAIT, 0),
+        EAGAIN
+    );
+    Ok(())
+}
+
+fn futex_wake_none() -> TestResult {
+    let word = 0u32;
+    check_eq!(futex(&word as *const u32 as u64, sys::FUTEX_WAKE, 1), 0);
+    Ok(())
+}
+
+fn futex_efault() -> TestResult {
+    check_errno!(futex(0, sys::FUTEX_WAIT, 0), EFAULT);
+    Ok(())
+}
+
+fn futex_bad_op() -> TestResult {
+    let word = 0u32;
+    check_errno!(futex(&word as *const u32 as u64, 99, 0), ENOSYS);
+    Ok(())
+}
@@ -0,0 +1,220 @@
+//! pipe2 / poll / select
+
+use crate::sys::{self, PollFd, SyscallNumber};
+use crate::{Test, TestResult};
+
+pub const TESTS: &[Test] = &[
+    Test::new("pipe: data written is read back", round_trip),
+    Test::new("pipe: pipe2 into NULL is EFAULT", pipe2_efault),
+    Test::new("pipe: write to read end is EBADF", write_read_end),
+    Test::todo(
+        "pipe: read from write end is EBADF",
+        read_write_end,
+        "falls through to the regular file path",
+    ),
+    Test::new("pipe: O_CLOEXEC sets FD_CLOEXEC", cloexec_flag),
+    Test::todo(
+        "pipe: read returns 0 after writer closes",
+        eof_after_close,
+        "close does not drop the pipe's writer count",
+    ),
+    Test::todo(
+        "pipe: write without reader is EPIPE",
+        epipe_without_reader,
+        "close does not drop the pipe's reader count",
+    ),
+    Test::new("poll: empty pipe with zero timeout returns 0", poll_timeout),
+    Test::new("poll: negative fd is ignored", poll_negative_fd),
+    Test::new("poll: NULL fds is EFAULT", poll_efault),
+    Test::todo(
+        "poll: readable pipe reports POLLIN",
+        poll_readable,
+        "poll only knows about tty and signalfd",
+    ),
+    Test::todo(
+        "poll: unused fd reports POLLNVAL",
+        poll_nval,
+        "poll only knows about tty and signalfd",
+    ),
+    Test::new("select: zero timeout returns 0", select_timeout),
+    Test::new(
+        "select: negative timeout is EINVAL",
+        select_negative_timeout,
+    ),
+    Test::todo(
+        "select: readable pipe is reported",
+        select_readable,
+        "select only knows about tty",
+    ),
+];
+
+fn pipe() -> Result<(i64, i64), String> {
+    sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))
+}
+
+fn close_both((rd, wr): (i64, i64)) {
+    sys::close(rd);
+    sys::close(wr);
+}
+
+fn round_trip() -> TestResult {
+    let (rd, wr) = pipe()?;
+    check_eq!(sys::write(wr, b"hello"), 5);
+    let mut buf = [0u8; 8];
+    check_eq!(sys::read(rd, &mut buf), 5);
+    check_eq!(&buf[..5], b"hello");
+    close_both((rd, wr));
+    Ok(())
+}
+
+fn pipe2_efault() -> TestResult {
+    check_errno!(sys::sys2(SyscallNumber::Pipe2, 0, 0), EFAULT);
+    Ok(())
+}
+
+fn write_read_end() -> TestResult {
+    let fds = pipe()?;
+    let ret = sys::write(fds.0, b"x");
+    close_both(fds);
+    check_errno!(ret, EBADF);
+    Ok(())
+}
+
+fn read_write_end() -> TestResult {
+    let fds = pipe()?;
+    let mut buf = [0u8; 1];
+    let ret = sys::read(fds.1, &mut buf);
+    close_both(fds);
+    check_errno!(ret, EBADF);
+    Ok(())
+}
+
+fn cloexec_flag() -> TestResult {
+    let fds = sys::pipe2(sys::O_CLOEXEC).map_err(|e| format!("pipe2 returned {}", e))?;
+    let rd_flags = sys::sys2(SyscallNumber::Fcntl, fds.0 as u64, sys::F_GETFD);
+    let wr_flags = sys::sys2(SyscallNumber::Fcntl, fds.1 as u64, sys::F_GETFD);
+    close_both(fds);
+    check_eq!(rd_flags, sys::FD_CLOEXEC);
+    check_eq!(wr_flags, sys::FD_CLOEXEC);
+    Ok(())
+}
+
+fn eof_after_close() -> TestResult {
+    let (rd, wr) = pipe()?;
+    check_eq!(sys::write(wr, b"x"), 1);
+    check_eq!(sys::close(wr), 0);
+    let mut buf = [0u8; 4];
+    check_eq!(sys::read(rd, &mut buf), 1);
+    check_eq!(sys::read(rd, &mut buf), 0);
+    sys::close(rd);
+    Ok(())
+}
+
+fn epipe_without_reader() -> TestResult {
+    let (rd, wr) = pipe()?;
+    // SIGPIPE で終わらないように無視する
+    let ignore = swiftlib::signal::SigAction::disposition(swiftlib::signal::SIG_IGN);
+    let _ = swiftlib::signal::sigaction(swiftlib::signal::SIGPIPE, Some(&ignore));
+    check_eq!(sys::close(rd), 0);
+    let ret = sys::write(wr, b"x");
+    sys::close(wr);
+    check_errno!(ret, EPIPE);
+    Ok(())
+}
+
+fn poll(fds: &mut [PollFd], timeout_ms: i64) -> i64 {
+    sys::sys3(
+        SyscallNumber::Poll,
+        fds.as_mut_ptr() as u64,
+        fds.len() as u64,
+        timeout_ms as u64,
+    )
+}
+
+fn pollfd(fd: i64) -> PollFd {
+    PollFd {
+        fd: fd as i32,
+        events: sys::POLLIN,
+        revents: -1,
+    }
+}
+
+fn poll_timeout() -> TestResult {
+    let (rd, wr) = pipe()?;
+    let mut fds = [pollfd(rd)];
+    let ret = poll(&mut fds, 0);
+    close_both((rd, wr));
+    check_eq!(ret, 0);
+    check_eq!(fds[0].revents, 0);
+    Ok(())
+}
+
+fn poll_negative_fd() -> TestResult {
+    let mut fds = [pollfd(-1)];
+    check_eq!(poll(&mut fds, 0), 0);
+    check_eq!(fds[0].revents, 0);
+    Ok(())
+}
+
+fn poll_efault() -> TestResult {
+    check_errno!(sys::sys3(SyscallNumber::Poll, 0, 1, 0), EFAULT);
+    Ok(())
+}
+
+fn poll_readable() -> TestResult {
+    let (rd, wr) = pipe()?;
+    check_eq!(sys::write(wr, b"x"), 1);
+    let mut fds = [pollfd(rd)];
+    let ret = poll(&mut fds, 0);
+    close_both((rd, wr));
+    check_eq!(ret, 1);
+    check_eq!(fds[0].revents & sys::POLLIN, sys::POLLIN);
+    Ok(())
+}
+
+fn poll_nval() -> TestResult {
+    let mut fds = [pollfd(sys::UNUSED_FD)];
+    check_eq!(poll(&mut fds, 0), 1);
+    check_eq!(fds[0].revents, sys::POLLNVAL);
+    Ok(())
+}
+
+/// select(2): `nfds` 未満の読み込み集合 `readfds` を `timeout = (秒, マイクロ秒)` で待つ
+fn select(nfds: i64, readfds: &mut u64, timeout: (i64, i64)) -> i64 {
+    let timeout = [timeout.0, timeout.1];
+    sys::sys5(
+        SyscallNumber::Select,
+        nfds as u64,
+        readfds as *mut u64 as u64,
+        0,
+        0,
+        timeout.as_ptr() as u64,
+    )
+}
+
+fn select_timeout() -> TestResult {
+    let (rd, wr) = pipe()?;
+    let mut readfds = 1u64 << rd;
+    let ret = select(rd + 1, &mut readfds, (0, 0));
+    close_both((rd, wr));
+    check_eq!(ret, 0);
+    check_eq!(readfds, 0);
+    Ok(())
+}
+
+fn select_negative_timeout() -> TestResult {
+    let mut readfds = 0u64;
+    check_errno!(select(1, &mut readfds, (-1, 0)), EINVAL);
+    Ok(())
+}
+
+fn select_readable() -> TestResult {
+    let (rd, wr) = pipe()?;
+    check_eq!(sys::write(wr, b"x"), 1);
+    let mut readfds = 1u64 << rd;
+    let ret = select(rd + 1, &mut readfds, (0, 0));
+    close_both((rd, wr));
+    check_eq!(ret, 1);
+    check_eq!(readfds, 1u64 << rd);
+    Ok(())
+}
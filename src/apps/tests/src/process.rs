@@ -0,0 +1,156 @@
+//! fork / execve / wait4 / exit
+
+use crate::sys::{self, SyscallNumber};
+use crate::{Test, TestResult, EXIT_WITH_ARG};
+use std::ffi::CString;
+
+const SELF_ELF: &str = "/applications/tests.app/entry.elf";
+
+pub const TESTS: &[Test] = &[
+    Test::new("fork: child sees 0 and its own pid", fork_pids),
+    Test::new("fork: child's parent is the caller", fork_ppid),
+    Test::new("fork: child gets a copy of memory", fork_copies_memory),
+    Test::new("wait4: exit status is reported", wait_exit_status),
+    Test::new("wait4: no children is ECHILD", wait_echild),
+    Test::new("wait4: WNOHANG on running child returns 0", wait_wnohang),
+    Test::new("wait4: unknown options are EINVAL", wait_bad_options),
+    Test::new("wait4: nonexistent pid is ECHILD", wait_unknown_pid),
+    Test::new("execve: missing path is ENOENT", execve_enoent),
+    Test::new("execve: new image runs with argv", execve_self),
+    Test::todo(
+        "execve: NULL path is EFAULT",
+        execve_null,
+        "NULL path is reported as EINVAL",
+    ),
+];
+
+fn fork_pids() -> TestResult {
+    let parent = sys::getpid();
+    let pipe = sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))?;
+    let pid = sys::spawn(|| {
+        let me = sys::getpid();
+        sys::write(pipe.1, &me.to_ne_bytes());
+        0
+    });
+    check!(pid > 0, "fork returned {}", pid);
+    check!(pid != parent, "child pid equals parent pid {}", parent);
+    let mut buf = [0u8; 8];
+    check_eq!(sys::read(pipe.0, &mut buf), 8);
+    check_eq!(i64::from_ne_bytes(buf), pid);
+    check_eq!(sys::wait4(pid, 0).map(|(p, _)| p), Ok(pid));
+    sys::close(pipe.0);
+    sys::close(pipe.1);
+    Ok(())
+}
+
+fn fork_ppid() -> TestResult {
+    let parent = sys::getpid();
+    let pid = sys::spawn(|| (sys::sys0(SyscallNumber::GetPpid) != parent) as i32);
+    check!(pid > 0, "fork returned {}", pid);
+    let (_, status) = sys::wait4(pid, 0).map_err(|e| format!("wait4 returned {}", e))?;
+    check_eq!(sys::exit_code(status), Some(0));
+    Ok(())
+}
+
+fn fork_copies_memory() -> TestResult {
+    let mut value = 1u32;
+    let value_ptr = &mut value as *mut u32;
+    let pid = sys::spawn(|| {
+        // SAFETY: fork 後の子プロセスが自分のコピーを書き換えるだけ
+        unsafe {
+            let copied = value_ptr.read_volatile();
+            value_ptr.write_volatile(2);
+            (copied != 1) as i32
+        }
+    });
+    check!(pid > 0, "fork returned {}", pid);
+    let (_, status) = sys::wait4(pid, 0).map_err(|e| format!("wait4 returned {}", e))?;
+    check_eq!(sys::exit_code(status), Some(0));
+    // SAFETY: 自分のスタック上の値を読むだけ
+    check_eq!(unsafe { value_ptr.read_volatile() }, 1);
+    Ok(())
+}
+
+fn wait_exit_status() -> TestResult {
+    let pid = sys::spawn(|| 42);
+    check!(pid > 0, "fork returned {}", pid);
+    let (reaped, status) = sys::wait4(pid, 0).map_err(|e| format!("wait4 returned {}", e))?;
+    check_eq!(reaped, pid);
+    check_eq!(sys::exit_code(status), Some(42));
+    check_eq!(sys::term_signal(status), None);
+    Ok(())
+}
+
+fn wait_echild() -> TestResult {
+    check_eq!(sys::wait4(-1, sys::WNOHANG), Err(-sys::ECHILD));
+    Ok(())
+}
+
+fn wait_wnohang() -> TestResult {
+    let pipe = sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))?;
+    let pid = sys::spawn(|| {
+        // 親が書き込むまで待つ
+        let mut buf = [0u8; 1];
+        sys::read(pipe.0, &mut buf);
+        0
+    });
+    check!(pid > 0, "fork returned {}", pid);
+    let early = sys::wait4(pid, sys::WNOHANG);
+    sys::write(pipe.1, b"x");
+    let reaped = sys::wait4(pid, 0);
+    sys::close(pipe.0);
+    sys::close(pipe.1);
+    check_eq!(early.map(|(p, _)| p), Ok(0));
+    check_eq!(reaped.map(|(p, _)| p), Ok(pid));
+    Ok(())
+}
+
+fn wait_bad_options() -> TestResult {
+    check_eq!(sys::wait4(-1, 0x8000_0000), Err(-sys::EINVAL));
+    Ok(())
+}
+
+fn wait_unknown_pid() -> TestResult {
+    check_eq!(sys::wait4(0x7fff_fff0, sys::WNOHANG), Err(-sys::ECHILD));
+    Ok(())
+}
+
+fn execve(path: &CString, args: &[&str]) -> i64 {
+    let args: Vec<CString> = args
+        .iter()
+        .map(|a| CString::new(*a).unwrap_or_default())
+        .collect();
+    let mut argv: Vec<*const i8> = args.iter().map(|a| a.as_ptr().cast()).collect();
+    argv.push(std::ptr::null());
+    let envp: [*const i8; 1] = [std::ptr::null()];
+    sys::sys3(
+        SyscallNumber::Execve,
+        path.as_ptr() as u64,
+        argv.as_ptr() as u64,
+        envp.as_ptr() as u64,
+    )
+}
+
+fn execve_enoent() -> TestResult {
+    let path = CString::new("/no/such/binary").unwrap_or_default();
+    check_errno!(execve(&path, &["binary"]), ENOENT);
+    Ok(())
+}
+
+fn execve_self() -> TestResult {
+    let pid = sys::spawn(|| {
+        let path = CString::new(SELF_ELF).unwrap_or_default();
+        execve(&path, &[SELF_ELF, EXIT_WITH_ARG, "7"]);
+        // ここに来たら execve の失敗
+        127
+    });
+    check!(pid > 0, "fork returned {}", pid);
+    let (_, status) = sys::wait4(pid, 0).map_err(|e| format!("wait4 returned {}", e))?;
+    check_eq!(sys::exit_code(status), Some(7));
+    Ok(())
+}
+
+fn execve_null() -> TestResult {
+    check_errno!(sys::sys3(SyscallNumber::Execve, 0, 0, 0), EFAULT);
+    Ok(())
+}
@@ -0,0 +1,137 @@
+//! kill / rt_sigaction / rt_sigprocmask
+
+use crate::sys::{self, SyscallNumber};
+use crate::{Test, TestResult};
+use std::sync::atomic::{AtomicI32, Ordering};
+use swiftlib::signal::{self, SigAction, SigInfo};
+
+pub const TESTS: &[Test] = &[
+    Test::new("sigaction: signal 0 and 65 are EINVAL", sigaction_range),
+    Test::new(
+        "sigaction: SIGKILL/SIGSTOP are EINVAL",
+        sigaction_unblockable,
+    ),
+    Test::new("sigaction: previous action is returned", sigaction_old),
+    Test::new(
+        "kill: handler runs for signal sent to self",
+        kill_self_handler,
+    ),
+    Test::new("kill: invalid signal is EINVAL", kill_einval),
+    Test::new("kill: nonexistent pid is ESRCH", kill_esrch),
+    Test::new("kill: SIGTERM default action terminates", kill_default_term),
+    Test::new(
+        "sigprocmask: blocked signal stays pending",
+        sigprocmask_block,
+    ),
+    Test::new("sigaction: SIG_IGN discards the signal", sig_ign),
+];
+
+/// 最後にハンドラが受け取ったシグナル
+static CAUGHT: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn record(sig: i32, _info: *const SigInfo, _ctx: *mut u8) {
+    CAUGHT.store(sig, Ordering::SeqCst);
+}
+
+fn install(sig: i32) -> Result<SigAction, String> {
+    let action = SigAction::new(record, 0, 0);
+    signal::sigaction(sig, Some(&action)).map_err(|e| format!("sigaction returned {}", e as i64))
+}
+
+/// swiftlib の `Result<_, u64>` を負の errno にする
+fn errno<T>(ret: Result<T, u64>) -> i64 {
+    match ret {
+        Ok(_) => 0,
+        Err(e) => e as i64,
+    }
+}
+
+fn sigaction_range() -> TestResult {
+    let action = SigAction::new(record, 0, 0);
+    check_errno!(errno(signal::sigaction(0, Some(&action))), EINVAL);
+    check_errno!(errno(signal::sigaction(65, Some(&action))), EINVAL);
+    Ok(())
+}
+
+fn sigaction_unblockable() -> TestResult {
+    let action = SigAction::new(record, 0, 0);
+    check_errno!(
+        errno(signal::sigaction(signal::SIGKILL, Some(&action))),
+        EINVAL
+    );
+    check_errno!(
+        errno(signal::sigaction(signal::SIGSTOP, Some(&action))),
+        EINVAL
+    );
+    Ok(())
+}
+
+fn sigaction_old() -> TestResult {
+    let first = install(signal::SIGUSR2)?;
+    check_eq!(first.handler, signal::SIG_DFL);
+    let ignore = SigAction::disposition(signal::SIG_IGN);
+    let old = signal::sigaction(signal::SIGUSR2, Some(&ignore))
+        .map_err(|e| format!("sigaction returned {}", e as i64))?;
+    check_eq!(old.handler, SigAction::new(record, 0, 0).handler);
+    Ok(())
+}
+
+fn kill_self_handler() -> TestResult {
+    install(signal::SIGUSR1)?;
+    CAUGHT.store(0, Ordering::SeqCst);
+    check_eq!(errno(signal::kill(sys::getpid(), signal::SIGUSR1)), 0);
+    check_eq!(CAUGHT.load(Ordering::SeqCst), signal::SIGUSR1);
+    Ok(())
+}
+
+fn kill_einval() -> TestResult {
+    check_errno!(errno(signal::kill(sys::getpid(), 65)), EINVAL);
+    Ok(())
+}
+
+fn kill_esrch() -> TestResult {
+    check_errno!(errno(signal::kill(0x7fff_fff0, signal::SIGTERM)), ESRCH);
+    Ok(())
+}
+
+fn kill_default_term() -> TestResult {
+    let pipe = sys::pipe2(0).map_err(|e| format!("pipe2 returned {}", e))?;
+    let pid = sys::spawn(|| {
+        // シグナルが届くまで待つ（親は何も書き込まない）
+        let mut buf = [0u8; 1];
+        sys::read(pipe.0, &mut buf);
+        0
+    });
+    check!(pid > 0, "fork returned {}", pid);
+    check_eq!(errno(signal::kill(pid, signal::SIGTERM)), 0);
+    let (_, status) = sys::wait4(pid, 0).map_err(|e| format!("wait4 returned {}", e))?;
+    sys::close(pipe.0);
+    sys::close(pipe.1);
+    check_eq!(sys::term_signal(status), Some(signal::SIGTERM));
+    Ok(())
+}
+
+fn sigprocmask_block() -> TestResult {
+    install(signal::SIGUSR1)?;
+    CAUGHT.store(0, Ordering::SeqCst);
+    let mask = signal::sig_bit(signal::SIGUSR1);
+    signal::sigprocmask(signal::SIG_BLOCK, Some(mask))
+        .map_err(|e| format!("sigprocmask returned {}", e as i64))?;
+    check_eq!(errno(signal::kill(sys::getpid(), signal::SIGUSR1)), 0);
+    check_eq!(CAUGHT.load(Ordering::SeqCst), 0);
+    // 解除すると保留中のシグナルが届く
+    signal::sigprocmask(signal::SIG_UNBLOCK, Some(mask))
+        .map_err(|e| format!("sigprocmask returned {}", e as i64))?;
+    sys::sys0(SyscallNumber::GetPid);
+    check_eq!(CAUGHT.load(Ordering::SeqCst), signal::SIGUSR1);
+    Ok(())
+}
+
+fn sig_ign() -> TestResult {
+    let ignore = SigAction::disposition(signal::SIG_IGN);
+    signal::sigaction(signal::SIGTERM, Some(&ignore))
+        .map_err(|e| format!("sigaction returned {}", e as i64))?;
+    check_eq!(errno(signal::kill(sys::getpid(), signal::SIGTERM)), 0);
+    // ここまで来れば SIGTERM で終了していない
+    Ok(())
+}
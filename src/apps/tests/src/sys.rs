@@ -0,0 +1,178 @@
+//! テストから生のシステムコールを呼ぶためのラッパー
+//!
+//! libc の関数は -1 と errno に変換してしまうので、カーネルの戻り値
+//! （失敗時は負の errno）をそのまま見るために swiftlib の `syscall` を直接使う。
+
+pub use swiftlib::sys::SyscallNumber;
+
+extern "C" {
+    /// swiftlib の `syscall(nr, a0..a4)`（戻り値は変換しない）
+    fn syscall(nr: u64, ...) -> i64;
+}
+
+pub fn sys0(nr: SyscallNumber) -> i64 {
+    unsafe { syscall(nr as u64) }
+}
+
+pub fn sys1(nr: SyscallNumber, a0: u64) -> i64 {
+    unsafe { syscall(nr as u64, a0) }
+}
+
+pub fn sys2(nr: SyscallNumber, a0: u64, a1: u64) -> i64 {
+    unsafe { syscall(nr as u64, a0, a1) }
+}
+
+pub fn sys3(nr: SyscallNumber, a0: u64, a1: u64, a2: u64) -> i64 {
+    unsafe { syscall(nr as u64, a0, a1, a2) }
+}
+
+pub fn sys4(nr: SyscallNumber, a0: u64, a1: u64, a2: u64, a3: u64) -> i64 {
+    unsafe { syscall(nr as u64, a0, a1, a2, a3) }
+}
+
+pub fn sys5(nr: SyscallNumber, a0: u64, a1: u64, a2: u64, a3: u64, a4: u64) -> i64 {
+    unsafe { syscall(nr as u64, a0, a1, a2, a3, a4) }
+}
+
+// errno（カーネルは負の値で返す）
+pub const ENOENT: i64 = 2;
+pub const ESRCH: i64 = 3;
+pub const EBADF: i64 = 9;
+pub const ECHILD: i64 = 10;
+pub const EAGAIN: i64 = 11;
+pub const EFAULT: i64 = 14;
+pub const ENOTDIR: i64 = 20;
+pub const EINVAL: i64 = 22;
+pub const ESPIPE: i64 = 29;
+pub const EPIPE: i64 = 32;
+pub const ENOSYS: i64 = 38;
+
+// open(2)
+pub const O_RDONLY: u64 = 0;
+pub const O_DIRECTORY: u64 = 0x10000;
+pub const O_CLOEXEC: u64 = 0x80000;
+
+// lseek(2)
+pub const SEEK_SET: u64 = 0;
+pub const SEEK_CUR: u64 = 1;
+pub const SEEK_END: u64 = 2;
+
+// fcntl(2)
+pub const F_GETFD: u64 = 1;
+pub const FD_CLOEXEC: i64 = 1;
+
+// poll(2)
+pub const POLLIN: i16 = 0x1;
+pub const POLLNVAL: i16 = 0x20;
+
+// mmap(2)
+pub const PROT_READ: u64 = 0x1;
+pub const PROT_WRITE: u64 = 0x2;
+pub const MAP_PRIVATE: u64 = 0x2;
+pub const MAP_ANONYMOUS: u64 = 0x20;
+
+// futex(2)
+pub const FUTEX_WAIT: u64 = 0;
+pub const FUTEX_WAKE: u64 = 1;
+
+// wait4(2)
+pub const WNOHANG: u64 = 0x1;
+
+/// 使われていないはずの FD 番号
+pub const UNUSED_FD: i64 = 200;
+
+/// struct pollfd
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: i16,
+    pub revents: i16,
+}
+
+pub fn open(path: &str, flags: u64) -> i64 {
+    let path = std::ffi::CString::new(path).unwrap_or_default();
+    sys2(SyscallNumber::Open, path.as_ptr() as u64, flags)
+}
+
+pub fn close(fd: i64) -> i64 {
+    sys1(SyscallNumber::Close, fd as u64)
+}
+
+pub fn read(fd: i64, buf: &mut [u8]) -> i64 {
+    sys3(
+        SyscallNumber::Read,
+        fd as u64,
+        buf.as_mut_ptr() as u64,
+        buf.len() as u64,
+    )
+}
+
+pub fn write(fd: i64, buf: &[u8]) -> i64 {
+    sys3(
+        SyscallNumber::Write,
+        fd as u64,
+        buf.as_ptr() as u64,
+        buf.len() as u64,
+    )
+}
+
+pub fn lseek(fd: i64, offset: i64, whence: u64) -> i64 {
+    sys3(SyscallNumber::Lseek, fd as u64, offset as u64, whence)
+}
+
+/// pipe2(2): `(読み込み端, 書き込み端)` か負の errno
+pub fn pipe2(flags: u64) -> Result<(i64, i64), i64> {
+    let mut fds = [0i32; 2];
+    let ret = sys2(SyscallNumber::Pipe2, fds.as_mut_ptr() as u64, flags);
+    if ret < 0 {
+        return Err(ret);
+    }
+    Ok((fds[0] as i64, fds[1] as i64))
+}
+
+pub fn fork() -> i64 {
+    sys0(SyscallNumber::Fork)
+}
+
+pub fn getpid() -> i64 {
+    sys0(SyscallNumber::GetPid)
+}
+
+/// wait4(2): `(pid, status)` か負の errno
+pub fn wait4(pid: i64, options: u64) -> Result<(i64, i32), i64> {
+    let mut status = 0i32;
+    let ret = sys4(
+        SyscallNumber::Wait,
+        pid as u64,
+        &mut status as *mut i32 as u64,
+        options,
+        0,
+    );
+    if ret < 0 {
+        return Err(ret);
+    }
+    Ok((ret, status))
+}
+
+/// 子プロセスとして `body` を実行し、その終了コードで `_exit` する
+pub fn spawn(body: impl FnOnce() -> i32) -> i64 {
+    let pid = fork();
+    if pid == 0 {
+        let code = body();
+        sys1(SyscallNumber::Exit, code as u64);
+        unreachable!();
+    }
+    pid
+}
+
+/// 正常終了ならその終了コード
+pub fn exit_code(status: i32) -> Option<i32> {
+    (status & 0x7f == 0).then_some((status >> 8) & 0xff)
+}
+
+/// シグナルで終了したならそのシグナル番号
+pub fn term_signal(status: i32) -> Option<i32> {
+    let sig = status & 0x7f;
+    (sig != 0 && sig != 0x7f).then_some(sig)
+}
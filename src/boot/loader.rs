@@ -43,6 +43,10 @@ static mut BOOT_INFO: BootInfo = BootInfo {
     initfs_size: 0,
     rootfs_addr: 0,
     rootfs_size: 0,
+    cmdline_addr: 0,
+    cmdline_size: 0,
+    crash_log_addr: 0,
+    crash_log_size: 0,
 };
 
 static mut MEMORY_MAP: [MemoryRegion; 256] = [MemoryRegion {
@@ -139,6 +143,49 @@ unsafe fn load_initfs(bt: &BootServices, image_handle: Handle) -> (u64, usize) {
     (0, 0)
 }
 
+/// `\system\cmdline.txt`（起動オプション）があれば読み込んで物理アドレスとサイズを返す
+unsafe fn load_cmdline(bt: &BootServices, image_handle: Handle) -> (u64, usize) {
+    let cmdline_path = cstr16!(r"\system\cmdline.txt");
+    let handles: alloc::vec::Vec<Handle> =
+        if let Ok(li) = bt.open_protocol_exclusive::<LoadedImage>(image_handle) {
+            if let Some(dev) = li.device() {
+                drop(li);
+                alloc::vec![dev]
+            } else {
+                bt.find_handles::<SimpleFileSystem>().unwrap_or_default()
+            }
+        } else {
+            bt.find_handles::<SimpleFileSystem>().unwrap_or_default()
+        };
+
+    for handle in handles {
+        if let Some((addr, size)) = try_load_raw(bt, image_handle, handle, cmdline_path, "cmdline")
+        {
+            return (addr, size);
+        }
+    }
+    (0, 0)
+}
+
+/// 永続クラッシュログ用の領域を固定の物理アドレスに予約する
+///
+/// 毎回同じアドレスを使うので、ウォームリセット後も前回の内容が残る。
+/// ファームウェアが使用中で確保できなければ無効（0）にする。
+unsafe fn reserve_crash_log(bt: &BootServices) -> (u64, usize) {
+    let size = mochios::crashlog::REGION_SIZE;
+    match bt.allocate_pages(
+        AllocateType::Address(mochios::crashlog::REGION_PHYS),
+        UefiMemType::RESERVED,
+        size / 0x1000,
+    ) {
+        Ok(addr) => (addr, size),
+        Err(e) => {
+            vga_println!("[WARN] crash log region unavailable: {:?}", e.status());
+            (0, 0)
+        }
+    }
+}
+
 /// 指定ハンドルから任意ファイルをページ単位でロードし (物理アドレス, サイズ) を返す
 unsafe fn try_load_raw(
     bt: &BootServices,
@@ -564,6 +611,18 @@ unsafe fn main(image_handle: Handle, mut system_table: SystemTable<Boot>) -> Sta
         unsafe { load_initfs(bt, image_handle) }
     };
 
+    // 起動オプションは任意（無ければ空）
+    let (cmdline_addr, cmdline_size) = {
+        let bt = system_table.boot_services();
+        unsafe { load_cmdline(bt, image_handle) }
+    };
+
+    // 永続クラッシュログ（前回の起動の内容を残したまま渡す）
+    let (crash_log_addr, crash_log_size) = {
+        let bt = system_table.boot_services();
+        unsafe { reserve_crash_log(bt) }
+    };
+
     // rootfs は起動後にFS層がマウントして利用するため、
     // ブートローダーではプリロードしない（起動時間短縮）
     let (rootfs_addr, rootfs_size) = (0u64, 0usize);
@@ -615,6 +674,10 @@ unsafe fn main(image_handle: Handle, mut system_table: SystemTable<Boot>) -> Sta
         BOOT_INFO.initfs_size = initfs_size;
         BOOT_INFO.rootfs_addr = rootfs_addr;
         BOOT_INFO.rootfs_size = rootfs_size;
+        BOOT_INFO.cmdline_addr = cmdline_addr;
+        BOOT_INFO.cmdline_size = cmdline_size;
+        BOOT_INFO.crash_log_addr = crash_log_addr;
+        BOOT_INFO.crash_log_size = crash_log_size;
     }
 
     // カーネルへジャンプ (system V AMD64 ABI)
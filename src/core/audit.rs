@@ -2,14 +2,18 @@
 //!
 //! panic の代わりに、拒否・隔離・破損検出・復旧不能な局所失敗を
 //! append-only な簡易リングバッファへ記録する。
+//!
+//! 記録は CAP_AUDIT_READ を持つプロセスが audit_read システムコールで読み出し、
+//! audit.service がディスクへ書き出す。
 
+use alloc::vec::Vec;
 use core::fmt;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::interrupt::spinlock::SpinLock;
 
-const AUDIT_CAPACITY: usize = 256;
-const AUDIT_MSG_LEN: usize = 160;
+pub const AUDIT_CAPACITY: usize = 256;
+pub const AUDIT_MSG_LEN: usize = 160;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AuditEventKind {
@@ -26,9 +30,25 @@ pub enum AuditEventKind {
     Memory,
 }
 
+impl AuditEventKind {
+    /// audit_read システムコールで使う番号（種別マスクのビット位置でもある）
+    pub const fn as_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+/// 監査レコード 1 件
+///
+/// 記録時点の PID/TID はプロセス外（起動処理や割り込み）なら 0。
+/// UID は今のところ全プロセス 0（`pgroup::getuid` と同じ）。
 #[derive(Clone, Copy)]
 pub struct AuditRecord {
     seq: u64,
+    /// 起動からのティック数（100 回 = 1 秒）
+    ticks: u64,
+    pid: u64,
+    tid: u64,
+    uid: u32,
     kind: AuditEventKind,
     len: usize,
     msg: [u8; AUDIT_MSG_LEN],
@@ -38,6 +58,10 @@ impl AuditRecord {
     const fn empty() -> Self {
         Self {
             seq: 0,
+            ticks: 0,
+            pid: 0,
+            tid: 0,
+            uid: 0,
             kind: AuditEventKind::Fault,
             len: 0,
             msg: [0; AUDIT_MSG_LEN],
@@ -46,7 +70,10 @@ impl AuditRecord {
 
     fn write_message(&mut self, message: &str) {
         let bytes = message.as_bytes();
-        let len = bytes.len().min(AUDIT_MSG_LEN);
+        let mut len = bytes.len().min(AUDIT_MSG_LEN);
+        while !message.is_char_boundary(len) {
+            len -= 1;
+        }
         self.msg[..len].copy_from_slice(&bytes[..len]);
         if len < AUDIT_MSG_LEN {
             self.msg[len..].fill(0);
@@ -65,52 +92,104 @@ impl AuditRecord {
     pub fn kind(&self) -> AuditEventKind {
         self.kind
     }
+
+    /// audit_read システムコールでユーザーへ渡す形式にする
+    pub fn to_wire(&self) -> AuditWireRecord {
+        AuditWireRecord {
+            seq: self.seq,
+            ticks: self.ticks,
+            pid: self.pid,
+            tid: self.tid,
+            uid: self.uid,
+            kind: self.kind.as_u32(),
+            len: self.len as u32,
+            _pad: 0,
+            msg: self.msg,
+        }
+    }
 }
 
 impl fmt::Debug for AuditRecord {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("AuditRecord")
             .field("seq", &self.seq)
+            .field("ticks", &self.ticks)
+            .field("pid", &self.pid)
+            .field("tid", &self.tid)
+            .field("uid", &self.uid)
             .field("kind", &self.kind)
             .field("message", &self.message())
             .finish()
     }
 }
 
+/// audit_read システムコールの 1 レコード（ユーザー側 `swiftlib::audit` と同じ配置）
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AuditWireRecord {
+    pub seq: u64,
+    pub ticks: u64,
+    pub pid: u64,
+    pub tid: u64,
+    pub uid: u32,
+    pub kind: u32,
+    pub len: u32,
+    pub _pad: u32,
+    pub msg: [u8; AUDIT_MSG_LEN],
+}
+
 static AUDIT_LOG: SpinLock<[AuditRecord; AUDIT_CAPACITY]> =
-    SpinLock::new([AuditRecord::empty(); AUDIT_CAPACITY]);
+    SpinLock::named("AUDIT_LOG", [AuditRecord::empty(); AUDIT_CAPACITY]);
 static AUDIT_SEQ: AtomicUsize = AtomicUsize::new(1);
 
 pub fn log(kind: AuditEventKind, message: &str) {
     let seq = AUDIT_SEQ.fetch_add(1, Ordering::Relaxed) as u64;
     let idx = (seq as usize) % AUDIT_CAPACITY;
+    // スケジューラがロックを持ったまま呼ぶこともあるので、ID は per-CPU から読む
+    let ticks = crate::interrupt::timer::get_ticks();
+    let pid = crate::percpu::current_process_raw_id();
+    let tid = crate::percpu::current_thread_raw_id();
+    let uid = crate::syscall::pgroup::getuid() as u32;
     {
         let mut log = AUDIT_LOG.lock();
         let slot = &mut log[idx];
         slot.seq = seq;
+        slot.ticks = ticks;
+        slot.pid = pid;
+        slot.tid = tid;
+        slot.uid = uid;
         slot.kind = kind;
         slot.write_message(message);
     }
-    crate::warn!("[AUDIT {:?} #{seq}] {}", kind, message);
+    crate::crashlog::audit(kind, message);
+    crate::warn!("[AUDIT {:?} #{seq} pid={pid} tid={tid}] {}", kind, message);
 }
 
-pub fn snapshot_into(out: &mut [AuditRecord]) -> usize {
-    if out.is_empty() {
-        return 0;
-    }
+/// `since` より後の記録のうち、種別が `kind_mask` に含まれるものを古い順に最大 `max` 件返す
+///
+/// `kind_mask` はビット `AuditEventKind::as_u32()` の集合で、0 なら全種別。
+/// リングから既に溢れた記録は返らない（呼び出し側は通し番号の飛びで気付ける）。
+pub fn read_since(since: u64, kind_mask: u64, max: usize) -> Vec<AuditRecord> {
+    // ロック中に確保しないよう先に取っておく
+    let mut out = Vec::with_capacity(max.min(AUDIT_CAPACITY));
     let next_seq = AUDIT_SEQ.load(Ordering::Acquire) as u64;
-    let start_seq = next_seq.saturating_sub(AUDIT_CAPACITY as u64).max(1);
+    let start_seq = next_seq
+        .saturating_sub(AUDIT_CAPACITY as u64)
+        .max(since.saturating_add(1))
+        .max(1);
     let log = AUDIT_LOG.lock();
-    let mut written = 0;
     for seq in start_seq..next_seq {
-        if written >= out.len() {
+        if out.len() >= max {
             break;
         }
-        let idx = (seq as usize) % AUDIT_CAPACITY;
-        if log[idx].seq == seq {
-            out[written] = log[idx];
-            written += 1;
+        let rec = &log[(seq as usize) % AUDIT_CAPACITY];
+        if rec.seq != seq {
+            continue;
+        }
+        if kind_mask != 0 && kind_mask & (1 << rec.kind.as_u32()) == 0 {
+            continue;
         }
+        out.push(*rec);
     }
-    written
+    out
 }
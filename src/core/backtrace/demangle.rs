@@ -0,0 +1,604 @@
+//! Rust シンボル名の復元
+//!
+//! v0 形式（`_R...`）と旧形式（`_ZN...E`）を読む。どちらもハッシュやクレートの
+//! 識別子は省く。例外ハンドラの中からも使うので、ヒープは使わず直接書き出す。
+//! 読めない名前（C の関数など）はそのまま表示する。
+
+use core::fmt::{self, Write};
+
+/// 表示時に復元するシンボル名
+pub struct Demangle<'a>(pub &'a str);
+
+impl fmt::Display for Demangle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.0;
+        let v0 = name.strip_prefix("_R").or_else(|| name.strip_prefix("__R"));
+        if let Some(inner) = v0 {
+            // 先に捨て書きで最後まで読めることを確かめる（途中まで書いてしまわないように）
+            if V0::new(inner.as_bytes(), &mut Discard).symbol().is_ok() {
+                return V0::new(inner.as_bytes(), f).symbol();
+            }
+        } else if let Some(inner) = split_legacy(name) {
+            return write_legacy(f, inner);
+        }
+        f.write_str(name)
+    }
+}
+
+/// 書き込みを捨てる出力先
+struct Discard;
+
+impl Write for Discard {
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        Ok(())
+    }
+}
+
+/// 入れ子の上限（壊れた名前や循環する後方参照でスタックを使い切らないように）
+const MAX_DEPTH: u32 = 64;
+
+/// v0 形式の読み取りと書き出し
+///
+/// 構文エラーも `fmt::Error` で返す。
+struct V0<'s, 'o> {
+    sym: &'s [u8],
+    pos: usize,
+    depth: u32,
+    /// 0 でなければ書き出さずに読み飛ばす（impl のパスなど）
+    skip: u32,
+    out: &'o mut dyn Write,
+}
+
+type Parse<T = ()> = Result<T, fmt::Error>;
+
+impl<'s, 'o> V0<'s, 'o> {
+    fn new(sym: &'s [u8], out: &'o mut dyn Write) -> Self {
+        Self {
+            sym,
+            pos: 0,
+            depth: 0,
+            skip: 0,
+            out,
+        }
+    }
+
+    fn write(&mut self, s: &str) -> Parse {
+        if self.skip == 0 {
+            self.out.write_str(s)?;
+        }
+        Ok(())
+    }
+
+    fn write_fmt_args(&mut self, args: fmt::Arguments<'_>) -> Parse {
+        if self.skip == 0 {
+            self.out.write_fmt(args)?;
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.sym.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> Parse<u8> {
+        let b = self.peek().ok_or(fmt::Error)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn enter(&mut self) -> Parse {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// `{0-9a-zA-Z} _`（`_` だけなら 0、それ以外は値 + 1）
+    fn base62(&mut self) -> Parse<u64> {
+        if self.eat(b'_') {
+            return Ok(0);
+        }
+        let mut value: u64 = 0;
+        loop {
+            let digit = match self.next()? {
+                b'_' => return value.checked_add(1).ok_or(fmt::Error),
+                b @ b'0'..=b'9' => b - b'0',
+                b @ b'a'..=b'z' => b - b'a' + 10,
+                b @ b'A'..=b'Z' => b - b'A' + 36,
+                _ => return Err(fmt::Error),
+            };
+            value = value
+                .checked_mul(62)
+                .and_then(|v| v.checked_add(digit as u64))
+                .ok_or(fmt::Error)?;
+        }
+    }
+
+    /// `tag` があれば続く base62 + 1、無ければ 0（disambiguator など）
+    fn opt_base62(&mut self, tag: u8) -> Parse<u64> {
+        if !self.eat(tag) {
+            return Ok(0);
+        }
+        self.base62()?.checked_add(1).ok_or(fmt::Error)
+    }
+
+    /// 10 進数（`0` の後ろには数字を続けない）
+    fn decimal(&mut self) -> Parse<usize> {
+        if self.eat(b'0') {
+            return Ok(0);
+        }
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let digits = &self.sym[start..self.pos];
+        if digits.is_empty() {
+            return Err(fmt::Error);
+        }
+        digits.iter().try_fold(0usize, |acc, &b| {
+            acc.checked_mul(10)
+                .and_then(|v| v.checked_add((b - b'0') as usize))
+                .ok_or(fmt::Error)
+        })
+    }
+
+    /// 識別子（punycode はそのまま表示する）
+    fn ident(&mut self) -> Parse<&'s str> {
+        self.eat(b'u');
+        let len = self.decimal()?;
+        self.eat(b'_');
+        let end = self.pos.checked_add(len).ok_or(fmt::Error)?;
+        let bytes = self.sym.get(self.pos..end).ok_or(fmt::Error)?;
+        self.pos = end;
+        core::str::from_utf8(bytes).map_err(|_| fmt::Error)
+    }
+
+    /// `B` の後の後方参照を辿って `f` で読み、元の位置に戻る
+    fn backref(&mut self, f: impl FnOnce(&mut Self) -> Parse) -> Parse {
+        let tag_pos = self.pos - 1;
+        let target = self.base62()? as usize;
+        if target >= tag_pos {
+            return Err(fmt::Error);
+        }
+        let saved = self.pos;
+        self.pos = target;
+        self.enter()?;
+        let result = f(self);
+        self.leave();
+        self.pos = saved;
+        result
+    }
+
+    fn symbol(&mut self) -> Parse {
+        if matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.decimal()?;
+        }
+        self.path(true)?;
+        // 実体化したクレート（表示しない）
+        if matches!(self.peek(), Some(b'A'..=b'Z')) {
+            self.skip += 1;
+            let result = self.path(false);
+            self.skip -= 1;
+            result?;
+        }
+        // 残り（`.llvm.*` などのベンダー接尾辞）は無視する
+        Ok(())
+    }
+
+    fn path(&mut self, in_value: bool) -> Parse {
+        self.enter()?;
+        let result = self.path_inner(in_value);
+        self.leave();
+        result
+    }
+
+    fn path_inner(&mut self, in_value: bool) -> Parse {
+        match self.next()? {
+            b'C' => {
+                self.opt_base62(b's')?;
+                let name = self.ident()?;
+                self.write(name)
+            }
+            b'N' => {
+                let ns = self.next()?;
+                self.path(in_value)?;
+                let dis = self.opt_base62(b's')?;
+                let name = self.ident()?;
+                if ns.is_ascii_uppercase() {
+                    let kind = match ns {
+                        b'C' => "closure",
+                        b'S' => "shim",
+                        _ => "",
+                    };
+                    self.write("::{")?;
+                    self.write(kind)?;
+                    if !name.is_empty() {
+                        self.write(":")?;
+                        self.write(name)?;
+                    }
+                    self.write_fmt_args(format_args!("#{}}}", dis))
+                } else if !name.is_empty() {
+                    self.write("::")?;
+                    self.write(name)
+                } else {
+                    Ok(())
+                }
+            }
+            tag @ (b'M' | b'X' | b'Y') => {
+                if tag != b'Y' {
+                    // impl の場所（表示しない）
+                    self.opt_base62(b's')?;
+                    self.skip += 1;
+                    let result = self.path(false);
+                    self.skip -= 1;
+                    result?;
+                }
+                self.write("<")?;
+                self.ty()?;
+                if tag != b'M' {
+                    self.write(" as ")?;
+                    self.path(false)?;
+                }
+                self.write(">")
+            }
+            b'I' => {
+                self.path(in_value)?;
+                if in_value {
+                    self.write("::")?;
+                }
+                self.write("<")?;
+                let mut first = true;
+                while !self.eat(b'E') {
+                    if !first {
+                        self.write(", ")?;
+                    }
+                    first = false;
+                    self.generic_arg()?;
+                }
+                self.write(">")
+            }
+            b'B' => self.backref(|p| p.path(in_value)),
+            _ => Err(fmt::Error),
+        }
+    }
+
+    fn generic_arg(&mut self) -> Parse {
+        if self.eat(b'L') {
+            self.base62()?;
+            self.write("'_")
+        } else if self.eat(b'K') {
+            self.konst()
+        } else {
+            self.ty()
+        }
+    }
+
+    fn ty(&mut self) -> Parse {
+        self.enter()?;
+        let result = self.ty_inner();
+        self.leave();
+        result
+    }
+
+    fn ty_inner(&mut self) -> Parse {
+        let tag = self.next()?;
+        if let Some(name) = basic_type(tag) {
+            return self.write(name);
+        }
+        match tag {
+            b'R' | b'Q' => {
+                self.write(if tag == b'R' { "&" } else { "&mut " })?;
+                if self.eat(b'L') && self.base62()? != 0 {
+                    self.write("'_ ")?;
+                }
+                self.ty()
+            }
+            b'P' => {
+                self.write("*const ")?;
+                self.ty()
+            }
+            b'O' => {
+                self.write("*mut ")?;
+                self.ty()
+            }
+            b'A' => {
+                self.write("[")?;
+                self.ty()?;
+                self.write("; ")?;
+                self.konst()?;
+                self.write("]")
+            }
+            b'S' => {
+                self.write("[")?;
+                self.ty()?;
+                self.write("]")
+            }
+            b'T' => {
+                self.write("(")?;
+                let mut count = 0;
+                while !self.eat(b'E') {
+                    if count > 0 {
+                        self.write(", ")?;
+                    }
+                    self.ty()?;
+                    count += 1;
+                }
+                if count == 1 {
+                    self.write(",")?;
+                }
+                self.write(")")
+            }
+            b'F' => self.fn_sig(),
+            b'D' => {
+                self.write("dyn ")?;
+                self.opt_base62(b'G')?;
+                let mut first = true;
+                while !self.eat(b'E') {
+                    if !first {
+                        self.write(" + ")?;
+                    }
+                    first = false;
+                    self.path(false)?;
+                    let mut open = false;
+                    while self.eat(b'p') {
+                        self.write(if open { ", " } else { "<" })?;
+                        open = true;
+                        let name = self.ident()?;
+                        self.write(name)?;
+                        self.write(" = ")?;
+                        self.ty()?;
+                    }
+                    if open {
+                        self.write(">")?;
+                    }
+                }
+                if !self.eat(b'L') {
+                    return Err(fmt::Error);
+                }
+                self.base62()?;
+                Ok(())
+            }
+            b'B' => self.backref(|p| p.ty()),
+            _ => {
+                self.pos -= 1;
+                self.path(false)
+            }
+        }
+    }
+
+    fn fn_sig(&mut self) -> Parse {
+        self.opt_base62(b'G')?;
+        if self.eat(b'U') {
+            self.write("unsafe ")?;
+        }
+        if self.eat(b'K') {
+            if self.eat(b'C') {
+                self.write("extern \"C\" ")?;
+            } else {
+                let abi = self.ident()?;
+                self.write("extern \"")?;
+                for part in abi.split('_').enumerate() {
+                    if part.0 > 0 {
+                        self.write("-")?;
+                    }
+                    self.write(part.1)?;
+                }
+                self.write("\" ")?;
+            }
+        }
+        self.write("fn(")?;
+        let mut first = true;
+        while !self.eat(b'E') {
+            if !first {
+                self.write(", ")?;
+            }
+            first = false;
+            self.ty()?;
+        }
+        self.write(")")?;
+        if self.eat(b'u') {
+            return Ok(());
+        }
+        self.write(" -> ")?;
+        self.ty()
+    }
+
+    fn konst(&mut self) -> Parse {
+        self.enter()?;
+        let result = self.konst_inner();
+        self.leave();
+        result
+    }
+
+    fn konst_inner(&mut self) -> Parse {
+        if self.eat(b'B') {
+            return self.backref(|p| p.konst());
+        }
+        if self.eat(b'p') {
+            return self.write("_");
+        }
+        let ty = self.next()?;
+        let negative = self.eat(b'n');
+        let mut value: u128 = 0;
+        let mut overflow = false;
+        loop {
+            let digit = match self.next()? {
+                b'_' => break,
+                b @ b'0'..=b'9' => b - b'0',
+                b @ b'a'..=b'f' => b - b'a' + 10,
+                _ => return Err(fmt::Error),
+            };
+            match value.checked_mul(16) {
+                Some(v) => value = v | digit as u128,
+                None => overflow = true,
+            }
+        }
+        match ty {
+            b'h' | b't' | b'm' | b'y' | b'o' | b'j' | b'a' | b's' | b'l' | b'x' | b'n' | b'i' => {
+                if overflow {
+                    return self.write("{big}");
+                }
+                if negative {
+                    self.write("-")?;
+                }
+                self.write_fmt_args(format_args!("{}", value))
+            }
+            b'b' => match value {
+                0 => self.write("false"),
+                1 => self.write("true"),
+                _ => Err(fmt::Error),
+            },
+            b'c' => {
+                let c = u32::try_from(value)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or(fmt::Error)?;
+                self.write_fmt_args(format_args!("{:?}", c))
+            }
+            _ => Err(fmt::Error),
+        }
+    }
+}
+
+fn basic_type(tag: u8) -> Option<&'static str> {
+    Some(match tag {
+        b'a' => "i8",
+        b'b' => "bool",
+        b'c' => "char",
+        b'd' => "f64",
+        b'e' => "str",
+        b'f' => "f32",
+        b'h' => "u8",
+        b'i' => "isize",
+        b'j' => "usize",
+        b'l' => "i32",
+        b'm' => "u32",
+        b'n' => "i128",
+        b'o' => "u128",
+        b's' => "i16",
+        b't' => "u16",
+        b'u' => "()",
+        b'v' => "...",
+        b'x' => "i64",
+        b'y' => "u64",
+        b'z' => "!",
+        b'p' => "_",
+        _ => return None,
+    })
+}
+
+/// `_ZN<len><ident>...E` の中身を返す（形式が壊れていれば None）
+///
+/// `E` の後ろ（`.llvm.*` など）は捨てる。
+fn split_legacy(name: &str) -> Option<&str> {
+    let inner = name
+        .strip_prefix("_ZN")
+        .or_else(|| name.strip_prefix("__ZN"))?;
+    let mut rest = inner;
+    loop {
+        if let Some(tail) = rest.strip_prefix('E') {
+            return Some(&inner[..inner.len() - tail.len() - 1]);
+        }
+        let (_, next) = next_ident(rest)?;
+        rest = next;
+    }
+}
+
+/// 先頭の `<len><ident>` を 1 つ取り出す
+fn next_ident(s: &str) -> Option<(&str, &str)> {
+    let digits = s.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    let len: usize = s[..digits].parse().ok()?;
+    let rest = &s[digits..];
+    if rest.len() < len || !rest.is_char_boundary(len) {
+        return None;
+    }
+    Some((&rest[..len], &rest[len..]))
+}
+
+fn is_hash(ident: &str) -> bool {
+    ident.len() == 17 && ident.starts_with('h') && ident[1..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn write_legacy(f: &mut fmt::Formatter<'_>, mut rest: &str) -> fmt::Result {
+    let mut first = true;
+    while let Some((ident, next)) = next_ident(rest) {
+        rest = next;
+        if rest.is_empty() && is_hash(ident) {
+            break;
+        }
+        if !first {
+            f.write_str("::")?;
+        }
+        first = false;
+        write_ident(f, ident)?;
+    }
+    Ok(())
+}
+
+fn write_ident(f: &mut fmt::Formatter<'_>, ident: &str) -> fmt::Result {
+    // `$` で始まる要素は `_` が前置されている
+    let mut s = if ident.starts_with("_$") {
+        &ident[1..]
+    } else {
+        ident
+    };
+    while !s.is_empty() {
+        if let Some(rest) = s.strip_prefix("..") {
+            f.write_str("::")?;
+            s = rest;
+        } else if let Some(rest) = s.strip_prefix('$') {
+            let Some(end) = rest.find('$') else {
+                return f.write_str(s);
+            };
+            let escape = &rest[..end];
+            match unescape(escape) {
+                Some(c) => f.write_char(c)?,
+                None => write!(f, "${}$", escape)?,
+            }
+            s = &rest[end + 1..];
+        } else {
+            let end = s
+                .char_indices()
+                .skip(1)
+                .find(|&(i, c)| c == '$' || (c == '.' && s[i..].starts_with("..")))
+                .map_or(s.len(), |(i, _)| i);
+            f.write_str(&s[..end])?;
+            s = &s[end..];
+        }
+    }
+    Ok(())
+}
+
+fn unescape(escape: &str) -> Option<char> {
+    Some(match escape {
+        "SP" => '@',
+        "BP" => '*',
+        "RF" => '&',
+        "LT" => '<',
+        "GT" => '>',
+        "LP" => '(',
+        "RP" => ')',
+        "C" => ',',
+        _ => {
+            let hex = escape.strip_prefix('u')?;
+            char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+        }
+    })
+}
@@ -0,0 +1,122 @@
+//! カーネルに埋め込むシンボル表
+//!
+//! リンク後にビルドスクリプト（builders/ksyms.rs）が `.ksyms` セクションへ
+//! 関数シンボルを書き込む。書き込まれていなければ表は空として扱う。
+//!
+//! ```text
+//! +0  "KSYM"
+//! +4  u32 シンボル数
+//! +8  u32 名前領域のオフセット（表の先頭から）
+//! +12 u32 名前領域のバイト数
+//! +16 { u64 アドレス, u32 サイズ, u32 名前のオフセット } × シンボル数（アドレス順）
+//!     名前領域（NUL 終端の文字列）
+//! ```
+
+use super::Symbol;
+
+/// 表の大きさ（ビルドスクリプトはこれを超える分を切り捨てる）
+const KSYMS_SIZE: usize = 1 << 20;
+const MAGIC: &[u8; 4] = b"KSYM";
+const HEADER_SIZE: usize = 16;
+const ENTRY_SIZE: usize = 16;
+/// サイズ不明のシンボルを次のシンボルまでとみなす上限
+const UNSIZED_LIMIT: u64 = 0x10000;
+
+/// 未書き込みの印を入れておく（ゼロだけだと NOBITS に置かれてファイルに領域が残らない）
+#[no_mangle]
+#[used]
+#[link_section = ".ksyms"]
+static mut MOCHIOS_KSYMS: [u8; KSYMS_SIZE] = {
+    let mut table = [0u8; KSYMS_SIZE];
+    table[0] = b'N';
+    table[1] = b'O';
+    table[2] = b'N';
+    table[3] = b'E';
+    table
+};
+
+struct Table {
+    data: &'static [u8],
+    count: usize,
+    strtab: &'static [u8],
+}
+
+fn u32_at(data: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(off..off + 4)?.try_into().ok()?))
+}
+
+fn u64_at(data: &[u8], off: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(off..off + 8)?.try_into().ok()?))
+}
+
+fn table() -> Option<Table> {
+    // SAFETY: 起動後は誰も書き換えない
+    let data: &'static [u8] = unsafe { &*core::ptr::addr_of!(MOCHIOS_KSYMS) };
+    if &data[..4] != MAGIC {
+        return None;
+    }
+    let count = u32_at(data, 4)? as usize;
+    let str_off = u32_at(data, 8)? as usize;
+    let str_len = u32_at(data, 12)? as usize;
+    if HEADER_SIZE + count * ENTRY_SIZE > str_off {
+        return None;
+    }
+    let strtab = data.get(str_off..str_off.checked_add(str_len)?)?;
+    Some(Table {
+        data,
+        count,
+        strtab,
+    })
+}
+
+impl Table {
+    fn entry(&self, i: usize) -> Option<(u64, u64, u32)> {
+        let off = HEADER_SIZE + i * ENTRY_SIZE;
+        Some((
+            u64_at(self.data, off)?,
+            u32_at(self.data, off + 8)? as u64,
+            u32_at(self.data, off + 12)?,
+        ))
+    }
+
+    fn name(&self, off: u32) -> Option<&'static str> {
+        let rest = self.strtab.get(off as usize..)?;
+        let len = rest.iter().position(|&b| b == 0)?;
+        core::str::from_utf8(&rest[..len]).ok()
+    }
+}
+
+/// 埋め込まれているシンボル数（未書き込みなら 0）
+pub fn count() -> usize {
+    table().map_or(0, |t| t.count)
+}
+
+/// カーネル内のアドレスを関数名 + オフセットに引く
+pub fn lookup(addr: u64) -> Option<Symbol<'static>> {
+    let table = table()?;
+    // addr 以下で最大のアドレスを持つエントリ
+    let mut lo = 0;
+    let mut hi = table.count;
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if table.entry(mid)?.0 <= addr {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    let (start, size, name) = table.entry(lo.checked_sub(1)?)?;
+    let offset = addr - start;
+    let inside = if size != 0 {
+        offset < size
+    } else {
+        offset < UNSIZED_LIMIT
+    };
+    if !inside {
+        return None;
+    }
+    Some(Symbol {
+        name: table.name(name)?,
+        offset,
+    })
+}
@@ -0,0 +1,311 @@
+//! スタックトレース
+//!
+//! フレームポインタ（rbp の連鎖）をたどって呼び出し元を列挙し、関数名 + オフセットで表示する。
+//! カーネルはビルド時に埋め込んだシンボル表（`ksyms`）、ユーザープロセスは exec 時に
+//! ELF から読んだ `.symtab`（`UserSymbols`）で引く。
+//!
+//! カーネルは `-C force-frame-pointers=yes`、ユーザーランドはターゲット仕様の
+//! `"frame-pointer": "always"` でビルドしている。フレームポインタを持たない関数
+//! （C ライブラリなど）を挟むと、そこで連鎖が途切れるか 1 段飛ぶ。
+
+mod demangle;
+pub mod ksyms;
+mod usyms;
+
+pub use demangle::Demangle;
+pub use usyms::UserSymbols;
+
+use crate::error;
+use crate::mem::paging;
+use core::fmt;
+use x86_64::structures::paging::PageTableFlags;
+use x86_64::VirtAddr;
+
+/// たどるフレーム数の上限（壊れた連鎖で止まらなくなるのを防ぐ）
+const MAX_FRAMES: usize = 32;
+/// ユーザー空間の上限（これ以上は canonical でもカーネル側）
+const USER_END: u64 = 0x0000_8000_0000_0000;
+
+/// 解決済みのシンボル
+pub struct Symbol<'a> {
+    /// シンボル名（マングルされたまま）
+    pub name: &'a str,
+    /// 関数先頭からのオフセット
+    pub offset: u64,
+}
+
+impl fmt::Display for Symbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{:#x}", Demangle(self.name), self.offset)
+    }
+}
+
+/// `table_phys` のページテーブルでスタック上の 1 ワードを読む
+///
+/// ユーザースタックをたどるときは、ユーザーからアクセスできるページ以外は読まない。
+fn read_stack_word(table_phys: u64, addr: u64, user: bool) -> Option<u64> {
+    let vaddr = VirtAddr::try_new(addr).ok()?;
+    if user {
+        if addr >= USER_END {
+            return None;
+        }
+        let (_, flags) = paging::translate_addr_in_table(table_phys, vaddr)?;
+        if !flags.contains(PageTableFlags::USER_ACCESSIBLE) {
+            return None;
+        }
+    }
+    paging::read_u64_in_table(table_phys, vaddr.as_u64())
+}
+
+/// rbp の連鎖をたどり、各フレームのアドレスを `frame(番号, アドレス)` に渡す
+///
+/// `rip` があれば #0 とし、以降は各フレームの戻りアドレスを並べる。
+/// rbp が 0・非整列・未マップ、または浅い方へ戻った時点で止める。
+fn walk(
+    table_phys: u64,
+    rip: Option<u64>,
+    mut rbp: u64,
+    user: bool,
+    mut frame: impl FnMut(usize, u64),
+) {
+    let mut depth = 0;
+    if let Some(rip) = rip {
+        frame(depth, rip);
+        depth += 1;
+    }
+    while depth < MAX_FRAMES {
+        if rbp == 0 || rbp & 7 != 0 {
+            break;
+        }
+        let Some(next) = read_stack_word(table_phys, rbp, user) else {
+            break;
+        };
+        let Some(ret) = rbp
+            .checked_add(8)
+            .and_then(|addr| read_stack_word(table_phys, addr, user))
+        else {
+            break;
+        };
+        if ret == 0 {
+            break;
+        }
+        frame(depth, ret);
+        depth += 1;
+        if next <= rbp {
+            break;
+        }
+        rbp = next;
+    }
+}
+
+/// `rip` から始めてフレームのアドレスだけを `out` に集め、集めた数を返す（プロファイラ用）
+///
+/// 割り込みの中から呼んでもよい（シンボルは引かない）。スタックを読むのに使う
+/// 物理メモリオフセットのロックを割り込まれた側が持っていれば、`rip` だけを返す。
+pub fn collect(table_phys: u64, rip: u64, rbp: u64, user: bool, out: &mut [u64]) -> usize {
+    if out.is_empty() {
+        return 0;
+    }
+    if paging::PHYS_OFFSET.is_locked() {
+        out[0] = rip;
+        return 1;
+    }
+    let mut len = 0;
+    walk(table_phys, Some(rip), rbp, user, |depth, addr| {
+        if let Some(slot) = out.get_mut(depth) {
+            *slot = addr;
+            len = depth + 1;
+        }
+    });
+    len
+}
+
+/// 呼び出し元の戻りアドレスを `skip` 段飛ばして `out` に集め、集めた数を返す（ヒープ検査用）
+///
+/// 物理メモリオフセットのロックが取られていれば（スタックを読めないので）何も集めない。
+#[inline(never)]
+pub fn collect_callers(skip: usize, out: &mut [u64]) -> usize {
+    if paging::PHYS_OFFSET.is_locked() {
+        return 0;
+    }
+    let mut len = 0;
+    walk(
+        current_table(),
+        None,
+        current_frame_pointer(),
+        false,
+        |depth, addr| {
+            if let Some(slot) = depth.checked_sub(skip).and_then(|i| out.get_mut(i)) {
+                *slot = addr;
+                len = depth - skip + 1;
+            }
+        },
+    );
+    len
+}
+
+/// `collect_callers` で集めた戻りアドレスを関数名付きで表示する（0 は終端）
+pub fn print_callers(addrs: &[u64]) {
+    for (depth, &addr) in addrs.iter().take_while(|&&a| a != 0).enumerate() {
+        error!("{}", resolve(depth, addr, true, ksyms::lookup));
+    }
+}
+
+/// 1 フレーム分の表示（`  #N 0x... 関数名+0x1a`）
+pub struct Frame<'a> {
+    depth: usize,
+    addr: u64,
+    symbol: Option<Symbol<'a>>,
+}
+
+impl fmt::Display for Frame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "  #{:<2} {:#018x} ", self.depth, self.addr)?;
+        match &self.symbol {
+            Some(sym) => write!(f, "{}", sym),
+            None => f.write_str("??"),
+        }
+    }
+}
+
+/// フレームのアドレスを関数名に引く
+///
+/// 戻りアドレスは call 命令の次を指すので、1 つ手前で引いて同じ関数に収める。
+fn resolve<'a>(
+    depth: usize,
+    addr: u64,
+    is_return: bool,
+    lookup: impl Fn(u64) -> Option<Symbol<'a>>,
+) -> Frame<'a> {
+    let probe = if is_return {
+        addr.wrapping_sub(1)
+    } else {
+        addr
+    };
+    let symbol = lookup(probe).map(|mut sym| {
+        sym.offset += addr - probe;
+        sym
+    });
+    Frame {
+        depth,
+        addr,
+        symbol,
+    }
+}
+
+pub(crate) fn current_table() -> u64 {
+    x86_64::registers::control::Cr3::read()
+        .0
+        .start_address()
+        .as_u64()
+}
+
+fn kernel_frames(rip: Option<u64>, rbp: u64, mut emit: impl FnMut(&Frame<'_>)) {
+    let first_is_rip = rip.is_some();
+    walk(current_table(), rip, rbp, false, |depth, addr| {
+        emit(&resolve(
+            depth,
+            addr,
+            depth > 0 || !first_is_rip,
+            ksyms::lookup,
+        ))
+    });
+}
+
+/// 現在のプロセスのユーザースタックをたどる（プロセスが無ければ false）
+fn user_frames(rip: u64, rbp: u64, mut emit: impl FnMut(&Frame<'_>)) -> bool {
+    let Some(pid) = crate::task::current_thread_id()
+        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
+    else {
+        return false;
+    };
+    let Some((table_phys, symbols)) =
+        crate::task::with_process(pid, |p| Some((p.page_table()?, p.symbols()))).flatten()
+    else {
+        return false;
+    };
+    walk(table_phys, Some(rip), rbp, true, |depth, addr| {
+        emit(&resolve(depth, addr, depth > 0, |a| {
+            symbols.as_deref().and_then(|s| s.lookup(a))
+        }))
+    });
+    true
+}
+
+#[inline(always)]
+fn current_frame_pointer() -> u64 {
+    let rbp: u64;
+    // SAFETY: rbp を読むだけ
+    unsafe {
+        core::arch::asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags));
+    }
+    rbp
+}
+
+/// 例外発生時のカーネルのスタックトレース（`rip`・`rbp` は割り込まれた時点の値）
+pub fn print_kernel(rip: u64, rbp: u64) {
+    error!("Kernel backtrace:");
+    kernel_frames(Some(rip), rbp, |frame| error!("{}", frame));
+}
+
+/// `print_kernel` と同じ内容を `out` に書く（クラッシュログ用）
+pub fn write_kernel(out: &mut dyn fmt::Write, rip: u64, rbp: u64) -> fmt::Result {
+    let mut result = Ok(());
+    kernel_frames(Some(rip), rbp, |frame| {
+        result = result.and_then(|_| writeln!(out, "{}", frame))
+    });
+    result
+}
+
+/// 現在の呼び出し元からのカーネルのスタックトレース（パニック時）
+#[inline(never)]
+pub fn print_current() {
+    error!("Kernel backtrace:");
+    kernel_frames(None, current_frame_pointer(), |frame| error!("{}", frame));
+}
+
+/// `print_current` と同じ内容を `out` に書く（クラッシュログ用）
+#[inline(never)]
+pub fn write_current(out: &mut dyn fmt::Write) -> fmt::Result {
+    let mut result = Ok(());
+    kernel_frames(None, current_frame_pointer(), |frame| {
+        result = result.and_then(|_| writeln!(out, "{}", frame))
+    });
+    result
+}
+
+/// 現在のプロセスのユーザー空間のスタックトレース
+///
+/// exec 時に読んだシンボル表があれば関数名で表示する。
+pub fn print_user(rip: u64, rbp: u64) {
+    let mut first = true;
+    user_frames(rip, rbp, |frame| {
+        if core::mem::take(&mut first) {
+            error!("User backtrace:");
+        }
+        error!("{}", frame)
+    });
+}
+
+/// `print_user` と同じ内容を `out` に書く（クラッシュログ用）
+pub fn write_user(out: &mut dyn fmt::Write, rip: u64, rbp: u64) -> fmt::Result {
+    let mut result = Ok(());
+    user_frames(rip, rbp, |frame| {
+        result = result.and_then(|_| writeln!(out, "{}", frame))
+    });
+    result
+}
+
+/// 割り込まれたコードの rbp
+///
+/// x86-interrupt ハンドラの本体から直接呼ぶこと。ハンドラの先頭で積まれた
+/// rbp（= 割り込み直前の値）を自分のフレームから読み出す。
+#[inline(always)]
+pub fn interrupted_frame_pointer() -> u64 {
+    let rbp: u64;
+    // SAFETY: フレームポインタ付きでビルドしているので [rbp] は呼び出し元の rbp
+    unsafe {
+        core::arch::asm!("mov {}, [rbp]", out(reg) rbp, options(readonly, nostack, preserves_flags));
+    }
+    rbp
+}
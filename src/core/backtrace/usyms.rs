@@ -0,0 +1,98 @@
+//! ユーザープロセスのシンボル表
+//!
+//! exec 時に ELF の `.symtab` から関数シンボルだけを集めて Process に持たせる
+//! （task/elf.rs の `load_symbols`）。fork では Arc を共有する。
+
+use super::Symbol;
+use alloc::vec::Vec;
+
+/// 1 プロセスあたりのシンボル数の上限
+const MAX_ENTRIES: usize = 16384;
+/// 1 プロセスあたりの名前領域の上限
+const MAX_STRINGS: usize = 1 << 20;
+/// サイズ不明のシンボルを次のシンボルまでとみなす上限
+const UNSIZED_LIMIT: u64 = 0x10000;
+
+struct Entry {
+    addr: u64,
+    size: u64,
+    name: u32,
+    name_len: u32,
+}
+
+/// 関数シンボルの表（アドレス順）
+pub struct UserSymbols {
+    entries: Vec<Entry>,
+    strings: Vec<u8>,
+}
+
+impl UserSymbols {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            strings: Vec::new(),
+        }
+    }
+
+    /// シンボルを 1 つ追加する（上限を超えたら false）
+    pub fn push(&mut self, addr: u64, size: u64, name: &[u8]) -> bool {
+        if self.entries.len() >= MAX_ENTRIES
+            || self.strings.len() + name.len() > MAX_STRINGS
+            || core::str::from_utf8(name).is_err()
+        {
+            return false;
+        }
+        self.entries.push(Entry {
+            addr,
+            size,
+            name: self.strings.len() as u32,
+            name_len: name.len() as u32,
+        });
+        self.strings.extend_from_slice(name);
+        true
+    }
+
+    /// 追加し終えたらアドレス順に並べる
+    pub fn finish(&mut self) {
+        self.entries.sort_unstable_by_key(|e| e.addr);
+        self.entries.dedup_by_key(|e| e.addr);
+        self.entries.shrink_to_fit();
+        self.strings.shrink_to_fit();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// アドレスを関数名 + オフセットに引く
+    pub fn lookup(&self, addr: u64) -> Option<Symbol<'_>> {
+        let idx = self.entries.partition_point(|e| e.addr <= addr);
+        let entry = self.entries.get(idx.checked_sub(1)?)?;
+        let offset = addr - entry.addr;
+        let limit = if entry.size != 0 {
+            entry.size
+        } else {
+            UNSIZED_LIMIT
+        };
+        if offset >= limit {
+            return None;
+        }
+        let start = entry.name as usize;
+        let bytes = &self.strings[start..start + entry.name_len as usize];
+        Some(Symbol {
+            // push で UTF-8 であることを確かめてある
+            name: core::str::from_utf8(bytes).ok()?,
+            offset,
+        })
+    }
+}
+
+impl Default for UserSymbols {
+    fn default() -> Self {
+        Self::new()
+    }
+}
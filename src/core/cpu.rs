@@ -538,8 +538,27 @@ pub fn reassert_runtime_hardening() {
     }
 }
 
+/// アーキテクチャ定義の性能カウンタのバージョン（CPUID 0Ah）
+///
+/// 汎用カウンタが無いか、unhalted core cycles を数えられなければ 0。
+pub fn perfmon_version() -> u32 {
+    if cpuid(0, 0).eax < 0xA {
+        return 0;
+    }
+    let leaf = cpuid(0xA, 0);
+    let version = leaf.eax & 0xff;
+    let counters = (leaf.eax >> 8) & 0xff;
+    let events = (leaf.eax >> 24) & 0xff;
+    // EBX のビットが立っているイベントは使えない（bit 0 が unhalted core cycles）
+    if counters == 0 || events == 0 || leaf.ebx & 1 != 0 {
+        return 0;
+    }
+    version
+}
+
+/// タイムスタンプカウンタを読む
 #[inline]
-fn rdtsc() -> u64 {
+pub fn rdtsc() -> u64 {
     let lo: u32;
     let hi: u32;
     unsafe {
@@ -548,6 +567,54 @@ fn rdtsc() -> u64 {
     ((hi as u64) << 32) | (lo as u64)
 }
 
+/// システムを再起動する
+///
+/// 8042 のリセット線、PCI のリセットレジスタ (0xCF9)、トリプルフォルトの順に試す。
+/// どれもウォームリセットなので RAM の内容（永続クラッシュログ）は残る。
+pub fn reboot() -> ! {
+    use x86_64::instructions::port::Port;
+
+    x86_64::instructions::interrupts::disable();
+    unsafe {
+        // 8042 の入力バッファが空くのを待ってからリセットパルスを送る
+        let mut kbc = Port::<u8>::new(0x64);
+        for _ in 0..0x10000 {
+            if kbc.read() & 0x02 == 0 {
+                break;
+            }
+            core::hint::spin_loop();
+        }
+        kbc.write(0xFE);
+        spin_cycles(100_000_000);
+
+        // リセット要求 (bit 1) を立ててから CPU リセット (bit 2)
+        let mut rst = Port::<u8>::new(0xCF9);
+        rst.write(0x02);
+        spin_cycles(1_000_000);
+        rst.write(0x06);
+        spin_cycles(100_000_000);
+
+        // 空の IDT で例外を起こせばトリプルフォルトでリセットされる
+        let idt = x86_64::structures::DescriptorTablePointer {
+            limit: 0,
+            base: x86_64::VirtAddr::zero(),
+        };
+        x86_64::instructions::tables::lidt(&idt);
+        asm!("int3", options(nomem, nostack));
+    }
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+/// TSC で `cycles` だけ待つ
+fn spin_cycles(cycles: u64) {
+    let start = rdtsc();
+    while rdtsc().wrapping_sub(start) < cycles {
+        core::hint::spin_loop();
+    }
+}
+
 #[inline]
 fn aslr_mix64(mut x: u64) -> u64 {
     x ^= x >> 30;
@@ -0,0 +1,463 @@
+//! 永続クラッシュログ
+//!
+//! ブートローダーが毎回同じ物理アドレスに予約する領域（`REGION_PHYS`）へ、
+//! パニック・カーネルの致命的な例外・ユーザープロセスの異常終了と、直近の監査ログを書き残す。
+//! ウォームリセットでは RAM の内容が残るので、次の起動で回収して `/proc/crash` から読める。
+//!
+//! ```text
+//! +0x0000  ヘッダ（マジック・起動回数・次の通し番号）
+//! +0x1000  監査ログ 32 件（256 バイト × 32、リング）
+//! +0x3000  クラッシュレコード（4 KiB × 残り全部、リング）
+//! ```
+//!
+//! 各レコードにはチェックサムを付け、電源投入直後のゴミや書きかけのレコードは読み捨てる。
+//! 書き込みはパニックや例外ハンドラの中から行うので、ヒープは使わない。
+
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+use crate::interrupt::spinlock::SpinLock;
+
+/// 予約する物理アドレス（カーネルのロード先 0x4000000 より下）
+pub const REGION_PHYS: u64 = 0x0300_0000;
+/// 予約する大きさ
+pub const REGION_SIZE: usize = 0x10000;
+
+const MAGIC: u64 = u64::from_le_bytes(*b"MOCHCRSH");
+const VERSION: u32 = 1;
+const CRASH_MAGIC: u32 = u32::from_le_bytes(*b"CRSH");
+const AUDIT_MAGIC: u32 = u32::from_le_bytes(*b"AUDT");
+
+const AUDIT_OFFSET: usize = 0x1000;
+const AUDIT_SLOT_SIZE: usize = 256;
+const AUDIT_SLOTS: usize = 32;
+const CRASH_OFFSET: usize = AUDIT_OFFSET + AUDIT_SLOT_SIZE * AUDIT_SLOTS;
+const CRASH_SLOT_SIZE: usize = 0x1000;
+
+/// クラッシュレコードに添える直近の監査ログの件数
+const AUDIT_TAIL: usize = 8;
+
+/// クラッシュの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashKind {
+    /// カーネルパニック
+    Panic = 1,
+    /// カーネルモードの致命的な例外
+    KernelFault = 2,
+    /// ユーザープロセスの異常終了
+    UserFault = 3,
+    /// 割り込みが止まったまま回り続けた（NMI ウォッチドッグ）
+    HardLockup = 4,
+}
+
+impl CrashKind {
+    fn from_u32(v: u32) -> Option<Self> {
+        match v {
+            1 => Some(Self::Panic),
+            2 => Some(Self::KernelFault),
+            3 => Some(Self::UserFault),
+            4 => Some(Self::HardLockup),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Panic => "panic",
+            Self::KernelFault => "kernel fault",
+            Self::UserFault => "user fault",
+            Self::HardLockup => "hard lockup",
+        }
+    }
+}
+
+#[repr(C)]
+struct Header {
+    magic: u64,
+    version: u32,
+    crash_slots: u32,
+    /// 起動回数（このブートの番号）
+    boot: u64,
+    /// 次に書くクラッシュレコードの通し番号
+    next_crash: u64,
+    /// 次に書く監査レコードの通し番号
+    next_audit: u64,
+}
+
+/// レコード（クラッシュ・監査共通）の先頭
+#[repr(C)]
+struct RecordHeader {
+    magic: u32,
+    kind: u32,
+    seq: u64,
+    boot: u64,
+    /// 起動からのティック数（100 回 = 1 秒）
+    ticks: u64,
+    len: u32,
+    checksum: u32,
+}
+
+const RECORD_HEADER_SIZE: usize = core::mem::size_of::<RecordHeader>();
+
+/// 予約領域（ブートローダーが確保できなかった場合は None）
+struct Region {
+    base: u64,
+    crash_slots: usize,
+    /// `init` で検証し終えたか（それまでは書き込まない）
+    ready: bool,
+}
+
+static REGION: SpinLock<Option<Region>> = SpinLock::named("CRASHLOG", None);
+
+impl Region {
+    fn header(&mut self) -> &mut Header {
+        // SAFETY: base は予約済みで、ヘッダは先頭に収まる
+        unsafe { &mut *(self.base as *mut Header) }
+    }
+
+    fn slot(&mut self, offset: usize, size: usize) -> &mut [u8] {
+        // SAFETY: offset + size は予約領域の中
+        unsafe { core::slice::from_raw_parts_mut((self.base + offset as u64) as *mut u8, size) }
+    }
+
+    fn crash_slot(&mut self, index: usize) -> &mut [u8] {
+        self.slot(CRASH_OFFSET + index * CRASH_SLOT_SIZE, CRASH_SLOT_SIZE)
+    }
+
+    fn audit_slot(&mut self, index: usize) -> &mut [u8] {
+        self.slot(AUDIT_OFFSET + index * AUDIT_SLOT_SIZE, AUDIT_SLOT_SIZE)
+    }
+}
+
+/// FNV-1a（レコードの破損検出用）
+fn checksum(header: &RecordHeader, body: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut feed = |bytes: &[u8]| {
+        for &b in bytes {
+            hash ^= b as u32;
+            hash = hash.wrapping_mul(0x0100_0193);
+        }
+    };
+    feed(&header.kind.to_le_bytes());
+    feed(&header.seq.to_le_bytes());
+    feed(&header.boot.to_le_bytes());
+    feed(&header.ticks.to_le_bytes());
+    feed(&header.len.to_le_bytes());
+    feed(body);
+    hash
+}
+
+/// スロットの中身が正しいレコードなら（ヘッダ, 本文）を返す
+fn parse(slot: &[u8], magic: u32) -> Option<(RecordHeader, &[u8])> {
+    // SAFETY: スロットはレコードヘッダより大きい
+    let header = unsafe { core::ptr::read_unaligned(slot.as_ptr() as *const RecordHeader) };
+    if header.magic != magic {
+        return None;
+    }
+    let body = slot.get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + header.len as usize)?;
+    if checksum(&header, body) != header.checksum {
+        return None;
+    }
+    Some((header, body))
+}
+
+/// スロットへレコードを書く（本文は `body` が書き込んだ分）
+fn write_record(
+    slot: &mut [u8],
+    magic: u32,
+    kind: u32,
+    seq: u64,
+    boot: u64,
+    body: impl FnOnce(&mut SlotWriter) -> fmt::Result,
+) {
+    // 書いている途中で止まっても読み捨てられるよう、先にマジックを消す
+    slot[..4].fill(0);
+    let mut writer = SlotWriter {
+        buf: &mut slot[RECORD_HEADER_SIZE..],
+        len: 0,
+    };
+    let _ = body(&mut writer);
+    let len = writer.len;
+    let mut header = RecordHeader {
+        magic: 0,
+        kind,
+        seq,
+        boot,
+        ticks: crate::interrupt::timer::get_ticks(),
+        len: len as u32,
+        checksum: 0,
+    };
+    header.checksum = checksum(&header, &slot[RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + len]);
+    header.magic = magic;
+    // SAFETY: スロットはレコードヘッダより大きい
+    unsafe { core::ptr::write_unaligned(slot.as_mut_ptr() as *mut RecordHeader, header) };
+}
+
+/// 固定長スロットへの書き込み（溢れた分は捨てる）
+pub struct SlotWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for SlotWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = self.buf.len() - self.len;
+        let mut n = s.len().min(room);
+        // UTF-8 の途中で切らない
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// ブートローダーが予約した領域を設定する（kernel_entry から呼ばれる）
+///
+/// 中身の検証と起動回数の更新は `init` で行う。
+///
+/// # Safety
+///
+/// `addr` から `size` バイトは、ブートローダーがこの用途のために予約した、カーネルから
+/// 読み書きできるメモリでなければならない。ほかの用途に割り当てられることはなく、
+/// カーネルが動いている間ずっと有効であること。
+pub unsafe fn set_region(addr: u64, size: usize) {
+    if addr == 0 || size < CRASH_OFFSET + CRASH_SLOT_SIZE {
+        return;
+    }
+    *REGION.lock() = Some(Region {
+        base: addr,
+        crash_slots: (size - CRASH_OFFSET) / CRASH_SLOT_SIZE,
+        ready: false,
+    });
+}
+
+/// 領域を検証し、前回までの起動で残ったレコードを報告する
+///
+/// ヘッダが壊れていれば（コールドブートなど）領域全体を初期化する。
+pub fn init() {
+    let mut guard = REGION.lock();
+    let Some(region) = guard.as_mut() else {
+        crate::warn!("crashlog: no persistent region reserved by the bootloader");
+        return;
+    };
+    let slots = region.crash_slots as u32;
+    let header = region.header();
+    let boot = if header.magic == MAGIC && header.version == VERSION && header.crash_slots == slots
+    {
+        header.boot.wrapping_add(1)
+    } else {
+        // SAFETY: 予約領域全体を消す
+        unsafe {
+            core::ptr::write_bytes(
+                region.base as *mut u8,
+                0,
+                CRASH_OFFSET + region.crash_slots * CRASH_SLOT_SIZE,
+            );
+        }
+        let header = region.header();
+        header.magic = MAGIC;
+        header.version = VERSION;
+        header.crash_slots = slots;
+        header.next_crash = 1;
+        header.next_audit = 1;
+        1
+    };
+    region.header().boot = boot;
+    region.ready = true;
+
+    let mut recovered = 0;
+    let mut latest: Option<(u64, u64, CrashKind)> = None;
+    for i in 0..region.crash_slots {
+        if let Some((rec, _)) = parse(region.crash_slot(i), CRASH_MAGIC) {
+            recovered += 1;
+            let kind = CrashKind::from_u32(rec.kind).unwrap_or(CrashKind::Panic);
+            if latest.is_none_or(|(seq, _, _)| rec.seq > seq) {
+                latest = Some((rec.seq, rec.boot, kind));
+            }
+        }
+    }
+    match latest {
+        Some((seq, prev_boot, kind)) => crate::warn!(
+            "crashlog: boot #{}, {} record(s) kept; latest #{} was a {} in boot #{} (see /proc/crash)",
+            boot,
+            recovered,
+            seq,
+            kind.name(),
+            prev_boot
+        ),
+        None => crate::info!("crashlog: boot #{}, no crash records", boot),
+    }
+    drop(guard);
+
+    // 再起動理由を監査ログに残す（直前の起動がクラッシュで終わったかどうか）
+    match latest {
+        Some((seq, prev_boot, kind)) if prev_boot.wrapping_add(1) == boot => crate::audit::log(
+            crate::audit::AuditEventKind::Restart,
+            &alloc::format!(
+                "boot #{} after {} in boot #{} (crash record #{})",
+                boot,
+                kind.name(),
+                prev_boot,
+                seq
+            ),
+        ),
+        _ if boot > 1 => crate::audit::log(
+            crate::audit::AuditEventKind::Restart,
+            &alloc::format!(
+                "boot #{} with no crash recorded in boot #{}",
+                boot,
+                boot - 1
+            ),
+        ),
+        _ => {}
+    }
+}
+
+/// クラッシュレコードを 1 件書く
+///
+/// `body` で本文（メッセージやスタックトレース）を書き、最後に直近の監査ログを添える。
+/// 多重フォルトなどで領域を使用中なら何もしない。
+pub fn record(kind: CrashKind, body: impl FnOnce(&mut SlotWriter) -> fmt::Result) {
+    let Some(mut guard) = REGION.try_lock() else {
+        return;
+    };
+    let Some(region) = guard.as_mut().filter(|r| r.ready) else {
+        return;
+    };
+    let header = region.header();
+    let seq = header.next_crash;
+    let boot = header.boot;
+    header.next_crash = seq.wrapping_add(1);
+    let audit_next = header.next_audit;
+
+    // 添える監査ログは予約領域側から読む（ロック済みの AUDIT_LOG に触れない）
+    let mut tail = [[0u8; AUDIT_SLOT_SIZE]; AUDIT_TAIL];
+    let mut tail_count = 0;
+    for audit_seq in audit_next.saturating_sub(AUDIT_TAIL as u64).max(1)..audit_next {
+        let slot = region.audit_slot(audit_seq as usize % AUDIT_SLOTS);
+        if matches!(parse(slot, AUDIT_MAGIC), Some((rec, _)) if rec.seq == audit_seq) {
+            tail[tail_count].copy_from_slice(slot);
+            tail_count += 1;
+        }
+    }
+
+    let index = (seq as usize) % region.crash_slots;
+    write_record(
+        region.crash_slot(index),
+        CRASH_MAGIC,
+        kind as u32,
+        seq,
+        boot,
+        |w| {
+            body(w)?;
+            if tail_count > 0 {
+                writeln!(w, "-- recent audit --")?;
+            }
+            for slot in &tail[..tail_count] {
+                if let Some((rec, text)) = parse(slot, AUDIT_MAGIC) {
+                    writeln!(
+                        w,
+                        "#{} {}",
+                        rec.seq,
+                        core::str::from_utf8(text).unwrap_or("?")
+                    )?;
+                }
+            }
+            Ok(())
+        },
+    );
+}
+
+/// 監査ログを予約領域にも残す（audit::log から呼ばれる）
+pub fn audit(kind: crate::audit::AuditEventKind, message: &str) {
+    let Some(mut guard) = REGION.try_lock() else {
+        return;
+    };
+    let Some(region) = guard.as_mut().filter(|r| r.ready) else {
+        return;
+    };
+    let header = region.header();
+    let seq = header.next_audit;
+    let boot = header.boot;
+    header.next_audit = seq.wrapping_add(1);
+    write_record(
+        region.audit_slot(seq as usize % AUDIT_SLOTS),
+        AUDIT_MAGIC,
+        0,
+        seq,
+        boot,
+        |w| write!(w, "{:?} {}", kind, message),
+    );
+}
+
+/// 全レコードを消す（起動回数と通し番号は残す）
+pub fn clear() {
+    let mut guard = REGION.lock();
+    let Some(region) = guard.as_mut().filter(|r| r.ready) else {
+        return;
+    };
+    for i in 0..region.crash_slots {
+        region.crash_slot(i)[..4].fill(0);
+    }
+    for i in 0..AUDIT_SLOTS {
+        region.audit_slot(i)[..4].fill(0);
+    }
+}
+
+fn write_ticks(out: &mut String, ticks: u64) {
+    let _ = write!(out, "{}.{:02}s", ticks / 100, ticks % 100);
+}
+
+/// `/proc/crash` の内容
+///
+/// クラッシュレコードを古い順に並べ、最後に予約領域に残っている監査ログを付ける。
+pub fn render() -> String {
+    let mut out = String::new();
+    let mut guard = REGION.lock();
+    let Some(region) = guard.as_mut().filter(|r| r.ready) else {
+        out.push_str("# no persistent crash log region\n");
+        return out;
+    };
+    let boot = region.header().boot;
+    let _ = writeln!(out, "# mochiOS crash log (current boot #{})", boot);
+
+    let mut order: alloc::vec::Vec<(u64, usize)> = (0..region.crash_slots)
+        .filter_map(|i| parse(region.crash_slot(i), CRASH_MAGIC).map(|(rec, _)| (rec.seq, i)))
+        .collect();
+    order.sort_unstable();
+    for (_, i) in order {
+        let Some((rec, text)) = parse(region.crash_slot(i), CRASH_MAGIC) else {
+            continue;
+        };
+        let kind = CrashKind::from_u32(rec.kind).map_or("unknown", CrashKind::name);
+        let _ = write!(out, "\n== #{} {} (boot #{}", rec.seq, kind, rec.boot);
+        if rec.boot != boot {
+            out.push_str(", previous boot");
+        }
+        out.push_str(", uptime ");
+        write_ticks(&mut out, rec.ticks);
+        out.push_str(") ==\n");
+        out.push_str(&String::from_utf8_lossy(text));
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+
+    let mut audits: alloc::vec::Vec<(u64, usize)> = (0..AUDIT_SLOTS)
+        .filter_map(|i| parse(region.audit_slot(i), AUDIT_MAGIC).map(|(rec, _)| (rec.seq, i)))
+        .collect();
+    audits.sort_unstable();
+    if !audits.is_empty() {
+        out.push_str("\n== audit ==\n");
+    }
+    for (_, i) in audits {
+        if let Some((rec, text)) = parse(region.audit_slot(i), AUDIT_MAGIC) {
+            let _ = write!(out, "boot #{} ", rec.boot);
+            write_ticks(&mut out, rec.ticks);
+            let _ = writeln!(out, " #{} {}", rec.seq, String::from_utf8_lossy(text));
+        }
+    }
+    out
+}
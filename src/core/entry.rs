@@ -4,16 +4,16 @@
 //! カーネルスタンドアローンバイナリのエントリポイント
 //!
 //! ブートローダーは sysv64 呼び出し規約で kernel_entry(boot_info_ptr) を呼ぶ。
-//! ここで自前の LockedHeap アロケータを設定してから mochios のカーネル本体へ移譲する。
+//! ここで自前の KernelHeap アロケータを設定してから mochios のカーネル本体へ移譲する。
 
 extern crate alloc;
 
-use linked_list_allocator::LockedHeap;
+use mochios::mem::allocator::KernelHeap;
 
 /// カーネルのグローバルアロケータ
-/// mem::init 内の init_heap がこの LockedHeap を初期化する
+/// mem::init 内の init_heap がこの KernelHeap を初期化する
 #[global_allocator]
-static KERNEL_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static KERNEL_ALLOCATOR: KernelHeap = KernelHeap::empty();
 
 /// ELF エントリポイント
 ///
@@ -24,7 +24,7 @@ static KERNEL_ALLOCATOR: LockedHeap = LockedHeap::empty();
 pub unsafe extern "sysv64" fn kernel_entry(boot_info_ptr: *mut mochios::BootInfo) -> ! {
     // kernel_heap_addr = &KERNEL_ALLOCATOR（init_heap がここを初期化する）
     (*boot_info_ptr).kernel_heap_addr =
-        &KERNEL_ALLOCATOR as *const LockedHeap as u64;
+        &KERNEL_ALLOCATOR as *const KernelHeap as u64;
 
     // ブートローダーがロードした initfs イメージを fs モジュールに設定
     mochios::init::fs::set_image(
@@ -36,6 +36,16 @@ pub unsafe extern "sysv64" fn kernel_entry(boot_info_ptr: *mut mochios::BootInfo
         (*boot_info_ptr).rootfs_size,
     );
 
+    mochios::init::cmdline::set(
+        (*boot_info_ptr).cmdline_addr,
+        (*boot_info_ptr).cmdline_size,
+    );
+
+    mochios::crashlog::set_region(
+        (*boot_info_ptr).crash_log_addr,
+        (*boot_info_ptr).crash_log_size,
+    );
+
     let boot_info: &'static mochios::BootInfo = &*(boot_info_ptr as *const _);
     mochios::kernel_entry(boot_info)
 }
@@ -0,0 +1,45 @@
+//! 起動オプション
+//!
+//! ブートローダーが ESP の `\system\cmdline.txt` を読み込んで BootInfo で渡す。
+//! 空白（改行を含む）区切りで `name` または `name=value` を並べる。
+//!
+//! ```text
+//! kgdb=wait
+//! loglevel=debug
+//! ```
+
+/// 起動オプション文字列（ブートローダーが確保したページをそのまま参照する）
+static mut CMDLINE: &[u8] = &[];
+
+/// 起動オプションを BootInfo から設定する（kernel_entry から呼ばれる）
+///
+/// # Safety
+///
+/// `addr` から `size` バイトは読み出せるメモリで、カーネルが動いている間ずっと
+/// 書き換えられずに残っていなければならない。ほかの CPU や割り込みが起動オプションを
+/// 読み始める前（単一スレッドの起動初期）に 1 度だけ呼ぶこと。
+pub unsafe fn set(addr: u64, size: usize) {
+    if addr != 0 && size != 0 {
+        CMDLINE = core::slice::from_raw_parts(addr as *const u8, size);
+    }
+}
+
+/// 起動オプション全体（UTF-8 でなければ空）
+pub fn as_str() -> &'static str {
+    let bytes = unsafe { CMDLINE };
+    core::str::from_utf8(bytes).unwrap_or("")
+}
+
+/// `name` の値を返す（`name` だけなら空文字列、無ければ `None`）
+///
+/// 同じ名前が複数あれば最後のものを使う。
+pub fn get(name: &str) -> Option<&'static str> {
+    as_str()
+        .split_ascii_whitespace()
+        .filter_map(|opt| match opt.split_once('=') {
+            Some((key, value)) if key == name => Some(value),
+            None if opt == name => Some(""),
+            _ => None,
+        })
+        .next_back()
+}
@@ -2,10 +2,13 @@
 
 use crate::{debug, interrupt, mem, task, util, BootInfo, MemoryRegion, Result};
 
+pub mod cmdline;
 pub mod fs;
 
 pub fn kinit(boot_info: &'static BootInfo) -> Result<&'static [MemoryRegion]> {
     util::console::init();
+    crate::kgdb::init();
+    crate::crashlog::init();
     util::vga::init(
         boot_info.framebuffer_addr,
         boot_info.screen_width,
@@ -23,6 +26,10 @@ pub fn kinit(boot_info: &'static BootInfo) -> Result<&'static [MemoryRegion]> {
         )
     };
 
+    crate::info!(
+        "Backtrace: {} kernel symbols embedded",
+        crate::backtrace::ksyms::count()
+    );
     crate::info!("Memory map has {} regions", memory_map.len());
     for (i, region) in memory_map.iter().enumerate() {
         debug!(
@@ -77,5 +84,14 @@ pub fn kinit(boot_info: &'static BootInfo) -> Result<&'static [MemoryRegion]> {
     // SYSCALL/SYSRET 命令サポートを初期化
     crate::syscall::syscall_entry::init_syscall();
 
+    // trace= / profile 起動オプション（per-CPU 状態の初期化後にリングを確保する）
+    crate::trace::init();
+
+    // 性能カウンタの NMI でハードロックアップを見張る（タイマーが動き始めてから）
+    crate::watchdog::nmi::init();
+
+    // kgdb=wait ならここでデバッガの接続を待つ
+    crate::kgdb::boot_break();
+
     Ok(memory_map)
 }
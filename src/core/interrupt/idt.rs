@@ -30,9 +30,23 @@ pub fn init() {
 
         // CPU例外ハンドラ
         idt.divide_error.set_handler_fn(divide_error_handler);
-        idt.debug.set_handler_fn(debug_handler);
-        idt.non_maskable_interrupt.set_handler_fn(nmi_handler);
-        idt.breakpoint.set_handler_fn(breakpoint_handler);
+        // #DB / #BP は ptrace のためにレジスタ一式を保存する naked エントリを使う
+        unsafe {
+            idt.debug
+                .set_handler_addr(x86_64::VirtAddr::new(debug_entry as *const () as u64));
+        }
+        // NMI は SYSCALL 直後（まだユーザースタック上）にも来るので専用スタックで受ける
+        unsafe {
+            idt.non_maskable_interrupt
+                .set_handler_fn(nmi_handler)
+                .set_stack_index(gdt::NMI_IST_INDEX);
+        }
+        unsafe {
+            // ユーザーの int3 を #GP にしないよう DPL=3 にする
+            idt.breakpoint
+                .set_handler_addr(x86_64::VirtAddr::new(breakpoint_entry as *const () as u64))
+                .set_privilege_level(PrivilegeLevel::Ring3);
+        }
         idt.overflow.set_handler_fn(overflow_handler);
         idt.bound_range_exceeded
             .set_handler_fn(bound_range_exceeded_handler);
@@ -54,7 +68,11 @@ pub fn init() {
             .set_handler_fn(stack_segment_fault_handler);
         idt.general_protection_fault
             .set_handler_fn(general_protection_fault_handler);
-        idt.page_fault.set_handler_fn(page_fault_handler);
+        // ページフォルトは SIGSEGV 送達のため汎用レジスタも保存する naked エントリを使う
+        unsafe {
+            idt.page_fault
+                .set_handler_addr(x86_64::VirtAddr::new(page_fault_entry as *const () as u64));
+        }
         idt.x87_floating_point
             .set_handler_fn(x87_floating_point_handler);
         idt.alignment_check.set_handler_fn(alignment_check_handler);
@@ -114,7 +132,7 @@ pub fn init() {
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn divide_error_handler(stack_frame: InterruptStackFrame) {
+extern "x86-interrupt" fn divide_error_handler(mut stack_frame: InterruptStackFrame) {
     let _entered_from_user = enter_from_user(&stack_frame);
     let is_user_mode = stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3;
     error!(
@@ -127,49 +145,260 @@ extern "x86-interrupt" fn divide_error_handler(stack_frame: InterruptStackFrame)
     );
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGFPE,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGFPE,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
+/// ユーザーモード例外でプロセスを終了させる前にスタックトレースとコアダンプを残す
+///
+/// x86-interrupt ハンドラからは汎用レジスタが取れないので、
+/// ダンプに入るのは割り込みフレーム（RIP/CS/RFLAGS/RSP/SS）だけになる。
+/// `rbp` はハンドラが `interrupted_frame_pointer` で読んだ割り込み直前の値。
+fn dump_core_for_user_exception(sig: usize, stack_frame: &InterruptStackFrame, rbp: u64) {
+    let rip = stack_frame.instruction_pointer.as_u64();
+    report_user_crash(sig, rip, rbp);
+    let regs = crate::syscall::coredump::CoreRegs::from_iret(
+        rip,
+        stack_frame.code_segment.0 as u64,
+        stack_frame.cpu_flags.bits(),
+        stack_frame.stack_pointer.as_u64(),
+        stack_frame.stack_segment.0 as u64,
+    );
+    let mut info = crate::task::SigInfo::new(sig, crate::task::SI_KERNEL);
+    info.addr = rip;
+    crate::syscall::coredump::dump_current(&info, regs);
+}
+
+/// デバッグ例外のエントリ (naked function)
+///
+/// エラーコードは無いので、保存領域の並びは int 0x80 エントリと同じになる。
+#[unsafe(naked)]
+unsafe extern "C" fn debug_entry() {
+    core::arch::naked_asm!(
+        "push rax",
+        "push rcx",
+        "push rdx",
+        "push rbx",
+        "push rbp",
+        "push rsi",
+        "push rdi",
+        "push r8",
+        "push r9",
+        "push r10",
+        "push r11",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        // 割り込みフレーム 5 qword + 15 qword で 16-byte 境界に揃っている
+        "mov rdi, rsp",
+        "call {handler}",
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop r11",
+        "pop r10",
+        "pop r9",
+        "pop r8",
+        "pop rdi",
+        "pop rsi",
+        "pop rbp",
+        "pop rbx",
+        "pop rdx",
+        "pop rcx",
+        "pop rax",
+        "iretq",
+        handler = sym debug_handler,
+    );
+}
+
 /// デバッグ例外ハンドラ
 ///
-/// デバッグ例外は、ブレークポイントやシングルステップなどのデバッグイベントで発生する
+/// デバッグ例外は、ブレークポイントやシングルステップなどのデバッグイベントで発生する。
+/// kgdb のブレークイン・シングルステップはスタブが先に処理する。
+/// ユーザーモードでは ptrace のシングルステップ・割り込み要求を処理し、
+/// それ以外は SIGTRAP を送達する。
 ///
 /// ## Arguments
-/// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn debug_handler(stack_frame: InterruptStackFrame) {
-    let entered_from_user = enter_from_user(&stack_frame);
-    debug!("EXCEPTION: DEBUG");
-    debug!("{:#?}", stack_frame);
+/// - `regs`: `debug_entry` が積んだレジスタ保存領域
+extern "sysv64" fn debug_handler(regs: *mut u64) {
+    // SAFETY: debug_entry が積んだ領域
+    let frame = unsafe { crate::syscall::signal::TrapFrame::from_interrupt(regs) };
+    let from_user = frame_is_user(&frame);
+    let entered_from_user = crate::syscall::syscall_entry::kpti_enter_for_trap(from_user);
+    // SAFETY: regs は debug_entry が積んだ保存領域
+    if unsafe { crate::kgdb::handle_debug(regs) } {
+        leave_to_user(entered_from_user);
+        return;
+    }
+    if !from_user {
+        // SYSCALL 直後などで TF を持ち込んだ場合: 止まらずに続ける
+        debug!("EXCEPTION: DEBUG (kernel) rip={:#x}", frame.rip());
+        frame.set_rflags(frame.rflags() & !(1 << 8));
+        leave_to_user(entered_from_user);
+        return;
+    }
+    if let Some(info) = crate::syscall::ptrace::debug_trap(frame) {
+        deliver_user_trap(frame, info, 1);
+    }
     leave_to_user(entered_from_user);
 }
 
+/// 割り込みフレームの CS がユーザーモードか
+fn frame_is_user(frame: &crate::syscall::signal::TrapFrame) -> bool {
+    frame.cs() & 3 == 3
+}
+
+/// ユーザーモードの #DB / #BP で SIGTRAP（またはトレーサーが差し替えたシグナル）を送達する
+///
+/// ハンドラがあればそこへ送り、なければコアダンプを残してプロセスを終了させる。
+fn deliver_user_trap(
+    frame: crate::syscall::signal::TrapFrame,
+    info: crate::task::SigInfo,
+    trapno: u64,
+) {
+    let fault = crate::syscall::signal::FaultContext {
+        trapno,
+        error_code: 0,
+        cr2: 0,
+    };
+    if crate::syscall::signal::force_fault_signal(frame, info, fault) {
+        return;
+    }
+    if matches!(
+        crate::task::default_action(info.signo),
+        crate::task::DefaultAction::Ignore | crate::task::DefaultAction::Continue
+    ) {
+        return;
+    }
+    report_user_crash(info.signo, frame.rip(), frame.rbp());
+    crate::syscall::coredump::dump_current(&info, frame.core_regs());
+    error!(
+        "Terminating user process on signal {} (rip={:#x})",
+        info.signo,
+        frame.rip()
+    );
+    crate::task::scheduler::exit_current_process(-1);
+}
+
 /// NMI (Non-Maskable Interrupt) ハンドラ
 ///
 /// NMIはマスクできない割り込みで、通常はハードウェアの障害や緊急事態を知らせるために使用される
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn nmi_handler(stack_frame: InterruptStackFrame) {
-    let _entered_from_user = enter_from_user(&stack_frame);
+extern "x86-interrupt" fn nmi_handler(mut stack_frame: InterruptStackFrame) {
+    // ハードロックアップ検出の NMI なら、何も触らずに戻る（ロックも CR3 も割り込まれた側のまま）
+    let interrupted_rbp = crate::backtrace::interrupted_frame_pointer();
+    if crate::watchdog::nmi::handle(&stack_frame, interrupted_rbp) {
+        return;
+    }
+    let entered_from_user = enter_from_user(&stack_frame);
     error!("EXCEPTION: NON-MASKABLE INTERRUPT");
     warn!("{:#?}", stack_frame);
-    halt_cpu();
+    // kgdb が有効なら NMI をブレークインとして扱い、再開できるようにする
+    if crate::kgdb::trap_interrupt(&mut stack_frame, crate::task::signal::SIGINT, true) {
+        leave_to_user(entered_from_user);
+        return;
+    }
+    // 理由の分からない NMI は記録だけして動き続ける
+    crate::audit::log(
+        crate::audit::AuditEventKind::Fault,
+        &alloc::format!(
+            "unknown NMI at rip={:#x}",
+            stack_frame.instruction_pointer.as_u64()
+        ),
+    );
+    leave_to_user(entered_from_user);
+}
+
+/// ブレークポイント例外のエントリ (naked function)
+///
+/// 並びは `debug_entry` と同じ。
+#[unsafe(naked)]
+unsafe extern "C" fn breakpoint_entry() {
+    core::arch::naked_asm!(
+        "push rax",
+        "push rcx",
+        "push rdx",
+        "push rbx",
+        "push rbp",
+        "push rsi",
+        "push rdi",
+        "push r8",
+        "push r9",
+        "push r10",
+        "push r11",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        "mov rdi, rsp",
+        "call {handler}",
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop r11",
+        "pop r10",
+        "pop r9",
+        "pop r8",
+        "pop rdi",
+        "pop rsi",
+        "pop rbp",
+        "pop rbx",
+        "pop rdx",
+        "pop rcx",
+        "pop rax",
+        "iretq",
+        handler = sym breakpoint_handler,
+    );
 }
 
 /// ブレークポイント例外ハンドラ
 ///
-/// ブレークポイント例外は、INT3命令によって発生する。デバッグ目的で使用する
+/// ブレークポイント例外は、INT3命令によって発生する。kgdb のブレークポイントとカーネル内の
+/// INT3 は（有効なら）スタブが処理する。ユーザーモードではトレース中なら
+/// ptrace 停止し、そうでなければ SIGTRAP（TRAP_BRKPT）を送達する。
 ///
 /// ## Arguments
-/// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
-    let entered_from_user = enter_from_user(&stack_frame);
-    warn!("EXCEPTION: BREAKPOINT");
-    debug!("{:#?}", stack_frame);
+/// - `regs`: `breakpoint_entry` が積んだレジスタ保存領域
+extern "sysv64" fn breakpoint_handler(regs: *mut u64) {
+    // SAFETY: breakpoint_entry が積んだ領域
+    let frame = unsafe { crate::syscall::signal::TrapFrame::from_interrupt(regs) };
+    let from_user = frame_is_user(&frame);
+    let entered_from_user = crate::syscall::syscall_entry::kpti_enter_for_trap(from_user);
+    // SAFETY: regs は breakpoint_entry が積んだ保存領域
+    if unsafe { crate::kgdb::handle_breakpoint(regs) } {
+        leave_to_user(entered_from_user);
+        return;
+    }
+    if !from_user {
+        warn!("EXCEPTION: BREAKPOINT (kernel) rip={:#x}", frame.rip());
+        leave_to_user(entered_from_user);
+        return;
+    }
+    let mut info = crate::task::SigInfo::new(
+        crate::task::signal::SIGTRAP,
+        crate::task::signal::TRAP_BRKPT,
+    );
+    info.addr = frame.rip();
+    if let Some(info) = crate::syscall::ptrace::fault_stop(frame, info) {
+        deliver_user_trap(frame, info, 3);
+    }
     leave_to_user(entered_from_user);
 }
 
@@ -179,7 +408,7 @@ extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn overflow_handler(stack_frame: InterruptStackFrame) {
+extern "x86-interrupt" fn overflow_handler(mut stack_frame: InterruptStackFrame) {
     let _entered_from_user = enter_from_user(&stack_frame);
     let is_user_mode = stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3;
     error!(
@@ -192,10 +421,19 @@ extern "x86-interrupt" fn overflow_handler(stack_frame: InterruptStackFrame) {
     );
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGSEGV,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGSEGV,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
@@ -205,7 +443,7 @@ extern "x86-interrupt" fn overflow_handler(stack_frame: InterruptStackFrame) {
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn bound_range_exceeded_handler(stack_frame: InterruptStackFrame) {
+extern "x86-interrupt" fn bound_range_exceeded_handler(mut stack_frame: InterruptStackFrame) {
     let _entered_from_user = enter_from_user(&stack_frame);
     let is_user_mode = stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3;
     error!(
@@ -218,10 +456,19 @@ extern "x86-interrupt" fn bound_range_exceeded_handler(stack_frame: InterruptSta
     );
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGSEGV,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGSEGV,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
@@ -485,11 +732,11 @@ fn dump_invalid_opcode_diagnostics(stack_frame: &InterruptStackFrame) {
 
 /// 無効命令例外ハンドラ
 ///
-/// 無効命令例外は、CPUが認識できない命令が実行されたときに発生する。ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合はシステム全体を停止する
+/// 無効命令例外は、CPUが認識できない命令が実行されたときに発生する。ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合は `fatal_kernel_exception` で復旧するか再起動する
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: InterruptStackFrame) {
+extern "x86-interrupt" fn invalid_opcode_handler(mut stack_frame: InterruptStackFrame) {
     let _entered_from_user = enter_from_user(&stack_frame);
     // ユーザーモードかチェック（code_segmentのRPLビットを確認）
     let is_user_mode = stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3;
@@ -509,10 +756,19 @@ extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: InterruptStackFram
     }
 
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGILL,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGILL,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
@@ -522,7 +778,7 @@ extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: InterruptStackFram
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn device_not_available_handler(stack_frame: InterruptStackFrame) {
+extern "x86-interrupt" fn device_not_available_handler(mut stack_frame: InterruptStackFrame) {
     let _entered_from_user = enter_from_user(&stack_frame);
     let is_user_mode = stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3;
     error!(
@@ -535,10 +791,19 @@ extern "x86-interrupt" fn device_not_available_handler(stack_frame: InterruptSta
     );
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGSEGV,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGSEGV,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
@@ -550,13 +815,19 @@ extern "x86-interrupt" fn device_not_available_handler(stack_frame: InterruptSta
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
 /// - `error_code`: ダブルフォルトのエラーコード（通常は0だが、特定の条件下で値が設定されることがある）
 extern "x86-interrupt" fn double_fault_handler(
-    stack_frame: InterruptStackFrame,
+    mut stack_frame: InterruptStackFrame,
     error_code: u64,
 ) -> ! {
     error!("EXCEPTION: DOUBLE FAULT");
     error!("Error code: {:#x}", error_code);
     error!("{:#?}", stack_frame);
-    halt_forever();
+    report_kernel_crash(
+        format_args!("double fault (error code {:#x})", error_code),
+        stack_frame.instruction_pointer.as_u64(),
+        crate::backtrace::interrupted_frame_pointer(),
+    );
+    crate::kgdb::trap_interrupt(&mut stack_frame, crate::task::signal::SIGSEGV, false);
+    crate::recovery::reboot(format_args!("double fault (error code {:#x})", error_code));
 }
 
 /// TSS無効例外ハンドラ
@@ -566,7 +837,10 @@ extern "x86-interrupt" fn double_fault_handler(
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
 /// - `error_code`: TSS無効例外のエラーコード（通常は0だが、特定の条件下で値が設定されることがある）
-extern "x86-interrupt" fn invalid_tss_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+extern "x86-interrupt" fn invalid_tss_handler(
+    mut stack_frame: InterruptStackFrame,
+    error_code: u64,
+) {
     let _entered_from_user = enter_from_user(&stack_frame);
     let is_user_mode = stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3;
     error!(
@@ -580,10 +854,19 @@ extern "x86-interrupt" fn invalid_tss_handler(stack_frame: InterruptStackFrame,
     error!("Error code: {:#x}", error_code);
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGSEGV,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGSEGV,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
@@ -595,7 +878,7 @@ extern "x86-interrupt" fn invalid_tss_handler(stack_frame: InterruptStackFrame,
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
 /// - `error_code`: セグメント不存在例外のエラーコード（通常は0だが、特定の条件下で値が設定されることがある）
 extern "x86-interrupt" fn segment_not_present_handler(
-    stack_frame: InterruptStackFrame,
+    mut stack_frame: InterruptStackFrame,
     error_code: u64,
 ) {
     let _entered_from_user = enter_from_user(&stack_frame);
@@ -611,10 +894,19 @@ extern "x86-interrupt" fn segment_not_present_handler(
     error!("Error code: {:#x}", error_code);
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGBUS,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGBUS,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
@@ -626,7 +918,7 @@ extern "x86-interrupt" fn segment_not_present_handler(
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
 /// - `error_code`: スタックセグメントフォルトのエラーコード（通常は0だが、特定の条件下で値が設定されることがある）
 extern "x86-interrupt" fn stack_segment_fault_handler(
-    stack_frame: InterruptStackFrame,
+    mut stack_frame: InterruptStackFrame,
     error_code: u64,
 ) {
     let _entered_from_user = enter_from_user(&stack_frame);
@@ -644,22 +936,31 @@ extern "x86-interrupt" fn stack_segment_fault_handler(
     warn!("{:#?}", stack_frame);
 
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGBUS,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGBUS,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
 /// 一般保護例外ハンドラ
 ///
-/// 一般保護例外は、セグメント違反やアクセス違反などの保護違反が発生した場合に発生する。ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合はシステム全体を停止する。
+/// 一般保護例外は、セグメント違反やアクセス違反などの保護違反が発生した場合に発生する。ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合は `fatal_kernel_exception` で復旧するか再起動する。
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
 /// - `error_code`: 一般保護例外のエラーコード（エラーコードのビットフィールドには、外部割り込みか、どのテーブルからの例外かなどの情報が含まれる）
 extern "x86-interrupt" fn general_protection_fault_handler(
-    stack_frame: InterruptStackFrame,
+    mut stack_frame: InterruptStackFrame,
     error_code: u64,
 ) {
     let _entered_from_user = enter_from_user(&stack_frame);
@@ -698,31 +999,106 @@ extern "x86-interrupt" fn general_protection_fault_handler(
     if is_user_mode {
         // Dump detailed virtual->physical diagnostics using the active CR3
         dump_invalid_opcode_diagnostics(&stack_frame);
+        dump_core_for_user_exception(
+            crate::task::signal::SIGSEGV,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGSEGV,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
+/// ページフォルト例外のエントリ (naked function)
+///
+/// int 0x80 エントリと同じ順序で汎用レジスタを積んでから Rust 側ハンドラを呼ぶ。
+/// ユーザーの SIGSEGV ハンドラへ送達する場合、Rust 側が保存領域の
+/// RDI/RSI/RDX と割り込みフレームの RIP/RSP を書き換える。
+///
+/// スタックレイアウト（低アドレスが先頭）:
+/// ```text
+/// [0..15] r15 .. rax（int 0x80 と同順）
+/// [15] error code, [16] RIP, [17] CS, [18] RFLAGS, [19] RSP, [20] SS
+/// ```
+#[unsafe(naked)]
+unsafe extern "C" fn page_fault_entry() {
+    core::arch::naked_asm!(
+        "push rax",
+        "push rcx",
+        "push rdx",
+        "push rbx",
+        "push rbp",
+        "push rsi",
+        "push rdi",
+        "push r8",
+        "push r9",
+        "push r10",
+        "push r11",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        "mov rdi, rsp",
+        // エラーコード込みで 21 qword 積んでいるので call 前に 16-byte 境界へ揃える
+        "sub rsp, 8",
+        "call {handler}",
+        "add rsp, 8",
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop r11",
+        "pop r10",
+        "pop r9",
+        "pop r8",
+        "pop rdi",
+        "pop rsi",
+        "pop rbp",
+        "pop rbx",
+        "pop rdx",
+        "pop rcx",
+        "pop rax",
+        // エラーコードを捨てる
+        "add rsp, 8",
+        "iretq",
+        handler = sym page_fault_handler,
+    );
+}
 
 /// ページフォルト例外ハンドラ
 ///
-/// ページフォルトは、仮想メモリ管理に関連する例外で、アクセス違反やページの不在などが原因で発生する。ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合はシステム全体を停止する。
+/// ページフォルトは、仮想メモリ管理に関連する例外で、アクセス違反やページの不在などが原因で発生する。ユーザーモードで発生した場合は SIGSEGV ハンドラがあればそこへ送達し、なければプロセスを終了させる。カーネルモードで発生した場合は、プロセスのために動いていた途中ならそのプロセスだけを殺して動き続け、そうでなければ再起動する（`recovery` を参照）。
 ///
 /// ## Arguments
-/// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-/// - `error_code`: ページフォルトのエラーコード（エラーコードのビットフィールドには、ページが存在しないか、書き込みアクセスか、ユーザーモードかなどの情報が含まれる）
-extern "x86-interrupt" fn page_fault_handler(
-    stack_frame: InterruptStackFrame,
-    error_code: x86_64::structures::idt::PageFaultErrorCode,
-) {
+/// - `regs`: `page_fault_entry` が積んだレジスタ保存領域（エラーコードと割り込みフレームを含む）
+extern "sysv64" fn page_fault_handler(regs: *mut u64) {
     use x86_64::registers::control::Cr2;
+    use x86_64::structures::idt::InterruptStackFrameValue;
     use x86_64::VirtAddr;
 
+    // SAFETY: page_fault_entry が積んだ領域で、[15] がエラーコード、[16..21] が割り込みフレーム
+    let error_code = x86_64::structures::idt::PageFaultErrorCode::from_bits_truncate(unsafe {
+        regs.add(15).read()
+    });
+    let stack_frame = unsafe { &*(regs.add(16) as *const InterruptStackFrameValue) };
+
     let faulting_addr = Cr2::read().unwrap_or(VirtAddr::new(0));
     let is_user_mode = error_code.contains(x86_64::structures::idt::PageFaultErrorCode::USER_MODE);
     let entered_from_user = crate::syscall::syscall_entry::kpti_enter_for_trap(is_user_mode);
+    crate::trace::emit(
+        crate::trace::Tracepoint::PageFault,
+        [
+            faulting_addr.as_u64(),
+            error_code.bits(),
+            stack_frame.instruction_pointer.as_u64(),
+        ],
+    );
 
     error!(
         "EXCEPTION: PAGE FAULT ({})",
@@ -795,15 +1171,62 @@ extern "x86-interrupt" fn page_fault_handler(
             }
         }
 
+        // SIGSEGV ハンドラ（sigaltstack 上のものを含む）があればそこへ送達する
+        let info =
+            crate::syscall::signal::segv_info(faulting_addr.as_u64(), is_protection_violation);
+        let fault = crate::syscall::signal::FaultContext {
+            trapno: 14,
+            error_code: error_code.bits(),
+            cr2: faulting_addr.as_u64(),
+        };
+        // SAFETY: regs は page_fault_entry が積んだ保存領域
+        let frame = unsafe { crate::syscall::signal::TrapFrame::from_exception(regs) };
+        // トレース中ならトレーサーに判断させる（捨てられたら命令を再実行する）
+        let Some(info) = crate::syscall::ptrace::fault_stop(frame, info) else {
+            leave_to_user(entered_from_user);
+            return;
+        };
+        if crate::syscall::signal::force_fault_signal(frame, info, fault) {
+            leave_to_user(entered_from_user);
+            return;
+        }
+
+        report_user_crash(info.signo, frame.rip(), frame.rbp());
+        crate::syscall::coredump::dump_current(&info, frame.core_regs());
         error!("Terminating faulting user process");
         debug!("{:#?}", stack_frame);
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        // カーネルモードでのページフォルト: システム全体を停止
+        // カーネルモードでのページフォルト: 障害を起こしたプロセスを殺すか、再起動する
+        let fault = crate::recovery::begin();
         error!("FATAL: Page fault in kernel mode!");
         error!("{:#?}", stack_frame);
+        // SAFETY: regs は page_fault_entry が積んだ保存領域で、[10] が割り込まれた時点の rbp
+        let rbp = unsafe { regs.add(10).read() };
+        report_kernel_crash(
+            format_args!(
+                "page fault at {:#x} (error code {:?})",
+                faulting_addr.as_u64(),
+                error_code
+            ),
+            stack_frame.instruction_pointer.as_u64(),
+            rbp,
+        );
         error!("Please report this to https://github.com/tas0dev/mochiOS/issues with the above log details. :(");
-        halt_cpu();
+        // SAFETY: regs は page_fault_entry が積んだ保存領域で、割り込みフレームは [16..]
+        if unsafe { crate::kgdb::trap_saved(regs, 16, crate::task::signal::SIGSEGV) } {
+            crate::recovery::cancel(fault);
+            leave_to_user(entered_from_user);
+            return;
+        }
+        crate::recovery::recover_or_reboot(
+            fault,
+            format_args!(
+                "page fault in kernel mode at {:#x} (rip={:#x})",
+                faulting_addr.as_u64(),
+                stack_frame.instruction_pointer.as_u64()
+            ),
+        );
     }
 }
 
@@ -875,11 +1298,11 @@ fn try_grow_user_stack(fault_addr: u64) -> bool {
 /// x87浮動小数点例外ハンドラ
 ///
 /// x87浮動小数点例外は、x87 FPU命令の実行中にエラーが発生した場合に発生する。通常はFPUの状態が不正な場合や、無効な操作が行われた場合に発生することが多い。
-/// ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合はシステム全体を停止する。
+/// ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合は `fatal_kernel_exception` で復旧するか再起動する。
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn x87_floating_point_handler(stack_frame: InterruptStackFrame) {
+extern "x86-interrupt" fn x87_floating_point_handler(mut stack_frame: InterruptStackFrame) {
     let _entered_from_user = enter_from_user(&stack_frame);
     let is_user_mode = stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3;
     error!(
@@ -892,23 +1315,32 @@ extern "x86-interrupt" fn x87_floating_point_handler(stack_frame: InterruptStack
     );
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGFPE,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGFPE,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
 /// アライメントチェック例外ハンドラ
 ///
 /// アライメントチェック例外は、特定のデータアクセスが適切にアライメントされていない場合に発生する。通常は、CPUが要求するアライメント要件を満たさないメモリアクセスが原因で発生することが多い。
-/// ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合はシステム全体を停止する。
+/// ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合は `fatal_kernel_exception` で復旧するか再起動する。
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
 /// - `error_code`: アライメントチェック例外のエラーコード（エラーコードのビットフィールドには、ユーザーモードか、外部割り込みかなどの情報が含まれる）
 extern "x86-interrupt" fn alignment_check_handler(
-    stack_frame: InterruptStackFrame,
+    mut stack_frame: InterruptStackFrame,
     error_code: u64,
 ) {
     let _entered_from_user = enter_from_user(&stack_frame);
@@ -924,10 +1356,19 @@ extern "x86-interrupt" fn alignment_check_handler(
     error!("Error code: {:#x}", error_code);
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGBUS,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGBUS,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
@@ -937,19 +1378,25 @@ extern "x86-interrupt" fn alignment_check_handler(
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn machine_check_handler(stack_frame: InterruptStackFrame) -> ! {
+extern "x86-interrupt" fn machine_check_handler(mut stack_frame: InterruptStackFrame) -> ! {
     error!("EXCEPTION: MACHINE CHECK");
     error!("{:#?}", stack_frame);
-    halt_forever();
+    report_kernel_crash(
+        format_args!("machine check"),
+        stack_frame.instruction_pointer.as_u64(),
+        crate::backtrace::interrupted_frame_pointer(),
+    );
+    crate::kgdb::trap_interrupt(&mut stack_frame, crate::task::signal::SIGBUS, false);
+    crate::recovery::reboot(format_args!("machine check"));
 }
 
 /// SIMD浮動小数点例外ハンドラ
 /// SIMD浮動小数点例外は、SIMD命令の実行中にエラーが発生した場合に発生する。通常は、SIMDレジスタの状態が不正な場合や、無効な操作が行われた場合に発生することが多い。
-/// ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合はシステム全体を停止する。
+/// ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合は `fatal_kernel_exception` で復旧するか再起動する。
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn simd_floating_point_handler(stack_frame: InterruptStackFrame) {
+extern "x86-interrupt" fn simd_floating_point_handler(mut stack_frame: InterruptStackFrame) {
     let _entered_from_user = enter_from_user(&stack_frame);
     let is_user_mode = stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3;
     error!(
@@ -962,20 +1409,29 @@ extern "x86-interrupt" fn simd_floating_point_handler(stack_frame: InterruptStac
     );
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGFPE,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGFPE,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
 /// 仮想化例外ハンドラ
 /// 仮想化例外は、仮想化機能を使用している環境で、仮想化関連のエラーが発生した場合に発生する。通常は、仮想化機能の設定ミスや、仮想化環境でサポートされていない操作が原因で発生することが多い。
-/// ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合はシステム全体を停止する。
+/// ユーザーモードで発生した場合はプロセスを終了させ、カーネルモードで発生した場合は `fatal_kernel_exception` で復旧するか再起動する。
 ///
 /// ## Arguments
 /// - `stack_frame`: 割り込み発生時のCPU状態を表す構造体
-extern "x86-interrupt" fn virtualization_handler(stack_frame: InterruptStackFrame) {
+extern "x86-interrupt" fn virtualization_handler(mut stack_frame: InterruptStackFrame) {
     let _entered_from_user = enter_from_user(&stack_frame);
     let is_user_mode = stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3;
     error!(
@@ -988,10 +1444,19 @@ extern "x86-interrupt" fn virtualization_handler(stack_frame: InterruptStackFram
     );
     error!("{:#?}", stack_frame);
     if is_user_mode {
+        dump_core_for_user_exception(
+            crate::task::signal::SIGSEGV,
+            &stack_frame,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
         error!("Terminating faulting user process");
         crate::task::scheduler::exit_current_process(-1);
     } else {
-        halt_cpu();
+        fatal_kernel_exception(
+            &mut stack_frame,
+            crate::task::signal::SIGSEGV,
+            crate::backtrace::interrupted_frame_pointer(),
+        );
     }
 }
 
@@ -1001,6 +1466,8 @@ extern "x86-interrupt" fn virtualization_handler(stack_frame: InterruptStackFram
 /// OS全体が停止する (C-2修正)。このハンドラはスキャンコードを読み捨て EOI を送る。
 extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
     let entered_from_user = enter_from_user(&_stack_frame);
+    crate::percpu::irq_enter();
+    crate::trace::emit(crate::trace::Tracepoint::Irq, [33, 0, 0]);
     let scancode: u8 = unsafe {
         let mut port = x86_64::instructions::port::Port::<u8>::new(0x60);
         port.read()
@@ -1010,12 +1477,15 @@ extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStac
     unsafe {
         super::pic::PIC_MASTER.end_of_interrupt();
     }
+    crate::percpu::irq_exit();
     leave_to_user(entered_from_user);
 }
 
 /// マウス割り込みハンドラ (IRQ12 / ベクタ 44)
 extern "x86-interrupt" fn mouse_interrupt_handler(_stack_frame: InterruptStackFrame) {
     let entered_from_user = enter_from_user(&_stack_frame);
+    crate::percpu::irq_enter();
+    crate::trace::emit(crate::trace::Tracepoint::Irq, [44, 0, 0]);
     let byte: u8 = unsafe {
         let mut port = x86_64::instructions::port::Port::<u8>::new(0x60);
         port.read()
@@ -1023,6 +1493,7 @@ extern "x86-interrupt" fn mouse_interrupt_handler(_stack_frame: InterruptStackFr
     crate::util::ps2mouse::push_byte(byte);
     // IRQ12 はスレーブPIC配下なので、スレーブ→マスターの順でEOIを送る
     super::send_eoi(44);
+    crate::percpu::irq_exit();
     leave_to_user(entered_from_user);
 }
 
@@ -1037,6 +1508,7 @@ extern "x86-interrupt" fn mouse_interrupt_handler(_stack_frame: InterruptStackFr
 /// このハンドラは、将来的に各デバイスに対応した具体的な処理を実装するためのプレースホルダとして使用される予定
 extern "x86-interrupt" fn generic_interrupt_handler(_stack_frame: InterruptStackFrame) {
     let entered_from_user = enter_from_user(&_stack_frame);
+    crate::percpu::irq_enter();
     debug!("INTERRUPT: GENERIC");
     // マスターPICのみにEOIを送信する (LOW-01)
     // このハンドラはどのIRQから呼ばれるか不明のため、IRQ 0-7 (マスターのみ) を想定して
@@ -1045,21 +1517,70 @@ extern "x86-interrupt" fn generic_interrupt_handler(_stack_frame: InterruptStack
     unsafe {
         super::pic::PIC_MASTER.end_of_interrupt();
     }
+    crate::percpu::irq_exit();
     leave_to_user(entered_from_user);
 }
 
-/// CPU割り込みを無効化してシステムを停止
-fn halt_cpu() {
-    x86_64::instructions::interrupts::disable();
-    loop {
-        x86_64::instructions::hlt();
+/// カーネルモードの致命的な例外: スタックトレースを出し、kgdb が有効ならデバッガで止まる。
+/// 無効ならプロセスのために動いていた途中ならそのプロセスを殺して動き続け、
+/// そうでなければ再起動する（`recovery` を参照）
+///
+/// デバッガから再開された場合は戻り、（書き換えられたかもしれない）RIP から再実行する。
+fn fatal_kernel_exception(stack_frame: &mut InterruptStackFrame, sig: usize, rbp: u64) {
+    let fault = crate::recovery::begin();
+    report_kernel_crash(
+        format_args!("exception in kernel mode (signal {})", sig),
+        stack_frame.instruction_pointer.as_u64(),
+        rbp,
+    );
+    if crate::kgdb::trap_interrupt(stack_frame, sig, true) {
+        crate::recovery::cancel(fault);
+        return;
     }
+    crate::recovery::recover_or_reboot(
+        fault,
+        format_args!(
+            "exception in kernel mode (signal {}) at {:#x}",
+            sig,
+            stack_frame.instruction_pointer.as_u64()
+        ),
+    );
 }
 
-/// CPU割り込みを無効化してシステムを停止（戻らない）
-fn halt_forever() -> ! {
-    x86_64::instructions::interrupts::disable();
-    loop {
-        x86_64::instructions::hlt();
-    }
+/// カーネルの致命的な例外のスタックトレースを出し、永続クラッシュログにも残す
+fn report_kernel_crash(what: core::fmt::Arguments<'_>, rip: u64, rbp: u64) {
+    crate::backtrace::print_kernel(rip, rbp);
+    crate::crashlog::record(crate::crashlog::CrashKind::KernelFault, |w| {
+        use core::fmt::Write;
+        writeln!(w, "{}", what)?;
+        writeln!(w, "rip={:#x} rbp={:#x}", rip, rbp)?;
+        crate::backtrace::write_kernel(w, rip, rbp)
+    });
+}
+
+/// ユーザープロセスを例外で終了させる前にスタックトレースを出し、永続クラッシュログにも残す
+fn report_user_crash(sig: usize, rip: u64, rbp: u64) {
+    crate::backtrace::print_user(rip, rbp);
+    let pid = crate::task::current_thread_id()
+        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()));
+    crate::crashlog::record(crate::crashlog::CrashKind::UserFault, |w| {
+        use core::fmt::Write;
+        let named = pid.and_then(|pid| {
+            crate::task::with_process(pid, |p| {
+                writeln!(
+                    w,
+                    "pid {} ({}) killed by signal {}",
+                    pid.as_u64(),
+                    p.name(),
+                    sig
+                )
+            })
+        });
+        match named {
+            Some(result) => result?,
+            None => writeln!(w, "unknown process killed by signal {}", sig)?,
+        }
+        writeln!(w, "rip={:#x} rbp={:#x}", rip, rbp)?;
+        crate::backtrace::write_user(w, rip, rbp)
+    });
 }
@@ -0,0 +1,433 @@
+//! スピンロックの順序検査とデッドロック検出（`lockdep` フィーチャ有効時のみ）
+//!
+//! `SpinLock::named` で名前を付けたロックは、名前ごとに 1 つのロッククラスになる
+//! （同じ名前の配列要素などは同じクラス）。CPU ごとに保持中のロックを積んでおき、
+//! 「A を持ったまま B を取った」という順序をクラス間の有向グラフに記録する。
+//! 新しい順序が既存の順序と循環を作れば、実際に詰まる前に両方の取得箇所を報告する。
+//!
+//! スピンが `SPIN_TIMEOUT_NS` を超えたら、保持者（CPU・スレッド・取得箇所）と
+//! 待っている側の保持ロック・スタックトレースを一度だけ表示する（その後も待ち続ける）。
+//!
+//! ここではアトミック変数しか使わない。報告中に取るロック（kmsg など）は検査しない。
+
+use crate::percpu::MAX_CPUS;
+use core::panic::Location;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+/// ロッククラスの最大数（順序グラフの行は u64 のビットマスク）
+const MAX_CLASSES: usize = 64;
+/// 1 CPU が同時に保持するロックとして記録する数の上限
+const MAX_HELD: usize = 16;
+/// クラスを持たない（名前の無い）ロック
+const NO_CLASS: u8 = u8::MAX;
+/// スピンがこれを超えたら保持者を表示する
+pub const SPIN_TIMEOUT_NS: u64 = 2_000_000_000;
+/// TSC の較正前に使うサイクル数
+const SPIN_TIMEOUT_CYCLES: u64 = 4_000_000_000;
+
+type Site = AtomicPtr<Location<'static>>;
+
+const fn no_site() -> Site {
+    AtomicPtr::new(core::ptr::null_mut())
+}
+
+fn store_site(site: &Site, at: &'static Location<'static>) {
+    site.store(at as *const _ as *mut _, Ordering::Relaxed);
+}
+
+fn load_site(site: &Site) -> Option<&'static Location<'static>> {
+    // SAFETY: 格納するのは `Location::caller()` が返す 'static な参照だけ
+    unsafe { site.load(Ordering::Relaxed).as_ref() }
+}
+
+/// `file:line`（不明なら `?`）
+struct At(Option<&'static Location<'static>>);
+
+impl core::fmt::Display for At {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            Some(loc) => write!(f, "{}:{}", loc.file(), loc.line()),
+            None => f.write_str("?"),
+        }
+    }
+}
+
+/// ロックごとの検査用の状態（`SpinLock` に埋め込まれる）
+pub struct LockDep {
+    name: &'static str,
+    /// クラス番号 + 1（0 は未割り当て、`NO_CLASS` はクラス無し）
+    class: AtomicU8,
+    /// 保持している CPU + 1（0 は保持者なし）
+    owner_cpu: AtomicUsize,
+    owner_tid: AtomicU64,
+    owner_at: Site,
+}
+
+impl LockDep {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            class: AtomicU8::new(0),
+            owner_cpu: AtomicUsize::new(0),
+            owner_tid: AtomicU64::new(0),
+            owner_at: no_site(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        if self.name.is_empty() {
+            "<unnamed>"
+        } else {
+            self.name
+        }
+    }
+
+    /// クラス番号（初回に名前から割り当てる）
+    fn class(&self) -> u8 {
+        match self.class.load(Ordering::Relaxed) {
+            0 => {}
+            NO_CLASS => return NO_CLASS,
+            c => return c - 1,
+        }
+        let class = if self.name.is_empty() {
+            NO_CLASS
+        } else {
+            register(self.name)
+        };
+        // NO_CLASS はそのまま NO_CLASS として残る
+        self.class.store(class.saturating_add(1), Ordering::Relaxed);
+        class
+    }
+
+    /// `lock()` で取得を待つ前に呼ぶ: 保持中のロックとの順序を検査する
+    pub fn before_acquire(&self, at: &'static Location<'static>) {
+        let class = self.class();
+        if class == NO_CLASS {
+            return;
+        }
+        let held = &HELD[crate::percpu::current_cpu_id()];
+        if held.reporting.load(Ordering::Relaxed) {
+            return;
+        }
+        for i in 0..held.depth() {
+            let prev = held.classes[i].load(Ordering::Relaxed);
+            if prev == NO_CLASS || prev == class {
+                continue;
+            }
+            let (p, c) = (prev as usize, class as usize);
+            if ORDER[p].load(Ordering::Relaxed) & (1 << c) != 0
+                || REPORTED[p].load(Ordering::Relaxed) & (1 << c) != 0
+            {
+                continue;
+            }
+            match find_path(c, p) {
+                Some(path) => {
+                    REPORTED[p].fetch_or(1 << c, Ordering::Relaxed);
+                    held.report(|| report_inversion(held, i, self, at, &path));
+                }
+                None => {
+                    store_site(&EDGE_AT[p][c], at);
+                    ORDER[p].fetch_or(1 << c, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// 取得できた直後に呼ぶ
+    pub fn acquired(&self, addr: usize, at: &'static Location<'static>) {
+        let cpu = crate::percpu::current_cpu_id();
+        self.owner_cpu.store(cpu + 1, Ordering::Relaxed);
+        self.owner_tid
+            .store(crate::percpu::current_thread_raw_id(), Ordering::Relaxed);
+        store_site(&self.owner_at, at);
+        HELD[cpu].push(addr, self.class(), at);
+    }
+
+    /// 解放する直前に呼ぶ
+    pub fn released(&self, addr: usize) {
+        self.owner_cpu.store(0, Ordering::Relaxed);
+        HELD[crate::percpu::current_cpu_id()].remove(addr);
+    }
+}
+
+/// `lock()` のスピン 1 回分の経過を見る
+pub struct Spin {
+    start: u64,
+    reported: bool,
+}
+
+impl Spin {
+    /// 今からスピンを始める
+    pub fn start() -> Self {
+        Self {
+            start: crate::cpu::rdtsc(),
+            reported: false,
+        }
+    }
+
+    /// 待ち時間が `SPIN_TIMEOUT_NS` を超えていれば一度だけ報告する
+    pub fn check(&mut self, dep: &LockDep, addr: usize, at: &'static Location<'static>) {
+        if self.reported {
+            return;
+        }
+        let elapsed = crate::cpu::rdtsc().saturating_sub(self.start);
+        let timed_out = match crate::interrupt::timer::tsc_delta_to_ns(elapsed) {
+            Some(ns) => ns >= SPIN_TIMEOUT_NS,
+            None => elapsed >= SPIN_TIMEOUT_CYCLES,
+        };
+        if !timed_out {
+            return;
+        }
+        self.reported = true;
+        let held = &HELD[crate::percpu::current_cpu_id()];
+        held.report(|| report_timeout(held, dep, addr, at));
+    }
+}
+
+/// CPU ごとの保持中のロック（その CPU だけが割り込み禁止中に触る）
+struct Held {
+    depth: AtomicUsize,
+    locks: [AtomicUsize; MAX_HELD],
+    classes: [AtomicU8; MAX_HELD],
+    sites: [Site; MAX_HELD],
+    /// 報告の出力中（その間に取るロックは検査しない）
+    reporting: AtomicBool,
+}
+
+impl Held {
+    const fn new() -> Self {
+        Self {
+            depth: AtomicUsize::new(0),
+            locks: [const { AtomicUsize::new(0) }; MAX_HELD],
+            classes: [const { AtomicU8::new(NO_CLASS) }; MAX_HELD],
+            sites: [const { no_site() }; MAX_HELD],
+            reporting: AtomicBool::new(false),
+        }
+    }
+
+    fn depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    fn push(&self, addr: usize, class: u8, at: &'static Location<'static>) {
+        let depth = self.depth();
+        // 溢れたものは記録しない（解放時も見つからないので無視される）
+        if depth >= MAX_HELD || self.reporting.load(Ordering::Relaxed) {
+            return;
+        }
+        self.locks[depth].store(addr, Ordering::Relaxed);
+        self.classes[depth].store(class, Ordering::Relaxed);
+        store_site(&self.sites[depth], at);
+        self.depth.store(depth + 1, Ordering::Relaxed);
+    }
+
+    /// 解放順が取得順と逆でなくてもよいよう、アドレスで探して詰める
+    fn remove(&self, addr: usize) {
+        let depth = self.depth();
+        let Some(index) = (0..depth)
+            .rev()
+            .find(|&i| self.locks[i].load(Ordering::Relaxed) == addr)
+        else {
+            return;
+        };
+        for i in index..depth - 1 {
+            self.locks[i].store(self.locks[i + 1].load(Ordering::Relaxed), Ordering::Relaxed);
+            self.classes[i].store(
+                self.classes[i + 1].load(Ordering::Relaxed),
+                Ordering::Relaxed,
+            );
+            self.sites[i].store(self.sites[i + 1].load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+        self.depth.store(depth - 1, Ordering::Relaxed);
+    }
+
+    fn report(&self, f: impl FnOnce()) {
+        if self.reporting.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        f();
+        self.reporting.store(false, Ordering::Relaxed);
+    }
+
+    fn print(&self, cpu: usize) {
+        let depth = self.depth();
+        if depth == 0 {
+            crate::error!("  cpu{} holds no other spinlocks", cpu);
+            return;
+        }
+        crate::error!("  spinlocks held by cpu{} (outermost first):", cpu);
+        for i in 0..depth {
+            crate::error!(
+                "    {:#x} {} taken at {}",
+                self.locks[i].load(Ordering::Relaxed),
+                class_name(self.classes[i].load(Ordering::Relaxed)),
+                At(load_site(&self.sites[i]))
+            );
+        }
+    }
+}
+
+static HELD: [Held; MAX_CPUS] = [const { Held::new() }; MAX_CPUS];
+
+/// この CPU の保持中の記録を捨てる（`spinlock::release_held` でまとめて外したとき）
+pub fn forget_held() {
+    HELD[crate::percpu::current_cpu_id()]
+        .depth
+        .store(0, Ordering::Relaxed);
+}
+
+/// `ORDER[a]` のビット b: クラス a を持ったままクラス b を取ったことがある
+static ORDER: [AtomicU64; MAX_CLASSES] = [const { AtomicU64::new(0) }; MAX_CLASSES];
+/// `ORDER` の各辺を最初に記録した取得箇所
+static EDGE_AT: [[Site; MAX_CLASSES]; MAX_CLASSES] =
+    [const { [const { no_site() }; MAX_CLASSES] }; MAX_CLASSES];
+/// 報告済みの逆順（同じ組み合わせは一度だけ報告する）
+static REPORTED: [AtomicU64; MAX_CLASSES] = [const { AtomicU64::new(0) }; MAX_CLASSES];
+
+/// クラス名（`&'static str` をポインタと長さに分けて持つ）
+static CLASS_PTRS: [AtomicPtr<u8>; MAX_CLASSES] =
+    [const { AtomicPtr::new(core::ptr::null_mut()) }; MAX_CLASSES];
+static CLASS_LENS: [AtomicUsize; MAX_CLASSES] = [const { AtomicUsize::new(0) }; MAX_CLASSES];
+static CLASS_COUNT: AtomicUsize = AtomicUsize::new(0);
+static REGISTERING: AtomicBool = AtomicBool::new(false);
+
+/// 名前に対応するクラス番号（初めての名前なら割り当てる。満杯なら `NO_CLASS`）
+fn register(name: &'static str) -> u8 {
+    while REGISTERING
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    let count = CLASS_COUNT.load(Ordering::Relaxed);
+    let class = match (0..count).find(|&i| class_name(i as u8) == name) {
+        Some(i) => i as u8,
+        None if count < MAX_CLASSES => {
+            CLASS_PTRS[count].store(name.as_ptr() as *mut u8, Ordering::Relaxed);
+            CLASS_LENS[count].store(name.len(), Ordering::Relaxed);
+            CLASS_COUNT.store(count + 1, Ordering::Relaxed);
+            count as u8
+        }
+        None => NO_CLASS,
+    };
+    REGISTERING.store(false, Ordering::Release);
+    class
+}
+
+fn class_name(class: u8) -> &'static str {
+    if class == NO_CLASS || class as usize >= CLASS_COUNT.load(Ordering::Relaxed) {
+        return "<unnamed>";
+    }
+    let ptr = CLASS_PTRS[class as usize].load(Ordering::Relaxed);
+    let len = CLASS_LENS[class as usize].load(Ordering::Relaxed);
+    // SAFETY: `register` が格納した `&'static str` を組み立て直すだけ
+    unsafe { core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr, len)) }
+}
+
+/// 順序グラフで `from` から `to` へ至る経路（両端を含む）
+struct Path {
+    nodes: [u8; MAX_CLASSES],
+    len: usize,
+}
+
+fn find_path(from: usize, to: usize) -> Option<Path> {
+    let mut parent = [NO_CLASS; MAX_CLASSES];
+    let mut queue = [0u8; MAX_CLASSES];
+    let (mut head, mut tail) = (0, 1);
+    let mut visited = 1u64 << from;
+    queue[0] = from as u8;
+    while head < tail {
+        let node = queue[head] as usize;
+        head += 1;
+        if node == to {
+            let mut path = Path {
+                nodes: [0; MAX_CLASSES],
+                len: 0,
+            };
+            let mut cur = to;
+            loop {
+                path.nodes[path.len] = cur as u8;
+                path.len += 1;
+                if cur == from {
+                    break;
+                }
+                cur = parent[cur] as usize;
+            }
+            path.nodes[..path.len].reverse();
+            return Some(path);
+        }
+        let mut next = ORDER[node].load(Ordering::Relaxed) & !visited;
+        visited |= next;
+        while next != 0 {
+            let n = next.trailing_zeros() as usize;
+            next &= next - 1;
+            parent[n] = node as u8;
+            queue[tail] = n as u8;
+            tail += 1;
+        }
+    }
+    None
+}
+
+fn report_inversion(
+    held: &Held,
+    held_index: usize,
+    dep: &LockDep,
+    at: &'static Location<'static>,
+    path: &Path,
+) {
+    let cpu = crate::percpu::current_cpu_id();
+    let prev = held.classes[held_index].load(Ordering::Relaxed);
+    crate::error!("lockdep: possible deadlock (lock order inversion)");
+    crate::error!(
+        "  cpu{} tid {} is acquiring {} at {}",
+        cpu,
+        crate::percpu::current_thread_raw_id(),
+        dep.name(),
+        At(Some(at))
+    );
+    crate::error!(
+        "  while holding {} taken at {}",
+        class_name(prev),
+        At(load_site(&held.sites[held_index]))
+    );
+    crate::error!("  but the opposite order was seen before:");
+    for pair in path.nodes[..path.len].windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        crate::error!(
+            "    {} -> {} at {}",
+            class_name(a),
+            class_name(b),
+            At(load_site(&EDGE_AT[a as usize][b as usize]))
+        );
+    }
+    held.print(cpu);
+    crate::backtrace::print_current();
+}
+
+fn report_timeout(held: &Held, dep: &LockDep, addr: usize, at: &'static Location<'static>) {
+    let cpu = crate::percpu::current_cpu_id();
+    crate::error!(
+        "lockdep: spinlock {} ({:#x}) not acquired after {} ms",
+        dep.name(),
+        addr,
+        SPIN_TIMEOUT_NS / 1_000_000
+    );
+    crate::error!(
+        "  waiter: cpu{} tid {} at {}",
+        cpu,
+        crate::percpu::current_thread_raw_id(),
+        At(Some(at))
+    );
+    match dep.owner_cpu.load(Ordering::Relaxed) {
+        0 => crate::error!("  holder: unknown (taken before tracking or force-unlocked)"),
+        owner => crate::error!(
+            "  holder: cpu{} tid {} taken at {}",
+            owner - 1,
+            dep.owner_tid.load(Ordering::Relaxed),
+            At(load_site(&dep.owner_at))
+        ),
+    }
+    held.print(cpu);
+    crate::backtrace::print_current();
+}
@@ -3,6 +3,8 @@
 //! IDT、PIC、タイマーなどの割込み処理を管理
 
 pub mod idt;
+#[cfg(feature = "lockdep")]
+pub mod lockdep;
 pub mod pic;
 pub mod spinlock;
 mod syscall;
@@ -1,10 +1,22 @@
 //! 割込み安全なスピンロック実装
 //!
 //! 割込みコンテキストでも安全に使用できるスピンロックを提供
+//!
+//! `lockdep` フィーチャを有効にすると、取得順序の検査とスピンのタイムアウト検出が
+//! 有効になる（`interrupt::lockdep` を参照）。
+//!
+//! CPU ごとに保持中のロックを記録しておき、カーネルの障害から復旧するときに
+//! 障害を起こした流れが持っていたロックを外せるようにする（`recovery` を参照）。
 
+#[cfg(feature = "lockdep")]
+use super::lockdep;
+use crate::percpu::MAX_CPUS;
 use core::cell::UnsafeCell;
 use core::ops::{Deref, DerefMut};
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+/// 1 CPU が同時に保持するロックとして記録する数の上限
+const MAX_HELD: usize = 16;
 
 /// 割込み安全なスピンロック
 ///
@@ -13,6 +25,9 @@ use core::sync::atomic::{AtomicBool, Ordering};
 pub struct SpinLock<T> {
     /// ロック状態を表すフラグ
     locked: AtomicBool,
+    /// 順序検査用の状態
+    #[cfg(feature = "lockdep")]
+    dep: lockdep::LockDep,
     /// 保護されるデータ
     data: UnsafeCell<T>,
 }
@@ -29,8 +44,22 @@ impl<T> SpinLock<T> {
     /// ## Returns
     /// - `SpinLock<T>`: 新しいスピンロックインスタンス
     pub const fn new(data: T) -> Self {
+        Self::named("", data)
+    }
+
+    /// 名前付きのスピンロックを作成
+    ///
+    /// `lockdep` フィーチャ有効時は名前がロッククラスになり、取得順序の検査対象になる。
+    /// 無効時は `new` と同じ。
+    ///
+    /// ## Arguments
+    /// - `name`: ロッククラス名（同じ名前のロックは同じクラスとして扱う）
+    /// - `data`: ロックで保護されるデータ
+    pub const fn named(name: &'static str, data: T) -> Self {
         Self {
             locked: AtomicBool::new(false),
+            #[cfg(feature = "lockdep")]
+            dep: lockdep::LockDep::new(name),
             data: UnsafeCell::new(data),
         }
     }
@@ -45,6 +74,7 @@ impl<T> SpinLock<T> {
     ///
     /// ## Returns
     /// - `SpinLockGuard<'_, T>`: ロックガード。ドロップ時にロックを解放し、割込み状態を復元する
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn lock(&self) -> SpinLockGuard<'_, T> {
         // 現在の割込みフラグを保存
         let interrupt_enabled = x86_64::instructions::interrupts::are_enabled();
@@ -52,6 +82,13 @@ impl<T> SpinLock<T> {
         // 割込みを無効化
         x86_64::instructions::interrupts::disable();
 
+        #[cfg(feature = "lockdep")]
+        let at = core::panic::Location::caller();
+        #[cfg(feature = "lockdep")]
+        self.dep.before_acquire(at);
+        #[cfg(feature = "lockdep")]
+        let mut spin = lockdep::Spin::start();
+
         // ロック取得を試みる
         while self
             .locked
@@ -60,13 +97,22 @@ impl<T> SpinLock<T> {
         {
             // ロックが取得できるまでスピン
             while self.locked.load(Ordering::Relaxed) {
+                #[cfg(feature = "lockdep")]
+                spin.check(&self.dep, self.addr(), at);
                 core::hint::spin_loop();
             }
         }
 
+        #[cfg(feature = "lockdep")]
+        self.dep.acquired(self.addr(), at);
+
+        let cpu = crate::percpu::current_cpu_id();
+        HELD[cpu].push(&self.locked);
+
         SpinLockGuard {
             lock: self,
             interrupt_enabled,
+            cpu,
         }
     }
 
@@ -79,6 +125,7 @@ impl<T> SpinLock<T> {
     ///
     /// ## Returns
     /// - `Option<SpinLockGuard<'_, T>>`: ロックガード。ロックが取得できた場合はSome、そうでない場合はNone
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T>> {
         let interrupt_enabled = x86_64::instructions::interrupts::are_enabled();
 
@@ -89,9 +136,15 @@ impl<T> SpinLock<T> {
             .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
             .is_ok()
         {
+            #[cfg(feature = "lockdep")]
+            self.dep
+                .acquired(self.addr(), core::panic::Location::caller());
+            let cpu = crate::percpu::current_cpu_id();
+            HELD[cpu].push(&self.locked);
             Some(SpinLockGuard {
                 lock: self,
                 interrupt_enabled,
+                cpu,
             })
         } else {
             // ロック取得失敗、割込み状態を復元
@@ -115,6 +168,9 @@ impl<T> SpinLock<T> {
     /// # Safety
     /// 呼び出し側は、現在このロックを保持しているか、他スレッドから同時アクセスされないことを保証する必要がある。
     pub unsafe fn force_unlock(&self) {
+        #[cfg(feature = "lockdep")]
+        self.dep.released(self.addr());
+        HELD[crate::percpu::current_cpu_id()].forget(&self.locked);
         self.locked.store(false, Ordering::Release);
     }
 
@@ -124,6 +180,11 @@ impl<T> SpinLock<T> {
     pub fn as_ptr(&self) -> *const T {
         self.data.get() as *const T
     }
+
+    #[cfg(feature = "lockdep")]
+    fn addr(&self) -> usize {
+        self as *const Self as usize
+    }
 }
 
 /// スピンロックガード
@@ -137,6 +198,8 @@ pub struct SpinLockGuard<'a, T> {
     lock: &'a SpinLock<T>,
     /// ロック取得時の割込み状態
     interrupt_enabled: bool,
+    /// 取得した CPU（保持中の記録から外すため）
+    cpu: usize,
 }
 
 impl<T> Deref for SpinLockGuard<'_, T> {
@@ -156,6 +219,9 @@ impl<T> DerefMut for SpinLockGuard<'_, T> {
 impl<T> Drop for SpinLockGuard<'_, T> {
     fn drop(&mut self) {
         // ロックを解放
+        #[cfg(feature = "lockdep")]
+        self.lock.dep.released(self.lock.addr());
+        HELD[self.cpu].remove(&self.lock.locked);
         self.lock.locked.store(false, Ordering::Release);
 
         // 割込み状態を復元
@@ -164,3 +230,89 @@ impl<T> Drop for SpinLockGuard<'_, T> {
         }
     }
 }
+
+/// CPU ごとの保持中のロック（取得順）
+///
+/// ロックを持つ間は割り込みが止まっているので、触るのはその CPU だけ。
+struct Held {
+    /// 保持中の数（`MAX_HELD` を超えた分は記録しない）
+    depth: AtomicUsize,
+    /// 各ロックの `locked` フラグ（null は記録できなかったもの）
+    locks: [AtomicPtr<AtomicBool>; MAX_HELD],
+}
+
+impl Held {
+    const fn new() -> Self {
+        Self {
+            depth: AtomicUsize::new(0),
+            locks: [const { AtomicPtr::new(core::ptr::null_mut()) }; MAX_HELD],
+        }
+    }
+
+    fn push(&self, locked: &AtomicBool) {
+        let depth = self.depth.load(Ordering::Relaxed);
+        if let Some(slot) = self.locks.get(depth) {
+            slot.store(locked as *const _ as *mut _, Ordering::Relaxed);
+        }
+        self.depth.store(depth + 1, Ordering::Relaxed);
+    }
+
+    /// 記録から外す（見つからなければ記録しきれなかった分として数だけ減らす）
+    fn remove(&self, locked: &AtomicBool) {
+        if !self.forget(locked) {
+            let depth = self.depth.load(Ordering::Relaxed);
+            self.depth.store(depth.saturating_sub(1), Ordering::Relaxed);
+        }
+    }
+
+    /// 記録にあれば外して `true`
+    fn forget(&self, locked: &AtomicBool) -> bool {
+        let depth = self.depth.load(Ordering::Relaxed);
+        let recorded = depth.min(MAX_HELD);
+        let ptr = locked as *const _ as *mut _;
+        // ガードは取得の逆順に外れることが多いので上から探す
+        let Some(index) = (0..recorded)
+            .rev()
+            .find(|&i| self.locks[i].load(Ordering::Relaxed) == ptr)
+        else {
+            return false;
+        };
+        for i in index..recorded - 1 {
+            let next = self.locks[i + 1].load(Ordering::Relaxed);
+            self.locks[i].store(next, Ordering::Relaxed);
+        }
+        // 記録しきれなかったロックが繰り上がる位置は中身が分からないので null にしておく
+        if depth > MAX_HELD {
+            self.locks[MAX_HELD - 1].store(core::ptr::null_mut(), Ordering::Relaxed);
+        }
+        self.depth.store(depth - 1, Ordering::Relaxed);
+        true
+    }
+}
+
+static HELD: [Held; MAX_CPUS] = [const { Held::new() }; MAX_CPUS];
+
+/// この CPU が保持中のロックをすべて外し、外した数を返す
+///
+/// カーネルの障害から復旧するときに、障害を起こした流れが持っていたロックを解放する。
+/// 記録しきれないほど持っていた（外せなかったロックがある）なら `None`。
+/// 守られていたデータは更新の途中かもしれない。
+///
+/// # Safety
+/// 保持していた流れが二度と再開しないこと。
+pub unsafe fn release_held() -> Option<usize> {
+    let held = &HELD[crate::percpu::current_cpu_id()];
+    let depth = held.depth.swap(0, Ordering::Relaxed);
+    let mut complete = depth <= MAX_HELD;
+    for slot in held.locks[..depth.min(MAX_HELD)].iter().rev() {
+        let ptr = slot.swap(core::ptr::null_mut(), Ordering::Relaxed);
+        // SAFETY: 記録はガードが外れるまで残るので、ロック本体はまだ生きている
+        match unsafe { ptr.as_ref() } {
+            Some(locked) => locked.store(false, Ordering::Release),
+            None => complete = false,
+        }
+    }
+    #[cfg(feature = "lockdep")]
+    lockdep::forget_held();
+    complete.then_some(depth)
+}
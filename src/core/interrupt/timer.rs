@@ -9,19 +9,44 @@ use x86_64::structures::idt::InterruptStackFrame;
 /// タイマー割り込みカウンタ（100回 = 1秒）
 static TIMER_TICKS: AtomicU64 = AtomicU64::new(0);
 
+/// TSC の較正に使うティック数（1 秒）
+const TSC_CALIBRATION_TICKS: u64 = 100;
+/// 1 ティックあたりのナノ秒
+const NS_PER_TICK: u64 = 10_000_000;
+/// ティック 1 の時点の TSC
+static TSC_AT_FIRST_TICK: AtomicU64 = AtomicU64::new(0);
+/// 1 ティックあたりの TSC（0 は較正前）
+static TSC_PER_TICK: AtomicU64 = AtomicU64::new(0);
+
 /// タイマー割り込みハンドラ（IRQ0）
 ///
 /// ## Arguments
 /// - `_stack_frame`: 割り込み発生時のスタックフレーム
-pub extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    let entered_from_user = crate::syscall::syscall_entry::kpti_enter_for_trap(
-        _stack_frame.code_segment.rpl() == x86_64::PrivilegeLevel::Ring3,
-    );
+pub extern "x86-interrupt" fn timer_interrupt_handler(mut _stack_frame: InterruptStackFrame) {
+    // 割り込まれた位置の rbp（プロファイラ用。ハンドラの先頭で読む）
+    let interrupted_rbp = crate::backtrace::interrupted_frame_pointer();
+    let from_user = _stack_frame.code_segment.rpl() == x86_64::PrivilegeLevel::Ring3;
+    let entered_from_user = crate::syscall::syscall_entry::kpti_enter_for_trap(from_user);
+    crate::percpu::irq_enter();
+    crate::trace::emit(crate::trace::Tracepoint::Irq, [32, 0, 0]);
+
+    // ptrace の割り込み要求があれば、ユーザーへ戻った直後の #DB でシグナルを配送させる
+    crate::syscall::ptrace::kick_current_if_requested(&mut _stack_frame);
+    // kgdb の Ctrl-C も同じく次の命令の #DB で止める
+    crate::kgdb::poll_break_in(&mut _stack_frame);
 
     // タイマーカウンタを増加
     let ticks = TIMER_TICKS
         .fetch_add(1, Ordering::Relaxed)
         .saturating_add(1);
+    calibrate_tsc(ticks);
+    crate::trace::profile::on_tick(
+        ticks,
+        _stack_frame.instruction_pointer.as_u64(),
+        interrupted_rbp,
+        from_user,
+    );
+    crate::mem::vdso::update_time(ticks);
     crate::syscall::time::wake_due_sleepers(ticks);
     crate::syscall::process::wake_due_futex_waiters(ticks);
 
@@ -30,6 +55,8 @@ pub extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptSta
 
     // End of Interrupt (EOI) 信号をPICに送信
     super::send_eoi(32);
+    // ここから先のプリエンプトは割り込まれたスレッドの文脈で行う
+    crate::percpu::irq_exit();
 
     // タイムスライスが尽きた場合はプリエンプト
     // switch_context がカーネルスタック状態を保存するため、
@@ -42,6 +69,31 @@ pub extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptSta
     crate::syscall::syscall_entry::kpti_leave_after_trap(entered_from_user);
 }
 
+/// 起動直後の 1 秒間で TSC の速さを測る
+fn calibrate_tsc(ticks: u64) {
+    if ticks == 1 {
+        TSC_AT_FIRST_TICK.store(crate::cpu::rdtsc(), Ordering::Relaxed);
+    } else if ticks == 1 + TSC_CALIBRATION_TICKS {
+        let start = TSC_AT_FIRST_TICK.load(Ordering::Relaxed);
+        let per_tick = crate::cpu::rdtsc().saturating_sub(start) / TSC_CALIBRATION_TICKS;
+        TSC_PER_TICK.store(per_tick, Ordering::Relaxed);
+    }
+}
+
+/// TSC の差分をナノ秒にする（較正が済むまでは None）
+pub fn tsc_delta_to_ns(delta: u64) -> Option<u64> {
+    let per_tick = TSC_PER_TICK.load(Ordering::Relaxed);
+    if per_tick == 0 {
+        return None;
+    }
+    Some((delta as u128 * NS_PER_TICK as u128 / per_tick as u128) as u64)
+}
+
+/// 起動からのナノ秒（TSC の較正前はティック単位）
+pub fn now_ns() -> u64 {
+    tsc_delta_to_ns(crate::cpu::rdtsc()).unwrap_or_else(|| get_ticks() * NS_PER_TICK)
+}
+
 /// 現在のタイマーティック数を取得
 ///
 /// ## Returns
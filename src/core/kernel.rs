@@ -1,8 +1,11 @@
+#[cfg(not(feature = "ktest"))]
+use crate::info;
 use crate::result::handle_kernel_error;
 use crate::result::{Kernel, Process};
+#[cfg(not(feature = "ktest"))]
 use crate::syscall::exec::exec_kernel_with_name;
 use crate::util::log::LogLevel;
-use crate::{debug, info, sprintln, vprintln};
+use crate::{debug, sprintln, vprintln};
 use crate::{init::kinit, task, util, BootInfo, MemoryRegion, Result};
 
 const KERNEL_THREAD_STACK_SIZE: usize = 4096 * 8;
@@ -14,9 +17,23 @@ static mut KERNEL_THREAD_STACK: KernelStack = KernelStack([0; KERNEL_THREAD_STAC
 
 /// カーネルメイン関数
 fn kernel_main() -> ! {
-    util::log::set_level(LogLevel::Info);
+    let level = crate::init::cmdline::get("loglevel")
+        .and_then(util::log::level_from_name)
+        .unwrap_or(LogLevel::Info);
+    util::log::set_level(level);
     debug!("Kernel started");
 
+    // テストビルドではユーザー空間を起動せず、テスト結果で QEMU を終了する
+    #[cfg(feature = "ktest")]
+    crate::ktest::run_all();
+
+    #[cfg(not(feature = "ktest"))]
+    start_userspace();
+}
+
+/// core.service を起動してアイドル状態に入る
+#[cfg(not(feature = "ktest"))]
+fn start_userspace() -> ! {
     // core.serviceのみ起動（他のサービスはcore.serviceが管理）
     info!("Starting core.service");
     let manager_pid = exec_kernel_with_name("core.service", "core.service");
@@ -24,11 +41,14 @@ fn kernel_main() -> ! {
         && task::with_process(task::ProcessId::from_u64(manager_pid), |_| ()).is_some()
     {
         crate::syscall::exec::register_service_manager_pid(manager_pid);
+        crate::watchdog::start(manager_pid);
     } else {
         crate::warn!(
             "Failed to register core.service as service manager (ret={:#x})",
             manager_pid
         );
+        // core.service が起動できなくても見張りのスレッドは動かしておく
+        crate::watchdog::start(0);
     }
 
     // カーネルはアイドル状態に入る
@@ -44,13 +64,13 @@ pub fn kernel_entry(boot_info: &'static BootInfo) -> ! {
         Ok(map) => map,
         Err(e) => {
             handle_kernel_error(e);
-            halt_forever();
+            crate::recovery::fatal(format_args!("kernel initialization failed: {}", e));
         }
     };
 
     create_kernel_proc(boot_info, memory_map).unwrap_or_else(|e| {
         handle_kernel_error(e);
-        halt_forever();
+        crate::recovery::fatal(format_args!("failed to create kernel process: {}", e));
     });
     task::start_scheduling();
 }
@@ -82,10 +102,3 @@ fn create_kernel_proc(
 
     Ok(())
 }
-
-/// システムを無限ループで停止
-fn halt_forever() -> ! {
-    loop {
-        x86_64::instructions::hlt();
-    }
-}
@@ -0,0 +1,652 @@
+//! カーネル GDB スタブ
+//!
+//! 起動オプション `kgdb`（または `kgdb=wait`）で有効になり、COM2 (0x2F8) で
+//! GDB リモートシリアルプロトコルを話す。COM1 はこれまで通りカーネルログに使う。
+//!
+//! - #BP / #DB と、カーネルモードの致命的な例外（ダブルフォルトを含む）で停止する
+//! - 実行中の Ctrl-C はタイマー割り込みで拾い、TF を立てて次の命令で停止する
+//! - `kgdb=wait` なら初期化の最後で止まってデバッガの接続を待つ
+//!
+//! スタブの中では割り込みを禁止したままポーリングでシリアルを読む。
+//! 汎用レジスタを保存しない x86-interrupt ハンドラから入った場合、
+//! RSP 以外の汎用レジスタは gdb には「値なし」として見える。
+
+mod packet;
+
+use crate::task::signal::{SIGINT, SIGTRAP};
+use crate::util::console::SerialPort;
+use core::sync::atomic::{AtomicBool, Ordering};
+use packet::{Incoming, Reply, BUF_SIZE};
+use spin::Mutex;
+use x86_64::registers::rflags::RFlags;
+use x86_64::structures::idt::InterruptStackFrame;
+use x86_64::VirtAddr;
+
+/// スタブが使うシリアルポート（COM2）
+const KGDB_PORT: u16 = 0x2F8;
+
+/// 同時に差し込めるソフトウェアブレークポイントの数
+const MAX_BREAKPOINTS: usize = 32;
+
+const INT3: u8 = 0xCC;
+
+/// g パケットのレジスタ数（rax..r15, rip, eflags, cs, ss）
+const GDB_REG_COUNT: usize = 20;
+
+/// 起動オプションで有効にされたか
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// `kgdb=wait`
+static WAIT_AT_BOOT: AtomicBool = AtomicBool::new(false);
+/// 実行中に Ctrl-C を受け取り、次の #DB で止まる
+static BREAK_REQUESTED: AtomicBool = AtomicBool::new(false);
+/// `s` で再開し、次の #DB で止まる
+static STEPPING: AtomicBool = AtomicBool::new(false);
+/// gdb が c / s の後で停止報告を待っている
+static GDB_WAITING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy)]
+struct Breakpoint {
+    addr: u64,
+    saved: u8,
+    used: bool,
+}
+
+impl Breakpoint {
+    const EMPTY: Self = Self {
+        addr: 0,
+        saved: 0,
+        used: false,
+    };
+}
+
+struct Stub {
+    port: SerialPort,
+    input: [u8; BUF_SIZE],
+    reply: Reply,
+    breakpoints: [Breakpoint; MAX_BREAKPOINTS],
+}
+
+static STUB: Mutex<Stub> = Mutex::new(Stub {
+    port: SerialPort::new(KGDB_PORT),
+    input: [0; BUF_SIZE],
+    reply: Reply::new(),
+    breakpoints: [Breakpoint::EMPTY; MAX_BREAKPOINTS],
+});
+
+/// 停止中のレジスタ（gdb の amd64 の並び）
+#[derive(Clone, Copy)]
+struct Registers {
+    /// rax, rbx, rcx, rdx, rsi, rdi, rbp, rsp, r8..r15
+    gpr: [u64; 16],
+    /// RSP 以外の汎用レジスタが保存されているか
+    gpr_saved: bool,
+    rip: u64,
+    rflags: u64,
+    cs: u64,
+    ss: u64,
+}
+
+/// gdb の汎用レジスタ番号 → int 0x80 と同じ保存領域の添字（RSP は割り込みフレーム側）
+const SAVED_INDEX: [usize; 16] = [14, 11, 13, 12, 9, 8, 10, usize::MAX, 7, 6, 5, 4, 3, 2, 1, 0];
+const GDB_RSP: usize = 7;
+
+/// 停止した文脈のレジスタの置き場所
+enum Frame<'a> {
+    /// naked エントリが積んだ保存領域（`iret` は割り込みフレームの先頭の添字）
+    Saved { regs: *mut u64, iret: usize },
+    /// x86-interrupt ハンドラの割り込みフレームだけ
+    Interrupt(&'a mut InterruptStackFrame),
+}
+
+impl Frame<'_> {
+    fn load(&self) -> Registers {
+        match self {
+            Frame::Saved { regs, iret } => {
+                // SAFETY: naked エントリが積んだ領域（呼び出し側が保証する）
+                let read = |i: usize| unsafe { regs.add(i).read() };
+                let mut gpr = [0u64; 16];
+                for (n, slot) in gpr.iter_mut().enumerate() {
+                    *slot = if n == GDB_RSP {
+                        read(iret + 3)
+                    } else {
+                        read(SAVED_INDEX[n])
+                    };
+                }
+                Registers {
+                    gpr,
+                    gpr_saved: true,
+                    rip: read(*iret),
+                    cs: read(iret + 1),
+                    rflags: read(iret + 2),
+                    ss: read(iret + 4),
+                }
+            }
+            Frame::Interrupt(frame) => {
+                let mut gpr = [0u64; 16];
+                gpr[GDB_RSP] = frame.stack_pointer.as_u64();
+                Registers {
+                    gpr,
+                    gpr_saved: false,
+                    rip: frame.instruction_pointer.as_u64(),
+                    cs: frame.code_segment.0 as u64,
+                    rflags: frame.cpu_flags.bits(),
+                    ss: frame.stack_segment.0 as u64,
+                }
+            }
+        }
+    }
+
+    /// 書き戻す（CS/SS は変えない）
+    fn store(&mut self, r: &Registers) {
+        match self {
+            Frame::Saved { regs, iret } => {
+                // SAFETY: load と同じ領域
+                let write = |i: usize, v: u64| unsafe { regs.add(i).write(v) };
+                for (n, &value) in r.gpr.iter().enumerate() {
+                    if n == GDB_RSP {
+                        write(*iret + 3, value);
+                    } else {
+                        write(SAVED_INDEX[n], value);
+                    }
+                }
+                write(*iret, r.rip);
+                write(*iret + 2, r.rflags);
+            }
+            Frame::Interrupt(frame) => {
+                let (Ok(rip), Ok(rsp)) =
+                    (VirtAddr::try_new(r.rip), VirtAddr::try_new(r.gpr[GDB_RSP]))
+                else {
+                    return;
+                };
+                // SAFETY: 戻り先の割り込みフレームを書き換えるだけ
+                unsafe {
+                    frame.as_mut().update(|f| {
+                        f.instruction_pointer = rip;
+                        f.stack_pointer = rsp;
+                        f.cpu_flags = RFlags::from_bits_truncate(r.rflags);
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Registers {
+    /// gdb のレジスタ番号 `n` の値とバイト幅（値が無ければ None）
+    fn get(&self, n: usize) -> Option<(Option<u64>, usize)> {
+        Some(match n {
+            0..=15 if n == GDB_RSP || self.gpr_saved => (Some(self.gpr[n]), 8),
+            0..=15 => (None, 8),
+            16 => (Some(self.rip), 8),
+            17 => (Some(self.rflags), 4),
+            18 => (Some(self.cs), 4),
+            19 => (Some(self.ss), 4),
+            _ => return None,
+        })
+    }
+
+    /// 書き込めるレジスタなら書いて true
+    fn set(&mut self, n: usize, value: u64) -> bool {
+        match n {
+            0..=15 if n == GDB_RSP || self.gpr_saved => self.gpr[n] = value,
+            16 => self.rip = value,
+            17 => self.rflags = value,
+            // セグメントと値の無いレジスタへの書き込みは黙って捨てる
+            0..=19 => {}
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// 起動オプションを読んで有効にする（kinit の最初で呼ばれる）
+pub fn init() {
+    let Some(value) = crate::init::cmdline::get("kgdb") else {
+        return;
+    };
+    STUB.lock().port.init();
+    WAIT_AT_BOOT.store(value == "wait", Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Release);
+    crate::info!("kgdb: enabled on serial port {:#x}", KGDB_PORT);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// `kgdb=wait` なら int3 で止まってデバッガの接続を待つ（kinit の最後で呼ばれる）
+pub fn boot_break() {
+    if WAIT_AT_BOOT.load(Ordering::Relaxed) {
+        crate::info!("kgdb: waiting for gdb to attach");
+        breakpoint();
+    }
+}
+
+/// 有効なら int3 でスタブに入る（パニックなど、その場で止めたい所から呼ぶ）
+pub fn breakpoint() {
+    if is_enabled() {
+        // SAFETY: #BP ハンドラがスタブへ入り、int3 の次の命令から戻る
+        unsafe { core::arch::asm!("int3") };
+    }
+}
+
+/// タイマー割り込みから呼ばれる: Ctrl-C が届いていれば次の命令で #DB を起こす
+pub fn poll_break_in(stack_frame: &mut InterruptStackFrame) {
+    if !is_enabled() {
+        return;
+    }
+    let Some(mut stub) = STUB.try_lock() else {
+        return;
+    };
+    let mut requested = false;
+    while let Some(b) = stub.port.try_receive_byte() {
+        requested |= b == 0x03;
+    }
+    drop(stub);
+    if requested {
+        BREAK_REQUESTED.store(true, Ordering::Release);
+        // SAFETY: 戻り先の割り込みフレームの RFLAGS に TF を足すだけ
+        unsafe {
+            stack_frame
+                .as_mut()
+                .update(|f| f.cpu_flags.insert(RFlags::TRAP_FLAG));
+        }
+    }
+}
+
+/// #DB から呼ばれる: Ctrl-C かシングルステップならスタブに入って true
+///
+/// ## Safety
+/// `regs` は `debug_entry` が積んだ保存領域（割り込みフレームは [15..]）
+pub unsafe fn handle_debug(regs: *mut u64) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    let requested = BREAK_REQUESTED.swap(false, Ordering::AcqRel);
+    let stepping = STEPPING.swap(false, Ordering::AcqRel);
+    if !requested && !stepping {
+        return false;
+    }
+    let mut frame = Frame::Saved { regs, iret: 15 };
+    let mut r = frame.load();
+    r.rflags &= !RFlags::TRAP_FLAG.bits();
+    frame.store(&r);
+    enter(frame, if requested { SIGINT } else { SIGTRAP }, true);
+    true
+}
+
+/// #BP から呼ばれる: スタブのブレークポイントかカーネル内の int3 ならスタブに入って true
+///
+/// 差し込んだブレークポイントで止まった場合は RIP を int3 の位置へ戻してから報告する。
+///
+/// ## Safety
+/// `regs` は `breakpoint_entry` が積んだ保存領域（割り込みフレームは [15..]）
+pub unsafe fn handle_breakpoint(regs: *mut u64) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    let mut frame = Frame::Saved { regs, iret: 15 };
+    let mut r = frame.load();
+    let hit = r.rip.wrapping_sub(1);
+    let ours = STUB
+        .try_lock()
+        .is_some_and(|stub| stub.breakpoints.iter().any(|bp| bp.used && bp.addr == hit));
+    if !ours && r.cs & 3 == 3 {
+        return false;
+    }
+    if ours {
+        r.rip = hit;
+        frame.store(&r);
+    }
+    enter(frame, SIGTRAP, true);
+    true
+}
+
+/// 汎用レジスタを保存済みの致命的な例外（カーネルモードのページフォルト）で止まる
+///
+/// 有効なら true を返し、呼び出し元は（gdb が書き換えたかもしれない）RIP から再実行する。
+///
+/// ## Safety
+/// `regs` は naked エントリが積んだ保存領域で、割り込みフレームは `[iret..]`
+pub unsafe fn trap_saved(regs: *mut u64, iret: usize, sig: usize) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    enter(Frame::Saved { regs, iret }, sig, true);
+    true
+}
+
+/// x86-interrupt ハンドラの致命的な例外で止まる
+///
+/// `resumable` が false（ダブルフォルトなど）なら、continue されても停止を報告し直すだけで、
+/// 戻ったら呼び出し元が停止させる。
+pub fn trap_interrupt(stack_frame: &mut InterruptStackFrame, sig: usize, resumable: bool) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    enter(Frame::Interrupt(stack_frame), sig, resumable);
+    true
+}
+
+/// スタブ本体: gdb の要求を処理し、再開か切断で戻る
+fn enter(mut frame: Frame<'_>, sig: usize, resumable: bool) {
+    // スタブの中で例外が起きて入れ子になった場合は何もしない（呼び出し元が停止させる）
+    let Some(mut guard) = STUB.try_lock() else {
+        return;
+    };
+    let stub = &mut *guard;
+    let sig = sig as u8;
+    let mut regs = frame.load();
+    crate::warn!(
+        "kgdb: stopped with signal {} at rip={:#x}, waiting for gdb",
+        sig,
+        regs.rip
+    );
+    if GDB_WAITING.swap(false, Ordering::AcqRel) {
+        stub.send_stop(sig);
+    }
+
+    loop {
+        let len = match packet::receive(&mut stub.port, &mut stub.input) {
+            Incoming::Packet(len) => len,
+            Incoming::Interrupt => {
+                stub.send_stop(sig);
+                continue;
+            }
+        };
+        match stub.handle(len, sig, &mut regs, resumable) {
+            Action::Stay => {}
+            Action::Resume { step } => {
+                regs.rflags &= !RFlags::TRAP_FLAG.bits();
+                if step {
+                    regs.rflags |= RFlags::TRAP_FLAG.bits();
+                }
+                STEPPING.store(step, Ordering::Release);
+                GDB_WAITING.store(true, Ordering::Release);
+                frame.store(&regs);
+                return;
+            }
+            Action::Detach => {
+                regs.rflags &= !RFlags::TRAP_FLAG.bits();
+                frame.store(&regs);
+                return;
+            }
+        }
+    }
+}
+
+/// 要求を処理したあとにすること
+enum Action {
+    Stay,
+    Resume { step: bool },
+    Detach,
+}
+
+impl Stub {
+    fn send_stop(&mut self, sig: u8) {
+        self.reply.clear();
+        self.reply.push(b'S');
+        self.reply.push_hex_byte(sig);
+        packet::send(&mut self.port, self.reply.as_bytes());
+    }
+
+    fn handle(&mut self, len: usize, sig: u8, regs: &mut Registers, resumable: bool) -> Action {
+        // input を借りたまま reply を組み立てられるよう、パケット本体を写しておく
+        let mut packet = [0u8; BUF_SIZE];
+        packet[..len].copy_from_slice(&self.input[..len]);
+        let packet = &packet[..len];
+        self.reply.clear();
+        let Some((&cmd, args)) = packet.split_first() else {
+            packet::send(&mut self.port, &[]);
+            return Action::Stay;
+        };
+        let mut action = Action::Stay;
+        let ok = match cmd {
+            b'?' => {
+                self.reply.push(b'S');
+                self.reply.push_hex_byte(sig);
+                None
+            }
+            b'g' => {
+                for n in 0..GDB_REG_COUNT {
+                    if let Some((value, width)) = regs.get(n) {
+                        match value {
+                            Some(v) => self.reply.push_hex_le(v, width),
+                            None => self.reply.push_unavailable(width),
+                        }
+                    }
+                }
+                None
+            }
+            b'G' => {
+                let mut off = 0;
+                for n in 0..GDB_REG_COUNT {
+                    let Some((_, width)) = regs.get(n) else {
+                        break;
+                    };
+                    let hex = args.get(off..).unwrap_or(&[]);
+                    if let Some(value) = packet::parse_hex_le(hex, width) {
+                        regs.set(n, value);
+                    }
+                    off += width * 2;
+                }
+                Some(true)
+            }
+            b'p' => match packet::parse_hex(args).and_then(|n| regs.get(n as usize)) {
+                Some((Some(v), width)) => {
+                    self.reply.push_hex_le(v, width);
+                    None
+                }
+                Some((None, width)) => {
+                    self.reply.push_unavailable(width);
+                    None
+                }
+                None => Some(false),
+            },
+            b'P' => {
+                let mut parts = args.splitn(2, |&b| b == b'=');
+                let n = parts.next().and_then(packet::parse_hex).map(|n| n as usize);
+                let written = match (n, parts.next()) {
+                    (Some(n), Some(hex)) => regs
+                        .get(n)
+                        .and_then(|(_, width)| packet::parse_hex_le(hex, width))
+                        .is_some_and(|value| regs.set(n, value)),
+                    _ => false,
+                };
+                Some(written)
+            }
+            b'm' => match parse_addr_len(args) {
+                Some((addr, len)) if len <= (BUF_SIZE - 4) / 2 => {
+                    let mut read_all = true;
+                    for i in 0..len as u64 {
+                        match self.read_byte(addr.wrapping_add(i)) {
+                            Some(b) => self.reply.push_hex_byte(b),
+                            None => {
+                                read_all = false;
+                                break;
+                            }
+                        }
+                    }
+                    // 1 バイトも読めなければエラー、途中までなら読めた分を返す
+                    if read_all || !self.reply.as_bytes().is_empty() {
+                        None
+                    } else {
+                        Some(false)
+                    }
+                }
+                _ => Some(false),
+            },
+            b'M' => {
+                let colon = args.iter().position(|&b| b == b':');
+                let parsed =
+                    colon.and_then(|c| Some((parse_addr_len(&args[..c])?, &args[c + 1..])));
+                match parsed {
+                    Some(((addr, len), data)) if data.len() == len * 2 => {
+                        let written = (0..len).all(|i| {
+                            packet::hex_byte_at(data, i)
+                                .is_some_and(|b| self.write_byte(addr.wrapping_add(i as u64), b))
+                        });
+                        Some(written)
+                    }
+                    _ => Some(false),
+                }
+            }
+            b'c' | b's' | b'C' | b'S' => {
+                // シグナル付き再開のシグナルは捨てる（カーネルには配送先が無い）
+                let addr_part = if cmd == b'C' || cmd == b'S' {
+                    args.splitn(2, |&b| b == b';').nth(1)
+                } else {
+                    Some(args)
+                };
+                if let Some(addr) = addr_part.and_then(packet::parse_hex) {
+                    regs.rip = addr;
+                }
+                if !resumable {
+                    // 戻れない例外: 同じ停止をもう一度報告する
+                    self.send_stop(sig);
+                    return Action::Stay;
+                }
+                return Action::Resume {
+                    step: cmd == b's' || cmd == b'S',
+                };
+            }
+            b'Z' | b'z' if args.first() == Some(&b'0') => {
+                let addr = args
+                    .get(2..)
+                    .and_then(|rest| rest.split(|&b| b == b',').next())
+                    .and_then(packet::parse_hex);
+                Some(match addr {
+                    Some(addr) if cmd == b'Z' => self.insert_breakpoint(addr),
+                    Some(addr) => self.remove_breakpoint(addr),
+                    None => false,
+                })
+            }
+            // カーネルは終了できないので kill も切断として扱う（返信しない）
+            b'k' => {
+                self.remove_all_breakpoints();
+                return Action::Detach;
+            }
+            b'D' => {
+                self.remove_all_breakpoints();
+                action = Action::Detach;
+                Some(true)
+            }
+            b'H' | b'T' => Some(true),
+            b'q' if args.starts_with(b"Supported") => {
+                self.reply.push_str("PacketSize=");
+                self.reply.push_hex_u64(BUF_SIZE as u64);
+                None
+            }
+            b'q' if args.starts_with(b"Attached") => {
+                self.reply.push(b'1');
+                None
+            }
+            // vCont などその他は未対応（空応答）
+            _ => None,
+        };
+        if let Some(ok) = ok {
+            self.reply.push_str(if ok { "OK" } else { "E01" });
+        }
+        packet::send(&mut self.port, self.reply.as_bytes());
+        action
+    }
+
+    /// 現在のページテーブルで引けるアドレスを直接マップ経由のポインタにする
+    fn byte_ptr(addr: u64) -> Option<*mut u8> {
+        let (table, _) = x86_64::registers::control::Cr3::read();
+        let (phys, _) = crate::mem::paging::translate_addr_in_table(
+            table.start_address().as_u64(),
+            VirtAddr::try_new(addr).ok()?,
+        )?;
+        let phys_off = crate::mem::paging::physical_memory_offset()?;
+        Some(phys.as_u64().checked_add(phys_off)? as *mut u8)
+    }
+
+    /// メモリを 1 バイト読む（差し込んだ int3 は元のバイトに見せる）
+    fn read_byte(&self, addr: u64) -> Option<u8> {
+        if let Some(bp) = self
+            .breakpoints
+            .iter()
+            .find(|bp| bp.used && bp.addr == addr)
+        {
+            return Some(bp.saved);
+        }
+        // SAFETY: ページテーブルで存在を確かめた直接マップ上のアドレス
+        Self::byte_ptr(addr).map(|p| unsafe { p.read_volatile() })
+    }
+
+    /// メモリを 1 バイト書く（読み取り専用のテキストも直接マップ経由で書ける）
+    fn write_byte(&mut self, addr: u64, value: u8) -> bool {
+        if let Some(bp) = self
+            .breakpoints
+            .iter_mut()
+            .find(|bp| bp.used && bp.addr == addr)
+        {
+            bp.saved = value;
+            return true;
+        }
+        Self::poke(addr, value)
+    }
+
+    fn poke(addr: u64, value: u8) -> bool {
+        match Self::byte_ptr(addr) {
+            Some(p) => {
+                // SAFETY: byte_ptr と同じ
+                unsafe { p.write_volatile(value) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert_breakpoint(&mut self, addr: u64) -> bool {
+        if self.breakpoints.iter().any(|bp| bp.used && bp.addr == addr) {
+            return true;
+        }
+        let Some(saved) = self.read_byte(addr) else {
+            return false;
+        };
+        let Some(slot) = self.breakpoints.iter_mut().find(|bp| !bp.used) else {
+            return false;
+        };
+        if !Self::poke(addr, INT3) {
+            return false;
+        }
+        *slot = Breakpoint {
+            addr,
+            saved,
+            used: true,
+        };
+        true
+    }
+
+    fn remove_breakpoint(&mut self, addr: u64) -> bool {
+        let Some(bp) = self
+            .breakpoints
+            .iter_mut()
+            .find(|bp| bp.used && bp.addr == addr)
+        else {
+            return true;
+        };
+        bp.used = false;
+        let (addr, saved) = (bp.addr, bp.saved);
+        Self::poke(addr, saved)
+    }
+
+    fn remove_all_breakpoints(&mut self) {
+        for bp in self.breakpoints.iter_mut() {
+            if bp.used {
+                bp.used = false;
+                Self::poke(bp.addr, bp.saved);
+            }
+        }
+    }
+}
+
+/// `addr,len` を分解する
+fn parse_addr_len(args: &[u8]) -> Option<(u64, usize)> {
+    let comma = args.iter().position(|&b| b == b',')?;
+    let addr = packet::parse_hex(&args[..comma])?;
+    let len = packet::parse_hex(&args[comma + 1..])? as usize;
+    Some((addr, len))
+}
@@ -0,0 +1,184 @@
+//! GDB リモートシリアルプロトコルの枠付け
+//!
+//! 致命的な例外の中からも使うので、ヒープは使わず固定長バッファだけで扱う。
+
+use crate::util::console::SerialPort;
+
+/// 送受信バッファの大きさ（qSupported で PacketSize として通知する）
+pub const BUF_SIZE: usize = 0x400;
+
+/// 受信した 1 単位
+pub enum Incoming {
+    /// 入力バッファに `len` バイトのパケット本体を受け取った
+    Packet(usize),
+    /// Ctrl-C（0x03）
+    Interrupt,
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+/// 1 バイト届くまでポーリングで待つ（割り込み禁止のまま呼ばれる）
+fn read_byte(port: &mut SerialPort) -> u8 {
+    loop {
+        if let Some(b) = port.try_receive_byte() {
+            return b;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// パケットか割り込みを 1 つ受け取る
+///
+/// チェックサムが合わないか、バッファに収まらないパケットには `-` を返して読み直す。
+pub fn receive(port: &mut SerialPort, buf: &mut [u8; BUF_SIZE]) -> Incoming {
+    loop {
+        match read_byte(port) {
+            0x03 => return Incoming::Interrupt,
+            b'$' => {}
+            _ => continue,
+        }
+        let mut len = 0;
+        let mut sum: u8 = 0;
+        let mut overflow = false;
+        loop {
+            let b = read_byte(port);
+            if b == b'#' {
+                break;
+            }
+            sum = sum.wrapping_add(b);
+            if len < BUF_SIZE {
+                buf[len] = b;
+                len += 1;
+            } else {
+                overflow = true;
+            }
+        }
+        let hi = hex_digit(read_byte(port));
+        let lo = hex_digit(read_byte(port));
+        match (hi, lo) {
+            (Some(hi), Some(lo)) if !overflow && (hi << 4 | lo) == sum => {
+                port.send_byte(b'+');
+                return Incoming::Packet(len);
+            }
+            _ => port.send_byte(b'-'),
+        }
+    }
+}
+
+/// パケットを送り、`+` が返るまで再送する
+pub fn send(port: &mut SerialPort, data: &[u8]) {
+    let sum = data.iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+    loop {
+        port.send_byte(b'$');
+        for &b in data {
+            port.send_byte(b);
+        }
+        port.send_byte(b'#');
+        port.send_byte(HEX[(sum >> 4) as usize]);
+        port.send_byte(HEX[(sum & 0xf) as usize]);
+        loop {
+            match read_byte(port) {
+                b'+' => return,
+                b'-' => break,
+                _ => continue,
+            }
+        }
+    }
+}
+
+/// 返信を組み立てる固定長バッファ（溢れた分は捨てる）
+pub struct Reply {
+    buf: [u8; BUF_SIZE],
+    len: usize,
+}
+
+impl Reply {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; BUF_SIZE],
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn push(&mut self, b: u8) {
+        if self.len < BUF_SIZE {
+            self.buf[self.len] = b;
+            self.len += 1;
+        }
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        for &b in s.as_bytes() {
+            self.push(b);
+        }
+    }
+
+    pub fn push_hex_byte(&mut self, b: u8) {
+        self.push(HEX[(b >> 4) as usize]);
+        self.push(HEX[(b & 0xf) as usize]);
+    }
+
+    /// `value` の下位 `width` バイトをリトルエンディアンの 16 進で積む
+    pub fn push_hex_le(&mut self, value: u64, width: usize) {
+        for b in &value.to_le_bytes()[..width] {
+            self.push_hex_byte(*b);
+        }
+    }
+
+    /// 値の無いレジスタ（`width` バイト分の `xx`）
+    pub fn push_unavailable(&mut self, width: usize) {
+        for _ in 0..width * 2 {
+            self.push(b'x');
+        }
+    }
+
+    /// 最上位の 0 を省いた 16 進数
+    pub fn push_hex_u64(&mut self, value: u64) {
+        let digits = (64 - value.leading_zeros()).div_ceil(4).max(1);
+        for i in (0..digits).rev() {
+            self.push(HEX[((value >> (i * 4)) & 0xf) as usize]);
+        }
+    }
+}
+
+/// 16 進の数値（`addr,len` の各要素など）
+pub fn parse_hex(s: &[u8]) -> Option<u64> {
+    if s.is_empty() || s.len() > 16 {
+        return None;
+    }
+    s.iter()
+        .try_fold(0u64, |acc, &b| Some(acc << 4 | hex_digit(b)? as u64))
+}
+
+/// `s` の `i` 番目のバイト（16 進 2 文字）
+pub fn hex_byte_at(s: &[u8], i: usize) -> Option<u8> {
+    let hi = hex_digit(*s.get(i * 2)?)?;
+    let lo = hex_digit(*s.get(i * 2 + 1)?)?;
+    Some(hi << 4 | lo)
+}
+
+/// リトルエンディアン 16 進のレジスタ値（`width` バイトに満たなければ None）
+pub fn parse_hex_le(s: &[u8], width: usize) -> Option<u64> {
+    let mut raw = [0u8; 8];
+    for (i, slot) in raw.iter_mut().enumerate().take(width) {
+        *slot = hex_byte_at(s, i)?;
+    }
+    Some(u64::from_le_bytes(raw))
+}
@@ -0,0 +1,186 @@
+//! カーネル内テスト（`ktest` フィーチャ有効時のみ）
+//!
+//! 各モジュールの `#[cfg(feature = "ktest")] mod ktests` に `kernel_test!` で書いたテストは
+//! `.ktest` セクションに集められ、起動時（core.service を起動する前）に順に実行される。
+//! 結果は TAP 形式でシリアルに出し、最後に QEMU の isa-debug-exit
+//! （`-device isa-debug-exit,iobase=0xf4,iosize=0x04`）へ成否を書き込んで終了する。
+//! `scripts/qemu-runner.sh` は成功を終了コード 0、失敗を 1 に読み替える。
+//!
+//! 起動オプション `ktest=文字列` を付けると、名前にその文字列を含むテストだけを実行する。
+//! テスト中のパニックはそのテストの失敗として報告し、残りは実行せずに終了する。
+
+use alloc::string::String;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// isa-debug-exit の I/O ポート
+const DEBUG_EXIT_PORT: u16 = 0xf4;
+
+/// 失敗したアサーション
+pub struct Failure {
+    pub file: &'static str,
+    pub line: u32,
+    pub message: String,
+}
+
+pub type TestResult = core::result::Result<(), Failure>;
+
+/// 登録されたテスト 1 件（`kernel_test!` が `.ktest` セクションに置く）
+#[repr(C)]
+pub struct KernelTest {
+    pub name: &'static str,
+    pub run: fn() -> TestResult,
+}
+
+/// QEMU に返す終了コード（QEMU 自体は `(値 << 1) | 1` で終了する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum QemuExitCode {
+    Success = 0x10,
+    Failed = 0x11,
+}
+
+/// テストを登録する
+///
+/// ```ignore
+/// kernel_test! {
+///     fn allocates_distinct_frames() {
+///         ktest_assert!(a != b);
+///         Ok(())
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! kernel_test {
+    ($(#[$meta:meta])* fn $name:ident() $body:block) => {
+        $(#[$meta])*
+        fn $name() -> $crate::ktest::TestResult $body
+
+        const _: () = {
+            #[used]
+            #[link_section = ".ktest"]
+            static TEST: $crate::ktest::KernelTest = $crate::ktest::KernelTest {
+                name: concat!(module_path!(), "::", stringify!($name)),
+                run: $name,
+            };
+        };
+    };
+}
+
+/// テストをその場で失敗させる
+#[macro_export]
+macro_rules! ktest_fail {
+    ($($arg:tt)+) => {
+        return Err($crate::ktest::Failure {
+            file: file!(),
+            line: line!(),
+            message: alloc::format!($($arg)+),
+        })
+    };
+}
+
+/// 条件が偽ならテストを失敗させる
+#[macro_export]
+macro_rules! ktest_assert {
+    ($cond:expr) => {
+        $crate::ktest_assert!($cond, "assertion failed: {}", stringify!($cond))
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::ktest_fail!($($arg)+);
+        }
+    };
+}
+
+/// 2 つの値が等しくなければテストを失敗させる
+#[macro_export]
+macro_rules! ktest_assert_eq {
+    ($left:expr, $right:expr) => {
+        match (&$left, &$right) {
+            (left, right) => $crate::ktest_assert!(
+                *left == *right,
+                "assertion failed: {} == {} (left: {:?}, right: {:?})",
+                stringify!($left),
+                stringify!($right),
+                left,
+                right
+            ),
+        }
+    };
+}
+
+extern "C" {
+    static __ktest_start: u8;
+    static __ktest_end: u8;
+}
+
+fn registered() -> &'static [KernelTest] {
+    // SAFETY: リンカスクリプトが `.ktest` の両端に置くシンボルで、間には
+    // `kernel_test!` が置いた `KernelTest` だけが並ぶ
+    unsafe {
+        let start = (&raw const __ktest_start).cast::<KernelTest>();
+        let end = &raw const __ktest_end;
+        let len = (end as usize - start as usize) / core::mem::size_of::<KernelTest>();
+        core::slice::from_raw_parts(start, len)
+    }
+}
+
+/// 実行中のテスト（パニック時の報告用）
+static CURRENT: AtomicPtr<KernelTest> = AtomicPtr::new(core::ptr::null_mut());
+static CURRENT_NUMBER: AtomicUsize = AtomicUsize::new(0);
+
+/// 登録されたテストをすべて実行し、QEMU を終了させる（kernel_main から呼ばれ、戻らない）
+pub fn run_all() -> ! {
+    let filter = crate::init::cmdline::get("ktest").unwrap_or("");
+    let tests = registered();
+    let selected = tests.iter().filter(|t| t.name.contains(filter)).count();
+
+    crate::sprintln!("TAP version 13");
+    crate::sprintln!("1..{}", selected);
+    let mut failed = 0;
+    for (index, test) in tests.iter().filter(|t| t.name.contains(filter)).enumerate() {
+        CURRENT_NUMBER.store(index + 1, Ordering::Relaxed);
+        CURRENT.store(test as *const _ as *mut _, Ordering::Relaxed);
+        match (test.run)() {
+            Ok(()) => crate::sprintln!("ok {} - {}", index + 1, test.name),
+            Err(failure) => {
+                failed += 1;
+                crate::sprintln!("not ok {} - {}", index + 1, test.name);
+                crate::sprintln!("  # {}:{}: {}", failure.file, failure.line, failure.message);
+            }
+        }
+    }
+    CURRENT.store(core::ptr::null_mut(), Ordering::Relaxed);
+
+    crate::sprintln!("# ktest: {} passed, {} failed", selected - failed, failed);
+    exit_qemu(if failed == 0 {
+        QemuExitCode::Success
+    } else {
+        QemuExitCode::Failed
+    })
+}
+
+/// パニックハンドラから呼ばれる: テスト中なら失敗として報告して終了する
+pub fn on_panic() {
+    // SAFETY: CURRENT には `.ktest` セクション内の要素だけを入れる
+    let Some(test) = (unsafe { CURRENT.load(Ordering::Relaxed).as_ref() }) else {
+        return;
+    };
+    let number = CURRENT_NUMBER.load(Ordering::Relaxed);
+    crate::sprintln!("not ok {} - {}", number, test.name);
+    crate::sprintln!("  # panicked (remaining tests skipped)");
+    crate::sprintln!("# ktest: aborted by panic");
+    exit_qemu(QemuExitCode::Failed);
+}
+
+/// isa-debug-exit に終了コードを書き込む（デバイスが無ければ停止する）
+pub fn exit_qemu(code: QemuExitCode) -> ! {
+    // SAFETY: isa-debug-exit への書き込みは QEMU を終了させるだけ
+    unsafe {
+        x86_64::instructions::port::Port::<u32>::new(DEBUG_EXIT_PORT).write(code as u32);
+    }
+    crate::sprintln!("# ktest: isa-debug-exit not present, halting");
+    loop {
+        x86_64::instructions::interrupts::disable();
+        x86_64::instructions::hlt();
+    }
+}
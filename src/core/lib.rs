@@ -33,6 +33,9 @@ pub mod result;
 /// 監査ログ
 pub mod audit;
 
+/// 永続クラッシュログ
+pub mod crashlog;
+
 /// 割込み管理
 pub mod interrupt;
 
@@ -49,6 +52,25 @@ pub mod elf;
 /// パニックハンドラ
 pub mod panic;
 
+/// カーネル障害からの復旧
+pub mod recovery;
+
+/// カーネル GDB スタブ
+pub mod kgdb;
+
+/// スタックトレース
+pub mod backtrace;
+
+/// トレースポイントとサンプリングプロファイラ
+pub mod trace;
+
+/// カーネルウォッチドッグ
+pub mod watchdog;
+
+/// カーネル内テスト
+#[cfg(feature = "ktest")]
+pub mod ktest;
+
 /// タスク管理
 pub mod task;
 
@@ -100,6 +122,14 @@ pub struct BootInfo {
     pub rootfs_addr: u64,
     /// rootfs イメージのサイズ（バイト。通常は0）
     pub rootfs_size: usize,
+    /// 起動オプション文字列の物理アドレス（`\system\cmdline.txt`。無ければ0）
+    pub cmdline_addr: u64,
+    /// 起動オプション文字列のサイズ（バイト）
+    pub cmdline_size: usize,
+    /// 永続クラッシュログ領域の物理アドレス（予約できなければ0）
+    pub crash_log_addr: u64,
+    /// 永続クラッシュログ領域のサイズ（バイト）
+    pub crash_log_size: usize,
 }
 
 /// メモリ領域の種類
@@ -1,3 +1,9 @@
+//! カーネルヒープ
+//!
+//! `heap-sanitizer` フィーチャを有効にすると、確保・解放が `sanitizer` を通るようになり、
+//! レッドゾーン・解放済み領域の汚染・解放の保留（quarantine）で破壊を検出する。
+
+use core::alloc::{GlobalAlloc, Layout};
 use linked_list_allocator::LockedHeap;
 use x86_64::{
     structures::paging::{
@@ -11,12 +17,51 @@ pub const HEAP_START: usize = 0x_4444_4444_0000;
 /// ヒープのサイズ
 pub const HEAP_SIZE: usize = 32 * 1024 * 1024; // 32 MiB
 
+/// カーネルのグローバルアロケータ（`entry.rs` が `#[global_allocator]` にする）
+pub struct KernelHeap {
+    heap: LockedHeap,
+}
+
+impl KernelHeap {
+    pub const fn empty() -> Self {
+        Self {
+            heap: LockedHeap::empty(),
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        #[cfg(feature = "heap-sanitizer")]
+        return sanitizer::alloc(&self.heap, layout);
+        #[cfg(not(feature = "heap-sanitizer"))]
+        self.heap.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        #[cfg(feature = "heap-sanitizer")]
+        return sanitizer::dealloc(&self.heap, ptr, layout);
+        #[cfg(not(feature = "heap-sanitizer"))]
+        self.heap.dealloc(ptr, layout)
+    }
+}
+
+/// 確保中・保留中のブロックをすべて検査し、見つけた破壊の数を返す
+///
+/// `heap-sanitizer` フィーチャが無効なら何もせず 0 を返す。
+pub fn check_heap() -> usize {
+    #[cfg(feature = "heap-sanitizer")]
+    return sanitizer::check_all();
+    #[cfg(not(feature = "heap-sanitizer"))]
+    0
+}
+
 /// ヒープを初期化
 ///
 /// ## Arguments
 /// - `mapper`: 仮想アドレスと物理アドレスのマッピングを管理するオブジェクト
 /// - `frame_allocator`: 物理フレームの割り当てを管理するオブジェクト
-/// - `heap_allocator_ptr`: ヒープアロケータのロックされたヒープへのポインタ
+/// - `heap_allocator_ptr`: `KernelHeap` へのポインタ
 ///
 /// ## Returns
 /// - `Ok(())` ヒープの初期化に成功した場合
@@ -48,8 +93,8 @@ pub fn init_heap(
 
     // ヒープアロケータを初期化
     unsafe {
-        let allocator = &mut *(heap_allocator_ptr as *mut LockedHeap);
-        allocator.lock().init(HEAP_START as *mut u8, HEAP_SIZE);
+        let allocator = &*(heap_allocator_ptr as *const KernelHeap);
+        allocator.heap.lock().init(HEAP_START as *mut u8, HEAP_SIZE);
     }
 
     Ok(())
@@ -76,3 +121,457 @@ fn alloc_error_handler(layout: alloc::alloc::Layout) -> ! {
         }
     }
 }
+
+/// ヒープサニタイザ（`heap-sanitizer` フィーチャ有効時のみ）
+///
+/// 各ブロックは `[詰め物][Header（末尾が左レッドゾーン）][ユーザー領域][右レッドゾーン]` の
+/// 形で確保する。解放時はレッドゾーンを検査してからユーザー領域を `FREE_BYTE` で埋め、
+/// すぐには返さず quarantine に入れておく。quarantine から押し出すときに汚染が
+/// 書き換わっていれば解放後の書き込み（use-after-free）として報告する。
+/// 確保・解放 `CHECK_INTERVAL` 回ごとに、確保中・保留中のブロックすべてを検査する。
+///
+/// 報告には確保した箇所と解放した箇所の呼び出し元（戻りアドレス）を関数名付きで付ける。
+#[cfg(feature = "heap-sanitizer")]
+mod sanitizer {
+    use super::{HEAP_SIZE, HEAP_START};
+    use crate::interrupt::spinlock::SpinLock;
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::mem::{align_of, size_of};
+    use core::ptr;
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use linked_list_allocator::LockedHeap;
+
+    /// 右レッドゾーン・左レッドゾーンのバイト数
+    const REDZONE: usize = 32;
+    const REDZONE_BYTE: u8 = 0xfc;
+    /// 解放済み領域を埋める値
+    const FREE_BYTE: u8 = 0x6b;
+    const MAGIC_LIVE: u64 = 0x4c49_5645_4845_4150;
+    const MAGIC_FREED: u64 = 0x4652_4545_4845_4150;
+    /// 記録する呼び出し元の段数
+    const SITE_DEPTH: usize = 6;
+    /// 呼び出し元のうち読み飛ばす段数（サニタイザ自身とアロケータの入口）
+    const SITE_SKIP: usize = 2;
+    /// quarantine に保留しておくブロック数とバイト数の上限
+    const QUARANTINE_SLOTS: usize = 1024;
+    const QUARANTINE_BYTES: usize = 2 * 1024 * 1024;
+    /// この回数の確保・解放ごとに全体を検査する
+    const CHECK_INTERVAL: u64 = 4096;
+    /// 1 回の検査で表示する報告の上限
+    const MAX_REPORTS: usize = 4;
+
+    #[repr(C, align(16))]
+    struct Header {
+        magic: u64,
+        size: usize,
+        /// ブロック先頭からユーザー領域までのバイト数
+        front: usize,
+        align: usize,
+        prev: *mut Header,
+        next: *mut Header,
+        alloc_site: [u64; SITE_DEPTH],
+        free_site: [u64; SITE_DEPTH],
+        /// 左レッドゾーン（ユーザー領域の直前）
+        redzone: [u8; REDZONE],
+    }
+
+    const HEADER_SIZE: usize = size_of::<Header>();
+
+    impl Header {
+        fn user(&self) -> *mut u8 {
+            (self as *const Header as *mut u8).wrapping_add(HEADER_SIZE)
+        }
+
+        fn block(&self) -> *mut u8 {
+            self.user().wrapping_sub(self.front)
+        }
+
+        fn block_layout(&self) -> Option<Layout> {
+            Layout::from_size_align(self.front + self.size + REDZONE, self.align).ok()
+        }
+    }
+
+    /// ユーザー領域のアドレスからヘッダへ（ヒープの外や非整列なら None）
+    fn header_of(user: *mut u8) -> Option<*mut Header> {
+        let addr = (user as usize).checked_sub(HEADER_SIZE)?;
+        let in_heap = addr >= HEAP_START && addr + HEADER_SIZE <= HEAP_START + HEAP_SIZE;
+        (in_heap && addr % align_of::<Header>() == 0).then_some(addr as *mut Header)
+    }
+
+    #[derive(Clone, Copy)]
+    enum Kind {
+        /// 左レッドゾーンの書き換え
+        Underflow,
+        /// 右レッドゾーンの書き換え
+        Overflow,
+        /// 解放済み領域の書き換え
+        UseAfterFree,
+        DoubleFree,
+        /// サニタイザが確保していないアドレスの解放
+        InvalidFree,
+        /// 確保時と異なるサイズでの解放
+        LayoutMismatch,
+        /// 確保中のリストの破壊（それ以降は検査できない）
+        CorruptList,
+    }
+
+    impl Kind {
+        fn describe(self) -> &'static str {
+            match self {
+                Kind::Underflow => "heap buffer underflow (left redzone overwritten)",
+                Kind::Overflow => "heap buffer overflow (right redzone overwritten)",
+                Kind::UseAfterFree => "write after free (free poison overwritten)",
+                Kind::DoubleFree => "double free",
+                Kind::InvalidFree => "free of a pointer not allocated by the kernel heap",
+                Kind::LayoutMismatch => "free with a layout different from the allocation",
+                Kind::CorruptList => "allocation list corrupted",
+            }
+        }
+    }
+
+    /// 報告 1 件（ロックの外で表示するためにヘッダから写しておく）
+    #[derive(Clone, Copy)]
+    struct Report {
+        kind: Kind,
+        /// ユーザー領域の先頭
+        addr: usize,
+        size: usize,
+        /// 壊れていた最初のバイトのユーザー領域先頭からのオフセット
+        offset: isize,
+        alloc_site: [u64; SITE_DEPTH],
+        free_site: [u64; SITE_DEPTH],
+    }
+
+    impl Report {
+        fn new(kind: Kind, addr: usize) -> Self {
+            Self {
+                kind,
+                addr,
+                size: 0,
+                offset: 0,
+                alloc_site: [0; SITE_DEPTH],
+                free_site: [0; SITE_DEPTH],
+            }
+        }
+
+        fn from_header(kind: Kind, header: &Header, offset: isize) -> Self {
+            Self {
+                kind,
+                addr: header.user() as usize,
+                size: header.size,
+                offset,
+                alloc_site: header.alloc_site,
+                free_site: header.free_site,
+            }
+        }
+
+        fn print(&self) {
+            crate::error!("heap-sanitizer: {}", self.kind.describe());
+            crate::error!(
+                "  object {:#x} ({} bytes), first bad byte at offset {}",
+                self.addr,
+                self.size,
+                self.offset
+            );
+            if self.alloc_site[0] != 0 {
+                crate::error!("  allocated at:");
+                crate::backtrace::print_callers(&self.alloc_site);
+            }
+            if self.free_site[0] != 0 {
+                crate::error!("  freed at:");
+                crate::backtrace::print_callers(&self.free_site);
+            }
+            crate::backtrace::print_current();
+        }
+    }
+
+    struct State {
+        /// 確保中のブロック（双方向リスト）
+        live: *mut Header,
+        /// 解放を保留しているブロック（古い順のリング）
+        quarantine: [*mut Header; QUARANTINE_SLOTS],
+        head: usize,
+        len: usize,
+        quarantine_bytes: usize,
+    }
+
+    // SAFETY: ポインタはヒープ上のヘッダで、STATE のロック中にだけたどる
+    unsafe impl Send for State {}
+
+    static STATE: SpinLock<State> = SpinLock::named(
+        "HEAP_SANITIZER",
+        State {
+            live: ptr::null_mut(),
+            quarantine: [ptr::null_mut(); QUARANTINE_SLOTS],
+            head: 0,
+            len: 0,
+            quarantine_bytes: 0,
+        },
+    );
+    static OPS: AtomicU64 = AtomicU64::new(0);
+
+    fn capture_site() -> [u64; SITE_DEPTH] {
+        let mut site = [0; SITE_DEPTH];
+        crate::backtrace::collect_callers(SITE_SKIP, &mut site);
+        site
+    }
+
+    /// `bytes` のうち `value` でない最初の位置
+    fn first_mismatch(bytes: &[u8], value: u8) -> Option<usize> {
+        bytes.iter().position(|&b| b != value)
+    }
+
+    /// レッドゾーンを検査する（見つけたら次回以降に重ねて報告しないよう書き直す）
+    ///
+    /// # Safety
+    /// `header` は確保中のブロックのヘッダであること
+    unsafe fn check_redzones(header: *mut Header) -> Option<Report> {
+        let h = &mut *header;
+        if let Some(i) = first_mismatch(&h.redzone, REDZONE_BYTE) {
+            h.redzone = [REDZONE_BYTE; REDZONE];
+            return Some(Report::from_header(
+                Kind::Underflow,
+                h,
+                i as isize - REDZONE as isize,
+            ));
+        }
+        let right = core::slice::from_raw_parts_mut(h.user().add(h.size), REDZONE);
+        if let Some(i) = first_mismatch(right, REDZONE_BYTE) {
+            right.fill(REDZONE_BYTE);
+            return Some(Report::from_header(
+                Kind::Overflow,
+                h,
+                (h.size + i) as isize,
+            ));
+        }
+        None
+    }
+
+    /// 解放済みの汚染を検査する（見つけたら汚染し直す）
+    ///
+    /// # Safety
+    /// `header` は quarantine 中のブロックのヘッダであること
+    unsafe fn check_poison(header: *mut Header) -> Option<Report> {
+        let h = &*header;
+        let user = core::slice::from_raw_parts_mut(h.user(), h.size);
+        let i = first_mismatch(user, FREE_BYTE)?;
+        user.fill(FREE_BYTE);
+        Some(Report::from_header(Kind::UseAfterFree, h, i as isize))
+    }
+
+    /// ロック中に見つけた報告を溜めておき、ロックの外で表示する
+    struct Reports {
+        items: [Option<Report>; MAX_REPORTS],
+        /// 見つけた総数（表示するのは先頭の `MAX_REPORTS` 件まで）
+        found: usize,
+    }
+
+    impl Reports {
+        const fn new() -> Self {
+            Self {
+                items: [None; MAX_REPORTS],
+                found: 0,
+            }
+        }
+
+        fn push(&mut self, report: Option<Report>) {
+            let Some(report) = report else {
+                return;
+            };
+            if let Some(slot) = self.items.get_mut(self.found) {
+                *slot = Some(report);
+            }
+            self.found += 1;
+        }
+
+        fn print(&self) {
+            for report in self.items.iter().flatten() {
+                report.print();
+            }
+            if self.found > MAX_REPORTS {
+                crate::error!(
+                    "heap-sanitizer: {} more report(s) suppressed",
+                    self.found - MAX_REPORTS
+                );
+            }
+        }
+    }
+
+    /// quarantine の古いものから、件数・バイト数が上限に収まるまで本当に解放する
+    ///
+    /// # Safety
+    /// `state` は STATE のロック中に得たものであること
+    unsafe fn evict(
+        heap: &LockedHeap,
+        state: &mut State,
+        max_len: usize,
+        max_bytes: usize,
+        reports: &mut Reports,
+    ) {
+        while state.len > max_len || (state.len > 0 && state.quarantine_bytes > max_bytes) {
+            let header = state.quarantine[state.head];
+            state.head = (state.head + 1) % QUARANTINE_SLOTS;
+            state.len -= 1;
+            let h = &*header;
+            state.quarantine_bytes -= h.size;
+            reports.push(check_poison(header));
+            if let Some(layout) = h.block_layout() {
+                heap.dealloc(h.block(), layout);
+            }
+        }
+    }
+
+    /// 確保・解放の回数を数え、`CHECK_INTERVAL` 回ごとに全体を検査する
+    fn tick() {
+        if OPS.fetch_add(1, Ordering::Relaxed) % CHECK_INTERVAL == CHECK_INTERVAL - 1 {
+            check_all();
+        }
+    }
+
+    pub unsafe fn alloc(heap: &LockedHeap, layout: Layout) -> *mut u8 {
+        let align = layout.align().max(align_of::<Header>());
+        let front = (HEADER_SIZE + align - 1) & !(align - 1);
+        let Some(block_layout) = front
+            .checked_add(layout.size())
+            .and_then(|n| n.checked_add(REDZONE))
+            .and_then(|total| Layout::from_size_align(total, align).ok())
+        else {
+            return ptr::null_mut();
+        };
+        let mut block = heap.alloc(block_layout);
+        if block.is_null() {
+            // 保留中のブロックをすべて返してから一度だけやり直す
+            let mut reports = Reports::new();
+            evict(heap, &mut STATE.lock(), 0, 0, &mut reports);
+            reports.print();
+            block = heap.alloc(block_layout);
+            if block.is_null() {
+                return block;
+            }
+        }
+
+        let user = block.add(front);
+        let header = user.sub(HEADER_SIZE) as *mut Header;
+        ptr::write_bytes(block, REDZONE_BYTE, front - HEADER_SIZE);
+        header.write(Header {
+            magic: MAGIC_LIVE,
+            size: layout.size(),
+            front,
+            align,
+            prev: ptr::null_mut(),
+            next: ptr::null_mut(),
+            alloc_site: capture_site(),
+            free_site: [0; SITE_DEPTH],
+            redzone: [REDZONE_BYTE; REDZONE],
+        });
+        ptr::write_bytes(user.add(layout.size()), REDZONE_BYTE, REDZONE);
+
+        {
+            let mut state = STATE.lock();
+            (*header).next = state.live;
+            if !state.live.is_null() {
+                (*state.live).prev = header;
+            }
+            state.live = header;
+        }
+        tick();
+        user
+    }
+
+    pub unsafe fn dealloc(heap: &LockedHeap, ptr: *mut u8, layout: Layout) {
+        let Some(header) = header_of(ptr) else {
+            Report::new(Kind::InvalidFree, ptr as usize).print();
+            return;
+        };
+        let free_site = capture_site();
+        let mut reports = Reports::new();
+        {
+            let mut state = STATE.lock();
+            let h = &mut *header;
+            match h.magic {
+                MAGIC_LIVE => {}
+                MAGIC_FREED => reports.push(Some(Report::from_header(Kind::DoubleFree, h, 0))),
+                _ => reports.push(Some(Report::new(Kind::InvalidFree, ptr as usize))),
+            }
+            if reports.found != 0 {
+                // 二重解放・不正な解放は無視する（リストや quarantine を壊さないため）
+                drop(state);
+                reports.print();
+                return;
+            }
+            if h.size != layout.size() {
+                reports.push(Some(Report::from_header(
+                    Kind::LayoutMismatch,
+                    h,
+                    layout.size() as isize - h.size as isize,
+                )));
+            }
+            reports.push(check_redzones(header));
+
+            // 確保中のリストから外す
+            if h.prev.is_null() {
+                state.live = h.next;
+            } else {
+                (*h.prev).next = h.next;
+            }
+            if !h.next.is_null() {
+                (*h.next).prev = h.prev;
+            }
+            h.magic = MAGIC_FREED;
+            h.prev = ptr::null_mut();
+            h.next = ptr::null_mut();
+            h.free_site = free_site;
+            ptr::write_bytes(ptr, FREE_BYTE, h.size);
+
+            evict(
+                heap,
+                &mut state,
+                QUARANTINE_SLOTS - 1,
+                usize::MAX,
+                &mut reports,
+            );
+            let slot = (state.head + state.len) % QUARANTINE_SLOTS;
+            state.quarantine[slot] = header;
+            state.len += 1;
+            state.quarantine_bytes += h.size;
+            evict(
+                heap,
+                &mut state,
+                QUARANTINE_SLOTS,
+                QUARANTINE_BYTES,
+                &mut reports,
+            );
+        }
+        reports.print();
+        tick();
+    }
+
+    /// 確保中・保留中のブロックをすべて検査し、見つけた破壊の数を返す
+    pub fn check_all() -> usize {
+        let mut reports = Reports::new();
+        {
+            let state = STATE.lock();
+            // SAFETY: リストと quarantine のヘッダは STATE のロック中は解放されない。
+            // リストのポインタはヒープ内・整列・マジックを確かめてからたどる
+            unsafe {
+                let mut cur = state.live;
+                while !cur.is_null() {
+                    let valid = header_of((cur as *mut u8).wrapping_add(HEADER_SIZE))
+                        .is_some_and(|h| (*h).magic == MAGIC_LIVE);
+                    if !valid {
+                        reports.push(Some(Report::new(Kind::CorruptList, cur as usize)));
+                        break;
+                    }
+                    reports.push(check_redzones(cur));
+                    cur = (*cur).next;
+                }
+                for i in 0..state.len {
+                    let header = state.quarantine[(state.head + i) % QUARANTINE_SLOTS];
+                    reports.push(check_poison(header));
+                }
+            }
+        }
+        reports.print();
+        reports.found
+    }
+}
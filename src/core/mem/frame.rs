@@ -284,3 +284,45 @@ mod tests {
         }
     }
 }
+
+#[cfg(feature = "ktest")]
+mod ktests {
+    use super::*;
+    use crate::{kernel_test, ktest_assert, ktest_assert_eq, ktest_fail};
+
+    kernel_test! {
+        fn allocates_distinct_usable_frames() {
+            let (Ok(a), Ok(b)) = (allocate_frame(), allocate_frame()) else {
+                ktest_fail!("allocate_frame failed");
+            };
+            ktest_assert!(a != b);
+            ktest_assert!(is_usable_physical_address(a.start_address().as_u64()));
+            ktest_assert!(is_usable_physical_address(b.start_address().as_u64()));
+            ktest_assert!(deallocate_frame(a).is_ok());
+            ktest_assert!(deallocate_frame(b).is_ok());
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn reuses_freed_frame_first() {
+            let Ok(frame) = allocate_frame() else {
+                ktest_fail!("allocate_frame failed");
+            };
+            ktest_assert!(deallocate_frame(frame).is_ok());
+            let again = allocate_frame().ok();
+            ktest_assert_eq!(again, Some(frame));
+            ktest_assert!(deallocate_frame(frame).is_ok());
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn rejects_unusable_frame() {
+            let low = PhysFrame::containing_address(PhysAddr::new(0));
+            ktest_assert!(!is_usable_physical_address(0));
+            ktest_assert!(deallocate_frame(low).is_err());
+            Ok(())
+        }
+    }
+}
@@ -11,16 +11,14 @@ use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector
 
 /// ダブルフォルト用ISTインデックス（TSSと同じ値を使用）
 pub const DOUBLE_FAULT_IST_INDEX: u16 = tss::DOUBLE_FAULT_IST_INDEX;
+/// NMI 用ISTインデックス（TSSと同じ値を使用）
+pub const NMI_IST_INDEX: u16 = tss::NMI_IST_INDEX;
 
 static GDT: Once<(GlobalDescriptorTable, Selectors)> = Once::new();
 
 fn halt_on_missing_gdt(which: &'static str) -> ! {
-    crate::audit::log(crate::audit::AuditEventKind::Fault, which);
     crate::warn!("GDT not initialized");
-    x86_64::instructions::interrupts::disable();
-    loop {
-        x86_64::instructions::hlt();
-    }
+    crate::recovery::fatal(format_args!("{}", which));
 }
 
 /// GDTセレクタ
@@ -10,6 +10,7 @@ pub mod gdt;
 pub mod paging;
 pub mod tss;
 pub(crate) mod user;
+pub mod vdso;
 
 /// メモリの初期化
 ///
@@ -103,6 +104,17 @@ pub fn init(boot_info: &'static crate::BootInfo) -> Result<()> {
         return Err(crate::Kernel::Memory(crate::result::Memory::InvalidAddress));
     }
 
+    drop(frame_alloc_lock);
+    drop(page_table_lock);
+    if let Err(e) = vdso::init() {
+        crate::warn!("vDSO initialization failed: {:?}", e);
+        crate::audit::log(
+            crate::audit::AuditEventKind::Fault,
+            "memory init vdso initialization failed",
+        );
+        return Err(e);
+    }
+
     crate::debug!("Heap initialized, disabling PIT");
     // PITを停止してからPICを初期化
     interrupt::disable_pit();
@@ -534,6 +534,40 @@ pub fn copy_to_user_in_table(table_phys: u64, dst_ptr: u64, src: &[u8]) -> Resul
     Ok(())
 }
 
+/// デバッガ（ptrace）用の書き込み
+///
+/// `copy_to_user_in_table` と違い WRITABLE でないページ（テキスト）にも書けるので、
+/// ブレークポイントの int3 を埋め込める。全プロセスで共有する vDSO には書かせない。
+pub fn poke_user_in_table(table_phys: u64, dst_ptr: u64, src: &[u8]) -> Result<()> {
+    if src.is_empty() {
+        return Ok(());
+    }
+    let phys_off = physical_memory_offset().ok_or(Kernel::Memory(Memory::NotMapped))?;
+    let _smap_guard = crate::cpu::SmapSmepGuard::new();
+
+    let mut copied = 0usize;
+    while copied < src.len() {
+        let cur = dst_ptr
+            .checked_add(copied as u64)
+            .ok_or(Kernel::Memory(Memory::InvalidAddress))?;
+        let (phys, page_off) = translate_user_addr_in_table(table_phys, cur, false)
+            .ok_or(Kernel::Memory(Memory::InvalidAddress))?;
+        if super::vdso::is_vdso_frame(phys) {
+            return Err(Kernel::Memory(Memory::PermissionDenied));
+        }
+        let chunk = core::cmp::min(4096usize.saturating_sub(page_off), src.len() - copied);
+        let dst = phys
+            .checked_add(phys_off)
+            .ok_or(Kernel::Memory(Memory::InvalidAddress))? as *mut u8;
+        unsafe {
+            core::ptr::copy_nonoverlapping(src[copied..].as_ptr(), dst, chunk);
+        }
+        copied += chunk;
+    }
+
+    Ok(())
+}
+
 /// 指定したページテーブルでユーザー範囲がすべて有効にマップされているか確認する
 pub fn is_user_range_mapped_in_table(table_phys: u64, addr: u64, len: u64) -> bool {
     if addr > USER_SPACE_END {
@@ -903,6 +937,12 @@ pub fn create_user_page_table() -> Result<u64> {
         );
     }
 
+    // 全プロセス共通の vDSO をマップ
+    if let Err(e) = super::vdso::map_into(new_l4_phys) {
+        let _ = destroy_user_page_table(new_l4_phys);
+        return Err(e);
+    }
+
     Ok(new_l4_phys)
 }
 
@@ -973,6 +1013,10 @@ pub fn clone_user_page_table(src_table_phys: u64) -> Result<u64> {
                     {
                         continue;
                     }
+                    // vDSO は create_user_page_table() で共有フレームをマップ済み
+                    if super::vdso::is_vdso_frame(pte.addr().as_u64()) {
+                        continue;
+                    }
 
                     let vaddr = ((l4i as u64) << 39)
                         | ((l3i as u64) << 30)
@@ -1301,7 +1345,6 @@ pub fn map_and_copy_segment_to(
 
         // 直接ページテーブルにエントリを作成（テンポラリマッピング経由で）
         
-
         crate::debug!(
             "Mapping page {:#x} (L4[{}]->L3[{}]->L2[{}]->L1[{}])",
             page_addr,
@@ -1353,7 +1396,10 @@ pub fn map_and_copy_segment_to(
                 let existing_write = existing_flags.contains(Flags::WRITABLE);
                 let new_exec = !final_flags.contains(Flags::NO_EXECUTE);
                 let new_write = final_flags.contains(Flags::WRITABLE);
-                if (existing_exec && new_write) || (existing_write && new_exec) {
+                if (existing_exec && new_write)
+                    || (existing_write && new_exec)
+                    || super::vdso::is_vdso_frame(existing_addr)
+                {
                     frame::deallocate_frame(frame);
                     return Err(Kernel::Memory(Memory::PermissionDenied));
                 }
@@ -1452,6 +1498,10 @@ pub fn protect_user_range_in_table(
         {
             return Err(Kernel::Memory(Memory::NotMapped));
         }
+        // 共有している vDSO の保護は変えさせない
+        if virt_to_phys_in_table(table_phys, page_addr).is_some_and(super::vdso::is_vdso_frame) {
+            return Err(Kernel::Memory(Memory::PermissionDenied));
+        }
 
         let mut new_flags = existing_flags;
         new_flags
@@ -1512,7 +1562,9 @@ pub fn unmap_range_in_table(table_phys: u64, addr: u64, length: u64) -> Result<(
         let page = Page::<Size4KiB>::containing_address(VirtAddr::new(page_addr));
         if let Ok((frame, flush)) = pt.unmap(page) {
             flush.ignore();
-            let _ = frame::deallocate_frame(frame);
+            if !super::vdso::is_vdso_frame(frame.start_address().as_u64()) {
+                let _ = frame::deallocate_frame(frame);
+            }
         }
         page_addr += 4096;
     }
@@ -1571,7 +1623,10 @@ fn destroy_user_l1_table(l1_phys: u64, phys_off: u64) {
         {
             continue;
         }
-        deallocate_4k_frame_by_phys(entry.addr().as_u64());
+        // vDSO のフレームは全プロセスで共有しているので解放しない
+        if !super::vdso::is_vdso_frame(entry.addr().as_u64()) {
+            deallocate_4k_frame_by_phys(entry.addr().as_u64());
+        }
         l1[i].set_unused();
     }
     deallocate_4k_frame_by_phys(l1_phys);
@@ -1718,6 +1773,42 @@ pub fn map_physical_range_to_user(
     Ok(())
 }
 
+/// 共有フレームを読み取り専用でユーザー空間へマップする（vDSO 用）
+///
+/// フレームの所有権は移らない。`executable` が false なら NX を付ける。
+///
+/// ## Arguments
+/// * `table_phys` - ユーザープロセスの L4 ページテーブルの物理アドレス
+/// * `virt_addr`  - マップ先の仮想アドレス (4KiB アライン済み)
+/// * `phys_addr`  - マップ元の物理アドレス (4KiB アライン済み)
+/// * `executable` - 実行可能にするか
+pub fn map_shared_frame_in_table(
+    table_phys: u64,
+    virt_addr: u64,
+    phys_addr: u64,
+    executable: bool,
+) -> Result<()> {
+    map_page_in_table(table_phys, virt_addr, phys_addr, false, true)?;
+    if executable {
+        return Ok(());
+    }
+
+    let phys_off = physical_memory_offset().ok_or(Kernel::Memory(Memory::NotMapped))?;
+    // Ensure SMAP/SMEP disabled while dereferencing HHDM pointers
+    let _smap_guard = crate::cpu::SmapSmepGuard::new();
+    let l4 = unsafe { &mut *((table_phys + phys_off) as *mut PageTable) };
+    let mut pt = unsafe { OffsetPageTable::new(l4, VirtAddr::new(phys_off)) };
+    let page = Page::<Size4KiB>::containing_address(VirtAddr::new(virt_addr));
+    let flags =
+        PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE | PageTableFlags::NO_EXECUTE;
+    unsafe {
+        pt.update_flags(page, flags)
+            .map_err(|_| Kernel::Memory(Memory::InvalidAddress))?
+            .ignore();
+    }
+    Ok(())
+}
+
 /// CR3を指定した物理アドレスのページテーブルに切り替える
 ///
 /// ## Arguments
@@ -1752,6 +1843,83 @@ pub fn virt_to_phys_in_table(table_phys: u64, virt_addr: u64) -> Option<u64> {
     translate_addr_in_table(table_phys, VirtAddr::new(virt_addr)).map(|(p, _)| p.as_u64())
 }
 
+/// ユーザー空間のマップ済み連続領域（保護属性が同じページをまとめたもの）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserRegion {
+    pub start: u64,
+    pub len: u64,
+    pub writable: bool,
+    pub executable: bool,
+}
+
+/// 指定したページテーブルのユーザー領域を低アドレス順に列挙する（コアダンプ用）
+///
+/// 隣接していて書き込み/実行属性が同じ 4KiB ページは 1 つの領域にまとめる。
+pub fn user_regions_in_table(table_phys: u64) -> Result<alloc::vec::Vec<UserRegion>> {
+    use x86_64::structures::paging::PageTableFlags as Flags;
+
+    let phys_off = physical_memory_offset().ok_or(Kernel::Memory(Memory::NotMapped))?;
+    let _smap_guard = crate::cpu::SmapSmepGuard::new();
+
+    let mut regions: alloc::vec::Vec<UserRegion> = alloc::vec::Vec::new();
+    let l4 = unsafe { &*((table_phys + phys_off) as *const PageTable) };
+    for l4i in 0usize..256 {
+        let l4e = &l4[l4i];
+        if l4e.is_unused() || !l4e.flags().contains(Flags::PRESENT) {
+            continue;
+        }
+        let l3 = unsafe { &*((l4e.addr().as_u64() + phys_off) as *const PageTable) };
+        for l3i in 0usize..512 {
+            let l3e = &l3[l3i];
+            if l3e.is_unused()
+                || !l3e.flags().contains(Flags::PRESENT)
+                || l3e.flags().contains(Flags::HUGE_PAGE)
+            {
+                continue;
+            }
+            let l2 = unsafe { &*((l3e.addr().as_u64() + phys_off) as *const PageTable) };
+            for l2i in 0usize..512 {
+                let l2e = &l2[l2i];
+                if l2e.is_unused()
+                    || !l2e.flags().contains(Flags::PRESENT)
+                    || l2e.flags().contains(Flags::HUGE_PAGE)
+                {
+                    continue;
+                }
+                let l1 = unsafe { &*((l2e.addr().as_u64() + phys_off) as *const PageTable) };
+                for l1i in 0usize..512 {
+                    let flags = l1[l1i].flags();
+                    if !flags.contains(Flags::PRESENT) || !flags.contains(Flags::USER_ACCESSIBLE) {
+                        continue;
+                    }
+                    let vaddr = ((l4i as u64) << 39)
+                        | ((l3i as u64) << 30)
+                        | ((l2i as u64) << 21)
+                        | ((l1i as u64) << 12);
+                    let writable = flags.contains(Flags::WRITABLE);
+                    let executable = !flags.contains(Flags::NO_EXECUTE);
+                    match regions.last_mut() {
+                        Some(last)
+                            if last.start + last.len == vaddr
+                                && last.writable == writable
+                                && last.executable == executable =>
+                        {
+                            last.len += 4096;
+                        }
+                        _ => regions.push(UserRegion {
+                            start: vaddr,
+                            len: 4096,
+                            writable,
+                            executable,
+                        }),
+                    }
+                }
+            }
+        }
+    }
+    Ok(regions)
+}
+
 /// 指定したページテーブルに物理ページをマップする
 ///
 /// # Arguments
@@ -1988,3 +2156,71 @@ pub fn unmap_page_in_table(table_phys: u64, virt_addr: u64) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(feature = "ktest")]
+mod ktests {
+    use super::*;
+    use crate::{kernel_test, ktest_assert, ktest_assert_eq, ktest_fail};
+
+    const TEST_VIRT: u64 = 0x4000_0000;
+
+    kernel_test! {
+        fn maps_translates_and_unmaps_user_page() {
+            let Ok(table) = create_user_page_table() else {
+                ktest_fail!("create_user_page_table failed");
+            };
+            let Ok(frame) = frame::allocate_frame() else {
+                ktest_fail!("allocate_frame failed");
+            };
+            let phys = frame.start_address().as_u64();
+
+            let mapped = map_page_in_table(table, TEST_VIRT, phys, true, true).is_ok();
+            let translated = virt_to_phys_in_table(table, TEST_VIRT + 0x123);
+            let copied = copy_to_user_in_table(table, TEST_VIRT + 8, b"ktest").is_ok();
+            let mut back = [0u8; 5];
+            let read = copy_from_user_in_table(table, TEST_VIRT + 8, &mut back).is_ok();
+            let wx = protect_user_range_in_table(table, TEST_VIRT, 4096, true, true, true);
+            let ro = protect_user_range_in_table(table, TEST_VIRT, 4096, true, false, false);
+            let unmapped = unmap_page_in_table(table, TEST_VIRT).is_ok();
+            let after = virt_to_phys_in_table(table, TEST_VIRT);
+
+            // 後始末: フレームは外してから自分で返し、テーブルだけを破棄する
+            let _ = frame::deallocate_frame(frame);
+            let _ = destroy_user_page_table(table);
+
+            ktest_assert!(mapped);
+            ktest_assert_eq!(translated, Some(phys + 0x123));
+            ktest_assert!(copied && read);
+            ktest_assert_eq!(&back, b"ktest");
+            ktest_assert!(wx.is_err(), "W+X mapping must be refused");
+            ktest_assert!(ro.is_ok());
+            ktest_assert!(unmapped);
+            ktest_assert_eq!(after, None);
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn rejects_misaligned_and_kernel_addresses() {
+            let Ok(table) = create_user_page_table() else {
+                ktest_fail!("create_user_page_table failed");
+            };
+            let misaligned = map_page_in_table(table, TEST_VIRT + 1, 0x1000, true, true);
+            let unmapped = virt_to_phys_in_table(table, TEST_VIRT);
+            let kernel = protect_user_range_in_table(
+                table,
+                USER_SPACE_END + 1,
+                4096,
+                true,
+                false,
+                false,
+            );
+            let _ = destroy_user_page_table(table);
+
+            ktest_assert!(misaligned.is_err());
+            ktest_assert_eq!(unmapped, None);
+            ktest_assert!(kernel.is_err());
+            Ok(())
+        }
+    }
+}
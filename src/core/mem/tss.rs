@@ -9,6 +9,8 @@ use x86_64::VirtAddr;
 
 /// ダブルフォルト用ISTインデックス
 pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+/// NMI 用ISTインデックス（SYSCALL 直後のユーザースタック上で受けないようにする）
+pub const NMI_IST_INDEX: u16 = 1;
 
 static TSS: Once<TaskStateSegment> = Once::new();
 
@@ -38,6 +40,15 @@ pub fn init() -> &'static TaskStateSegment {
             stack_end
         };
 
+        // NMI 用の専用スタックを設定
+        tss.interrupt_stack_table[NMI_IST_INDEX as usize] = {
+            const STACK_SIZE: usize = 4096 * 4;
+            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+
+            let stack_start = VirtAddr::from_ptr(unsafe { &raw const STACK });
+            stack_start + STACK_SIZE as u64
+        };
+
         // ユーザーモードからカーネルモードへの遷移用のRing0スタックを設定
         tss.privilege_stack_table[0] = {
             const RING0_STACK_SIZE: usize = 4096 * 32; // 128KB (増量: 16KB→128KB)
@@ -0,0 +1,376 @@
+//! vDSO（全ユーザープロセスへ共有マップするカーネル提供ページ）
+//!
+//! 2 ページ構成:
+//! - `base`: vvar。カーネルがタイマー割り込みごとに更新する時刻データ（ユーザーは読み取り専用・NX）
+//! - `base + 0x1000`: 最小限の ELF イメージ（読み取り・実行のみ）
+//!
+//! ELF イメージは次のシンボルを動的シンボル表で公開する:
+//! - `__vdso_clock_gettime` / `__vdso_gettimeofday` / `__vdso_getcpu`
+//! - `__vdso_rt_sigreturn`: シグナルハンドラの戻り先。sa_restorer に関係なく常にここへ戻る
+//!
+//! ユーザーには AT_SYSINFO_EHDR で ELF イメージの先頭が渡される。
+//! フレームは全プロセスで共有するため、ページテーブル破棄・munmap で解放せず、
+//! mprotect / mmap での上書きも拒否する（`is_vdso_frame`）。
+
+use super::{frame, paging};
+use crate::result::{Kernel, Memory, Result};
+use crate::syscall::SyscallNumber;
+use core::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+
+const PAGE_SIZE: u64 = 4096;
+/// vDSO を置く領域の下限（exec のユーザースタック上端より上）
+const VDSO_REGION_BASE: u64 = 0x0000_7FFF_FFF8_0000;
+/// 起動ごとにずらす範囲（ページ数）
+const VDSO_ASLR_PAGES: u64 = 0x70;
+/// vvar + ELF イメージ
+pub const VDSO_SIZE: u64 = 2 * PAGE_SIZE;
+
+/// 1 ティックのナノ秒数（PIT 100Hz）
+const TICK_NS: u64 = 10_000_000;
+
+/// vvar ページの先頭に置く時刻データ
+///
+/// vDSO のコードは `seq` が偶数かつ読み取り前後で一致するまで読み直す。
+#[repr(C)]
+struct VvarData {
+    /// シーケンスカウンタ（奇数なら更新中）
+    seq: AtomicU32,
+    _pad: u32,
+    /// 起動からのティック数
+    ticks: AtomicU64,
+    /// 1 ティックのナノ秒数
+    tick_ns: AtomicU64,
+}
+
+/// vvar の先頭ユーザー仮想アドレス（0 = 未初期化）
+static VDSO_BASE: AtomicU64 = AtomicU64::new(0);
+static VVAR_PHYS: AtomicU64 = AtomicU64::new(0);
+static TEXT_PHYS: AtomicU64 = AtomicU64::new(0);
+/// vvar のカーネル仮想アドレス（物理メモリオフセット経由）
+static VVAR_VIRT: AtomicU64 = AtomicU64::new(0);
+/// ELF イメージ内の `__vdso_rt_sigreturn` のオフセット
+static SIGRETURN_OFFSET: AtomicU64 = AtomicU64::new(0);
+
+// vDSO のコード本体。カーネルでは実行しないので .rodata に置き、
+// init() で ELF イメージの CODE_OFFSET 以降へコピーする。
+// ELF イメージは 1 ページに収まるので、RIP のページ先頭 - 0x1000 が vvar になる。
+core::arch::global_asm!(
+    ".pushsection .rodata.mochios_vdso, \"a\"",
+    ".balign 16",
+    ".global mochios_vdso_code_start",
+    "mochios_vdso_code_start:",
+    // int clock_gettime(clockid_t clk, struct timespec *ts)
+    // CLOCK_REALTIME / CLOCK_MONOTONIC 以外と ts == NULL はシステムコールへ回す
+    ".global mochios_vdso_clock_gettime",
+    "mochios_vdso_clock_gettime:",
+    "cmp edi, 1",
+    "ja 3f",
+    "test rsi, rsi",
+    "jz 3f",
+    "call 10f",
+    "xor edx, edx",
+    "mov r8d, 1000000000",
+    "div r8",
+    "mov qword ptr [rsi], rax",
+    "mov qword ptr [rsi + 8], rdx",
+    "xor eax, eax",
+    "ret",
+    "3:",
+    "mov eax, {clock_gettime}",
+    "int 0x80",
+    "ret",
+    // int gettimeofday(struct timeval *tv, struct timezone *tz)
+    ".global mochios_vdso_gettimeofday",
+    "mochios_vdso_gettimeofday:",
+    "test rsi, rsi",
+    "jz 4f",
+    "mov qword ptr [rsi], 0",
+    "4:",
+    "test rdi, rdi",
+    "jz 5f",
+    "call 10f",
+    "xor edx, edx",
+    "mov r8d, 1000",
+    "div r8",
+    "xor edx, edx",
+    "mov r8d, 1000000",
+    "div r8",
+    "mov qword ptr [rdi], rax",
+    "mov qword ptr [rdi + 8], rdx",
+    "5:",
+    "xor eax, eax",
+    "ret",
+    // int getcpu(unsigned *cpu, unsigned *node, void *cache)
+    // カーネルの current_cpu_id() と同じく CPUID の初期 APIC ID を返す
+    ".global mochios_vdso_getcpu",
+    "mochios_vdso_getcpu:",
+    "push rbx",
+    "mov eax, 1",
+    "xor ecx, ecx",
+    "cpuid",
+    "shr ebx, 24",
+    "test rdi, rdi",
+    "jz 6f",
+    "mov dword ptr [rdi], ebx",
+    "6:",
+    "test rsi, rsi",
+    "jz 7f",
+    "mov dword ptr [rsi], 0",
+    "7:",
+    "pop rbx",
+    "xor eax, eax",
+    "ret",
+    // シグナルハンドラの戻り先
+    ".global mochios_vdso_rt_sigreturn",
+    "mochios_vdso_rt_sigreturn:",
+    "mov eax, {rt_sigreturn}",
+    "int 0x80",
+    "ud2",
+    // rax = 起動からのナノ秒（破壊: rcx, rdx）
+    "10:",
+    "lea rcx, [rip]",
+    "and rcx, -4096",
+    "sub rcx, 4096",
+    "11:",
+    "mov edx, dword ptr [rcx]",
+    "test edx, 1",
+    "jnz 12f",
+    "mov rax, qword ptr [rcx + 8]",
+    "imul rax, qword ptr [rcx + 16]",
+    "cmp edx, dword ptr [rcx]",
+    "jne 11b",
+    "ret",
+    "12:",
+    "pause",
+    "jmp 11b",
+    ".global mochios_vdso_code_end",
+    "mochios_vdso_code_end:",
+    ".popsection",
+    clock_gettime = const SyscallNumber::ClockGettime as u64,
+    rt_sigreturn = const SyscallNumber::RtSigreturn as u64,
+);
+
+unsafe extern "C" {
+    static mochios_vdso_code_start: u8;
+    static mochios_vdso_code_end: u8;
+    static mochios_vdso_clock_gettime: u8;
+    static mochios_vdso_gettimeofday: u8;
+    static mochios_vdso_getcpu: u8;
+    static mochios_vdso_rt_sigreturn: u8;
+}
+
+// ---- ELF イメージのレイアウト（すべてページ先頭からのオフセット = 仮想アドレス） ----
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+const PHDR_OFFSET: usize = EHDR_SIZE;
+const PHDR_COUNT: usize = 2;
+const DYN_OFFSET: usize = PHDR_OFFSET + PHDR_SIZE * PHDR_COUNT;
+const DYN_COUNT: usize = 6;
+const HASH_OFFSET: usize = DYN_OFFSET + 16 * DYN_COUNT;
+const SYM_COUNT: usize = 1 + VDSO_SYMBOLS.len();
+const SYM_SIZE: usize = 24;
+const SYM_OFFSET: usize = (HASH_OFFSET + 4 * (3 + SYM_COUNT) + 7) & !7;
+const STR_OFFSET: usize = SYM_OFFSET + SYM_SIZE * SYM_COUNT;
+const CODE_OFFSET: usize = 0x200;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PF_X: u32 = 0x1;
+const PF_R: u32 = 0x4;
+const DT_NULL: u64 = 0;
+const DT_HASH: u64 = 4;
+const DT_STRTAB: u64 = 5;
+const DT_SYMTAB: u64 = 6;
+const DT_STRSZ: u64 = 10;
+const DT_SYMENT: u64 = 11;
+/// STB_GLOBAL << 4 | STT_FUNC
+const SYM_INFO_GLOBAL_FUNC: u8 = 0x12;
+
+/// 公開するシンボル名
+const VDSO_SYMBOLS: [&str; 4] = [
+    "__vdso_clock_gettime",
+    "__vdso_gettimeofday",
+    "__vdso_getcpu",
+    "__vdso_rt_sigreturn",
+];
+
+/// vDSO を初期化する（フレームアロケータとヒープの初期化後に一度だけ呼ぶ）
+pub fn init() -> Result<()> {
+    let phys_off = paging::physical_memory_offset().ok_or(Kernel::Memory(Memory::NotMapped))?;
+    let vvar_phys = frame::allocate_frame()?.start_address().as_u64();
+    let text_phys = frame::allocate_frame()?.start_address().as_u64();
+
+    let _smap_guard = crate::cpu::SmapSmepGuard::new();
+    let vvar = unsafe { &mut *((vvar_phys + phys_off) as *mut [u8; PAGE_SIZE as usize]) };
+    let text = unsafe { &mut *((text_phys + phys_off) as *mut [u8; PAGE_SIZE as usize]) };
+    vvar.fill(0);
+    text.fill(0);
+
+    let (code, offsets) = unsafe {
+        let start = &raw const mochios_vdso_code_start as usize;
+        let end = &raw const mochios_vdso_code_end as usize;
+        let code = core::slice::from_raw_parts(start as *const u8, end - start);
+        let offsets = [
+            &raw const mochios_vdso_clock_gettime as usize - start,
+            &raw const mochios_vdso_gettimeofday as usize - start,
+            &raw const mochios_vdso_getcpu as usize - start,
+            &raw const mochios_vdso_rt_sigreturn as usize - start,
+        ];
+        (code, offsets)
+    };
+    build_image(text, code, &offsets)?;
+
+    let vvar_data = unsafe { &*((vvar_phys + phys_off) as *const VvarData) };
+    vvar_data.tick_ns.store(TICK_NS, Ordering::Relaxed);
+    vvar_data
+        .ticks
+        .store(crate::interrupt::timer::get_ticks(), Ordering::Relaxed);
+
+    let slot = (crate::cpu::boot_entropy_u64() ^ crate::cpu::hw_random_u64().unwrap_or(0))
+        % VDSO_ASLR_PAGES;
+    let base = VDSO_REGION_BASE + slot * PAGE_SIZE;
+
+    VVAR_PHYS.store(vvar_phys, Ordering::Relaxed);
+    TEXT_PHYS.store(text_phys, Ordering::Relaxed);
+    SIGRETURN_OFFSET.store((CODE_OFFSET + offsets[3]) as u64, Ordering::Relaxed);
+    VVAR_VIRT.store(vvar_phys + phys_off, Ordering::Release);
+    VDSO_BASE.store(base, Ordering::Release);
+    crate::debug!("vDSO initialized at {:#x}", base);
+    Ok(())
+}
+
+/// ELF イメージを組み立てる
+fn build_image(text: &mut [u8], code: &[u8], offsets: &[usize; 4]) -> Result<()> {
+    let mut strtab = [0u8; CODE_OFFSET - STR_OFFSET];
+    let mut name_offsets = [0u32; VDSO_SYMBOLS.len()];
+    let mut str_len = 1;
+    for (i, name) in VDSO_SYMBOLS.iter().enumerate() {
+        let end = str_len + name.len() + 1;
+        if end > strtab.len() {
+            return Err(Kernel::Memory(Memory::OutOfMemory));
+        }
+        strtab[str_len..end - 1].copy_from_slice(name.as_bytes());
+        name_offsets[i] = str_len as u32;
+        str_len = end;
+    }
+    if CODE_OFFSET + code.len() > text.len() {
+        return Err(Kernel::Memory(Memory::OutOfMemory));
+    }
+
+    let mut put = |off: usize, bytes: &[u8]| text[off..off + bytes.len()].copy_from_slice(bytes);
+
+    // ELF ヘッダ
+    put(0, &[0x7F, b'E', b'L', b'F', 2, 1, 1, 0]);
+    put(16, &3u16.to_ne_bytes()); // ET_DYN
+    put(18, &0x3Eu16.to_ne_bytes()); // EM_X86_64
+    put(20, &1u32.to_ne_bytes());
+    put(32, &(PHDR_OFFSET as u64).to_ne_bytes());
+    put(52, &(EHDR_SIZE as u16).to_ne_bytes());
+    put(54, &(PHDR_SIZE as u16).to_ne_bytes());
+    put(56, &(PHDR_COUNT as u16).to_ne_bytes());
+
+    // プログラムヘッダ: ページ全体の PT_LOAD と PT_DYNAMIC
+    let mut put_phdr =
+        |index: usize, p_type: u32, flags: u32, offset: usize, size: u64, align: u64| {
+            let base = PHDR_OFFSET + index * PHDR_SIZE;
+            put(base, &p_type.to_ne_bytes());
+            put(base + 4, &flags.to_ne_bytes());
+            put(base + 8, &(offset as u64).to_ne_bytes());
+            put(base + 16, &(offset as u64).to_ne_bytes());
+            put(base + 24, &(offset as u64).to_ne_bytes());
+            put(base + 32, &size.to_ne_bytes());
+            put(base + 40, &size.to_ne_bytes());
+            put(base + 48, &align.to_ne_bytes());
+        };
+    put_phdr(0, PT_LOAD, PF_R | PF_X, 0, PAGE_SIZE, PAGE_SIZE);
+    put_phdr(1, PT_DYNAMIC, PF_R, DYN_OFFSET, (16 * DYN_COUNT) as u64, 8);
+
+    // 動的セクション
+    let dynamic = [
+        (DT_HASH, HASH_OFFSET as u64),
+        (DT_STRTAB, STR_OFFSET as u64),
+        (DT_SYMTAB, SYM_OFFSET as u64),
+        (DT_STRSZ, str_len as u64),
+        (DT_SYMENT, SYM_SIZE as u64),
+        (DT_NULL, 0),
+    ];
+    for (i, (tag, value)) in dynamic.iter().enumerate() {
+        put(DYN_OFFSET + i * 16, &tag.to_ne_bytes());
+        put(DYN_OFFSET + i * 16 + 8, &value.to_ne_bytes());
+    }
+
+    // SysV ハッシュ表: バケット 1 個に全シンボルを連ねる
+    put(HASH_OFFSET, &1u32.to_ne_bytes());
+    put(HASH_OFFSET + 4, &(SYM_COUNT as u32).to_ne_bytes());
+    put(HASH_OFFSET + 8, &((SYM_COUNT - 1) as u32).to_ne_bytes());
+    for i in 1..SYM_COUNT {
+        put(HASH_OFFSET + 12 + i * 4, &((i - 1) as u32).to_ne_bytes());
+    }
+
+    // シンボル表（0 番は未定義シンボル）。セクションヘッダは持たないが、
+    // st_shndx が 0 だと未定義扱いされるので 1 を入れる。
+    for (i, off) in offsets.iter().enumerate() {
+        let base = SYM_OFFSET + (i + 1) * SYM_SIZE;
+        put(base, &name_offsets[i].to_ne_bytes());
+        put(base + 4, &[SYM_INFO_GLOBAL_FUNC, 0]);
+        put(base + 6, &1u16.to_ne_bytes());
+        put(base + 8, &((CODE_OFFSET + off) as u64).to_ne_bytes());
+    }
+
+    put(STR_OFFSET, &strtab[..str_len]);
+    put(CODE_OFFSET, code);
+    Ok(())
+}
+
+/// ユーザーページテーブルへ vDSO をマップする（未初期化なら何もしない）
+pub fn map_into(table_phys: u64) -> Result<()> {
+    let Some(base) = base() else {
+        return Ok(());
+    };
+    paging::map_shared_frame_in_table(table_phys, base, VVAR_PHYS.load(Ordering::Relaxed), false)?;
+    paging::map_shared_frame_in_table(
+        table_phys,
+        base + PAGE_SIZE,
+        TEXT_PHYS.load(Ordering::Relaxed),
+        true,
+    )
+}
+
+/// vvar の先頭ユーザー仮想アドレス
+pub fn base() -> Option<u64> {
+    match VDSO_BASE.load(Ordering::Acquire) {
+        0 => None,
+        base => Some(base),
+    }
+}
+
+/// AT_SYSINFO_EHDR に渡す ELF イメージの先頭アドレス
+pub fn sysinfo_ehdr() -> Option<u64> {
+    base().map(|base| base + PAGE_SIZE)
+}
+
+/// シグナルハンドラの戻り番地（`__vdso_rt_sigreturn`）
+pub fn sigreturn_addr() -> Option<u64> {
+    sysinfo_ehdr().map(|ehdr| ehdr + SIGRETURN_OFFSET.load(Ordering::Relaxed))
+}
+
+/// 物理フレームが vDSO のものか
+pub fn is_vdso_frame(phys: u64) -> bool {
+    let phys = phys & !(PAGE_SIZE - 1);
+    phys != 0
+        && (phys == VVAR_PHYS.load(Ordering::Relaxed) || phys == TEXT_PHYS.load(Ordering::Relaxed))
+}
+
+/// タイマー割り込みから時刻データを更新する
+pub fn update_time(ticks: u64) {
+    let virt = VVAR_VIRT.load(Ordering::Acquire);
+    if virt == 0 {
+        return;
+    }
+    let vvar = unsafe { &*(virt as *const VvarData) };
+    let seq = vvar.seq.load(Ordering::Relaxed);
+    vvar.seq.store(seq.wrapping_add(1), Ordering::Relaxed);
+    fence(Ordering::Release);
+    vvar.ticks.store(ticks, Ordering::Relaxed);
+    vvar.seq.store(seq.wrapping_add(2), Ordering::Release);
+}
@@ -20,6 +20,8 @@ pub struct ErrorContext {
 #[allow(deprecated)]
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
+    // パニックした流れが持っていたロックを先に外す（出力で詰まらないように）
+    let fault = crate::recovery::begin();
     crate::info!("!!! KERNEL PANIC !!!");
 
     if let Some(loc) = info.location() {
@@ -32,20 +34,38 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
         warn!("Message: {}", s);
     }
 
-    warn!("system halted. Please reset.");
+    crate::backtrace::print_current();
 
-    // 割り込みを無効化
-    #[cfg(target_arch = "x86_64")]
-    unsafe {
-        x86_64::instructions::interrupts::disable();
-    }
+    // テスト中のパニックはそのテストの失敗として報告し、QEMU を終了する
+    #[cfg(feature = "ktest")]
+    crate::ktest::on_panic();
 
-    // システムを停止
-    loop {
-        #[cfg(target_arch = "x86_64")]
-        unsafe {
-            core::arch::asm!("hlt");
+    // 次の起動で /proc/crash から読めるよう、予約領域にも残す
+    crate::crashlog::record(crate::crashlog::CrashKind::Panic, |w| {
+        use core::fmt::Write;
+        if let Some(loc) = info.location() {
+            writeln!(
+                w,
+                "Location: {}:{}:{}",
+                loc.file(),
+                loc.line(),
+                loc.column()
+            )?;
         }
+        writeln!(w, "Message: {}", info.message())?;
+        crate::backtrace::write_current(w)
+    });
+
+    // kgdb が有効ならパニックした場所で止めて調べられるようにする
+    crate::kgdb::breakpoint();
+
+    // プロセスのために動いていた途中ならそのプロセスを殺して動き続け、そうでなければ再起動する
+    match info.location() {
+        Some(loc) => crate::recovery::recover_or_reboot(
+            fault,
+            format_args!("panic at {}:{}", loc.file(), loc.line()),
+        ),
+        None => crate::recovery::recover_or_reboot(fault, format_args!("panic")),
     }
 }
 
@@ -57,27 +77,13 @@ macro_rules! kernel_panic {
     ($msg:expr) => {
         {
             $crate::warn!("[KERNEL PANIC] {}", $msg);
-            #[cfg(target_arch = "x86_64")]
-            unsafe {
-                x86_64::instructions::interrupts::disable();
-            }
-            loop {
-                #[cfg(target_arch = "x86_64")]
-                unsafe { core::arch::asm!("hlt"); }
-            }
+            $crate::recovery::fatal(format_args!("{}", $msg))
         }
     };
     ($fmt:expr, $($arg:tt)*) => {
         {
             $crate::warn!($fmt, $($arg)*);
-            #[cfg(target_arch = "x86_64")]
-            unsafe {
-                x86_64::instructions::interrupts::disable();
-            }
-            loop {
-                #[cfg(target_arch = "x86_64")]
-                unsafe { core::arch::asm!("hlt"); }
-            }
+            $crate::recovery::fatal(format_args!($fmt, $($arg)*))
         }
     };
 }
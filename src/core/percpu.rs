@@ -3,11 +3,13 @@
 //! APIC ID をキーに CPU ローカルスロットを選択する。
 
 use core::arch::asm;
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use x86_64::registers::control::Cr3;
 
-const MAX_CPUS: usize = 64;
+/// per-CPU スロットの数
+pub const MAX_CPUS: usize = 64;
 const IA32_KERNEL_GS_BASE: u32 = 0xC000_0102;
+const IA32_TSC_AUX: u32 = 0xC000_0103;
 pub const GS_SYSCALL_KERNEL_RSP_OFFSET: usize = 8;
 pub const GS_SYSCALL_USER_RSP_TMP_OFFSET: usize = 24;
 
@@ -17,6 +19,10 @@ struct PerCpuState {
     syscall_kernel_rsp: AtomicU64,
     current_thread_id: AtomicU64,
     syscall_user_rsp_tmp: AtomicU64,
+    /// 現在のスレッドが属するプロセス ID（ログ用。ロックを取らずに読める）
+    current_process_id: AtomicU64,
+    /// 実行中のハードウェア割り込みハンドラの入れ子の深さ
+    irq_depth: AtomicU64,
 }
 
 impl PerCpuState {
@@ -26,12 +32,20 @@ impl PerCpuState {
             syscall_kernel_rsp: AtomicU64::new(0),
             current_thread_id: AtomicU64::new(0),
             syscall_user_rsp_tmp: AtomicU64::new(0),
+            current_process_id: AtomicU64::new(0),
+            irq_depth: AtomicU64::new(0),
         }
     }
 }
 
 static CPU_STATES: [PerCpuState; MAX_CPUS] = [const { PerCpuState::new() }; MAX_CPUS];
 
+/// `current_cpu_id` が CPU 番号を読む方法
+const CPU_ID_VIA_CPUID: u8 = 0;
+const CPU_ID_VIA_RDTSCP: u8 = 1;
+const CPU_ID_VIA_RDPID: u8 = 2;
+static CPU_ID_SOURCE: AtomicU8 = AtomicU8::new(CPU_ID_VIA_CPUID);
+
 #[inline]
 fn state_for_current_cpu() -> &'static PerCpuState {
     &CPU_STATES[current_cpu_id()]
@@ -46,21 +60,34 @@ fn halt_unsupported_cpu(_apic_id: u32) -> ! {
 }
 
 #[inline]
-unsafe fn write_kernel_gs_base(base: u64) {
-    let lo = base as u32;
-    let hi = (base >> 32) as u32;
+unsafe fn write_msr(msr: u32, value: u64) {
+    let lo = value as u32;
+    let hi = (value >> 32) as u32;
     asm!(
         "wrmsr",
-        in("ecx") IA32_KERNEL_GS_BASE,
+        in("ecx") msr,
         in("eax") lo,
         in("edx") hi,
         options(nomem, nostack)
     );
 }
 
+#[inline]
+unsafe fn write_kernel_gs_base(base: u64) {
+    write_msr(IA32_KERNEL_GS_BASE, base);
+}
+
+/// 現在の CPU のスロット番号（APIC ID）
+///
+/// スピンロックを取るたびに呼ばれる。CPUID はシリアライズ命令で KVM では VM exit にもなるので、
+/// `init_boot_cpu` の後は TSC_AUX に書いておいた APIC ID を RDPID（なければ RDTSCP）で読む。
 #[inline]
 pub fn current_cpu_id() -> usize {
-    let apic_id = local_apic_id() as usize;
+    let apic_id = match CPU_ID_SOURCE.load(Ordering::Relaxed) {
+        CPU_ID_VIA_RDPID => read_tsc_aux_rdpid(),
+        CPU_ID_VIA_RDTSCP => read_tsc_aux_rdtscp(),
+        _ => local_apic_id(),
+    } as usize;
     if apic_id < MAX_CPUS {
         apic_id
     } else {
@@ -69,22 +96,73 @@ pub fn current_cpu_id() -> usize {
 }
 
 #[inline]
-fn local_apic_id() -> u32 {
-    // CPUID leaf 1: EBX[31:24] = Initial APIC ID
+fn cpuid(leaf: u32, subleaf: u32) -> (u32, u32, u32, u32) {
+    let (eax, ecx, edx): (u32, u32, u32);
     let ebx: u64;
     unsafe {
         asm!(
             "xchg {tmp}, rbx",
             "cpuid",
             "xchg {tmp}, rbx",
-            inout("eax") 1u32 => _,
-            in("ecx") 0u32,
+            inout("eax") leaf => eax,
+            inout("ecx") subleaf => ecx,
             tmp = inout(reg) 0u64 => ebx,
-            out("edx") _,
+            out("edx") edx,
             options(nomem, nostack)
         );
     }
-    ((ebx as u32) >> 24) & 0xff
+    (eax, ebx as u32, ecx, edx)
+}
+
+#[inline]
+fn local_apic_id() -> u32 {
+    // CPUID leaf 1: EBX[31:24] = Initial APIC ID
+    (cpuid(1, 0).1 >> 24) & 0xff
+}
+
+#[inline]
+fn read_tsc_aux_rdpid() -> u32 {
+    let aux: u64;
+    unsafe {
+        asm!("rdpid {}", out(reg) aux, options(nomem, nostack, preserves_flags));
+    }
+    aux as u32
+}
+
+#[inline]
+fn read_tsc_aux_rdtscp() -> u32 {
+    let aux: u32;
+    unsafe {
+        asm!(
+            "rdtscp",
+            out("eax") _,
+            out("edx") _,
+            out("ecx") aux,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    aux
+}
+
+/// TSC_AUX に APIC ID を書き、`current_cpu_id` が CPUID を使わずに済むようにする
+///
+/// RDPID も RDTSCP も無い CPU では CPUID のまま。
+/// 他の CPU を起動するときも、ロックを取る前にその CPU の TSC_AUX を書くこと。
+fn enable_fast_cpu_id(apic_id: usize) {
+    let has_rdpid = cpuid(0, 0).0 >= 7 && (cpuid(7, 0).2 & (1 << 22)) != 0;
+    let has_rdtscp =
+        cpuid(0x8000_0000, 0).0 >= 0x8000_0001 && (cpuid(0x8000_0001, 0).3 & (1 << 27)) != 0;
+    let source = if has_rdpid {
+        CPU_ID_VIA_RDPID
+    } else if has_rdtscp {
+        CPU_ID_VIA_RDTSCP
+    } else {
+        return;
+    };
+    unsafe {
+        write_msr(IA32_TSC_AUX, apic_id as u64);
+    }
+    CPU_ID_SOURCE.store(source, Ordering::SeqCst);
 }
 
 pub fn init_boot_cpu(syscall_kernel_rsp: u64) {
@@ -109,6 +187,10 @@ pub fn init_boot_cpu(syscall_kernel_rsp: u64) {
         .store(syscall_kernel_rsp, Ordering::SeqCst);
     state.current_thread_id.store(0, Ordering::SeqCst);
     state.syscall_user_rsp_tmp.store(0, Ordering::SeqCst);
+    state.current_process_id.store(0, Ordering::SeqCst);
+    if apic_id < MAX_CPUS {
+        enable_fast_cpu_id(apic_id);
+    }
     install_current_cpu_gs_base();
 }
 
@@ -119,6 +201,11 @@ pub fn install_current_cpu_gs_base() {
     }
 }
 
+/// 初期化済み（`init_boot_cpu` を通った）CPU のスロット番号
+pub fn online_cpus() -> impl Iterator<Item = usize> {
+    (0..MAX_CPUS).filter(|&i| CPU_STATES[i].kernel_cr3.load(Ordering::SeqCst) != 0)
+}
+
 pub fn kernel_cr3() -> u64 {
     state_for_current_cpu().kernel_cr3.load(Ordering::SeqCst)
 }
@@ -146,3 +233,34 @@ pub fn set_current_thread_raw_id(id: u64) {
         .current_thread_id
         .store(id, Ordering::SeqCst);
 }
+
+pub fn current_process_raw_id() -> u64 {
+    state_for_current_cpu()
+        .current_process_id
+        .load(Ordering::SeqCst)
+}
+
+pub fn set_current_process_raw_id(id: u64) {
+    state_for_current_cpu()
+        .current_process_id
+        .store(id, Ordering::SeqCst);
+}
+
+/// ハードウェア割り込みハンドラに入った
+pub fn irq_enter() {
+    state_for_current_cpu()
+        .irq_depth
+        .fetch_add(1, Ordering::SeqCst);
+}
+
+/// ハードウェア割り込みハンドラの処理を終えた（スケジュールする前に呼ぶ）
+pub fn irq_exit() {
+    state_for_current_cpu()
+        .irq_depth
+        .fetch_sub(1, Ordering::SeqCst);
+}
+
+/// ハードウェア割り込みハンドラの中か（プロセスのために動いているのではない）
+pub fn in_irq() -> bool {
+    state_for_current_cpu().irq_depth.load(Ordering::SeqCst) != 0
+}
@@ -0,0 +1,160 @@
+//! カーネル障害からの復旧
+//!
+//! ユーザー・サービスのプロセスのためにカーネルが動いている途中（システムコールや
+//! そこから起きたページフォルト）で例外やパニックが起きても、システムは止めずに
+//! そのプロセスだけを殺す。障害を起こした流れが持っていたスピンロックを外し、
+//! 監査ログに残し、プロセスの全スレッドを終わらせてから、このスレッドを捨てる
+//! （メモリや fd は通常の終了と同じく、親が回収するときに解放される）。
+//!
+//! 割り込みハンドラの中・カーネルスレッド・スケジューラの起動前・復旧中の再度の障害は
+//! 復旧できないので、クラッシュログを書いた後で再起動する。待ち時間は起動オプション
+//! `panic=<秒>`（既定 5 秒。`panic=0` なら再起動せずに止まったまま）。
+
+use crate::percpu::MAX_CPUS;
+use crate::task::{PrivilegeLevel, ProcessId, ThreadId};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// 再起動までの既定の待ち時間（秒）
+const DEFAULT_REBOOT_DELAY_SECS: u64 = 5;
+
+/// CPU ごとの復旧中フラグ（復旧の途中でまた障害が起きたら再起動する）
+static RECOVERING: [AtomicBool; MAX_CPUS] = [const { AtomicBool::new(false) }; MAX_CPUS];
+
+/// 処理中の障害（`begin` で作り、`recover_or_reboot` に渡す）
+pub struct Fault {
+    /// 保持中だったロックをすべて外せたか
+    locks_released: bool,
+    /// この CPU で復旧中にまた起きた障害か
+    nested: bool,
+}
+
+/// 障害の処理を始める: 障害を起こした流れが持っていたスピンロックを外す
+///
+/// ログやスタックトレースの出力がそのロックで詰まらないよう、例外・パニックハンドラの
+/// 最初に呼ぶ。再起動する場合にも外しておいて害はない。
+pub fn begin() -> Fault {
+    let nested = RECOVERING[crate::percpu::current_cpu_id()].swap(true, Ordering::SeqCst);
+    // SAFETY: 障害を起こした流れは再開しない（プロセスごと捨てるか再起動する。
+    // kgdb から再開する場合はロックの状態ごと調べる人に任せる）
+    let released = unsafe { crate::interrupt::spinlock::release_held() };
+    if let Some(count) = released.filter(|&n| n > 0) {
+        crate::warn!(
+            "recovery: released {} spinlock(s) held by the faulting context",
+            count
+        );
+    }
+    Fault {
+        locks_released: released.is_some(),
+        nested,
+    }
+}
+
+/// 障害の処理をやめて、障害を起こした流れを再開する（kgdb から再開されたとき）
+pub fn cancel(fault: Fault) {
+    if !fault.nested {
+        RECOVERING[crate::percpu::current_cpu_id()].store(false, Ordering::SeqCst);
+    }
+}
+
+/// 障害を起こしたプロセスを殺して動き続ける。復旧できなければ再起動する
+///
+/// `what` は監査ログに残す障害の説明。
+pub fn recover_or_reboot(fault: Fault, what: core::fmt::Arguments<'_>) -> ! {
+    let cpu = crate::percpu::current_cpu_id();
+    let Some((pid, tid)) = victim(&fault) else {
+        reboot(what);
+    };
+    let name = crate::task::with_process(pid, |p| alloc::string::String::from(p.name()))
+        .unwrap_or_default();
+    crate::warn!(
+        "recovery: {}; killing pid {} ({}) and continuing",
+        what,
+        pid.as_u64(),
+        name
+    );
+    crate::audit::log(
+        crate::audit::AuditEventKind::Fault,
+        &alloc::format!(
+            "recovered from kernel fault ({}) by killing pid {} ({})",
+            what,
+            pid.as_u64(),
+            name
+        ),
+    );
+
+    // 同じプロセスのほかのスレッドも終わらせる（最後の 1 本がこのスレッドになるように）
+    let mut others = alloc::vec::Vec::new();
+    crate::task::for_each_thread(|t| {
+        if t.process_id() == pid && t.id() != tid {
+            others.push(t.id());
+        }
+    });
+    for other in others {
+        crate::task::terminate_thread(other);
+    }
+
+    // ここから先の障害は current_thread が無いので再起動になる
+    RECOVERING[cpu].store(false, Ordering::SeqCst);
+    crate::task::scheduler::exit_current_process(-1)
+}
+
+/// 復旧できない障害: クラッシュログを書いて再起動する
+pub fn fatal(what: core::fmt::Arguments<'_>) -> ! {
+    crate::error!("FATAL: {}", what);
+    crate::backtrace::print_current();
+    crate::crashlog::record(crate::crashlog::CrashKind::KernelFault, |w| {
+        use core::fmt::Write;
+        writeln!(w, "{}", what)?;
+        crate::backtrace::write_current(w)
+    });
+    reboot(what);
+}
+
+/// 復旧できない障害: 再起動する（クラッシュログは書き終えていること）
+pub fn reboot(what: core::fmt::Arguments<'_>) -> ! {
+    crate::audit::log(
+        crate::audit::AuditEventKind::Fault,
+        &alloc::format!("unrecoverable kernel fault: {}", what),
+    );
+    reboot_after_delay();
+}
+
+/// 殺せば済むプロセスとスレッド（割り込みハンドラ中やカーネルスレッドなら None）
+fn victim(fault: &Fault) -> Option<(ProcessId, ThreadId)> {
+    if fault.nested || !fault.locks_released {
+        return None;
+    }
+    if crate::percpu::in_irq() || !crate::task::is_scheduler_enabled() {
+        return None;
+    }
+    let tid = crate::task::current_thread_id()?;
+    let pid = crate::task::with_thread(tid, |t| t.process_id())?;
+    let privilege = crate::task::with_process(pid, |p| p.privilege())?;
+    (privilege != PrivilegeLevel::Core).then_some((pid, tid))
+}
+
+/// `panic=<秒>` だけ待って再起動する（`panic=0` なら止まったまま）
+fn reboot_after_delay() -> ! {
+    x86_64::instructions::interrupts::disable();
+    let secs = crate::init::cmdline::get("panic")
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_REBOOT_DELAY_SECS);
+    if secs == 0 {
+        crate::warn!("system halted (panic=0). Please reset.");
+        loop {
+            x86_64::instructions::hlt();
+        }
+    }
+    crate::warn!("rebooting in {} second(s)...", secs);
+    let start = crate::cpu::rdtsc();
+    loop {
+        let elapsed = crate::cpu::rdtsc().wrapping_sub(start);
+        // TSC の較正前は 1 サイクルを 1ns とみなす
+        let ns = crate::interrupt::timer::tsc_delta_to_ns(elapsed).unwrap_or(elapsed);
+        if ns >= secs.saturating_mul(1_000_000_000) {
+            break;
+        }
+        core::hint::spin_loop();
+    }
+    crate::cpu::reboot()
+}
@@ -198,8 +198,6 @@ pub fn handle_kernel_error(error: Kernel) {
             crate::warn!("Unknown error: {:?}", error);
         }
     }
-
-    crate::info!("system halted.");
 }
 
 /// 結果型のエイリアス
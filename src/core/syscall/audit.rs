@@ -0,0 +1,46 @@
+//! audit_read システムコール（監査ログの読み出し）
+//!
+//! CAP_AUDIT_READ を持つプロセス（audit.service）だけが呼べる。
+//! レコードの配置は `audit::AuditWireRecord` を参照。
+
+use alloc::vec::Vec;
+
+use super::types::{EFAULT, EPERM};
+use crate::audit::{self, AuditWireRecord, AUDIT_CAPACITY};
+
+const WIRE_SIZE: usize = core::mem::size_of::<AuditWireRecord>();
+
+/// audit_read システムコール
+///
+/// 通し番号が `since_seq` より後の記録を、種別マスク `kind_mask`（0 なら全種別）で
+/// 絞り込んで `buf` に最大 `count` 件書き、書いた件数を返す。
+pub fn audit_read(buf_ptr: u64, count: u64, since_seq: u64, kind_mask: u64) -> u64 {
+    if !crate::task::current_has_audited(crate::task::capability::CAP_AUDIT_READ, "audit_read") {
+        return EPERM;
+    }
+    let max = core::cmp::min(count, AUDIT_CAPACITY as u64) as usize;
+    if max == 0 {
+        return 0;
+    }
+    if !crate::syscall::validate_user_ptr(buf_ptr, (max * WIRE_SIZE) as u64) {
+        return EFAULT;
+    }
+
+    let records = audit::read_since(since_seq, kind_mask, max);
+    let mut bytes = Vec::with_capacity(records.len() * WIRE_SIZE);
+    for rec in &records {
+        let wire = rec.to_wire();
+        // SAFETY: AuditWireRecord は repr(C) で、詰め物は明示した _pad だけ
+        let raw = unsafe {
+            core::slice::from_raw_parts(&wire as *const AuditWireRecord as *const u8, WIRE_SIZE)
+        };
+        bytes.extend_from_slice(raw);
+    }
+    if bytes.is_empty() {
+        return 0;
+    }
+    match crate::syscall::copy_to_user(buf_ptr, &bytes) {
+        Ok(()) => records.len() as u64,
+        Err(e) => e,
+    }
+}
@@ -0,0 +1,161 @@
+//! ケーパビリティ関連のシステムコール
+
+use super::types::{EFAULT, EINVAL, EPERM, ESRCH, SUCCESS};
+use crate::task::capability::{CapabilitySet, CAP_FS_WRITE, CAP_SET_CAPS, CAP_WIRE_SIZE};
+use crate::task::{ProcessId, ThreadId};
+
+fn current_pid() -> Option<ProcessId> {
+    crate::task::current_thread_id()
+        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
+}
+
+/// `pid == 0` を呼び出し元プロセスとして解決する
+fn resolve_target(pid: u64) -> Result<ProcessId, u64> {
+    if pid == 0 {
+        return current_pid().ok_or(ESRCH);
+    }
+    let target = ProcessId::from_u64(pid);
+    if crate::task::with_process(target, |_| ()).is_none() {
+        return Err(ESRCH);
+    }
+    Ok(target)
+}
+
+/// プロセスのケーパビリティを取得する
+///
+/// # 引数
+/// - `pid`: 対象プロセスID（0 = 自プロセス）
+/// - `out_ptr`: `CAP_WIRE_SIZE` バイトの出力バッファ
+pub fn get_capabilities(pid: u64, out_ptr: u64) -> u64 {
+    if !crate::syscall::validate_user_ptr(out_ptr, CAP_WIRE_SIZE as u64) {
+        return EFAULT;
+    }
+    let target = match resolve_target(pid) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let caps = match crate::task::with_process(target, |p| *p.capabilities()) {
+        Some(c) => c,
+        None => return ESRCH,
+    };
+    match crate::syscall::copy_to_user(out_ptr, &caps.to_wire()) {
+        Ok(()) => SUCCESS,
+        Err(e) => e,
+    }
+}
+
+/// スレッドが属するプロセスのケーパビリティを取得する
+///
+/// IPC を受け取ったサービスが送信元（スレッドID）の権限を確かめるのに使う。
+/// コアダンプを書いている最中のスレッドは CAP_FS_WRITE を持つものとして返す。
+///
+/// # 引数
+/// - `tid`: 対象スレッドID
+/// - `out_ptr`: `CAP_WIRE_SIZE` バイトの出力バッファ
+///
+/// # 戻り値
+/// スレッドが属するプロセスID
+pub fn get_thread_capabilities(tid: u64, out_ptr: u64) -> u64 {
+    if !crate::syscall::validate_user_ptr(out_ptr, CAP_WIRE_SIZE as u64) {
+        return EFAULT;
+    }
+    let pid = match crate::task::with_thread(ThreadId::from_u64(tid), |t| t.process_id()) {
+        Some(p) => p,
+        None => return ESRCH,
+    };
+    let mut caps = match crate::task::with_process(pid, |p| *p.capabilities()) {
+        Some(c) => c,
+        None => return ESRCH,
+    };
+    if super::coredump::is_dumping(tid) {
+        caps = caps.with(CAP_FS_WRITE);
+    }
+    match crate::syscall::copy_to_user(out_ptr, &caps.to_wire()) {
+        Ok(()) => pid.as_u64(),
+        Err(e) => e,
+    }
+}
+
+/// プロセスのケーパビリティを設定する
+///
+/// 新しい集合は呼び出し元が持つ集合の部分集合でなければならない。
+/// 自プロセスの権限を手放す操作は常に許可し、他プロセスへの設定には
+/// CAP_SET_CAPS を要求する。サービスマネージャー（core.service）の集合は
+/// 本人以外には変えられない。
+///
+/// # 引数
+/// - `pid`: 対象プロセスID（0 = 自プロセス）
+/// - `in_ptr`: `CAP_WIRE_SIZE` バイトの入力バッファ
+pub fn set_capabilities(pid: u64, in_ptr: u64) -> u64 {
+    let mut buf = [0u8; CAP_WIRE_SIZE];
+    if let Err(e) = crate::syscall::copy_from_user(in_ptr, &mut buf) {
+        return e;
+    }
+    let requested = match CapabilitySet::from_wire(&buf) {
+        Some(c) => c,
+        None => return EINVAL,
+    };
+    let target = match resolve_target(pid) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+
+    let caller_caps = crate::task::current_capabilities();
+    let is_self = current_pid() == Some(target);
+    if !is_self && !caller_caps.has(CAP_SET_CAPS) {
+        crate::audit::log(
+            crate::audit::AuditEventKind::Deny,
+            "set_capabilities: caller lacks CAP_SET_CAPS",
+        );
+        return EPERM;
+    }
+    if !requested.is_subset_of(&caller_caps) {
+        crate::audit::log(
+            crate::audit::AuditEventKind::Deny,
+            "set_capabilities: requested set exceeds caller capabilities",
+        );
+        return EPERM;
+    }
+    if !is_self && target.as_u64() == super::exec::service_manager_pid() {
+        crate::audit::log(
+            crate::audit::AuditEventKind::Deny,
+            "set_capabilities: service manager capabilities are not changeable by others",
+        );
+        return EPERM;
+    }
+
+    if crate::task::with_process_mut(target, |p| p.set_capabilities(requested)).is_none() {
+        return ESRCH;
+    }
+    crate::audit::log(
+        crate::audit::AuditEventKind::Policy,
+        "set_capabilities: capability set updated",
+    );
+    SUCCESS
+}
+
+/// exec と同時に子へ与えるケーパビリティを読み出す
+///
+/// `set_capabilities` で他プロセスに設定するのと同じ条件（CAP_SET_CAPS を持ち、
+/// 呼び出し元の部分集合であること）を課す。
+pub fn read_exec_capabilities(in_ptr: u64) -> Result<CapabilitySet, u64> {
+    let mut buf = [0u8; CAP_WIRE_SIZE];
+    crate::syscall::copy_from_user(in_ptr, &mut buf)?;
+    let requested = CapabilitySet::from_wire(&buf).ok_or(EINVAL)?;
+    let caller_caps = crate::task::current_capabilities();
+    if !caller_caps.has(CAP_SET_CAPS) {
+        crate::audit::log(
+            crate::audit::AuditEventKind::Deny,
+            "exec_with_caps: caller lacks CAP_SET_CAPS",
+        );
+        return Err(EPERM);
+    }
+    if !requested.is_subset_of(&caller_caps) {
+        crate::audit::log(
+            crate::audit::AuditEventKind::Deny,
+            "exec_with_caps: requested set exceeds caller capabilities",
+        );
+        return Err(EPERM);
+    }
+    Ok(requested)
+}
@@ -0,0 +1,582 @@
+//! ELF コアダンプ
+//!
+//! 異常終了するユーザープロセスのレジスタとアドレス空間を ELF コア形式
+//! （ET_CORE, PT_NOTE + PT_LOAD）で書き出す。出力は fs.service 経由で
+//! `<core dir>/core.<name>.<pid>` に置き、ホストの gdb でそのまま読み込める。
+//!
+//! - PT_NOTE: NT_PRSTATUS（レジスタ）, NT_PRPSINFO, NT_SIGINFO, NT_AUXV
+//! - PT_LOAD: ユーザーページテーブル上のマップ済み領域（保護属性ごとにまとめる）
+//!
+//! RLIMIT_CORE のソフトリミットを超える分は書かない（0 ならダンプしない）。
+//!
+//! 書き込みは異常終了するプロセスのスレッドから fs.service に送る。fs.service は
+//! 送信元に CAP_FS_WRITE を求めるので、書いている間そのスレッドを `DUMPING` に載せ、
+//! `get_thread_capabilities` が CAP_FS_WRITE 付きで返すようにする（スレッドは
+//! カーネル内にいるので、その間にユーザーコードが要求を送ることはない）。
+
+use super::types::{EFAULT, EINVAL, EIO, EPERM, ERANGE, SUCCESS};
+use crate::interrupt::spinlock::SpinLock;
+use crate::task::{SigInfo, RLIMIT_CORE};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 出力先ディレクトリの既定値
+const DEFAULT_CORE_DIR: &str = "/cores";
+/// 出力先ディレクトリの最大長（ファイル名を足しても FS_PATH_MAX に収まるようにする）
+const CORE_DIR_MAX: usize = 256;
+
+/// 出力先ディレクトリ（空なら既定値）
+static CORE_DIR: SpinLock<String> = SpinLock::named("CORE_DIR", String::new());
+
+/// コアダンプを書いているスレッド
+static DUMPING: SpinLock<Vec<u64>> = SpinLock::named("COREDUMP_THREADS", Vec::new());
+
+/// `tid` がコアダンプを書いている最中か
+pub(crate) fn is_dumping(tid: u64) -> bool {
+    DUMPING.lock().contains(&tid)
+}
+
+// ---- ELF 定数 ----
+const ET_CORE: u16 = 4;
+const EM_X86_64: u16 = 62;
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const PAGE_SIZE: u64 = 4096;
+
+// ---- ノート種別 ----
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+const NT_AUXV: u32 = 6;
+const NT_SIGINFO: u32 = 0x5349_4749;
+
+/// struct elf_prstatus（x86-64）のサイズと pr_reg の位置
+const PRSTATUS_SIZE: usize = 336;
+const PRSTATUS_REG: usize = 112;
+/// struct elf_prpsinfo（x86-64）のサイズ
+const PRPSINFO_SIZE: usize = 136;
+/// auxv として読む最大バイト数
+const AUXV_MAX: u64 = 64 * 16;
+
+/// user_regs_struct の要素数
+pub const CORE_REG_COUNT: usize = 27;
+
+// user_regs_struct 内のインデックス
+const UR_RBP: usize = 4;
+const UR_RBX: usize = 5;
+const UR_RAX: usize = 10;
+const UR_RCX: usize = 11;
+const UR_RDX: usize = 12;
+const UR_RSI: usize = 13;
+const UR_RDI: usize = 14;
+const UR_ORIG_RAX: usize = 15;
+const UR_RIP: usize = 16;
+const UR_CS: usize = 17;
+const UR_EFLAGS: usize = 18;
+const UR_RSP: usize = 19;
+const UR_SS: usize = 20;
+const UR_FS_BASE: usize = 21;
+const UR_DS: usize = 23;
+const UR_ES: usize = 24;
+
+/// コアダンプに残すレジスタ（Linux x86-64 の user_regs_struct と同じ並び）
+#[derive(Debug, Clone, Copy)]
+pub struct CoreRegs {
+    regs: [u64; CORE_REG_COUNT],
+}
+
+impl CoreRegs {
+    /// 割り込みフレームだけから作る（汎用レジスタは 0）
+    pub fn from_iret(rip: u64, cs: u64, rflags: u64, rsp: u64, ss: u64) -> Self {
+        let mut regs = [0u64; CORE_REG_COUNT];
+        regs[UR_ORIG_RAX] = u64::MAX;
+        regs[UR_RIP] = rip;
+        regs[UR_CS] = cs;
+        regs[UR_EFLAGS] = rflags;
+        regs[UR_RSP] = rsp;
+        regs[UR_SS] = ss;
+        regs[UR_DS] = ss;
+        regs[UR_ES] = ss;
+        Self { regs }
+    }
+
+    /// int 0x80 / ページフォルトのエントリが積んだ順（r15, r14, ..., r8, rdi, rsi, rbp, rbx, rdx, rcx, rax）の
+    /// 汎用レジスタと割り込みフレームから作る
+    pub fn from_saved(gprs: [u64; 15], rip: u64, cs: u64, rflags: u64, rsp: u64, ss: u64) -> Self {
+        let mut out = Self::from_iret(rip, cs, rflags, rsp, ss);
+        // r15..r12 は user_regs_struct でも先頭 4 つ
+        out.regs[..4].copy_from_slice(&gprs[..4]);
+        // r11..r8
+        out.regs[6..10].copy_from_slice(&gprs[4..8]);
+        out.regs[UR_RDI] = gprs[8];
+        out.regs[UR_RSI] = gprs[9];
+        out.regs[UR_RBP] = gprs[10];
+        out.regs[UR_RBX] = gprs[11];
+        out.regs[UR_RDX] = gprs[12];
+        out.regs[UR_RCX] = gprs[13];
+        out.regs[UR_RAX] = gprs[14];
+        out
+    }
+
+    /// 直近の syscall で保存した RIP/RSP/RFLAGS から作る（kill(getpid(), SIGABRT) など）
+    pub fn from_syscall_context(rip: u64, rsp: u64, rflags: u64) -> Self {
+        let cs = crate::mem::gdt::user_code_selector() as u64 | 3;
+        let ss = crate::mem::gdt::user_data_selector() as u64 | 3;
+        Self::from_iret(rip, cs, rflags, rsp, ss)
+    }
+
+    /// user_regs_struct の並びそのままから作る（PTRACE_SETREGS / POKEUSER）
+    pub fn from_array(regs: [u64; CORE_REG_COUNT]) -> Self {
+        Self { regs }
+    }
+
+    pub fn as_array(&self) -> &[u64; CORE_REG_COUNT] {
+        &self.regs
+    }
+
+    /// `from_saved` の逆変換: 保存領域の順の汎用レジスタと (rip, rflags, rsp)
+    pub fn to_saved(&self) -> ([u64; 15], u64, u64, u64) {
+        let mut gprs = [0u64; 15];
+        gprs[..4].copy_from_slice(&self.regs[..4]);
+        gprs[4..8].copy_from_slice(&self.regs[6..10]);
+        gprs[8] = self.regs[UR_RDI];
+        gprs[9] = self.regs[UR_RSI];
+        gprs[10] = self.regs[UR_RBP];
+        gprs[11] = self.regs[UR_RBX];
+        gprs[12] = self.regs[UR_RDX];
+        gprs[13] = self.regs[UR_RCX];
+        gprs[14] = self.regs[UR_RAX];
+        (
+            gprs,
+            self.regs[UR_RIP],
+            self.regs[UR_EFLAGS],
+            self.regs[UR_RSP],
+        )
+    }
+
+    pub fn orig_rax(&self) -> u64 {
+        self.regs[UR_ORIG_RAX]
+    }
+
+    pub fn set_orig_rax(&mut self, value: u64) {
+        self.regs[UR_ORIG_RAX] = value;
+    }
+
+    pub fn fs_base(&self) -> u64 {
+        self.regs[UR_FS_BASE]
+    }
+
+    pub fn set_fs_base(&mut self, value: u64) {
+        self.regs[UR_FS_BASE] = value;
+    }
+}
+
+/// 現在の出力先ディレクトリ
+fn core_dir() -> String {
+    let dir = CORE_DIR.lock();
+    if dir.is_empty() {
+        String::from(DEFAULT_CORE_DIR)
+    } else {
+        dir.clone()
+    }
+}
+
+/// get_core_dump_dir システムコール
+///
+/// NUL 終端した出力先ディレクトリを `buf` に書き、NUL を除いた長さを返す。
+pub fn get_core_dir(buf_ptr: u64, len: u64) -> u64 {
+    let dir = core_dir();
+    let needed = dir.len() as u64 + 1;
+    if len < needed {
+        return ERANGE;
+    }
+    if !crate::syscall::validate_user_ptr(buf_ptr, needed) {
+        return EFAULT;
+    }
+    let mut out = Vec::with_capacity(needed as usize);
+    out.extend_from_slice(dir.as_bytes());
+    out.push(0);
+    match crate::syscall::copy_to_user(buf_ptr, &out) {
+        Ok(()) => dir.len() as u64,
+        Err(e) => e,
+    }
+}
+
+/// set_core_dump_dir システムコール（CAP_SYS_CONFIG 専用）
+///
+/// 絶対パスのみ受け付ける。ディレクトリ自体は事前に作っておく必要がある。
+pub fn set_core_dir(path_ptr: u64) -> u64 {
+    if !crate::task::current_has(crate::task::capability::CAP_SYS_CONFIG) {
+        crate::audit::log(
+            crate::audit::AuditEventKind::Deny,
+            "set_core_dump_dir: caller lacks CAP_SYS_CONFIG",
+        );
+        return EPERM;
+    }
+    let path = match crate::syscall::read_user_cstring(path_ptr, CORE_DIR_MAX) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let trimmed = path.trim_end_matches('/');
+    if !path.starts_with('/') || trimmed.is_empty() {
+        return EINVAL;
+    }
+    *CORE_DIR.lock() = String::from(trimmed);
+    crate::audit::log(
+        crate::audit::AuditEventKind::Policy,
+        "set_core_dump_dir: core dump directory updated",
+    );
+    SUCCESS
+}
+
+/// ダンプ対象プロセスの情報
+struct DumpTarget {
+    name: String,
+    pid: u64,
+    ppid: u64,
+    pgid: u64,
+    sid: u64,
+    page_table: u64,
+    limit: u64,
+    auxv: (u64, u64),
+    pending: u64,
+    blocked: u64,
+    usage: crate::task::CpuUsage,
+    children_usage: crate::task::CpuUsage,
+}
+
+fn current_target() -> Option<DumpTarget> {
+    let tid = crate::task::current_thread_id()?;
+    let pid = crate::task::with_thread(tid, |t| t.process_id())?;
+    crate::task::with_process(pid, |p| {
+        let state = p.signal_state();
+        Some(DumpTarget {
+            name: String::from(p.name()),
+            pid: pid.as_u64(),
+            ppid: p.parent_id().map(|id| id.as_u64()).unwrap_or(0),
+            pgid: p.pgid(),
+            sid: p.sid(),
+            page_table: p.page_table()?,
+            limit: p.rlimits().get(RLIMIT_CORE).map(|l| l.cur).unwrap_or(0),
+            auxv: p.auxv(),
+            pending: state.pending,
+            blocked: state.mask,
+            usage: p.usage(),
+            children_usage: p.children_usage(),
+        })
+    })
+    .flatten()
+}
+
+/// 異常終了する現在のプロセスのコアダンプを書き出す
+///
+/// 失敗してもログを残すだけで、呼び出し側はそのままプロセスを終了させる。
+pub fn dump_current(info: &SigInfo, mut regs: CoreRegs) {
+    let Some(target) = current_target() else {
+        return;
+    };
+    if target.limit == 0 {
+        return;
+    }
+    // fs.service 自身のダンプは自分宛ての IPC 待ちになるので書けない
+    let fs_pid = super::fs::fs_service_tid().and_then(|tid| {
+        crate::task::with_thread(crate::task::ThreadId::from_u64(tid), |t| {
+            t.process_id().as_u64()
+        })
+    });
+    if fs_pid == Some(target.pid) {
+        crate::warn!("coredump: skipping fs.service (pid={})", target.pid);
+        return;
+    }
+    if let Some(tid) = crate::task::current_thread_id() {
+        if let Some(fs_base) = crate::task::with_thread(tid, |t| t.fs_base()) {
+            regs.set_fs_base(fs_base);
+        }
+    }
+
+    let file_name: String = target
+        .name
+        .chars()
+        .map(|c| if c == '/' { '_' } else { c })
+        .collect();
+    let path = alloc::format!("{}/core.{}.{}", core_dir(), file_name, target.pid);
+
+    let tid = crate::task::current_thread_id().map(|t| t.as_u64());
+    if let Some(tid) = tid {
+        DUMPING.lock().push(tid);
+    }
+    let result = write_core(&path, &target, info, &regs);
+    if let Some(tid) = tid {
+        DUMPING.lock().retain(|&t| t != tid);
+    }
+    match result {
+        Ok(size) => crate::info!(
+            "coredump: pid={} sig={} -> {} ({} bytes)",
+            target.pid,
+            info.signo,
+            path,
+            size
+        ),
+        Err(e) => {
+            crate::warn!(
+                "coredump: pid={} sig={} failed to write {}: errno={}",
+                target.pid,
+                info.signo,
+                path,
+                -(e as i64)
+            );
+            crate::audit::log(
+                crate::audit::AuditEventKind::Fault,
+                "coredump: failed to write core file",
+            );
+        }
+    }
+}
+
+/// fs.service のファイルへ 4KiB 単位でまとめて書く（上限を超える分は捨てる）
+struct CoreWriter {
+    fd: u64,
+    buf: Vec<u8>,
+    written: u64,
+    limit: u64,
+}
+
+impl CoreWriter {
+    fn write(&mut self, data: &[u8]) -> Result<(), u64> {
+        let room = self.limit.saturating_sub(self.written) as usize;
+        let data = &data[..core::cmp::min(data.len(), room)];
+        self.written += data.len() as u64;
+        for chunk in data.chunks(PAGE_SIZE as usize) {
+            let space = PAGE_SIZE as usize - self.buf.len();
+            let head = core::cmp::min(space, chunk.len());
+            self.buf.extend_from_slice(&chunk[..head]);
+            if self.buf.len() == PAGE_SIZE as usize {
+                self.flush()?;
+            }
+            self.buf.extend_from_slice(&chunk[head..]);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), u64> {
+        let mut done = 0;
+        while done < self.buf.len() {
+            let n = super::fs::write_via_fs_service(self.fd, &self.buf[done..])?;
+            if n == 0 {
+                return Err(EIO);
+            }
+            done += n;
+        }
+        self.buf.clear();
+        Ok(())
+    }
+
+    fn is_full(&self) -> bool {
+        self.written >= self.limit
+    }
+}
+
+fn write_core(
+    path: &str,
+    target: &DumpTarget,
+    info: &SigInfo,
+    regs: &CoreRegs,
+) -> Result<u64, u64> {
+    let regions = crate::mem::paging::user_regions_in_table(target.page_table).map_err(|_| EIO)?;
+    // e_phnum は 16bit（PN_XNUM は使わない）
+    let regions = &regions[..core::cmp::min(regions.len(), u16::MAX as usize - 1)];
+    let notes = build_notes(target, info, regs);
+
+    let phnum = 1 + regions.len();
+    let notes_off = EHDR_SIZE + phnum * PHDR_SIZE;
+    let data_off = (notes_off + notes.len()).next_multiple_of(PAGE_SIZE as usize) as u64;
+
+    let mut head = vec![0u8; notes_off];
+    put_ehdr(&mut head[..EHDR_SIZE], phnum as u16);
+    put_phdr(
+        &mut head[EHDR_SIZE..EHDR_SIZE + PHDR_SIZE],
+        PT_NOTE,
+        0,
+        notes_off as u64,
+        0,
+        notes.len() as u64,
+        4,
+    );
+    let mut offset = data_off;
+    for (i, region) in regions.iter().enumerate() {
+        let mut flags = PF_R;
+        if region.writable {
+            flags |= PF_W;
+        }
+        if region.executable {
+            flags |= PF_X;
+        }
+        let at = EHDR_SIZE + (i + 1) * PHDR_SIZE;
+        put_phdr(
+            &mut head[at..at + PHDR_SIZE],
+            PT_LOAD,
+            flags,
+            offset,
+            region.start,
+            region.len,
+            PAGE_SIZE,
+        );
+        offset += region.len;
+    }
+
+    let fd = super::fs::create_via_fs_service(path)?;
+    let mut writer = CoreWriter {
+        fd,
+        buf: Vec::with_capacity(PAGE_SIZE as usize),
+        written: 0,
+        limit: target.limit,
+    };
+    let result = (|| {
+        writer.write(&head)?;
+        writer.write(&notes)?;
+        let pad = data_off as usize - notes_off - notes.len();
+        writer.write(&vec![0u8; pad])?;
+
+        let mut page = vec![0u8; PAGE_SIZE as usize];
+        'regions: for region in regions {
+            let mut addr = region.start;
+            while addr < region.start + region.len {
+                if writer.is_full() {
+                    break 'regions;
+                }
+                // 読めないページ（途中で解放されたなど）は 0 で埋める
+                if crate::mem::paging::copy_from_user_in_table(target.page_table, addr, &mut page)
+                    .is_err()
+                {
+                    page.fill(0);
+                }
+                writer.write(&page)?;
+                addr += PAGE_SIZE;
+            }
+        }
+        writer.flush()
+    })();
+    super::fs::close_remote_fd_from_kernel(fd);
+    result.map(|_| writer.written)
+}
+
+fn put_u16(out: &mut [u8], off: usize, v: u16) {
+    out[off..off + 2].copy_from_slice(&v.to_le_bytes());
+}
+
+fn put_u32(out: &mut [u8], off: usize, v: u32) {
+    out[off..off + 4].copy_from_slice(&v.to_le_bytes());
+}
+
+fn put_u64(out: &mut [u8], off: usize, v: u64) {
+    out[off..off + 8].copy_from_slice(&v.to_le_bytes());
+}
+
+fn put_ehdr(out: &mut [u8], phnum: u16) {
+    out[..4].copy_from_slice(b"\x7fELF");
+    out[4] = 2; // ELFCLASS64
+    out[5] = 1; // ELFDATA2LSB
+    out[6] = 1; // EV_CURRENT
+    put_u16(out, 16, ET_CORE);
+    put_u16(out, 18, EM_X86_64);
+    put_u32(out, 20, 1);
+    put_u64(out, 32, EHDR_SIZE as u64); // e_phoff
+    put_u16(out, 52, EHDR_SIZE as u16);
+    put_u16(out, 54, PHDR_SIZE as u16);
+    put_u16(out, 56, phnum);
+    put_u16(out, 58, 64); // e_shentsize
+}
+
+fn put_phdr(
+    out: &mut [u8],
+    p_type: u32,
+    flags: u32,
+    offset: u64,
+    vaddr: u64,
+    size: u64,
+    align: u64,
+) {
+    put_u32(out, 0, p_type);
+    put_u32(out, 4, flags);
+    put_u64(out, 8, offset);
+    put_u64(out, 16, vaddr);
+    put_u64(out, 24, 0); // p_paddr
+    put_u64(out, 32, size); // p_filesz
+    put_u64(out, 40, size); // p_memsz
+    put_u64(out, 48, align);
+}
+
+/// "CORE" 名のノートを 1 つ追加する（名前・本体とも 4 バイト境界に揃える）
+fn push_note(out: &mut Vec<u8>, n_type: u32, desc: &[u8]) {
+    const NAME: &[u8] = b"CORE\0";
+    out.extend_from_slice(&(NAME.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    out.extend_from_slice(&n_type.to_le_bytes());
+    out.extend_from_slice(NAME);
+    out.resize(out.len().next_multiple_of(4), 0);
+    out.extend_from_slice(desc);
+    out.resize(out.len().next_multiple_of(4), 0);
+}
+
+/// tick 数を struct timeval に変換して書く（1 tick = 10ms）
+fn put_timeval(out: &mut [u8], off: usize, ticks: u64) {
+    put_u64(out, off, ticks / 100);
+    put_u64(out, off + 8, (ticks % 100) * 10_000);
+}
+
+fn build_notes(target: &DumpTarget, info: &SigInfo, regs: &CoreRegs) -> Vec<u8> {
+    let mut notes = Vec::new();
+
+    let mut prstatus = [0u8; PRSTATUS_SIZE];
+    put_u32(&mut prstatus, 0, info.signo as u32);
+    put_u32(&mut prstatus, 4, info.code as u32);
+    put_u32(&mut prstatus, 8, info.errno as u32);
+    put_u16(&mut prstatus, 12, info.signo as u16); // pr_cursig
+    put_u64(&mut prstatus, 16, target.pending);
+    put_u64(&mut prstatus, 24, target.blocked);
+    put_u32(&mut prstatus, 32, target.pid as u32);
+    put_u32(&mut prstatus, 36, target.ppid as u32);
+    put_u32(&mut prstatus, 40, target.pgid as u32);
+    put_u32(&mut prstatus, 44, target.sid as u32);
+    put_timeval(&mut prstatus, 48, target.usage.user_ticks);
+    put_timeval(&mut prstatus, 64, target.usage.system_ticks);
+    put_timeval(&mut prstatus, 80, target.children_usage.user_ticks);
+    put_timeval(&mut prstatus, 96, target.children_usage.system_ticks);
+    for (i, reg) in regs.regs.iter().enumerate() {
+        put_u64(&mut prstatus, PRSTATUS_REG + i * 8, *reg);
+    }
+    // pr_fpvalid は 0（FPU 状態は残さない）
+    push_note(&mut notes, NT_PRSTATUS, &prstatus);
+
+    let mut prpsinfo = [0u8; PRPSINFO_SIZE];
+    prpsinfo[1] = b'R'; // pr_sname
+    put_u32(&mut prpsinfo, 24, target.pid as u32);
+    put_u32(&mut prpsinfo, 28, target.ppid as u32);
+    put_u32(&mut prpsinfo, 32, target.pgid as u32);
+    put_u32(&mut prpsinfo, 36, target.sid as u32);
+    let name = target.name.rsplit('/').next().unwrap_or("").as_bytes();
+    let fname_len = core::cmp::min(name.len(), 15);
+    prpsinfo[40..40 + fname_len].copy_from_slice(&name[..fname_len]);
+    let args_len = core::cmp::min(target.name.len(), 79);
+    prpsinfo[56..56 + args_len].copy_from_slice(&target.name.as_bytes()[..args_len]);
+    push_note(&mut notes, NT_PRPSINFO, &prpsinfo);
+
+    push_note(&mut notes, NT_SIGINFO, &info.to_siginfo_bytes());
+
+    let (auxv_addr, auxv_len) = target.auxv;
+    if auxv_addr != 0 && auxv_len != 0 {
+        let mut auxv = vec![0u8; core::cmp::min(auxv_len, AUXV_MAX) as usize];
+        if crate::mem::paging::copy_from_user_in_table(target.page_table, auxv_addr, &mut auxv)
+            .is_ok()
+        {
+            push_note(&mut notes, NT_AUXV, &auxv);
+        }
+    }
+
+    notes
+}
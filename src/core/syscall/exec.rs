@@ -1,4 +1,5 @@
 use crate::elf::loader as elf_loader;
+use crate::task::capability::{CapabilitySet, CAP_KILL_ANY, CAP_SPAWN_SERVICE};
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec;
@@ -10,6 +11,8 @@ use x86_64::structures::paging::Mapper;
 /// `.service` 実行を許可するサービスマネージャープロセスID
 /// 0 は未登録。
 static SERVICE_MANAGER_PID: AtomicU64 = AtomicU64::new(0);
+/// カーネルが起動するサービスマネージャーの initfs 上のパス（プロセス名も同じ）
+const SERVICE_MANAGER_PATH: &str = "core.service";
 const EM_X86_64: u16 = 0x3E;
 static EXEC_ASLR_COUNTER: AtomicU64 = AtomicU64::new(0);
 const STACK_TOP_BASE: u64 = 0x0000_7FFF_FFF0_0000;
@@ -23,6 +26,9 @@ struct InitialUserStack {
     stack_base_vaddr: u64,
     stack_end_vaddr: u64,
     initial_rsp: u64,
+    /// auxv の先頭アドレスとバイト数（コアダンプ用に記録する）
+    auxv_vaddr: u64,
+    auxv_len: u64,
     page_data: Vec<u8>,
 }
 
@@ -51,6 +57,16 @@ pub fn register_service_manager_pid(pid: u64) {
     SERVICE_MANAGER_PID.store(pid, Ordering::SeqCst);
 }
 
+/// 登録中のサービスマネージャーPID（未登録なら 0）
+pub fn service_manager_pid() -> u64 {
+    SERVICE_MANAGER_PID.load(Ordering::SeqCst)
+}
+
+/// `pid` がサービスマネージャーなら登録を外し、core.service を起動し直せるようにする
+pub fn release_service_manager_pid(pid: u64) {
+    let _ = SERVICE_MANAGER_PID.compare_exchange(pid, 0, Ordering::SeqCst, Ordering::SeqCst);
+}
+
 #[inline]
 fn aslr_mix64(mut x: u64) -> u64 {
     x ^= x >> 30;
@@ -100,32 +116,32 @@ fn caller_can_launch_service() -> bool {
         return true;
     };
 
-    if crate::task::with_process(caller_pid, |p| {
-        p.privilege() == crate::task::PrivilegeLevel::Core
-    })
-    .unwrap_or(false)
-    {
-        return true;
-    }
-
-    let manager_pid_raw = SERVICE_MANAGER_PID.load(Ordering::SeqCst);
-    if manager_pid_raw == 0 || caller_pid.as_u64() != manager_pid_raw {
-        return false;
-    }
-    let manager_pid = crate::task::ProcessId::from_u64(manager_pid_raw);
-    crate::task::with_process(manager_pid, |p| {
+    // サービスマネージャーは登録時に CAP_SPAWN_SERVICE を受け取る
+    crate::task::with_process(caller_pid, |p| {
         let state = p.state();
         let alive = state != crate::task::ProcessState::Zombie
             && state != crate::task::ProcessState::Terminated;
-        let privileged = matches!(
-            p.privilege(),
-            crate::task::PrivilegeLevel::Service | crate::task::PrivilegeLevel::Core
-        );
-        alive && privileged
+        alive && p.capabilities().has(CAP_SPAWN_SERVICE)
     })
     .unwrap_or(false)
 }
 
+/// カーネル（Core 権限のプロセスかプロセスを持たないスレッド）からの呼び出しか
+fn caller_is_kernel() -> bool {
+    crate::task::current_thread_id()
+        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
+        .and_then(|pid| crate::task::with_process(pid, |p| p.privilege()))
+        .is_none_or(|lvl| lvl == crate::task::PrivilegeLevel::Core)
+}
+
+/// カーネルが initfs から起動するサービスマネージャーか
+///
+/// 名前の末尾では判定せず、initfs のパスと名前が完全に一致し、かつカーネルが
+/// 起動したものだけをサービスマネージャーとみなす。
+fn is_service_manager_exec(process_name: &str, exec_path: &str) -> bool {
+    process_name == SERVICE_MANAGER_PATH && exec_path == SERVICE_MANAGER_PATH && caller_is_kernel()
+}
+
 fn caller_is_service_or_core() -> bool {
     crate::task::current_thread_id()
         .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
@@ -171,6 +187,30 @@ fn read_nul_args_from_user(
 /// カーネル内から実行可能ファイルを読み込み実行するシステムコール
 /// args_ptr: ヌル区切り引数文字列へのポインタ（"arg1\0arg2\0\0"形式）、0 なら引数なし
 pub fn exec_kernel(path_ptr: u64, args_ptr: u64) -> u64 {
+    exec_kernel_env(path_ptr, args_ptr, 0)
+}
+
+/// 環境変数付きで実行可能ファイルを実行するシステムコール
+/// env_ptr: ヌル区切りの "KEY=VALUE" 文字列へのポインタ（args_ptr と同じ形式）、0 なら空
+pub fn exec_kernel_env(path_ptr: u64, args_ptr: u64, env_ptr: u64) -> u64 {
+    exec_kernel_env_caps(path_ptr, args_ptr, env_ptr, None)
+}
+
+/// ケーパビリティを指定して実行可能ファイルを実行するシステムコール
+/// caps_ptr: `CAP_WIRE_SIZE` バイトのケーパビリティ集合。最初の命令が走る前から子に適用される
+pub fn exec_kernel_with_caps(path_ptr: u64, args_ptr: u64, env_ptr: u64, caps_ptr: u64) -> u64 {
+    match super::capability::read_exec_capabilities(caps_ptr) {
+        Ok(caps) => exec_kernel_env_caps(path_ptr, args_ptr, env_ptr, Some(caps)),
+        Err(e) => e,
+    }
+}
+
+fn exec_kernel_env_caps(
+    path_ptr: u64,
+    args_ptr: u64,
+    env_ptr: u64,
+    caps: Option<CapabilitySet>,
+) -> u64 {
     let mut provided_path: Option<String> = None;
     if path_ptr != 0 {
         let path = match crate::syscall::read_user_cstring(path_ptr, 256) {
@@ -191,29 +231,41 @@ pub fn exec_kernel(path_ptr: u64, args_ptr: u64) -> u64 {
         Err(e) => return e,
     };
     let extra_args: Vec<&str> = extra_args_owned.iter().map(|s| s.as_str()).collect();
-    exec_internal(path, None, &extra_args)
+    let envs_owned = match read_nul_args_from_user(env_ptr, 1024, 64) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    if envs_owned.iter().any(|e| !e.contains('=')) {
+        return crate::syscall::types::EINVAL;
+    }
+    let envs: Vec<&str> = envs_owned.iter().map(|s| s.as_str()).collect();
+    exec_internal(path, None, &extra_args, &envs, caps)
 }
 
 /// 名前を指定してカーネル内から実行可能ファイルを実行する（カーネル内部用）
 pub fn exec_kernel_with_name(path: &str, name: &str) -> u64 {
-    exec_internal(path, Some(name), &[])
+    exec_internal(path, Some(name), &[], &[], None)
 }
 
-fn exec_internal(path: &str, name_override: Option<&str>, args: &[&str]) -> u64 {
+fn exec_internal(
+    path: &str,
+    name_override: Option<&str>,
+    args: &[&str],
+    envs: &[&str],
+    caps: Option<CapabilitySet>,
+) -> u64 {
     let mut process_name = name_override
         .map(|s| s.to_string())
         .unwrap_or_else(|| path.rsplit('/').next().unwrap_or(path).to_string());
     // Special-case mapping: drivers/net.elf should be exposed as "netdrv" for compatibility
-    if process_name == "net"
-        || process_name == "net.elf"
-        || path.ends_with("/bin/drivers/net.elf")
+    if process_name == "net" || process_name == "net.elf" || path.ends_with("/bin/drivers/net.elf")
     {
         process_name = "netdrv".to_string();
     }
     if let Some(data) = crate::init::fs::read(path) {
-        exec_with_data(&data, &process_name, path, args, None)
+        exec_with_data_env(&data, &process_name, path, args, envs, None, caps)
     } else if let Some(data) = crate::kmod::fs::read_all(path) {
-        exec_with_data(&data, &process_name, path, args, None)
+        exec_with_data_env(&data, &process_name, path, args, envs, None, caps)
     } else {
         crate::warn!("exec: file not found: {}", path);
         crate::syscall::types::ENOENT
@@ -257,6 +309,7 @@ fn resolve_exec_privilege(process_name: &str, exec_path: &str) -> crate::task::P
     // .service は従来通り Service 権限で実行。
     // bin/drivers 配下は Service/Core 呼び出し元からの起動時に Service 権限を付与する。
     // Kagami / ViewKit / Binder / Dock はデスクトップ描画のため Service 権限を付与する。
+    // gdbserver はデバッグ用シリアルポートを直接叩くため Service 権限を付与する。
     let is_driver_path =
         exec_path.starts_with("bin/drivers/") || exec_path.starts_with("/bin/drivers/");
     let is_kagami_viewkit_path = matches!(
@@ -265,10 +318,12 @@ fn resolve_exec_privilege(process_name: &str, exec_path: &str) -> crate::task::P
             | "/applications/ViewKit.app/entry.elf"
             | "/applications/Binder.app/entry.elf"
             | "/applications/Dock.app/entry.elf"
+            | "/applications/gdbserver.app/entry.elf"
             | "applications/Kagami.app/entry.elf"
             | "applications/ViewKit.app/entry.elf"
             | "applications/Binder.app/entry.elf"
             | "applications/Dock.app/entry.elf"
+            | "applications/gdbserver.app/entry.elf"
     );
     if process_name.ends_with(".service")
         || is_kagami_viewkit_path
@@ -280,6 +335,28 @@ fn resolve_exec_privilege(process_name: &str, exec_path: &str) -> crate::task::P
     }
 }
 
+/// 新プロセスに与えるケーパビリティを決める
+///
+/// 権限レベルの既定値を、起動元プロセスが持つ集合で絞り込む。
+/// サービスマネージャー（core.service）だけは全権限を受け取る。
+/// svcctl は core.service にサービスの起動・停止を頼むので、起動元が持っていれば
+/// CAP_SPAWN_SERVICE と CAP_KILL_ANY も引き継ぐ。
+fn resolve_exec_capabilities(
+    privilege: crate::task::PrivilegeLevel,
+    is_core_service: bool,
+    exec_path: &str,
+) -> CapabilitySet {
+    let spawner = crate::task::current_capabilities();
+    if is_core_service {
+        return spawner;
+    }
+    let mut caps = CapabilitySet::default_for(privilege);
+    if matches!(exec_path, "/bin/svcctl" | "bin/svcctl") {
+        caps = caps.with(CAP_SPAWN_SERVICE).with(CAP_KILL_ANY);
+    }
+    caps.intersect(&spawner)
+}
+
 fn map_initial_tls(table_phys: u64, aslr_seed: u64) -> Result<u64, u64> {
     let tls_base = TLS_BASE_MIN
         .saturating_add(aslr_offset_pages(aslr_seed ^ 0x19d7_3c6a, TLS_ASLR_MAX_PAGES) * 4096);
@@ -405,10 +482,13 @@ fn build_initial_user_stack(
         return Err(crate::syscall::types::EINVAL);
     }
 
+    let auxv_vaddr = initial_rsp + 8 + (argv.len() as u64 * 8) + 8 + (envp.len() as u64 * 8) + 8;
     Ok(InitialUserStack {
         stack_base_vaddr,
         stack_end_vaddr,
         initial_rsp,
+        auxv_vaddr,
+        auxv_len: auxv_entries.len() as u64 * 16,
         page_data,
     })
 }
@@ -424,6 +504,29 @@ fn exec_with_data(
     exec_path: &str,
     args: &[&str],
     parent_override: Option<crate::task::ProcessId>,
+) -> u64 {
+    exec_with_data_env(
+        data,
+        process_name,
+        exec_path,
+        args,
+        &[],
+        parent_override,
+        None,
+    )
+}
+
+/// 環境変数（"KEY=VALUE" の列）付きで ELF データから新プロセスを起動する
+///
+/// `caps` が与えられればそれを、無ければ権限レベルの既定値を新プロセスに与える。
+fn exec_with_data_env(
+    data: &[u8],
+    process_name: &str,
+    exec_path: &str,
+    args: &[&str],
+    envs: &[&str],
+    parent_override: Option<crate::task::ProcessId>,
+    caps: Option<CapabilitySet>,
 ) -> u64 {
     crate::debug!("exec: name={}", process_name);
     let aslr_seed = next_aslr_seed(process_name);
@@ -711,7 +814,7 @@ fn exec_with_data(
                 argv1
             );
         }
-        let envs: [&str; 0] = [];
+        let sysinfo_ehdr = crate::mem::vdso::sysinfo_ehdr().unwrap_or(0);
         let auxv_entries = [
             (3u64, phdr_vaddr),
             (4u64, phentsize),
@@ -729,14 +832,17 @@ fn exec_with_data(
             (23u64, 0u64),
             (25u64, 0u64),
             (31u64, 0u64),
+            (33u64, sysinfo_ehdr), // AT_SYSINFO_EHDR
             (0u64, 0u64),
         ];
         let InitialUserStack {
             stack_base_vaddr,
             stack_end_vaddr,
             initial_rsp,
+            auxv_vaddr,
+            auxv_len,
             page_data,
-        } = match build_initial_user_stack(aslr_seed, &all_args, &envs, exec_path, &auxv_entries) {
+        } = match build_initial_user_stack(aslr_seed, &all_args, envs, exec_path, &auxv_entries) {
             Ok(stack) => stack,
             Err(errno) => return errno,
         };
@@ -867,10 +973,18 @@ fn exec_with_data(
                 .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
         });
         let privilege = resolve_exec_privilege(process_name, exec_path);
+        let is_core_service = is_service_manager_exec(process_name, exec_path);
         let mut proc = crate::task::Process::new(process_name, privilege, parent_pid, 0);
+        proc.set_capabilities(
+            caps.unwrap_or_else(|| {
+                resolve_exec_capabilities(privilege, is_core_service, exec_path)
+            }),
+        );
         proc.set_page_table(new_pt_phys);
         proc.set_stack_bottom(stack_base_vaddr);
         proc.set_stack_top(stack_end_vaddr + 4096);
+        proc.set_auxv(auxv_vaddr, auxv_len);
+        proc.set_symbols(crate::task::load_symbols(data, 0));
         crate::info!(
             "[STACK_INIT] {}: stack_base={:#x}, stack_end={:#x}, stack_top={:#x}",
             proc.name(),
@@ -898,7 +1012,6 @@ fn exec_with_data(
             Err(errno) => return errno,
         };
         let pid = proc.id();
-        let is_core_service = process_name.ends_with("core.service");
         if is_core_service
             && SERVICE_MANAGER_PID
                 .compare_exchange(0, pid.as_u64(), Ordering::SeqCst, Ordering::SeqCst)
@@ -1079,7 +1192,7 @@ pub fn execve_syscall(path_ptr: u64, argv: u64, envp: u64) -> u64 {
     let path = path_owned.as_str();
     let aslr_seed = next_aslr_seed(path);
 
-    // サービス起動はサービスマネージャー(Coreまたは登録PID)に限定
+    // サービス起動は CAP_SPAWN_SERVICE を持つプロセス（サービスマネージャー等）に限定
     if path.ends_with(".service") && !caller_can_launch_service() {
         return EPERM;
     }
@@ -1197,6 +1310,7 @@ pub fn execve_syscall(path_ptr: u64, argv: u64, envp: u64) -> u64 {
     let argc = argv_strings.len();
     let argv_refs: Vec<&str> = argv_strings.iter().map(|s| s.as_str()).collect();
     let envp_refs: Vec<&str> = envp_strings.iter().map(|s| s.as_str()).collect();
+    let sysinfo_ehdr = crate::mem::vdso::sysinfo_ehdr().unwrap_or(0);
     let auxv_entries = [
         (3u64, phdr_vaddr),
         (4u64, phentsize),
@@ -1214,12 +1328,15 @@ pub fn execve_syscall(path_ptr: u64, argv: u64, envp: u64) -> u64 {
         (23u64, 0u64),
         (25u64, 0u64),
         (31u64, 0u64),
+        (33u64, sysinfo_ehdr), // AT_SYSINFO_EHDR
         (0u64, 0u64),
     ];
     let InitialUserStack {
         stack_base_vaddr,
         stack_end_vaddr,
         initial_rsp,
+        auxv_vaddr,
+        auxv_len,
         page_data,
     } = match build_initial_user_stack(
         aslr_seed,
@@ -1294,6 +1411,8 @@ pub fn execve_syscall(path_ptr: u64, argv: u64, envp: u64) -> u64 {
         None => return EINVAL,
     };
     crate::task::with_thread_mut(current_tid, |t| t.set_fs_base(initial_fs_base));
+    // 新しいイメージのシンボル表（ロックを取る前に読んでおく）
+    let symbols = crate::task::load_symbols(data, 0);
     let old_pt_phys = crate::task::with_process_mut(pid, |p| {
         let prev = p.page_table();
         p.set_page_table(new_pt_phys);
@@ -1301,6 +1420,8 @@ pub fn execve_syscall(path_ptr: u64, argv: u64, envp: u64) -> u64 {
         p.set_heap_end(heap_base + heap_map_size);
         p.set_stack_bottom(stack_base_vaddr);
         p.set_stack_top(stack_end_vaddr + 4096);
+        p.set_auxv(auxv_vaddr, auxv_len);
+        p.set_symbols(symbols);
         crate::info!(
             "[STACK_INIT] {}: stack_base={:#x}, stack_end={:#x}, stack_top={:#x}",
             p.name(),
@@ -1324,6 +1445,8 @@ pub fn execve_syscall(path_ptr: u64, argv: u64, envp: u64) -> u64 {
     // 新しいページテーブルに切り替えてジャンプ
     unsafe {
         crate::mem::paging::switch_page_table(new_pt_phys);
+        // トレース中なら新しいイメージの先頭で止まり、トレーサーに知らせる
+        let (entry, initial_rsp) = crate::syscall::ptrace::exec_stop(entry, initial_rsp);
         crate::task::jump_to_usermode(entry, initial_rsp);
     }
 }
@@ -1501,12 +1624,12 @@ pub fn exec_from_buffer_named_args_with_requester_syscall(
         };
         match crate::task::with_thread(requester, |t| t.process_id()) {
             Some(pid) => {
-                let caller_is_core = crate::task::with_process(caller_pid, |p| {
-                    p.privilege() == crate::task::PrivilegeLevel::Core
+                let caller_can_spawn = crate::task::with_process(caller_pid, |p| {
+                    p.capabilities().has(CAP_SPAWN_SERVICE)
                 })
                 .unwrap_or(false);
 
-                if pid != caller_pid && !caller_is_core {
+                if pid != caller_pid && !caller_can_spawn {
                     return EPERM;
                 }
                 Some(pid)
@@ -1,6 +1,9 @@
 //! ファイルシステム関連のシステムコール
 
-use super::types::{EBADF, EEXIST, EFAULT, EINVAL, EIO, ENOENT, ENOSYS, ENOTDIR, ESRCH, SUCCESS};
+use super::types::{
+    EACCES, EBADF, EEXIST, EFAULT, EINVAL, EIO, ENOENT, ENOSYS, ENOTDIR, EPERM, ESRCH, SUCCESS,
+};
+use crate::task::capability::CAP_FS_WRITE;
 use crate::task::fd_table::{FdTable, FileHandle, FD_BASE, O_CLOEXEC, PROCESS_MAX_FDS};
 use alloc::string::String;
 use alloc::string::ToString;
@@ -53,6 +56,7 @@ pub(crate) struct FsRequest {
 impl FsRequest {
     pub(crate) const OP_OPEN: u64 = 1;
     pub(crate) const OP_READ: u64 = 2;
+    pub(crate) const OP_WRITE: u64 = 3;
     pub(crate) const OP_CLOSE: u64 = 4;
     pub(crate) const OP_STAT: u64 = 6;
     pub(crate) const OP_FSTAT: u64 = 7;
@@ -73,6 +77,11 @@ static CACHED_FS_SERVICE_TID: core::sync::atomic::AtomicU64 = core::sync::atomic
 
 #[inline]
 pub(crate) fn fs_service_tid() -> Option<u64> {
+    // 登録されたエンドポイントを優先する（終了していれば後継の登録を待つ）
+    if let Some(tid) = super::fs_endpoint::endpoint() {
+        return Some(tid);
+    }
+
     let cached = CACHED_FS_SERVICE_TID.load(core::sync::atomic::Ordering::Acquire);
     if cached != 0 && crate::task::thread_id_exists(cached) {
         return Some(cached);
@@ -115,7 +124,30 @@ fn recv_from_fs_with_timeout(fs_tid: u64, buf: &mut [u8]) -> Result<usize, u64>
     }
 }
 
+/// 応答を待っている間に `fs_tid` が終了したなら、引き継いだ後継の fs.service を返す
+fn fs_service_successor(fs_tid: u64) -> Option<u64> {
+    if crate::task::thread_id_exists(fs_tid) {
+        return None;
+    }
+    fs_service_tid().filter(|&tid| tid != fs_tid)
+}
+
+/// fs.service に要求を送り、応答を受け取る
+///
+/// 応答する前に fs.service が終了したら、後継に 1 度だけ送り直す。引き継ぎでは
+/// 古い fs.service は状態を渡した後に要求を処理しないので、送り直しても二重にはならない
+/// （落ちた場合は、処理し終えて応答する前に落ちたものだけが二重になりうる）。
 pub(crate) fn fs_service_request(fs_tid: u64, req: &FsRequest) -> Result<FsResponse, u64> {
+    match fs_service_request_once(fs_tid, req) {
+        Err(EIO) => match fs_service_successor(fs_tid) {
+            Some(next) => fs_service_request_once(next, req),
+            None => Err(EIO),
+        },
+        result => result,
+    }
+}
+
+fn fs_service_request_once(fs_tid: u64, req: &FsRequest) -> Result<FsResponse, u64> {
     let req_slice = unsafe {
         core::slice::from_raw_parts(
             req as *const _ as *const u8,
@@ -278,6 +310,61 @@ pub(crate) fn close_remote_fd_from_kernel(fd_remote: u64) {
     let _ = close_via_fs_service(fd_remote);
 }
 
+/// fs.service 上にファイルを作成（既存なら切り詰め）し、書き込み用に開く
+pub(crate) fn create_via_fs_service(path: &str) -> Result<u64, u64> {
+    open_via_fs_service(path, O_WRONLY | O_CREAT | O_TRUNC)
+}
+
+/// fs.service のファイルへ書き込む（1 回で最大 FS_DATA_MAX バイト）
+///
+/// OP_WRITE は FsRequest（arg1 = リモート FD, arg2 = 長さ）の直後にデータ本体を続けて
+/// 1 メッセージで送る。例外ハンドラからも呼ばれるので、送受信バッファはヒープに置く。
+pub(crate) fn write_via_fs_service(fd_remote: u64, data: &[u8]) -> Result<usize, u64> {
+    let fs_tid = fs_service_tid().ok_or(ESRCH)?;
+    let len = core::cmp::min(data.len(), FS_DATA_MAX);
+    let req = FsRequest {
+        op: FsRequest::OP_WRITE,
+        arg1: fd_remote,
+        arg2: len as u64,
+        path: [0; FS_PATH_MAX],
+    };
+    let header = unsafe {
+        core::slice::from_raw_parts(
+            &req as *const _ as *const u8,
+            core::mem::size_of::<FsRequest>(),
+        )
+    };
+    let mut msg = Vec::with_capacity(header.len() + len);
+    msg.extend_from_slice(header);
+    msg.extend_from_slice(&data[..len]);
+
+    let mut resp_buf = vec![0u8; core::mem::size_of::<FsResponse>()];
+    let n = match send_write_once(fs_tid, &msg, &mut resp_buf) {
+        // fs_service_request と同じく、引き継いだ後継に 1 度だけ送り直す
+        Err(EIO) => match fs_service_successor(fs_tid) {
+            Some(next) => send_write_once(next, &msg, &mut resp_buf)?,
+            None => return Err(EIO),
+        },
+        result => result?,
+    };
+    // status と len だけ読めればよい
+    if n < 16 {
+        return Err(EIO);
+    }
+    let status = i64::from_ne_bytes(resp_buf[0..8].try_into().map_err(|_| EIO)?);
+    if status < 0 {
+        return Err((-status) as u64);
+    }
+    Ok(core::cmp::min(status as usize, len))
+}
+
+fn send_write_once(fs_tid: u64, msg: &[u8], resp_buf: &mut [u8]) -> Result<usize, u64> {
+    if !crate::syscall::ipc::send_from_kernel(fs_tid, msg) {
+        return Err(EIO);
+    }
+    recv_from_fs_with_timeout(fs_tid, resp_buf)
+}
+
 fn stat_path_via_fs_service(path: &str) -> Result<(u16, u64), u64> {
     let fs_tid = fs_service_tid().ok_or(ESRCH)?;
     let req = FsRequest {
@@ -471,6 +558,45 @@ const O_EXCL: u64 = 0o200;
 const O_TRUNC: u64 = 0o1000;
 const O_APPEND: u64 = 0o2000;
 
+/// fs.service への作成・切り詰め・書き込みの要求か
+///
+/// OP_WRITE と、書き込み・作成・切り詰め・追記のフラグ付きの OP_OPEN が該当する。
+pub(crate) fn is_mutating_fs_request(msg: &[u8]) -> bool {
+    if msg.len() < core::mem::size_of::<FsRequest>() {
+        return false;
+    }
+    let word = |i: usize| u64::from_ne_bytes(msg[i * 8..i * 8 + 8].try_into().unwrap_or([0; 8]));
+    match word(0) {
+        FsRequest::OP_WRITE => true,
+        FsRequest::OP_OPEN => word(2) & (O_ACCMODE | O_CREAT | O_TRUNC | O_APPEND) != 0,
+        _ => false,
+    }
+}
+
+/// `tid` が fs.service のスレッドか
+pub(crate) fn is_fs_service_thread(tid: u64) -> bool {
+    let tid = crate::task::ThreadId::from_u64(tid);
+    crate::task::with_thread(tid, |t| t.process_id())
+        .and_then(|pid| crate::task::with_process(pid, |p| p.name() == "fs.service"))
+        .unwrap_or(false)
+}
+
+/// プロセス `pid`（0 ならどれかのプロセス）が fs.service のハンドル `fd_remote` を
+/// FD テーブルに持っているか
+pub(crate) fn holds_remote_fd(pid: u64, fd_remote: u64) -> bool {
+    let mut found = false;
+    crate::task::for_each_process(|p| {
+        if found || (pid != 0 && p.id().as_u64() != pid) {
+            return;
+        }
+        let table = p.fd_table();
+        found = (FD_BASE..PROCESS_MAX_FDS)
+            .filter_map(|fd| table.get(fd))
+            .any(|fh| fh.is_remote && fh.fd_remote == fd_remote);
+    });
+    found
+}
+
 pub(crate) fn is_tty_like_path(path: &str) -> bool {
     path == "/dev/tty"
         || path == "/dev/console"
@@ -496,14 +622,107 @@ fn make_tty_handle(path: &str) -> alloc::boxed::Box<FileHandle> {
         pipe_id: None,
         pipe_write: false,
         open_flags: O_RDWR,
+        signalfd_mask: None,
     })
 }
 
+/// 永続クラッシュログ（crashlog.rs）を読むための仮想ファイル
+const PROC_CRASH_PATH: &str = "/proc/crash";
+/// カーネルログ（util/kmsg.rs）を読むための仮想ファイル
+const DEV_KMSG_PATH: &str = "/dev/kmsg";
+/// プロファイラ（trace/profile.rs）のサンプルを読むための仮想ファイル
+const PROC_PROFILE_PATH: &str = "/proc/profile";
+
+/// 開いた時点の内容を持つ読み込み専用のハンドルを割り当てる
+fn alloc_snapshot_handle(owner_pid: u64, flags: u64, data: Vec<u8>) -> u64 {
+    let handle = alloc::boxed::Box::new(FileHandle {
+        data: data.into_boxed_slice(),
+        pos: 0,
+        dir_path: None,
+        is_remote: false,
+        fd_remote: 0,
+        remote_refs: None,
+        pipe_id: None,
+        pipe_write: false,
+        open_flags: flags,
+        signalfd_mask: None,
+    });
+    let cloexec = (flags & O_CLOEXEC) != 0;
+    match with_fd_table_mut(owner_pid, |t| t.alloc(handle, cloexec)) {
+        Some(Some(fd)) => fd as u64,
+        _ => ENOSYS,
+    }
+}
+
+/// `/proc/crash` を開く
+///
+/// 読み込みは開いた時点の内容のスナップショット。O_TRUNC 付きで開くと
+/// 記録を消す（CAP_SYS_CONFIG が必要）。それ以外の書き込みは受け付けない。
+fn open_proc_crash(owner_pid: u64, flags: u64) -> u64 {
+    let data = if has_write_intent(flags) {
+        if (flags & O_TRUNC) == 0 {
+            return EPERM;
+        }
+        if !crate::task::current_has_audited(
+            crate::task::capability::CAP_SYS_CONFIG,
+            "clear /proc/crash",
+        ) {
+            return EPERM;
+        }
+        crate::crashlog::clear();
+        Vec::new()
+    } else {
+        crate::crashlog::render().into_bytes()
+    };
+    alloc_snapshot_handle(owner_pid, flags, data)
+}
+
+/// `/dev/kmsg` を開く（開いた時点のカーネルログ。消去は syslog で行う）
+fn open_dev_kmsg(owner_pid: u64, flags: u64) -> u64 {
+    if has_write_intent(flags) {
+        return EPERM;
+    }
+    alloc_snapshot_handle(owner_pid, flags, crate::util::kmsg::read_all().into_bytes())
+}
+
+/// `/proc/profile` を開く
+///
+/// 開くたびにそれまでに溜まったサンプルを取り出し、folded 形式にまとめた内容になる。
+fn open_proc_profile(owner_pid: u64, flags: u64) -> u64 {
+    if has_write_intent(flags) {
+        return EPERM;
+    }
+    alloc_snapshot_handle(
+        owner_pid,
+        flags,
+        crate::trace::profile::render_folded().into_bytes(),
+    )
+}
+
 fn has_write_intent(flags: u64) -> bool {
     let acc = flags & O_ACCMODE;
     acc == O_WRONLY || acc == O_RDWR || (flags & (O_CREAT | O_TRUNC)) != 0
 }
 
+/// 書き込み用に開く（ディスク上のファイルを fs.service で作成・変更する）
+///
+/// CAP_FS_WRITE を持たないプロセスには EACCES を返す。
+fn open_for_write(owner_pid: u64, path: &str, flags: u64) -> u64 {
+    if !crate::task::current_has_audited(CAP_FS_WRITE, "open for writing") {
+        return EACCES;
+    }
+    if (flags & (O_CREAT | O_EXCL)) == (O_CREAT | O_EXCL)
+        && (stat_path_via_fs_service(path).is_ok() || fallback_file_metadata(path).is_some())
+    {
+        return EEXIST;
+    }
+    // カーネル内で管理する FD_CLOEXEC は fs.service へ渡さない。
+    match open_via_fs_service(path, flags & !O_CLOEXEC) {
+        Ok(remote_fd) => install_handle(owner_pid, flags, Vec::new(), None, Some(remote_fd)),
+        Err(errno) => errno,
+    }
+}
+
 fn open_resolved_for_pid(owner_pid: u64, path: &str, flags: u64) -> u64 {
     if is_tty_like_path(path) {
         let cloexec = (flags & O_CLOEXEC) != 0;
@@ -513,42 +732,18 @@ fn open_resolved_for_pid(owner_pid: u64, path: &str, flags: u64) -> u64 {
         };
     }
 
-    if has_write_intent(flags) {
-        let exists_in_service = stat_path_via_fs_service(path).is_ok();
-        let exists_in_fallback = fallback_file_metadata(path).is_some();
-        if (flags & (O_CREAT | O_EXCL)) == (O_CREAT | O_EXCL)
-            && (exists_in_service || exists_in_fallback)
-        {
-            return EEXIST;
-        }
+    if path == PROC_CRASH_PATH {
+        return open_proc_crash(owner_pid, flags);
+    }
+    if path == DEV_KMSG_PATH {
+        return open_dev_kmsg(owner_pid, flags);
+    }
+    if path == PROC_PROFILE_PATH {
+        return open_proc_profile(owner_pid, flags);
+    }
 
-        let mut data_vec = Vec::new();
-        if (flags & O_TRUNC) == 0 && exists_in_fallback {
-            if let Some(d) = crate::kmod::fs::read_all(path) {
-                data_vec = d;
-            }
-        }
-        let start_pos = if (flags & O_APPEND) != 0 {
-            data_vec.len()
-        } else {
-            0
-        };
-        let cloexec = (flags & O_CLOEXEC) != 0;
-        let handle = alloc::boxed::Box::new(FileHandle {
-            data: data_vec.into_boxed_slice(),
-            pos: start_pos,
-            dir_path: None,
-            is_remote: false,
-            fd_remote: 0,
-            remote_refs: None,
-            pipe_id: None,
-            pipe_write: false,
-            open_flags: flags,
-        });
-        return match with_fd_table_mut(owner_pid, |t| t.alloc(handle, cloexec)) {
-            Some(Some(fd)) => fd as u64,
-            _ => ENOSYS,
-        };
+    if has_write_intent(flags) {
+        return open_for_write(owner_pid, path, flags);
     }
 
     // カーネル内で管理する FD_CLOEXEC は fs.service へ渡さない。
@@ -600,27 +795,41 @@ fn open_resolved_for_pid(owner_pid: u64, path: &str, flags: u64) -> u64 {
         }
     };
 
+    install_handle(
+        owner_pid,
+        flags,
+        data_vec,
+        dir_path,
+        is_remote.then_some(fd_remote),
+    )
+}
+
+/// 開いたファイルを FD テーブルに登録する（`remote` は fs.service 側の FD）
+fn install_handle(
+    owner_pid: u64,
+    flags: u64,
+    data_vec: Vec<u8>,
+    dir_path: Option<String>,
+    remote: Option<u64>,
+) -> u64 {
     let cloexec = (flags & O_CLOEXEC) != 0;
     let handle = alloc::boxed::Box::new(FileHandle {
         data: data_vec.into_boxed_slice(),
         pos: 0,
         dir_path,
-        is_remote,
-        fd_remote,
-        remote_refs: if is_remote {
-            Some(Arc::new(AtomicUsize::new(1)))
-        } else {
-            None
-        },
+        is_remote: remote.is_some(),
+        fd_remote: remote.unwrap_or(0),
+        remote_refs: remote.map(|_| Arc::new(AtomicUsize::new(1))),
         pipe_id: None,
         pipe_write: false,
         open_flags: flags,
+        signalfd_mask: None,
     });
 
     match with_fd_table_mut(owner_pid, |t| t.alloc(handle, cloexec)) {
         Some(Some(fd)) => fd as u64,
         _ => {
-            if is_remote {
+            if let Some(fd_remote) = remote {
                 let _ = close_via_fs_service(fd_remote);
             }
             ENOSYS
@@ -1059,7 +1268,7 @@ pub fn read(fd: u64, buf_ptr: u64, len: u64) -> u64 {
     local.len() as u64
 }
 
-/// Write: 開かれたファイルへデータを書き込む（ローカル一時FDのみ）
+/// Write: 開かれたファイルへデータを書き込む（リモートFDは fs.service へ送る）
 pub fn write(fd: u64, buf_ptr: u64, len: u64) -> u64 {
     if buf_ptr == 0 {
         return EFAULT;
@@ -1087,11 +1296,33 @@ pub fn write(fd: u64, buf_ptr: u64, len: u64) -> u64 {
         return errno;
     }
 
+    let (is_remote, fd_remote, open_flags) = match with_fd_table(pid, |t| {
+        t.get(idx)
+            .map(|fh| (fh.is_remote, fh.fd_remote, fh.open_flags))
+    }) {
+        Some(Some(v)) => v,
+        _ => return EBADF,
+    };
+    if is_remote {
+        // 書き込み用に開いたリモート FD（CAP_FS_WRITE を持つプロセスだけが得られる）にだけ書く
+        if !has_write_intent(open_flags) {
+            return EBADF;
+        }
+        // FS_DATA_MAX ずつ送る。途中で失敗したらそこまでに書けた分を返す
+        let mut written = 0;
+        while written < buf.len() {
+            match write_via_fs_service(fd_remote, &buf[written..]) {
+                Ok(0) => break,
+                Ok(n) => written += n,
+                Err(e) if written == 0 => return e,
+                Err(_) => break,
+            }
+        }
+        return written as u64;
+    }
+
     let wrote = with_fd_table_mut(pid, |t| {
         let fh = t.get_mut(idx).ok_or(EBADF)?;
-        if fh.is_remote {
-            return Err(ENOSYS);
-        }
         let end = fh.pos.checked_add(buf.len()).ok_or(EINVAL)?;
         let mut data = fh.data.to_vec();
         if end > data.len() {
@@ -1271,6 +1502,7 @@ pub fn dup(fd: u64) -> u64 {
                 pipe_id: fh.pipe_id,
                 pipe_write: fh.pipe_write,
                 open_flags: fh.open_flags,
+                signalfd_mask: fh.signalfd_mask,
             })
         })
     });
@@ -1330,6 +1562,7 @@ pub fn dup2(old_fd: u64, new_fd: u64) -> u64 {
                     pipe_id: fh.pipe_id,
                     pipe_write: fh.pipe_write,
                     open_flags: fh.open_flags,
+                    signalfd_mask: fh.signalfd_mask,
                 })
             })
         });
@@ -0,0 +1,171 @@
+//! fs_endpoint システムコール（fs.service の引き継ぎ）
+//!
+//! fs.service は起動して状態を復元し終えたら自分をエンドポイントとして登録する。
+//! カーネルのリモート FD の要求は登録されたスレッドへ送り、登録したスレッドが
+//! 終了していれば後継（再起動・更新した fs.service）が登録するのを待つ。
+//!
+//! 落ちた fs.service の状態を後継が拾えるよう、エンドポイントはオープン中のハンドルなどを
+//! 直列化してカーネルに預けておける（中身はカーネルは解釈しない）。
+//!
+//! リモート FD は fork や dup で複数のプロセスに共有される。エンドポイントは、ハンドルを
+//! 開いたプロセス以外からの要求を `FS_ENDPOINT_HOLDS` でカーネルの FD テーブルと照らし合わせる。
+//!
+//! 動いているエンドポイントに状態の引き渡しを頼めるのは、core.service が起動した別の
+//! fs.service だけ。エンドポイントは `FS_ENDPOINT_IS_SUCCESSOR` で要求元を確かめる。
+
+use super::types::{EINVAL, EPERM, ESRCH, SUCCESS};
+use crate::interrupt::spinlock::SpinLock;
+use crate::task::{PrivilegeLevel, ThreadId};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// 呼び出したスレッドをエンドポイントとして登録する
+pub const FS_ENDPOINT_REGISTER: u64 = 0;
+/// 登録されているエンドポイントのスレッド ID を返す（無ければ 0）
+pub const FS_ENDPOINT_QUERY: u64 = 1;
+/// 状態を預ける (ptr, len)（登録したエンドポイントだけ）
+pub const FS_ENDPOINT_SAVE: u64 = 2;
+/// 預けた状態を読む (ptr, len) → 状態の長さ
+pub const FS_ENDPOINT_LOAD: u64 = 3;
+/// プロセスがリモート FD を持っているか (fd_remote, pid) → 1 / 0（登録したエンドポイントだけ）
+///
+/// `pid == 0` ならどれかのプロセスが持っているか。
+pub const FS_ENDPOINT_HOLDS: u64 = 4;
+/// スレッドが後継になれる fs.service か (tid) → 1 / 0（登録したエンドポイントだけ）
+pub const FS_ENDPOINT_IS_SUCCESSOR: u64 = 5;
+
+/// 預けられる状態の最大長
+const FS_STATE_MAX: usize = 4096;
+/// エンドポイントが終了してから後継の登録を待つ時間（1 tick = 10ms）
+const SUCCESSOR_WAIT_TICKS: u64 = 300;
+
+/// 登録されたエンドポイント（0 なら未登録）
+static ENDPOINT: AtomicU64 = AtomicU64::new(0);
+/// 預けられた状態
+static STATE: SpinLock<Vec<u8>> = SpinLock::named("FS_ENDPOINT_STATE", Vec::new());
+
+/// fs_endpoint システムコール
+pub fn fs_endpoint(op: u64, ptr: u64, len: u64) -> u64 {
+    let Some(current) = crate::task::current_thread_id().map(|t| t.as_u64()) else {
+        return ESRCH;
+    };
+    match op {
+        FS_ENDPOINT_REGISTER => {
+            if !caller_is_fs_service() {
+                return EPERM;
+            }
+            let previous = ENDPOINT.swap(current, Ordering::AcqRel);
+            crate::info!(
+                "fs_endpoint: fs.service endpoint {} -> {}",
+                previous,
+                current
+            );
+            SUCCESS
+        }
+        FS_ENDPOINT_QUERY => {
+            let tid = ENDPOINT.load(Ordering::Acquire);
+            if tid != 0 && crate::task::thread_id_exists(tid) {
+                tid
+            } else {
+                0
+            }
+        }
+        FS_ENDPOINT_SAVE => {
+            if ENDPOINT.load(Ordering::Acquire) != current {
+                return EPERM;
+            }
+            let len = len as usize;
+            if len > FS_STATE_MAX {
+                return EINVAL;
+            }
+            let mut buf = alloc::vec![0u8; len];
+            if let Err(errno) = super::copy_from_user(ptr, &mut buf) {
+                return errno;
+            }
+            *STATE.lock() = buf;
+            SUCCESS
+        }
+        FS_ENDPOINT_LOAD => {
+            if !caller_is_fs_service() {
+                return EPERM;
+            }
+            let state = STATE.lock().clone();
+            let n = state.len().min(len as usize);
+            if let Err(errno) = super::copy_to_user(ptr, &state[..n]) {
+                return errno;
+            }
+            state.len() as u64
+        }
+        FS_ENDPOINT_HOLDS => {
+            if ENDPOINT.load(Ordering::Acquire) != current {
+                return EPERM;
+            }
+            super::fs::holds_remote_fd(len, ptr) as u64
+        }
+        FS_ENDPOINT_IS_SUCCESSOR => {
+            if ENDPOINT.load(Ordering::Acquire) != current {
+                return EPERM;
+            }
+            is_successor(current, ptr) as u64
+        }
+        _ => EINVAL,
+    }
+}
+
+/// 登録されているエンドポイント
+///
+/// 登録したスレッドが終了していれば、後継が登録するまで `SUCCESSOR_WAIT_TICKS` 待つ。
+/// 後継が来なければ登録を消して None を返す（以降は名前で fs.service を探す）。
+pub(crate) fn endpoint() -> Option<u64> {
+    let tid = ENDPOINT.load(Ordering::Acquire);
+    if tid == 0 {
+        return None;
+    }
+    if crate::task::thread_id_exists(tid) {
+        return Some(tid);
+    }
+    let start = super::time::get_ticks();
+    while super::time::get_ticks().saturating_sub(start) <= SUCCESSOR_WAIT_TICKS {
+        let next = ENDPOINT.load(Ordering::Acquire);
+        if next == 0 {
+            // ほかの呼び出し元が待ちきれずに登録を消した
+            return None;
+        }
+        if next != tid && crate::task::thread_id_exists(next) {
+            return Some(next);
+        }
+        crate::task::yield_now();
+    }
+    crate::warn!(
+        "fs_endpoint: fs.service {} exited and no successor registered",
+        tid
+    );
+    let _ = ENDPOINT.compare_exchange(tid, 0, Ordering::AcqRel, Ordering::Acquire);
+    None
+}
+
+/// 呼び出し元が Service 以上の権限で動く fs.service か
+fn caller_is_fs_service() -> bool {
+    crate::task::current_thread_id().is_some_and(|tid| fs_service_parent(tid).is_some())
+}
+
+/// `tid` がエンドポイント `endpoint` とは別のプロセスの、core.service が起動した fs.service か
+fn is_successor(endpoint: u64, tid: u64) -> bool {
+    let (endpoint, tid) = (ThreadId::from_u64(endpoint), ThreadId::from_u64(tid));
+    let manager = super::exec::service_manager_pid();
+    manager != 0
+        && crate::task::with_thread(tid, |t| t.process_id())
+            != crate::task::with_thread(endpoint, |t| t.process_id())
+        && fs_service_parent(tid) == Some(manager)
+}
+
+/// `tid` が Service 以上の権限で動く fs.service のスレッドなら、そのプロセスの親の PID
+/// （親が無ければ 0）
+fn fs_service_parent(tid: ThreadId) -> Option<u64> {
+    let pid = crate::task::with_thread(tid, |t| t.process_id())?;
+    crate::task::with_process(pid, |p| {
+        (p.name() == "fs.service" && p.privilege() != PrivilegeLevel::User)
+            .then(|| p.parent_id().map_or(0, |parent| parent.as_u64()))
+    })
+    .flatten()
+}
@@ -314,7 +314,7 @@ pub fn read(fd: u64, buf_ptr: u64, len: u64) -> u64 {
     super::types::EBADF
 }
 
-/// fd >= 3 からの読み取り（パイプ読み込み端 or 通常ファイル）
+/// fd >= 3 からの読み取り（パイプ読み込み端 / signalfd / 通常ファイル）
 fn read_fd(fd: u64, buf_ptr: u64, len: u64) -> u64 {
     let pid = match crate::task::current_thread_id()
         .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
@@ -324,6 +324,16 @@ fn read_fd(fd: u64, buf_ptr: u64, len: u64) -> u64 {
     };
 
     let idx = fd as usize;
+    let signalfd = crate::task::with_process(pid, |p| {
+        p.fd_table()
+            .get(idx)
+            .and_then(|fh| fh.signalfd_mask.map(|mask| (mask, fh.open_flags)))
+    })
+    .flatten();
+    if let Some((mask, open_flags)) = signalfd {
+        return crate::syscall::signal::signalfd_read(mask, open_flags, buf_ptr, len);
+    }
+
     let fd_info = crate::task::with_process(pid, |p| {
         p.fd_table().get(idx).map(|fh| {
             (
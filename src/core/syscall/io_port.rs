@@ -3,21 +3,22 @@
 use crate::syscall::{EFAULT, EINVAL, EPERM, SUCCESS};
 use core::arch::asm;
 
-/// 呼び出し元プロセスがI/Oポートアクセス権限を持つか確認する
+/// 呼び出し元プロセスが `port` から `width` バイトのアクセス権を持つか確認する
 ///
-/// ServiceまたはCore権限レベルのプロセスのみ許可する
-fn caller_has_port_privilege() -> bool {
-    crate::task::current_thread_id()
-        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
-        .and_then(|pid| {
-            crate::task::with_process(pid, |p| {
-                matches!(
-                    p.privilege(),
-                    crate::task::PrivilegeLevel::Core | crate::task::PrivilegeLevel::Service
-                )
-            })
-        })
-        .unwrap_or(false)
+/// ケーパビリティのポート範囲に含まれる場合のみ許可する（拒否は監査ログに残す）
+fn caller_has_port_privilege(port: u64, width: u64) -> bool {
+    if crate::task::current_capabilities().allows_port(port, width) {
+        return true;
+    }
+    crate::audit::log(
+        crate::audit::AuditEventKind::Deny,
+        &alloc::format!(
+            "port I/O {:#x} (width {}) outside capability ranges",
+            port,
+            width
+        ),
+    );
+    false
 }
 
 /// I/Oポートから読み取り
@@ -29,15 +30,15 @@ fn caller_has_port_privilege() -> bool {
 /// # Returns
 /// 読み取った値、またはエラー時は EINVAL
 pub fn port_in(port: u64, size: u64) -> u64 {
-    // 権限チェック: ServiceまたはCore権限のプロセスのみI/Oポートアクセスを許可
-    if !caller_has_port_privilege() {
-        return EPERM;
-    }
-
     if port > 0xFFFF {
         return EINVAL;
     }
 
+    // 権限チェック: ケーパビリティで許可されたポート範囲のみアクセスを許可
+    if !caller_has_port_privilege(port, size) {
+        return EPERM;
+    }
+
     let port = port as u16;
 
     unsafe {
@@ -90,15 +91,15 @@ pub fn port_in(port: u64, size: u64) -> u64 {
 /// # Returns
 /// SUCCESS、またはエラー時は EINVAL
 pub fn port_out(port: u64, value: u64, size: u64) -> u64 {
-    // 権限チェック: ServiceまたはCore権限のプロセスのみI/Oポートアクセスを許可
-    if !caller_has_port_privilege() {
-        return EPERM;
-    }
-
     if port > 0xFFFF {
         return EINVAL;
     }
 
+    // 権限チェック: ケーパビリティで許可されたポート範囲のみアクセスを許可
+    if !caller_has_port_privilege(port, size) {
+        return EPERM;
+    }
+
     let port = port as u16;
 
     unsafe {
@@ -147,12 +148,12 @@ pub fn port_out(port: u64, value: u64, size: u64) -> u64 {
 /// # Returns
 /// SUCCESS、またはエラー時は EINVAL / EFAULT / EPERM
 pub fn port_in_words(port: u64, dst_ptr: u64, count: u64) -> u64 {
-    if !caller_has_port_privilege() {
-        return EPERM;
-    }
     if port > 0xFFFF || count == 0 {
         return EINVAL;
     }
+    if !caller_has_port_privilege(port, 2) {
+        return EPERM;
+    }
 
     let byte_len = match count.checked_mul(2) {
         Some(v) => v,
@@ -194,12 +195,12 @@ pub fn port_in_words(port: u64, dst_ptr: u64, count: u64) -> u64 {
 /// # Returns
 /// SUCCESS、またはエラー時は EINVAL / EFAULT / EPERM
 pub fn port_out_words(port: u64, src_ptr: u64, count: u64) -> u64 {
-    if !caller_has_port_privilege() {
-        return EPERM;
-    }
     if port > 0xFFFF || count == 0 {
         return EINVAL;
     }
+    if !caller_has_port_privilege(port, 2) {
+        return EPERM;
+    }
 
     let byte_len = match count.checked_mul(2) {
         Some(v) => v,
@@ -1,12 +1,40 @@
 use crate::interrupt::spinlock::SpinLock;
 use alloc::vec;
 use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
 
-use super::{EAGAIN, EFAULT, EINVAL};
+use super::{EAGAIN, EFAULT, EINVAL, EPERM};
 
 const MAX_THREADS: usize = crate::task::ThreadQueue::MAX_THREADS;
 const MAILBOX_CAP: usize = 64;
 const MAX_MSG_SIZE: usize = 4128; // FsResponse(4112) / DiskBulkResponse(2064) を収容
+/// 異常 IPC の監査記録の最短間隔（ティック）
+const ABNORMAL_AUDIT_INTERVAL: u64 = 100;
+
+static LAST_ABNORMAL_AUDIT: AtomicU64 = AtomicU64::new(0);
+static SUPPRESSED_ABNORMAL: AtomicU64 = AtomicU64::new(0);
+
+/// 異常な IPC（宛先不明・過大なメッセージ・受信箱溢れ）を監査ログに残す
+///
+/// 送り続けるプロセスで監査リングが埋まらないよう、1 秒に 1 件までにして
+/// 間引いた件数を次の記録に添える。
+fn audit_abnormal(args: fmt::Arguments) {
+    let now = crate::interrupt::timer::get_ticks();
+    let last = LAST_ABNORMAL_AUDIT.load(Ordering::Relaxed);
+    if last != 0 && now.wrapping_sub(last) < ABNORMAL_AUDIT_INTERVAL {
+        SUPPRESSED_ABNORMAL.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    LAST_ABNORMAL_AUDIT.store(now.max(1), Ordering::Relaxed);
+    let suppressed = SUPPRESSED_ABNORMAL.swap(0, Ordering::Relaxed);
+    let message = if suppressed > 0 {
+        alloc::format!("{} ({} similar events suppressed)", args, suppressed)
+    } else {
+        alloc::format!("{}", args)
+    };
+    crate::audit::log(crate::audit::AuditEventKind::Ipc, &message);
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Message {
@@ -249,7 +277,8 @@ impl Mailbox {
     }
 }
 
-static MAILBOXES: SpinLock<[Mailbox; MAX_THREADS]> = SpinLock::new([Mailbox::new(); MAX_THREADS]);
+static MAILBOXES: SpinLock<[Mailbox; MAX_THREADS]> =
+    SpinLock::named("MAILBOXES", [Mailbox::new(); MAX_THREADS]);
 
 /// カーネル内部からIPC送信（ユーザー空間コピー不要）
 pub fn send_from_kernel(dest_thread_id: u64, data: &[u8]) -> bool {
@@ -415,6 +444,14 @@ pub fn send_map_header_from_kernel(dest_thread_id: u64, map_start: u64, total: u
     })
 }
 
+/// 呼び出し元が User 権限のプロセスか
+fn sender_is_user() -> bool {
+    crate::task::current_thread_id()
+        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
+        .and_then(|pid| crate::task::with_process(pid, |p| p.privilege()))
+        .is_some_and(|lvl| lvl == crate::task::PrivilegeLevel::User)
+}
+
 /// IPC送信
 /// arg0: dest_thread_id
 /// arg1: buf_ptr
@@ -426,6 +463,10 @@ pub fn send(dest_thread_id: u64, buf_ptr: u64, len: u64) -> u64 {
 
     let len = len as usize;
     if len > MAX_MSG_SIZE {
+        audit_abnormal(format_args!(
+            "ipc send of {} bytes to thread {} exceeds {}",
+            len, dest_thread_id, MAX_MSG_SIZE
+        ));
         return EINVAL;
     }
     if len > 0 && buf_ptr == 0 {
@@ -440,7 +481,13 @@ pub fn send(dest_thread_id: u64, buf_ptr: u64, len: u64) -> u64 {
     let (idx, dest_generation) =
         match crate::task::thread_slot_index_and_generation_by_u64(dest_thread_id) {
             Some(v) => v,
-            None => return EINVAL,
+            None => {
+                audit_abnormal(format_args!(
+                    "ipc send to unknown thread {}",
+                    dest_thread_id
+                ));
+                return EINVAL;
+            }
         };
 
     if idx >= MAX_THREADS || idx > (u16::MAX as usize) {
@@ -460,6 +507,20 @@ pub fn send(dest_thread_id: u64, buf_ptr: u64, len: u64) -> u64 {
         }
     }
 
+    // fs.service を変更する要求は、カーネル（open/write システムコール）経由でだけ届ける。
+    // 送信元の CAP_FS_WRITE とハンドルの持ち主は fs.service が確かめるが、
+    // User 権限のプロセスが直接送ってくるものはここで落としておく。
+    if super::fs::is_mutating_fs_request(&data[..len])
+        && super::fs::is_fs_service_thread(dest_thread_id)
+        && sender_is_user()
+    {
+        audit_abnormal(format_args!(
+            "ipc: direct mutating fs request from thread {} rejected",
+            sender
+        ));
+        return EPERM;
+    }
+
     let mut boxes = MAILBOXES.lock();
     if boxes[idx]
         .push_message(
@@ -471,10 +532,19 @@ pub fn send(dest_thread_id: u64, buf_ptr: u64, len: u64) -> u64 {
         )
         .is_err()
     {
+        drop(boxes);
+        audit_abnormal(format_args!(
+            "ipc mailbox of thread {} is full",
+            dest_thread_id
+        ));
         return EAGAIN;
     }
     let waiter = boxes[idx].take_waiter();
     drop(boxes);
+    crate::trace::emit(
+        crate::trace::Tracepoint::IpcSend,
+        [dest_thread_id, len as u64, 0],
+    );
     if waiter != 0 {
         crate::task::wake_thread(crate::task::ThreadId::from_u64(waiter));
     }
@@ -647,6 +717,10 @@ pub fn recv(buf_ptr: u64, max_len: u64) -> u64 {
             return err;
         }
     }
+    crate::trace::emit(
+        crate::trace::Tracepoint::IpcRecv,
+        [from, copy_len as u64, 0],
+    );
 
     // 上位32bitに送信元ID、下位32bitに長さ
     (from << 32) | (copy_len as u64)
@@ -711,6 +785,10 @@ pub fn recv_blocking(buf_ptr: u64, max_len: u64) -> u64 {
                         return err;
                     }
                 }
+                crate::trace::emit(
+                    crate::trace::Tracepoint::IpcRecv,
+                    [from, copy_len as u64, 0],
+                );
                 return (from << 32) | (copy_len as u64);
             }
             None => {
@@ -824,3 +902,85 @@ pub fn recv_blocking_from_sender_for_kernel(
         }
     }
 }
+
+#[cfg(feature = "ktest")]
+mod ktests {
+    use super::*;
+    use crate::{kernel_test, ktest_assert, ktest_assert_eq, ktest_fail};
+
+    fn self_tid() -> u64 {
+        crate::task::current_thread_id()
+            .map(|t| t.as_u64())
+            .unwrap_or(0)
+    }
+
+    /// 自分宛てに残ったメッセージを捨てる
+    fn drain(tid: u64) {
+        let mut buf = [0u8; 16];
+        while let Ok(Some(_)) = recv_from_sender_for_kernel_nonblocking(tid, &mut buf) {}
+    }
+
+    kernel_test! {
+        fn delivers_messages_in_order() {
+            let tid = self_tid();
+            ktest_assert!(tid != 0);
+            ktest_assert!(send_from_kernel(tid, b"first"));
+            ktest_assert!(send_from_kernel(tid, b"second"));
+            let mut buf = [0u8; 16];
+            let n = recv_from_sender_for_kernel_nonblocking(tid, &mut buf);
+            ktest_assert_eq!(n, Ok(Some(5)));
+            ktest_assert_eq!(&buf[..5], b"first");
+            let n = recv_from_sender_for_kernel_nonblocking(tid, &mut buf);
+            ktest_assert_eq!(n, Ok(Some(6)));
+            ktest_assert_eq!(&buf[..6], b"second");
+            ktest_assert_eq!(recv_from_sender_for_kernel_nonblocking(tid, &mut buf), Ok(None));
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn filters_by_sender() {
+            let tid = self_tid();
+            ktest_assert!(send_from_kernel(tid, b"mine"));
+            let mut buf = [0u8; 16];
+            ktest_assert_eq!(recv_from_sender_for_kernel_nonblocking(tid + 1, &mut buf), Ok(None));
+            ktest_assert_eq!(recv_from_sender_for_kernel_nonblocking(tid, &mut buf), Ok(Some(4)));
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn rejects_oversized_and_unknown_destinations() {
+            let tid = self_tid();
+            let big = alloc::vec![0u8; MAX_MSG_SIZE + 1];
+            ktest_assert!(!send_from_kernel(tid, &big));
+            ktest_assert!(!send_from_kernel(u64::MAX, b"lost"));
+            let exact = alloc::vec![0u8; MAX_MSG_SIZE];
+            ktest_assert!(send_from_kernel(tid, &exact));
+            let mut buf = alloc::vec![0u8; MAX_MSG_SIZE];
+            let n = recv_from_sender_for_kernel_nonblocking(tid, &mut buf);
+            ktest_assert_eq!(n, Ok(Some(MAX_MSG_SIZE)));
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn mailbox_refuses_beyond_capacity() {
+            let tid = self_tid();
+            for i in 0..MAILBOX_CAP {
+                if !send_from_kernel(tid, &[i as u8]) {
+                    drain(tid);
+                    ktest_fail!("send {} of {} failed", i, MAILBOX_CAP);
+                }
+            }
+            let overflow = send_from_kernel(tid, b"x");
+            let mut buf = [0u8; 4];
+            let first = recv_from_sender_for_kernel_nonblocking(tid, &mut buf);
+            drain(tid);
+            ktest_assert!(!overflow);
+            ktest_assert_eq!(first, Ok(Some(1)));
+            ktest_assert_eq!(buf[0], 0);
+            Ok(())
+        }
+    }
+}
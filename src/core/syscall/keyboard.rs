@@ -1,22 +1,10 @@
 use crate::syscall::{EINVAL, ENODATA, EPERM, SUCCESS};
 
-/// 入力監視 API（tap）を呼び出せるか確認する
+/// 入力注入 API を呼び出せるか確認する
 ///
-/// Service または Core 権限のみ許可する。
-fn caller_has_input_privilege() -> bool {
-    crate::task::current_thread_id()
-        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
-        .and_then(|pid| {
-            crate::task::with_process(pid, |p| {
-                matches!(
-                    p.privilege(),
-                    crate::task::PrivilegeLevel::Core
-                        | crate::task::PrivilegeLevel::Service
-                        | crate::task::PrivilegeLevel::User
-                )
-            })
-        })
-        .unwrap_or(false)
+/// CAP_INPUT_INJECT を持つプロセスのみ許可する。
+fn caller_has_inject_privilege() -> bool {
+    crate::task::current_has_audited(crate::task::capability::CAP_INPUT_INJECT, "keyboard inject")
 }
 
 /// PS/2 キーボードから rawスキャンコードを1バイト読み取り
@@ -29,19 +17,18 @@ pub fn read_char() -> u64 {
 }
 
 /// ドライバ監視用キューから rawスキャンコードを1バイト読み取る（非破壊 tap）
+///
+/// 通常入力を消費しないので、どのプロセスからも呼べる。
 pub fn read_char_tap() -> u64 {
-    if !caller_has_input_privilege() {
-        return EPERM;
-    }
     match crate::util::ps2kbd::pop_tap_scancode() {
         Some(sc) => sc as u64,
         None => ENODATA,
     }
 }
 
-/// raw スキャンコードを通常入力キューへ注入する（Service/Core専用）
+/// raw スキャンコードを通常入力キューへ注入する（CAP_INPUT_INJECT 保持プロセス専用）
 pub fn inject_scancode(scancode: u64) -> u64 {
-    if !caller_has_input_privilege() {
+    if !caller_has_inject_privilege() {
         return EPERM;
     }
     if scancode > 0xFF {
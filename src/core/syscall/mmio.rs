@@ -3,18 +3,16 @@ use x86_64::VirtAddr;
 
 const MAX_MMIO_MAP_SIZE: u64 = 64 * 1024 * 1024;
 
-fn caller_has_mmio_privilege() -> bool {
-    crate::task::current_thread_id()
-        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
-        .and_then(|pid| {
-            crate::task::with_process(pid, |p| {
-                matches!(
-                    p.privilege(),
-                    crate::task::PrivilegeLevel::Core | crate::task::PrivilegeLevel::Service
-                )
-            })
-        })
-        .unwrap_or(false)
+/// 呼び出し元が物理範囲 `[phys, phys + size)` をマップしてよいか（拒否は監査ログに残す）
+fn caller_has_mmio_privilege(phys: u64, size: u64) -> bool {
+    if crate::task::current_capabilities().allows_mmio(phys, size) {
+        return true;
+    }
+    crate::audit::log(
+        crate::audit::AuditEventKind::Deny,
+        &alloc::format!("map MMIO {:#x}+{:#x} outside capability ranges", phys, size),
+    );
+    false
 }
 
 fn current_process_page_table() -> Option<u64> {
@@ -38,9 +36,6 @@ fn translate_user_vaddr_to_phys(table_phys: u64, user_vaddr: u64) -> Result<u64,
 /// 成功時: マップ済みユーザー仮想アドレス
 /// 失敗時: errno
 pub fn map_physical_range(phys_addr: u64, size: u64) -> u64 {
-    if !caller_has_mmio_privilege() {
-        return EPERM;
-    }
     if size == 0 {
         return EINVAL;
     }
@@ -56,6 +51,10 @@ pub fn map_physical_range(phys_addr: u64, size: u64) -> u64 {
         _ => return EINVAL,
     };
 
+    if !caller_has_mmio_privilege(aligned_phys, mapped_size) {
+        return EPERM;
+    }
+
     if !crate::mem::frame::is_allowed_mmio_range(aligned_phys, mapped_size) {
         return EINVAL;
     }
@@ -124,7 +123,7 @@ pub fn map_physical_range(phys_addr: u64, size: u64) -> u64 {
 /// 現在はページの pin/refcount を行わないため、呼び出し側は DMA 完了まで
 /// 対象ページがアンマップされないことを保証する必要がある。
 pub fn virt_to_phys(user_vaddr: u64) -> u64 {
-    if !caller_has_mmio_privilege() {
+    if !crate::task::current_has_audited(crate::task::capability::CAP_DMA, "virt_to_phys") {
         return EPERM;
     }
     if user_vaddr == 0 {
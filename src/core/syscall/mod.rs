@@ -1,23 +1,33 @@
 //! システムコール
 
+pub mod audit;
+pub mod capability;
+pub mod coredump;
 pub mod exec;
 pub mod fs;
+pub mod fs_endpoint;
 pub mod io;
 pub mod io_port;
 pub mod ipc;
 pub mod keyboard;
 pub mod mmio;
 pub mod mouse;
+pub mod perf;
 pub mod pgroup;
 pub mod pipe;
 pub mod privileged;
 pub mod process;
+pub mod ptrace;
+pub mod reboot;
 pub mod signal;
+pub mod strace;
 pub mod syscall_entry;
+pub mod syslog;
 pub mod task;
 pub mod time;
 pub mod tty;
 pub mod vga;
+pub mod watchdog;
 
 mod console;
 mod linux;
@@ -73,7 +83,7 @@ pub fn with_user_memory_access<R>(f: impl FnOnce() -> R) -> R {
     f()
 }
 
-fn current_user_page_table() -> Option<u64> {
+pub(crate) fn current_user_page_table() -> Option<u64> {
     crate::task::current_thread_id()
         .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
         .and_then(|pid| crate::task::with_process(pid, |p| p.page_table()))
@@ -199,7 +209,8 @@ pub fn write_user_u16(ptr: u64, value: u16) -> Result<(), u64> {
 }
 
 pub use types::{
-    SyscallNumber, EAGAIN, EBADF, EFAULT, EINVAL, ENODATA, ENOENT, ENOSYS, EPERM, ESRCH, SUCCESS,
+    SyscallNumber, EAGAIN, EBADF, EFAULT, EINVAL, ENODATA, ENOENT, ENOMEM, ENOSYS, EPERM, ESRCH,
+    SUCCESS,
 };
 
 use x86_64::structures::idt::InterruptStackFrame;
@@ -225,13 +236,19 @@ pub fn dispatch(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, arg4: u64)
         x if x == SyscallNumber::Kill as u64 => signal::kill(arg0, arg1),
         x if x == SyscallNumber::Tkill as u64 => signal::tkill(arg0, arg1),
         x if x == SyscallNumber::Tgkill as u64 => signal::tgkill(arg0, arg1, arg2),
-        x if x == SyscallNumber::Sigaltstack as u64 => process::sigaltstack(arg0, arg1),
+        x if x == SyscallNumber::Sigaltstack as u64 => signal::sigaltstack(arg0, arg1),
+        x if x == SyscallNumber::RtSigtimedwait as u64 => signal::rt_sigtimedwait(arg0, arg1, arg2),
+        x if x == SyscallNumber::RtSigqueueinfo as u64 => signal::rt_sigqueueinfo(arg0, arg1, arg2),
+        x if x == SyscallNumber::RtSigsuspend as u64 => signal::rt_sigsuspend(arg0),
+        x if x == SyscallNumber::Signalfd as u64 => signal::signalfd4(arg0, arg1, arg2, 0),
+        x if x == SyscallNumber::Signalfd4 as u64 => signal::signalfd4(arg0, arg1, arg2, arg3),
         x if x == SyscallNumber::Statfs as u64 => fs::statfs(arg0, arg1),
         x if x == SyscallNumber::GetPid as u64 => process::getpid(),
         x if x == SyscallNumber::Clone as u64 => process::fork(),
         x if x == SyscallNumber::Fork as u64 => process::fork(),
         x if x == SyscallNumber::Execve as u64 => exec::execve_syscall(arg0, arg1, arg2),
-        x if x == SyscallNumber::Wait as u64 => process::wait(arg0, arg1, arg2),
+        x if x == SyscallNumber::Wait as u64 => process::wait(arg0, arg1, arg2, arg3),
+        x if x == SyscallNumber::Waitid as u64 => process::waitid(arg0, arg1, arg2, arg3, arg4),
         x if x == SyscallNumber::GetTid as u64 => process::gettid(),
         x if x == SyscallNumber::Futex as u64 => process::futex(arg0, arg1 as u32, arg2, arg3),
         x if x == SyscallNumber::ArchPrctl as u64 => process::arch_prctl(arg0, arg1),
@@ -250,6 +267,11 @@ pub fn dispatch(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, arg4: u64)
         x if x == SyscallNumber::IpcRecv as u64 => ipc::recv(arg0, arg1),
         x if x == SyscallNumber::IpcRecvWait as u64 => ipc::recv_blocking(arg0, arg1),
         x if x == SyscallNumber::Exec as u64 => exec::exec_kernel(arg0, arg1),
+        x if x == SyscallNumber::ExecWithEnv as u64 => exec::exec_kernel_env(arg0, arg1, arg2),
+        x if x == SyscallNumber::FsEndpoint as u64 => fs_endpoint::fs_endpoint(arg0, arg1, arg2),
+        x if x == SyscallNumber::ExecWithCaps as u64 => {
+            exec::exec_kernel_with_caps(arg0, arg1, arg2, arg3)
+        }
         x if x == SyscallNumber::ExecFromFsStream as u64 => exec::exec_from_fs_stream(arg0, arg1),
         x if x == SyscallNumber::Sleep as u64 => process::sleep(arg0),
         x if x == SyscallNumber::Log as u64 => io::log(arg0, arg1, arg2),
@@ -310,6 +332,17 @@ pub fn dispatch(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, arg4: u64)
         }
         x if x == SyscallNumber::ListProcesses as u64 => process::list_processes(arg0, arg1),
         x if x == SyscallNumber::GetThreadPrivilege as u64 => task::get_thread_privilege(arg0),
+        x if x == SyscallNumber::GetCapabilities as u64 => capability::get_capabilities(arg0, arg1),
+        x if x == SyscallNumber::SetCapabilities as u64 => capability::set_capabilities(arg0, arg1),
+        x if x == SyscallNumber::GetThreadCapabilities as u64 => {
+            capability::get_thread_capabilities(arg0, arg1)
+        }
+        x if x == SyscallNumber::GetCoreDumpDir as u64 => coredump::get_core_dir(arg0, arg1),
+        x if x == SyscallNumber::SetCoreDumpDir as u64 => coredump::set_core_dir(arg0),
+        x if x == SyscallNumber::AuditRead as u64 => audit::audit_read(arg0, arg1, arg2, arg3),
+        x if x == SyscallNumber::SyscallTrace as u64 => strace::syscall_trace(arg0, arg1, arg2),
+        x if x == SyscallNumber::Perf as u64 => perf::perf(arg0, arg1, arg2),
+        x if x == SyscallNumber::Watchdog as u64 => watchdog::watchdog(arg0, arg1, arg2, arg3),
         x if x == SyscallNumber::GetFramebufferInfo as u64 => vga::get_framebuffer_info(arg0),
         x if x == SyscallNumber::MapFramebuffer as u64 => vga::map_framebuffer(),
         x if x == SyscallNumber::ExecFromBuffer as u64 => {
@@ -356,8 +389,11 @@ pub fn dispatch(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, arg4: u64)
         x if x == SyscallNumber::Nanosleep as u64 => pgroup::nanosleep(arg0, arg1),
         x if x == SyscallNumber::Uname as u64 => pgroup::uname(arg0),
         x if x == SyscallNumber::Getrlimit as u64 => pgroup::getrlimit(arg0, arg1),
+        x if x == SyscallNumber::Setrlimit as u64 => pgroup::setrlimit(arg0, arg1),
         x if x == SyscallNumber::SetTidAddress as u64 => pgroup::set_tid_address(arg0),
         x if x == SyscallNumber::Prlimit64 as u64 => pgroup::prlimit64(arg0, arg1, arg2, arg3),
+        x if x == SyscallNumber::Ptrace as u64 => ptrace::ptrace(arg0, arg1, arg2, arg3),
+        x if x == SyscallNumber::Syslog as u64 => syslog::syslog(arg0, arg1, arg2),
         x if x == SyscallNumber::SetRobustList as u64 => process::set_robust_list(arg0, arg1),
         x if x == SyscallNumber::Pipe2 as u64 => pipe::pipe2_syscall(arg0, arg1),
         x if x == SyscallNumber::Openat as u64 => fs::openat(arg0 as i64, arg1, arg2, arg3),
@@ -371,6 +407,7 @@ pub fn dispatch(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, arg4: u64)
         x if x == SyscallNumber::Ppoll as u64 => pgroup::ppoll(arg0, arg1, arg2, arg3, arg4),
         x if x == SyscallNumber::Readlinkat as u64 => fs::readlinkat(arg0 as i64, arg1, arg2, arg3),
         x if x == SyscallNumber::Getrandom as u64 => process::getrandom(arg0, arg1, arg2),
+        x if x == SyscallNumber::Reboot as u64 => reboot::reboot(arg0, arg1, arg2),
         x if x == SyscallNumber::MapPhysicalPages as u64 => {
             privileged::map_physical_pages(arg0, arg1, arg2, arg3)
         }
@@ -388,7 +425,19 @@ pub fn dispatch(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, arg4: u64)
     }
 }
 
-/// fork/clone のみ、現在スレッドへユーザーコンテキストを保存する
+fn needs_user_context(num: u64) -> bool {
+    num == SyscallNumber::Clone as u64
+        || num == SyscallNumber::Fork as u64
+        || num == SyscallNumber::Sigaltstack as u64
+        || num == SyscallNumber::Kill as u64
+        || num == SyscallNumber::Tkill as u64
+        || num == SyscallNumber::Tgkill as u64
+}
+
+/// fork/clone・sigaltstack・kill 系のみ、現在スレッドへユーザーコンテキストを保存する
+///
+/// sigaltstack は「現在代替スタック上で実行中か」の判定に user RSP を使う。
+/// kill 系は自分宛ての SIGABRT などで即時終了するときのコアダンプに使う。
 #[no_mangle]
 pub extern "sysv64" fn save_user_context_for_fork(
     num: u64,
@@ -396,7 +445,7 @@ pub extern "sysv64" fn save_user_context_for_fork(
     user_rsp: u64,
     user_rflags: u64,
 ) {
-    if num != SyscallNumber::Clone as u64 && num != SyscallNumber::Fork as u64 {
+    if !needs_user_context(num) {
         return;
     }
     if let Some(tid) = crate::task::current_thread_id() {
@@ -487,7 +536,7 @@ extern "sysv64" fn syscall_interrupt_handler_rust(kstack: *mut u64) -> u64 {
     crate::cpu::reassert_runtime_hardening();
 
     let syscall_num = unsafe { kstack.add(14).read() };
-    if syscall_num == SyscallNumber::Clone as u64 || syscall_num == SyscallNumber::Fork as u64 {
+    if needs_user_context(syscall_num) {
         let user_rip = unsafe { kstack.add(15).read() };
         let user_rflags = unsafe { kstack.add(17).read() };
         let user_rsp = unsafe { kstack.add(18).read() };
@@ -499,19 +548,46 @@ extern "sysv64" fn syscall_interrupt_handler_rust(kstack: *mut u64) -> u64 {
         crate::task::with_thread_mut(tid, |t| t.set_in_syscall(true));
     }
 
-    let ret = dispatch(
-        syscall_num,
+    // PTRACE_SYSCALL で止められたトレーサーは syscall 番号を書き換えられる
+    let syscall_num = if ptrace::any_tracee() {
+        ptrace::syscall_entry_stop(
+            unsafe { signal::TrapFrame::from_int80(kstack) },
+            syscall_num,
+        )
+    } else {
+        syscall_num
+    };
+
+    let args = [
         unsafe { kstack.add(8).read() },  // saved rdi = arg0
         unsafe { kstack.add(9).read() },  // saved rsi = arg1
         unsafe { kstack.add(12).read() }, // saved rdx = arg2
         unsafe { kstack.add(5).read() },  // saved r10 = arg3
         unsafe { kstack.add(7).read() },  // saved r8  = arg4
-    );
+        unsafe { kstack.add(6).read() },  // saved r9  = arg5
+    ];
+    let trace = strace::enter(syscall_num, args);
+    let start = crate::trace::syscall_start();
+    let ret = dispatch(syscall_num, args[0], args[1], args[2], args[3], args[4]);
+    crate::trace::syscall_done(syscall_num, ret, start);
+    if let Some(entry) = trace {
+        strace::exit(entry, ret);
+    }
 
     if let Some(tid) = current_tid {
         crate::task::with_thread_mut(tid, |t| t.set_in_syscall(false));
     }
 
+    let ret = if ptrace::any_tracee() {
+        ptrace::syscall_exit_stop(
+            unsafe { signal::TrapFrame::from_int80(kstack) },
+            syscall_num,
+            ret,
+        )
+    } else {
+        ret
+    };
+
     let ret = signal::signal_and_return(kstack, ret);
     syscall_entry::restore_page_table(prev_cr3);
     ret
@@ -534,7 +610,13 @@ extern "C" fn syscall_handler_rust(
         crate::task::with_thread_mut(tid, |t| t.set_in_syscall(true));
     }
 
+    let trace = strace::enter(num, [arg0, arg1, arg2, arg3, arg4, 0]);
+    let start = crate::trace::syscall_start();
     let ret = dispatch(num, arg0, arg1, arg2, arg3, arg4);
+    crate::trace::syscall_done(num, ret, start);
+    if let Some(entry) = trace {
+        strace::exit(entry, ret);
+    }
 
     if let Some(tid) = current_tid {
         crate::task::with_thread_mut(tid, |t| t.set_in_syscall(false));
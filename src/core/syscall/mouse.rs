@@ -2,21 +2,9 @@ use crate::syscall::{EINVAL, ENODATA, EPERM, SUCCESS};
 
 /// マウス入力注入 API を呼び出せるか確認する
 ///
-/// Service または Core 権限のみ許可する。
+/// CAP_INPUT_INJECT を持つプロセスのみ許可する。
 fn caller_has_mouse_inject_privilege() -> bool {
-    crate::task::current_thread_id()
-        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
-        .and_then(|pid| {
-            crate::task::with_process(pid, |p| {
-                matches!(
-                    p.privilege(),
-                    crate::task::PrivilegeLevel::Core
-                        | crate::task::PrivilegeLevel::Service
-                        | crate::task::PrivilegeLevel::User
-                )
-            })
-        })
-        .unwrap_or(false)
+    crate::task::current_has_audited(crate::task::capability::CAP_INPUT_INJECT, "mouse inject")
 }
 
 /// PS/2 マウスパケットを 1 つ読み取る（非ブロッキング）
@@ -68,7 +56,7 @@ pub fn read_packet_blocking() -> Result<u64, u64> {
     }
 }
 
-/// マウスパケットを通常入力キューへ注入する（Service/Core専用）
+/// マウスパケットを通常入力キューへ注入する（CAP_INPUT_INJECT 保持プロセス専用）
 ///
 /// `packet` は `b0 | (b1 << 8) | (b2 << 16)` 形式。
 /// 互換のため `wheel` を含む 4 バイト (`b3<<24`) も受理する。
@@ -0,0 +1,81 @@
+//! perf システムコール（トレースポイントとプロファイラの操作）
+//!
+//! 状態の取得と記録の読み出しは誰でもでき、有効化・停止には CAP_PERFMON が必要。
+//! プロファイラのサンプルは `/proc/profile` で読む。
+
+use alloc::vec::Vec;
+
+use super::types::{EFAULT, EINVAL, EPERM, SUCCESS};
+use crate::trace::{self, profile, TraceRecord};
+
+/// 有効にするトレースポイントのマスクを設定する (mask) → それまでのマスク
+pub const PERF_SET_EVENTS: u64 = 0;
+/// トレース記録を読み出す (buf, count) → 件数
+pub const PERF_READ_EVENTS: u64 = 1;
+/// プロファイラを動かす (interval_ticks)
+pub const PERF_PROFILE_START: u64 = 2;
+/// プロファイラを止める
+pub const PERF_PROFILE_STOP: u64 = 3;
+/// 状態を返す → 下位 32 bit にトレースポイントのマスク、bit 32 にプロファイラの動作中
+pub const PERF_STATUS: u64 = 4;
+
+/// 1 回の読み出しの上限
+const MAX_READ: usize = 1024;
+
+const RECORD_SIZE: usize = core::mem::size_of::<TraceRecord>();
+
+/// perf システムコール
+pub fn perf(op: u64, arg1: u64, arg2: u64) -> u64 {
+    match op {
+        PERF_READ_EVENTS => return read_events(arg1, arg2),
+        PERF_STATUS => return trace::events() as u64 | (profile::is_running() as u64) << 32,
+        PERF_SET_EVENTS | PERF_PROFILE_START | PERF_PROFILE_STOP => {}
+        _ => return EINVAL,
+    }
+    if !crate::task::current_has_audited(crate::task::capability::CAP_PERFMON, "perf") {
+        return EPERM;
+    }
+    match op {
+        PERF_SET_EVENTS => {
+            if arg1 & !(trace::ALL_EVENTS as u64) != 0 {
+                return EINVAL;
+            }
+            trace::set_events(arg1 as u32) as u64
+        }
+        PERF_PROFILE_START => {
+            profile::start(arg1);
+            SUCCESS
+        }
+        PERF_PROFILE_STOP => {
+            profile::stop();
+            SUCCESS
+        }
+        _ => EINVAL,
+    }
+}
+
+fn read_events(buf_ptr: u64, count: u64) -> u64 {
+    let max = core::cmp::min(count, MAX_READ as u64) as usize;
+    if max == 0 {
+        return 0;
+    }
+    if !crate::syscall::validate_user_ptr(buf_ptr, (max * RECORD_SIZE) as u64) {
+        return EFAULT;
+    }
+    let records = trace::drain(max);
+    if records.is_empty() {
+        return 0;
+    }
+    let mut bytes = Vec::with_capacity(records.len() * RECORD_SIZE);
+    for record in &records {
+        // SAFETY: TraceRecord は repr(C) で詰め物が無い
+        let raw = unsafe {
+            core::slice::from_raw_parts(record as *const TraceRecord as *const u8, RECORD_SIZE)
+        };
+        bytes.extend_from_slice(raw);
+    }
+    match crate::syscall::copy_to_user(buf_ptr, &bytes) {
+        Ok(()) => records.len() as u64,
+        Err(e) => e,
+    }
+}
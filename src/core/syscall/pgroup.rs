@@ -34,8 +34,26 @@ fn is_tty_fd(fd: i32) -> bool {
     .unwrap_or(false)
 }
 
+/// signalfd なら読み出し可能なシグナルが保留されているか
+fn signalfd_ready_for_fd(fd: i32) -> bool {
+    if fd < FD_BASE as i32 {
+        return false;
+    }
+    let pid = match current_pid() {
+        Some(p) => p,
+        None => return false,
+    };
+    crate::task::with_process(pid, |p| {
+        p.fd_table()
+            .get(fd as usize)
+            .and_then(|fh| fh.signalfd_mask)
+    })
+    .flatten()
+    .is_some_and(crate::syscall::signal::signalfd_ready)
+}
+
 fn stdin_ready_for_fd(fd: i32) -> bool {
-    is_tty_fd(fd) && stdin_ready()
+    (is_tty_fd(fd) && stdin_ready()) || signalfd_ready_for_fd(fd)
 }
 
 fn stdout_ready(fd: i32) -> bool {
@@ -341,7 +359,7 @@ pub fn mprotect(addr: u64, len: u64, prot: u64) -> u64 {
 
 /// poll システムコール（最小実装）
 ///
-/// TTY fd の read/write readiness を返す。
+/// TTY fd の read/write readiness と、signalfd の read readiness を返す。
 pub fn poll(fds_ptr: u64, nfds: u64, timeout_arg: u64) -> u64 {
     const POLLFD_SIZE: u64 = 8; // i32 fd, i16 events, i16 revents
     if nfds == 0 {
@@ -637,23 +655,92 @@ pub fn nanosleep(req_ptr: u64, _rem_ptr: u64) -> u64 {
     SUCCESS
 }
 
-/// getrlimit システムコール（リソース上限を無限大で返す）
-pub fn getrlimit(_resource: u64, rlim_ptr: u64) -> u64 {
-    if rlim_ptr == 0 || !crate::syscall::validate_user_ptr(rlim_ptr, 16) {
-        return EINVAL;
+/// struct rlimit のサイズ（rlim_cur, rlim_max）
+const RLIMIT_SIZE: u64 = 16;
+
+fn read_user_rlimit(ptr: u64) -> Result<crate::task::RLimit, u64> {
+    if !crate::syscall::validate_user_ptr(ptr, RLIMIT_SIZE) {
+        return Err(EFAULT);
+    }
+    let cur = crate::syscall::read_user_u64(ptr)?;
+    let max = crate::syscall::read_user_u64(ptr + 8)?;
+    Ok(crate::task::RLimit::new(cur, max))
+}
+
+fn write_user_rlimit(ptr: u64, limit: crate::task::RLimit) -> u64 {
+    if !crate::syscall::validate_user_ptr(ptr, RLIMIT_SIZE) {
+        return EFAULT;
     }
-    let mut buf = [0u8; 16];
-    buf[..8].copy_from_slice(&u64::MAX.to_ne_bytes());
-    buf[8..].copy_from_slice(&u64::MAX.to_ne_bytes());
-    crate::syscall::copy_to_user(rlim_ptr, &buf)
+    let mut buf = [0u8; RLIMIT_SIZE as usize];
+    buf[..8].copy_from_slice(&limit.cur.to_ne_bytes());
+    buf[8..].copy_from_slice(&limit.max.to_ne_bytes());
+    crate::syscall::copy_to_user(ptr, &buf)
         .map(|_| SUCCESS)
         .unwrap_or_else(|e| e)
 }
 
-/// prlimit64 システムコール（スタブ: 無限大を返し、設定を無視）
-pub fn prlimit64(_pid: u64, _resource: u64, _new_limit: u64, old_limit: u64) -> u64 {
+/// getrlimit システムコール
+pub fn getrlimit(resource: u64, rlim_ptr: u64) -> u64 {
+    prlimit64(0, resource, 0, rlim_ptr)
+}
+
+/// setrlimit システムコール
+pub fn setrlimit(resource: u64, rlim_ptr: u64) -> u64 {
+    if rlim_ptr == 0 {
+        return EFAULT;
+    }
+    prlimit64(0, resource, rlim_ptr, 0)
+}
+
+/// prlimit64 システムコール
+///
+/// pid が 0 なら自分自身。他プロセスの上限を読み書きするには CAP_KILL_ANY が必要。
+/// 旧値を返してから新しい値を設定する。
+pub fn prlimit64(pid: u64, resource: u64, new_limit: u64, old_limit: u64) -> u64 {
+    let self_pid = match current_pid() {
+        Some(p) => p,
+        None => return ESRCH,
+    };
+    let target = if pid == 0 || pid == self_pid.as_u64() {
+        self_pid
+    } else {
+        if !crate::task::current_has_audited(
+            crate::task::capability::CAP_KILL_ANY,
+            "prlimit64 on another process",
+        ) {
+            return EPERM;
+        }
+        crate::task::ids::ProcessId::from_u64(pid)
+    };
+    let resource = resource as usize;
+
+    let new = if new_limit != 0 {
+        match read_user_rlimit(new_limit) {
+            Ok(v) => Some(v),
+            Err(e) => return e,
+        }
+    } else {
+        None
+    };
+
+    let result = crate::task::with_process_mut(target, |p| {
+        let old = p.rlimits().get(resource).ok_or(EINVAL)?;
+        if let Some(new) = new {
+            p.rlimits_mut().set(resource, new).map_err(|e| match e {
+                crate::task::rlimit::RLimitError::InvalidResource => EINVAL,
+                crate::task::rlimit::RLimitError::RaiseHardLimit => EPERM,
+            })?;
+        }
+        Ok(old)
+    });
+    let old = match result {
+        Some(Ok(old)) => old,
+        Some(Err(e)) => return e,
+        None => return ESRCH,
+    };
+
     if old_limit != 0 {
-        return getrlimit(0, old_limit);
+        return write_user_rlimit(old_limit, old);
     }
     SUCCESS
 }
@@ -95,7 +95,7 @@ impl PipeBuffer {
 /// SpinLock でガードした Option<PipeBuffer> の配列
 static PIPE_TABLE: SpinLock<[Option<PipeBuffer>; MAX_PIPES]> = {
     const INIT: Option<PipeBuffer> = None;
-    SpinLock::new([INIT; MAX_PIPES])
+    SpinLock::named("PIPE_TABLE", [INIT; MAX_PIPES])
 };
 
 /// 新しいパイプを確保してパイプ ID を返す。失敗した場合は None。
@@ -244,6 +244,7 @@ pub fn pipe2_syscall(pipefd_ptr: u64, flags: u64) -> u64 {
         pipe_id: Some(pipe_id),
         pipe_write: false,
         open_flags: 0,
+        signalfd_mask: None,
     });
     let write_handle = alloc::boxed::Box::new(FileHandle {
         data: alloc::boxed::Box::new([]),
@@ -255,6 +256,7 @@ pub fn pipe2_syscall(pipefd_ptr: u64, flags: u64) -> u64 {
         pipe_id: Some(pipe_id),
         pipe_write: true,
         open_flags: 1,
+        signalfd_mask: None,
     });
 
     let pid_id = crate::task::ids::ProcessId::from_u64(pid);
@@ -281,3 +283,71 @@ pub fn pipe2_syscall(pipefd_ptr: u64, flags: u64) -> u64 {
         }
     }
 }
+
+#[cfg(feature = "ktest")]
+mod ktests {
+    use super::*;
+    use crate::syscall::types::{EAGAIN, EPIPE};
+    use crate::{kernel_test, ktest_assert, ktest_assert_eq, ktest_fail};
+
+    kernel_test! {
+        fn round_trips_data_in_order() {
+            let Some(id) = alloc_pipe() else {
+                ktest_fail!("alloc_pipe failed");
+            };
+            ktest_assert_eq!(pipe_write(id, b"hello "), Ok(6));
+            ktest_assert_eq!(pipe_write(id, b"pipe"), Ok(4));
+            let mut buf = [0u8; 16];
+            let n = pipe_read_blocking(id, &mut buf);
+            ktest_assert_eq!(&buf[..n], b"hello pipe");
+            close_write_end(id);
+            close_read_end(id);
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn reports_eof_after_writer_closes() {
+            let Some(id) = alloc_pipe() else {
+                ktest_fail!("alloc_pipe failed");
+            };
+            ktest_assert_eq!(pipe_write(id, b"x"), Ok(1));
+            close_write_end(id);
+            let mut buf = [0u8; 4];
+            ktest_assert_eq!(pipe_read_blocking(id, &mut buf), 1);
+            ktest_assert_eq!(pipe_read_blocking(id, &mut buf), 0);
+            close_read_end(id);
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn write_without_reader_is_epipe() {
+            let Some(id) = alloc_pipe() else {
+                ktest_fail!("alloc_pipe failed");
+            };
+            close_read_end(id);
+            ktest_assert_eq!(pipe_write(id, b"x"), Err(EPIPE));
+            close_write_end(id);
+            ktest_assert_eq!(pipe_write(id, b"x"), Err(EPIPE));
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn full_buffer_is_eagain() {
+            let Some(id) = alloc_pipe() else {
+                ktest_fail!("alloc_pipe failed");
+            };
+            let chunk = alloc::vec![0x5au8; PIPE_BUF_SIZE + 1];
+            ktest_assert_eq!(pipe_write(id, &chunk), Ok(PIPE_BUF_SIZE));
+            ktest_assert_eq!(pipe_write(id, b"x"), Err(EAGAIN));
+            let mut buf = [0u8; 1];
+            ktest_assert_eq!(pipe_read_blocking(id, &mut buf), 1);
+            ktest_assert_eq!(pipe_write(id, b"yz"), Ok(1));
+            close_write_end(id);
+            close_read_end(id);
+            Ok(())
+        }
+    }
+}
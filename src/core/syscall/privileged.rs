@@ -1,26 +1,20 @@
-//! 特権システムコール（Service権限プロセス専用）
+//! 特権システムコール（ケーパビリティ保持プロセス専用）
 //!
-//! これらのsyscallはPrivilegeLevel::Serviceのプロセスのみ呼び出し可能。
+//! これらのsyscallは CAP_SHARED_MEMORY / CAP_DMA を持つプロセスのみ呼び出し可能。
 //! 物理メモリ直接操作、ゼロコピーIO等の実装に使用する。
 
 use super::types::{EFAULT, EINVAL, EPERM};
-use crate::task::ids::PrivilegeLevel;
+use crate::task::capability::{CAP_DMA, CAP_SHARED_MEMORY};
 use alloc::vec::Vec;
 use x86_64::instructions::tlb;
 use x86_64::VirtAddr;
 
-/// 現在スレッドのプロセス権限レベルを取得
-fn current_process_privilege() -> Option<PrivilegeLevel> {
-    crate::task::current_thread_id()
-        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
-        .and_then(|pid| crate::task::with_process(pid, |p| p.privilege()))
-}
-
-/// Service権限チェック
-fn require_service_privilege() -> Result<(), u64> {
-    match current_process_privilege() {
-        Some(PrivilegeLevel::Core) | Some(PrivilegeLevel::Service) => Ok(()),
-        _ => Err(EPERM),
+/// ケーパビリティチェック（拒否は監査ログに残る）
+fn require_capability(flag: u64, what: &str) -> Result<(), u64> {
+    if crate::task::current_has_audited(flag, what) {
+        Ok(())
+    } else {
+        Err(EPERM)
     }
 }
 
@@ -134,7 +128,7 @@ pub fn map_physical_pages(
     virt_addr_hint: u64,
 ) -> u64 {
     // 権限チェック
-    if let Err(e) = require_service_privilege() {
+    if let Err(e) = require_capability(CAP_SHARED_MEMORY, "map_physical_pages") {
         return e;
     }
 
@@ -175,7 +169,7 @@ pub fn map_physical_pages(
 /// エラー時: 負のエラーコード
 pub fn get_physical_addr(virt_addr: u64, target_thread_id: u64) -> u64 {
     // 権限チェック
-    if let Err(e) = require_service_privilege() {
+    if let Err(e) = require_capability(CAP_DMA, "get_physical_addr") {
         return e;
     }
 
@@ -228,7 +222,7 @@ pub fn alloc_shared_pages(
     virt_addr_hint: u64,
 ) -> u64 {
     // 権限チェック
-    if let Err(e) = require_service_privilege() {
+    if let Err(e) = require_capability(CAP_SHARED_MEMORY, "alloc_shared_pages") {
         return e;
     }
 
@@ -379,7 +373,7 @@ pub fn alloc_shared_pages(
 /// エラー時: 負のエラーコード
 pub fn unmap_pages(virt_addr: u64, page_count: u64, deallocate: u64) -> u64 {
     // 権限チェック
-    if let Err(e) = require_service_privilege() {
+    if let Err(e) = require_capability(CAP_SHARED_MEMORY, "unmap_pages") {
         return e;
     }
 
@@ -410,7 +404,10 @@ pub fn unmap_pages(virt_addr: u64, page_count: u64, deallocate: u64) -> u64 {
         for i in 0..page_count {
             let target_virt = virt_addr + (i * 0x1000);
             if let Some(phys) = crate::mem::paging::virt_to_phys_in_table(page_table, target_virt) {
-                phys_addrs.push(phys);
+                // 共有の vDSO フレームは解放対象にしない
+                if !crate::mem::vdso::is_vdso_frame(phys) {
+                    phys_addrs.push(phys);
+                }
             }
         }
     }
@@ -466,7 +463,7 @@ pub fn ipc_send_pages(
     map_start: u64,
 ) -> u64 {
     // 権限チェック
-    if let Err(e) = require_service_privilege() {
+    if let Err(e) = require_capability(CAP_SHARED_MEMORY, "ipc_send_pages") {
         return e;
     }
 
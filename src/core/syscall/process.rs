@@ -2,6 +2,7 @@
 
 use super::types::{EFAULT, EINVAL, ENOMEM, ENOSYS, SUCCESS};
 use crate::interrupt::spinlock::SpinLock;
+use crate::task::signal::{CLD_CONTINUED, CLD_EXITED, CLD_STOPPED, CLD_TRAPPED};
 use crate::task::ThreadId;
 
 /// ユーザー空間の上限アドレス (x86-64 canonical hole 下側)
@@ -24,7 +25,7 @@ struct FutexWaitEntry {
 const MAX_FUTEX_WAITERS: usize = crate::task::ThreadQueue::MAX_THREADS;
 const NO_TIMEOUT_WAKE_TICK: u64 = u64::MAX;
 static FUTEX_WAIT_QUEUE: SpinLock<[Option<FutexWaitEntry>; MAX_FUTEX_WAITERS]> =
-    SpinLock::new([None; MAX_FUTEX_WAITERS]);
+    SpinLock::named("FUTEX_WAIT_QUEUE", [None; MAX_FUTEX_WAITERS]);
 
 #[inline]
 fn aslr_mix64(mut x: u64) -> u64 {
@@ -189,6 +190,7 @@ pub fn list_processes(buf_ptr: u64, buf_len: u64) -> u64 {
             ProcessState::Running => 1,
             ProcessState::Sleeping => 3,
             ProcessState::Zombie => 4,
+            ProcessState::Stopped => 5,
             ProcessState::Terminated => 4,
             _ => 0,
         };
@@ -368,21 +370,36 @@ pub fn fork() -> u64 {
         None => return ENOSYS,
     };
 
-    let (parent_priv, parent_priority, parent_pt, heap_start, heap_end, stack_bottom, stack_top) =
-        match crate::task::with_process(parent_pid, |p| {
-            (
-                p.privilege(),
-                p.priority(),
-                p.page_table(),
-                p.heap_start(),
-                p.heap_end(),
-                p.stack_bottom(),
-                p.stack_top(),
-            )
-        }) {
-            Some(v) => v,
-            None => return ENOSYS,
-        };
+    let (
+        parent_priv,
+        parent_caps,
+        parent_priority,
+        parent_pt,
+        heap_start,
+        heap_end,
+        stack_bottom,
+        stack_top,
+        parent_rlimits,
+        parent_auxv,
+        parent_symbols,
+    ) = match crate::task::with_process(parent_pid, |p| {
+        (
+            p.privilege(),
+            *p.capabilities(),
+            p.priority(),
+            p.page_table(),
+            p.heap_start(),
+            p.heap_end(),
+            p.stack_bottom(),
+            p.stack_top(),
+            *p.rlimits(),
+            p.auxv(),
+            p.symbols(),
+        )
+    }) {
+        Some(v) => v,
+        None => return ENOSYS,
+    };
     let parent_pt = match parent_pt {
         Some(pt) => pt,
         None => return ENOSYS,
@@ -408,11 +425,17 @@ pub fn fork() -> u64 {
 
     let mut child_proc =
         crate::task::Process::new("fork", parent_priv, Some(parent_pid), parent_priority);
+    // ケーパビリティは親と同じ集合を継承する
+    child_proc.set_capabilities(parent_caps);
     child_proc.set_page_table(child_pt);
     child_proc.set_heap_start(heap_start);
     child_proc.set_heap_end(heap_end);
     child_proc.set_stack_bottom(stack_bottom);
     child_proc.set_stack_top(stack_top);
+    // リソース上限と auxv の位置（アドレス空間ごと複製済み）、シンボル表も引き継ぐ
+    *child_proc.rlimits_mut() = parent_rlimits;
+    child_proc.set_auxv(parent_auxv.0, parent_auxv.1);
+    child_proc.set_symbols(parent_symbols);
     crate::info!(
         "[STACK_INIT] FORK child: stack_bottom={:#x}, stack_top={:#x}",
         stack_bottom,
@@ -437,6 +460,8 @@ pub fn fork() -> u64 {
             return ENOMEM;
         }
     };
+    // トレース中なら子のスレッドが走り出す前に対象へ加える
+    super::strace::on_fork(parent_pid, child_pid);
     let child_thread = crate::task::Thread::new_fork_child(
         child_pid,
         user_rip,
@@ -447,6 +472,7 @@ pub fn fork() -> u64 {
         KERNEL_THREAD_STACK_SIZE,
     );
     if crate::task::add_thread(child_thread).is_none() {
+        super::strace::forget(child_pid);
         let _ = crate::task::remove_process(child_pid);
         let _ = crate::mem::paging::destroy_user_page_table(child_pt);
         return ENOMEM;
@@ -479,64 +505,269 @@ pub fn sleep(milliseconds: u64) -> u64 {
     SUCCESS
 }
 
-/// Waitシステムコール (wait4)
+// ---- wait4 / waitid ----
+const WNOHANG: u64 = 0x1;
+/// wait4 の WUNTRACED と waitid の WSTOPPED は同じ値
+const WUNTRACED: u64 = 0x2;
+const WEXITED: u64 = 0x4;
+const WCONTINUED: u64 = 0x8;
+const WNOWAIT: u64 = 0x0100_0000;
+
+const P_ALL: u64 = 0;
+const P_PID: u64 = 1;
+const P_PGID: u64 = 2;
+
+/// struct rusage (Linux x86-64) のサイズ
+const RUSAGE_SIZE: usize = 144;
+/// siginfo_t のサイズ
+const SIGINFO_SIZE: usize = 128;
+
+/// wait 系 syscall で報告する子プロセスの状態変化
+enum WaitOutcome {
+    Exited(crate::task::ReapedChild),
+    Job(
+        crate::task::ProcessId,
+        crate::task::JobEvent,
+        crate::task::CpuUsage,
+    ),
+}
+
+impl WaitOutcome {
+    fn pid(&self) -> crate::task::ProcessId {
+        match self {
+            WaitOutcome::Exited(c) => c.pid,
+            WaitOutcome::Job(pid, _, _) => *pid,
+        }
+    }
+
+    fn usage(&self) -> crate::task::CpuUsage {
+        match self {
+            WaitOutcome::Exited(c) => c.usage,
+            WaitOutcome::Job(_, _, usage) => *usage,
+        }
+    }
+
+    /// wait4 の status 値 (WIFEXITED / WIFSTOPPED / WIFCONTINUED 互換)
+    fn wait_status(&self) -> i32 {
+        match self {
+            WaitOutcome::Exited(c) => ((c.exit_code & 0xff) << 8) as i32,
+            WaitOutcome::Job(_, crate::task::JobEvent::Stopped(sig), _) => {
+                ((*sig as i32) << 8) | 0x7f
+            }
+            WaitOutcome::Job(_, crate::task::JobEvent::Continued, _) => 0xffff,
+            WaitOutcome::Job(_, crate::task::JobEvent::Traced(sig), _) => {
+                ((*sig as i32) << 8) | 0x7f
+            }
+        }
+    }
+
+    /// waitid の (si_code, si_status)
+    fn siginfo_code_status(&self) -> (i32, i32) {
+        match self {
+            WaitOutcome::Exited(c) => (CLD_EXITED, (c.exit_code & 0xff) as i32),
+            WaitOutcome::Job(_, crate::task::JobEvent::Stopped(sig), _) => {
+                (CLD_STOPPED, *sig as i32)
+            }
+            WaitOutcome::Job(_, crate::task::JobEvent::Continued, _) => {
+                (CLD_CONTINUED, crate::task::SIGCONT as i32)
+            }
+            WaitOutcome::Job(_, crate::task::JobEvent::Traced(sig), _) => {
+                (CLD_TRAPPED, *sig as i32)
+            }
+        }
+    }
+}
+
+fn current_process_id() -> Option<crate::task::ProcessId> {
+    current_thread_id().and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
+}
+
+/// 子プロセスの状態変化を待つ共通処理
+///
+/// `Ok(None)` は WNOHANG 指定で報告すべき子がまだいないことを表す。
+fn wait_for_child(
+    parent: crate::task::ProcessId,
+    selector: crate::task::WaitSelector,
+    options: u64,
+) -> Result<Option<WaitOutcome>, u64> {
+    let want_exited = options & WEXITED != 0;
+    let want_stopped = options & WUNTRACED != 0;
+    let want_continued = options & WCONTINUED != 0;
+    let consume = options & WNOWAIT == 0;
+
+    loop {
+        if want_exited {
+            let child = if consume {
+                crate::task::reap_zombie_child_process(parent, selector)
+            } else {
+                crate::task::peek_zombie_child_process(parent, selector)
+            };
+            if let Some(child) = child {
+                return Ok(Some(WaitOutcome::Exited(child)));
+            }
+        }
+
+        // ptrace 停止は WUNTRACED が無くてもトレーサーへ報告する
+        if let Some((pid, event, usage)) = crate::task::take_traced_stop(parent, selector, consume)
+        {
+            return Ok(Some(WaitOutcome::Job(pid, event, usage)));
+        }
+
+        if want_stopped || want_continued {
+            if let Some((pid, event, usage)) = crate::task::take_child_job_event(
+                parent,
+                selector,
+                want_stopped,
+                want_continued,
+                consume,
+            ) {
+                return Ok(Some(WaitOutcome::Job(pid, event, usage)));
+            }
+        }
+
+        if !crate::task::has_child_process(parent, selector) {
+            return Err(ECHILD);
+        }
+
+        if options & WNOHANG != 0 {
+            return Ok(None);
+        }
+
+        crate::task::yield_now();
+    }
+}
+
+fn ticks_to_timeval(ticks: u64) -> (u64, u64) {
+    let ms = ticks.saturating_mul(TICK_MS);
+    (ms / 1000, (ms % 1000) * 1000)
+}
+
+/// struct rusage をユーザー空間へ書き込む（ru_utime / ru_stime のみ埋める）
+fn write_rusage(ptr: u64, usage: crate::task::CpuUsage) -> Result<(), u64> {
+    let mut buf = [0u8; RUSAGE_SIZE];
+    let (us, uus) = ticks_to_timeval(usage.user_ticks);
+    let (ss, sus) = ticks_to_timeval(usage.system_ticks);
+    buf[0..8].copy_from_slice(&us.to_ne_bytes());
+    buf[8..16].copy_from_slice(&uus.to_ne_bytes());
+    buf[16..24].copy_from_slice(&ss.to_ne_bytes());
+    buf[24..32].copy_from_slice(&sus.to_ne_bytes());
+    super::copy_to_user(ptr, &buf)
+}
+
+/// Wait4システムコール
 ///
 /// # 引数
-/// - `pid`: 待機するプロセスID (-1 = 任意の子プロセス)
+/// - `pid`: 待機対象 (>0 = 指定PID, -1 = 任意の子, 0 = 同じプロセスグループ, < -1 = グループ -pid)
 /// - `status_ptr`: 終了ステータスを書き込むポインタ (0 = 無視)
-/// - `options`: WNOHANG(0x1) = ノンブロッキング
-pub fn wait(_pid: u64, status_ptr: u64, options: u64) -> u64 {
-    const WNOHANG: u64 = 0x1;
-    let pid = _pid as i64;
-    if options & !WNOHANG != 0 {
+/// - `options`: WNOHANG / WUNTRACED / WCONTINUED
+/// - `rusage_ptr`: 子の CPU 使用時間を書き込む struct rusage (0 = 無視)
+pub fn wait(pid: u64, status_ptr: u64, options: u64, rusage_ptr: u64) -> u64 {
+    if options & !(WNOHANG | WUNTRACED | WCONTINUED) != 0 {
         return EINVAL;
     }
-    if pid < -1 || pid == 0 {
-        return EINVAL;
-    }
-
     if status_ptr != 0 && !super::validate_user_ptr(status_ptr, 4) {
         return EFAULT;
     }
+    if rusage_ptr != 0 && !super::validate_user_ptr(rusage_ptr, RUSAGE_SIZE as u64) {
+        return EFAULT;
+    }
 
     // 呼び出し元プロセス
-    let current_pid = match current_thread_id()
-        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
-    {
+    let current_pid = match current_process_id() {
         Some(pid) => pid,
         None => return ECHILD,
     };
 
-    let target_pid = if pid == -1 {
-        None
-    } else {
-        Some(crate::task::ProcessId::from_u64(pid as u64))
+    let pid = pid as i64;
+    let selector = match pid {
+        -1 => crate::task::WaitSelector::Any,
+        0 => {
+            let pgid = crate::task::with_process(current_pid, |p| p.pgid()).unwrap_or(0);
+            crate::task::WaitSelector::Group(pgid)
+        }
+        p if p > 0 => crate::task::WaitSelector::Pid(crate::task::ProcessId::from_u64(p as u64)),
+        p => crate::task::WaitSelector::Group(p.unsigned_abs()),
     };
 
     // POSIX互換の待機: ゾンビを回収、存在しなければブロックまたはWNOHANGで0
-    loop {
-        if let Some((reaped_pid, exit_code)) =
-            crate::task::reap_zombie_child_process(current_pid, target_pid)
-        {
-            if status_ptr != 0 {
-                let status = ((exit_code & 0xff) << 8) as i32;
-                if crate::syscall::write_user_i32(status_ptr, status).is_err() {
-                    return EFAULT;
-                }
-            }
-            return reaped_pid.as_u64();
-        }
+    let outcome = match wait_for_child(current_pid, selector, options | WEXITED) {
+        Ok(Some(o)) => o,
+        Ok(None) => return 0,
+        Err(e) => return e,
+    };
 
-        if !crate::task::has_child_process(current_pid, target_pid) {
-            return ECHILD;
-        }
+    if status_ptr != 0 && crate::syscall::write_user_i32(status_ptr, outcome.wait_status()).is_err()
+    {
+        return EFAULT;
+    }
+    if rusage_ptr != 0 && write_rusage(rusage_ptr, outcome.usage()).is_err() {
+        return EFAULT;
+    }
+    outcome.pid().as_u64()
+}
 
-        if options & WNOHANG != 0 {
-            return 0;
+/// Waitidシステムコール
+///
+/// # 引数
+/// - `idtype`: P_ALL / P_PID / P_PGID
+/// - `id`: PID またはプロセスグループID (P_PGID で 0 なら呼び出し元のグループ)
+/// - `infop`: siginfo_t を書き込むポインタ (0 = 無視)
+/// - `options`: WEXITED / WSTOPPED / WCONTINUED と WNOHANG / WNOWAIT の組み合わせ
+/// - `rusage_ptr`: 子の CPU 使用時間を書き込む struct rusage (0 = 無視)
+pub fn waitid(idtype: u64, id: u64, infop: u64, options: u64, rusage_ptr: u64) -> u64 {
+    let allowed = WNOHANG | WUNTRACED | WEXITED | WCONTINUED | WNOWAIT;
+    if options & !allowed != 0 || options & (WEXITED | WUNTRACED | WCONTINUED) == 0 {
+        return EINVAL;
+    }
+    if infop != 0 && !super::validate_user_ptr(infop, SIGINFO_SIZE as u64) {
+        return EFAULT;
+    }
+    if rusage_ptr != 0 && !super::validate_user_ptr(rusage_ptr, RUSAGE_SIZE as u64) {
+        return EFAULT;
+    }
+
+    let current_pid = match current_process_id() {
+        Some(pid) => pid,
+        None => return ECHILD,
+    };
+
+    let selector = match idtype {
+        P_ALL => crate::task::WaitSelector::Any,
+        P_PID if id != 0 => crate::task::WaitSelector::Pid(crate::task::ProcessId::from_u64(id)),
+        P_PGID => {
+            let pgid = if id == 0 {
+                crate::task::with_process(current_pid, |p| p.pgid()).unwrap_or(0)
+            } else {
+                id
+            };
+            crate::task::WaitSelector::Group(pgid)
         }
+        _ => return EINVAL,
+    };
 
-        crate::task::yield_now();
+    let outcome = match wait_for_child(current_pid, selector, options) {
+        Ok(o) => o,
+        Err(e) => return e,
+    };
+
+    // WNOHANG で対象がいない場合は si_pid = 0 の siginfo を返す
+    let mut info = [0u8; SIGINFO_SIZE];
+    if let Some(outcome) = outcome.as_ref() {
+        let (code, status) = outcome.siginfo_code_status();
+        info[0..4].copy_from_slice(&(crate::task::SIGCHLD as i32).to_ne_bytes());
+        info[8..12].copy_from_slice(&code.to_ne_bytes());
+        info[16..20].copy_from_slice(&(outcome.pid().as_u64() as i32).to_ne_bytes());
+        info[24..28].copy_from_slice(&status.to_ne_bytes());
     }
+    if infop != 0 && super::copy_to_user(infop, &info).is_err() {
+        return EFAULT;
+    }
+    if let Some(outcome) = outcome.as_ref() {
+        if rusage_ptr != 0 && write_rusage(rusage_ptr, outcome.usage()).is_err() {
+            return EFAULT;
+        }
+    }
+    SUCCESS
 }
 
 /// Mmapシステムコール
@@ -916,13 +1147,6 @@ pub fn arch_prctl(code: u64, addr: u64) -> u64 {
     }
 }
 
-/// sigaltstack システムコール（最小実装）
-///
-/// 互換目的で成功を返す。現状 alt stack の切り替えは行わない。
-pub fn sigaltstack(_ss: u64, _old_ss: u64) -> u64 {
-    SUCCESS
-}
-
 /// set_robust_list システムコール（最小実装）
 ///
 /// glibc 初期化互換のため成功を返す。
@@ -0,0 +1,684 @@
+//! ptrace システムコール
+//!
+//! Linux x86-64 互換のリクエスト番号で、デバッガ（gdbserver）に必要な範囲を実装する。
+//! トレーシーは次の場所で止まり、トレーサーへ SIGCHLD（CLD_TRAPPED）を送ってから
+//! PTRACE_CONT などで再開されるまで待つ。
+//!
+//! - シグナル配送の直前（SIGKILL 以外。#BP / #DB / ページフォルトの同期シグナルを含む）
+//! - PTRACE_SYSCALL 指定時の syscall 入口と出口（int 0x80 経路のみ）
+//! - execve 直後（新しいイメージの先頭）
+//!
+//! 停止中のレジスタはトレーシーのカーネルスタック上の保存領域を直接読み書きする。
+//! 走行中のトレーシーへ割り込みたいとき（PTRACE_ATTACH の SIGSTOP など）は、
+//! 次のタイマー割り込みで RFLAGS.TF を立て、続く #DB でシグナルを配送させる。
+
+use super::signal::TrapFrame;
+use super::types::{EFAULT, EINVAL, EIO, ENOSYS, EPERM, ESRCH, SUCCESS};
+use crate::syscall::coredump::{CoreRegs, CORE_REG_COUNT};
+use crate::task::ptrace::{PTRACE_O_EXITKILL, PTRACE_O_MASK, PTRACE_O_TRACESYSGOOD};
+use crate::task::signal::{CLD_TRAPPED, SIGTRAP, TRAP_TRACE};
+use crate::task::{
+    current_thread_id, with_process, with_process_mut, ProcessId, PtraceState, ResumeMode, SigInfo,
+    StopKind, TraceStop, SIGCHLD, SIGKILL, SIGSTOP, SI_USER,
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use x86_64::structures::idt::InterruptStackFrame;
+
+// ---- リクエスト番号 (Linux x86-64 互換) ----
+const PTRACE_TRACEME: u64 = 0;
+const PTRACE_PEEKTEXT: u64 = 1;
+const PTRACE_PEEKDATA: u64 = 2;
+const PTRACE_PEEKUSER: u64 = 3;
+const PTRACE_POKETEXT: u64 = 4;
+const PTRACE_POKEDATA: u64 = 5;
+const PTRACE_POKEUSER: u64 = 6;
+const PTRACE_CONT: u64 = 7;
+const PTRACE_KILL: u64 = 8;
+const PTRACE_SINGLESTEP: u64 = 9;
+const PTRACE_GETREGS: u64 = 12;
+const PTRACE_SETREGS: u64 = 13;
+const PTRACE_ATTACH: u64 = 16;
+const PTRACE_DETACH: u64 = 17;
+const PTRACE_SYSCALL: u64 = 24;
+const PTRACE_SETOPTIONS: u64 = 0x4200;
+const PTRACE_GETSIGINFO: u64 = 0x4202;
+
+/// struct user_regs_struct のバイト数
+const USER_REGS_SIZE: usize = CORE_REG_COUNT * 8;
+/// user_regs_struct 内の orig_rax / fs_base の位置
+const UR_ORIG_RAX_OFF: u64 = 15 * 8;
+const UR_FS_BASE_OFF: u64 = 21 * 8;
+
+const RFLAGS_TF: u64 = 1 << 8;
+const USER_SPACE_END: u64 = 0x0000_7FFF_FFFF_FFFF;
+
+/// トレース中のプロセス数（0 の間は syscall 経路のフックを素通りする）
+static TRACEE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// トレース中のプロセスが 1 つでもあるか
+#[inline]
+pub fn any_tracee() -> bool {
+    TRACEE_COUNT.load(Ordering::Relaxed) != 0
+}
+
+fn current_pid() -> Option<ProcessId> {
+    let tid = current_thread_id()?;
+    crate::task::with_thread(tid, |t| t.process_id())
+}
+
+/// ptrace システムコール
+///
+/// # 引数
+/// - `request`: PTRACE_* リクエスト
+/// - `pid`: 対象プロセス
+/// - `addr`: 対象アドレス / user_regs_struct 内のオフセット
+/// - `data`: 書き込む値、結果の書き出し先、または再開時に注入するシグナル
+///
+/// PEEK 系は raw syscall と同じく結果を `*data` に書き、0 を返す。
+pub fn ptrace(request: u64, pid: u64, addr: u64, data: u64) -> u64 {
+    let caller = match current_pid() {
+        Some(pid) => pid,
+        None => return ESRCH,
+    };
+    match request {
+        PTRACE_TRACEME => return traceme(caller),
+        PTRACE_ATTACH => return attach(caller, ProcessId::from_u64(pid)),
+        _ => {}
+    }
+
+    let target = ProcessId::from_u64(pid);
+    let stop = match with_process(target, |p| {
+        p.ptrace()
+            .filter(|state| state.tracer == caller)
+            .map(|state| state.stop)
+    }) {
+        Some(Some(stop)) => stop,
+        _ => return ESRCH,
+    };
+    // KILL だけは停止していなくても受け付ける
+    if request == PTRACE_KILL {
+        super::signal::deliver_signal_to_pid(target, SIGKILL);
+        return SUCCESS;
+    }
+    let stop = match stop {
+        Some(stop) => stop,
+        None => return ESRCH,
+    };
+
+    match request {
+        PTRACE_PEEKTEXT | PTRACE_PEEKDATA => peek_text(target, addr, data),
+        PTRACE_POKETEXT | PTRACE_POKEDATA => poke_text(target, addr, data),
+        PTRACE_PEEKUSER => peek_user(&stop, addr, data),
+        PTRACE_POKEUSER => poke_user(target, &stop, addr, data),
+        PTRACE_GETREGS => get_regs(&stop, data),
+        PTRACE_SETREGS => set_regs(target, &stop, data),
+        PTRACE_GETSIGINFO => super::copy_to_user(data, &stop.info.to_siginfo_bytes())
+            .map(|_| SUCCESS)
+            .unwrap_or(EFAULT),
+        PTRACE_SETOPTIONS => {
+            if data & !PTRACE_O_MASK != 0 {
+                return EINVAL;
+            }
+            with_process_mut(target, |p| {
+                if let Some(state) = p.ptrace_mut() {
+                    state.options = data;
+                }
+            });
+            SUCCESS
+        }
+        PTRACE_CONT => resume(target, ResumeMode::Continue, data),
+        PTRACE_SYSCALL => resume(target, ResumeMode::Syscall, data),
+        PTRACE_SINGLESTEP => resume(target, ResumeMode::SingleStep, data),
+        PTRACE_DETACH => detach(target, data),
+        _ => EIO,
+    }
+}
+
+/// PTRACE_TRACEME: 親をトレーサーにする
+fn traceme(caller: ProcessId) -> u64 {
+    let parent = match with_process(caller, |p| (p.parent_id(), p.tracer())) {
+        Some((Some(parent), None)) => parent,
+        Some(_) => return EPERM,
+        None => return ESRCH,
+    };
+    with_process_mut(caller, |p| p.set_ptrace(PtraceState::new(parent)));
+    TRACEE_COUNT.fetch_add(1, Ordering::Relaxed);
+    SUCCESS
+}
+
+/// `caller` が `target` をトレースしてよいか（ptrace と strace で共通）
+///
+/// 自分の子で、かつ自分の権限を超えないプロセスなら許可する。
+/// それ以外は CAP_SYS_PTRACE が必要で、拒否は監査ログに残す。
+pub(crate) fn may_trace(caller: ProcessId, target: ProcessId, what: &str) -> Result<(), u64> {
+    if caller == target {
+        return Err(EPERM);
+    }
+    let caller_caps = crate::task::capability::current_capabilities();
+    let (is_child, within_caps) = with_process(target, |p| {
+        (
+            p.parent_id() == Some(caller),
+            p.capabilities().is_subset_of(&caller_caps),
+        )
+    })
+    .ok_or(ESRCH)?;
+    if (is_child && within_caps)
+        || crate::task::current_has(crate::task::capability::CAP_SYS_PTRACE)
+    {
+        return Ok(());
+    }
+    crate::audit::log(
+        crate::audit::AuditEventKind::Deny,
+        &alloc::format!(
+            "{}: pid {} denied (not a child or target has more capabilities)",
+            what,
+            target.as_u64()
+        ),
+    );
+    Err(EPERM)
+}
+
+/// PTRACE_ATTACH: 対象をトレースし、SIGSTOP で止める（許可の条件は `may_trace`）
+fn attach(caller: ProcessId, target: ProcessId) -> u64 {
+    if let Err(e) = may_trace(caller, target, "ptrace attach") {
+        return e;
+    }
+    match with_process(target, |p| p.tracer().is_some()) {
+        Some(false) => {}
+        Some(true) => return EPERM,
+        None => return ESRCH,
+    }
+
+    // ジョブ制御で止まっているなら動かして、SIGSTOP を ptrace 停止として受け取らせる
+    let job_stopped = with_process_mut(target, |p| {
+        p.set_ptrace(PtraceState::new(caller));
+        p.mark_continued()
+    })
+    .unwrap_or(false);
+    TRACEE_COUNT.fetch_add(1, Ordering::Relaxed);
+    if job_stopped {
+        crate::task::resume_process_threads(target);
+    }
+    let mut info = SigInfo::new(SIGSTOP, SI_USER);
+    info.pid = caller.as_u64();
+    super::signal::deliver_siginfo_to_pid(target, info);
+    SUCCESS
+}
+
+/// PTRACE_DETACH: トレースを外して再開させる（`data` が非 0 ならそのシグナルを送る）
+fn detach(target: ProcessId, data: u64) -> u64 {
+    if data > 64 {
+        return EIO;
+    }
+    let Some(state) = with_process_mut(target, |p| p.take_ptrace()).flatten() else {
+        return ESRCH;
+    };
+    TRACEE_COUNT.fetch_sub(1, Ordering::Relaxed);
+    release(target, state);
+    if data != 0 {
+        super::signal::deliver_signal_to_pid(target, data as usize);
+    }
+    SUCCESS
+}
+
+/// トレースを外したプロセスを停止から解放する
+fn release(target: ProcessId, state: PtraceState) {
+    if let Some(stop) = state.stop {
+        // SAFETY: トレーシーは停止中で、stop.frame の保存領域はまだ有効
+        let frame = unsafe { TrapFrame::from_raw(stop.frame, stop.iret) };
+        frame.set_rflags(frame.rflags() & !RFLAGS_TF);
+        if stop.kind == StopKind::SyscallEntry {
+            frame.set_rax(stop.orig_rax);
+        }
+        crate::task::resume_process_threads(target);
+    }
+}
+
+/// 停止中のトレーシーを再開させる
+fn resume(target: ProcessId, mode: ResumeMode, sig: u64) -> u64 {
+    if sig > 64 {
+        return EIO;
+    }
+    let stop = match with_process_mut(target, |p| {
+        let state = p.ptrace_mut()?;
+        let stop = state.stop.take()?;
+        state.resume = mode;
+        state.resume_sig = sig as usize;
+        Some(stop)
+    }) {
+        Some(Some(stop)) => stop,
+        _ => return ESRCH,
+    };
+
+    // SAFETY: トレーシーは停止中で、stop.frame の保存領域はまだ有効
+    let frame = unsafe { TrapFrame::from_raw(stop.frame, stop.iret) };
+    let rflags = frame.rflags();
+    frame.set_rflags(if mode == ResumeMode::SingleStep {
+        rflags | RFLAGS_TF
+    } else {
+        rflags & !RFLAGS_TF
+    });
+    // syscall 入口では RAX に -ENOSYS を見せていたので、実行する番号へ戻す
+    if stop.kind == StopKind::SyscallEntry {
+        frame.set_rax(stop.orig_rax);
+    }
+    crate::task::resume_process_threads(target);
+    SUCCESS
+}
+
+// ---- レジスタ / メモリアクセス ---------------------------------------------------
+
+/// 停止中のトレーシーのレジスタ（user_regs_struct）
+fn stop_regs(stop: &TraceStop) -> CoreRegs {
+    // SAFETY: トレーシーは停止中で、stop.frame の保存領域はまだ有効
+    let frame = unsafe { TrapFrame::from_raw(stop.frame, stop.iret) };
+    let mut regs = frame.core_regs();
+    regs.set_orig_rax(stop.orig_rax);
+    let tid = crate::task::ThreadId::from_u64(stop.tid);
+    if let Some(fs_base) = crate::task::with_thread(tid, |t| t.fs_base()) {
+        regs.set_fs_base(fs_base);
+    }
+    regs
+}
+
+/// 書き換えたレジスタを停止中のトレーシーへ戻す
+fn store_regs(target: ProcessId, stop: &TraceStop, regs: &CoreRegs) -> u64 {
+    // SAFETY: トレーシーは停止中で、stop.frame の保存領域はまだ有効
+    let frame = unsafe { TrapFrame::from_raw(stop.frame, stop.iret) };
+    if regs.fs_base() > USER_SPACE_END || !frame.apply_core_regs(regs) {
+        return EIO;
+    }
+    let tid = crate::task::ThreadId::from_u64(stop.tid);
+    crate::task::with_thread_mut(tid, |t| t.set_fs_base(regs.fs_base()));
+    let orig_rax = regs.orig_rax();
+    with_process_mut(target, |p| {
+        if let Some(stop) = p.ptrace_mut().and_then(|s| s.stop.as_mut()) {
+            stop.orig_rax = orig_rax;
+        }
+    });
+    SUCCESS
+}
+
+fn get_regs(stop: &TraceStop, data: u64) -> u64 {
+    let regs = stop_regs(stop);
+    let mut buf = [0u8; USER_REGS_SIZE];
+    for (i, reg) in regs.as_array().iter().enumerate() {
+        buf[i * 8..i * 8 + 8].copy_from_slice(&reg.to_ne_bytes());
+    }
+    super::copy_to_user(data, &buf)
+        .map(|_| SUCCESS)
+        .unwrap_or(EFAULT)
+}
+
+fn set_regs(target: ProcessId, stop: &TraceStop, data: u64) -> u64 {
+    let mut buf = [0u8; USER_REGS_SIZE];
+    if super::copy_from_user(data, &mut buf).is_err() {
+        return EFAULT;
+    }
+    let mut regs = [0u64; CORE_REG_COUNT];
+    for (i, reg) in regs.iter_mut().enumerate() {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(&buf[i * 8..i * 8 + 8]);
+        *reg = u64::from_ne_bytes(b);
+    }
+    store_regs(target, stop, &CoreRegs::from_array(regs))
+}
+
+/// user_regs_struct 内のオフセットを添字へ（8 バイト境界のみ）
+fn user_offset_index(offset: u64) -> Option<usize> {
+    if offset % 8 != 0 || offset >= USER_REGS_SIZE as u64 {
+        return None;
+    }
+    Some((offset / 8) as usize)
+}
+
+fn peek_user(stop: &TraceStop, offset: u64, data: u64) -> u64 {
+    let Some(idx) = user_offset_index(offset) else {
+        return EIO;
+    };
+    let value = stop_regs(stop).as_array()[idx];
+    super::copy_to_user(data, &value.to_ne_bytes())
+        .map(|_| SUCCESS)
+        .unwrap_or(EFAULT)
+}
+
+fn poke_user(target: ProcessId, stop: &TraceStop, offset: u64, data: u64) -> u64 {
+    let Some(idx) = user_offset_index(offset) else {
+        return EIO;
+    };
+    let mut regs = *stop_regs(stop).as_array();
+    regs[idx] = data;
+    // orig_rax / fs_base 以外のセグメント類は書き換えても反映されない
+    if offset != UR_ORIG_RAX_OFF && offset != UR_FS_BASE_OFF && idx > 20 {
+        return SUCCESS;
+    }
+    store_regs(target, stop, &CoreRegs::from_array(regs))
+}
+
+fn tracee_page_table(target: ProcessId) -> Option<u64> {
+    with_process(target, |p| p.page_table()).flatten()
+}
+
+fn peek_text(target: ProcessId, addr: u64, data: u64) -> u64 {
+    let Some(table) = tracee_page_table(target) else {
+        return ESRCH;
+    };
+    let mut word = [0u8; 8];
+    if crate::mem::paging::copy_from_user_in_table(table, addr, &mut word).is_err() {
+        return EIO;
+    }
+    super::copy_to_user(data, &word)
+        .map(|_| SUCCESS)
+        .unwrap_or(EFAULT)
+}
+
+fn poke_text(target: ProcessId, addr: u64, data: u64) -> u64 {
+    let Some(table) = tracee_page_table(target) else {
+        return ESRCH;
+    };
+    match crate::mem::paging::poke_user_in_table(table, addr, &data.to_ne_bytes()) {
+        Ok(()) => SUCCESS,
+        Err(_) => EIO,
+    }
+}
+
+// ---- トレーシー側の停止 ----------------------------------------------------------
+
+/// 現在プロセスを ptrace 停止させ、トレーサーが再開させるまで待つ
+///
+/// 戻り値は再開時に注入するよう指定されたシグナル（0 なら無し）。
+/// 待っている間にトレースが外れた場合も 0 を返す。
+fn stop_current(
+    frame: TrapFrame,
+    kind: StopKind,
+    status: u8,
+    info: SigInfo,
+    orig_rax: u64,
+) -> usize {
+    let (Some(pid), Some(tid)) = (current_pid(), current_thread_id()) else {
+        return 0;
+    };
+    let (frame_addr, iret) = frame.into_raw();
+    let tracer = with_process_mut(pid, |p| {
+        let state = p.ptrace_mut()?;
+        state.resume_sig = 0;
+        state.stop = Some(TraceStop {
+            kind,
+            status,
+            info,
+            tid: tid.as_u64(),
+            frame: frame_addr,
+            iret,
+            orig_rax,
+            reported: false,
+        });
+        Some(state.tracer)
+    })
+    .flatten();
+    let Some(tracer) = tracer else {
+        return 0;
+    };
+
+    crate::task::stop_process_threads(pid);
+    let mut chld = SigInfo::new(SIGCHLD, CLD_TRAPPED);
+    chld.pid = pid.as_u64();
+    chld.status = status as i32;
+    super::signal::deliver_siginfo_to_pid(tracer, chld);
+
+    loop {
+        crate::task::wait_while_stopped();
+        match with_process(pid, |p| {
+            p.ptrace().map(|s| (s.stop.is_some(), s.resume_sig))
+        }) {
+            Some(Some((true, _))) => {
+                // トレーサー以外の要因で起こされた: 再開要求までもう一度止まる
+                crate::task::with_thread_mut(tid, |t| t.stop());
+            }
+            Some(Some((false, sig))) => return sig,
+            _ => return 0,
+        }
+    }
+}
+
+fn is_traced(pid: ProcessId) -> bool {
+    with_process(pid, |p| p.tracer().is_some()).unwrap_or(false)
+}
+
+/// シグナル配送停止
+///
+/// トレーサーが捨てたら None、差し替えたら新しいシグナルを返す。
+/// 差し替えたシグナルがブロック中なら保留に戻して None を返す。
+pub fn signal_delivery_stop(frame: TrapFrame, info: SigInfo) -> Option<SigInfo> {
+    let sig = stop_current(frame, StopKind::Signal, info.signo as u8, info, u64::MAX);
+    if sig == 0 {
+        return None;
+    }
+    if sig == info.signo {
+        return Some(info);
+    }
+    let pid = current_pid()?;
+    let mut next = SigInfo::new(sig, SI_USER);
+    next.pid = with_process(pid, |p| p.parent_id())
+        .flatten()
+        .map(|p| p.as_u64())
+        .unwrap_or(0);
+    let blocked = with_process(pid, |p| {
+        p.signal_state().mask & crate::task::sig_bit(sig) != 0
+    })
+    .unwrap_or(false);
+    if blocked {
+        with_process_mut(pid, |p| p.signal_state_mut().enqueue(next));
+        return None;
+    }
+    Some(next)
+}
+
+/// 例外（#BP / #DB / ページフォルト）で送る同期シグナルの配送停止
+///
+/// トレースされていなければそのまま返す。None ならシグナルを送らずに復帰する。
+pub fn fault_stop(frame: TrapFrame, info: SigInfo) -> Option<SigInfo> {
+    match current_pid() {
+        Some(pid) if is_traced(pid) => signal_delivery_stop(frame, info),
+        _ => Some(info),
+    }
+}
+
+/// syscall 停止の wait status（TRACESYSGOOD なら SIGTRAP | 0x80）
+fn syscall_stop_status(pid: ProcessId) -> Option<u8> {
+    with_process(pid, |p| {
+        p.ptrace()
+            .filter(|s| s.resume == ResumeMode::Syscall)
+            .map(|s| {
+                if s.options & PTRACE_O_TRACESYSGOOD != 0 {
+                    SIGTRAP as u8 | 0x80
+                } else {
+                    SIGTRAP as u8
+                }
+            })
+    })
+    .flatten()
+}
+
+/// syscall 入口の停止（PTRACE_SYSCALL）
+///
+/// トレーサーには RAX = -ENOSYS、orig_rax = 番号として見せる。
+/// 戻り値は実行する syscall 番号（トレーサーが orig_rax を書き換えた場合はその値）。
+pub fn syscall_entry_stop(frame: TrapFrame, num: u64) -> u64 {
+    let Some(pid) = current_pid() else {
+        return num;
+    };
+    let Some(status) = syscall_stop_status(pid) else {
+        return num;
+    };
+    frame.set_rax(ENOSYS);
+    stop_current(
+        frame,
+        StopKind::SyscallEntry,
+        status,
+        SigInfo::new(SIGTRAP, status as i32),
+        num,
+    );
+    // 再開時に resume() が RAX へ orig_rax を戻している
+    frame.rax()
+}
+
+/// syscall 出口の停止（PTRACE_SYSCALL）
+///
+/// 戻り値はユーザーへ返す値（トレーサーが RAX を書き換えた場合はその値）。
+pub fn syscall_exit_stop(frame: TrapFrame, num: u64, ret: u64) -> u64 {
+    // rt_sigreturn はこのあと保存領域ごと書き戻すので止まっても意味がない
+    if num == super::SyscallNumber::RtSigreturn as u64 {
+        return ret;
+    }
+    let Some(pid) = current_pid() else {
+        return ret;
+    };
+    let Some(status) = syscall_stop_status(pid) else {
+        return ret;
+    };
+    frame.set_rax(ret);
+    stop_current(
+        frame,
+        StopKind::SyscallExit,
+        status,
+        SigInfo::new(SIGTRAP, status as i32),
+        num,
+    );
+    let ret = frame.rax();
+    // signal_and_return は RAX の位置から syscall 番号を読む
+    frame.set_rax(num);
+    ret
+}
+
+/// execve 直後の停止
+///
+/// 新しいイメージの先頭で SIGTRAP 停止する。レジスタは RIP/RSP 以外まだ無いので、
+/// 一時的な保存領域を作って見せ、書き換えられた RIP/RSP を返す。
+/// 再開時のシグナル注入とシングルステップは無視する。
+pub fn exec_stop(entry: u64, rsp: u64) -> (u64, u64) {
+    let Some(pid) = current_pid() else {
+        return (entry, rsp);
+    };
+    if !is_traced(pid) {
+        return (entry, rsp);
+    }
+    let mut regs = [0u64; 20];
+    regs[15] = entry;
+    regs[16] = crate::mem::gdt::user_code_selector() as u64 | 3;
+    regs[17] = 0x202;
+    regs[18] = rsp;
+    regs[19] = crate::mem::gdt::user_data_selector() as u64 | 3;
+    // SAFETY: regs は停止が終わるまでこの関数のスタック上にある
+    let frame = unsafe { TrapFrame::from_int80(regs.as_mut_ptr()) };
+    stop_current(
+        frame,
+        StopKind::Exec,
+        SIGTRAP as u8,
+        SigInfo::new(SIGTRAP, SI_USER),
+        u64::MAX,
+    );
+    (frame.rip(), frame.rsp())
+}
+
+/// ユーザーモードの #DB
+///
+/// ptrace のシングルステップなら SIGTRAP で停止し、割り込み要求のための TF なら
+/// 保留中のシグナルを配送する。戻り値は通常どおり送るべきシグナル（None なら処理済み）。
+pub fn debug_trap(frame: TrapFrame) -> Option<SigInfo> {
+    let mut info = SigInfo::new(SIGTRAP, TRAP_TRACE);
+    info.addr = frame.rip();
+    let Some(pid) = current_pid() else {
+        return Some(info);
+    };
+    let traced = with_process_mut(pid, |p| {
+        p.ptrace_mut().map(|s| {
+            let kicked = s.kicked;
+            s.kicked = false;
+            (kicked, s.resume)
+        })
+    })
+    .flatten();
+    let Some((kicked, mode)) = traced else {
+        return Some(info);
+    };
+
+    if mode == ResumeMode::SingleStep {
+        frame.set_rflags(frame.rflags() & !RFLAGS_TF);
+        return signal_delivery_stop(frame, info);
+    }
+    if kicked {
+        frame.set_rflags(frame.rflags() & !RFLAGS_TF);
+        let ret = super::signal::deliver_pending_signals(frame, frame.rax());
+        frame.set_rax(ret);
+        return None;
+    }
+    // ユーザー自身が TF を立てていた
+    signal_delivery_stop(frame, info)
+}
+
+/// 走行中のトレーシーを止めたい（シグナルが保留された）ことを記録する
+pub fn request_interrupt(pid: ProcessId) {
+    with_process_mut(pid, |p| {
+        if let Some(state) = p.ptrace_mut() {
+            state.interrupt = true;
+        }
+    });
+}
+
+/// タイマー割り込みから呼ぶ: 割り込み要求のあるトレーシーがユーザーモードで
+/// 走っていれば TF を立て、次の命令の後の #DB でシグナルを配送させる
+pub fn kick_current_if_requested(stack_frame: &mut InterruptStackFrame) {
+    if !any_tracee() || stack_frame.code_segment.rpl() != x86_64::PrivilegeLevel::Ring3 {
+        return;
+    }
+    let Some(pid) = current_pid() else {
+        return;
+    };
+    let kick = with_process_mut(pid, |p| match p.ptrace_mut() {
+        Some(state) if state.interrupt && !state.kicked => {
+            state.kicked = true;
+            true
+        }
+        _ => false,
+    })
+    .unwrap_or(false);
+    if kick {
+        // SAFETY: ユーザーへ戻る割り込みフレームの RFLAGS に TF を足すだけ
+        unsafe {
+            stack_frame.as_mut().update(|f| {
+                f.cpu_flags
+                    .insert(x86_64::registers::rflags::RFlags::TRAP_FLAG)
+            });
+        }
+    }
+}
+
+/// プロセス終了時の後始末（`mark_process_exited` から呼ばれる）
+///
+/// 終了したプロセスがトレーシーなら数を減らし、トレーサーなら
+/// トレーシーを解放する（PTRACE_O_EXITKILL 指定なら SIGKILL）。
+pub fn on_process_exit(pid: ProcessId, was_traced: bool) {
+    if was_traced {
+        TRACEE_COUNT.fetch_sub(1, Ordering::Relaxed);
+    }
+    if !any_tracee() {
+        return;
+    }
+    let mut tracees = alloc::vec::Vec::new();
+    crate::task::for_each_process(|p| {
+        if p.tracer() == Some(pid) {
+            tracees.push(p.id());
+        }
+    });
+    for tracee in tracees {
+        let Some(state) = with_process_mut(tracee, |p| p.take_ptrace()).flatten() else {
+            continue;
+        };
+        TRACEE_COUNT.fetch_sub(1, Ordering::Relaxed);
+        if state.options & PTRACE_O_EXITKILL != 0 {
+            super::signal::deliver_signal_to_pid(tracee, SIGKILL);
+        } else {
+            release(tracee, state);
+        }
+    }
+}
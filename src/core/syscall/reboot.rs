@@ -0,0 +1,33 @@
+//! reboot システムコール（Linux の reboot(2) 互換。再起動だけを受け付ける）
+//!
+//! CAP_SYS_CONFIG が必要。core.service が reboot-system ポリシーのサービスの停止を
+//! 受けて呼ぶ。
+
+use super::types::{EINVAL, EPERM};
+
+/// 1 つ目のマジック値
+pub const LINUX_REBOOT_MAGIC1: u64 = 0xfee1_dead;
+/// 2 つ目のマジック値
+pub const LINUX_REBOOT_MAGIC2: u64 = 672_274_793;
+/// 再起動する
+pub const LINUX_REBOOT_CMD_RESTART: u64 = 0x0123_4567;
+
+/// reboot システムコール (magic1, magic2, cmd)
+pub fn reboot(magic1: u64, magic2: u64, cmd: u64) -> u64 {
+    if magic1 != LINUX_REBOOT_MAGIC1 || magic2 != LINUX_REBOOT_MAGIC2 {
+        return EINVAL;
+    }
+    if !crate::task::current_has_audited(crate::task::capability::CAP_SYS_CONFIG, "reboot") {
+        return EPERM;
+    }
+    if cmd != LINUX_REBOOT_CMD_RESTART {
+        return EINVAL;
+    }
+    let pid = crate::percpu::current_process_raw_id();
+    crate::warn!("reboot requested by pid {}", pid);
+    crate::audit::log(
+        crate::audit::AuditEventKind::Restart,
+        &alloc::format!("reboot requested by pid {}", pid),
+    );
+    crate::cpu::reboot()
+}
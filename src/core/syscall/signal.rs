@@ -2,11 +2,19 @@
 //!
 //! rt_sigaction / rt_sigprocmask / kill / rt_sigreturn と、
 //! syscall リターン時のシグナル送達ロジックを実装する。
+//! ジョブ制御（SIGSTOP/SIGTSTP/SIGTTIN/SIGTTOU による停止と SIGCONT による再開）もここで扱う。
+//! siginfo 付きの送達（SA_SIGINFO）、代替シグナルスタック、rt_sigqueueinfo、
+//! rt_sigtimedwait / rt_sigsuspend、signalfd もここに置く。
 
-use super::types::{EINVAL, EPERM, ESRCH, SUCCESS};
+use super::types::{EAGAIN, EBADF, EFAULT, EINTR, EINVAL, ENOMEM, EPERM, ESRCH, SUCCESS};
+use crate::task::fd_table::{FileHandle, O_CLOEXEC};
+use crate::task::signal::{CLD_CONTINUED, CLD_EXITED, CLD_STOPPED};
 use crate::task::{
-    current_thread_id, default_action, thread_to_process_id, with_process, with_process_mut,
-    DefaultAction, PrivilegeLevel, ProcessId, SigAction, SIGCHLD, SIGKILL, SIG_DFL, SIG_IGN,
+    current_thread_id, default_action, is_stop_signal, sig_bit, thread_to_process_id, with_process,
+    with_process_mut, AltStack, DefaultAction, ProcessId, SigAction, SigInfo, MINSIGSTKSZ,
+    SA_NODEFER, SA_ONSTACK, SA_RESETHAND, SEGV_ACCERR, SEGV_MAPERR, SIGCHLD, SIGCONT, SIGKILL,
+    SIGNAL_FRAME_MAGIC, SIGSEGV, SIGSTOP, SIG_DFL, SIG_IGN, SI_KERNEL, SI_TKILL, SI_USER,
+    SS_DISABLE, SS_ONSTACK, STOP_SIGNAL_MASK,
 };
 
 // ---- rt_sigprocmask の how 引数 ----
@@ -15,16 +23,34 @@ const SIG_UNBLOCK: u64 = 1;
 const SIG_SETMASK: u64 = 2;
 
 // ---- SIGKILL / SIGSTOP はブロック・ハンドラ変更不可 ----
-const SIGSTOP: usize = 19;
 const UNCATCHABLE_MASK: u64 = (1u64 << (SIGKILL - 1)) | (1u64 << (SIGSTOP - 1)); // SIGKILL | SIGSTOP
 
 // ---- ユーザー空間の struct sigaction レイアウト (Linux x86-64 互換) ----
 // sa_handler:  [+0]  u64
 // sa_flags:    [+8]  u64
-// sa_restorer: [+16] u64
+// sa_restorer: [+16] u64  (互換のため保存するだけ。戻り番地は常に vDSO の __vdso_rt_sigreturn)
 // sa_mask:     [+24] u64  (128-bit mask, 上位64bitは今回使わない)
 const SIGACTION_SIZE: u64 = 32;
 
+/// SIGCHLD の sa_flags: 子の停止/再開では SIGCHLD を送らない
+const SA_NOCLDSTOP: u64 = 0x1;
+
+/// siginfo_t / struct signalfd_siginfo のサイズ
+const SIGINFO_SIZE: u64 = 128;
+/// stack_t のサイズ（ss_sp, ss_flags + padding, ss_size）
+const STACK_T_SIZE: u64 = 24;
+/// signalfd4 の flags: SFD_NONBLOCK (= O_NONBLOCK)
+const SFD_NONBLOCK: u64 = 0x800;
+/// signalfd4 の flags: SFD_CLOEXEC (= O_CLOEXEC)
+const SFD_CLOEXEC: u64 = O_CLOEXEC;
+/// x86-64 ABI のレッドゾーン。ハンドラのフレームはこの下に積む
+const RED_ZONE: u64 = 128;
+/// sigreturn でユーザーが変更できる RFLAGS ビット (CF/PF/AF/ZF/SF/TF/DF/OF/AC)
+const USER_RFLAGS_MASK: u64 = 0x40DD5;
+/// RFLAGS.TF / RFLAGS.DF（ハンドラ開始時にクリアする）
+const RFLAGS_TF: u64 = 1 << 8;
+const RFLAGS_DF: u64 = 1 << 10;
+
 /// rt_sigaction システムコール
 ///
 /// # 引数
@@ -189,7 +215,7 @@ pub fn kill(pid_raw: u64, sig_raw: u64) -> u64 {
         if !caller_can_signal_target(target) {
             return EPERM;
         }
-        deliver_signal_to_pid(target, sig)
+        deliver_siginfo_to_pid(target, sender_info(sig, SI_USER))
     } else if target_pid_raw == -1 {
         if !caller_can_broadcast_signal() {
             return EPERM;
@@ -201,7 +227,7 @@ pub fn kill(pid_raw: u64, sig_raw: u64) -> u64 {
         crate::task::for_each_process(|p| pids.push(p.id()));
         for pid in pids {
             if Some(pid) != current_pid {
-                if deliver_signal_to_pid(pid, sig) == SUCCESS {
+                if deliver_siginfo_to_pid(pid, sender_info(sig, SI_USER)) == SUCCESS {
                     found = true;
                 }
             }
@@ -236,7 +262,7 @@ pub fn tkill(tid_raw: u64, sig_raw: u64) -> u64 {
     if sig == 0 {
         return SUCCESS;
     }
-    deliver_signal_to_pid(target_pid, sig)
+    deliver_siginfo_to_pid(target_pid, sender_info(sig, SI_TKILL))
 }
 
 /// tgkill システムコール
@@ -263,19 +289,28 @@ pub fn tgkill(tgid_raw: u64, tid_raw: u64, sig_raw: u64) -> u64 {
     if sig == 0 {
         return SUCCESS;
     }
-    deliver_signal_to_pid(target_pid, sig)
+    deliver_siginfo_to_pid(target_pid, sender_info(sig, SI_TKILL))
 }
 
 /// 指定プロセスにシグナルを送達する（カーネル内部からも呼ばれる）
 pub fn deliver_signal_to_pid(pid: ProcessId, sig: usize) -> u64 {
+    deliver_siginfo_to_pid(pid, SigInfo::new(sig, SI_KERNEL))
+}
+
+/// siginfo 付きでシグナルを送達する
+///
+/// 無視されるシグナルは保留せずに捨てる。リアルタイムシグナルのキューが
+/// 満杯なら EAGAIN を返す。
+pub fn deliver_siginfo_to_pid(pid: ProcessId, info: SigInfo) -> u64 {
+    let sig = info.signo;
     if sig < 1 || sig > 64 {
         return EINVAL;
     }
 
-    let exists = with_process(pid, |_| ()).is_some();
-    if !exists {
-        return ESRCH;
-    }
+    let traced = match with_process(pid, |p| p.tracer().is_some()) {
+        Some(traced) => traced,
+        None => return ESRCH,
+    };
 
     // SIGKILL はハンドラを無視して即終了
     if sig == SIGKILL {
@@ -283,33 +318,80 @@ pub fn deliver_signal_to_pid(pid: ProcessId, sig: usize) -> u64 {
         return SUCCESS;
     }
 
-    // SYSCALL 経路では return-to-user 前のシグナル送達フックがまだないため、
-    // 「自分宛て + 非ブロック + 既定動作=Terminate」はここで即時終了させる。
-    if current_pid().is_some_and(|cur| cur == pid) {
-        let (blocked, action) = match with_process(pid, |p| {
-            let state = p.signal_state();
-            let blocked = (state.mask & (1u64 << (sig - 1))) != 0;
-            (blocked, state.action(sig))
-        }) {
-            Some(v) => v,
+    // トレース中は既定動作を先取りせず、配送時の ptrace 停止でトレーサーに渡す
+    if traced {
+        match with_process_mut(pid, |p| p.signal_state_mut().enqueue(info)) {
+            Some(true) => {}
+            Some(false) => return EAGAIN,
             None => return ESRCH,
-        };
+        }
+        wake_first_thread_of(pid);
+        crate::syscall::ptrace::request_interrupt(pid);
+        return SUCCESS;
+    }
 
-        if !blocked {
-            if action.is_ignored() {
-                return SUCCESS;
+    // SIGCONT はハンドラ・マスクに関係なく停止中のプロセスを再開させる。
+    // 逆に停止シグナルは保留中の SIGCONT を打ち消す。
+    if sig == SIGCONT {
+        continue_process(pid);
+    } else if is_stop_signal(sig) {
+        with_process_mut(pid, |p| {
+            p.signal_state_mut().clear_pending(sig_bit(SIGCONT))
+        });
+    }
+
+    let (blocked, action) = match with_process(pid, |p| {
+        let state = p.signal_state();
+        let blocked = (state.mask & sig_bit(sig)) != 0;
+        (blocked, state.action(sig))
+    }) {
+        Some(v) => v,
+        None => return ESRCH,
+    };
+
+    if !blocked {
+        if action.is_ignored() {
+            return SUCCESS;
+        }
+        if action.is_default() {
+            match default_action(sig) {
+                // SIGSTOP はマスク・ハンドラ不可なので常にここで止まる
+                DefaultAction::Stop => {
+                    stop_process(pid, sig);
+                    return SUCCESS;
+                }
+                DefaultAction::Continue | DefaultAction::Ignore => return SUCCESS,
+                DefaultAction::Terminate | DefaultAction::Core => {}
             }
-            if action.is_default() && matches!(default_action(sig), DefaultAction::Terminate) {
+        }
+    }
+
+    // SYSCALL 経路では return-to-user 前のシグナル送達フックがまだないため、
+    // 「自分宛て + 非ブロック + 既定動作=Terminate/Core」はここで即時終了させる。
+    // Core のレジスタは syscall 入口で保存した RIP/RSP/RFLAGS のみ。
+    if current_pid().is_some_and(|cur| cur == pid) && !blocked && action.is_default() {
+        match default_action(sig) {
+            DefaultAction::Terminate => crate::task::exit_current_task(sig as u64),
+            DefaultAction::Core => {
+                if let Some((rip, rsp, rflags)) = current_thread_id()
+                    .and_then(|tid| crate::task::with_thread(tid, |t| t.syscall_user_context()))
+                {
+                    let regs =
+                        crate::syscall::coredump::CoreRegs::from_syscall_context(rip, rsp, rflags);
+                    crate::syscall::coredump::dump_current(&info, regs);
+                }
                 crate::task::exit_current_task(sig as u64);
             }
-            if action.is_default() && matches!(default_action(sig), DefaultAction::Ignore) {
-                return SUCCESS;
-            }
+            _ => {}
         }
     }
 
-    // pending ビットをセット
-    with_process_mut(pid, |p| p.signal_state_mut().set_pending(sig));
+    // siginfo をキューに積む
+    match with_process_mut(pid, |p| p.signal_state_mut().enqueue(info)) {
+        Some(true) => {}
+        Some(false) => return EAGAIN,
+        None => return ESRCH,
+    }
 
     // ブロッキング待機しているスレッドを起床させる（シグナルを受け取れるよう）
     wake_first_thread_of(pid);
@@ -319,15 +401,261 @@ pub fn deliver_signal_to_pid(pid: ProcessId, sig: usize) -> u64 {
 
 /// 子プロセス終了時に親プロセスへ SIGCHLD を送達する（scheduler から呼ばれる）
 pub fn deliver_sigchld_to_parent(child_pid: ProcessId) {
+    let (parent, exit_code) = match with_process(child_pid, |p| (p.parent_id(), p.exit_code())) {
+        Some((Some(parent), code)) => (parent, code.unwrap_or(0)),
+        _ => return,
+    };
+    deliver_siginfo_to_pid(
+        parent,
+        child_info(child_pid, CLD_EXITED, (exit_code & 0xff) as i32),
+    );
+}
+
+/// 子プロセスの停止/再開を親へ SIGCHLD で知らせる（SA_NOCLDSTOP 指定時は送らない）
+fn notify_parent_job_change(child_pid: ProcessId, code: i32, status: i32) {
     let parent_pid = match with_process(child_pid, |p| p.parent_id()) {
         Some(Some(pid)) => pid,
         _ => return,
     };
-    deliver_signal_to_pid(parent_pid, SIGCHLD);
+    let nocldstop = with_process(parent_pid, |p| {
+        p.signal_state().action(SIGCHLD).flags & SA_NOCLDSTOP != 0
+    })
+    .unwrap_or(true);
+    if !nocldstop {
+        deliver_siginfo_to_pid(parent_pid, child_info(child_pid, code, status));
+    }
+}
+
+/// プロセスを停止させる（停止系シグナルの既定動作）
+///
+/// 自プロセスを止めた場合は SIGCONT で再開されるまで戻らない。
+fn stop_process(pid: ProcessId, sig: usize) {
+    let stopped = with_process_mut(pid, |p| p.mark_stopped(sig as u8)).unwrap_or(false);
+    if !stopped {
+        return;
+    }
+    crate::task::stop_process_threads(pid);
+    notify_parent_job_change(pid, CLD_STOPPED, sig as i32);
+    if current_pid() == Some(pid) {
+        crate::task::wait_while_stopped();
+    }
+}
+
+/// 停止中のプロセスを再開させ、保留中の停止シグナルを破棄する
+fn continue_process(pid: ProcessId) {
+    let resumed = with_process_mut(pid, |p| {
+        p.signal_state_mut().clear_pending(STOP_SIGNAL_MASK);
+        p.mark_continued()
+    })
+    .unwrap_or(false);
+    if !resumed {
+        return;
+    }
+    crate::task::resume_process_threads(pid);
+    notify_parent_job_change(pid, CLD_CONTINUED, SIGCONT as i32);
 }
 
 // ---- syscall リターン時のシグナル送達 ----------------------------------------
 
+// 保存済みレジスタ領域の汎用レジスタ位置（int 0x80 / 例外エントリ共通の push 順）
+const REG_R15: usize = 0;
+const REG_R14: usize = 1;
+const REG_R13: usize = 2;
+const REG_R12: usize = 3;
+const REG_R11: usize = 4;
+const REG_R10: usize = 5;
+const REG_R9: usize = 6;
+const REG_R8: usize = 7;
+const REG_RDI: usize = 8;
+const REG_RSI: usize = 9;
+const REG_RBP: usize = 10;
+const REG_RBX: usize = 11;
+const REG_RDX: usize = 12;
+const REG_RCX: usize = 13;
+const REG_RAX: usize = 14;
+
+/// struct sigcontext の先頭に並ぶ汎用レジスタの順序（r8..r15, rdi, rsi, rbp, rbx, rdx, rax, rcx）
+const SIGCONTEXT_GPRS: [usize; 15] = [
+    REG_R8, REG_R9, REG_R10, REG_R11, REG_R12, REG_R13, REG_R14, REG_R15, REG_RDI, REG_RSI,
+    REG_RBP, REG_RBX, REG_RDX, REG_RAX, REG_RCX,
+];
+
+// ---- ucontext_t レイアウト (Linux x86-64 カーネル互換, 304 バイト) ----
+// [+0]   uc_flags, [+8] uc_link, [+16] uc_stack (ss_sp, ss_flags, ss_size)
+// [+40]  uc_mcontext (struct sigcontext, 256 バイト)
+// [+296] uc_sigmask
+const UC_STACK: usize = 16;
+const UC_MCONTEXT: usize = 40;
+const UC_SIGMASK: usize = 296;
+const UCONTEXT_SIZE: usize = 304;
+// sigcontext 内のオフセット
+const SC_RAX: usize = 104;
+const SC_RSP: usize = 120;
+const SC_RIP: usize = 128;
+const SC_EFLAGS: usize = 136;
+const SC_CSGSFS: usize = 144;
+const SC_ERR: usize = 152;
+const SC_TRAPNO: usize = 160;
+const SC_OLDMASK: usize = 168;
+const SC_CR2: usize = 176;
+/// __reserved1[0] にフレーム検証用のマジックを置く
+const SC_MAGIC: usize = 192;
+
+/// シグナルフレーム全体: 戻り番地 + ucontext + siginfo
+const SIGFRAME_SIZE: u64 = 8 + UCONTEXT_SIZE as u64 + SIGINFO_SIZE;
+
+/// 例外で送達するシグナルに添える CPU 情報（sigcontext の trapno/err/cr2）
+#[derive(Debug, Clone, Copy)]
+pub struct FaultContext {
+    pub trapno: u64,
+    pub error_code: u64,
+    pub cr2: u64,
+}
+
+/// カーネルスタック上に保存されたユーザーレジスタ
+///
+/// int 0x80 エントリとページフォルトエントリは同じ順序で汎用レジスタを積み、
+/// その上に CPU の割り込みフレーム（例外はエラーコード付き）が続く。
+#[derive(Clone, Copy)]
+pub struct TrapFrame {
+    regs: *mut u64,
+    /// 割り込みフレーム（RIP）の位置
+    iret: usize,
+}
+
+impl TrapFrame {
+    /// int 0x80 エントリの保存領域
+    ///
+    /// # Safety
+    /// `kstack` は int 0x80 エントリが積んだレジスタ領域を指している必要がある。
+    pub unsafe fn from_int80(kstack: *mut u64) -> Self {
+        Self {
+            regs: kstack,
+            iret: 15,
+        }
+    }
+
+    /// エラーコード付き例外エントリの保存領域
+    ///
+    /// # Safety
+    /// `regs` は例外エントリが積んだレジスタ領域を指している必要がある。
+    pub unsafe fn from_exception(regs: *mut u64) -> Self {
+        Self { regs, iret: 16 }
+    }
+
+    /// エラーコードなし例外エントリ（#DB / #BP）の保存領域（並びは int 0x80 と同じ）
+    ///
+    /// # Safety
+    /// `regs` は例外エントリが積んだレジスタ領域を指している必要がある。
+    pub unsafe fn from_interrupt(regs: *mut u64) -> Self {
+        Self { regs, iret: 15 }
+    }
+
+    /// 保存領域の先頭アドレスと割り込みフレームの位置（ptrace 停止の記録用）
+    pub fn into_raw(self) -> (u64, usize) {
+        (self.regs as u64, self.iret)
+    }
+
+    /// `into_raw` で記録した値から戻す
+    ///
+    /// # Safety
+    /// 保存領域を積んだスレッドがまだそのエントリの中に留まっている必要がある。
+    pub unsafe fn from_raw(regs: u64, iret: usize) -> Self {
+        Self {
+            regs: regs as *mut u64,
+            iret,
+        }
+    }
+
+    fn get(&self, idx: usize) -> u64 {
+        unsafe { self.regs.add(idx).read() }
+    }
+
+    fn set(&self, idx: usize, value: u64) {
+        unsafe { self.regs.add(idx).write(value) }
+    }
+
+    pub fn rax(&self) -> u64 {
+        self.get(REG_RAX)
+    }
+
+    pub fn set_rax(&self, value: u64) {
+        self.set(REG_RAX, value)
+    }
+
+    pub fn rip(&self) -> u64 {
+        self.get(self.iret)
+    }
+
+    pub fn cs(&self) -> u64 {
+        self.get(self.iret + 1)
+    }
+
+    pub fn rflags(&self) -> u64 {
+        self.get(self.iret + 2)
+    }
+
+    pub fn rsp(&self) -> u64 {
+        self.get(self.iret + 3)
+    }
+
+    /// フレームポインタ（スタックトレースの起点）
+    pub fn rbp(&self) -> u64 {
+        self.get(REG_RBP)
+    }
+
+    fn ss(&self) -> u64 {
+        self.get(self.iret + 4)
+    }
+
+    /// コアダンプ用のレジスタ一式
+    pub fn core_regs(&self) -> crate::syscall::coredump::CoreRegs {
+        let mut gprs = [0u64; 15];
+        for (i, v) in gprs.iter_mut().enumerate() {
+            *v = self.get(i);
+        }
+        crate::syscall::coredump::CoreRegs::from_saved(
+            gprs,
+            self.rip(),
+            self.cs(),
+            self.rflags(),
+            self.rsp(),
+            self.ss(),
+        )
+    }
+
+    fn set_rip(&self, value: u64) {
+        self.set(self.iret, value)
+    }
+
+    pub fn set_rflags(&self, value: u64) {
+        self.set(self.iret + 2, value)
+    }
+
+    fn set_rsp(&self, value: u64) {
+        self.set(self.iret + 3, value)
+    }
+
+    /// PTRACE_SETREGS などで書き換えたレジスタを保存領域へ戻す
+    ///
+    /// CS/SS と特権に関わる RFLAGS ビットは変えさせない。RIP/RSP が
+    /// ユーザー空間の canonical アドレスでなければ何もせず false を返す。
+    pub fn apply_core_regs(&self, regs: &crate::syscall::coredump::CoreRegs) -> bool {
+        const USER_SPACE_END: u64 = 0x0000_7FFF_FFFF_FFFF;
+        let (gprs, rip, rflags, rsp) = regs.to_saved();
+        if rip > USER_SPACE_END || rsp > USER_SPACE_END {
+            return false;
+        }
+        for (i, v) in gprs.iter().enumerate() {
+            self.set(i, *v);
+        }
+        self.set_rip(rip);
+        self.set_rsp(rsp);
+        self.set_rflags((self.rflags() & !USER_RFLAGS_MASK) | (rflags & USER_RFLAGS_MASK));
+        true
+    }
+}
+
 /// int 0x80 リターン時に呼ばれる: pending シグナルの送達とシグナルフレームの設定
 ///
 /// # 引数
@@ -348,31 +676,64 @@ pub fn deliver_sigchld_to_parent(child_pid: ProcessId) {
 /// ```
 #[no_mangle]
 pub extern "sysv64" fn signal_and_return(kstack: *mut u64, syscall_ret: u64) -> u64 {
+    let frame = unsafe { TrapFrame::from_int80(kstack) };
     // kstack[14] = [rsp+112] = 元の syscall 番号（dispatch 呼び出し前の push rax）
-    let syscall_num = unsafe { kstack.add(14).read() };
+    let syscall_num = frame.get(REG_RAX);
 
     // rt_sigreturn (15): シグナルフレームから元のコンテキストを復元
     if syscall_num == crate::syscall::SyscallNumber::RtSigreturn as u64 {
-        rt_sigreturn(kstack);
-        return 0;
+        return rt_sigreturn(frame);
     }
 
+    deliver_pending_signals(frame, syscall_ret)
+}
+
+/// ユーザーへ戻る直前に保留中のシグナルを 1 つ送達する
+///
+/// int 0x80 の復帰時に加え、ptrace の割り込み（#DB）からも呼ばれる。
+/// 戻り値はユーザーへ返す RAX。
+pub fn deliver_pending_signals(frame: TrapFrame, syscall_ret: u64) -> u64 {
     // シグナルを持つ current process を取得
     let pid = match current_pid() {
         Some(p) => p,
         None => return syscall_ret,
     };
+    let mut syscall_ret = syscall_ret;
 
     // 送達すべきシグナルを1つ取り出す
-    let sig = match with_process_mut(pid, |p| p.signal_state_mut().take_next_deliverable()) {
-        Some(Some(s)) => s,
-        _ => return syscall_ret,
+    let mut info = match with_process_mut(pid, |p| {
+        if let Some(state) = p.ptrace_mut() {
+            state.interrupt = false;
+        }
+        p.signal_state_mut().take_next_deliverable()
+    }) {
+        Some(Some(info)) => info,
+        _ => {
+            restore_suspend_mask(pid);
+            return syscall_ret;
+        }
     };
 
+    // トレース中はシグナル配送停止でトレーサーに判断させる（捨てる・差し替える）
+    if info.signo != SIGKILL && with_process(pid, |p| p.tracer().is_some()).unwrap_or(false) {
+        frame.set(REG_RAX, syscall_ret);
+        let delivered = crate::syscall::ptrace::signal_delivery_stop(frame, info);
+        syscall_ret = frame.get(REG_RAX);
+        match delivered {
+            Some(next) => info = next,
+            None => {
+                restore_suspend_mask(pid);
+                return syscall_ret;
+            }
+        }
+    }
+    let sig = info.signo;
+
     let action =
         with_process(pid, |p| p.signal_state().action(sig)).unwrap_or(SigAction::default_action());
 
     if action.is_ignored() {
+        restore_suspend_mask(pid);
         return syscall_ret;
     }
 
@@ -382,128 +743,585 @@ pub extern "sysv64" fn signal_and_return(kstack: *mut u64, syscall_ret: u64) ->
             DefaultAction::Terminate => {
                 crate::task::exit_current_task(sig as u64);
             }
-            DefaultAction::Ignore => return syscall_ret,
+            DefaultAction::Core => {
+                crate::syscall::coredump::dump_current(&info, frame.core_regs());
+                crate::task::exit_current_task(sig as u64);
+            }
+            DefaultAction::Stop => {
+                stop_process(pid, sig);
+            }
+            DefaultAction::Ignore | DefaultAction::Continue => {}
         }
+        restore_suspend_mask(pid);
+        return syscall_ret;
     }
 
-    // ユーザーハンドラへリダイレクト
-    unsafe { setup_signal_frame(kstack, sig, &action) };
+    // ハンドラから戻ったときに syscall の戻り値を復元できるよう、RAX に入れてから保存する
+    frame.set(REG_RAX, syscall_ret);
+    if !setup_signal_frame(frame, &info, &action, None) {
+        // ユーザースタックが不正 → 強制終了
+        crate::task::exit_current_task(SIGSEGV as u64);
+    }
 
-    // ハンドラには syscall の戻り値ではなくシグナル番号が RDI に入る（フレーム設定済み）
     // RAX はハンドラには見えないが一応 0 にする
     0
 }
 
-/// int 0x80 割り込みフレームにシグナルフレームを積み、ハンドラへリダイレクトする
-///
-/// # Safety
-/// `kstack` は有効な割り込みスタックフレームを指している必要がある。
-unsafe fn setup_signal_frame(kstack: *mut u64, sig: usize, action: &SigAction) {
-    // 割り込みフレームから user RIP / RSP / RFLAGS を取得
-    let user_rip = kstack.add(15).read();
-    let user_rflags = kstack.add(17).read();
-    let user_rsp = kstack.add(18).read();
-
-    // ユーザースタック上にシグナルフレームを構築
-    // レイアウト（低アドレス → 高アドレス, 新 RSP は先頭）:
-    //   [new_rsp + 0]:  sa_restorer  ← ハンドラの戻り先（return address）
-    //   [new_rsp + 8]:  saved RIP
-    //   [new_rsp + 16]: saved RSP
-    //   [new_rsp + 24]: saved RFLAGS
-    //
-    // ハンドラ呼び出し規約: x86-64 では `call` の直後は RSP % 16 == 8 なので、
-    // 戻り番地を積んだ直後のスタックトップとして new_rsp を渡す。
-    const FRAME_BYTES: u64 = 32;
-    let aligned = (user_rsp.wrapping_sub(FRAME_BYTES)) & !15u64;
-    let new_rsp = aligned.wrapping_sub(8); // call 直後を模倣: RSP % 16 == 8
-
-    // フレームをユーザースタックに書き込む
-    let ok = write_signal_frame(new_rsp, action.restorer, user_rip, user_rsp, user_rflags);
-    if !ok {
-        // ユーザースタックが不正 → 強制終了
-        crate::task::exit_current_task(11); // SIGSEGV
-    }
+/// rt_sigsuspend で退避したマスクが残っていれば戻す
+fn restore_suspend_mask(pid: ProcessId) {
+    with_process_mut(pid, |p| {
+        let state = p.signal_state_mut();
+        if let Some(mask) = state.saved_mask.take() {
+            state.mask = mask;
+        }
+    });
+}
 
-    // ハンドラ実行中は action.mask のシグナルをブロック
-    if let Some(pid) = current_pid() {
-        with_process_mut(pid, |p| {
-            p.signal_state_mut().mask |= action.mask;
-        });
+/// 例外ハンドラから同期シグナル（SIGSEGV など）を送達する
+///
+/// ユーザーハンドラが登録されていてブロックされていなければシグナルフレームを積み、
+/// 例外からの復帰先をハンドラへ書き換えて true を返す。
+/// それ以外（既定動作・無視・ブロック中・フレームを積めない）は false を返すので、
+/// 呼び出し側でプロセスを終了させる。
+pub fn force_fault_signal(frame: TrapFrame, info: SigInfo, fault: FaultContext) -> bool {
+    let pid = match current_pid() {
+        Some(p) => p,
+        None => return false,
+    };
+    let (blocked, action) = match with_process(pid, |p| {
+        let state = p.signal_state();
+        (
+            state.mask & sig_bit(info.signo) != 0,
+            state.action(info.signo),
+        )
+    }) {
+        Some(v) => v,
+        None => return false,
+    };
+    if blocked || !action.has_user_handler() {
+        return false;
     }
+    setup_signal_frame(frame, &info, &action, Some(fault))
+}
 
-    // 割り込みフレームを書き換えてハンドラへリダイレクト
-    kstack.add(8).write(sig as u64); // RDI = シグナル番号（ハンドラの第1引数）
-    kstack.add(15).write(action.handler); // user RIP → ハンドラ
-    kstack.add(18).write(new_rsp); // user RSP → シグナルフレーム先頭
+/// ページフォルトの si_code（保護違反なら SEGV_ACCERR、未マップなら SEGV_MAPERR）
+pub fn segv_info(addr: u64, protection_violation: bool) -> SigInfo {
+    let mut info = SigInfo::new(
+        SIGSEGV,
+        if protection_violation {
+            SEGV_ACCERR
+        } else {
+            SEGV_MAPERR
+        },
+    );
+    info.addr = addr;
+    info
 }
 
-/// シグナルフレームをユーザースタックに書き込む
-fn write_signal_frame(
-    new_rsp: u64,
-    restorer: u64,
-    saved_rip: u64,
-    saved_rsp: u64,
-    saved_rflags: u64,
+/// ユーザースタック（または代替スタック）に rt_sigframe を積み、ハンドラへリダイレクトする
+///
+/// フレーム（低アドレス → 高アドレス, 新 RSP は先頭）:
+/// ```text
+/// [new_rsp + 0]        vDSO の __vdso_rt_sigreturn（ハンドラの戻り番地。sa_restorer は使わない）
+/// [new_rsp + 8]        ucontext_t（レジスタ・旧マスク・代替スタック情報）
+/// [new_rsp + 8 + 304]  siginfo_t
+/// ```
+/// ハンドラは `handler(sig, &siginfo, &ucontext)` の形で呼ばれる。
+/// SA_SIGINFO なしのハンドラも同じフレームで、第 2/3 引数を無視するだけ。
+fn setup_signal_frame(
+    frame: TrapFrame,
+    info: &SigInfo,
+    action: &SigAction,
+    fault: Option<FaultContext>,
 ) -> bool {
-    // 書き込みアドレスの検証（32バイト）
-    if !crate::syscall::validate_user_ptr(new_rsp, 32) {
+    let pid = match current_pid() {
+        Some(p) => p,
+        None => return false,
+    };
+    let restorer = match crate::mem::vdso::sigreturn_addr() {
+        Some(addr) => addr,
+        None => return false,
+    };
+    let (altstack, old_mask) = match with_process_mut(pid, |p| {
+        let state = p.signal_state_mut();
+        let old_mask = state.saved_mask.take().unwrap_or(state.mask);
+        (state.altstack, old_mask)
+    }) {
+        Some(v) => v,
+        None => return false,
+    };
+
+    let user_rsp = frame.rsp();
+    let on_altstack = altstack.contains(user_rsp);
+    let use_altstack = action.flags & SA_ONSTACK != 0 && altstack.is_enabled() && !on_altstack;
+    let base = if use_altstack {
+        altstack.sp + altstack.size
+    } else {
+        user_rsp.wrapping_sub(RED_ZONE)
+    };
+
+    // ucontext を 16 バイト境界に置き、戻り番地を積んだ直後（call 直後）を模倣して
+    // ハンドラ開始時に RSP % 16 == 8 になるようにする。
+    let uc_addr = base.wrapping_sub(UCONTEXT_SIZE as u64 + SIGINFO_SIZE) & !15u64;
+    let new_rsp = uc_addr.wrapping_sub(8);
+    let info_addr = uc_addr + UCONTEXT_SIZE as u64;
+    if use_altstack && new_rsp < altstack.sp {
+        return false;
+    }
+
+    let stack_flags = if on_altstack {
+        SS_ONSTACK
+    } else {
+        altstack.flags & SS_DISABLE
+    };
+    let mut buf = [0u8; SIGFRAME_SIZE as usize];
+    buf[0..8].copy_from_slice(&restorer.to_ne_bytes());
+    encode_ucontext(
+        &mut buf[8..8 + UCONTEXT_SIZE],
+        frame,
+        old_mask,
+        &altstack,
+        stack_flags,
+        fault,
+    );
+    buf[8 + UCONTEXT_SIZE..].copy_from_slice(&info.to_siginfo_bytes());
+
+    if !crate::syscall::validate_user_ptr(new_rsp, SIGFRAME_SIZE)
+        || crate::syscall::copy_to_user(new_rsp, &buf).is_err()
+    {
         return false;
     }
-    let mut frame = [0u8; 32];
-    frame[0..8].copy_from_slice(&restorer.to_ne_bytes());
-    frame[8..16].copy_from_slice(&saved_rip.to_ne_bytes());
-    frame[16..24].copy_from_slice(&saved_rsp.to_ne_bytes());
-    frame[24..32].copy_from_slice(&saved_rflags.to_ne_bytes());
-    crate::syscall::copy_to_user(new_rsp, &frame).is_ok()
+
+    // ハンドラ実行中は sa_mask と（SA_NODEFER でなければ）自分自身をブロックする
+    let sig = info.signo;
+    with_process_mut(pid, |p| {
+        let state = p.signal_state_mut();
+        let mut mask = old_mask | action.mask;
+        if action.flags & SA_NODEFER == 0 {
+            mask |= sig_bit(sig);
+        }
+        state.mask = mask & !UNCATCHABLE_MASK;
+        if action.flags & SA_RESETHAND != 0 {
+            state.set_action(sig, SigAction::default_action());
+        }
+    });
+
+    // 保存領域を書き換えてハンドラへリダイレクト
+    frame.set(REG_RDI, sig as u64); // 第1引数: シグナル番号
+    frame.set(REG_RSI, info_addr); // 第2引数: siginfo_t*
+    frame.set(REG_RDX, uc_addr); // 第3引数: ucontext_t*
+    frame.set(REG_RAX, 0);
+    frame.set_rip(action.handler);
+    frame.set_rsp(new_rsp);
+    frame.set_rflags(frame.rflags() & !(RFLAGS_TF | RFLAGS_DF));
+    true
+}
+
+/// ucontext_t をエンコードする
+fn encode_ucontext(
+    out: &mut [u8],
+    frame: TrapFrame,
+    old_mask: u64,
+    altstack: &AltStack,
+    stack_flags: u32,
+    fault: Option<FaultContext>,
+) {
+    let mut put = |off: usize, v: u64| out[off..off + 8].copy_from_slice(&v.to_ne_bytes());
+
+    put(UC_STACK, altstack.sp);
+    put(UC_STACK + 8, stack_flags as u64);
+    put(UC_STACK + 16, altstack.size);
+
+    let mc = UC_MCONTEXT;
+    for (i, reg) in SIGCONTEXT_GPRS.iter().enumerate() {
+        put(mc + i * 8, frame.get(*reg));
+    }
+    put(mc + SC_RSP, frame.rsp());
+    put(mc + SC_RIP, frame.rip());
+    put(mc + SC_EFLAGS, frame.rflags());
+    // cs, gs, fs, ss（各 16bit）
+    put(
+        mc + SC_CSGSFS,
+        (frame.cs() & 0xffff) | ((frame.ss() & 0xffff) << 48),
+    );
+    if let Some(fault) = fault {
+        put(mc + SC_ERR, fault.error_code);
+        put(mc + SC_TRAPNO, fault.trapno);
+        put(mc + SC_CR2, fault.cr2);
+    }
+    put(mc + SC_OLDMASK, old_mask);
+    put(mc + SC_MAGIC, SIGNAL_FRAME_MAGIC);
+    put(UC_SIGMASK, old_mask);
 }
 
 /// rt_sigreturn システムコール
 ///
 /// シグナルハンドラから戻るときに呼ばれる。
-/// シグナルフレームから保存された RIP / RSP / RFLAGS を復元する。
+/// ハンドラが `ret` で戻り番地を pop した後、vDSO のトランポリンが int 0x80 (RAX=15) を実行するので、
+/// その時点の user RSP は ucontext_t の先頭を指している。
+/// ucontext_t から汎用レジスタ・RIP・RSP・RFLAGS とシグナルマスクを復元し、
+/// 中断されたコンテキストの RAX を返す。
+fn rt_sigreturn(frame: TrapFrame) -> u64 {
+    let uc_addr = frame.rsp();
+    let mut uc = [0u8; UCONTEXT_SIZE];
+    if !crate::syscall::validate_user_ptr(uc_addr, UCONTEXT_SIZE as u64)
+        || crate::syscall::copy_from_user(uc_addr, &mut uc).is_err()
+    {
+        crate::task::exit_current_task(SIGSEGV as u64);
+    }
+    let get = |off: usize| {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(&uc[off..off + 8]);
+        u64::from_ne_bytes(b)
+    };
+
+    let mc = UC_MCONTEXT;
+    let saved_rip = get(mc + SC_RIP);
+    let saved_rsp = get(mc + SC_RSP);
+    // 壊れたフレームや非 canonical な復帰先で iretq させない
+    if get(mc + SC_MAGIC) != SIGNAL_FRAME_MAGIC
+        || !crate::syscall::validate_user_ptr(saved_rip, 1)
+        || !crate::syscall::validate_user_ptr(saved_rsp, 1)
+    {
+        crate::task::exit_current_task(SIGSEGV as u64);
+    }
+
+    for (i, reg) in SIGCONTEXT_GPRS.iter().enumerate() {
+        frame.set(*reg, get(mc + i * 8));
+    }
+    frame.set_rip(saved_rip);
+    frame.set_rsp(saved_rsp);
+    // IOPL や IF などはユーザーに変更させない
+    let rflags = (frame.rflags() & !USER_RFLAGS_MASK) | (get(mc + SC_EFLAGS) & USER_RFLAGS_MASK);
+    frame.set_rflags(rflags);
+
+    let mask = get(UC_SIGMASK) & !UNCATCHABLE_MASK;
+    if let Some(pid) = current_pid() {
+        with_process_mut(pid, |p| p.signal_state_mut().mask = mask);
+    }
+
+    get(mc + SC_RAX)
+}
+
+// ---- sigaltstack / sigqueue / sigwait / signalfd --------------------------------
+
+/// sigaltstack システムコール
 ///
 /// # 引数
-/// - `kstack`: int 0x80 割り込みスタックフレーム先頭ポインタ
-pub fn rt_sigreturn(kstack: *mut u64) {
-    // ハンドラが `ret` した後、restorer が int 0x80 (RAX=15) を実行する。
-    // `ret` で sa_restorer を pop したので、user RSP は +8 されている。
-    // つまり user_rsp は saved_rip の直前を指している。
-    let user_rsp = unsafe { kstack.add(18).read() };
+/// - `ss_ptr`: 新しい stack_t (NULLなら変更しない)
+/// - `old_ss_ptr`: 現在の stack_t の書き出し先 (NULLなら無視)
+pub fn sigaltstack(ss_ptr: u64, old_ss_ptr: u64) -> u64 {
+    let pid = match current_pid() {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let current = with_process(pid, |p| p.signal_state().altstack).unwrap_or(AltStack::disabled());
+    let on_stack = current_user_rsp().is_some_and(|rsp| current.contains(rsp));
 
-    if !crate::syscall::validate_user_ptr(user_rsp, 24) {
-        crate::task::exit_current_task(11); // SIGSEGV
+    if old_ss_ptr != 0 {
+        let flags = if on_stack {
+            SS_ONSTACK
+        } else {
+            current.flags & SS_DISABLE
+        };
+        let mut buf = [0u8; STACK_T_SIZE as usize];
+        buf[0..8].copy_from_slice(&current.sp.to_ne_bytes());
+        buf[8..12].copy_from_slice(&flags.to_ne_bytes());
+        buf[16..24].copy_from_slice(&current.size.to_ne_bytes());
+        if !crate::syscall::validate_user_ptr(old_ss_ptr, STACK_T_SIZE) {
+            return EFAULT;
+        }
+        if let Err(e) = crate::syscall::copy_to_user(old_ss_ptr, &buf) {
+            return e;
+        }
     }
 
-    let saved_rip = match crate::syscall::read_user_u64(user_rsp) {
-        Ok(v) => v,
-        Err(_) => {
-            crate::task::exit_current_task(11);
-        }
+    if ss_ptr == 0 {
+        return SUCCESS;
+    }
+    let mut buf = [0u8; STACK_T_SIZE as usize];
+    if let Err(e) = crate::syscall::copy_from_user(ss_ptr, &mut buf) {
+        return e;
+    }
+    // 実行中の代替スタックは差し替えられない
+    if on_stack {
+        return EPERM;
+    }
+    let word = |off: usize| {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(&buf[off..off + 8]);
+        u64::from_ne_bytes(b)
+    };
+    let sp = word(0);
+    let flags = word(8) as u32;
+    let size = word(16);
+
+    let new_stack = if flags == SS_DISABLE {
+        AltStack::disabled()
+    } else if flags & !SS_ONSTACK != 0 {
+        return EINVAL;
+    } else if size < MINSIGSTKSZ {
+        return ENOMEM;
+    } else if !crate::syscall::validate_user_ptr(sp, size) {
+        return EFAULT;
+    } else {
+        AltStack { sp, size, flags: 0 }
+    };
+    with_process_mut(pid, |p| p.signal_state_mut().altstack = new_stack);
+    SUCCESS
+}
+
+/// rt_sigqueueinfo システムコール
+///
+/// # 引数
+/// - `pid_raw`: ターゲット PID
+/// - `sig_raw`: シグナル番号 (0=存在確認のみ)
+/// - `info_ptr`: 送信する siginfo_t（si_code と si_value を使う）
+pub fn rt_sigqueueinfo(pid_raw: u64, sig_raw: u64, info_ptr: u64) -> u64 {
+    let sig = sig_raw as usize;
+    if sig > 64 {
+        return EINVAL;
+    }
+    let target = ProcessId::from_u64(pid_raw);
+    if with_process(target, |_| ()).is_none() {
+        return ESRCH;
+    }
+    if !caller_can_signal_target(target) {
+        return EPERM;
+    }
+
+    let mut buf = [0u8; SIGINFO_SIZE as usize];
+    if let Err(e) = crate::syscall::copy_from_user(info_ptr, &mut buf) {
+        return e;
+    }
+    let mut code_bytes = [0u8; 4];
+    code_bytes.copy_from_slice(&buf[8..12]);
+    let code = i32::from_ne_bytes(code_bytes);
+    // カーネル発・kill 発を装うことは他プロセス宛てでは許さない
+    if current_pid() != Some(target) && (code >= 0 || code == SI_TKILL) {
+        return EPERM;
+    }
+    if sig == 0 {
+        return SUCCESS;
+    }
+
+    let mut info = sender_info(sig, code);
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&buf[24..32]);
+    info.value = u64::from_ne_bytes(value);
+    deliver_siginfo_to_pid(target, info)
+}
+
+/// rt_sigtimedwait システムコール
+///
+/// `set` に含まれるシグナルが保留されるまで待ち、1 件取り出して番号を返す。
+/// 通常は対象シグナルをブロックした上で呼ぶ。
+///
+/// # 引数
+/// - `set_ptr`: 待つシグナル集合
+/// - `info_ptr`: siginfo_t の書き出し先 (NULLなら無視)
+/// - `timeout_ptr`: timespec (NULLなら無期限)
+pub fn rt_sigtimedwait(set_ptr: u64, info_ptr: u64, timeout_ptr: u64) -> u64 {
+    let pid = match current_pid() {
+        Some(p) => p,
+        None => return EINVAL,
     };
-    let saved_rsp = match crate::syscall::read_user_u64(user_rsp + 8) {
-        Ok(v) => v,
-        Err(_) => {
-            crate::task::exit_current_task(11);
+    let set = match crate::syscall::read_user_u64(set_ptr) {
+        Ok(v) => v & !UNCATCHABLE_MASK,
+        Err(e) => return e,
+    };
+    let deadline = if timeout_ptr == 0 {
+        None
+    } else {
+        match read_timeout_ticks(timeout_ptr) {
+            Ok(ticks) => Some(crate::syscall::time::get_ticks().saturating_add(ticks)),
+            Err(e) => return e,
         }
     };
-    let saved_rflags = match crate::syscall::read_user_u64(user_rsp + 16) {
-        Ok(v) => v,
-        Err(_) => {
-            crate::task::exit_current_task(11);
+    if info_ptr != 0 && !crate::syscall::validate_user_ptr(info_ptr, SIGINFO_SIZE) {
+        return EFAULT;
+    }
+
+    let mut taken = None;
+    let mut interrupted = false;
+    wait_for_signal_event(deadline, || {
+        with_process_mut(pid, |p| {
+            let state = p.signal_state_mut();
+            taken = state.take_from(set);
+            // 待ち対象外のシグナルが届いたらハンドラを走らせるため中断する
+            interrupted = taken.is_none() && state.pending & !state.mask & !set != 0;
+        });
+        taken.is_some() || interrupted
+    });
+
+    let info = match taken {
+        Some(info) => info,
+        None if interrupted => return EINTR,
+        None => return EAGAIN,
+    };
+    if info_ptr != 0 {
+        if let Err(e) = crate::syscall::copy_to_user(info_ptr, &info.to_siginfo_bytes()) {
+            return e;
         }
+    }
+    info.signo as u64
+}
+
+/// rt_sigsuspend システムコール
+///
+/// マスクを一時的に `set` に置き換え、ハンドラを持つシグナルが届くまで眠る。
+/// 元のマスクはシグナルフレームに保存され、ハンドラから戻ると復元される。
+/// 常に EINTR を返す。
+pub fn rt_sigsuspend(set_ptr: u64) -> u64 {
+    let pid = match current_pid() {
+        Some(p) => p,
+        None => return EINVAL,
     };
+    let set = match crate::syscall::read_user_u64(set_ptr) {
+        Ok(v) => v & !UNCATCHABLE_MASK,
+        Err(e) => return e,
+    };
+    with_process_mut(pid, |p| {
+        let state = p.signal_state_mut();
+        state.saved_mask = Some(state.mask);
+        state.mask = set;
+    });
+    wait_for_signal_event(None, || {
+        with_process(pid, |p| {
+            let state = p.signal_state();
+            state.pending & !state.mask != 0
+        })
+        .unwrap_or(true)
+    });
+    EINTR
+}
 
-    unsafe {
-        kstack.add(15).write(saved_rip);
-        kstack.add(17).write(saved_rflags);
-        kstack.add(18).write(saved_rsp);
+/// signalfd4 システムコール（signalfd は flags=0 として扱う）
+///
+/// # 引数
+/// - `fd_raw`: -1 なら新規作成、既存の signalfd ならマスクを更新
+/// - `mask_ptr`: 読み出すシグナル集合
+/// - `sizemask`: マスクのバイト数（8 固定）
+/// - `flags`: SFD_NONBLOCK / SFD_CLOEXEC
+pub fn signalfd4(fd_raw: u64, mask_ptr: u64, sizemask: u64, flags: u64) -> u64 {
+    if sizemask != 8 || flags & !(SFD_NONBLOCK | SFD_CLOEXEC) != 0 {
+        return EINVAL;
     }
+    let pid = match current_pid() {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let mask = match crate::syscall::read_user_u64(mask_ptr) {
+        Ok(v) => v & !UNCATCHABLE_MASK,
+        Err(e) => return e,
+    };
 
-    // ハンドラ実行中にブロックしていたマスクを元に戻す（簡易: マスクをクリア）
-    // TODO: シグナルフレームに旧マスクを保存して正確に復元する
-    if let Some(pid) = current_pid() {
-        with_process_mut(pid, |p| p.signal_state_mut().mask = 0);
+    if fd_raw as i64 != -1 {
+        let updated = with_process_mut(pid, |p| match p.fd_table_mut().get_mut(fd_raw as usize) {
+            Some(fh) if fh.signalfd_mask.is_some() => {
+                fh.signalfd_mask = Some(mask);
+                Ok(fd_raw)
+            }
+            Some(_) => Err(EINVAL),
+            None => Err(EBADF),
+        });
+        return match updated {
+            Some(Ok(fd)) => fd,
+            Some(Err(e)) => e,
+            None => ESRCH,
+        };
+    }
+
+    let handle = alloc::boxed::Box::new(FileHandle::new_signalfd(mask, flags & SFD_NONBLOCK));
+    let cloexec = flags & SFD_CLOEXEC != 0;
+    match with_process_mut(pid, |p| p.fd_table_mut().alloc(handle, cloexec)).flatten() {
+        Some(fd) => fd as u64,
+        None => super::types::EMFILE,
+    }
+}
+
+/// signalfd からの読み出し（io::read から呼ばれる）
+///
+/// バッファに収まるだけ struct signalfd_siginfo を返す。
+/// 該当シグナルがなければ、O_NONBLOCK なら EAGAIN、そうでなければ届くまで待つ。
+pub fn signalfd_read(mask: u64, open_flags: u64, buf_ptr: u64, len: u64) -> u64 {
+    if len < SIGINFO_SIZE {
+        return EINVAL;
+    }
+    if !crate::syscall::validate_user_ptr(buf_ptr, len) {
+        return EFAULT;
+    }
+    let pid = match current_pid() {
+        Some(p) => p,
+        None => return EBADF,
+    };
+    if open_flags & SFD_NONBLOCK == 0 {
+        wait_for_signal_event(None, || signalfd_ready(mask));
+    }
+
+    let max = (len / SIGINFO_SIZE) as usize;
+    let mut out = alloc::vec::Vec::new();
+    with_process_mut(pid, |p| {
+        let state = p.signal_state_mut();
+        while out.len() < max {
+            match state.take_from(mask) {
+                Some(info) => out.push(info),
+                None => break,
+            }
+        }
+    });
+    if out.is_empty() {
+        return EAGAIN;
+    }
+    for (i, info) in out.iter().enumerate() {
+        let dst = buf_ptr + i as u64 * SIGINFO_SIZE;
+        if let Err(e) = crate::syscall::copy_to_user(dst, &info.to_signalfd_bytes()) {
+            return e;
+        }
     }
+    out.len() as u64 * SIGINFO_SIZE
+}
+
+/// signalfd が読み出し可能か（poll 用）
+pub fn signalfd_ready(mask: u64) -> bool {
+    current_pid()
+        .and_then(|pid| with_process(pid, |p| p.signal_state().pending & mask != 0))
+        .unwrap_or(false)
+}
+
+/// `ready` が真になるか期限（ティック）が来るまで眠る。期限切れなら false を返す。
+///
+/// シグナル送達時の wake_thread で早めに起きる。起床を取りこぼしても 1 tick で再確認する。
+fn wait_for_signal_event(deadline: Option<u64>, mut ready: impl FnMut() -> bool) -> bool {
+    loop {
+        if ready() {
+            return true;
+        }
+        let now = crate::syscall::time::get_ticks();
+        if deadline.is_some_and(|d| now >= d) {
+            return false;
+        }
+        crate::syscall::time::sleep_until(now + 1);
+    }
+}
+
+/// timespec をティック数（切り上げ）に変換する
+fn read_timeout_ticks(ts_ptr: u64) -> Result<u64, u64> {
+    const TICK_NS: u64 = 10_000_000;
+    let sec = crate::syscall::read_user_i64(ts_ptr)?;
+    let nsec = crate::syscall::read_user_i64(ts_ptr + 8)?;
+    if sec < 0 || !(0..1_000_000_000).contains(&nsec) {
+        return Err(EINVAL);
+    }
+    Ok((sec as u64)
+        .saturating_mul(100)
+        .saturating_add((nsec as u64).div_ceil(TICK_NS)))
+}
+
+/// 現在スレッドの直近の syscall 時点の user RSP
+fn current_user_rsp() -> Option<u64> {
+    let tid = current_thread_id()?;
+    crate::task::with_thread(tid, |t| t.syscall_user_context().1)
 }
 
 // ---- ヘルパー関数 -------------------------------------------------------
@@ -513,9 +1331,19 @@ fn current_pid() -> Option<ProcessId> {
     crate::task::with_thread(tid, |t| t.process_id())
 }
 
-fn current_privilege() -> Option<PrivilegeLevel> {
-    let pid = current_pid()?;
-    with_process(pid, |p| p.privilege())
+/// ユーザーからの送信を表す siginfo（送信元 PID 付き）
+fn sender_info(sig: usize, code: i32) -> SigInfo {
+    let mut info = SigInfo::new(sig, code);
+    info.pid = current_pid().map(|p| p.as_u64()).unwrap_or(0);
+    info
+}
+
+/// 子の状態変化を親に知らせる SIGCHLD の siginfo
+fn child_info(child: ProcessId, code: i32, status: i32) -> SigInfo {
+    let mut info = SigInfo::new(SIGCHLD, code);
+    info.pid = child.as_u64();
+    info.status = status;
+    info
 }
 
 fn caller_can_signal_target(target: ProcessId) -> bool {
@@ -526,11 +1354,14 @@ fn caller_can_signal_target(target: ProcessId) -> bool {
     if caller == target {
         return true;
     }
-    matches!(current_privilege(), Some(PrivilegeLevel::Core))
+    crate::task::current_has_audited(
+        crate::task::capability::CAP_KILL_ANY,
+        &alloc::format!("signal to pid {}", target.as_u64()),
+    )
 }
 
 fn caller_can_broadcast_signal() -> bool {
-    matches!(current_privilege(), Some(PrivilegeLevel::Core))
+    crate::task::current_has_audited(crate::task::capability::CAP_KILL_ANY, "broadcast signal")
 }
 
 /// 指定プロセスの最初のスレッドを起床させる
@@ -0,0 +1,461 @@
+//! syscall トレース（strace）
+//!
+//! トレーサーごとにセッションを 1 つ持ち、対象プロセスの syscall の入口と出口を
+//! イベントとしてセッションのリングに積む。ptrace と違い対象は止まらないので、
+//! トレーサーは SyscallTrace の STRACE_READ で溜まったイベントを後から読む。
+//!
+//! 入口のイベントには引数、出口のイベントには戻り値と所要時間が入る。入口と出口は
+//! 同じ通し番号を持つ。パスや read/write のバッファは先頭 `TRACE_DATA_LEN` バイトを
+//! 対象のアドレス空間にいるうちに写し取っておく（トレーサーからは読めないため）。
+//!
+//! 許可の条件は ptrace と同じ（`ptrace::may_trace`）。
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::types::{SyscallNumber, EFAULT, EINVAL, EPERM, ESRCH, SUCCESS};
+use crate::interrupt::spinlock::SpinLock;
+use crate::task::ProcessId;
+
+// ---- SyscallTrace の操作 ----
+/// 対象にアタッチする (pid, flags)
+pub const STRACE_ATTACH: u64 = 0;
+/// 対象から外れる (pid)。pid 0 なら全部
+pub const STRACE_DETACH: u64 = 1;
+/// 溜まったイベントを読む (buf, count)。読んだ件数を返す
+pub const STRACE_READ: u64 = 2;
+/// 次に fork する自分の子を対象にする (flags)。`strace CMD` 用
+pub const STRACE_ATTACH_NEXT_CHILD: u64 = 3;
+/// 残っている対象の数（予約中の子を含む）
+pub const STRACE_COUNT: u64 = 4;
+
+/// 対象が fork した子も対象にする
+pub const STRACE_FOLLOW_FORK: u64 = 1 << 0;
+
+// ---- イベント種別 ----
+pub const TRACE_ENTER: u32 = 0;
+pub const TRACE_EXIT: u32 = 1;
+/// 対象プロセスの終了（`ret` に終了コード）
+pub const TRACE_PROC_EXIT: u32 = 2;
+/// リングが溢れて捨てたイベント（`ret` に件数）
+pub const TRACE_LOST: u32 = 3;
+
+/// イベントに写し取るデータの最大長
+pub const TRACE_DATA_LEN: usize = 64;
+/// セッションごとに溜めるイベント数
+const TRACE_RING_CAPACITY: usize = 1024;
+
+/// トレースイベント（ユーザー側 `swiftlib::strace::TraceEvent` と同じ配置）
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TraceEvent {
+    pub kind: u32,
+    pub data_len: u32,
+    /// 入口と出口の対応付けに使う通し番号
+    pub seq: u64,
+    pub pid: u64,
+    pub tid: u64,
+    pub nr: u64,
+    pub args: [u64; 6],
+    pub ret: u64,
+    /// 起動からのティック数
+    pub ticks: u64,
+    /// 出口イベントの所要時間（TSC 較正前は 0）
+    pub duration_ns: u64,
+    pub data: [u8; TRACE_DATA_LEN],
+}
+
+impl TraceEvent {
+    const fn new(kind: u32, pid: u64) -> Self {
+        Self {
+            kind,
+            data_len: 0,
+            seq: 0,
+            pid,
+            tid: 0,
+            nr: 0,
+            args: [0; 6],
+            ret: 0,
+            ticks: 0,
+            duration_ns: 0,
+            data: [0; TRACE_DATA_LEN],
+        }
+    }
+}
+
+const EVENT_SIZE: usize = core::mem::size_of::<TraceEvent>();
+
+struct Session {
+    tracer: u64,
+    targets: Vec<u64>,
+    flags: u64,
+    /// 次に fork する子を対象にする（STRACE_ATTACH_NEXT_CHILD）
+    next_child: Option<u64>,
+    events: VecDeque<TraceEvent>,
+    lost: u64,
+    next_seq: u64,
+}
+
+impl Session {
+    fn push(&mut self, event: TraceEvent) {
+        if self.events.len() >= TRACE_RING_CAPACITY {
+            self.events.pop_front();
+            self.lost += 1;
+        }
+        self.events.push_back(event);
+    }
+}
+
+static SESSIONS: SpinLock<Vec<Session>> = SpinLock::named("STRACE_SESSIONS", Vec::new());
+/// 対象（予約を含む）が 1 つでもあるか（無い間は syscall 経路のフックを素通りする）
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+fn update_active(sessions: &[Session]) {
+    let active = sessions
+        .iter()
+        .any(|s| !s.targets.is_empty() || s.next_child.is_some());
+    ACTIVE.store(active, Ordering::Relaxed);
+}
+
+/// syscall 入口で記録した内容（出口まで持ち回る）
+pub struct TraceEntry {
+    seq: u64,
+    pid: u64,
+    nr: u64,
+    args: [u64; 6],
+    start_tsc: u64,
+}
+
+/// 入口でパス引数として写し取る引数の位置
+fn path_arg(nr: u64) -> Option<usize> {
+    const ARG0: &[SyscallNumber] = &[
+        SyscallNumber::Open,
+        SyscallNumber::Stat,
+        SyscallNumber::Lstat,
+        SyscallNumber::Access,
+        SyscallNumber::Execve,
+        SyscallNumber::Truncate,
+        SyscallNumber::Unlink,
+        SyscallNumber::Readlink,
+        SyscallNumber::Statfs,
+        SyscallNumber::Mkdir,
+        SyscallNumber::Rmdir,
+        SyscallNumber::Chdir,
+        SyscallNumber::Exec,
+        SyscallNumber::ExecFromFsStream,
+    ];
+    const ARG1: &[SyscallNumber] = &[
+        SyscallNumber::Openat,
+        SyscallNumber::Newfstatat,
+        SyscallNumber::Unlinkat,
+        SyscallNumber::Faccessat,
+        SyscallNumber::Readlinkat,
+    ];
+    if ARG0.iter().any(|&n| n as u64 == nr) {
+        Some(0)
+    } else if ARG1.iter().any(|&n| n as u64 == nr) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// 対象のアドレス空間から読めるだけ読む（失敗しても監査ログには残さない）
+fn peek_user(ptr: u64, out: &mut [u8]) -> usize {
+    if out.is_empty() || !super::validate_user_ptr(ptr, 1) {
+        return 0;
+    }
+    let Some(table) = super::current_user_page_table() else {
+        return 0;
+    };
+    // ページ境界の先が読めなくても手前の分は取る
+    let first = out.len().min(0x1000 - (ptr & 0xfff) as usize);
+    if crate::mem::paging::copy_from_user_in_table(table, ptr, &mut out[..first]).is_err() {
+        return 0;
+    }
+    if first == out.len() {
+        return first;
+    }
+    match crate::mem::paging::copy_from_user_in_table(table, ptr + first as u64, &mut out[first..])
+    {
+        Ok(()) => out.len(),
+        Err(_) => first,
+    }
+}
+
+/// パス（NUL 終端）を写し取る
+fn capture_path(ptr: u64, event: &mut TraceEvent) {
+    let n = peek_user(ptr, &mut event.data);
+    let len = event.data[..n].iter().position(|&b| b == 0).unwrap_or(n);
+    event.data_len = len as u32;
+}
+
+fn capture_buffer(ptr: u64, len: u64, event: &mut TraceEvent) {
+    let want = (len as usize).min(TRACE_DATA_LEN);
+    event.data_len = peek_user(ptr, &mut event.data[..want]) as u32;
+}
+
+/// 現在プロセスが対象なら、そのセッションのトレーサーを返す
+fn current_target() -> Option<u64> {
+    let pid = crate::percpu::current_process_raw_id();
+    if pid == 0 {
+        return None;
+    }
+    let sessions = SESSIONS.lock();
+    sessions
+        .iter()
+        .any(|s| s.targets.contains(&pid))
+        .then_some(pid)
+}
+
+/// 対象のイベントをセッションに積む（積む前に対象から外れていたら捨てる）
+fn push_event(pid: u64, event: TraceEvent) {
+    let mut sessions = SESSIONS.lock();
+    if let Some(session) = sessions.iter_mut().find(|s| s.targets.contains(&pid)) {
+        session.push(event);
+    }
+}
+
+/// syscall 入口のフック（対象でなければ None）
+pub fn enter(nr: u64, args: [u64; 6]) -> Option<TraceEntry> {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return None;
+    }
+    let pid = current_target()?;
+    let mut event = TraceEvent::new(TRACE_ENTER, pid);
+    event.tid = crate::percpu::current_thread_raw_id();
+    event.nr = nr;
+    event.args = args;
+    event.ticks = crate::interrupt::timer::get_ticks();
+    if let Some(i) = path_arg(nr) {
+        capture_path(args[i], &mut event);
+    } else if nr == SyscallNumber::Write as u64 {
+        capture_buffer(args[1], args[2], &mut event);
+    }
+
+    let seq = {
+        let mut sessions = SESSIONS.lock();
+        let session = sessions.iter_mut().find(|s| s.targets.contains(&pid))?;
+        let seq = session.next_seq;
+        session.next_seq += 1;
+        event.seq = seq;
+        session.push(event);
+        seq
+    };
+    Some(TraceEntry {
+        seq,
+        pid,
+        nr,
+        args,
+        start_tsc: crate::cpu::rdtsc(),
+    })
+}
+
+/// syscall 出口のフック
+pub fn exit(entry: TraceEntry, ret: u64) {
+    let duration = crate::cpu::rdtsc().saturating_sub(entry.start_tsc);
+    let mut event = TraceEvent::new(TRACE_EXIT, entry.pid);
+    event.seq = entry.seq;
+    event.tid = crate::percpu::current_thread_raw_id();
+    event.nr = entry.nr;
+    event.args = entry.args;
+    event.ret = ret;
+    event.ticks = crate::interrupt::timer::get_ticks();
+    event.duration_ns = crate::interrupt::timer::tsc_delta_to_ns(duration).unwrap_or(0);
+    if entry.nr == SyscallNumber::Read as u64 && (ret as i64) > 0 {
+        capture_buffer(entry.args[1], ret, &mut event);
+    }
+    push_event(entry.pid, event);
+}
+
+/// fork のフック（子プロセスのスレッドが走り出す前に呼ぶ）
+pub fn on_fork(parent: ProcessId, child: ProcessId) {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    let (parent, child) = (parent.as_u64(), child.as_u64());
+    let mut sessions = SESSIONS.lock();
+    for session in sessions.iter_mut() {
+        if session.tracer == parent {
+            if let Some(flags) = session.next_child.take() {
+                session.flags = flags;
+                session.targets.push(child);
+                continue;
+            }
+        }
+        if session.flags & STRACE_FOLLOW_FORK != 0 && session.targets.contains(&parent) {
+            session.targets.push(child);
+        }
+    }
+    update_active(&sessions);
+}
+
+/// プロセス終了時の後始末（`mark_process_exited` から呼ばれる）
+///
+/// 終了したのが対象なら TRACE_PROC_EXIT を積んで外し、トレーサーならセッションを捨てる。
+pub fn on_process_exit(pid: ProcessId, exit_code: u64) {
+    let pid = pid.as_u64();
+    let mut sessions = SESSIONS.lock();
+    if sessions.is_empty() {
+        return;
+    }
+    sessions.retain(|s| s.tracer != pid);
+    for session in sessions.iter_mut() {
+        if let Some(pos) = session.targets.iter().position(|&t| t == pid) {
+            session.targets.swap_remove(pos);
+            let mut event = TraceEvent::new(TRACE_PROC_EXIT, pid);
+            event.ret = exit_code;
+            event.ticks = crate::interrupt::timer::get_ticks();
+            session.push(event);
+        }
+    }
+    update_active(&sessions);
+}
+
+/// fork に失敗して消した子をどのセッションからも外す
+pub fn forget(pid: ProcessId) {
+    let pid = pid.as_u64();
+    let mut sessions = SESSIONS.lock();
+    for session in sessions.iter_mut() {
+        session.targets.retain(|&t| t != pid);
+    }
+    update_active(&sessions);
+}
+
+/// 呼び出し元のセッションを `f` で操作する（無ければ作る）
+fn with_session<R>(tracer: u64, f: impl FnOnce(&mut Session) -> R) -> R {
+    let mut sessions = SESSIONS.lock();
+    let idx = match sessions.iter().position(|s| s.tracer == tracer) {
+        Some(idx) => idx,
+        None => {
+            sessions.push(Session {
+                tracer,
+                targets: Vec::new(),
+                flags: 0,
+                next_child: None,
+                events: VecDeque::new(),
+                lost: 0,
+                next_seq: 1,
+            });
+            sessions.len() - 1
+        }
+    };
+    let ret = f(&mut sessions[idx]);
+    update_active(&sessions);
+    ret
+}
+
+fn attach(caller: ProcessId, target: ProcessId, flags: u64) -> u64 {
+    if let Err(e) = super::ptrace::may_trace(caller, target, "strace attach") {
+        return e;
+    }
+    let target_raw = target.as_u64();
+    {
+        let sessions = SESSIONS.lock();
+        if sessions.iter().any(|s| s.targets.contains(&target_raw)) {
+            return EPERM;
+        }
+    }
+    with_session(caller.as_u64(), |s| {
+        s.flags = flags;
+        s.targets.push(target_raw);
+    });
+    SUCCESS
+}
+
+fn detach(caller: ProcessId, target: u64) -> u64 {
+    let mut sessions = SESSIONS.lock();
+    let Some(session) = sessions.iter_mut().find(|s| s.tracer == caller.as_u64()) else {
+        return ESRCH;
+    };
+    let ret = if target == 0 {
+        session.targets.clear();
+        session.next_child = None;
+        SUCCESS
+    } else if let Some(pos) = session.targets.iter().position(|&t| t == target) {
+        session.targets.swap_remove(pos);
+        SUCCESS
+    } else {
+        ESRCH
+    };
+    update_active(&sessions);
+    ret
+}
+
+fn read(caller: ProcessId, buf_ptr: u64, count: u64) -> u64 {
+    let max = core::cmp::min(count, TRACE_RING_CAPACITY as u64) as usize;
+    if max == 0 {
+        return 0;
+    }
+    if !super::validate_user_ptr(buf_ptr, (max * EVENT_SIZE) as u64) {
+        return EFAULT;
+    }
+    // ロック中に確保しないよう先に取っておく
+    let mut events = Vec::with_capacity(max);
+    {
+        let mut sessions = SESSIONS.lock();
+        let Some(session) = sessions.iter_mut().find(|s| s.tracer == caller.as_u64()) else {
+            return ESRCH;
+        };
+        if session.lost > 0 {
+            let mut lost = TraceEvent::new(TRACE_LOST, 0);
+            lost.ret = core::mem::take(&mut session.lost);
+            events.push(lost);
+        }
+        while events.len() < max {
+            match session.events.pop_front() {
+                Some(event) => events.push(event),
+                None => break,
+            }
+        }
+    }
+    if events.is_empty() {
+        return 0;
+    }
+    // SAFETY: TraceEvent は repr(C) で詰め物が無い
+    let bytes = unsafe {
+        core::slice::from_raw_parts(events.as_ptr() as *const u8, events.len() * EVENT_SIZE)
+    };
+    match super::copy_to_user(buf_ptr, bytes) {
+        Ok(()) => events.len() as u64,
+        Err(e) => e,
+    }
+}
+
+fn count(caller: ProcessId) -> u64 {
+    let sessions = SESSIONS.lock();
+    sessions
+        .iter()
+        .find(|s| s.tracer == caller.as_u64())
+        .map(|s| s.targets.len() as u64 + s.next_child.is_some() as u64)
+        .unwrap_or(0)
+}
+
+/// SyscallTrace システムコール
+pub fn syscall_trace(op: u64, arg1: u64, arg2: u64) -> u64 {
+    let Some(caller) = crate::task::current_thread_id()
+        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
+    else {
+        return ESRCH;
+    };
+    match op {
+        STRACE_ATTACH => {
+            if arg2 & !STRACE_FOLLOW_FORK != 0 {
+                return EINVAL;
+            }
+            attach(caller, ProcessId::from_u64(arg1), arg2)
+        }
+        STRACE_DETACH => detach(caller, arg1),
+        STRACE_READ => read(caller, arg1, arg2),
+        STRACE_ATTACH_NEXT_CHILD => {
+            if arg1 & !STRACE_FOLLOW_FORK != 0 {
+                return EINVAL;
+            }
+            with_session(caller.as_u64(), |s| s.next_child = Some(arg1));
+            SUCCESS
+        }
+        STRACE_COUNT => count(caller),
+        _ => EINVAL,
+    }
+}
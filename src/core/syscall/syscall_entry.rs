@@ -41,9 +41,10 @@ pub fn init_syscall() {
     let lstar_val = syscall_entry as *const () as u64;
 
     // IA32_FMASK: SYSCALL 時に RFLAGS からクリアするビット
-    // IF (bit 9) をクリアして割り込み禁止にする
+    // IF (bit 9) をクリアして割り込み禁止にする。TF (bit 8) もクリアし、
+    // シングルステップ中の SYSCALL でカーネル側に #DB を持ち込まない
     const IA32_FMASK: u32 = 0xC000_0084;
-    let fmask_val: u64 = 0x200; // IF ビット
+    let fmask_val: u64 = 0x300; // IF | TF
 
     unsafe {
         // EFER.SCE を設定
@@ -199,13 +200,15 @@ pub unsafe extern "C" fn syscall_entry() {
         "mov ds, cx",
         "mov es, cx",
 
-        // fork/clone のときだけ現在スレッドへユーザーコンテキストを記録
+        // fork/clone/sigaltstack のときだけ現在スレッドへユーザーコンテキストを記録
         // align slot ありレイアウト:
         // [rsp+8]=user RSP, [rsp+64]=user RFLAGS, [rsp+72]=user RIP, [rsp+128]=syscall num
         "mov rax, [rsp + 128]",
         "cmp rax, 56",
         "je 3f",
         "cmp rax, 57",
+        "je 3f",
+        "cmp rax, 131",
         "jne 4f",
         "3:",
         "mov rdi, rax",
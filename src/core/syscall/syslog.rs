@@ -0,0 +1,118 @@
+//! syslog システムコール（カーネルログの読み出しと制御）
+//!
+//! アクション番号は Linux の syslog(2) と同じ。読み出し形式は `util::kmsg` を参照。
+//! 読み出しは誰でもでき、消去・コンソール制御・ログレベル変更は CAP_SYS_CONFIG が必要。
+
+use super::types::{EFAULT, EINVAL, EPERM, SUCCESS};
+use crate::util::{kmsg, log};
+
+pub const SYSLOG_ACTION_CLOSE: u64 = 0;
+pub const SYSLOG_ACTION_OPEN: u64 = 1;
+/// 未読分を読んで既読にする
+pub const SYSLOG_ACTION_READ: u64 = 2;
+/// 残っている分を全て読む（入りきらなければ新しい方を優先）
+pub const SYSLOG_ACTION_READ_ALL: u64 = 3;
+/// READ_ALL の後に消去する
+pub const SYSLOG_ACTION_READ_CLEAR: u64 = 4;
+pub const SYSLOG_ACTION_CLEAR: u64 = 5;
+pub const SYSLOG_ACTION_CONSOLE_OFF: u64 = 6;
+pub const SYSLOG_ACTION_CONSOLE_ON: u64 = 7;
+/// ログレベルを `len`（0=Trace .. 4=Error）にする
+pub const SYSLOG_ACTION_CONSOLE_LEVEL: u64 = 8;
+pub const SYSLOG_ACTION_SIZE_UNREAD: u64 = 9;
+pub const SYSLOG_ACTION_SIZE_BUFFER: u64 = 10;
+
+fn require_config(action: u64) -> Result<(), u64> {
+    if crate::task::current_has(crate::task::capability::CAP_SYS_CONFIG) {
+        return Ok(());
+    }
+    crate::audit::log(
+        crate::audit::AuditEventKind::Deny,
+        &alloc::format!("syslog: action {} requires CAP_SYS_CONFIG", action),
+    );
+    Err(EPERM)
+}
+
+/// `text` を `buf` に書き、書いたバイト数を返す
+fn copy_out(buf_ptr: u64, text: &[u8]) -> u64 {
+    if text.is_empty() {
+        return 0;
+    }
+    if !crate::syscall::validate_user_ptr(buf_ptr, text.len() as u64) {
+        return EFAULT;
+    }
+    match crate::syscall::copy_to_user(buf_ptr, text) {
+        Ok(()) => text.len() as u64,
+        Err(e) => e,
+    }
+}
+
+/// 全体を `len` に収める（古い方の行を捨てる）
+fn tail_lines(text: &str, len: usize) -> &str {
+    if text.len() <= len {
+        return text;
+    }
+    let cut = text.len() - len;
+    if text.as_bytes()[cut - 1] == b'\n' {
+        return &text[cut..];
+    }
+    match text[cut..].find('\n') {
+        Some(pos) if cut + pos + 1 < text.len() => &text[cut + pos + 1..],
+        _ => "",
+    }
+}
+
+/// syslog システムコール
+///
+/// 読み出し系は書いたバイト数、SIZE_* は大きさ、それ以外は 0 を返す。
+pub fn syslog(action: u64, buf_ptr: u64, len: u64) -> u64 {
+    match action {
+        SYSLOG_ACTION_CLOSE | SYSLOG_ACTION_OPEN => SUCCESS,
+        SYSLOG_ACTION_READ => {
+            let text = kmsg::read_unread(len as usize);
+            copy_out(buf_ptr, text.as_bytes())
+        }
+        SYSLOG_ACTION_READ_ALL | SYSLOG_ACTION_READ_CLEAR => {
+            if action == SYSLOG_ACTION_READ_CLEAR {
+                if let Err(e) = require_config(action) {
+                    return e;
+                }
+            }
+            let text = kmsg::read_all();
+            let ret = copy_out(buf_ptr, tail_lines(&text, len as usize).as_bytes());
+            if action == SYSLOG_ACTION_READ_CLEAR && (ret as i64) >= 0 {
+                kmsg::clear();
+            }
+            ret
+        }
+        SYSLOG_ACTION_CLEAR => match require_config(action) {
+            Ok(()) => {
+                kmsg::clear();
+                SUCCESS
+            }
+            Err(e) => e,
+        },
+        SYSLOG_ACTION_CONSOLE_OFF | SYSLOG_ACTION_CONSOLE_ON => match require_config(action) {
+            Ok(()) => {
+                log::set_console_enabled(action == SYSLOG_ACTION_CONSOLE_ON);
+                SUCCESS
+            }
+            Err(e) => e,
+        },
+        SYSLOG_ACTION_CONSOLE_LEVEL => {
+            let Some(level) = u8::try_from(len).ok().and_then(log::level_from_u8) else {
+                return EINVAL;
+            };
+            match require_config(action) {
+                Ok(()) => {
+                    log::set_level(level);
+                    SUCCESS
+                }
+                Err(e) => e,
+            }
+        }
+        SYSLOG_ACTION_SIZE_UNREAD => kmsg::unread_bytes() as u64,
+        SYSLOG_ACTION_SIZE_BUFFER => kmsg::capacity_bytes() as u64,
+        _ => EINVAL,
+    }
+}
@@ -12,7 +12,7 @@ struct SleepEntry {
 
 const MAX_SLEEPERS: usize = crate::task::ThreadQueue::MAX_THREADS;
 static SLEEP_QUEUE: SpinLock<[Option<SleepEntry>; MAX_SLEEPERS]> =
-    SpinLock::new([None; MAX_SLEEPERS]);
+    SpinLock::named("SLEEP_QUEUE", [None; MAX_SLEEPERS]);
 
 fn register_sleep_entry(tid: ThreadId, wake_tick: u64) -> bool {
     let mut queue = SLEEP_QUEUE.lock();
@@ -122,7 +122,7 @@ impl TtyState {
     }
 }
 
-static TTY_STATE: SpinLock<TtyState> = SpinLock::new(TtyState::new());
+static TTY_STATE: SpinLock<TtyState> = SpinLock::named("TTY_STATE", TtyState::new());
 static INPUT_QUEUE: crate::util::fifo::Fifo<u8, 1024> = crate::util::fifo::Fifo::new();
 
 fn push_bytes(bytes: &[u8]) {
@@ -40,8 +40,10 @@ pub enum SyscallNumber {
     Fork = 57,
     /// Execve
     Execve = 59,
-    /// Wait
+    /// Wait (wait4)
     Wait = 61,
+    /// waitid
+    Waitid = 247,
     /// 現在のプロセスIDを取得
     GetPid = 39,
     /// 現在のスレッドIDを取得
@@ -72,6 +74,16 @@ pub enum SyscallNumber {
     GetPpid = 110,
     /// sigaltstack
     Sigaltstack = 131,
+    /// rt_sigtimedwait
+    RtSigtimedwait = 128,
+    /// rt_sigqueueinfo
+    RtSigqueueinfo = 129,
+    /// rt_sigsuspend
+    RtSigsuspend = 130,
+    /// signalfd
+    Signalfd = 282,
+    /// signalfd4
+    Signalfd4 = 289,
     /// statfs
     Statfs = 137,
     /// setpgid
@@ -122,6 +134,8 @@ pub enum SyscallNumber {
     Uname = 63,
     /// getrlimit
     Getrlimit = 97,
+    /// setrlimit
+    Setrlimit = 160,
     /// set_tid_address
     SetTidAddress = 218,
     /// openat
@@ -130,6 +144,10 @@ pub enum SyscallNumber {
     Getdents64 = 217,
     /// prlimit64
     Prlimit64 = 302,
+    /// ptrace
+    Ptrace = 101,
+    /// syslog (カーネルログの読み出しと制御)
+    Syslog = 103,
     /// pipe2
     Pipe2 = 293,
     /// newfstatat (fstatat)
@@ -148,6 +166,8 @@ pub enum SyscallNumber {
     Readlinkat = 267,
     /// getrandom
     Getrandom = 318,
+    /// reboot（再起動のみ。CAP_SYS_CONFIG 専用）
+    Reboot = 169,
 
     // mochiOS独自syscall (Linux未使用番号帯: 512+)
     /// スケジューラへ譲る
@@ -206,9 +226,9 @@ pub enum SyscallNumber {
     PortInWords = 538,
     /// I/Oポートへ 16-bit ワード列を一括書き込み
     PortOutWords = 539,
-    /// キーボード入力キューへ raw スキャンコードを注入（Service/Core専用）
+    /// キーボード入力キューへ raw スキャンコードを注入（CAP_INPUT_INJECT 専用）
     KeyboardInject = 540,
-    /// マウス入力キューへ 3バイトパケットを注入（Service/Core専用）
+    /// マウス入力キューへ 3バイトパケットを注入（CAP_INPUT_INJECT 専用）
     MouseInject = 541,
     /// メモリ上の ELF バッファと実行パス名から新プロセスを起動
     ExecFromBufferNamed = 542,
@@ -218,20 +238,44 @@ pub enum SyscallNumber {
     ExecFromBufferNamedArgsWithRequester = 544,
     /// Execute by streaming ELF image into kernel (path_ptr, args_ptr)
     ExecFromFsStream = 545,
-    /// 物理ページ配列をターゲットプロセスのアドレス空間にマップ（Service権限専用）
+    /// 物理ページ配列をターゲットプロセスのアドレス空間にマップ（CAP_SHARED_MEMORY 専用）
     MapPhysicalPages = 546,
-    /// 仮想アドレスから物理アドレスを取得（Service権限専用）
+    /// 仮想アドレスから物理アドレスを取得（CAP_DMA 専用）
     GetPhysicalAddr = 547,
-    /// 共有用物理ページを割り当て、自プロセスにマップして物理アドレスを返す（Service権限専用）
+    /// 共有用物理ページを割り当て、自プロセスにマップして物理アドレスを返す（CAP_SHARED_MEMORY 専用）
     AllocSharedPages = 548,
-    /// 物理ページをアンマップして解放（Service権限専用）
+    /// 物理ページをアンマップして解放（CAP_SHARED_MEMORY 専用）
     UnmapPages = 549,
-    /// IPC経由で物理ページをターゲットプロセスへ送信（Service権限専用）
+    /// IPC経由で物理ページをターゲットプロセスへ送信（CAP_SHARED_MEMORY 専用）
     IpcSendPages = 550,
     /// PS/2 マウスの3バイトパケットを読み取る（ブロッキング版）
     MouseReadWait = 551,
     /// プロセス一覧を取得（ユーザーバッファへ書き込む）
     ListProcesses = 552,
+    /// プロセスのケーパビリティを取得 (pid, out_ptr)
+    GetCapabilities = 553,
+    /// プロセスのケーパビリティを設定 (pid, in_ptr)（呼び出し元の部分集合のみ）
+    SetCapabilities = 554,
+    /// コアダンプの出力先ディレクトリを取得 (buf, len)
+    GetCoreDumpDir = 555,
+    /// コアダンプの出力先ディレクトリを設定 (path_ptr)（CAP_SYS_CONFIG 専用）
+    SetCoreDumpDir = 556,
+    /// 監査ログを読み出す (buf, count, since_seq, kind_mask)（CAP_AUDIT_READ 専用）
+    AuditRead = 557,
+    /// syscall トレース (op, arg1, arg2)（操作は syscall::strace を参照）
+    SyscallTrace = 558,
+    /// トレースポイントとプロファイラの操作 (op, arg1, arg2)（CAP_PERFMON 専用）
+    Perf = 559,
+    /// ウォッチドッグの操作 (op, arg1, arg2, arg3)（操作は watchdog モジュールを参照）
+    Watchdog = 560,
+    /// 環境変数付きで実行可能ファイルを実行 (path_ptr, args_ptr, env_ptr)
+    ExecWithEnv = 561,
+    /// fs.service のエンドポイント登録と状態の引き継ぎ (op, ptr, len)
+    FsEndpoint = 562,
+    /// ケーパビリティを指定して実行可能ファイルを実行 (path_ptr, args_ptr, env_ptr, caps_ptr)（CAP_SET_CAPS 専用）
+    ExecWithCaps = 563,
+    /// スレッドが属するプロセスのケーパビリティを取得 (tid, out_ptr) → プロセスID
+    GetThreadCapabilities = 564,
 }
 
 /// 成功
@@ -246,10 +290,14 @@ pub const ENOENT: u64 = (-2i64) as u64;
 pub const ENOTDIR: u64 = (-20i64) as u64;
 /// プロセスが見つからない
 pub const ESRCH: u64 = (-3i64) as u64;
+/// シグナルにより中断された
+pub const EINTR: u64 = (-4i64) as u64;
 /// I/Oエラー
 pub const EIO: u64 = (-5i64) as u64;
 /// 不正なファイルディスクリプタ
 pub const EBADF: u64 = (-9i64) as u64;
+/// アクセス権がない
+pub const EACCES: u64 = (-13i64) as u64;
 /// 不正なアドレス
 pub const EFAULT: u64 = (-14i64) as u64;
 /// デバイスが見つからない
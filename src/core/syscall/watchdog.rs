@@ -0,0 +1,62 @@
+//! watchdog システムコール（カーネルウォッチドッグへのハートビートと登録）
+//!
+//! 自分自身の登録・解除・ハートビートは誰でもでき、ほかのプロセスの登録・解除には
+//! CAP_SPAWN_SERVICE が必要（サービスを起動する側が、起動したサービスを登録する）。
+
+use super::types::{EINVAL, EPERM, ESRCH, SUCCESS};
+use crate::task::ProcessId;
+use crate::watchdog::{self, Action};
+
+/// ハートビートを送る
+pub const WATCHDOG_HEARTBEAT: u64 = 0;
+/// 見張ってもらう (pid, timeout_ms, action)（pid 0 は自分）
+pub const WATCHDOG_REGISTER: u64 = 1;
+/// 見張りをやめてもらう (pid)（pid 0 は自分）
+pub const WATCHDOG_UNREGISTER: u64 = 2;
+/// 状態を返す → bit 0 に縮退モード中、上位 32 bit に core.service の再起動回数
+pub const WATCHDOG_STATUS: u64 = 3;
+
+/// watchdog システムコール
+pub fn watchdog(op: u64, arg1: u64, arg2: u64, arg3: u64) -> u64 {
+    let Some(current) = current_pid() else {
+        return ESRCH;
+    };
+    let result = match op {
+        WATCHDOG_HEARTBEAT => watchdog::heartbeat(current),
+        WATCHDOG_REGISTER => {
+            let Some(action) = Action::from_u64(arg3) else {
+                return EINVAL;
+            };
+            target(current, arg1).and_then(|pid| watchdog::register(pid, arg2, action))
+        }
+        WATCHDOG_UNREGISTER => target(current, arg1).and_then(watchdog::unregister),
+        WATCHDOG_STATUS => {
+            return watchdog::safe_mode() as u64 | (watchdog::core_restarts() as u64) << 32;
+        }
+        _ => return EINVAL,
+    };
+    match result {
+        Ok(()) => SUCCESS,
+        Err(errno) => errno,
+    }
+}
+
+fn current_pid() -> Option<ProcessId> {
+    crate::task::current_thread_id()
+        .and_then(|tid| crate::task::with_thread(tid, |t| t.process_id()))
+}
+
+/// 操作の対象（自分以外なら CAP_SPAWN_SERVICE を確かめる）
+fn target(current: ProcessId, pid_raw: u64) -> Result<ProcessId, u64> {
+    if pid_raw == 0 || pid_raw == current.as_u64() {
+        return Ok(current);
+    }
+    if !crate::task::current_has_audited(crate::task::capability::CAP_SPAWN_SERVICE, "watchdog") {
+        return Err(EPERM);
+    }
+    let pid = ProcessId::from_u64(pid_raw);
+    if crate::task::with_process(pid, |_| ()).is_none() {
+        return Err(ESRCH);
+    }
+    Ok(pid)
+}
@@ -0,0 +1,348 @@
+//! プロセスケーパビリティ
+//!
+//! PrivilegeLevel の三段階だけでは「I/O ポートを叩けるプロセスは DMA も
+//! 入力注入も全部できる」状態になるため、特権操作ごとに細かい権限を持たせる。
+//! 各プロセスは `CapabilitySet` を 1 つ持ち、特権 syscall はそれを見て判定する。
+//!
+//! 付与ルール:
+//! - 生成時は PrivilegeLevel ごとの既定値と、生成元プロセスの集合の積になる
+//! - fork した子は親の集合をそのまま継承する
+//! - `set_capabilities` syscall では自分が持っている範囲内でしか付与できない
+
+use super::ids::PrivilegeLevel;
+
+/// DMA 用に仮想アドレスを物理アドレスへ変換できる
+pub const CAP_DMA: u64 = 1 << 0;
+/// サービスの起動・他プロセスの代理 exec ができる
+pub const CAP_SPAWN_SERVICE: u64 = 1 << 1;
+/// キーボード/マウス入力の注入・タップができる
+pub const CAP_INPUT_INJECT: u64 = 1 << 2;
+/// 任意のプロセスへシグナルを送れる
+pub const CAP_KILL_ANY: u64 = 1 << 3;
+/// 物理ページのマップ・共有ページの確保と受け渡しができる
+pub const CAP_SHARED_MEMORY: u64 = 1 << 4;
+/// 他プロセスのケーパビリティを（自分の範囲内で）設定できる
+pub const CAP_SET_CAPS: u64 = 1 << 5;
+/// カーネルの設定（コアダンプの出力先など）を変更できる
+pub const CAP_SYS_CONFIG: u64 = 1 << 6;
+/// 子孫でないプロセス・自分より強い権限のプロセスを ptrace でアタッチできる
+pub const CAP_SYS_PTRACE: u64 = 1 << 7;
+/// 監査ログを読み出せる（audit_read）
+pub const CAP_AUDIT_READ: u64 = 1 << 8;
+/// トレースポイント・プロファイラを操作し、記録を読み出せる（perf）
+pub const CAP_PERFMON: u64 = 1 << 9;
+/// fs.service 経由でディスク上のファイルを作成・変更できる（無ければ書き込み用の open は EACCES）
+pub const CAP_FS_WRITE: u64 = 1 << 10;
+
+/// 定義済みフラグ全体
+pub const CAP_ALL_FLAGS: u64 = CAP_DMA
+    | CAP_SPAWN_SERVICE
+    | CAP_INPUT_INJECT
+    | CAP_KILL_ANY
+    | CAP_SHARED_MEMORY
+    | CAP_SET_CAPS
+    | CAP_SYS_CONFIG
+    | CAP_SYS_PTRACE
+    | CAP_AUDIT_READ
+    | CAP_PERFMON
+    | CAP_FS_WRITE;
+
+/// 1 プロセスが保持できるポート範囲/MMIO 範囲の最大数
+pub const MAX_CAP_RANGES: usize = 4;
+
+/// ユーザー空間とやり取りする `CapabilitySet` のバイト長
+///
+/// レイアウト (すべて u64, little endian):
+/// - `[0]` flags
+/// - `[1]` port 範囲数, `[2..10]` (start, end) x 4
+/// - `[10]` MMIO 範囲数, `[11..19]` (start, end) x 4
+pub const CAP_WIRE_SIZE: usize = 8 * (1 + 1 + MAX_CAP_RANGES * 2 + 1 + MAX_CAP_RANGES * 2);
+
+const PORT_MAX: u64 = 0xFFFF;
+const MMIO_MAX: u64 = u64::MAX;
+
+/// 閉区間 `[start, end]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl CapRange {
+    pub const EMPTY: Self = Self { start: 1, end: 0 };
+
+    pub const fn new(start: u64, end: u64) -> Self {
+        Self { start, end }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.start > self.end
+    }
+
+    fn contains(&self, start: u64, end: u64) -> bool {
+        !self.is_empty() && self.start <= start && end <= self.end
+    }
+
+    fn intersect(&self, other: &CapRange) -> CapRange {
+        CapRange {
+            start: self.start.max(other.start),
+            end: self.end.min(other.end),
+        }
+    }
+}
+
+/// 固定長の範囲リスト
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RangeList {
+    ranges: [CapRange; MAX_CAP_RANGES],
+    len: usize,
+}
+
+impl RangeList {
+    const fn empty() -> Self {
+        Self {
+            ranges: [CapRange::EMPTY; MAX_CAP_RANGES],
+            len: 0,
+        }
+    }
+
+    const fn full(max: u64) -> Self {
+        let mut list = Self::empty();
+        list.ranges[0] = CapRange::new(0, max);
+        list.len = 1;
+        list
+    }
+
+    fn as_slice(&self) -> &[CapRange] {
+        &self.ranges[..self.len]
+    }
+
+    fn push(&mut self, range: CapRange) -> bool {
+        if range.is_empty() {
+            return true;
+        }
+        if self.len >= MAX_CAP_RANGES {
+            return false;
+        }
+        self.ranges[self.len] = range;
+        self.len += 1;
+        true
+    }
+
+    fn covers(&self, start: u64, end: u64) -> bool {
+        self.as_slice().iter().any(|r| r.contains(start, end))
+    }
+
+    fn is_subset_of(&self, other: &RangeList) -> bool {
+        self.as_slice().iter().all(|r| other.covers(r.start, r.end))
+    }
+
+    fn intersect(&self, other: &RangeList) -> RangeList {
+        let mut out = RangeList::empty();
+        for a in self.as_slice() {
+            for b in other.as_slice() {
+                // 溢れた分は捨てる（権限が狭まる方向なので安全側）
+                let _ = out.push(a.intersect(b));
+            }
+        }
+        out
+    }
+}
+
+/// プロセスが保持するケーパビリティの集合
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapabilitySet {
+    flags: u64,
+    ports: RangeList,
+    mmio: RangeList,
+}
+
+impl CapabilitySet {
+    /// 何も持たない集合
+    pub const fn empty() -> Self {
+        Self {
+            flags: 0,
+            ports: RangeList::empty(),
+            mmio: RangeList::empty(),
+        }
+    }
+
+    /// すべてを持つ集合（Core 用）
+    pub const fn all() -> Self {
+        Self {
+            flags: CAP_ALL_FLAGS,
+            ports: RangeList::full(PORT_MAX),
+            mmio: RangeList::full(MMIO_MAX),
+        }
+    }
+
+    /// 権限レベルごとの既定値
+    ///
+    /// Service は従来 Service 権限で許可されていた操作をそのまま持つ。
+    /// 任意プロセスへのシグナル送信・サービス起動・ケーパビリティ設定は
+    /// 従来通り Core（とサービスマネージャー）に限る。
+    pub const fn default_for(privilege: PrivilegeLevel) -> Self {
+        match privilege {
+            PrivilegeLevel::Core => Self::all(),
+            PrivilegeLevel::Service => Self {
+                flags: CAP_DMA | CAP_INPUT_INJECT | CAP_SHARED_MEMORY,
+                ports: RangeList::full(PORT_MAX),
+                mmio: RangeList::full(MMIO_MAX),
+            },
+            PrivilegeLevel::User => Self::empty(),
+        }
+    }
+
+    /// フラグを追加した集合
+    pub const fn with(mut self, flag: u64) -> Self {
+        self.flags |= flag;
+        self
+    }
+
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    pub fn has(&self, flag: u64) -> bool {
+        flag != 0 && (self.flags & flag) == flag
+    }
+
+    /// `port` から `width` バイトのポートアクセスが許可されているか
+    pub fn allows_port(&self, port: u64, width: u64) -> bool {
+        if width == 0 {
+            return false;
+        }
+        match port.checked_add(width - 1) {
+            Some(end) if end <= PORT_MAX => self.ports.covers(port, end),
+            _ => false,
+        }
+    }
+
+    /// 物理アドレス `[start, start + len)` の MMIO マップが許可されているか
+    pub fn allows_mmio(&self, start: u64, len: u64) -> bool {
+        if len == 0 {
+            return false;
+        }
+        match start.checked_add(len - 1) {
+            Some(end) => self.mmio.covers(start, end),
+            None => false,
+        }
+    }
+
+    /// `self` が `other` の部分集合か
+    pub fn is_subset_of(&self, other: &CapabilitySet) -> bool {
+        (self.flags & !other.flags) == 0
+            && self.ports.is_subset_of(&other.ports)
+            && self.mmio.is_subset_of(&other.mmio)
+    }
+
+    /// 両方に含まれる部分だけを残した集合
+    pub fn intersect(&self, other: &CapabilitySet) -> CapabilitySet {
+        CapabilitySet {
+            flags: self.flags & other.flags,
+            ports: self.ports.intersect(&other.ports),
+            mmio: self.mmio.intersect(&other.mmio),
+        }
+    }
+
+    /// ユーザー空間の表現からデコードする
+    pub fn from_wire(buf: &[u8; CAP_WIRE_SIZE]) -> Option<Self> {
+        let word = |i: usize| {
+            let mut b = [0u8; 8];
+            b.copy_from_slice(&buf[i * 8..i * 8 + 8]);
+            u64::from_le_bytes(b)
+        };
+        let flags = word(0);
+        if flags & !CAP_ALL_FLAGS != 0 {
+            return None;
+        }
+        let decode = |base: usize, max: u64| -> Option<RangeList> {
+            let count = word(base) as usize;
+            if count > MAX_CAP_RANGES {
+                return None;
+            }
+            let mut list = RangeList::empty();
+            for i in 0..count {
+                let start = word(base + 1 + i * 2);
+                let end = word(base + 2 + i * 2);
+                if start > end || end > max {
+                    return None;
+                }
+                list.push(CapRange::new(start, end));
+            }
+            Some(list)
+        };
+        Some(Self {
+            flags,
+            ports: decode(1, PORT_MAX)?,
+            mmio: decode(2 + MAX_CAP_RANGES * 2, MMIO_MAX)?,
+        })
+    }
+
+    /// ユーザー空間の表現へエンコードする
+    pub fn to_wire(&self) -> [u8; CAP_WIRE_SIZE] {
+        let mut buf = [0u8; CAP_WIRE_SIZE];
+        let mut put = |i: usize, v: u64| buf[i * 8..i * 8 + 8].copy_from_slice(&v.to_le_bytes());
+        put(0, self.flags);
+        let lists = [(1, &self.ports), (2 + MAX_CAP_RANGES * 2, &self.mmio)];
+        for (base, list) in lists {
+            put(base, list.len as u64);
+            for (i, r) in list.as_slice().iter().enumerate() {
+                put(base + 1 + i * 2, r.start);
+                put(base + 2 + i * 2, r.end);
+            }
+        }
+        buf
+    }
+}
+
+/// 現在スレッドが属するプロセスのケーパビリティ
+///
+/// カーネルコンテキスト（プロセスに属さない）からの呼び出しは全権限として扱う。
+pub fn current_capabilities() -> CapabilitySet {
+    let pid =
+        super::current_thread_id().and_then(|tid| super::with_thread(tid, |t| t.process_id()));
+    match pid {
+        Some(pid) => {
+            super::with_process(pid, |p| *p.capabilities()).unwrap_or(CapabilitySet::empty())
+        }
+        None => CapabilitySet::all(),
+    }
+}
+
+/// 現在プロセスが `flag` を持つか
+pub fn current_has(flag: u64) -> bool {
+    current_capabilities().has(flag)
+}
+
+/// フラグ 1 つの表示名（監査ログ用）
+pub fn flag_name(flag: u64) -> &'static str {
+    match flag {
+        CAP_DMA => "CAP_DMA",
+        CAP_SPAWN_SERVICE => "CAP_SPAWN_SERVICE",
+        CAP_INPUT_INJECT => "CAP_INPUT_INJECT",
+        CAP_KILL_ANY => "CAP_KILL_ANY",
+        CAP_SHARED_MEMORY => "CAP_SHARED_MEMORY",
+        CAP_SET_CAPS => "CAP_SET_CAPS",
+        CAP_SYS_CONFIG => "CAP_SYS_CONFIG",
+        CAP_SYS_PTRACE => "CAP_SYS_PTRACE",
+        CAP_AUDIT_READ => "CAP_AUDIT_READ",
+        CAP_PERFMON => "CAP_PERFMON",
+        CAP_FS_WRITE => "CAP_FS_WRITE",
+        _ => "CAP_?",
+    }
+}
+
+/// `current_has` と同じだが、持っていなければ拒否を監査ログに残す
+///
+/// `what` は拒否した操作の名前（syscall 名など）。
+pub fn current_has_audited(flag: u64, what: &str) -> bool {
+    if current_has(flag) {
+        return true;
+    }
+    crate::audit::log(
+        crate::audit::AuditEventKind::Deny,
+        &alloc::format!("{}: caller lacks {}", what, flag_name(flag)),
+    );
+    false
+}
@@ -198,10 +198,19 @@ pub unsafe fn switch_to_thread(current_id: Option<ThreadId>, next_id: ThreadId)
     };
 
     drop(queue);
+    crate::trace::emit(
+        crate::trace::Tracepoint::SchedSwitch,
+        [
+            current_id.map_or(0, |id| id.as_u64()),
+            next_id.as_u64(),
+            next_process_id.as_u64(),
+        ],
+    );
 
     // 実際に切り替える直前に current thread を更新する。
     // これにより「currentだけ先に更新される競合窓」を避ける。
     crate::task::set_current_thread(Some(next_id));
+    crate::task::set_current_process(next_process_id);
 
     // TSSのRSP0とSYSCALL用カーネルスタックを更新
     crate::mem::tss::set_rsp0(next_kstack_top);
@@ -340,9 +349,18 @@ pub unsafe fn switch_to_thread_from_isr(
     }
 
     drop(queue);
+    crate::trace::emit(
+        crate::trace::Tracepoint::SchedSwitch,
+        [
+            current_id.map_or(0, |id| id.as_u64()),
+            next_id.as_u64(),
+            next_process_id.as_u64(),
+        ],
+    );
 
     // ISR 経路でも、実際の遷移直前に current thread を更新する。
     crate::task::set_current_thread(Some(next_id));
+    crate::task::set_current_process(next_process_id);
 
     // TSSのRSP0を更新
     crate::mem::tss::set_rsp0(next_kstack_top);
@@ -4,7 +4,8 @@ use crate::init;
 use crate::mem::{paging, user};
 use crate::result::{Kernel, Memory, Process, Result};
 use crate::task::{
-    add_process, add_thread, remove_process, PrivilegeLevel, Process as TaskProcess, Thread,
+    add_process, add_thread, remove_process, with_process_mut, PrivilegeLevel,
+    Process as TaskProcess, Thread,
 };
 use core::sync::atomic::{AtomicU64, Ordering};
 
@@ -24,6 +25,9 @@ const DT_RELAENT: i64 = 9;
 
 const R_X86_64_RELATIVE: u32 = 8;
 
+const SHT_SYMTAB: u32 = 2;
+const STT_FUNC: u8 = 2;
+
 const PIE_LOAD_BIAS: u64 = 0x2000_0000;
 const PIE_ASLR_WINDOW_PAGES: u64 = 0x4000; // 64MiB
 static PIE_ASLR_COUNTER: AtomicU64 = AtomicU64::new(0);
@@ -47,6 +51,32 @@ struct Elf64Header {
     e_shstrndx: u16,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Elf64Shdr {
+    sh_name: u32,
+    sh_type: u32,
+    sh_flags: u64,
+    sh_addr: u64,
+    sh_offset: u64,
+    sh_size: u64,
+    sh_link: u32,
+    sh_info: u32,
+    sh_addralign: u64,
+    sh_entsize: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Elf64Sym {
+    st_name: u32,
+    st_info: u8,
+    st_other: u8,
+    st_shndx: u16,
+    st_value: u64,
+    st_size: u64,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy)]
 struct Elf64Phdr {
@@ -228,6 +258,60 @@ pub fn load_elf_into(table_phys: u64, data: &[u8]) -> Result<LoadedElf> {
     })
 }
 
+/// ELF の `.symtab` から関数シンボルを読み、スタックトレース用の表を作る
+///
+/// strip されていてシンボルが無ければ None。アドレスには `load_bias` を足す。
+pub fn load_symbols(
+    data: &[u8],
+    load_bias: u64,
+) -> Option<alloc::sync::Arc<crate::backtrace::UserSymbols>> {
+    let header = parse_header(data).ok()?;
+    if header.e_ident[0..4] != ELF_MAGIC
+        || header.e_shentsize as usize != core::mem::size_of::<Elf64Shdr>()
+    {
+        return None;
+    }
+    let shdr = |index: usize| -> Option<Elf64Shdr> {
+        let off = index
+            .checked_mul(core::mem::size_of::<Elf64Shdr>())?
+            .checked_add(header.e_shoff as usize)?;
+        read_struct(data, off)
+    };
+    let symtab = (0..header.e_shnum as usize)
+        .filter_map(shdr)
+        .find(|sh| sh.sh_type == SHT_SYMTAB)?;
+    let strtab = shdr(symtab.sh_link as usize)?;
+    let strings = data
+        .get(strtab.sh_offset as usize..(strtab.sh_offset.checked_add(strtab.sh_size)? as usize))?;
+
+    let mut symbols = crate::backtrace::UserSymbols::new();
+    let count = symtab.sh_size as usize / core::mem::size_of::<Elf64Sym>();
+    for i in 0..count {
+        let off = (symtab.sh_offset as usize).saturating_add(i * core::mem::size_of::<Elf64Sym>());
+        let Some(sym) = read_struct::<Elf64Sym>(data, off) else {
+            break;
+        };
+        if sym.st_info & 0xf != STT_FUNC || sym.st_value == 0 || sym.st_shndx == 0 {
+            continue;
+        }
+        let Some(name) = strings.get(sym.st_name as usize..) else {
+            continue;
+        };
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+        if name.is_empty() {
+            continue;
+        }
+        if !symbols.push(sym.st_value.wrapping_add(load_bias), sym.st_size, name) {
+            break;
+        }
+    }
+    if symbols.is_empty() {
+        return None;
+    }
+    symbols.finish();
+    Some(alloc::sync::Arc::new(symbols))
+}
+
 pub fn spawn_service(path: &str, name: &'static str) -> Result<()> {
     let data = init::fs::read(path).ok_or(Kernel::InvalidParam)?;
     let new_pt_phys = paging::create_user_page_table()?;
@@ -244,6 +328,9 @@ pub fn spawn_service(path: &str, name: &'static str) -> Result<()> {
 
     let loaded = load_elf_into(new_pt_phys, &data)?;
 
+    let symbols = load_symbols(&data, loaded.load_bias);
+    with_process_mut(pid, |p| p.set_symbols(symbols));
+
     let stack_size = (loaded.stack_top - loaded.stack_bottom) as usize;
     let kernel_stack_size = stack_size
         .checked_add(4095)
@@ -292,6 +379,7 @@ pub fn spawn_service(path: &str, name: &'static str) -> Result<()> {
     const AT_PHNUM: u64 = 5;
     const AT_PAGESZ: u64 = 6;
     const AT_ENTRY: u64 = 9;
+    const AT_SYSINFO_EHDR: u64 = 33;
 
     // Fetch ELF header again to compute phdr addr and counts
     let header = parse_header(&data)?;
@@ -301,6 +389,12 @@ pub fn spawn_service(path: &str, name: &'static str) -> Result<()> {
     let at_phnum = header.e_phnum as u64;
 
     // push auxv (key,val) ... AT_NULL
+    let auxv_end = sp;
+    let auxv_count: u64 = if crate::mem::vdso::sysinfo_ehdr().is_some() {
+        7
+    } else {
+        6
+    };
     let mut push_u64 = |val: u64| -> Result<()> {
         let new_sp = sp
             .checked_sub(8)
@@ -316,6 +410,12 @@ pub fn spawn_service(path: &str, name: &'static str) -> Result<()> {
     push_u64(0)?;
     push_u64(AT_NULL)?;
 
+    // AT_SYSINFO_EHDR（vDSO）
+    if let Some(ehdr) = crate::mem::vdso::sysinfo_ehdr() {
+        push_u64(ehdr)?;
+        push_u64(AT_SYSINFO_EHDR)?;
+    }
+
     // AT_ENTRY
     push_u64(loaded.entry)?;
     push_u64(AT_ENTRY)?;
@@ -349,6 +449,10 @@ pub fn spawn_service(path: &str, name: &'static str) -> Result<()> {
     // final alignment: ensure %16 == 0
     sp &= !0xF;
 
+    // コアダンプの NT_AUXV 用に auxv の位置を記録する
+    let auxv_len = auxv_count * 16;
+    with_process_mut(pid, |p| p.set_auxv(auxv_end - auxv_len, auxv_len));
+
     thread.context_mut().rsp = sp;
     thread.context_mut().rbp = 0;
 
@@ -551,6 +655,14 @@ fn read_rela(data: &[u8], offset: usize) -> Result<Elf64Rela> {
     Ok(unsafe { *ptr })
 }
 
+/// `offset` から ELF の構造体を 1 つ読む（範囲外なら None）
+fn read_struct<T: Copy>(data: &[u8], offset: usize) -> Option<T> {
+    if offset.checked_add(core::mem::size_of::<T>())? > data.len() {
+        return None;
+    }
+    Some(unsafe { core::ptr::read_unaligned(data.as_ptr().add(offset) as *const T) })
+}
+
 fn read_phdr(data: &[u8], offset: usize) -> Result<Elf64Phdr> {
     if offset + core::mem::size_of::<Elf64Phdr>() > data.len() {
         return Err(Kernel::InvalidParam);
@@ -37,6 +37,8 @@ pub struct FileHandle {
     pub pipe_write: bool,
     /// open()/openat() のファイル状態フラグ（F_GETFL/F_SETFL 用）
     pub open_flags: u64,
+    /// Some(mask) であれば signalfd（mask に含まれるシグナルを読み出す）
+    pub signalfd_mask: Option<u64>,
 }
 
 impl FileHandle {
@@ -51,6 +53,7 @@ impl FileHandle {
             pipe_id: Some(pipe_id),
             pipe_write: false,
             open_flags: 0,
+            signalfd_mask: None,
         }
     }
 
@@ -65,6 +68,22 @@ impl FileHandle {
             pipe_id: Some(pipe_id),
             pipe_write: true,
             open_flags: 1,
+            signalfd_mask: None,
+        }
+    }
+
+    pub fn new_signalfd(mask: u64, open_flags: u64) -> Self {
+        Self {
+            data: Box::new([]),
+            pos: 0,
+            dir_path: None,
+            is_remote: false,
+            fd_remote: 0,
+            remote_refs: None,
+            pipe_id: None,
+            pipe_write: false,
+            open_flags,
+            signalfd_mask: Some(mask),
         }
     }
 
@@ -231,6 +250,7 @@ impl FdTable {
                 pipe_id: fh.pipe_id,
                 pipe_write: fh.pipe_write,
                 open_flags: fh.open_flags,
+                signalfd_mask: fh.signalfd_mask,
             });
             new_table.entries[i] = Box::into_raw(new_fh) as u64;
             new_table.flags[i] = self.flags[i];
@@ -267,3 +287,82 @@ impl Drop for FdTable {
         self.close_all();
     }
 }
+
+#[cfg(feature = "ktest")]
+mod ktests {
+    use super::*;
+    use crate::{kernel_test, ktest_assert, ktest_assert_eq};
+
+    kernel_test! {
+        fn allocates_lowest_free_fd() {
+            let mut table = FdTable::new_boxed();
+            let a = table.alloc(Box::new(FileHandle::new_pipe_read(0)), false);
+            let b = table.alloc(Box::new(FileHandle::new_pipe_write(0)), false);
+            ktest_assert_eq!(a, Some(FD_BASE));
+            ktest_assert_eq!(b, Some(FD_BASE + 1));
+            ktest_assert!(table.close_fd(FD_BASE));
+            ktest_assert!(!table.close_fd(FD_BASE));
+            let c = table.alloc(Box::new(FileHandle::new_pipe_read(1)), false);
+            ktest_assert_eq!(c, Some(FD_BASE));
+            ktest_assert_eq!(table.get(FD_BASE).and_then(|h| h.pipe_id), Some(1));
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn rejects_out_of_range_fds() {
+            let mut table = FdTable::new_boxed();
+            for fd in [0, 1, 2, PROCESS_MAX_FDS, usize::MAX] {
+                ktest_assert!(table.get(fd).is_none());
+                ktest_assert!(table.get_flags(fd).is_none());
+                ktest_assert!(!table.set_flags(fd, FD_CLOEXEC));
+                ktest_assert!(!table.close_fd(fd));
+            }
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn fails_when_table_is_full() {
+            let mut table = FdTable::new_boxed();
+            for expected in FD_BASE..PROCESS_MAX_FDS {
+                let fd = table.alloc(Box::new(FileHandle::new_pipe_read(0)), false);
+                ktest_assert_eq!(fd, Some(expected));
+            }
+            let overflow = table.alloc(Box::new(FileHandle::new_pipe_read(0)), false);
+            ktest_assert_eq!(overflow, None);
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn closes_only_cloexec_fds() {
+            let mut table = FdTable::new_boxed();
+            let keep = table.alloc(Box::new(FileHandle::new_pipe_read(0)), false);
+            let drop_on_exec = table.alloc(Box::new(FileHandle::new_pipe_read(0)), true);
+            let (Some(keep), Some(drop_on_exec)) = (keep, drop_on_exec) else {
+                crate::ktest_fail!("alloc failed");
+            };
+            ktest_assert_eq!(table.get_flags(drop_on_exec), Some(FD_CLOEXEC));
+            table.close_cloexec_fds();
+            ktest_assert!(table.get(keep).is_some());
+            ktest_assert!(table.get(drop_on_exec).is_none());
+            Ok(())
+        }
+    }
+
+    kernel_test! {
+        fn fork_copies_entries_and_flags() {
+            let mut table = FdTable::new_boxed();
+            let Some(fd) = table.alloc(Box::new(FileHandle::new_pipe_write(5)), true) else {
+                crate::ktest_fail!("alloc failed");
+            };
+            let child = table.clone_for_fork();
+            ktest_assert_eq!(child.get_flags(fd), Some(FD_CLOEXEC));
+            ktest_assert_eq!(child.get(fd).map(|h| (h.pipe_id, h.pipe_write)), Some((Some(5), true)));
+            ktest_assert!(table.close_fd(fd));
+            ktest_assert!(child.get(fd).is_some());
+            Ok(())
+        }
+    }
+}
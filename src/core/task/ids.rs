@@ -71,6 +71,8 @@ pub enum ThreadState {
     Blocked,
     /// スリープ中
     Sleeping,
+    /// ジョブ制御シグナルで停止中（SIGCONT まで実行しない）
+    Stopped,
     /// 終了済み
     Terminated,
 }
@@ -82,6 +84,8 @@ pub enum ProcessState {
     Running,
     /// スリープ中（すべてのスレッドがSleeping）
     Sleeping,
+    /// 停止中（SIGSTOP/SIGTSTP/SIGTTIN/SIGTTOU を受けて SIGCONT 待ち）
+    Stopped,
     /// ゾンビ（終了したが親に回収されていない）
     Zombie,
     /// 終了済み
@@ -2,39 +2,49 @@
 //!
 //! マルチタスク機能を提供（プロセスとスレッドの管理）
 
+pub mod capability;
 pub mod context;
 mod elf;
 pub mod fd_table;
 pub mod ids;
 pub mod process;
+pub mod ptrace;
+pub mod rlimit;
 pub mod scheduler;
 pub mod signal;
 pub mod thread;
 pub mod usermode;
 
+pub use capability::{current_capabilities, current_has, current_has_audited, CapabilitySet};
 pub use context::{switch_context, switch_to_thread, Context};
+pub use elf::load_symbols;
 pub use fd_table::{FdTable, FileHandle, FD_BASE, PROCESS_MAX_FDS};
 pub use ids::{PrivilegeLevel, ProcessId, ProcessState, ThreadId, ThreadState};
 pub use process::{
     add_process, find_process_id_by_name, for_each_process, has_child_process, mark_process_exited,
-    process_count, reap_zombie_child_process, remove_process, with_process, with_process_mut,
-    Process, ProcessTable,
+    peek_zombie_child_process, process_count, reap_zombie_child_process, remove_process,
+    take_child_job_event, take_traced_stop, with_process, with_process_mut, CpuUsage, JobEvent,
+    Process, ProcessTable, ReapedChild, WaitSelector,
 };
+pub use ptrace::{PtraceState, ResumeMode, StopKind, TraceStop};
+pub use rlimit::{RLimit, RLimits, RLIMIT_CORE, RLIM_INFINITY};
 pub use scheduler::{
     block_current_thread, disable_scheduler, enable_scheduler, exit_current_task, init_scheduler,
-    is_scheduler_enabled, schedule, schedule_and_switch, scheduler_tick, set_time_slice,
-    sleep_thread, sleep_thread_unless_woken, start_scheduling, terminate_thread, wake_thread,
-    yield_now, Scheduler,
+    is_scheduler_enabled, resume_process_threads, schedule, schedule_and_switch, scheduler_tick,
+    set_time_slice, sleep_thread, sleep_thread_unless_woken, start_scheduling,
+    stop_process_threads, terminate_thread, wait_while_stopped, wake_thread, yield_now, Scheduler,
 };
 pub use signal::{
-    default_action, sigreturn_stub_addr, DefaultAction, SigAction, SignalState, SA_RESTORER,
-    SIGCHLD, SIGINT, SIGKILL, SIGNAL_FRAME_MAGIC, SIGTERM, SIG_DFL, SIG_IGN,
-    USER_SIGRETURN_STUB_OFFSET,
+    default_action, is_stop_signal, sig_bit, AltStack, DefaultAction, SigAction, SigInfo,
+    SignalState, MINSIGSTKSZ, SA_NODEFER, SA_ONSTACK, SA_RESETHAND, SA_RESTORER, SA_SIGINFO,
+    SEGV_ACCERR, SEGV_MAPERR, SIGCHLD, SIGCONT, SIGINT, SIGKILL, SIGNAL_FRAME_MAGIC, SIGRTMIN,
+    SIGSEGV, SIGSTOP, SIGTERM, SIG_DFL, SIG_IGN, SI_KERNEL, SI_QUEUE, SI_TKILL, SI_USER,
+    SS_DISABLE, SS_ONSTACK, STOP_SIGNAL_MASK,
 };
 pub use thread::{
     add_thread, allocate_kernel_stack, count_threads_by_state, current_thread_id, for_each_thread,
-    free_kernel_stack, peek_next_thread, remove_thread, set_current_thread, thread_count,
-    thread_id_exists, thread_slot_index, thread_slot_index_and_generation,
+    free_kernel_stack, peek_next_thread, remove_thread, set_current_process, set_current_thread,
+    thread_count, thread_id_exists, thread_slot_index, thread_slot_index_and_generation,
     thread_slot_index_and_generation_by_u64, thread_slot_index_by_u64, thread_to_process_id,
     with_thread, with_thread_mut, Thread, ThreadQueue,
 };
@@ -3,10 +3,60 @@ use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
 
+use super::capability::CapabilitySet;
 use super::fd_table::FdTable;
 use super::ids::{PrivilegeLevel, ProcessId, ProcessState};
+use super::ptrace::PtraceState;
+use super::rlimit::RLimits;
 use super::signal::SignalState;
 
+/// wait 系 syscall の子プロセス選択条件（wait4 の pid 引数 / waitid の idtype）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitSelector {
+    /// 任意の子プロセス
+    Any,
+    /// 指定 PID の子プロセス
+    Pid(ProcessId),
+    /// 指定プロセスグループに属する子プロセス
+    Group(u64),
+}
+
+/// 親へまだ報告していないジョブ制御イベント
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobEvent {
+    /// 指定シグナルで停止した
+    Stopped(u8),
+    /// SIGCONT で再開した
+    Continued,
+    /// ptrace 停止した（トレーサーだけに報告する）
+    Traced(u8),
+}
+
+/// CPU 使用時間（タイマー tick 単位）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuUsage {
+    /// ユーザーモードで消費した tick 数
+    pub user_ticks: u64,
+    /// カーネル（syscall 中）で消費した tick 数
+    pub system_ticks: u64,
+}
+
+impl CpuUsage {
+    fn add(&mut self, other: CpuUsage) {
+        self.user_ticks = self.user_ticks.saturating_add(other.user_ticks);
+        self.system_ticks = self.system_ticks.saturating_add(other.system_ticks);
+    }
+}
+
+/// 回収したゾンビ子プロセスの情報
+#[derive(Debug, Clone, Copy)]
+pub struct ReapedChild {
+    pub pid: ProcessId,
+    pub exit_code: u64,
+    /// 子自身と、子が回収した子孫の CPU 使用時間の合計
+    pub usage: CpuUsage,
+}
+
 /// プロセス構造体
 ///
 /// メモリ空間とリソースを管理する実行単位。
@@ -22,6 +72,8 @@ pub struct Process {
     state: ProcessState,
     /// 権限レベル
     privilege: PrivilegeLevel,
+    /// 特権操作ごとのケーパビリティ
+    capabilities: CapabilitySet,
     /// 親プロセスID（存在する場合）
     parent_id: Option<ProcessId>,
     /// ページテーブルのアドレス（メモリ空間）。Noneの場合はカーネル空間を共有。
@@ -49,6 +101,20 @@ pub struct Process {
     signal_state: alloc::boxed::Box<SignalState>,
     /// プロセスごとのファイルディスクリプタテーブル — ヒープに置いてスタック消費を抑える
     fd_table: alloc::boxed::Box<FdTable>,
+    /// 親へ未報告の停止/再開イベント
+    job_event: Option<JobEvent>,
+    /// 自身の CPU 使用時間
+    usage: CpuUsage,
+    /// 回収済み子孫の CPU 使用時間の合計
+    children_usage: CpuUsage,
+    /// リソース上限
+    rlimits: RLimits,
+    /// 初期スタック上の auxv（先頭アドレス, バイト数）。コアダンプの NT_AUXV に使う
+    auxv: (u64, u64),
+    /// トレースされている場合の ptrace 状態
+    ptrace: Option<PtraceState>,
+    /// 実行中の ELF の関数シンボル（スタックトレース用。fork では共有する）
+    symbols: Option<alloc::sync::Arc<crate::backtrace::UserSymbols>>,
 }
 
 impl Process {
@@ -80,6 +146,7 @@ impl Process {
             name_len: len,
             state: ProcessState::Running,
             privilege,
+            capabilities: CapabilitySet::default_for(privilege),
             parent_id,
             page_table: None, // TODO: ページテーブル実装後に設定
             heap_start,
@@ -98,6 +165,13 @@ impl Process {
             sid: 0,
             signal_state: alloc::boxed::Box::new(SignalState::new()),
             fd_table: FdTable::new_boxed(),
+            job_event: None,
+            usage: CpuUsage::default(),
+            children_usage: CpuUsage::default(),
+            rlimits: RLimits::new(),
+            auxv: (0, 0),
+            ptrace: None,
+            symbols: None,
         }
     }
 
@@ -126,6 +200,16 @@ impl Process {
         self.privilege
     }
 
+    /// ケーパビリティを取得
+    pub fn capabilities(&self) -> &CapabilitySet {
+        &self.capabilities
+    }
+
+    /// ケーパビリティを設定
+    pub fn set_capabilities(&mut self, caps: CapabilitySet) {
+        self.capabilities = caps;
+    }
+
     /// 親プロセスIDを取得
     pub fn parent_id(&self) -> Option<ProcessId> {
         self.parent_id
@@ -142,9 +226,128 @@ impl Process {
     }
 
     /// 終了状態へ遷移
+    ///
+    /// トレースは外す（ゾンビの回収は本来の親だけが行う）。
     pub fn mark_exited(&mut self, exit_code: u64) {
         self.state = ProcessState::Zombie;
         self.exit_code = Some(exit_code);
+        self.ptrace = None;
+    }
+
+    /// ジョブ制御で停止状態へ遷移（停止できた場合 true）
+    pub fn mark_stopped(&mut self, sig: u8) -> bool {
+        match self.state {
+            ProcessState::Zombie | ProcessState::Terminated | ProcessState::Stopped => false,
+            _ => {
+                self.state = ProcessState::Stopped;
+                self.job_event = Some(JobEvent::Stopped(sig));
+                true
+            }
+        }
+    }
+
+    /// 停止状態から再開（再開した場合 true）
+    ///
+    /// ptrace 停止中はトレーサーの再開要求でしか動かさない。
+    pub fn mark_continued(&mut self) -> bool {
+        if self.state != ProcessState::Stopped || self.is_trace_stopped() {
+            return false;
+        }
+        self.state = ProcessState::Running;
+        self.job_event = Some(JobEvent::Continued);
+        true
+    }
+
+    /// 未報告のジョブ制御イベントを参照
+    pub fn job_event(&self) -> Option<JobEvent> {
+        self.job_event
+    }
+
+    /// 未報告のジョブ制御イベントを消費
+    pub fn take_job_event(&mut self) -> Option<JobEvent> {
+        self.job_event.take()
+    }
+
+    /// タイマー 1 tick 分の CPU 時間を計上
+    pub fn account_tick(&mut self, in_kernel: bool) {
+        if in_kernel {
+            self.usage.system_ticks = self.usage.system_ticks.saturating_add(1);
+        } else {
+            self.usage.user_ticks = self.usage.user_ticks.saturating_add(1);
+        }
+    }
+
+    /// 自身の CPU 使用時間
+    pub fn usage(&self) -> CpuUsage {
+        self.usage
+    }
+
+    /// 回収済み子孫の CPU 使用時間
+    pub fn children_usage(&self) -> CpuUsage {
+        self.children_usage
+    }
+
+    /// リソース上限を取得
+    pub fn rlimits(&self) -> &RLimits {
+        &self.rlimits
+    }
+
+    /// リソース上限への可変アクセス
+    pub fn rlimits_mut(&mut self) -> &mut RLimits {
+        &mut self.rlimits
+    }
+
+    /// 初期スタック上の auxv の位置（先頭アドレス, バイト数）
+    pub fn auxv(&self) -> (u64, u64) {
+        self.auxv
+    }
+
+    /// 初期スタック上の auxv の位置を記録（exec 時）
+    pub fn set_auxv(&mut self, addr: u64, len: u64) {
+        self.auxv = (addr, len);
+    }
+
+    /// 実行中の ELF の関数シンボル
+    pub fn symbols(&self) -> Option<alloc::sync::Arc<crate::backtrace::UserSymbols>> {
+        self.symbols.clone()
+    }
+
+    /// 実行中の ELF の関数シンボルを設定（exec / fork 時）
+    pub fn set_symbols(
+        &mut self,
+        symbols: Option<alloc::sync::Arc<crate::backtrace::UserSymbols>>,
+    ) {
+        self.symbols = symbols;
+    }
+
+    /// ptrace 状態を取得
+    pub fn ptrace(&self) -> Option<&PtraceState> {
+        self.ptrace.as_ref()
+    }
+
+    /// ptrace 状態への可変アクセス
+    pub fn ptrace_mut(&mut self) -> Option<&mut PtraceState> {
+        self.ptrace.as_mut()
+    }
+
+    /// トレースを開始する
+    pub fn set_ptrace(&mut self, state: PtraceState) {
+        self.ptrace = Some(state);
+    }
+
+    /// トレースを外して状態を返す
+    pub fn take_ptrace(&mut self) -> Option<PtraceState> {
+        self.ptrace.take()
+    }
+
+    /// トレーサーのプロセスID
+    pub fn tracer(&self) -> Option<ProcessId> {
+        self.ptrace.as_ref().map(|s| s.tracer)
+    }
+
+    /// ptrace 停止中か
+    pub fn is_trace_stopped(&self) -> bool {
+        self.ptrace.as_ref().is_some_and(|s| s.stop.is_some())
     }
 
     /// ページテーブルアドレスを取得
@@ -268,6 +471,10 @@ impl core::fmt::Debug for Process {
             .field("name", &self.name())
             .field("state", &self.state)
             .field("privilege", &self.privilege)
+            .field(
+                "capabilities",
+                &format_args!("{:#x}", self.capabilities.flags()),
+            )
             .field("parent_id", &self.parent_id)
             .field("priority", &self.priority)
             .field("exit_code", &self.exit_code);
@@ -457,46 +664,152 @@ impl ProcessTable {
             .find(|p| p.name().contains(name))
     }
 
-    fn is_child_match(process: &Process, parent: ProcessId, target: Option<ProcessId>) -> bool {
-        if process.parent_id() != Some(parent) {
-            return false;
-        }
-        if let Some(target_id) = target {
-            process.id() == target_id
-        } else {
-            true
+    fn is_child_match(process: &Process, parent: ProcessId, selector: WaitSelector) -> bool {
+        process.parent_id() == Some(parent) && Self::is_selected(process, selector)
+    }
+
+    /// `tracer` がトレースしているプロセスか（子でなくても wait の対象になる）
+    fn is_tracee_match(process: &Process, tracer: ProcessId, selector: WaitSelector) -> bool {
+        process.tracer() == Some(tracer) && Self::is_selected(process, selector)
+    }
+
+    fn is_selected(process: &Process, selector: WaitSelector) -> bool {
+        match selector {
+            WaitSelector::Any => true,
+            WaitSelector::Pid(pid) => process.id() == pid,
+            WaitSelector::Group(pgid) => process.pgid() == pgid,
         }
     }
 
-    /// 対象に一致する子プロセスが存在するかを返す
-    pub fn has_child(&self, parent: ProcessId, target: Option<ProcessId>) -> bool {
+    /// 対象に一致する子プロセス（またはトレーシー）が存在するかを返す
+    pub fn has_child(&self, parent: ProcessId, selector: WaitSelector) -> bool {
         self.processes
             .iter()
             .filter_map(|slot| slot.as_ref())
-            .any(|p| Self::is_child_match(p, parent, target))
+            .any(|p| {
+                Self::is_child_match(p, parent, selector)
+                    || Self::is_tracee_match(p, parent, selector)
+            })
     }
 
     /// ゾンビ子プロセスを1つ回収する
+    ///
+    /// 回収した子の CPU 使用時間は親の `children_usage` に加算する。
     pub fn reap_zombie_child(
         &mut self,
         parent: ProcessId,
-        target: Option<ProcessId>,
-    ) -> Option<(ProcessId, u64, Option<u64>)> {
+        selector: WaitSelector,
+    ) -> Option<(ReapedChild, Option<u64>)> {
+        let mut reaped = None;
         for slot in &mut self.processes {
             let should_reap = slot.as_ref().is_some_and(|proc| {
-                Self::is_child_match(proc, parent, target) && proc.state() == ProcessState::Zombie
+                Self::is_child_match(proc, parent, selector) && proc.state() == ProcessState::Zombie
             });
             if !should_reap {
                 continue;
             }
 
             if let Some(proc) = slot.take() {
-                let pid = proc.id();
-                let exit_code = proc.exit_code().unwrap_or(0);
-                let page_table = proc.page_table();
+                let mut usage = proc.usage;
+                usage.add(proc.children_usage);
+                let child = ReapedChild {
+                    pid: proc.id(),
+                    exit_code: proc.exit_code().unwrap_or(0),
+                    usage,
+                };
                 self.count = self.count.saturating_sub(1);
-                return Some((pid, exit_code, page_table));
+                reaped = Some((child, proc.page_table()));
+                break;
+            }
+        }
+        let (child, page_table) = reaped?;
+        if let Some(parent_proc) = self.get_mut(parent) {
+            parent_proc.children_usage.add(child.usage);
+        }
+        Some((child, page_table))
+    }
+
+    /// 回収せずにゾンビ子プロセスの情報だけを返す（WNOWAIT 用）
+    pub fn peek_zombie_child(
+        &self,
+        parent: ProcessId,
+        selector: WaitSelector,
+    ) -> Option<ReapedChild> {
+        self.processes
+            .iter()
+            .filter_map(|slot| slot.as_ref())
+            .find(|p| {
+                Self::is_child_match(p, parent, selector) && p.state() == ProcessState::Zombie
+            })
+            .map(|p| {
+                let mut usage = p.usage;
+                usage.add(p.children_usage);
+                ReapedChild {
+                    pid: p.id(),
+                    exit_code: p.exit_code().unwrap_or(0),
+                    usage,
+                }
+            })
+    }
+
+    /// ゾンビにはなっていないが wait に報告すべきジョブ制御イベントを持つ子を探す
+    ///
+    /// `consume` が true ならイベントを消費する（WNOWAIT 以外）。
+    pub fn take_child_job_event(
+        &mut self,
+        parent: ProcessId,
+        selector: WaitSelector,
+        want_stopped: bool,
+        want_continued: bool,
+        consume: bool,
+    ) -> Option<(ProcessId, JobEvent, CpuUsage)> {
+        for proc in self.processes.iter_mut().filter_map(|slot| slot.as_mut()) {
+            if !Self::is_child_match(proc, parent, selector) {
+                continue;
+            }
+            let wanted = match proc.job_event {
+                Some(JobEvent::Stopped(_)) => want_stopped,
+                Some(JobEvent::Continued) => want_continued,
+                Some(JobEvent::Traced(_)) | None => false,
+            };
+            if !wanted {
+                continue;
+            }
+            let event = if consume {
+                proc.take_job_event()
+            } else {
+                proc.job_event()
+            }?;
+            return Some((proc.id(), event, proc.usage));
+        }
+        None
+    }
+
+    /// トレーサーへ未報告の ptrace 停止を持つトレーシーを探す
+    ///
+    /// `consume` が true なら報告済みにする（WNOWAIT 以外）。
+    pub fn take_traced_stop(
+        &mut self,
+        tracer: ProcessId,
+        selector: WaitSelector,
+        consume: bool,
+    ) -> Option<(ProcessId, JobEvent, CpuUsage)> {
+        for proc in self.processes.iter_mut().filter_map(|slot| slot.as_mut()) {
+            if !Self::is_tracee_match(proc, tracer, selector) {
+                continue;
+            }
+            let usage = proc.usage;
+            let id = proc.id();
+            let Some(stop) = proc.ptrace_mut().and_then(|s| s.stop.as_mut()) else {
+                continue;
+            };
+            if stop.reported {
+                continue;
+            }
+            if consume {
+                stop.reported = true;
             }
+            return Some((id, JobEvent::Traced(stop.status), usage));
         }
         None
     }
@@ -514,7 +827,8 @@ impl Default for ProcessTable {
 }
 
 /// グローバルプロセステーブル
-static PROCESS_TABLE: SpinLock<ProcessTable> = SpinLock::new(ProcessTable::new());
+static PROCESS_TABLE: SpinLock<ProcessTable> =
+    SpinLock::named("PROCESS_TABLE", ProcessTable::new());
 
 /// プロセステーブルにプロセスを追加
 pub fn add_process(process: Process) -> Option<ProcessId> {
@@ -563,33 +877,72 @@ where
 
 /// プロセスを終了状態（Zombie）へ遷移させる
 pub fn mark_process_exited(id: ProcessId, exit_code: u64) {
-    let mut table = PROCESS_TABLE.lock();
-    if let Some(proc) = table.get_mut(id) {
-        proc.mark_exited(exit_code);
-    }
+    let was_traced = {
+        let mut table = PROCESS_TABLE.lock();
+        match table.get_mut(id) {
+            Some(proc) => {
+                let traced = proc.tracer().is_some();
+                proc.mark_exited(exit_code);
+                traced
+            }
+            None => false,
+        }
+    };
+    crate::syscall::ptrace::on_process_exit(id, was_traced);
+    crate::syscall::strace::on_process_exit(id, exit_code);
 }
 
 /// 一致する子プロセスが存在するか確認する
-pub fn has_child_process(parent: ProcessId, target: Option<ProcessId>) -> bool {
-    PROCESS_TABLE.lock().has_child(parent, target)
+pub fn has_child_process(parent: ProcessId, selector: WaitSelector) -> bool {
+    PROCESS_TABLE.lock().has_child(parent, selector)
 }
 
 /// 一致するゾンビ子プロセスを回収する
-pub fn reap_zombie_child_process(
-    parent: ProcessId,
-    target: Option<ProcessId>,
-) -> Option<(ProcessId, u64)> {
-    let (pid, exit_code, page_table) = PROCESS_TABLE.lock().reap_zombie_child(parent, target)?;
+pub fn reap_zombie_child_process(parent: ProcessId, selector: WaitSelector) -> Option<ReapedChild> {
+    let (child, page_table) = PROCESS_TABLE.lock().reap_zombie_child(parent, selector)?;
     if let Some(table_phys) = page_table {
         if let Err(e) = crate::mem::paging::destroy_user_page_table(table_phys) {
             crate::warn!(
                 "Failed to destroy child page table while reaping pid={:?}: {:?}",
-                pid,
+                child.pid,
                 e
             );
         }
     }
-    Some((pid, exit_code))
+    Some(child)
+}
+
+/// 回収せずに一致するゾンビ子プロセスの情報を返す
+pub fn peek_zombie_child_process(parent: ProcessId, selector: WaitSelector) -> Option<ReapedChild> {
+    PROCESS_TABLE.lock().peek_zombie_child(parent, selector)
+}
+
+/// 未報告の停止/再開イベントを持つ子プロセスを探す
+pub fn take_child_job_event(
+    parent: ProcessId,
+    selector: WaitSelector,
+    want_stopped: bool,
+    want_continued: bool,
+    consume: bool,
+) -> Option<(ProcessId, JobEvent, CpuUsage)> {
+    PROCESS_TABLE.lock().take_child_job_event(
+        parent,
+        selector,
+        want_stopped,
+        want_continued,
+        consume,
+    )
+}
+
+/// 未報告の ptrace 停止を持つトレーシーを探す
+pub fn take_traced_stop(
+    tracer: ProcessId,
+    selector: WaitSelector,
+    consume: bool,
+) -> Option<(ProcessId, JobEvent, CpuUsage)> {
+    PROCESS_TABLE
+        .lock()
+        .take_traced_stop(tracer, selector, consume)
 }
 
 /// 現在のプロセス数を取得
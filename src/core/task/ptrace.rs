@@ -0,0 +1,89 @@
+//! ptrace のトレース状態
+//!
+//! トレーシー（トレースされる側）のプロセスが 1 つずつ持つ。停止中のレジスタは
+//! トレーシー自身のカーネルスタック上に積まれた保存領域（int 0x80 / 例外エントリ）を
+//! 指しておき、トレーサーはそこを直接読み書きする。
+
+use super::ids::ProcessId;
+use super::signal::SigInfo;
+
+/// PTRACE_SETOPTIONS: syscall 停止の wait status を SIGTRAP | 0x80 にする
+pub const PTRACE_O_TRACESYSGOOD: u64 = 0x1;
+/// PTRACE_SETOPTIONS: トレーサーが終了したらトレーシーを SIGKILL する
+pub const PTRACE_O_EXITKILL: u64 = 0x10_0000;
+/// 対応しているオプション
+pub const PTRACE_O_MASK: u64 = PTRACE_O_TRACESYSGOOD | PTRACE_O_EXITKILL;
+
+/// 再開後にどこで次に止まるか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeMode {
+    /// シグナル・ブレークポイントでだけ止まる（PTRACE_CONT）
+    Continue,
+    /// 加えて syscall の入口/出口でも止まる（PTRACE_SYSCALL）
+    Syscall,
+    /// 1 命令実行したら止まる（PTRACE_SINGLESTEP）
+    SingleStep,
+}
+
+/// 停止した理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopKind {
+    /// シグナル配送停止（ブレークポイント・シングルステップ・例外を含む）
+    Signal,
+    /// syscall 入口
+    SyscallEntry,
+    /// syscall 出口
+    SyscallExit,
+    /// execve 直後（新しいイメージの先頭）
+    Exec,
+}
+
+/// 停止中のトレーシーの情報
+#[derive(Debug, Clone, Copy)]
+pub struct TraceStop {
+    pub kind: StopKind,
+    /// wait status に載せるシグナル番号（TRACESYSGOOD の syscall 停止では SIGTRAP | 0x80）
+    pub status: u8,
+    /// PTRACE_GETSIGINFO で返す内容
+    pub info: SigInfo,
+    /// 停止したスレッド ID
+    pub tid: u64,
+    /// 保存レジスタ領域の先頭アドレス
+    pub frame: u64,
+    /// 保存領域内の割り込みフレーム（RIP）の位置
+    pub iret: usize,
+    /// user_regs_struct の orig_rax（syscall 停止以外は -1）
+    pub orig_rax: u64,
+    /// トレーサーの wait へ報告済みか
+    pub reported: bool,
+}
+
+/// トレーシーが持つ状態
+#[derive(Debug, Clone, Copy)]
+pub struct PtraceState {
+    pub tracer: ProcessId,
+    /// PTRACE_O_*
+    pub options: u64,
+    pub resume: ResumeMode,
+    /// 再開時に注入するシグナル（0 なら注入しない）
+    pub resume_sig: usize,
+    pub stop: Option<TraceStop>,
+    /// ユーザー空間で走り続けているトレーシーを止めたい（次のタイマーで TF を立てる）
+    pub interrupt: bool,
+    /// `interrupt` のために TF を立てた（次の #DB はシングルステップ要求ではない）
+    pub kicked: bool,
+}
+
+impl PtraceState {
+    pub const fn new(tracer: ProcessId) -> Self {
+        Self {
+            tracer,
+            options: 0,
+            resume: ResumeMode::Continue,
+            resume_sig: 0,
+            stop: None,
+            interrupt: false,
+            kicked: false,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! プロセスごとのリソース上限（getrlimit / setrlimit / prlimit64）
+//!
+//! 値の並びは Linux x86-64 の RLIMIT_* と同じ。fork した子は親の上限を継承し、
+//! exec では引き継いだまま変えない。
+
+/// 上限なし
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+pub const RLIMIT_CPU: usize = 0;
+pub const RLIMIT_FSIZE: usize = 1;
+pub const RLIMIT_DATA: usize = 2;
+pub const RLIMIT_STACK: usize = 3;
+/// コアダンプの最大サイズ（0 ならダンプしない）
+pub const RLIMIT_CORE: usize = 4;
+pub const RLIMIT_NOFILE: usize = 7;
+/// リソースの種類数
+pub const RLIM_NLIMITS: usize = 16;
+
+/// struct rlimit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RLimit {
+    /// ソフトリミット
+    pub cur: u64,
+    /// ハードリミット
+    pub max: u64,
+}
+
+impl RLimit {
+    pub const fn unlimited() -> Self {
+        Self {
+            cur: RLIM_INFINITY,
+            max: RLIM_INFINITY,
+        }
+    }
+
+    pub const fn new(cur: u64, max: u64) -> Self {
+        Self { cur, max }
+    }
+}
+
+/// プロセスが持つ上限の集合
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RLimits {
+    limits: [RLimit; RLIM_NLIMITS],
+}
+
+impl RLimits {
+    /// 既定値
+    ///
+    /// スタックはフォルト時に伸ばせる上限（8 MiB）、FD 数はテーブルの大きさに合わせる。
+    /// コアダンプは Linux と同じくソフトリミット 0（ダンプしない）で、必要なら
+    /// setrlimit で引き上げる。
+    pub const fn new() -> Self {
+        let mut limits = [RLimit::unlimited(); RLIM_NLIMITS];
+        limits[RLIMIT_STACK] = RLimit::new(8 * 1024 * 1024, RLIM_INFINITY);
+        limits[RLIMIT_CORE] = RLimit::new(0, RLIM_INFINITY);
+        limits[RLIMIT_NOFILE] = RLimit::new(
+            super::fd_table::PROCESS_MAX_FDS as u64,
+            super::fd_table::PROCESS_MAX_FDS as u64,
+        );
+        Self { limits }
+    }
+
+    pub fn get(&self, resource: usize) -> Option<RLimit> {
+        self.limits.get(resource).copied()
+    }
+
+    /// 上限を設定する
+    ///
+    /// ソフトリミットがハードリミットを超える値やハードリミットの引き上げは拒否する。
+    /// errno への変換は呼び出し側で行う。
+    pub fn set(&mut self, resource: usize, new: RLimit) -> Result<(), RLimitError> {
+        let slot = self
+            .limits
+            .get_mut(resource)
+            .ok_or(RLimitError::InvalidResource)?;
+        if new.cur > new.max {
+            return Err(RLimitError::InvalidResource);
+        }
+        if new.max > slot.max {
+            return Err(RLimitError::RaiseHardLimit);
+        }
+        *slot = new;
+        Ok(())
+    }
+}
+
+impl Default for RLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `RLimits::set` の失敗理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RLimitError {
+    /// 不明なリソース、または cur > max
+    InvalidResource,
+    /// ハードリミットの引き上げ
+    RaiseHardLimit,
+}
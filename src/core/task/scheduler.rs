@@ -1,7 +1,7 @@
 use crate::interrupt::spinlock::SpinLock;
 
 use super::context::switch_to_thread;
-use super::ids::{ThreadId, ThreadState};
+use super::ids::{ProcessId, ThreadId, ThreadState};
 use super::thread::{
     current_thread_id, remove_thread, set_current_thread, with_thread, with_thread_mut,
     THREAD_QUEUE,
@@ -82,7 +82,7 @@ impl Default for Scheduler {
 }
 
 /// グローバルスケジューラ
-static SCHEDULER: SpinLock<Scheduler> = SpinLock::new(Scheduler::new());
+static SCHEDULER: SpinLock<Scheduler> = SpinLock::named("SCHEDULER", Scheduler::new());
 
 /// スケジューラを初期化
 pub fn init_scheduler() {
@@ -116,8 +116,13 @@ pub fn is_scheduler_enabled() -> bool {
 /// スケジューリングが必要な場合はtrue
 pub fn scheduler_tick() -> bool {
     if let Some(tid) = current_thread_id() {
-        if with_thread(tid, |t| t.in_syscall()).unwrap_or(false) {
-            return false;
+        let current = with_thread(tid, |t| (t.process_id(), t.in_syscall()));
+        if let Some((pid, in_syscall)) = current {
+            // rusage 用に 1 tick 分の CPU 時間を user/system に振り分ける
+            crate::task::with_process_mut(pid, |p| p.account_tick(in_syscall));
+            if in_syscall {
+                return false;
+            }
         }
     }
     SCHEDULER.lock().tick()
@@ -224,6 +229,9 @@ pub fn wake_thread(id: ThreadId) {
         let state = thread.state();
         if state == ThreadState::Sleeping || state == ThreadState::Blocked {
             thread.set_state(ThreadState::Ready);
+        } else if state == ThreadState::Stopped {
+            // 停止中は起床させず、SIGCONT 後に Ready で戻れるよう記録だけする
+            thread.wake_while_stopped();
         } else if state == ThreadState::Ready {
             // まだ眠っていない場合、起床要求を記録しておく
             thread.set_pending_wakeup();
@@ -249,6 +257,42 @@ pub fn sleep_thread_unless_woken(id: ThreadId) -> bool {
     .unwrap_or(false)
 }
 
+fn threads_of(pid: ProcessId) -> alloc::vec::Vec<ThreadId> {
+    let mut tids = alloc::vec::Vec::new();
+    crate::task::for_each_thread(|thread| {
+        if thread.process_id() == pid {
+            tids.push(thread.id());
+        }
+    });
+    tids
+}
+
+/// プロセスの全スレッドを Stopped にする（SIGSTOP 系の既定動作）
+pub fn stop_process_threads(pid: ProcessId) {
+    for tid in threads_of(pid) {
+        with_thread_mut(tid, |thread| thread.stop());
+    }
+}
+
+/// プロセスの全スレッドの停止を解除する（SIGCONT）
+pub fn resume_process_threads(pid: ProcessId) {
+    for tid in threads_of(pid) {
+        with_thread_mut(tid, |thread| thread.resume());
+    }
+}
+
+/// 現在スレッドが Stopped の間 CPU を手放し続ける
+///
+/// 自プロセス宛ての停止シグナルを処理したあとに呼ぶ。
+pub fn wait_while_stopped() {
+    let Some(tid) = current_thread_id() else {
+        return;
+    };
+    while with_thread(tid, |t| t.state() == ThreadState::Stopped).unwrap_or(false) {
+        yield_now();
+    }
+}
+
 /// 子プロセス終了時に親プロセスの先頭スレッドの IPC waiter を起床させる。
 /// IPC recv_blocking でスリープしている親スレッドを叩き起こし、child exit を検知させる。
 fn wake_parent_ipc_waiter(exited_pid: crate::task::ProcessId) {
@@ -1,4 +1,9 @@
 //! プロセスごとのシグナル状態
+//!
+//! 標準シグナル (1–31) は保留中に同じシグナルが来ても 1 つにまとめ、
+//! リアルタイムシグナル (32–64) は送られた回数だけ siginfo ごとキューに積む。
+
+use alloc::collections::VecDeque;
 
 /// シグナルハンドラのデフォルト動作（SIG_DFL）
 pub const SIG_DFL: u64 = 0;
@@ -9,7 +14,13 @@ pub const SIG_IGN: u64 = 1;
 pub const SIGHUP: usize = 1;
 pub const SIGINT: usize = 2;
 pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
 pub const SIGKILL: usize = 9;
+pub const SIGSEGV: usize = 11;
 pub const SIGTERM: usize = 15;
 pub const SIGCHLD: usize = 17;
 pub const SIGCONT: usize = 18;
@@ -17,12 +28,61 @@ pub const SIGSTOP: usize = 19;
 pub const SIGTSTP: usize = 20;
 pub const SIGTTIN: usize = 21;
 pub const SIGTTOU: usize = 22;
+pub const SIGXCPU: usize = 24;
+pub const SIGXFSZ: usize = 25;
 pub const SIGWINCH: usize = 28;
+pub const SIGSYS: usize = 31;
+/// リアルタイムシグナルの範囲
+pub const SIGRTMIN: usize = 32;
+pub const SIGRTMAX: usize = 64;
 
 // ----- SA_* フラグ -----
+/// ハンドラに siginfo_t / ucontext_t を渡す
+pub const SA_SIGINFO: u64 = 0x00000004;
 pub const SA_RESTORER: u64 = 0x04000000;
-/// カーネルが各プロセスへ固定配置する sigreturn スタブのオフセット。
-pub const USER_SIGRETURN_STUB_OFFSET: u64 = 0x2000;
+/// sigaltstack で登録した代替スタック上でハンドラを実行する
+pub const SA_ONSTACK: u64 = 0x08000000;
+/// ハンドラ実行中も同じシグナルをブロックしない
+pub const SA_NODEFER: u64 = 0x40000000;
+/// 一度送達したらハンドラを SIG_DFL に戻す
+pub const SA_RESETHAND: u64 = 0x80000000;
+
+// ----- si_code -----
+/// kill / tkill などユーザーからの送信
+pub const SI_USER: i32 = 0;
+/// カーネル内部からの送信
+pub const SI_KERNEL: i32 = 0x80;
+/// sigqueue (rt_sigqueueinfo) による送信
+pub const SI_QUEUE: i32 = -1;
+/// tkill / tgkill による送信
+pub const SI_TKILL: i32 = -6;
+/// SIGSEGV: マップされていないアドレス
+pub const SEGV_MAPERR: i32 = 1;
+/// SIGSEGV: 権限違反
+pub const SEGV_ACCERR: i32 = 2;
+/// SIGTRAP: ブレークポイント（int3）
+pub const TRAP_BRKPT: i32 = 1;
+/// SIGTRAP: シングルステップ
+pub const TRAP_TRACE: i32 = 2;
+/// SIGCHLD: 子が終了した
+pub const CLD_EXITED: i32 = 1;
+/// SIGCHLD: トレース中の子が ptrace 停止した
+pub const CLD_TRAPPED: i32 = 4;
+/// SIGCHLD: 子が停止した
+pub const CLD_STOPPED: i32 = 5;
+/// SIGCHLD: 子が再開した
+pub const CLD_CONTINUED: i32 = 6;
+
+// ----- sigaltstack -----
+/// 現在代替スタック上で実行中
+pub const SS_ONSTACK: u32 = 1;
+/// 代替スタック無効
+pub const SS_DISABLE: u32 = 2;
+/// 代替スタックの最小サイズ
+pub const MINSIGSTKSZ: u64 = 2048;
+
+/// プロセスあたりに積めるリアルタイムシグナルの最大数
+pub const RT_SIGQUEUE_MAX: usize = 64;
 /// シグナルフレーム整合性検証用のマジック値。
 pub const SIGNAL_FRAME_MAGIC: u64 = 0x6d6f_6368_695f_7367;
 
@@ -31,21 +91,43 @@ pub const SIGNAL_FRAME_MAGIC: u64 = 0x6d6f_6368_695f_7367;
 pub enum DefaultAction {
     /// プロセスを終了する
     Terminate,
+    /// コアダンプを書き出してから終了する
+    Core,
     /// シグナルを無視する
     Ignore,
+    /// プロセスを停止する（SIGCONT で再開）
+    Stop,
+    /// 停止中なら再開する
+    Continue,
 }
 
 /// シグナル番号に対応するデフォルト動作を返す
 pub fn default_action(sig: usize) -> DefaultAction {
     match sig {
         // 無視するシグナル
-        SIGCHLD | SIGCONT | SIGWINCH => DefaultAction::Ignore,
-        SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU => DefaultAction::Ignore,
+        SIGCHLD | SIGWINCH => DefaultAction::Ignore,
+        // ジョブ制御
+        SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU => DefaultAction::Stop,
+        SIGCONT => DefaultAction::Continue,
+        // 異常終了系はコアダンプを残す
+        SIGQUIT | SIGILL | SIGTRAP | SIGABRT | SIGBUS | SIGFPE | SIGSEGV | SIGXCPU | SIGXFSZ
+        | SIGSYS => DefaultAction::Core,
         // それ以外はすべてプロセス終了
         _ => DefaultAction::Terminate,
     }
 }
 
+/// 停止系シグナル（既定動作が Stop）か
+pub fn is_stop_signal(sig: usize) -> bool {
+    matches!(sig, SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU)
+}
+
+/// 停止系シグナル全体のビットマスク
+pub const STOP_SIGNAL_MASK: u64 = (1u64 << (SIGSTOP - 1))
+    | (1u64 << (SIGTSTP - 1))
+    | (1u64 << (SIGTTIN - 1))
+    | (1u64 << (SIGTTOU - 1));
+
 /// 1つのシグナルに対するアクション（Linux の struct sigaction と互換）
 #[derive(Clone, Copy)]
 pub struct SigAction {
@@ -80,9 +162,113 @@ impl SigAction {
     }
 }
 
+/// シグナル番号 → マスクのビット
 #[inline]
-pub fn sigreturn_stub_addr(stack_top: u64) -> Option<u64> {
-    stack_top.checked_add(USER_SIGRETURN_STUB_OFFSET)
+pub fn sig_bit(sig: usize) -> u64 {
+    if (1..=64).contains(&sig) {
+        1u64 << (sig - 1)
+    } else {
+        0
+    }
+}
+
+/// 1 件分のシグナル情報（siginfo_t の内容）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigInfo {
+    pub signo: usize,
+    pub code: i32,
+    pub errno: i32,
+    /// 送信元 PID（SIGCHLD では子の PID）
+    pub pid: u64,
+    pub uid: u32,
+    /// SIGCHLD の終了コード / 停止シグナル
+    pub status: i32,
+    /// 障害アドレス（SIGSEGV など）
+    pub addr: u64,
+    /// sigqueue で渡された sigval
+    pub value: u64,
+}
+
+impl SigInfo {
+    pub const fn new(signo: usize, code: i32) -> Self {
+        Self {
+            signo,
+            code,
+            errno: 0,
+            pid: 0,
+            uid: 0,
+            status: 0,
+            addr: 0,
+            value: 0,
+        }
+    }
+
+    /// 障害系シグナル（union に si_addr を持つ）か
+    fn is_fault(&self) -> bool {
+        matches!(self.signo, SIGILL | SIGTRAP | SIGFPE | SIGSEGV | SIGBUS) && self.code > 0
+    }
+
+    /// Linux x86-64 の siginfo_t (128 バイト) へエンコードする
+    pub fn to_siginfo_bytes(&self) -> [u8; 128] {
+        let mut buf = [0u8; 128];
+        buf[0..4].copy_from_slice(&(self.signo as i32).to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.errno.to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.code.to_ne_bytes());
+        if self.is_fault() {
+            buf[16..24].copy_from_slice(&self.addr.to_ne_bytes());
+        } else {
+            buf[16..20].copy_from_slice(&(self.pid as i32).to_ne_bytes());
+            buf[20..24].copy_from_slice(&self.uid.to_ne_bytes());
+            if self.signo == SIGCHLD && self.code > 0 {
+                buf[24..28].copy_from_slice(&self.status.to_ne_bytes());
+            } else {
+                buf[24..32].copy_from_slice(&self.value.to_ne_bytes());
+            }
+        }
+        buf
+    }
+
+    /// signalfd から読み出す struct signalfd_siginfo (128 バイト) へエンコードする
+    pub fn to_signalfd_bytes(&self) -> [u8; 128] {
+        let mut buf = [0u8; 128];
+        buf[0..4].copy_from_slice(&(self.signo as u32).to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.errno.to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.code.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(self.pid as u32).to_ne_bytes());
+        buf[16..20].copy_from_slice(&self.uid.to_ne_bytes());
+        buf[40..44].copy_from_slice(&self.status.to_ne_bytes());
+        buf[44..48].copy_from_slice(&(self.value as i32).to_ne_bytes());
+        buf[48..56].copy_from_slice(&self.value.to_ne_bytes());
+        buf[72..80].copy_from_slice(&self.addr.to_ne_bytes());
+        buf
+    }
+}
+
+/// sigaltstack で登録された代替シグナルスタック
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AltStack {
+    pub sp: u64,
+    pub size: u64,
+    pub flags: u32,
+}
+
+impl AltStack {
+    pub const fn disabled() -> Self {
+        Self {
+            sp: 0,
+            size: 0,
+            flags: SS_DISABLE,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.flags & SS_DISABLE == 0 && self.size != 0
+    }
+
+    /// `rsp` が代替スタック上にあるか
+    pub fn contains(&self, rsp: u64) -> bool {
+        self.is_enabled() && rsp > self.sp && rsp - self.sp <= self.size
+    }
 }
 
 /// プロセスのシグナル状態
@@ -92,7 +278,15 @@ pub struct SignalState {
     /// ブロック中のシグナルマスク（ビット i = シグナル i+1 がブロック中）
     pub mask: u64,
     /// 保留（pending）シグナルビットマップ
+    ///
+    /// ビットが立っているシグナルは `queue` に 1 件以上の siginfo を持つ。
     pub pending: u64,
+    /// 保留中シグナルの siginfo（到着順）
+    pub queue: VecDeque<SigInfo>,
+    /// 代替シグナルスタック
+    pub altstack: AltStack,
+    /// rt_sigsuspend 中に退避した元のマスク（ハンドラ復帰時に戻す）
+    pub saved_mask: Option<u64>,
 }
 
 impl SignalState {
@@ -101,25 +295,72 @@ impl SignalState {
             actions: [SigAction::default_action(); 64],
             mask: 0,
             pending: 0,
+            queue: VecDeque::new(),
+            altstack: AltStack::disabled(),
+            saved_mask: None,
         }
     }
 
-    /// シグナルを pending にセットする
+    /// シグナルを pending にセットする（カーネル発の siginfo を付ける）
     pub fn set_pending(&mut self, sig: usize) {
-        if sig >= 1 && sig <= 64 {
-            self.pending |= 1u64 << (sig - 1);
+        let _ = self.enqueue(SigInfo::new(sig, SI_KERNEL));
+    }
+
+    /// siginfo 付きでシグナルを保留する
+    ///
+    /// 標準シグナルが既に保留中なら何もしない（1 つにまとめる）。
+    /// リアルタイムシグナルのキューが満杯なら false を返す。
+    pub fn enqueue(&mut self, info: SigInfo) -> bool {
+        let bit = sig_bit(info.signo);
+        if bit == 0 {
+            return false;
+        }
+        if info.signo < SIGRTMIN {
+            if self.pending & bit != 0 {
+                return true;
+            }
+        } else {
+            let queued_rt = self.queue.iter().filter(|i| i.signo >= SIGRTMIN).count();
+            if queued_rt >= RT_SIGQUEUE_MAX {
+                return false;
+            }
+        }
+        self.queue.push_back(info);
+        self.pending |= bit;
+        true
+    }
+
+    /// pending からビットマスクで指定したシグナルを取り除く
+    pub fn clear_pending(&mut self, mask: u64) {
+        self.pending &= !mask;
+        self.queue.retain(|i| sig_bit(i.signo) & mask == 0);
+    }
+
+    /// 指定シグナルの siginfo を 1 件取り出す
+    fn dequeue(&mut self, sig: usize) -> SigInfo {
+        let info = match self.queue.iter().position(|i| i.signo == sig) {
+            Some(idx) => self.queue.remove(idx),
+            None => None,
+        };
+        if !self.queue.iter().any(|i| i.signo == sig) {
+            self.pending &= !sig_bit(sig);
         }
+        info.unwrap_or(SigInfo::new(sig, SI_KERNEL))
     }
 
-    /// ブロックされていない pending シグナルを1つ取り出す（ビットをクリアして番号を返す）
-    pub fn take_next_deliverable(&mut self) -> Option<usize> {
-        let deliverable = self.pending & !self.mask;
-        if deliverable == 0 {
+    /// `set` に含まれる pending シグナルのうち番号が最小のものを取り出す
+    pub fn take_from(&mut self, set: u64) -> Option<SigInfo> {
+        let candidates = self.pending & set;
+        if candidates == 0 {
             return None;
         }
-        let bit = deliverable.trailing_zeros() as usize;
-        self.pending &= !(1u64 << bit);
-        Some(bit + 1)
+        let sig = candidates.trailing_zeros() as usize + 1;
+        Some(self.dequeue(sig))
+    }
+
+    /// ブロックされていない pending シグナルを1つ取り出す
+    pub fn take_next_deliverable(&mut self) -> Option<SigInfo> {
+        self.take_from(!self.mask)
     }
 
     /// 指定シグナルのアクションを取得
@@ -57,6 +57,8 @@ pub struct Thread {
     futex_timed_out: bool,
     /// IPC受信などで眠る前に起床要求が来たことを示すフラグ
     pending_wakeup: bool,
+    /// 停止される直前の状態（Stopped 中のみ Some）
+    state_before_stop: Option<ThreadState>,
 }
 
 // Simple kernel stack pool for creating kernel stacks for threads
@@ -68,13 +70,13 @@ const KSTACK_GUARD_BYTES: usize = KSTACK_PAGE_BYTES;
 /// 各エントリは guard_addr（= スタックベース - KSTACK_GUARD_BYTES）を格納。0 = 空き
 const KSTACK_FREE_LIST_CAP: usize = 32;
 static KSTACK_FREE_LIST: SpinLock<[u64; KSTACK_FREE_LIST_CAP]> =
-    SpinLock::new([0u64; KSTACK_FREE_LIST_CAP]);
+    SpinLock::named("KSTACK_FREE_LIST", [0u64; KSTACK_FREE_LIST_CAP]);
 
 #[repr(align(4096))]
 struct KernelStackPool([u8; KSTACK_POOL_SIZE]);
 
 static KSTACK_POOL: SpinLock<KernelStackPool> =
-    SpinLock::new(KernelStackPool([0; KSTACK_POOL_SIZE]));
+    SpinLock::named("KSTACK_POOL", KernelStackPool([0; KSTACK_POOL_SIZE]));
 static NEXT_KSTACK_OFFSET: core::sync::atomic::AtomicUsize =
     core::sync::atomic::AtomicUsize::new(0);
 
@@ -315,6 +317,7 @@ impl Thread {
             syscall_user_rflags: 0,
             futex_timed_out: false,
             pending_wakeup: false,
+            state_before_stop: None,
         }
     }
 
@@ -420,6 +423,7 @@ impl Thread {
             syscall_user_rflags: 0,
             futex_timed_out: false,
             pending_wakeup: false,
+            state_before_stop: None,
         }
     }
 
@@ -526,6 +530,7 @@ impl Thread {
             syscall_user_rflags: user_rflags,
             futex_timed_out: false,
             pending_wakeup: false,
+            state_before_stop: None,
         }
     }
 
@@ -587,6 +592,40 @@ impl Thread {
         v
     }
 
+    /// ジョブ制御で停止させる（元の状態は SIGCONT 時に戻す）
+    pub fn stop(&mut self) {
+        match self.state {
+            ThreadState::Stopped | ThreadState::Terminated => {}
+            prev => {
+                self.state_before_stop = Some(prev);
+                self.state = ThreadState::Stopped;
+            }
+        }
+    }
+
+    /// 停止を解除して停止前の状態へ戻す
+    ///
+    /// 停止中に起床要求が来ていた場合は Ready で戻る。
+    pub fn resume(&mut self) {
+        if self.state != ThreadState::Stopped {
+            return;
+        }
+        self.state = match self.state_before_stop.take() {
+            Some(ThreadState::Sleeping) => ThreadState::Sleeping,
+            Some(ThreadState::Blocked) => ThreadState::Blocked,
+            _ => ThreadState::Ready,
+        };
+    }
+
+    /// 停止中に届いた起床要求を記録する
+    pub fn wake_while_stopped(&mut self) {
+        if let Some(prev) = self.state_before_stop.as_mut() {
+            if matches!(*prev, ThreadState::Sleeping | ThreadState::Blocked) {
+                *prev = ThreadState::Ready;
+            }
+        }
+    }
+
     /// スレッドIDを取得
     pub fn id(&self) -> ThreadId {
         self.id
@@ -886,7 +925,8 @@ impl Default for ThreadQueue {
 }
 
 /// グローバルスレッドキュー
-pub(super) static THREAD_QUEUE: SpinLock<ThreadQueue> = SpinLock::new(ThreadQueue::new());
+pub(super) static THREAD_QUEUE: SpinLock<ThreadQueue> =
+    SpinLock::named("THREAD_QUEUE", ThreadQueue::new());
 
 /// スレッドキューにスレッドを追加
 pub fn add_thread(thread: Thread) -> Option<ThreadId> {
@@ -982,13 +1022,23 @@ pub fn current_thread_id() -> Option<ThreadId> {
 }
 
 /// 現在実行中のスレッドIDを設定
+///
+/// `None` にしたときは現在のプロセスIDもクリアする。
 pub fn set_current_thread(id: Option<ThreadId>) {
     let raw = id.map(|v| v.as_u64()).unwrap_or(0);
     x86_64::instructions::interrupts::without_interrupts(|| {
         crate::percpu::set_current_thread_raw_id(raw);
+        if raw == 0 {
+            crate::percpu::set_current_process_raw_id(0);
+        }
     });
 }
 
+/// 現在実行中のスレッドが属するプロセスを設定（コンテキストスイッチ時）
+pub fn set_current_process(id: ProcessId) {
+    crate::percpu::set_current_process_raw_id(id.as_u64());
+}
+
 /// スレッドIDからプロセスIDを取得
 pub fn thread_to_process_id(thread_id: u64) -> Option<ProcessId> {
     with_thread(ThreadId::from_u64(thread_id), |t| t.process_id())
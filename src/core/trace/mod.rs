@@ -0,0 +1,226 @@
+//! 静的トレースポイント
+//!
+//! カーネルの要所（コンテキストスイッチ、IPC の送受信、ページフォルト、割り込み、syscall）に
+//! 置いた `emit` が、有効にされているものだけを CPU ごとのリングに積む。無効なときは
+//! マスクを 1 回読むだけで戻る。リングが一杯なら古い記録から捨て、捨てた数を数えておく。
+//!
+//! 設定と読み出しは perf システムコールで行う（設定には CAP_PERFMON が必要）。
+//! 起動オプション `trace=sched,irq,...`（`trace=all` で全部）で起動直後から有効にでき、
+//! `profile`（`profile=N` で N ティックに 1 回）でプロファイラも起動直後から動かせる。
+
+pub mod profile;
+
+use crate::interrupt::spinlock::SpinLock;
+use crate::percpu::MAX_CPUS;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// 1 CPU あたりのリングの記録数
+const RING_CAPACITY: usize = 4096;
+
+/// トレースポイントの種類（番号はユーザー側の `perf` モジュールと共通）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Tracepoint {
+    /// コンテキストスイッチ（切替前の tid, 切替後の tid, 切替後の pid）
+    SchedSwitch = 0,
+    /// IPC 送信（宛先 tid, 長さ）
+    IpcSend = 1,
+    /// IPC 受信（送信元 tid, 長さ）
+    IpcRecv = 2,
+    /// ページフォルト（アドレス, エラーコード, rip）
+    PageFault = 3,
+    /// ハードウェア割り込み（ベクタ番号）
+    Irq = 4,
+    /// syscall の完了（番号, 戻り値, 所要時間 ns）
+    Syscall = 5,
+}
+
+const NAMES: [&str; 6] = ["sched", "ipc_send", "ipc_recv", "fault", "irq", "syscall"];
+
+/// 捨てた記録の数を知らせる疑似記録の種類（`args[0]` に件数）
+pub const TRACE_LOST: u32 = 0xff;
+
+impl Tracepoint {
+    const fn bit(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+/// 定義済みの種類すべてのマスク
+pub const ALL_EVENTS: u32 = (1 << NAMES.len()) - 1;
+
+/// トレース記録 1 件（ユーザーへはこの配置のまま渡す）
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TraceRecord {
+    /// 起動からのナノ秒
+    pub time_ns: u64,
+    pub pid: u64,
+    pub tid: u64,
+    pub id: u32,
+    pub cpu: u32,
+    pub args: [u64; 3],
+}
+
+struct Ring {
+    records: VecDeque<TraceRecord>,
+    lost: u64,
+}
+
+impl Ring {
+    const fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+            lost: 0,
+        }
+    }
+
+    fn push(&mut self, record: TraceRecord) {
+        // 確保していないリングには積まない（割り込みの中で確保しないため）
+        if self.records.capacity() == 0 {
+            return;
+        }
+        if self.records.len() >= RING_CAPACITY {
+            self.records.pop_front();
+            self.lost += 1;
+        }
+        self.records.push_back(record);
+    }
+}
+
+static ENABLED: AtomicU32 = AtomicU32::new(0);
+static RINGS: [SpinLock<Ring>; MAX_CPUS] =
+    [const { SpinLock::named("TRACE_RINGS", Ring::new()) }; MAX_CPUS];
+
+/// トレースポイントを通過した
+#[inline]
+pub fn emit(tp: Tracepoint, args: [u64; 3]) {
+    if ENABLED.load(Ordering::Relaxed) & tp.bit() != 0 {
+        record(tp as u32, args);
+    }
+}
+
+#[inline(never)]
+fn record(id: u32, args: [u64; 3]) {
+    let cpu = crate::percpu::current_cpu_id();
+    let record = TraceRecord {
+        time_ns: crate::interrupt::timer::now_ns(),
+        pid: crate::percpu::current_process_raw_id(),
+        tid: crate::percpu::current_thread_raw_id(),
+        id,
+        cpu: cpu as u32,
+        args,
+    };
+    // 同じ CPU でリングを操作している最中（読み出しなど）に来たものは捨てる
+    if let Some(mut ring) = RINGS[cpu].try_lock() {
+        ring.push(record);
+    }
+}
+
+/// syscall の入口で呼ぶ（syscall トレースポイントが無効なら 0）
+#[inline]
+pub fn syscall_start() -> u64 {
+    if ENABLED.load(Ordering::Relaxed) & Tracepoint::Syscall.bit() != 0 {
+        crate::cpu::rdtsc()
+    } else {
+        0
+    }
+}
+
+/// syscall の出口で呼ぶ（`start` は `syscall_start` の戻り値）
+#[inline]
+pub fn syscall_done(nr: u64, ret: u64, start: u64) {
+    if start == 0 {
+        return;
+    }
+    let elapsed = crate::cpu::rdtsc().saturating_sub(start);
+    let ns = crate::interrupt::timer::tsc_delta_to_ns(elapsed).unwrap_or(0);
+    emit(Tracepoint::Syscall, [nr, ret, ns]);
+}
+
+/// 有効にする種類を設定し、それまでのマスクを返す
+///
+/// 有効にする前に、起動済みの CPU のリングを確保しておく。
+pub fn set_events(mask: u32) -> u32 {
+    let mask = mask & ALL_EVENTS;
+    if mask != 0 {
+        for cpu in crate::percpu::online_cpus() {
+            if RINGS[cpu].lock().records.capacity() != 0 {
+                continue;
+            }
+            let mut records = VecDeque::with_capacity(RING_CAPACITY);
+            let mut ring = RINGS[cpu].lock();
+            if ring.records.capacity() == 0 {
+                core::mem::swap(&mut ring.records, &mut records);
+            }
+        }
+    }
+    ENABLED.swap(mask, Ordering::Relaxed)
+}
+
+/// 有効になっている種類のマスク
+pub fn events() -> u32 {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 溜まった記録を CPU ごとに古い順で最大 `max` 件取り出す
+///
+/// 捨てた記録があれば、その CPU の先頭に `TRACE_LOST` の記録を 1 件入れる。
+pub fn drain(max: usize) -> Vec<TraceRecord> {
+    // リングのロック中に伸ばさないよう先に確保しておく
+    let mut out = Vec::with_capacity(max);
+    for cpu in crate::percpu::online_cpus() {
+        if out.len() >= max {
+            break;
+        }
+        let mut ring = RINGS[cpu].lock();
+        if ring.lost != 0 {
+            out.push(TraceRecord {
+                time_ns: 0,
+                pid: 0,
+                tid: 0,
+                id: TRACE_LOST,
+                cpu: cpu as u32,
+                args: [ring.lost, 0, 0],
+            });
+            ring.lost = 0;
+        }
+        while out.len() < max {
+            match ring.records.pop_front() {
+                Some(record) => out.push(record),
+                None => break,
+            }
+        }
+    }
+    out
+}
+
+/// 種類の名前から番号へ
+pub fn parse_event(name: &str) -> Option<u32> {
+    NAMES.iter().position(|n| *n == name).map(|i| i as u32)
+}
+
+/// 起動オプションの `trace=` と `profile` を反映する（kinit の最後で呼ばれる）
+pub fn init() {
+    if let Some(value) = crate::init::cmdline::get("trace") {
+        let mask = value.split(',').fold(0, |mask, name| match name {
+            "all" => ALL_EVENTS,
+            _ => match parse_event(name) {
+                Some(id) => mask | (1 << id),
+                None => {
+                    crate::warn!("trace: unknown tracepoint '{}'", name);
+                    mask
+                }
+            },
+        });
+        set_events(mask);
+        crate::info!("trace: tracepoints enabled (mask={:#x})", mask);
+    }
+    if let Some(value) = crate::init::cmdline::get("profile") {
+        let interval = value.parse().unwrap_or(1);
+        profile::start(interval);
+        crate::info!("trace: profiler started (every {} tick(s))", interval);
+    }
+}
@@ -0,0 +1,227 @@
+//! サンプリングプロファイラ
+//!
+//! タイマー割り込み（100 Hz）の `interval` 回に 1 回、割り込まれた位置の RIP と
+//! フレームポインタの連鎖を CPU ごとのリングに積む。ユーザーモードならユーザースタックを、
+//! カーネルモードならカーネルスタックをたどる（syscall 中のサンプルはユーザー側の
+//! 呼び出し元までは含まない）。
+//!
+//! `/proc/profile` を開くと溜まったサンプルを取り出してシンボルを引き、flamegraph.pl などが
+//! そのまま読める folded 形式（`プロセス名;外側の関数;...;内側の関数 回数`）にまとめて返す。
+//! カーネルの関数には `_[k]` を付ける。
+
+use crate::backtrace::{self, ksyms, Demangle, UserSymbols};
+use crate::interrupt::spinlock::SpinLock;
+use crate::percpu::MAX_CPUS;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// 1 サンプルでたどるフレーム数の上限
+const MAX_DEPTH: usize = 16;
+/// 1 CPU あたりのリングのサンプル数（100 Hz で 80 秒ぶん）
+const RING_CAPACITY: usize = 8192;
+
+#[derive(Clone, Copy)]
+struct Sample {
+    pid: u64,
+    user: bool,
+    depth: u8,
+    /// 内側（割り込まれた位置）から順に並ぶ
+    frames: [u64; MAX_DEPTH],
+}
+
+struct Ring {
+    samples: VecDeque<Sample>,
+    lost: u64,
+}
+
+impl Ring {
+    const fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            lost: 0,
+        }
+    }
+}
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+/// 何ティックに 1 回サンプルを取るか
+static INTERVAL: AtomicU64 = AtomicU64::new(1);
+static SAMPLES: [SpinLock<Ring>; MAX_CPUS] =
+    [const { SpinLock::named("PROFILE_SAMPLES", Ring::new()) }; MAX_CPUS];
+
+/// プロファイラを動かす（`interval` ティックに 1 回。0 は 1 とみなす）
+pub fn start(interval: u64) {
+    for cpu in crate::percpu::online_cpus() {
+        if SAMPLES[cpu].lock().samples.capacity() != 0 {
+            continue;
+        }
+        let mut samples = VecDeque::with_capacity(RING_CAPACITY);
+        let mut ring = SAMPLES[cpu].lock();
+        if ring.samples.capacity() == 0 {
+            core::mem::swap(&mut ring.samples, &mut samples);
+        }
+    }
+    INTERVAL.store(interval.max(1), Ordering::Relaxed);
+    RUNNING.store(true, Ordering::Release);
+}
+
+/// プロファイラを止める（溜まったサンプルは `/proc/profile` で読める）
+pub fn stop() {
+    RUNNING.store(false, Ordering::Release);
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::Acquire)
+}
+
+/// タイマー割り込みから呼ばれる: 割り込まれた位置を記録する
+///
+/// `rbp` は `backtrace::interrupted_frame_pointer` で読んだ割り込み直前の値。
+pub fn on_tick(ticks: u64, rip: u64, rbp: u64, user: bool) {
+    if !is_running() || !ticks.is_multiple_of(INTERVAL.load(Ordering::Relaxed)) {
+        return;
+    }
+    let pid = crate::percpu::current_process_raw_id();
+    let table = if user {
+        let Some(table) =
+            crate::task::with_process(crate::task::ProcessId::from_u64(pid), |p| p.page_table())
+                .flatten()
+        else {
+            return;
+        };
+        table
+    } else {
+        backtrace::current_table()
+    };
+    let mut sample = Sample {
+        pid,
+        user,
+        depth: 0,
+        frames: [0; MAX_DEPTH],
+    };
+    sample.depth = backtrace::collect(table, rip, rbp, user, &mut sample.frames) as u8;
+
+    let cpu = crate::percpu::current_cpu_id();
+    let Some(mut ring) = SAMPLES[cpu].try_lock() else {
+        return;
+    };
+    if ring.samples.capacity() == 0 {
+        return;
+    }
+    if ring.samples.len() >= RING_CAPACITY {
+        ring.samples.pop_front();
+        ring.lost += 1;
+    }
+    ring.samples.push_back(sample);
+}
+
+/// 溜まったサンプルを取り出す
+fn take_samples() -> (Vec<Sample>, u64) {
+    let mut all = Vec::new();
+    let mut lost = 0;
+    for cpu in crate::percpu::online_cpus() {
+        let mut samples = VecDeque::new();
+        {
+            let mut ring = SAMPLES[cpu].lock();
+            if ring.samples.is_empty() && ring.lost == 0 {
+                continue;
+            }
+            // 空のリングと入れ替え、確保し直しはロックの外で行う
+            core::mem::swap(&mut ring.samples, &mut samples);
+            lost += core::mem::take(&mut ring.lost);
+        }
+        let capacity = samples.capacity();
+        all.extend(samples.drain(..));
+        let mut ring = SAMPLES[cpu].lock();
+        if ring.samples.capacity() == 0 && capacity != 0 {
+            core::mem::swap(&mut ring.samples, &mut samples);
+        }
+    }
+    (all, lost)
+}
+
+/// プロセスごとの名前とシンボル表（終了していれば名前は `pid N`）
+struct ProcessInfo {
+    name: String,
+    symbols: Option<Arc<UserSymbols>>,
+}
+
+fn process_info(pid: u64) -> ProcessInfo {
+    crate::task::with_process(crate::task::ProcessId::from_u64(pid), |p| ProcessInfo {
+        name: String::from(p.name()),
+        symbols: p.symbols(),
+    })
+    .unwrap_or_else(|| ProcessInfo {
+        name: alloc::format!("pid {}", pid),
+        symbols: None,
+    })
+}
+
+/// folded 形式の 1 フレーム（`;` は区切りなので置き換える）
+fn push_frame(line: &mut String, name: Option<&str>, addr: u64, kernel: bool) {
+    line.push(';');
+    let start = line.len();
+    match name {
+        Some(name) => {
+            let _ = write!(line, "{}", Demangle(name));
+        }
+        None => {
+            let _ = write!(line, "{:#x}", addr);
+        }
+    }
+    if line[start..].contains(';') {
+        let frame = line.split_off(start).replace(';', ":");
+        line.push_str(&frame);
+    }
+    if kernel {
+        line.push_str("_[k]");
+    }
+}
+
+/// 溜まったサンプルを取り出し、folded 形式の文字列にまとめる
+pub fn render_folded() -> String {
+    let (samples, lost) = take_samples();
+
+    // 同じスタックをまとめてからシンボルを引く
+    let mut stacks: BTreeMap<(u64, bool, Vec<u64>), u64> = BTreeMap::new();
+    for sample in &samples {
+        let frames = sample.frames[..sample.depth as usize].to_vec();
+        *stacks.entry((sample.pid, sample.user, frames)).or_insert(0) += 1;
+    }
+
+    let mut processes: BTreeMap<u64, ProcessInfo> = BTreeMap::new();
+    let mut folded: BTreeMap<String, u64> = BTreeMap::new();
+    for ((pid, user, frames), count) in stacks {
+        let info = processes.entry(pid).or_insert_with(|| process_info(pid));
+        let mut line = info.name.replace(';', ":");
+        // 外側から内側へ。#0 以外は戻りアドレスなので 1 つ手前で引く
+        for (depth, &addr) in frames.iter().enumerate().rev() {
+            let probe = if depth == 0 {
+                addr
+            } else {
+                addr.wrapping_sub(1)
+            };
+            if user {
+                let symbol = info.symbols.as_deref().and_then(|s| s.lookup(probe));
+                push_frame(&mut line, symbol.map(|s| s.name), addr, false);
+            } else {
+                let symbol = ksyms::lookup(probe);
+                push_frame(&mut line, symbol.map(|s| s.name), addr, true);
+            }
+        }
+        *folded.entry(line).or_insert(0) += count;
+    }
+
+    let mut out = String::new();
+    for (line, count) in folded {
+        let _ = writeln!(out, "{} {}", line, count);
+    }
+    if lost != 0 {
+        let _ = writeln!(out, "[lost] {}", lost);
+    }
+    out
+}
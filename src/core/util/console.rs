@@ -39,7 +39,7 @@ pub struct SerialPort {
 
 impl SerialPort {
     /// 新しいシリアルポートを作成
-    const fn new(base: u16) -> Self {
+    pub const fn new(base: u16) -> Self {
         Self {
             data: Port::new(base),
             int_en: Port::new(base + 1),
@@ -78,6 +78,17 @@ impl SerialPort {
         }
     }
 
+    /// 受信データがあれば 1 バイト受け取る
+    pub fn try_receive_byte(&mut self) -> Option<u8> {
+        unsafe {
+            if self.line_status.read() & 0x01 != 0 {
+                Some(self.data.read())
+            } else {
+                None
+            }
+        }
+    }
+
     /// 文字列を送信
     pub fn send_str(&mut self, s: &str) {
         for byte in s.bytes() {
@@ -93,6 +104,14 @@ impl fmt::Write for SerialPort {
     }
 }
 
+/// シリアルのロックを強制的に外す
+///
+/// # Safety
+/// ロックを持っている側が二度と戻らない場合（ハードロックアップの報告）にだけ呼ぶこと。
+pub unsafe fn force_unlock() {
+    SERIAL.force_unlock();
+}
+
 /// シリアルポートを初期化
 pub fn init() {
     lock_serial(|serial| serial.init());
@@ -0,0 +1,228 @@
+//! カーネルログのリングバッファ
+//!
+//! `util::log` が出力したメッセージを、レベル・ティック・CPU・PID 付きで固定長スロットの
+//! リングに残す。ユーザーランドからは syslog システムコールと `/dev/kmsg` で読む（`dmesg`）。
+//!
+//! 例外ハンドラやパニックの中からも書かれるのでヒープは使わず、ロックが取れない
+//! （ログ出力中に多重に入った）場合はそのメッセージを諦める。
+//!
+//! 読み出し形式は 1 メッセージ 1 行で、`/dev/kmsg` に倣う。
+//!
+//! ```text
+//! レベル,通し番号,ティック,CPU,PID;本文
+//! ```
+//!
+//! レベルは 0=Trace .. 4=Error、PID 0 はプロセス外（起動処理や割り込み）。
+//! 本文中の改行は「改行 + 空白」にして、続きの行であることが分かるようにする。
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use super::log::{level_as_u8, LogLevel};
+use crate::interrupt::spinlock::SpinLock;
+
+/// 保持するメッセージ数
+pub const KMSG_CAPACITY: usize = 512;
+/// 1 メッセージの最大長（超えた分は切り捨てる）
+pub const KMSG_MSG_LEN: usize = 224;
+
+#[derive(Clone, Copy)]
+struct KmsgRecord {
+    seq: u64,
+    ticks: u64,
+    pid: u64,
+    cpu: u32,
+    level: LogLevel,
+    len: u16,
+    msg: [u8; KMSG_MSG_LEN],
+}
+
+impl KmsgRecord {
+    const fn empty() -> Self {
+        Self {
+            seq: 0,
+            ticks: 0,
+            pid: 0,
+            cpu: 0,
+            level: LogLevel::Info,
+            len: 0,
+            msg: [0; KMSG_MSG_LEN],
+        }
+    }
+
+    fn message(&self) -> &str {
+        // MsgWriter が文字境界で切っているので常に UTF-8
+        core::str::from_utf8(&self.msg[..self.len as usize]).unwrap_or("<invalid-kmsg-utf8>")
+    }
+
+    /// 読み出し形式の 1 行を書く
+    fn write_line(&self, out: &mut String) {
+        let _ = write!(
+            out,
+            "{},{},{},{},{};",
+            level_as_u8(self.level),
+            self.seq,
+            self.ticks,
+            self.cpu,
+            self.pid
+        );
+        for line in self.message().split_inclusive('\n') {
+            out.push_str(line);
+            if line.ends_with('\n') {
+                out.push(' ');
+            }
+        }
+        out.push('\n');
+    }
+}
+
+struct Ring {
+    records: [KmsgRecord; KMSG_CAPACITY],
+    /// 次に書くメッセージの通し番号
+    next_seq: u64,
+    /// これより前のメッセージは消去済み（clear）
+    first_seq: u64,
+    /// 破壊的な読み出し（SYSLOG_ACTION_READ）の位置
+    read_seq: u64,
+}
+
+impl Ring {
+    const fn new() -> Self {
+        Self {
+            records: [KmsgRecord::empty(); KMSG_CAPACITY],
+            next_seq: 1,
+            first_seq: 1,
+            read_seq: 1,
+        }
+    }
+
+    /// まだ残っている最古の通し番号（`from` 以降）
+    fn oldest_from(&self, from: u64) -> u64 {
+        from.max(self.first_seq)
+            .max(self.next_seq.saturating_sub(KMSG_CAPACITY as u64))
+    }
+
+    fn get(&self, seq: u64) -> Option<&KmsgRecord> {
+        let rec = &self.records[(seq as usize) % KMSG_CAPACITY];
+        (rec.seq == seq).then_some(rec)
+    }
+}
+
+static KMSG: SpinLock<Ring> = SpinLock::named("KMSG", Ring::new());
+
+/// 固定長バッファへの書き込み（溢れた分は文字境界で捨てる）
+struct MsgWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for MsgWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut n = s.len().min(self.buf.len() - self.len);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// メッセージを 1 件記録する（util::log から呼ばれる）
+pub fn push(level: LogLevel, args: fmt::Arguments) {
+    let mut msg = [0u8; KMSG_MSG_LEN];
+    let mut writer = MsgWriter {
+        buf: &mut msg,
+        len: 0,
+    };
+    let _ = writer.write_fmt(args);
+    let len = writer.len;
+    let ticks = crate::interrupt::timer::get_ticks();
+    let pid = crate::percpu::current_process_raw_id();
+    let cpu = crate::percpu::current_cpu_id() as u32;
+
+    let Some(mut ring) = KMSG.try_lock() else {
+        return;
+    };
+    let seq = ring.next_seq;
+    ring.next_seq += 1;
+    ring.records[(seq as usize) % KMSG_CAPACITY] = KmsgRecord {
+        seq,
+        ticks,
+        pid,
+        cpu,
+        level,
+        len: len as u16,
+        msg,
+    };
+}
+
+/// `from` 以降に残っているメッセージを写し取る（ロック中は整形しない）
+fn snapshot(from: impl FnOnce(&Ring) -> u64) -> Vec<KmsgRecord> {
+    let mut out = Vec::with_capacity(KMSG_CAPACITY);
+    let ring = KMSG.lock();
+    let start = ring.oldest_from(from(&ring));
+    for seq in start..ring.next_seq {
+        if let Some(rec) = ring.get(seq) {
+            out.push(*rec);
+        }
+    }
+    out
+}
+
+/// 残っている全メッセージ（SYSLOG_ACTION_READ_ALL と `/dev/kmsg`）
+pub fn read_all() -> String {
+    let mut out = String::new();
+    for rec in snapshot(|ring| ring.first_seq) {
+        rec.write_line(&mut out);
+    }
+    out
+}
+
+/// 未読のメッセージを最大 `max` バイト分読み、既読にする（SYSLOG_ACTION_READ）
+///
+/// 行の途中では切らない。最初の 1 行が `max` に収まらなければ何も返さない。
+pub fn read_unread(max: usize) -> String {
+    let mut out = String::new();
+    let mut line = String::new();
+    let mut consumed = None;
+    for rec in snapshot(|ring| ring.read_seq) {
+        line.clear();
+        rec.write_line(&mut line);
+        if out.len() + line.len() > max {
+            break;
+        }
+        out.push_str(&line);
+        consumed = Some(rec.seq);
+    }
+    if let Some(seq) = consumed {
+        let mut ring = KMSG.lock();
+        ring.read_seq = ring.read_seq.max(seq + 1);
+    }
+    out
+}
+
+/// 未読メッセージの大きさ（SYSLOG_ACTION_SIZE_UNREAD）
+pub fn unread_bytes() -> usize {
+    let mut line = String::new();
+    let mut total = 0;
+    for rec in snapshot(|ring| ring.read_seq) {
+        line.clear();
+        rec.write_line(&mut line);
+        total += line.len();
+    }
+    total
+}
+
+/// リングバッファの大きさ（SYSLOG_ACTION_SIZE_BUFFER）
+pub fn capacity_bytes() -> usize {
+    KMSG_CAPACITY * KMSG_MSG_LEN
+}
+
+/// 記録済みのメッセージを全て消す（通し番号は続きから振る）
+pub fn clear() {
+    let mut ring = KMSG.lock();
+    ring.first_seq = ring.next_seq;
+    ring.read_seq = ring.read_seq.max(ring.first_seq);
+}
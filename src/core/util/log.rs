@@ -1,6 +1,6 @@
 //! ロギングユーティリティ
 
-use core::sync::atomic::{AtomicU8, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 
 /// ログレベル
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -19,6 +19,8 @@ pub enum LogLevel {
 
 /// 現在のログレベル（デフォルト: Info）
 static LOG_LEVEL: AtomicU8 = AtomicU8::new(2);
+/// シリアルへ出力するか（false でもリングバッファには記録する）
+static CONSOLE_ENABLED: AtomicBool = AtomicBool::new(true);
 
 fn level_to_u8(level: LogLevel) -> u8 {
     match level {
@@ -30,21 +32,67 @@ fn level_to_u8(level: LogLevel) -> u8 {
     }
 }
 
+/// 数値（0=Trace .. 4=Error）からログレベルへ
+pub fn level_from_u8(v: u8) -> Option<LogLevel> {
+    match v {
+        0 => Some(LogLevel::Trace),
+        1 => Some(LogLevel::Debug),
+        2 => Some(LogLevel::Info),
+        3 => Some(LogLevel::Warn),
+        4 => Some(LogLevel::Error),
+        _ => None,
+    }
+}
+
+/// 名前（`trace` .. `error`）か数値からログレベルへ（起動オプション `loglevel=` 用）
+pub fn level_from_name(name: &str) -> Option<LogLevel> {
+    match name {
+        "trace" => Some(LogLevel::Trace),
+        "debug" => Some(LogLevel::Debug),
+        "info" => Some(LogLevel::Info),
+        "warn" => Some(LogLevel::Warn),
+        "err" | "error" => Some(LogLevel::Error),
+        _ => name.parse().ok().and_then(level_from_u8),
+    }
+}
+
+/// ログレベルを数値（0=Trace .. 4=Error）にする
+pub fn level_as_u8(level: LogLevel) -> u8 {
+    level_to_u8(level)
+}
+
 fn should_log(level: LogLevel) -> bool {
     level_to_u8(level) >= LOG_LEVEL.load(Ordering::Relaxed)
 }
 
 /// ログレベルを設定
+///
+/// これより低いレベルのメッセージは出力せず、リングバッファにも残さない。
 pub fn set_level(level: LogLevel) {
     LOG_LEVEL.store(level_to_u8(level), Ordering::Relaxed);
 }
 
-/// ログ出力（シリアルとVGAの両方）
+/// 現在のログレベル
+pub fn level() -> LogLevel {
+    level_from_u8(LOG_LEVEL.load(Ordering::Relaxed)).unwrap_or(LogLevel::Info)
+}
+
+/// シリアルへの出力を有効・無効にする（syslog の CONSOLE_ON / CONSOLE_OFF）
+pub fn set_console_enabled(enabled: bool) {
+    CONSOLE_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// ログ出力（シリアルとカーネルログのリングバッファ）
 pub fn log(level: LogLevel, args: core::fmt::Arguments) {
     if !should_log(level) {
         return;
     }
 
+    super::kmsg::push(level, args);
+    if !CONSOLE_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
     use crate::{sprint, sprintln, vprint, vprintln};
 
     let prefix = match level {
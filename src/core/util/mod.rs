@@ -1,5 +1,6 @@
 pub mod console;
 pub mod fifo;
+pub mod kmsg;
 pub mod log;
 pub mod ps2kbd;
 pub mod ps2mouse;
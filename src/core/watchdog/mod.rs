@@ -0,0 +1,409 @@
+//! カーネルウォッチドッグ
+//!
+//! core.service と登録されたプロセスがハートビート（watchdog システムコール）を期限内に
+//! 送っているかを専用のカーネルスレッドで見張る。core.service が無応答になっても、
+//! 終了してしまっても、ここで復旧動作が走る。期限を過ぎたら登録時に決めた動作を取る。
+//!
+//! - `Restart`: プロセスを SIGKILL で止める。core.service ならカーネルが起動し直し、
+//!   ほかのプロセスは core.service が起動し直す
+//! - `Dump`: スレッドの状態を監査ログに、カーネルのスタックトレースをカーネルログに残す
+//! - `SafeMode`: 縮退モードに入ってから `Restart` と同じことをする。縮退モードの
+//!   core.service は重要サービスだけを起動する
+//!
+//! core.service は起動直後にカーネルが登録する（ユーザーからは外せない）。期限と動作は
+//! 起動オプション `watchdog=<秒>|off` と `watchdog_action=restart|dump|safe` で変えられる。
+//! core.service の再起動が `RESTART_LIMIT` 回を超えたら縮退モードへ移り、縮退モードで
+//! さらに止まった場合は再起動を繰り返さず `Dump` だけにする。
+//!
+//! カーネル自身のハングは `nmi` が検出する。
+
+pub mod nmi;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::audit::{self, AuditEventKind};
+use crate::interrupt::spinlock::SpinLock;
+use crate::interrupt::timer;
+use crate::syscall::{EINVAL, ENOENT, ENOMEM, EPERM};
+use crate::task::{self, ProcessId, ProcessState, ThreadState};
+
+/// 1 秒あたりのティック数
+const TICKS_PER_SEC: u64 = 100;
+/// 期限切れを調べる間隔（ティック）
+const CHECK_INTERVAL: u64 = 50;
+/// core.service の既定の期限（秒）
+const DEFAULT_CORE_TIMEOUT_SECS: u64 = 30;
+/// 登録できる期限の下限（ミリ秒）
+pub const MIN_TIMEOUT_MS: u64 = 100;
+/// core.service を起動し直す回数の上限（これを超えたら縮退モード）
+const RESTART_LIMIT: u32 = 3;
+/// SIGKILL のあと、終了を待つ時間（ティック）
+const KILL_GRACE: u64 = TICKS_PER_SEC;
+/// 同時に見張れるプロセス数
+const MAX_WATCHED: usize = 32;
+
+const WATCHDOG_STACK_SIZE: usize = 4096 * 8;
+
+/// 期限切れのときに取る動作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Restart = 0,
+    Dump = 1,
+    SafeMode = 2,
+}
+
+impl Action {
+    /// watchdog システムコールで使う番号から
+    pub fn from_u64(v: u64) -> Option<Self> {
+        match v {
+            0 => Some(Self::Restart),
+            1 => Some(Self::Dump),
+            2 => Some(Self::SafeMode),
+            _ => None,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "restart" => Some(Self::Restart),
+            "dump" => Some(Self::Dump),
+            "safe" => Some(Self::SafeMode),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    pid: ProcessId,
+    /// 期限（ティック）
+    timeout: u64,
+    last_beat: u64,
+    action: Action,
+    /// カーネルが登録した core.service（ユーザーからは外せない）
+    pinned: bool,
+    /// 期限切れを処理済み（次のハートビートまで繰り返さない）
+    fired: bool,
+}
+
+static WATCHED: SpinLock<[Option<Entry>; MAX_WATCHED]> =
+    SpinLock::named("WATCHDOG", [None; MAX_WATCHED]);
+/// 縮退モード中か
+static SAFE_MODE: AtomicBool = AtomicBool::new(false);
+/// core.service を起動し直した回数
+static CORE_RESTARTS: AtomicU32 = AtomicU32::new(0);
+
+#[repr(align(16))]
+struct WatchdogStack([u8; WATCHDOG_STACK_SIZE]);
+
+static mut WATCHDOG_STACK: WatchdogStack = WatchdogStack([0; WATCHDOG_STACK_SIZE]);
+
+/// 縮退モード中か
+pub fn safe_mode() -> bool {
+    SAFE_MODE.load(Ordering::Acquire)
+}
+
+/// core.service を起動し直した回数
+pub fn core_restarts() -> u32 {
+    CORE_RESTARTS.load(Ordering::Relaxed)
+}
+
+fn enter_safe_mode(reason: &str) {
+    if !SAFE_MODE.swap(true, Ordering::AcqRel) {
+        audit::log(
+            AuditEventKind::Policy,
+            &alloc::format!("watchdog: entering safe mode ({})", reason),
+        );
+    }
+}
+
+/// `pid` を見張る（登録済みなら期限と動作を置き換える）
+pub fn register(pid: ProcessId, timeout_ms: u64, action: Action) -> Result<(), u64> {
+    insert(pid, timeout_ms, action, false)
+}
+
+fn insert(pid: ProcessId, timeout_ms: u64, action: Action, pinned: bool) -> Result<(), u64> {
+    if timeout_ms < MIN_TIMEOUT_MS {
+        return Err(EINVAL);
+    }
+    let timeout = timeout_ms.div_ceil(1000 / TICKS_PER_SEC);
+    let entry = Entry {
+        pid,
+        timeout,
+        last_beat: timer::get_ticks(),
+        action,
+        pinned,
+        fired: false,
+    };
+    let mut watched = WATCHED.lock();
+    if let Some(slot) = watched.iter_mut().flatten().find(|e| e.pid == pid) {
+        if slot.pinned && !pinned {
+            return Err(EPERM);
+        }
+        *slot = entry;
+        return Ok(());
+    }
+    let slot = watched.iter_mut().find(|s| s.is_none()).ok_or(ENOMEM)?;
+    *slot = Some(entry);
+    Ok(())
+}
+
+/// 見張りをやめる（カーネルが登録したものは外せない）
+pub fn unregister(pid: ProcessId) -> Result<(), u64> {
+    let mut watched = WATCHED.lock();
+    let slot = watched
+        .iter_mut()
+        .find(|s| s.is_some_and(|e| e.pid == pid))
+        .ok_or(ENOENT)?;
+    if slot.is_some_and(|e| e.pinned) {
+        return Err(EPERM);
+    }
+    *slot = None;
+    Ok(())
+}
+
+/// `pid` のハートビートを受け取る
+pub fn heartbeat(pid: ProcessId) -> Result<(), u64> {
+    let mut watched = WATCHED.lock();
+    let entry = watched
+        .iter_mut()
+        .flatten()
+        .find(|e| e.pid == pid)
+        .ok_or(ENOENT)?;
+    entry.last_beat = timer::get_ticks();
+    entry.fired = false;
+    Ok(())
+}
+
+fn is_alive(pid: ProcessId) -> bool {
+    task::with_process(pid, |p| {
+        !matches!(p.state(), ProcessState::Zombie | ProcessState::Terminated)
+    })
+    .unwrap_or(false)
+}
+
+fn process_name(pid: ProcessId) -> String {
+    task::with_process(pid, |p| String::from(p.name())).unwrap_or_else(|| String::from("?"))
+}
+
+/// core.service を登録し、見張りのカーネルスレッドを起こす（kernel_main から呼ばれる）
+pub fn start(core_pid: u64) {
+    let timeout_secs = match crate::init::cmdline::get("watchdog") {
+        Some("off") => {
+            crate::info!("watchdog: disabled by watchdog=off");
+            return;
+        }
+        Some(value) => value.parse().unwrap_or(DEFAULT_CORE_TIMEOUT_SECS),
+        None => DEFAULT_CORE_TIMEOUT_SECS,
+    };
+    let action = crate::init::cmdline::get("watchdog_action")
+        .and_then(Action::from_name)
+        .unwrap_or(Action::Restart);
+
+    if core_pid != 0 {
+        let pid = ProcessId::from_u64(core_pid);
+        if let Err(errno) = insert(pid, timeout_secs.saturating_mul(1000), action, true) {
+            crate::warn!(
+                "watchdog: failed to watch core.service (errno={:#x})",
+                errno
+            );
+        }
+    }
+
+    let Some(kernel_pid) =
+        task::current_thread_id().and_then(|tid| task::with_thread(tid, |t| t.process_id()))
+    else {
+        crate::warn!("watchdog: no kernel process to attach the watchdog thread");
+        return;
+    };
+    let stack_addr = unsafe { (&raw const WATCHDOG_STACK as *const u8) as u64 };
+    let thread = task::Thread::new(
+        kernel_pid,
+        "watchdog",
+        watchdog_main,
+        stack_addr,
+        WATCHDOG_STACK_SIZE,
+    );
+    if task::add_thread(thread).is_none() {
+        crate::warn!("watchdog: failed to start the watchdog thread");
+        return;
+    }
+    crate::info!(
+        "watchdog: watching core.service (timeout {} s, action {:?})",
+        timeout_secs,
+        action
+    );
+}
+
+fn watchdog_main() -> ! {
+    loop {
+        let now = timer::get_ticks();
+        for (entry, alive) in collect_expired(now) {
+            on_expired(entry, alive, now);
+        }
+        crate::syscall::time::sleep_until(now + CHECK_INTERVAL);
+    }
+}
+
+/// 期限を過ぎたもの・終了したものを取り出す（処理はロックの外で行う）
+fn collect_expired(now: u64) -> Vec<(Entry, bool)> {
+    let mut expired = Vec::new();
+    let mut watched = WATCHED.lock();
+    for slot in watched.iter_mut() {
+        let Some(entry) = slot.as_mut() else {
+            continue;
+        };
+        let alive = is_alive(entry.pid);
+        if !alive && !entry.pinned {
+            // 普通に終わったプロセスは見張りをやめるだけ
+            *slot = None;
+            continue;
+        }
+        if entry.fired || (alive && now.saturating_sub(entry.last_beat) < entry.timeout) {
+            continue;
+        }
+        entry.fired = true;
+        expired.push((*entry, alive));
+        if !entry.pinned && entry.action != Action::Dump {
+            // 止めるプロセスは起動し直した側がもう一度登録する
+            *slot = None;
+        }
+    }
+    expired
+}
+
+fn on_expired(entry: Entry, alive: bool, now: u64) {
+    let name = process_name(entry.pid);
+    let silent_ms = now.saturating_sub(entry.last_beat) * (1000 / TICKS_PER_SEC);
+    let what = if alive {
+        alloc::format!("missed heartbeat for {} ms", silent_ms)
+    } else {
+        String::from("exited")
+    };
+    audit::log(
+        AuditEventKind::Fault,
+        &alloc::format!("watchdog: {} (pid={}) {}", name, entry.pid.as_u64(), what),
+    );
+
+    let mut action = entry.action;
+    if entry.pinned && action != Action::Dump {
+        let restarts = CORE_RESTARTS.load(Ordering::Relaxed);
+        if safe_mode() && restarts > RESTART_LIMIT {
+            // 縮退モードでも止まるなら、再起動を繰り返さず調べられる状態で残す
+            action = Action::Dump;
+        } else if restarts >= RESTART_LIMIT {
+            enter_safe_mode("core.service restart limit reached");
+        }
+    }
+    if action == Action::SafeMode {
+        enter_safe_mode("watchdog action");
+    }
+
+    match action {
+        Action::Dump => dump(entry.pid, &name),
+        Action::Restart | Action::SafeMode => {
+            if alive {
+                kill(entry.pid);
+            }
+            if entry.pinned {
+                restart_core(entry);
+            }
+        }
+    }
+}
+
+fn kill(pid: ProcessId) {
+    let _ = crate::syscall::signal::deliver_signal_to_pid(pid, task::SIGKILL);
+    let deadline = timer::get_ticks() + KILL_GRACE;
+    while is_alive(pid) && timer::get_ticks() < deadline {
+        crate::syscall::time::sleep_until(timer::get_ticks() + 1);
+    }
+    if is_alive(pid) {
+        crate::warn!("watchdog: pid={} did not exit after SIGKILL", pid.as_u64());
+    }
+}
+
+/// core.service を起動し直し、見張りを新しい PID へ移す
+fn restart_core(old: Entry) {
+    let count = CORE_RESTARTS.fetch_add(1, Ordering::Relaxed) + 1;
+    crate::syscall::exec::release_service_manager_pid(old.pid.as_u64());
+    let new_pid = crate::syscall::exec::exec_kernel_with_name("core.service", "core.service");
+    if (new_pid as i64) <= 0 || !is_alive(ProcessId::from_u64(new_pid)) {
+        audit::log(
+            AuditEventKind::Restart,
+            &alloc::format!(
+                "watchdog: failed to restart core.service (ret={:#x})",
+                new_pid
+            ),
+        );
+        return;
+    }
+    crate::syscall::exec::register_service_manager_pid(new_pid);
+    {
+        let mut watched = WATCHED.lock();
+        if let Some(entry) = watched.iter_mut().flatten().find(|e| e.pid == old.pid) {
+            entry.pid = ProcessId::from_u64(new_pid);
+            entry.last_beat = timer::get_ticks();
+            entry.fired = false;
+        }
+    }
+    audit::log(
+        AuditEventKind::Restart,
+        &alloc::format!(
+            "watchdog: restarted core.service as pid={} (restart #{}{})",
+            new_pid,
+            count,
+            if safe_mode() { ", safe mode" } else { "" }
+        ),
+    );
+}
+
+/// スレッドごとの状態を監査ログに、止まっている位置をカーネルログに残す
+fn dump(pid: ProcessId, name: &str) {
+    struct ThreadDump {
+        tid: u64,
+        state: ThreadState,
+        in_syscall: bool,
+        user_rip: u64,
+        rip: u64,
+        rbp: u64,
+    }
+
+    let mut threads = Vec::new();
+    task::for_each_thread(|t| {
+        if t.process_id() == pid {
+            threads.push(ThreadDump {
+                tid: t.id().as_u64(),
+                state: t.state(),
+                in_syscall: t.in_syscall(),
+                user_rip: t.syscall_user_context().0,
+                rip: t.context().rip,
+                rbp: t.context().rbp,
+            });
+        }
+    });
+
+    crate::warn!("watchdog: dumping {} (pid={})", name, pid.as_u64());
+    for t in &threads {
+        let mut line = String::new();
+        let _ = write!(
+            line,
+            "watchdog: {} pid={} tid={} {:?}",
+            name,
+            pid.as_u64(),
+            t.tid,
+            t.state
+        );
+        if t.in_syscall {
+            let _ = write!(line, " in syscall from rip={:#x}", t.user_rip);
+        }
+        audit::log(AuditEventKind::Fault, &line);
+        // 実行中のスレッドの保存済みコンテキストは古いので、止まっているものだけ辿る
+        if t.state != ThreadState::Running && t.rip != 0 {
+            crate::backtrace::print_kernel(t.rip, t.rbp);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! NMI によるハードロックアップ検出
+//!
+//! 性能カウンタ 0 で unhalted core cycles を数え、溢れるたびに LAPIC から NMI を受ける。
+//! その間にタイマー割り込みが 1 度も進んでいなければ、割り込みを止めたままカーネルが
+//! 回り続けているとみなし、`HARD_LOCKUP_NS` を超えたら割り込まれた位置のスタックトレースを
+//! 出してクラッシュログに残し、停止する。
+//!
+//! 性能カウンタの無い環境（QEMU の TCG など）と kgdb 有効時（デバッガで止めている間も
+//! タイマーは進まない）では動かさない。起動オプション `nmi_watchdog=off` でも止められる。
+
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use x86_64::structures::idt::InterruptStackFrame;
+use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame, Size4KiB};
+use x86_64::{PhysAddr, PrivilegeLevel, VirtAddr};
+
+use crate::interrupt::timer;
+
+const IA32_APIC_BASE: u32 = 0x1B;
+const IA32_PMC0: u32 = 0xC1;
+const IA32_PERFEVTSEL0: u32 = 0x186;
+const IA32_PERF_GLOBAL_STATUS: u32 = 0x38E;
+const IA32_PERF_GLOBAL_CTRL: u32 = 0x38F;
+const IA32_PERF_GLOBAL_OVF_CTRL: u32 = 0x390;
+
+/// IA32_APIC_BASE: APIC が有効
+const APIC_GLOBAL_ENABLE: u64 = 1 << 11;
+/// IA32_APIC_BASE: x2APIC モード（MMIO ではレジスタに触れない）
+const APIC_X2APIC_ENABLE: u64 = 1 << 10;
+/// スプリアス割り込みベクタレジスタ（bit 8 がソフトウェア有効）
+const LAPIC_SVR: u64 = 0xF0;
+/// LVT 性能カウンタレジスタ
+const LAPIC_LVT_PERF: u64 = 0x340;
+/// LVT の配送モード: NMI
+const LVT_DELIVERY_NMI: u32 = 0b100 << 8;
+
+/// unhalted core cycles（イベント 3Ch, umask 00h）を OS/USR とも数え、溢れたら割り込む
+const EVTSEL_CYCLES: u64 = 0x3C | (1 << 16) | (1 << 17) | (1 << 20) | (1 << 22);
+/// NMI の間隔（サイクル）。PMC への書き込みは下位 32 bit の符号拡張なので 2^31 未満にする
+const PERIOD: u64 = 0x7FFF_FFFF;
+/// タイマーが進まないままこれだけ経てばハードロックアップとみなす
+const HARD_LOCKUP_NS: u64 = 10_000_000_000;
+
+static ARMED: AtomicBool = AtomicBool::new(false);
+static PERFMON_VERSION: AtomicU32 = AtomicU32::new(0);
+/// LAPIC レジスタの仮想アドレス
+static LAPIC_BASE: AtomicU64 = AtomicU64::new(0);
+/// 直前の NMI で見たタイマーティック
+static LAST_TICKS: AtomicU64 = AtomicU64::new(0);
+/// タイマーティックが最後に進んだのを見た時点の TSC
+static STALL_SINCE: AtomicU64 = AtomicU64::new(0);
+
+fn rdmsr(msr: u32) -> u64 {
+    let lo: u32;
+    let hi: u32;
+    // SAFETY: 呼び出し元は CPUID で存在を確かめた MSR だけを読む
+    unsafe {
+        core::arch::asm!("rdmsr", in("ecx") msr, out("eax") lo, out("edx") hi, options(nomem, nostack));
+    }
+    ((hi as u64) << 32) | lo as u64
+}
+
+unsafe fn wrmsr(msr: u32, value: u64) {
+    core::arch::asm!(
+        "wrmsr",
+        in("ecx") msr,
+        in("eax") value as u32,
+        in("edx") (value >> 32) as u32,
+        options(nomem, nostack)
+    );
+}
+
+fn lapic_read(offset: u64) -> u32 {
+    // SAFETY: LAPIC_BASE は init() でカーネルのページテーブルにマップ済み
+    unsafe { core::ptr::read_volatile((LAPIC_BASE.load(Ordering::Relaxed) + offset) as *const u32) }
+}
+
+fn lapic_write(offset: u64, value: u32) {
+    // SAFETY: 同上
+    unsafe {
+        core::ptr::write_volatile(
+            (LAPIC_BASE.load(Ordering::Relaxed) + offset) as *mut u32,
+            value,
+        )
+    }
+}
+
+/// LAPIC のレジスタをカーネルのページテーブルに用意し、仮想アドレスを返す
+fn map_lapic() -> Option<u64> {
+    let apic_base = rdmsr(IA32_APIC_BASE);
+    if apic_base & APIC_GLOBAL_ENABLE == 0 || apic_base & APIC_X2APIC_ENABLE != 0 {
+        return None;
+    }
+    let phys = apic_base & 0x000F_FFFF_FFFF_F000;
+    let virt = phys.checked_add(crate::mem::paging::physical_memory_offset()?)?;
+    if crate::mem::paging::translate_addr(VirtAddr::new(virt)) != Some(PhysAddr::new(phys)) {
+        let page = Page::<Size4KiB>::containing_address(VirtAddr::new(virt));
+        let frame = PhysFrame::<Size4KiB>::containing_address(PhysAddr::new(phys));
+        let flags = PageTableFlags::PRESENT
+            | PageTableFlags::WRITABLE
+            | PageTableFlags::NO_CACHE
+            | PageTableFlags::NO_EXECUTE;
+        crate::mem::paging::map_page(page, frame, flags).ok()?;
+    }
+    Some(virt)
+}
+
+/// 使えればハードロックアップ検出を始める（kinit の最後で呼ばれる）
+pub fn init() {
+    if crate::init::cmdline::get("nmi_watchdog") == Some("off") {
+        crate::info!("nmi watchdog: disabled by nmi_watchdog=off");
+        return;
+    }
+    if crate::kgdb::is_enabled() {
+        crate::info!("nmi watchdog: disabled while kgdb is enabled");
+        return;
+    }
+    let version = crate::cpu::perfmon_version();
+    if version == 0 {
+        crate::info!("nmi watchdog: no usable performance counter, hard lockup detection disabled");
+        return;
+    }
+    let Some(base) = map_lapic() else {
+        crate::warn!("nmi watchdog: local APIC is not available in xAPIC mode");
+        return;
+    };
+    LAPIC_BASE.store(base, Ordering::Relaxed);
+    if lapic_read(LAPIC_SVR) & (1 << 8) == 0 {
+        crate::warn!("nmi watchdog: local APIC is software-disabled");
+        return;
+    }
+
+    PERFMON_VERSION.store(version, Ordering::Relaxed);
+    LAST_TICKS.store(timer::get_ticks(), Ordering::Relaxed);
+    STALL_SINCE.store(crate::cpu::rdtsc(), Ordering::Relaxed);
+    // SAFETY: perfmon_version() が 1 以上なので PMC0 / PERFEVTSEL0 は存在する
+    unsafe {
+        wrmsr(IA32_PERFEVTSEL0, 0);
+        wrmsr(IA32_PMC0, PERIOD.wrapping_neg());
+        if version >= 2 {
+            wrmsr(IA32_PERF_GLOBAL_OVF_CTRL, 1);
+            wrmsr(IA32_PERF_GLOBAL_CTRL, rdmsr(IA32_PERF_GLOBAL_CTRL) | 1);
+        }
+    }
+    // すぐに NMI が来ても自分のものと分かるよう、LVT より先に有効にする
+    ARMED.store(true, Ordering::Release);
+    lapic_write(LAPIC_LVT_PERF, LVT_DELIVERY_NMI);
+    // SAFETY: 同上
+    unsafe { wrmsr(IA32_PERFEVTSEL0, EVTSEL_CYCLES) };
+    crate::info!(
+        "nmi watchdog: hard lockup detection enabled (perfmon v{}, threshold {} s)",
+        version,
+        HARD_LOCKUP_NS / 1_000_000_000
+    );
+}
+
+/// 溢れたのが性能カウンタ 0 か
+fn counter_overflowed() -> bool {
+    if PERFMON_VERSION.load(Ordering::Relaxed) >= 2 {
+        rdmsr(IA32_PERF_GLOBAL_STATUS) & 1 != 0
+    } else {
+        // -PERIOD から数え上げるので、溢れる前は下位 32 bit の最上位が立っている
+        rdmsr(IA32_PMC0) & (1 << 31) == 0
+    }
+}
+
+/// カウンタを巻き戻し、LVT を配送可能に戻す（NMI を配送するたびにマスクされる）
+fn rearm() {
+    // SAFETY: init() で存在を確かめた MSR
+    unsafe {
+        if PERFMON_VERSION.load(Ordering::Relaxed) >= 2 {
+            wrmsr(IA32_PERF_GLOBAL_OVF_CTRL, 1);
+        }
+        wrmsr(IA32_PMC0, PERIOD.wrapping_neg());
+    }
+    // ユーザーのページテーブルには LAPIC が無いので、書く間だけカーネルの CR3 にする
+    let previous = crate::syscall::syscall_entry::switch_to_kernel_page_table();
+    lapic_write(LAPIC_LVT_PERF, LVT_DELIVERY_NMI);
+    crate::syscall::syscall_entry::restore_page_table(previous);
+}
+
+/// NMI ハンドラの先頭から呼ばれる。ハードロックアップ検出の NMI なら true
+///
+/// ロックは取らない（割り込まれた側が持っているかもしれない）。
+pub fn handle(frame: &InterruptStackFrame, rbp: u64) -> bool {
+    if !ARMED.load(Ordering::Acquire) || !counter_overflowed() {
+        return false;
+    }
+    rearm();
+
+    let ticks = timer::get_ticks();
+    let now = crate::cpu::rdtsc();
+    if LAST_TICKS.swap(ticks, Ordering::Relaxed) != ticks {
+        STALL_SINCE.store(now, Ordering::Relaxed);
+        return true;
+    }
+    let stalled = timer::tsc_delta_to_ns(now.saturating_sub(STALL_SINCE.load(Ordering::Relaxed)));
+    if let Some(ns) = stalled.filter(|&ns| ns >= HARD_LOCKUP_NS) {
+        report(frame, rbp, ns);
+    }
+    true
+}
+
+/// 割り込まれた位置を報告して停止する
+fn report(frame: &InterruptStackFrame, rbp: u64, stalled_ns: u64) -> ! {
+    ARMED.store(false, Ordering::Release);
+    // SAFETY: init() で存在を確かめた MSR
+    unsafe { wrmsr(IA32_PERFEVTSEL0, 0) };
+    let _ = crate::syscall::syscall_entry::switch_to_kernel_page_table();
+    // 止まった側が持ったままのロックは二度と外れない
+    // SAFETY: このあとは戻らない
+    unsafe { crate::util::console::force_unlock() };
+
+    let rip = frame.instruction_pointer.as_u64();
+    let in_kernel = frame.code_segment.rpl() == PrivilegeLevel::Ring0;
+    let stalled_ms = stalled_ns / 1_000_000;
+    crate::info!("!!! HARD LOCKUP !!!");
+    crate::error!(
+        "timer interrupts have not run for {} ms (rip={:#x})",
+        stalled_ms,
+        rip
+    );
+    if in_kernel {
+        crate::backtrace::print_kernel(rip, rbp);
+    }
+    crate::crashlog::record(crate::crashlog::CrashKind::HardLockup, |w| {
+        writeln!(w, "timer interrupts have not run for {} ms", stalled_ms)?;
+        if in_kernel {
+            crate::backtrace::write_kernel(w, rip, rbp)
+        } else {
+            writeln!(w, "rip={:#x} (user)", rip)
+        }
+    });
+    crate::warn!("system halted. Please reset.");
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
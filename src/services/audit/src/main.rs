@@ -0,0 +1,148 @@
+use swiftlib::audit::{self, AuditRecord};
+use swiftlib::fs;
+use swiftlib::io;
+use swiftlib::time;
+
+/// 書き出し先（古いものは `.1` .. `.ROTATE_KEEP` に回す）
+const LOG_PATH: &str = "/log/audit.log";
+/// これを超えそうになったらローテーションする
+const MAX_LOG_SIZE: usize = 64 * 1024;
+/// 残す世代数
+const ROTATE_KEEP: usize = 3;
+/// 1 回の audit_read で読む件数
+const BATCH: usize = 32;
+/// 新しい記録が無いときの待ち時間
+const POLL_MS: u64 = 500;
+
+struct AuditLog {
+    /// 書き出し済みの最後の通し番号
+    last_seq: u64,
+    /// LOG_PATH の現在の大きさ
+    size: usize,
+}
+
+impl AuditLog {
+    fn open() -> Self {
+        let size = match fs::read_file_via_fs(LOG_PATH, MAX_LOG_SIZE * 2) {
+            Ok(Some(data)) => data.len(),
+            _ => 0,
+        };
+        Self { last_seq: 0, size }
+    }
+
+    /// 記録を 1 行にする
+    fn format(rec: &AuditRecord) -> String {
+        format!(
+            "[{:>5}.{:02}] #{} {} pid={} tid={} uid={}: {}\n",
+            rec.ticks / 100,
+            rec.ticks % 100,
+            rec.seq,
+            rec.kind_name(),
+            rec.pid,
+            rec.tid,
+            rec.uid,
+            rec.message()
+        )
+    }
+
+    /// 読み出した記録をまとめて書き出す
+    fn persist(&mut self, records: &[AuditRecord]) {
+        let mut text = String::new();
+        for rec in records {
+            // 通し番号が飛んでいたらカーネルのリングから溢れている
+            if self.last_seq != 0 && rec.seq > self.last_seq + 1 {
+                text.push_str(&format!(
+                    "lost {} record(s) before #{}\n",
+                    rec.seq - self.last_seq - 1,
+                    rec.seq
+                ));
+            }
+            text.push_str(&Self::format(rec));
+            self.last_seq = rec.seq;
+        }
+        self.append(&text);
+    }
+
+    fn append(&mut self, text: &str) {
+        if self.size > 0 && self.size + text.len() > MAX_LOG_SIZE {
+            rotate();
+            self.size = 0;
+        }
+        let fd = io::open(LOG_PATH, io::O_WRONLY | io::O_CREAT | io::O_APPEND);
+        if fd < 0 {
+            println!("[AUDIT] Failed to open {}", LOG_PATH);
+            return;
+        }
+        if write_all(fd as u64, text.as_bytes()) {
+            self.size += text.len();
+        } else {
+            println!("[AUDIT] Failed to write {}", LOG_PATH);
+        }
+        let _ = io::close(fd as u64);
+    }
+}
+
+fn write_all(fd: u64, mut data: &[u8]) -> bool {
+    while !data.is_empty() {
+        let n = io::write(fd, data);
+        if (n as i64) <= 0 {
+            return false;
+        }
+        data = &data[(n as usize).min(data.len())..];
+    }
+    true
+}
+
+fn rotated_path(n: usize) -> String {
+    format!("{}.{}", LOG_PATH, n)
+}
+
+/// `from` の中身で `to` を置き換える（fs.service に rename が無いのでコピーする）
+fn copy_file(from: &str, to: &str) {
+    let data = match fs::read_file_via_fs(from, MAX_LOG_SIZE * 2) {
+        Ok(Some(data)) => data,
+        _ => return,
+    };
+    let fd = io::open(to, io::O_WRONLY | io::O_CREAT | io::O_TRUNC);
+    if fd < 0 {
+        println!("[AUDIT] Failed to open {} for rotation", to);
+        return;
+    }
+    if !write_all(fd as u64, &data) {
+        println!("[AUDIT] Failed to write {} for rotation", to);
+    }
+    let _ = io::close(fd as u64);
+}
+
+/// audit.log → audit.log.1 → .. → audit.log.ROTATE_KEEP と世代を送り、audit.log を空にする
+fn rotate() {
+    for n in (1..ROTATE_KEEP).rev() {
+        copy_file(&rotated_path(n), &rotated_path(n + 1));
+    }
+    copy_file(LOG_PATH, &rotated_path(1));
+    let fd = io::open(LOG_PATH, io::O_WRONLY | io::O_CREAT | io::O_TRUNC);
+    if fd >= 0 {
+        let _ = io::close(fd as u64);
+    }
+    println!("[AUDIT] Rotated {}", LOG_PATH);
+}
+
+fn main() {
+    println!("[AUDIT] Audit service started");
+
+    let mut log = AuditLog::open();
+    // カーネルの通し番号は起動ごとに 1 から振り直される
+    log.append("--- boot ---\n");
+
+    let mut records = [AuditRecord::empty(); BATCH];
+    loop {
+        match audit::read(&mut records, log.last_seq, audit::ALL_KINDS) {
+            Ok(0) => time::sleep_ms(POLL_MS),
+            Ok(n) => log.persist(&records[..n]),
+            Err(errno) => {
+                println!("[AUDIT] audit_read failed: errno={}", errno as i64);
+                time::sleep_ms(POLL_MS * 10);
+            }
+        }
+    }
+}
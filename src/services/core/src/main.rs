@@ -1,100 +1,120 @@
+use swiftlib::capability;
 use swiftlib::ipc;
 use swiftlib::process;
+use swiftlib::service;
 use swiftlib::task;
 use swiftlib::time;
+use swiftlib::watchdog;
+
+mod manifest;
+mod supervisor;
+
+use manifest::ServiceManifest;
+use supervisor::Supervisor;
 
 /// READY通知OPコード
 const OP_NOTIFY_READY: u64 = 0xFF;
 
-/// サービス定義
-struct ServiceDef {
-    name: &'static str,
-    path: &'static str,
-}
+/// サービスの出力をカーネルが IPC で転送してくるときの最大の大きさ
+const OUTPUT_CHUNK: usize = 512;
 
-const CRITICAL_SERVICES: &[ServiceDef] = &[];
+/// 監視ループの間隔（終了したサービスに気づくまでの最大の遅れ）
+const MONITOR_INTERVAL_MS: u64 = 200;
 
-const BACKGROUND_SERVICES: &[ServiceDef] = &[
-    ServiceDef { name: "driver.service", path: "/system/services/driver.service" },
-];
+/// 起動中にサービスの READY を待つ間隔
+const BOOT_POLL_INTERVAL_MS: u64 = 10;
 
-#[cfg(feature = "run_tests")]
-const TEST_PATH: &str = "tests";
+const EPERM: i64 = -1;
+const ENOENT: i64 = -2;
+const EIO: i64 = -5;
+/// 同じ名前のサービスが core.service の外で既に動いている
+const EEXIST: i64 = -17;
+const EINVAL: i64 = -22;
 
-fn start_service(service: &ServiceDef) -> Option<u64> {
-    println!("[CORE] Starting service: {}", service.name);
-    match process::exec(service.path) {
-        Ok(pid) => {
-            println!("[CORE] {} started (PID={})", service.name, pid);
-            Some(pid)
-        }
-        Err(_) => {
-            println!("[CORE] Failed to start {}", service.name);
-            None
-        }
+/// テストスイート（run_tests フィーチャ有効時のみ起動する）
+#[cfg(feature = "run_tests")]
+const TEST_PATH: &str = "/applications/tests.app/entry.elf";
+
+/// システムコールファザー（run_fuzzer フィーチャ有効時のみ起動する）
+#[cfg(feature = "run_fuzzer")]
+const FUZZER_PATH: &str = "/applications/fuzzer.app/entry.elf";
+
+/// ファザーに渡す引数（ビルド時の MOCHIOS_FUZZ_ARGS、空白区切り）
+#[cfg(feature = "run_fuzzer")]
+const FUZZER_ARGS: &str = match option_env!("MOCHIOS_FUZZ_ARGS") {
+    Some(args) => args,
+    None => "",
+};
+
+/// QEMU の isa-debug-exit ポート（`scripts/qemu-runner.sh` が付ける）
+#[cfg(any(feature = "run_tests", feature = "run_fuzzer"))]
+const DEBUG_EXIT_PORT: u16 = 0xf4;
+
+/// マニフェストに従ってサービスを起動する
+///
+/// マニフェストに書かれたケーパビリティは exec と同時に与えるので、サービスは最初の
+/// 命令から書かれた権限だけで動く。何も書かれていないサービスはカーネル既定の集合のまま動かす。
+fn start_service(manifest: &ServiceManifest) -> Result<u64, i64> {
+    if !is_allowed_service_path(&manifest.path) {
+        println!(
+            "[CORE] Refusing to start {}: disallowed path {}",
+            manifest.name, manifest.path
+        );
+        return Err(EPERM);
     }
-}
-
-fn start_background_service(service: &ServiceDef) -> Option<u64> {
-    println!("[CORE] Starting background service: {}", service.name);
-    match exec_file_via_fs_service(service.path) {
-        Ok(pid) => {
-            println!("[CORE] {} started (PID={})", service.name, pid);
-            Some(pid)
-        }
-        Err(errno) => {
-            println!("[CORE] exec failed for {}: errno={}, falling back", service.name, errno);
-            start_service(service)
-        }
+    if service_already_running(&manifest.path) {
+        return Err(EEXIST);
     }
-}
-
-fn wait_for_ready(expected_pids: &[u64]) -> bool {
-    let mut pending: Vec<u64> = expected_pids.iter().copied().filter(|pid| *pid != 0).collect();
-
-    if pending.is_empty() {
-        println!("[CORE] WARNING: no critical services to wait for");
-        return true;
+    println!("[CORE] Starting service: {}", manifest.name);
+    let args: Vec<&str> = manifest.args.iter().map(String::as_str).collect();
+    let env: Vec<&str> = manifest.env.iter().map(String::as_str).collect();
+    let fallback = service_name_from_path(&manifest.path);
+    if let Some(caps) = &manifest.capabilities {
+        return process::exec_with_capabilities(&manifest.path, &args, &env, caps)
+            .or_else(|_| process::exec_with_capabilities(fallback, &args, &env, caps));
     }
+    if args.is_empty() && env.is_empty() {
+        exec_file_via_fs_service(&manifest.path)
+    } else {
+        process::exec_with_env(&manifest.path, &args, &env)
+            .or_else(|_| process::exec_with_env(fallback, &args, &env))
+    }
+}
 
-    let total = pending.len();
-    let mut recv_buf = [0u8; 64];
-    let timeout = std::time::Duration::from_secs(20);
-    let start = std::time::Instant::now();
-
-    println!("[CORE] Waiting for {} critical service(s) to be ready...", total);
-
-    while !pending.is_empty() {
-        if start.elapsed() >= timeout {
-            println!("[CORE] ERROR: timed out waiting for critical services");
-            return false;
-        }
+/// 届いている IPC を読み、READY 通知・サービスの出力をスーパーバイザに渡す
+fn drain_messages(supervisor: &mut Supervisor) {
+    let mut recv_buf = [0u8; OUTPUT_CHUNK];
+    loop {
         let (sender, len) = ipc::ipc_recv(&mut recv_buf);
         if sender == 0 && len == 0 {
-            time::sleep_ms(0);
+            return;
+        }
+        if sender == 0 {
             continue;
         }
-
-        if sender != 0 && (len as usize) >= 8 {
-            // OP コードだけ読む
-            let op = u64::from_le_bytes(recv_buf[..8].try_into().unwrap_or([0; 8]));
-            if op == OP_NOTIFY_READY {
-                if let Some(pos) = pending.iter().position(|pid| *pid == sender) {
-                    pending.swap_remove(pos);
-                    let ready_count = total - pending.len();
-                    println!(
-                        "[CORE] Critical service ready (PID={}, {}/{})",
-                        sender, ready_count, total
-                    );
-                    if pending.is_empty() {
-                        return true;
-                    }
-                }
-            }
+        let msg = &recv_buf[..len as usize];
+        let op = msg
+            .get(..8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap_or([0; 8])));
+        if op == Some(OP_NOTIFY_READY) {
+            supervisor.notify_ready(sender);
+            continue;
+        }
+        if !service::is_request(msg) {
+            // 監視しているサービスの標準出力・標準エラー
+            supervisor.capture_output(sender, msg);
+            continue;
         }
+        let Ok((_, caps)) = capability::get_thread_capabilities(sender) else {
+            // 送り主はもう居ないので応答しない
+            continue;
+        };
+        let (status, payload) = match service::decode_request(msg) {
+            Some((op, name)) => supervisor.handle_request(op, name, caps),
+            None => (EINVAL, Vec::new()),
+        };
+        let _ = ipc::ipc_send(sender, &service::encode_response(status, &payload));
     }
-
-    true
 }
 
 fn exec_file_via_fs_service(path: &str) -> Result<u64, i64> {
@@ -115,6 +135,10 @@ fn is_allowed_service_path(path: &str) -> bool {
     if path.is_empty() || path.contains("..") {
         return false;
     }
+    // initfs のサービスは名前だけで指定する
+    if !path.contains('/') {
+        return path.ends_with(".service");
+    }
     path.starts_with("/system/services/")
         || path.starts_with("/bin/")
         || path.starts_with("system/services/")
@@ -128,87 +152,91 @@ fn service_already_running(path: &str) -> bool {
         || task::find_process_by_name(&format!("/system/services/{}", name)).is_some()
 }
 
-fn fs_open_read_lines(path: &str) -> Result<Vec<String>, i64> {
-    match std::fs::read_to_string(path) {
-        Ok(text) => {
-            let mut lines = Vec::new();
-            for line in text.lines() {
-                let line = line.trim();
-                if line.is_empty() || line.starts_with('#') {
-                    continue;
+/// アプリを起動して終了を待ち、結果を isa-debug-exit で QEMU に返す
+///
+/// 成功（終了コード 0）は 0x10、既知の失敗だけが残った場合（終了コード 2）は 0x12、
+/// それ以外の失敗（起動できなかった場合を含む）は 0x11 を書き込む。
+/// QEMU 以外で動いている場合は何も起きず、そのまま監視ループに入る。
+/// 待っている間に届いた IPC（READY 通知・svcctl・サービスの出力）はいつも通り処理する。
+#[cfg(any(feature = "run_tests", feature = "run_fuzzer"))]
+fn run_and_exit_qemu(supervisor: &mut Supervisor, name: &str, path: &str, args: &[&str]) {
+    println!("[CORE] Starting {}...", name);
+    let exit_value = match process::exec_with_args(path, args) {
+        Ok(pid) => {
+            println!("[CORE] {} started (PID={})", name, pid);
+            loop {
+                // アプリ自身の出力は監視しているサービスのものではないので捨てられる。
+                // 子を回収する supervisor.poll() はアプリの終了状態まで奪うので呼ばない
+                drain_messages(supervisor);
+                match task::wait4(pid as i64, task::WNOHANG) {
+                    Ok(result) if result.pid == 0 => {
+                        // 長く走るアプリを待つ間もウォッチドッグに生きていると知らせる
+                        let _ = watchdog::heartbeat();
+                        time::sleep_ms(10);
+                    }
+                    Ok(result) => {
+                        println!("[CORE] {} finished (status={:#x})", name, result.status);
+                        break match (result.exited(), result.exit_status()) {
+                            (true, 0) => 0x10,
+                            (true, 2) => 0x12,
+                            _ => 0x11,
+                        };
+                    }
+                    Err(errno) => {
+                        println!("[CORE] wait for {} failed: errno={}", name, errno);
+                        break 0x11;
+                    }
                 }
-                lines.push(line.to_string());
             }
-            Ok(lines)
         }
-        Err(_) => Err(-2),
-    }
+        Err(_) => {
+            println!("[CORE] Failed to start {}", name);
+            0x11
+        }
+    };
+    swiftlib::port::outl(DEBUG_EXIT_PORT, exit_value);
 }
 
 fn main() {
     println!("[CORE] Service Manager Started");
 
-    let mut critical_pids = [0u64; CRITICAL_SERVICES.len()];
-    for (idx, service) in CRITICAL_SERVICES.iter().enumerate() {
-        let Some(pid) = start_service(service) else {
-            println!(
-                "[CORE] ERROR: failed to start critical service {}, aborting startup",
-                service.name
-            );
-            return;
-        };
-        critical_pids[idx] = pid;
+    // ウォッチドッグが core.service を何度も再起動した後は必須サービスだけで動く
+    let safe_mode = watchdog::status().map(|s| s.safe_mode).unwrap_or(false);
+    if safe_mode {
+        println!("[CORE] Safe mode: starting critical services only");
     }
 
-    if !wait_for_ready(&critical_pids) {
-        println!("[CORE] Critical services readiness failed; aborting startup");
-        return;
-    }
+    let manifests = manifest::load();
+    println!("[CORE] Loaded {} service manifest(s)", manifests.len());
+    let mut supervisor = Supervisor::new(manifests, safe_mode);
 
-    // Try to read /config/services.list and start listed services from rootfs.
-    match fs_open_read_lines("/config/services.list") {
-        Ok(lines) => {
-            println!("[CORE] Found services.list with {} entries", lines.len());
-            for p in lines {
-                if !is_allowed_service_path(&p) {
-                    println!("[CORE] Skipping disallowed service path: {}", p);
-                    continue;
-                }
-                if service_already_running(&p) {
-                    println!(
-                        "[CORE] Skipping {} ({} already running)",
-                        p,
-                        service_name_from_path(&p)
-                    );
-                    continue;
-                }
-                println!("[CORE] Requesting exec for {}", p);
-                match exec_file_via_fs_service(&p) {
-                    Ok(pid) => println!("[CORE] {} started (PID={})", p, pid),
-                    Err(errno) => println!("[CORE] Failed to exec {}: errno={}", p, errno),
-                }
-            }
-        }
-        Err(errno) => {
-            println!("[CORE] No services.list (errno={}), falling back to background list", errno);
-            for service in BACKGROUND_SERVICES {
-                let _ = start_background_service(service);
-            }
+    // 依存関係に従ってすべて起動し終える（または諦める）まで待つ
+    loop {
+        let _ = watchdog::heartbeat();
+        drain_messages(&mut supervisor);
+        supervisor.poll();
+        if !supervisor.booting() {
+            break;
         }
+        time::sleep_ms(BOOT_POLL_INTERVAL_MS);
     }
+    println!("[CORE] Startup complete");
 
     #[cfg(feature = "run_tests")]
+    run_and_exit_qemu(&mut supervisor, "tests", TEST_PATH, &[]);
+
+    #[cfg(feature = "run_fuzzer")]
     {
-        println!("[CORE] Starting test application...");
-        match process::exec(TEST_PATH) {
-            Ok(pid) => println!("[CORE] tests started (PID={})", pid),
-            Err(_) => println!("[CORE] Failed to start tests"),
-        }
-        time::sleep_ms(100);
+        let args: Vec<&str> = FUZZER_ARGS.split_whitespace().collect();
+        run_and_exit_qemu(&mut supervisor, "fuzzer", FUZZER_PATH, &args);
     }
 
     println!("[CORE] Entering monitoring loop...");
     loop {
-        time::sleep_ms(1000);
+        // watchdog=off で登録されていなければ ENOENT になるだけ
+        let _ = watchdog::heartbeat();
+        drain_messages(&mut supervisor);
+        supervisor.poll();
+        time::sleep_ms(MONITOR_INTERVAL_MS);
     }
 }
@@ -0,0 +1,334 @@
+//! サービスマニフェストの読み込み
+//!
+//! ビルド時に rootfs へコピーされた `src/services/index.toml` を読む。書式は
+//! index.toml の先頭のコメントを参照。TOML のうちマニフェストで使う形だけ
+//! （`[core.service.名前]` のセクション、1 行に収まる文字列・整数・真偽値・文字列の配列、
+//! `#` のコメント）を受け付け、読めない行は警告して飛ばす。
+
+use std::time::Duration;
+
+use swiftlib::capability::{self, CapabilitySet, MAX_CAP_RANGES};
+
+use crate::supervisor::RestartPolicy;
+
+/// 実行時に読むマニフェスト
+const MANIFEST_PATH: &str = "/config/services.toml";
+/// rootfs から読めなかったときに使う、ビルド時のマニフェスト
+const BUILTIN_MANIFEST: &str = include_str!("../../index.toml");
+/// svcctl enable/disable で変えた自動起動の設定（1 行に "enable 名前" か "disable 名前"）
+const OVERRIDES_PATH: &str = "/config/services.override";
+
+const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// 準備完了の条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Readiness {
+    /// 起動できた時点で準備完了
+    Started,
+    /// READY 通知 (OP_NOTIFY_READY) が届いたら準備完了
+    Notify,
+}
+
+/// 1 つのサービスのマニフェスト
+#[derive(Debug, Clone)]
+pub struct ServiceManifest {
+    /// "audit.service" の形の名前
+    pub name: String,
+    pub description: String,
+    pub path: String,
+    pub autostart: bool,
+    /// svcctl enable/disable で変えた自動起動の設定（`autostart` より優先する）
+    pub enabled: Option<bool>,
+    pub order: u32,
+    /// 先に準備完了になっている必要があるサービス（"xxx.service" の形）
+    pub after: Vec<String>,
+    pub ready: Readiness,
+    pub ready_timeout: Duration,
+    /// 起動と同時に与えるケーパビリティ（None ならカーネル既定のまま）
+    pub capabilities: Option<CapabilitySet>,
+    pub args: Vec<String>,
+    pub env: Vec<String>,
+    pub policy: RestartPolicy,
+    /// 縮退モードでも起動する
+    pub critical: bool,
+}
+
+/// マニフェストを読み込み、起動順（order）に並べて返す
+pub fn load() -> Vec<ServiceManifest> {
+    let (source, text) = match std::fs::read_to_string(MANIFEST_PATH) {
+        Ok(text) => (MANIFEST_PATH, text),
+        Err(_) => {
+            println!(
+                "[CORE] Cannot read {}, using built-in service manifests",
+                MANIFEST_PATH
+            );
+            ("built-in manifest", BUILTIN_MANIFEST.to_string())
+        }
+    };
+    let mut manifests = parse(source, &text);
+    manifests.sort_by_key(|m| m.order);
+    apply_overrides(&mut manifests);
+    manifests
+}
+
+fn apply_overrides(manifests: &mut [ServiceManifest]) {
+    let Ok(text) = std::fs::read_to_string(OVERRIDES_PATH) else {
+        return;
+    };
+    for line in text.lines() {
+        let enabled = match line.trim().split_once(' ') {
+            Some(("enable", name)) => (name.trim(), true),
+            Some(("disable", name)) => (name.trim(), false),
+            _ => continue,
+        };
+        if let Some(m) = manifests.iter_mut().find(|m| m.name == enabled.0) {
+            m.enabled = Some(enabled.1);
+        }
+    }
+}
+
+/// 自動起動の設定を保存する（svcctl で変えたものだけを書く）
+pub fn save_overrides<'a>(manifests: impl Iterator<Item = &'a ServiceManifest>) -> Result<(), i64> {
+    let mut text = String::new();
+    for m in manifests {
+        if let Some(enabled) = m.enabled {
+            let verb = if enabled { "enable" } else { "disable" };
+            text.push_str(&format!("{} {}\n", verb, m.name));
+        }
+    }
+    std::fs::write(OVERRIDES_PATH, text).map_err(|_| crate::EIO)
+}
+
+/// "audit" と "audit.service" のどちらでも "audit.service" にする
+pub fn service_name(name: &str) -> String {
+    if name.ends_with(".service") {
+        name.to_string()
+    } else {
+        format!("{}.service", name)
+    }
+}
+
+enum Value {
+    Str(String),
+    Int(u64),
+    Bool(bool),
+    List(Vec<String>),
+}
+
+/// 組み立て中のマニフェスト
+struct Entry {
+    manifest: ServiceManifest,
+    fs: String,
+    path: Option<String>,
+    /// capabilities・ports・mmio のどれかが書かれていた
+    restricts_caps: bool,
+    caps: CapabilitySet,
+}
+
+fn parse(source: &str, text: &str) -> Vec<ServiceManifest> {
+    let mut manifests = Vec::new();
+    let mut current: Option<Entry> = None;
+    for (idx, raw) in text.lines().enumerate() {
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if let Some(entry) = current.take() {
+                manifests.push(entry.finish());
+            }
+            // [core.service] は core.service 自身なので読まない
+            current = section.trim().strip_prefix("core.service.").map(Entry::new);
+            continue;
+        }
+        let Some(entry) = current.as_mut() else {
+            continue;
+        };
+        let result = match line.split_once('=') {
+            Some((key, value)) => parse_value(value.trim())
+                .ok_or_else(|| format!("invalid value for '{}'", key.trim()))
+                .and_then(|value| entry.set(key.trim(), value)),
+            None => Err(String::from("expected 'key = value'")),
+        };
+        if let Err(msg) = result {
+            println!("[CORE] {}:{}: {}", source, idx + 1, msg);
+        }
+    }
+    if let Some(entry) = current.take() {
+        manifests.push(entry.finish());
+    }
+    manifests
+}
+
+impl Entry {
+    fn new(name: &str) -> Self {
+        Self {
+            manifest: ServiceManifest {
+                name: service_name(name),
+                description: String::new(),
+                path: String::new(),
+                autostart: false,
+                enabled: None,
+                order: 999,
+                after: Vec::new(),
+                ready: Readiness::Started,
+                ready_timeout: DEFAULT_READY_TIMEOUT,
+                capabilities: None,
+                args: Vec::new(),
+                env: Vec::new(),
+                policy: RestartPolicy::Restart,
+                critical: false,
+            },
+            fs: String::from("ata"),
+            path: None,
+            restricts_caps: false,
+            caps: CapabilitySet::empty(),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: Value) -> Result<(), String> {
+        let m = &mut self.manifest;
+        match (key, value) {
+            ("description", Value::Str(s)) => m.description = s,
+            ("dir", Value::Str(_)) => {}
+            ("fs", Value::Str(s)) => self.fs = s,
+            ("path", Value::Str(s)) => self.path = Some(s),
+            ("autostart", Value::Bool(b)) => m.autostart = b,
+            ("critical", Value::Bool(b)) => m.critical = b,
+            ("order", Value::Int(n)) => m.order = n.min(u32::MAX as u64) as u32,
+            ("after", Value::List(names)) => {
+                m.after = names.iter().map(|n| service_name(n)).collect();
+            }
+            ("ready", Value::Str(s)) => {
+                m.ready = match s.as_str() {
+                    "started" => Readiness::Started,
+                    "notify" => Readiness::Notify,
+                    _ => return Err(format!("unknown ready condition '{}'", s)),
+                }
+            }
+            ("ready_timeout_ms", Value::Int(ms)) => m.ready_timeout = Duration::from_millis(ms),
+            ("restart", Value::Str(s)) => {
+                m.policy = match s.as_str() {
+                    "restart" => RestartPolicy::Restart,
+                    "ignore" => RestartPolicy::Ignore,
+                    "reboot" => RestartPolicy::RebootSystem,
+                    _ => return Err(format!("unknown restart policy '{}'", s)),
+                }
+            }
+            ("args", Value::List(args)) => m.args = args,
+            ("env", Value::List(env)) => {
+                if let Some(bad) = env.iter().find(|e| !e.contains('=')) {
+                    return Err(format!("env entry '{}' is not KEY=VALUE", bad));
+                }
+                m.env = env;
+            }
+            ("capabilities", Value::List(names)) => {
+                self.restricts_caps = true;
+                for name in names {
+                    let flag = capability::flag_by_name(&name)
+                        .ok_or_else(|| format!("unknown capability '{}'", name))?;
+                    self.caps = self.caps.with(flag);
+                }
+            }
+            ("ports", Value::List(ranges)) => {
+                self.restricts_caps = true;
+                for range in ranges {
+                    if self.caps.port_count >= MAX_CAP_RANGES {
+                        return Err(format!("at most {} port ranges", MAX_CAP_RANGES));
+                    }
+                    let (start, end) = parse_range(&range)
+                        .filter(|&(_, end)| end <= u16::MAX as u64)
+                        .ok_or_else(|| format!("invalid port range '{}'", range))?;
+                    self.caps = self.caps.with_ports(start as u16, end as u16);
+                }
+            }
+            ("mmio", Value::List(ranges)) => {
+                self.restricts_caps = true;
+                for range in ranges {
+                    if self.caps.mmio_count >= MAX_CAP_RANGES {
+                        return Err(format!("at most {} MMIO ranges", MAX_CAP_RANGES));
+                    }
+                    let (start, end) = parse_range(&range)
+                        .ok_or_else(|| format!("invalid MMIO range '{}'", range))?;
+                    self.caps = self.caps.with_mmio(start, end);
+                }
+            }
+            (key, _) => return Err(format!("unknown key or wrong type: '{}'", key)),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> ServiceManifest {
+        let mut manifest = self.manifest;
+        manifest.path = self.path.unwrap_or_else(|| {
+            if self.fs == "initfs" {
+                manifest.name.clone()
+            } else {
+                format!("/system/services/{}", manifest.name)
+            }
+        });
+        manifest.capabilities = self.restricts_caps.then_some(self.caps);
+        manifest
+    }
+}
+
+/// 文字列の外にある `#` から後ろを捨てる
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        let mut items = Vec::new();
+        let mut rest = inner.trim_start();
+        while !rest.is_empty() {
+            let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'')?;
+            let end = rest[1..].find(quote)? + 1;
+            items.push(rest[1..end].to_string());
+            rest = rest[end + 1..].trim_start();
+            // 要素の間と末尾のカンマ
+            rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+        }
+        return Some(Value::List(items));
+    }
+    match text {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        _ => {}
+    }
+    if text.starts_with('"') || text.starts_with('\'') {
+        return parse_string(text).map(Value::Str);
+    }
+    parse_int(text).map(Value::Int)
+}
+
+fn parse_string(text: &str) -> Option<String> {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .or_else(|| text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')))?;
+    Some(inner.to_string())
+}
+
+fn parse_int(text: &str) -> Option<u64> {
+    let text = text.replace('_', "");
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// "0x1F0-0x1F7" を (開始, 終了) にする
+fn parse_range(text: &str) -> Option<(u64, u64)> {
+    let (start, end) = text.split_once('-')?;
+    let (start, end) = (parse_int(start.trim())?, parse_int(end.trim())?);
+    (start <= end).then_some((start, end))
+}
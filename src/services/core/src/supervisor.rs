@@ -0,0 +1,566 @@
+//! サービスの起動と監視
+//!
+//! マニフェストの依存関係（`after`）に従ってサービスを起動する。依存がすべて
+//! 準備完了になったサービスから、依存の無いもの同士は並行して起動する。
+//! 準備完了は `ready = "notify"` なら READY 通知が届いたとき、それ以外は起動できたとき。
+//!
+//! 終了した子は wait4(WNOHANG) で回収し、マニフェストのポリシーに従って
+//! 起動し直す・放っておく・システムを再起動する。
+//! 起動し直すときは落ちるたびに待ち時間を倍にし（`MAX_BACKOFF` まで）、
+//! `STABLE_UPTIME` 以上動いてから落ちたなら最初の待ち時間に戻す。
+//! `BUDGET_WINDOW` の間に `RESTART_BUDGET` 回を超えて落ちたら諦める。
+//!
+//! svcctl からの操作（`swiftlib::service`）もここで扱う。止めるときは SIGTERM を送り、
+//! `STOP_TIMEOUT` 以内に終わらなければ SIGKILL を送る。
+//!
+//! サービスの標準出力・標準エラーはカーネルが親の core.service に IPC で転送してくるので、
+//! 行ごとにサービス名を付けた記録にして logd.service に送る。
+//!
+//! 監視できるのは自分の子だけなので、core.service 自身が再起動された後は
+//! 前の core.service が起動したサービスは監視されない。
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use swiftlib::capability::{CapabilitySet, CAP_KILL_ANY, CAP_SPAWN_SERVICE};
+use swiftlib::log::{self, Level, Record};
+use swiftlib::privileged;
+use swiftlib::service::{self, ServiceState, ServiceStatus};
+use swiftlib::signal;
+use swiftlib::task;
+
+use crate::manifest::{self, Readiness, ServiceManifest};
+
+/// 最初の再起動までの待ち時間
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// 再起動までの待ち時間の上限
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// これだけ動いてから落ちたなら待ち時間を最初に戻す
+const STABLE_UPTIME: Duration = Duration::from_secs(30);
+/// `BUDGET_WINDOW` の間に再起動してよい回数
+const RESTART_BUDGET: usize = 5;
+const BUDGET_WINDOW: Duration = Duration::from_secs(300);
+/// SIGTERM を送ってから SIGKILL を送るまでの時間
+const STOP_TIMEOUT: Duration = Duration::from_secs(3);
+/// サービスごとに覚えておく出来事の数
+const MAX_EVENTS: usize = 32;
+/// 出力を logd.service に送らないサービス（自分の出力が自分に戻ってくる）
+const LOGD_SERVICE: &str = "logd";
+
+/// サービスが終了したときの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// バックオフを挟んで起動し直す（予算を使い切ったら諦める）
+    Restart,
+    /// 何もしない
+    Ignore,
+    /// システムを再起動する
+    RebootSystem,
+}
+
+struct Supervised {
+    manifest: ServiceManifest,
+    state: ServiceState,
+    pid: Option<u64>,
+    /// 準備完了になった
+    ready: bool,
+    /// READY 通知を待ちきれなかった
+    ready_timed_out: bool,
+    started_at: Instant,
+    backoff: Duration,
+    /// 再起動する時刻
+    restart_at: Option<Instant>,
+    /// `BUDGET_WINDOW` 内の再起動の時刻
+    restarts: VecDeque<Instant>,
+    /// 起動し直した回数（svcctl restart を含む）
+    restart_count: u64,
+    last_exit: Option<i32>,
+    /// 止めている途中なら SIGKILL を送る時刻
+    kill_at: Option<Instant>,
+    /// 止め終わったら起動し直す（svcctl restart）
+    restart_after_stop: bool,
+    /// 出来事の記録（svcctl logs）
+    events: VecDeque<String>,
+    /// 出来事の時刻の基準（core.service の起動時刻）
+    boot: Instant,
+    /// 改行がまだ届いていない出力
+    output: Vec<u8>,
+}
+
+/// 依存先の状態
+enum Dependency {
+    Ready,
+    Waiting,
+    Failed,
+}
+
+pub struct Supervisor {
+    services: Vec<Supervised>,
+}
+
+impl Supervisor {
+    /// 縮退モードでは `critical` なサービスだけを起動する
+    pub fn new(manifests: Vec<ServiceManifest>, safe_mode: bool) -> Self {
+        let now = Instant::now();
+        let services = manifests
+            .into_iter()
+            .map(|manifest| {
+                let enabled = manifest.enabled.unwrap_or(manifest.autostart);
+                let autostart = enabled && (manifest.critical || !safe_mode);
+                Supervised {
+                    manifest,
+                    state: if autostart {
+                        ServiceState::Pending
+                    } else {
+                        ServiceState::Stopped
+                    },
+                    pid: None,
+                    ready: false,
+                    ready_timed_out: false,
+                    started_at: now,
+                    backoff: INITIAL_BACKOFF,
+                    restart_at: None,
+                    restarts: VecDeque::new(),
+                    restart_count: 0,
+                    last_exit: None,
+                    kill_at: None,
+                    restart_after_stop: false,
+                    events: VecDeque::new(),
+                    boot: now,
+                    output: Vec::new(),
+                }
+            })
+            .collect();
+        let mut supervisor = Self { services };
+        supervisor.reject_cycles();
+        supervisor
+    }
+
+    /// 起動待ち・READY 待ちのサービスが残っているか
+    pub fn booting(&self) -> bool {
+        self.services.iter().any(|s| {
+            s.state == ServiceState::Pending
+                || (s.state == ServiceState::Running && !s.ready && !s.ready_timed_out)
+        })
+    }
+
+    /// READY 通知を受け取る
+    pub fn notify_ready(&mut self, sender: u64) {
+        let Some(service) = self.services.iter_mut().find(|s| s.pid == Some(sender)) else {
+            return;
+        };
+        if !service.ready {
+            service.ready = true;
+            let ms = service.started_at.elapsed().as_millis();
+            service.event(format!("ready (PID={}, {}ms)", sender, ms));
+        }
+    }
+
+    /// サービスの出力を受け取る（監視していないスレッドからのものは捨てる）
+    pub fn capture_output(&mut self, sender: u64, data: &[u8]) {
+        if let Some(service) = self.services.iter_mut().find(|s| s.pid == Some(sender)) {
+            service.capture_output(data);
+        }
+    }
+
+    /// 終了した子を回収し、起動できるサービスを起動する
+    pub fn poll(&mut self) {
+        loop {
+            match task::wait4(-1, task::WNOHANG) {
+                Ok(result) if result.pid > 0 => self.on_exit(result.pid as u64, result),
+                // 終了した子がいない（ECHILD を含む）
+                _ => break,
+            }
+        }
+
+        let now = Instant::now();
+        for service in &mut self.services {
+            if service.state == ServiceState::BackingOff
+                && service.restart_at.is_some_and(|at| at <= now)
+            {
+                service.restart_at = None;
+                service.restarts.push_back(now);
+                service.restart_count += 1;
+                service.start(now);
+            }
+            if service.state == ServiceState::Running
+                && !service.ready
+                && !service.ready_timed_out
+                && now.duration_since(service.started_at) > service.manifest.ready_timeout
+            {
+                service.ready_timed_out = true;
+                let ms = service.manifest.ready_timeout.as_millis();
+                service.event(format!("did not become ready within {}ms", ms));
+            }
+            if service.state == ServiceState::Stopping
+                && service.kill_at.is_some_and(|at| at <= now)
+            {
+                service.kill_at = None;
+                if let Some(pid) = service.pid {
+                    service.event(format!(
+                        "did not stop in time, sending SIGKILL to PID={}",
+                        pid
+                    ));
+                    let _ = signal::kill(pid as i64, signal::SIGKILL);
+                }
+            }
+        }
+
+        for index in 0..self.services.len() {
+            if self.services[index].state != ServiceState::Pending {
+                continue;
+            }
+            match self.dependencies(index) {
+                Dependency::Waiting => {}
+                Dependency::Failed => self.services[index].state = ServiceState::Failed,
+                Dependency::Ready => self.services[index].start(now),
+            }
+        }
+    }
+
+    /// svcctl からの要求を処理し、(status, ペイロード) を返す
+    ///
+    /// 操作は要求元のケーパビリティ `caps` で認可する（stop は CAP_KILL_ANY、
+    /// start・restart・enable・disable は CAP_SPAWN_SERVICE）。
+    pub fn handle_request(&mut self, op: u64, name: &str, caps: CapabilitySet) -> (i64, Vec<u8>) {
+        if op == service::OP_LIST {
+            let payload = self
+                .services
+                .iter()
+                .flat_map(|s| s.status().to_bytes())
+                .collect();
+            return (0, payload);
+        }
+        let name = manifest::service_name(name);
+        let Some(index) = self.services.iter().position(|s| s.manifest.name == name) else {
+            return (crate::ENOENT, Vec::new());
+        };
+        let required = match op {
+            service::OP_STATUS | service::OP_LOGS => None,
+            service::OP_STOP => Some(CAP_KILL_ANY),
+            _ => Some(CAP_SPAWN_SERVICE),
+        };
+        if required.is_some_and(|flag| !caps.has(flag)) {
+            return (crate::EPERM, Vec::new());
+        }
+        let service = &mut self.services[index];
+        match op {
+            service::OP_STATUS => (0, service.status().to_bytes().to_vec()),
+            service::OP_LOGS => {
+                let mut text = String::new();
+                for line in &service.events {
+                    text.push_str(line);
+                    text.push('\n');
+                }
+                (0, text.into_bytes())
+            }
+            service::OP_START => {
+                service.request_start();
+                (0, Vec::new())
+            }
+            service::OP_STOP => {
+                service.restart_after_stop = false;
+                service.request_stop();
+                (0, Vec::new())
+            }
+            service::OP_RESTART => {
+                if service.state == ServiceState::Running {
+                    service.restart_after_stop = true;
+                    service.request_stop();
+                } else {
+                    service.request_start();
+                }
+                (0, Vec::new())
+            }
+            service::OP_ENABLE | service::OP_DISABLE => {
+                let enabled = op == service::OP_ENABLE;
+                service.manifest.enabled = Some(enabled);
+                service.event(String::from(if enabled { "enabled" } else { "disabled" }));
+                match manifest::save_overrides(self.services.iter().map(|s| &s.manifest)) {
+                    Ok(()) => (0, Vec::new()),
+                    Err(errno) => (errno, Vec::new()),
+                }
+            }
+            _ => (crate::EINVAL, Vec::new()),
+        }
+    }
+
+    /// 起動待ちのサービスの依存先をまとめて調べる
+    fn dependencies(&mut self, index: usize) -> Dependency {
+        let mut result = Dependency::Ready;
+        let mut failure = None;
+        for dep in &self.services[index].manifest.after {
+            let Some(target) = self.services.iter().find(|s| &s.manifest.name == dep) else {
+                failure = Some(format!("not starting: unknown dependency {}", dep));
+                break;
+            };
+            if target.ready {
+                continue;
+            }
+            if matches!(target.state, ServiceState::Stopped | ServiceState::Failed)
+                || target.ready_timed_out
+            {
+                failure = Some(format!("not starting: dependency {} is not available", dep));
+                break;
+            }
+            result = Dependency::Waiting;
+        }
+        match failure {
+            Some(msg) => {
+                self.services[index].event(msg);
+                Dependency::Failed
+            }
+            None => result,
+        }
+    }
+
+    /// 依存関係が循環しているサービスを起動しないようにする
+    fn reject_cycles(&mut self) {
+        for index in 0..self.services.len() {
+            let mut visited = vec![false; self.services.len()];
+            let mut stack = vec![index];
+            let mut cyclic = false;
+            while let Some(current) = stack.pop() {
+                for dep in &self.services[current].manifest.after {
+                    let Some(next) = self.services.iter().position(|s| &s.manifest.name == dep)
+                    else {
+                        continue;
+                    };
+                    if next == index {
+                        cyclic = true;
+                    } else if !visited[next] {
+                        visited[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            if cyclic && self.services[index].state == ServiceState::Pending {
+                let service = &mut self.services[index];
+                service.event(String::from("not starting: circular dependency"));
+                service.state = ServiceState::Failed;
+            }
+        }
+    }
+
+    fn on_exit(&mut self, pid: u64, result: task::WaitResult) {
+        let Some(service) = self.services.iter_mut().find(|s| s.pid == Some(pid)) else {
+            // 監視していない子（テストなど）
+            return;
+        };
+        let stopping = service.state == ServiceState::Stopping;
+        service.flush_output();
+        service.pid = None;
+        service.ready = false;
+        service.kill_at = None;
+        service.state = ServiceState::Stopped;
+        let uptime = service.started_at.elapsed();
+        if result.exited() {
+            service.last_exit = Some(result.exit_status());
+            service.event(format!(
+                "PID={} exited with status {} after {}s",
+                pid,
+                result.exit_status(),
+                uptime.as_secs()
+            ));
+        } else {
+            service.event(format!(
+                "PID={} terminated (status={:#x}) after {}s",
+                pid,
+                result.status,
+                uptime.as_secs()
+            ));
+        }
+
+        if stopping {
+            // svcctl で止めたので、ポリシーに関わらず restart のときだけ起動し直す
+            if core::mem::take(&mut service.restart_after_stop) {
+                service.restart_count += 1;
+                service.start(Instant::now());
+            }
+            return;
+        }
+
+        match service.manifest.policy {
+            RestartPolicy::Ignore => {}
+            RestartPolicy::RebootSystem => {
+                service.event(String::from(
+                    "required service exited, rebooting the system",
+                ));
+                let errno = privileged::reboot();
+                service.event(format!(
+                    "reboot failed: errno={}, restarting instead",
+                    errno as i64
+                ));
+                service.schedule_restart(uptime);
+            }
+            RestartPolicy::Restart => {
+                if !service.schedule_restart(uptime) {
+                    service.event(format!(
+                        "crashed more than {} times in {}s, giving up",
+                        RESTART_BUDGET,
+                        BUDGET_WINDOW.as_secs()
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl Supervised {
+    /// 出来事を記録し、コンソールと logd.service にも出す
+    fn event(&mut self, msg: String) {
+        println!("[CORE] {}: {}", self.manifest.name, msg);
+        let line = format!("{}: {}", self.manifest.name, msg);
+        let _ = log::send(&Record::now("core", Level::Info, &line));
+        let at = self.boot.elapsed();
+        if self.events.len() >= MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(format!(
+            "[{:>5}.{:03}] {}",
+            at.as_secs(),
+            at.subsec_millis(),
+            msg
+        ));
+    }
+
+    /// 出力を行に分け、揃った行を logd.service に送る
+    fn capture_output(&mut self, data: &[u8]) {
+        if self.manifest.name == LOGD_SERVICE {
+            return;
+        }
+        for &byte in data {
+            if byte == b'\n' {
+                self.flush_output();
+            } else {
+                self.output.push(byte);
+                if self.output.len() >= log::MAX_MESSAGE {
+                    self.flush_output();
+                }
+            }
+        }
+    }
+
+    /// 溜まっている出力を 1 行として送る
+    ///
+    /// logd.service に送れなくても、出力はカーネルがシリアルに出しているので捨てる。
+    fn flush_output(&mut self) {
+        if self.output.is_empty() {
+            return;
+        }
+        let bytes = core::mem::take(&mut self.output);
+        let line = String::from_utf8_lossy(&bytes);
+        let line = line.trim_end_matches('\r');
+        if !line.is_empty() {
+            let _ = log::send(&Record::now(&self.manifest.name, Level::Info, line));
+        }
+    }
+
+    fn status(&self) -> ServiceStatus {
+        let mut status = ServiceStatus::new(&self.manifest.name, self.state);
+        if let Some(pid) = self.pid {
+            status.pid = pid;
+            status.uptime_ms = self.started_at.elapsed().as_millis() as u64;
+        }
+        status.restarts = self.restart_count;
+        status.last_exit = self.last_exit;
+        status.ready = self.ready;
+        status.enabled = self.manifest.enabled.unwrap_or(self.manifest.autostart);
+        status
+    }
+
+    /// svcctl start: 止まっていれば依存を待ってから起動する（再起動の予算も戻す）
+    fn request_start(&mut self) {
+        match self.state {
+            ServiceState::Stopped | ServiceState::Failed => {
+                self.event(String::from("start requested"));
+                self.restarts.clear();
+                self.backoff = INITIAL_BACKOFF;
+                self.state = ServiceState::Pending;
+            }
+            ServiceState::BackingOff => {
+                self.event(String::from("start requested, skipping backoff"));
+                self.restart_at = Some(Instant::now());
+            }
+            ServiceState::Stopping => self.restart_after_stop = true,
+            ServiceState::Pending | ServiceState::Running => {}
+        }
+    }
+
+    /// svcctl stop: SIGTERM を送る（起動前・再起動待ちならそのまま止める）
+    fn request_stop(&mut self) {
+        match self.state {
+            ServiceState::Running => {
+                let Some(pid) = self.pid else {
+                    return;
+                };
+                self.event(format!("stop requested, sending SIGTERM to PID={}", pid));
+                self.state = ServiceState::Stopping;
+                self.kill_at = Some(Instant::now() + STOP_TIMEOUT);
+                if signal::kill(pid as i64, signal::SIGTERM).is_err() {
+                    let _ = signal::kill(pid as i64, signal::SIGKILL);
+                }
+            }
+            ServiceState::Pending | ServiceState::BackingOff => {
+                self.event(String::from("stopped before starting"));
+                self.restart_at = None;
+                self.state = ServiceState::Stopped;
+            }
+            ServiceState::Stopping | ServiceState::Stopped | ServiceState::Failed => {}
+        }
+    }
+
+    /// 再起動を予約する（予算を使い切っていれば false）
+    fn schedule_restart(&mut self, uptime: Duration) -> bool {
+        let now = Instant::now();
+        while self
+            .restarts
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > BUDGET_WINDOW)
+        {
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= RESTART_BUDGET {
+            self.state = ServiceState::Failed;
+            return false;
+        }
+        if uptime >= STABLE_UPTIME {
+            self.backoff = INITIAL_BACKOFF;
+        }
+        self.event(format!("restarting in {}ms", self.backoff.as_millis()));
+        self.state = ServiceState::BackingOff;
+        self.restart_at = Some(now + self.backoff);
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+        true
+    }
+
+    fn start(&mut self, now: Instant) {
+        self.ready = false;
+        self.ready_timed_out = false;
+        self.started_at = now;
+        match crate::start_service(&self.manifest) {
+            Ok(pid) => {
+                self.event(format!("started (PID={})", pid));
+                self.pid = Some(pid);
+                self.state = ServiceState::Running;
+                self.ready = self.manifest.ready == Readiness::Started;
+            }
+            Err(crate::EEXIST) => {
+                self.event(String::from(
+                    "already running outside core.service, not supervising it",
+                ));
+                self.state = ServiceState::Stopped;
+            }
+            Err(errno) => {
+                self.event(format!("failed to start: errno={}", errno));
+                self.state = ServiceState::Failed;
+                // 起動できなかったのも 1 回の失敗として数え、次の待ち時間で試す
+                if self.manifest.policy != RestartPolicy::Ignore
+                    && !self.schedule_restart(Duration::ZERO)
+                {
+                    self.event(String::from("giving up"));
+                }
+            }
+        }
+    }
+}
@@ -7,8 +7,8 @@ use swiftlib::ipc;
 use swiftlib::io;
 
 const OP_NOTIFY_READY: u64 = 0xFF;
+/// 起動するドライバの一覧（ビルド時に src/drivers から生成される）
 const DRIVER_CONFIG_PATH: &str = "/config/drivers.list";
-const DEFAULT_DRIVERS: &[&str] = &["/bin/drivers/usb.elf"];
 
 fn load_driver_list() -> Vec<String> {
     let mut drivers = Vec::new();
@@ -25,18 +25,12 @@ fn load_driver_list() -> Vec<String> {
         }
         Err(_) => {
             println!(
-                "[DRIVER] Failed to open {} (using defaults)",
+                "[DRIVER] Failed to open {} (no drivers started)",
                 DRIVER_CONFIG_PATH
             );
         }
     }
 
-    if drivers.is_empty() {
-        for path in DEFAULT_DRIVERS {
-            drivers.push((*path).to_string());
-        }
-    }
-
     drivers
 }
 
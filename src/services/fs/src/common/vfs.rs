@@ -126,6 +126,11 @@ pub trait FileSystem: Send + Sync {
 
     /// 同期（変更をディスクに書き込む）
     fn sync(&mut self) -> VfsResult<()>;
+
+    /// 引き継ぎで渡すメモリ上の中身（ディスクにあるものは None）
+    fn snapshot(&self) -> Option<Vec<u8>> {
+        None
+    }
 }
 
 /// ファイルハンドル
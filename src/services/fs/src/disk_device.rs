@@ -1,8 +1,9 @@
 //! ディスクサービスを使用したブロックデバイス実装
 
 use core::mem::size_of;
+use core::sync::atomic::{AtomicU64, Ordering};
 
-use swiftlib::ipc;
+use swiftlib::{ipc, task};
 
 use crate::common::vfs::{VfsError, VfsResult};
 use crate::ext2::BlockDevice;
@@ -35,8 +36,11 @@ struct DiskResponse {
 }
 
 /// ディスクサービスを使用したブロックデバイス
+///
+/// disk.service が再起動されると PID が変わるので、送信に失敗したら名前で探し直して
+/// もう一度だけ送る。
 pub struct DiskServiceDevice {
-    disk_service_pid: u64,
+    disk_service_pid: AtomicU64,
     disk_id: u64,
     sector_size: usize,
 }
@@ -45,12 +49,30 @@ impl DiskServiceDevice {
     /// 新しいディスクデバイスを作成
     pub fn new(disk_service_pid: u64, disk_id: u64) -> Self {
         Self {
-            disk_service_pid,
+            disk_service_pid: AtomicU64::new(disk_service_pid),
             disk_id,
             sector_size: 512,
         }
     }
 
+    /// リクエストを disk.service に送り、送った先の PID を返す
+    fn send(&self, req: &[u8]) -> VfsResult<u64> {
+        let pid = self.disk_service_pid.load(Ordering::Relaxed);
+        if ipc::ipc_send(pid, req) == 0 {
+            return Ok(pid);
+        }
+        // 再起動された disk.service を探し直す
+        let new_pid = task::find_process_by_name("disk.service")
+            .filter(|&p| p != pid)
+            .ok_or(VfsError::IoError)?;
+        println!("[FS] disk.service restarted (PID {} -> {})", pid, new_pid);
+        self.disk_service_pid.store(new_pid, Ordering::Relaxed);
+        if ipc::ipc_send(new_pid, req) != 0 {
+            return Err(VfsError::IoError);
+        }
+        Ok(new_pid)
+    }
+
     /// セクタを読み取る（内部用）
     fn read_sector(&self, lba: u64, buf: &mut [u8]) -> VfsResult<()> {
         if buf.len() < 512 {
@@ -70,10 +92,7 @@ impl DiskServiceDevice {
         };
 
         // リクエストを送信
-        let result = ipc::ipc_send(self.disk_service_pid, req_slice);
-        if result != 0 {
-            return Err(VfsError::IoError);
-        }
+        let disk_service_pid = self.send(req_slice)?;
 
         // レスポンスを受信（EAGAIN の場合はスピン、最大 1000 回）
         let mut resp_buf = [0u8; size_of::<DiskResponse>()];
@@ -86,7 +105,7 @@ impl DiskServiceDevice {
             break (s, l);
         };
 
-        if sender != self.disk_service_pid || (len as usize) < size_of::<DiskResponse>() {
+        if sender != disk_service_pid || (len as usize) < size_of::<DiskResponse>() {
             return Err(VfsError::IoError);
         }
 
@@ -122,10 +141,7 @@ impl DiskServiceDevice {
             core::slice::from_raw_parts(&req as *const _ as *const u8, size_of::<DiskRequest>())
         };
 
-        let result = ipc::ipc_send(self.disk_service_pid, req_slice);
-        if result != 0 {
-            return Err(VfsError::IoError);
-        }
+        let disk_service_pid = self.send(req_slice)?;
 
         // レスポンスを受信（EAGAIN の場合はスピン）
         let mut resp_buf = [0u8; size_of::<DiskResponse>()];
@@ -137,7 +153,7 @@ impl DiskServiceDevice {
             break (s, l);
         };
 
-        if sender != self.disk_service_pid || (len as usize) < size_of::<DiskResponse>() {
+        if sender != disk_service_pid || (len as usize) < size_of::<DiskResponse>() {
             return Err(VfsError::IoError);
         }
 
@@ -0,0 +1,279 @@
+//! fs.service の引き継ぎ
+//!
+//! 新しい fs.service は、動いている fs.service（カーネルに登録されたエンドポイント）が
+//! あれば OP_HANDOVER を送って状態を受け取り、無ければカーネルに預けられた状態を読む。
+//! 状態を復元してからエンドポイントを登録するので、カーネルのリモート FD
+//! （fs.service のハンドル番号）は更新や再起動の後もそのまま使える。
+//!
+//! 状態はマウントしたファイルシステムとオープン中のハンドル（開いた PID・inode・オフセット・フラグ）。
+//! 動いている fs.service からの引き継ぎでは InitFs の中身も受け取る。カーネルに預ける
+//! 状態には入れない（大きすぎる）ので、落ちたときに InitFs だったならハンドルは捨てる。
+//! カーネルに預けるのはハンドルを開いた・閉じた・書き込んだときだけなので、落ちた後は
+//! 最後に預けてからの read で進んだオフセットが戻る。
+//!
+//! OP_HANDOVER は、core.service が起動した別の fs.service（`fs_endpoint::is_successor`）からだけ受け付ける。
+//!
+//! 引き継ぎの流れ:
+//! 1. 新 → 旧: FsRequest（op = OP_HANDOVER）
+//! 2. 旧 → 新: FsResponse（status = 0, len = 状態の長さ）、続けて状態を `CHUNK` バイトずつ
+//! 3. 旧は以降の要求を処理せずに終了し、新が状態を復元してエンドポイントを登録する
+
+use core::mem::size_of;
+use swiftlib::fs_endpoint;
+use swiftlib::ipc;
+use swiftlib::task;
+
+use crate::{FsRequest, FsResponse};
+
+const MAGIC: u32 = 0x4F48_5346; // "FSHO"
+const VERSION: u32 = 2;
+
+/// 1 メッセージで送る状態の大きさ
+const CHUNK: usize = 4096;
+/// 状態の大きさの上限（InitFs が満杯でも収まる）
+const MAX_STATE: usize = 1024 * 1024;
+/// 旧 fs.service の応答を待つ時間
+const REPLY_TIMEOUT_MS: u64 = 5000;
+const POLL_MS: u64 = 10;
+/// 新 fs.service の受信箱が空くのを待つ回数（`POLL_MS` ごと）
+const SEND_RETRIES: usize = 500;
+
+/// マウントしたファイルシステム
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mount {
+    InitFs,
+    Ext2 { disk: u8 },
+}
+
+/// オープン中のハンドル
+#[derive(Debug, Clone, Copy)]
+pub struct Handle {
+    /// ハンドル番号（カーネルのリモート FD）
+    pub index: usize,
+    /// 開いたプロセスの PID
+    pub owner: u64,
+    pub inode: u64,
+    pub offset: u64,
+    pub flags: u32,
+}
+
+/// 引き継ぐ状態
+pub struct State {
+    pub mount: Mount,
+    pub handles: Vec<Handle>,
+    /// InitFs の中身（`FileSystem::snapshot`）
+    pub initfs: Option<Vec<u8>>,
+}
+
+impl State {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC.to_le_bytes());
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        let (kind, disk) = match self.mount {
+            Mount::InitFs => (0u32, 0u32),
+            Mount::Ext2 { disk } => (1, disk as u32),
+        };
+        out.extend_from_slice(&kind.to_le_bytes());
+        out.extend_from_slice(&disk.to_le_bytes());
+        out.extend_from_slice(&(self.handles.len() as u32).to_le_bytes());
+        for handle in &self.handles {
+            out.extend_from_slice(&(handle.index as u32).to_le_bytes());
+            out.extend_from_slice(&handle.flags.to_le_bytes());
+            out.extend_from_slice(&handle.owner.to_le_bytes());
+            out.extend_from_slice(&handle.inode.to_le_bytes());
+            out.extend_from_slice(&handle.offset.to_le_bytes());
+        }
+        let initfs = self.initfs.as_deref().unwrap_or(&[]);
+        out.extend_from_slice(&(initfs.len() as u64).to_le_bytes());
+        out.extend_from_slice(initfs);
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(bytes);
+        if r.u32()? != MAGIC || r.u32()? != VERSION {
+            return None;
+        }
+        let mount = match (r.u32()?, r.u32()?) {
+            (0, _) => Mount::InitFs,
+            (1, disk) => Mount::Ext2 { disk: disk as u8 },
+            _ => return None,
+        };
+        let count = r.u32()? as usize;
+        let mut handles = Vec::new();
+        for _ in 0..count {
+            let index = r.u32()? as usize;
+            let flags = r.u32()?;
+            handles.push(Handle {
+                index,
+                flags,
+                owner: r.u64()?,
+                inode: r.u64()?,
+                offset: r.u64()?,
+            });
+        }
+        let len = r.u64()? as usize;
+        let initfs = r.bytes(len)?;
+        Some(Self {
+            mount,
+            handles,
+            initfs: (!initfs.is_empty()).then(|| initfs.to_vec()),
+        })
+    }
+}
+
+/// リトルエンディアンの値を順に読む
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(head)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .and_then(|b| b.try_into().ok())
+            .map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.bytes(8)
+            .and_then(|b| b.try_into().ok())
+            .map(u64::from_le_bytes)
+    }
+}
+
+/// 前の fs.service の状態を受け取る（動いていれば直接、落ちていればカーネルから）
+pub fn receive() -> Option<State> {
+    if let Some(old) = fs_endpoint::query() {
+        println!("[FS] Taking over from running fs.service (TID={})", old);
+        match request(old) {
+            Some(state) => return Some(state),
+            None => println!("[FS] Handover from TID={} failed", old),
+        }
+    }
+    let saved = fs_endpoint::load().ok().filter(|s| !s.is_empty())?;
+    let state = State::decode(&saved);
+    if state.is_none() {
+        println!("[FS] Ignoring unreadable saved state");
+    }
+    state
+}
+
+/// 動いている fs.service に OP_HANDOVER を送り、状態を受け取る
+fn request(old: u64) -> Option<State> {
+    let req = FsRequest {
+        op: FsRequest::OP_HANDOVER,
+        arg1: 0,
+        arg2: 0,
+        path: [0; 128],
+    };
+    let req_bytes = unsafe {
+        core::slice::from_raw_parts(&req as *const _ as *const u8, size_of::<FsRequest>())
+    };
+    if ipc::ipc_send(old, req_bytes) != 0 {
+        return None;
+    }
+
+    let mut buf = vec![0u8; size_of::<FsResponse>().max(CHUNK)];
+    let n = recv_from(old, &mut buf)?;
+    if n < 16 {
+        return None;
+    }
+    let status = i64::from_le_bytes(buf[..8].try_into().ok()?);
+    let total = u64::from_le_bytes(buf[8..16].try_into().ok()?) as usize;
+    if status < 0 || total > MAX_STATE {
+        return None;
+    }
+    let mut state = Vec::with_capacity(total);
+    while state.len() < total {
+        let n = recv_from(old, &mut buf)?;
+        let take = n.min(total - state.len());
+        state.extend_from_slice(&buf[..take]);
+    }
+    State::decode(&state)
+}
+
+/// `from` からのメッセージを待つ（ほかからのメッセージは捨てる）
+fn recv_from(from: u64, buf: &mut [u8]) -> Option<usize> {
+    let mut waited = 0;
+    loop {
+        let (sender, len) = ipc::ipc_recv(buf);
+        if sender == from && len > 0 {
+            return Some(len as usize);
+        }
+        if sender != 0 {
+            println!("[FS] Dropping message from TID={} during handover", sender);
+            continue;
+        }
+        if waited >= REPLY_TIMEOUT_MS {
+            return None;
+        }
+        task::sleep(POLL_MS);
+        waited += POLL_MS;
+    }
+}
+
+/// OP_HANDOVER に応えて状態を送る（送り終えたら呼び出し元は終了する）
+pub fn send(to: u64, state: &State) -> bool {
+    let bytes = state.encode();
+    let mut header = FsResponse {
+        status: 0,
+        len: bytes.len() as u64,
+        data: [0; 128],
+    };
+    if bytes.len() > MAX_STATE {
+        header.status = -27; // EFBIG
+        header.len = 0;
+    }
+    let header_bytes = unsafe {
+        core::slice::from_raw_parts(&header as *const _ as *const u8, size_of::<FsResponse>())
+    };
+    if !send_retrying(to, header_bytes) || header.status < 0 {
+        return false;
+    }
+    bytes.chunks(CHUNK).all(|chunk| send_retrying(to, chunk))
+}
+
+/// 受信箱が一杯なら空くまで待って送る
+fn send_retrying(to: u64, data: &[u8]) -> bool {
+    for _ in 0..SEND_RETRIES {
+        if ipc::ipc_send(to, data) == 0 {
+            return true;
+        }
+        task::sleep(POLL_MS);
+    }
+    false
+}
+
+/// 状態をカーネルに預ける（InitFs の中身は入れない）
+pub fn save(mount: Mount, handles: Vec<Handle>) {
+    let state = State {
+        mount,
+        handles,
+        initfs: None,
+    };
+    if let Err(errno) = fs_endpoint::save(&state.encode()) {
+        println!("[FS] Failed to save state: errno={}", errno as i64);
+    }
+}
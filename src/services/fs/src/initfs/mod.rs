@@ -10,6 +10,7 @@ use core::sync::atomic::{AtomicU64, Ordering};
 use crate::common::vfs::{
     DirEntry, FileAttr, FileSystem, FileType, VfsError, VfsResult,
 };
+use crate::handover::Reader;
 
 const MAX_FILES: usize = 64;
 const FILE_SIZE: usize = 4096;
@@ -112,6 +113,49 @@ impl InitFs {
         Ok(())
     }
 
+    /// 中身を直列化する（fs.service の引き継ぎ用）
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.next_inode.load(Ordering::SeqCst).to_le_bytes());
+        for node in self.inodes.iter().filter(|n| n.used) {
+            out.extend_from_slice(&node.inode_num.to_le_bytes());
+            out.extend_from_slice(&node.parent.to_le_bytes());
+            out.push((node.file_type == FileType::Directory) as u8);
+            out.extend_from_slice(&(node.name.len() as u16).to_le_bytes());
+            out.extend_from_slice(node.name.as_bytes());
+            out.extend_from_slice(&(node.data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&node.data);
+        }
+        out
+    }
+
+    /// `snapshot` で直列化した中身から作り直す
+    pub fn from_snapshot(bytes: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(bytes);
+        let next_inode = r.u64()?;
+        let mut inodes = vec![InitFsInode::new_empty(); MAX_FILES];
+        while let Some(inode_num) = r.u64() {
+            let parent = r.u64()?;
+            let is_dir = r.u8()? != 0;
+            let name_len = r.u16()? as usize;
+            let name = String::from(core::str::from_utf8(r.bytes(name_len)?).ok()?);
+            let data_len = r.u32()? as usize;
+            let data = r.bytes(data_len)?.to_vec();
+            let slot = inodes.get_mut(inode_num as usize)?;
+            *slot = if is_dir {
+                InitFsInode::new_dir(inode_num, name, parent)
+            } else {
+                InitFsInode::new_file(inode_num, name, parent)
+            };
+            slot.size = data.len() as u64;
+            slot.data = data;
+        }
+        inodes[Self::ROOT_INODE as usize].used.then_some(Self {
+            inodes,
+            next_inode: AtomicU64::new(next_inode),
+        })
+    }
+
     fn allocate_inode(&self) -> VfsResult<u64> {
         let inode_num = self.next_inode.fetch_add(1, Ordering::SeqCst);
         if (inode_num as usize) >= MAX_FILES {
@@ -358,4 +402,8 @@ impl FileSystem for InitFs {
         // メモリ上なので何もしない。だって再起動したら全部どっか行ってるし書き込みできないから
         Ok(())
     }
+
+    fn snapshot(&self) -> Option<Vec<u8>> {
+        Some(self.encode())
+    }
 }
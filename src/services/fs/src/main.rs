@@ -1,25 +1,42 @@
 use core::mem::size_of;
 use std::boxed;
+use swiftlib::capability::{self, CAP_FS_WRITE};
+use swiftlib::fs_endpoint;
 use swiftlib::ipc;
 use swiftlib::task;
 
 mod common;
 mod disk_device;
 mod ext2;
+mod handover;
 mod initfs;
 
 use common::{resolve_path, FileHandle, FileSystem, VfsError};
 use disk_device::DiskServiceDevice;
 use ext2::Ext2Fs;
+use handover::{Handle, Mount};
 use initfs::InitFs;
 
 const MAX_HANDLES: usize = 16;
+/// OP_WRITE 1 回あたりの最大データ長（カーネルの FS_DATA_MAX と同じ）
+const WRITE_CHUNK_MAX: usize = 4096;
+
+// open フラグ（Linux 互換）
+const O_ACCMODE: u64 = 0o3;
+const O_CREAT: u64 = 0o100;
+const O_TRUNC: u64 = 0o1000;
+const O_APPEND: u64 = 0o2000;
+
+const EBADF: i64 = -9;
+const EACCES: i64 = -13;
 
 #[derive(Clone, Copy)]
 struct OpenFile {
     used: bool,
     handle: FileHandle,
     fs_id: usize,
+    /// 開いたプロセスの PID
+    owner: u64,
 }
 
 impl OpenFile {
@@ -32,6 +49,7 @@ impl OpenFile {
                 flags: 0,
             },
             fs_id: 0,
+            owner: 0,
         }
     }
 }
@@ -41,6 +59,9 @@ static mut HANDLES: [OpenFile; MAX_HANDLES] = [OpenFile::new(); MAX_HANDLES];
 /// マウントされたファイルシステム（ext2 優先、InitFs フォールバック）
 static mut MOUNTED_FS: Option<Box<dyn FileSystem>> = None;
 
+/// マウントしたファイルシステムの種類（引き継ぎで渡す）
+static mut MOUNT: Mount = Mount::InitFs;
+
 /// READY通知
 const OP_NOTIFY_READY: u64 = 0xFF;
 
@@ -58,6 +79,8 @@ impl FsRequest {
     const OP_READ: u64 = 2;
     const OP_WRITE: u64 = 3;
     const OP_CLOSE: u64 = 4;
+    /// 新しい fs.service に状態を渡して終了する（handover を参照）
+    const OP_HANDOVER: u64 = 0x100;
 }
 
 #[repr(C)]
@@ -68,8 +91,9 @@ struct FsResponse {
     data: [u8; 128],
 }
 
+/// 受信バッファ（OP_WRITE はリクエストの直後にデータ本体が続く）
 #[repr(align(8))]
-struct AlignedBuffer([u8; 256]);
+struct AlignedBuffer([u8; size_of::<FsRequest>() + WRITE_CHUNK_MAX]);
 
 fn vfs_error_to_errno(err: VfsError) -> i64 {
     match err {
@@ -112,6 +136,7 @@ fn mount_filesystem() {
                 println!("[FS] ext2 filesystem mounted from ATA disk.");
                 unsafe {
                     MOUNTED_FS = Some(Box::new(fs));
+                    MOUNT = Mount::Ext2 { disk: 1 };
                 }
                 return;
             }
@@ -131,10 +156,139 @@ fn mount_filesystem() {
     }
     unsafe {
         MOUNTED_FS = Some(boxed::Box::new(initfs));
+        MOUNT = Mount::InitFs;
     }
     println!("[FS] InitFS mounted as fallback.");
 }
 
+/// 前の fs.service から受け取った状態でマウントし、ハンドルを復元する
+fn restore(state: handover::State) {
+    let initfs = state.initfs.as_deref().and_then(InitFs::from_snapshot);
+    match initfs {
+        Some(fs) if state.mount == Mount::InitFs => unsafe {
+            MOUNTED_FS = Some(Box::new(fs));
+            MOUNT = Mount::InitFs;
+            println!("[FS] Took over InitFS contents.");
+        },
+        _ => mount_filesystem(),
+    }
+
+    let mount = unsafe { MOUNT };
+    if mount != state.mount {
+        println!("[FS] Mounted a different filesystem, dropping inherited handles");
+        return;
+    }
+    if mount == Mount::InitFs && state.initfs.is_none() {
+        // 落ちた fs.service の InitFs の中身は失われている
+        println!("[FS] InitFS contents were lost, dropping inherited handles");
+        return;
+    }
+    let mut restored = 0;
+    for h in state.handles.iter().filter(|h| h.index < MAX_HANDLES) {
+        unsafe {
+            HANDLES[h.index] = OpenFile {
+                used: true,
+                handle: FileHandle {
+                    inode: h.inode,
+                    offset: h.offset,
+                    flags: h.flags,
+                },
+                fs_id: 0,
+                owner: h.owner,
+            };
+        }
+        restored += 1;
+    }
+    println!("[FS] Restored {} open handle(s).", restored);
+}
+
+/// オープン中のハンドル
+fn open_handles() -> Vec<Handle> {
+    (0..MAX_HANDLES)
+        .filter_map(|index| {
+            let file = unsafe { HANDLES[index] };
+            file.used.then_some(Handle {
+                index,
+                owner: file.owner,
+                inode: file.handle.inode,
+                offset: file.handle.offset,
+                flags: file.handle.flags,
+            })
+        })
+        .collect()
+}
+
+/// 新しい fs.service に状態を渡して終了する
+///
+/// 渡せなくても終了する（ハンドルはカーネルに預けてあるので、新しい方はそれを使う）。
+fn hand_over(to: u64) -> ! {
+    let initfs = unsafe {
+        match MOUNTED_FS {
+            Some(ref mut fs) => {
+                let _ = fs.sync();
+                fs.snapshot()
+            }
+            None => None,
+        }
+    };
+    let state = handover::State {
+        mount: unsafe { MOUNT },
+        handles: open_handles(),
+        initfs,
+    };
+    if handover::send(to, &state) {
+        println!("[FS] Handed over to TID={}, exiting", to);
+    } else {
+        println!("[FS] Failed to hand over to TID={}, exiting", to);
+    }
+    task::exit(0)
+}
+
+/// パスを親ディレクトリと最後の要素に分ける
+fn split_parent(path: &str) -> (&str, &str) {
+    let trimmed = path.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(idx) => (&trimmed[..idx], &trimmed[idx + 1..]),
+        None => ("", trimmed),
+    }
+}
+
+/// 書き込み・作成・切り詰め・追記を伴う open か
+fn is_mutating_open(flags: u64) -> bool {
+    flags & (O_ACCMODE | O_CREAT | O_TRUNC | O_APPEND) != 0
+}
+
+/// プロセス `pid` がハンドル `fd` を使えるか
+///
+/// 開いたプロセスか、fork や dup で受け継いでカーネルの FD テーブルに持っているプロセスだけ。
+fn may_use_handle(fd: usize, pid: u64) -> bool {
+    if fd >= MAX_HANDLES {
+        return false;
+    }
+    let file = unsafe { HANDLES[fd] };
+    file.used && (file.owner == pid || fs_endpoint::holds(pid, fd as u64))
+}
+
+/// パスを開く（O_CREAT なら無いときに作成、O_TRUNC なら長さ 0 にする）
+fn open_inode(fs: &mut dyn FileSystem, path: &str, flags: u64) -> Result<u64, VfsError> {
+    let inode = match resolve_path(fs, path) {
+        Ok(inode) => inode,
+        Err(VfsError::NotFound) if flags & O_CREAT != 0 => {
+            let (parent, name) = split_parent(path);
+            if name.is_empty() {
+                return Err(VfsError::InvalidArgument);
+            }
+            let parent_inode = resolve_path(fs, parent)?;
+            fs.create(parent_inode, name, 0o644)?
+        }
+        Err(e) => return Err(e),
+    };
+    if flags & O_TRUNC != 0 {
+        fs.truncate(inode, 0)?;
+    }
+    Ok(inode)
+}
+
 /// core.service に準備完了を通知する
 fn notify_ready_to_core() {
     let core_pid = match task::find_process_by_name("core.service") {
@@ -154,10 +308,21 @@ fn notify_ready_to_core() {
 fn main() {
     println!("[FS] Service Started.");
 
-    mount_filesystem();
+    match handover::receive() {
+        Some(state) => restore(state),
+        None => mount_filesystem(),
+    }
+    // 状態を復元し終えてから、カーネルの要求の送り先になる
+    if let Err(errno) = fs_endpoint::register() {
+        println!(
+            "[FS] WARNING: failed to register endpoint: errno={}",
+            errno as i64
+        );
+    }
+    handover::save(unsafe { MOUNT }, open_handles());
     notify_ready_to_core();
 
-    let mut recv_buf = AlignedBuffer([0u8; 256]);
+    let mut recv_buf = AlignedBuffer([0u8; size_of::<FsRequest>() + WRITE_CHUNK_MAX]);
 
     loop {
         let (sender, len) = ipc::ipc_recv(&mut recv_buf.0);
@@ -172,6 +337,14 @@ fn main() {
             let req: FsRequest = unsafe { core::ptr::read(recv_buf.0.as_ptr() as *const _) };
             println!("[FS] REQ op={} from PID={}", req.op, sender);
 
+            // 送信元のプロセスと、ディスク上のファイルを変更できるか
+            let Ok((pid, caps)) = capability::get_thread_capabilities(sender) else {
+                continue;
+            };
+            let can_write = caps.has(CAP_FS_WRITE);
+            // ハンドル表が変わった要求の後だけ、状態をカーネルに預け直す
+            let mut handles_changed = false;
+
             let mut resp = FsResponse {
                 status: -1,
                 len: 0,
@@ -179,6 +352,9 @@ fn main() {
             };
 
             match req.op {
+                FsRequest::OP_OPEN if is_mutating_open(req.arg2) && !can_write => {
+                    resp.status = EACCES;
+                }
                 FsRequest::OP_OPEN => {
                     let mut path_len = 0;
                     while path_len < 128 && req.path[path_len] != 0 {
@@ -187,20 +363,30 @@ fn main() {
 
                     if let Ok(path_str) = core::str::from_utf8(&req.path[..path_len]) {
                         unsafe {
-                            if let Some(ref fs) = MOUNTED_FS {
-                                match resolve_path(fs.as_ref(), path_str) {
+                            if let Some(ref mut fs) = MOUNTED_FS {
+                                match open_inode(fs.as_mut(), path_str, req.arg2) {
                                     Ok(inode) => {
                                         let mut handle_idx: i64 = -1;
+                                        // O_APPEND は開いた時点の末尾から書き始める
+                                        let start = if req.arg2 & O_APPEND != 0 {
+                                            fs.stat(inode).map(|attr| attr.size).unwrap_or(0)
+                                        } else {
+                                            0
+                                        };
                                         for i in 0..MAX_HANDLES {
                                             if !HANDLES[i].used {
                                                 HANDLES[i].used = true;
-                                                HANDLES[i].handle = FileHandle::new(inode, 0);
+                                                HANDLES[i].handle =
+                                                    FileHandle::new(inode, req.arg2 as u32);
+                                                HANDLES[i].handle.offset = start;
                                                 HANDLES[i].fs_id = 0;
+                                                HANDLES[i].owner = pid;
                                                 handle_idx = i as i64;
                                                 break;
                                             }
                                         }
                                         resp.status = handle_idx;
+                                        handles_changed = true;
                                     }
                                     Err(e) => {
                                         resp.status = vfs_error_to_errno(e);
@@ -214,7 +400,7 @@ fn main() {
                     let fd = req.arg1 as usize;
                     let read_len = req.arg2 as usize;
 
-                    if fd < MAX_HANDLES && unsafe { HANDLES[fd].used } {
+                    if may_use_handle(fd, pid) {
                         unsafe {
                             if let Some(ref fs) = MOUNTED_FS {
                                 let handle = &mut HANDLES[fd].handle;
@@ -238,29 +424,70 @@ fn main() {
                             }
                         }
                     } else {
-                        resp.status = -9; // EBADF
+                        resp.status = EBADF;
                     }
                 }
+                FsRequest::OP_WRITE if !can_write => {
+                    resp.status = EACCES;
+                }
                 FsRequest::OP_WRITE => {
-                    resp.status = vfs_error_to_errno(VfsError::NotSupported);
+                    let fd = req.arg1 as usize;
+                    let payload = &recv_buf.0[size_of::<FsRequest>()..len as usize];
+                    let write_len = core::cmp::min(req.arg2 as usize, payload.len());
+
+                    if may_use_handle(fd, pid) {
+                        unsafe {
+                            if let Some(ref mut fs) = MOUNTED_FS {
+                                let handle = &mut HANDLES[fd].handle;
+                                match fs.write(handle.inode, handle.offset, &payload[..write_len]) {
+                                    Ok(written) => {
+                                        resp.status = written as i64;
+                                        handle.offset += written as u64;
+                                        handles_changed = true;
+                                    }
+                                    Err(e) => {
+                                        resp.status = vfs_error_to_errno(e);
+                                    }
+                                }
+                            }
+                        }
+                    } else {
+                        resp.status = EBADF;
+                    }
                 }
                 FsRequest::OP_CLOSE => {
                     let fd = req.arg1 as usize;
-                    if fd < MAX_HANDLES && unsafe { HANDLES[fd].used } {
+                    // 最後の参照が消えたハンドルは、カーネルがどのプロセスの文脈からでも閉じる
+                    let orphaned = fd < MAX_HANDLES
+                        && unsafe { HANDLES[fd].used }
+                        && !fs_endpoint::holds(0, fd as u64);
+                    if orphaned || may_use_handle(fd, pid) {
                         unsafe {
                             HANDLES[fd].used = false;
                         }
                         resp.status = 0;
+                        handles_changed = true;
                     } else {
-                        resp.status = -9; // EBADF
+                        resp.status = EBADF;
                     }
                 }
+                FsRequest::OP_HANDOVER => {
+                    // core.service が起動した新しい fs.service からだけ受け付ける
+                    if fs_endpoint::is_successor(sender) {
+                        hand_over(sender);
+                    }
+                    resp.status = -1; // EPERM
+                }
                 _ => {
                     println!("[FS] Unknown OP: {}", req.op);
                     continue;
                 }
             }
 
+            if handles_changed && resp.status >= 0 {
+                handover::save(unsafe { MOUNT }, open_handles());
+            }
+
             let resp_slice = unsafe {
                 core::slice::from_raw_parts(&resp as *const _ as *const u8, size_of::<FsResponse>())
             };
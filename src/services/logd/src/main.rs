@@ -0,0 +1,259 @@
+//! 構造化ログの受け口（logd.service）
+//!
+//! `swiftlib::log` の記録を IPC で受け取り、1 件 1 行で `LOG_PATH` に書き出す。
+//! 監視しているサービスの標準出力・標準エラーは core.service が行ごとの記録にして送ってくる。
+//! 問い合わせにはローテーションした古いファイルも含めて、サービスとレベルで絞り込んだ
+//! 新しい記録を返す。
+//!
+//! 行の形: `[    12.345] WARN  audit: message`
+
+use swiftlib::fs;
+use swiftlib::io;
+use swiftlib::ipc;
+use swiftlib::log::{self, Level, Query, Record};
+
+const LOG_DIR: &str = "/var/log";
+/// 書き出し先（古いものは `.1` .. `.ROTATE_KEEP` に回す）
+const LOG_PATH: &str = "/var/log/messages.log";
+/// これを超えそうになったらローテーションする
+const MAX_LOG_SIZE: usize = 64 * 1024;
+/// 残す世代数
+const ROTATE_KEEP: usize = 3;
+/// 受け取るメッセージの最大長
+const MAX_REQUEST: usize = log::HEADER_SIZE + log::MAX_MESSAGE;
+
+struct MessageLog {
+    /// LOG_PATH の現在の大きさ
+    size: usize,
+    /// まだ書き出していない行
+    pending: String,
+}
+
+impl MessageLog {
+    fn open() -> Self {
+        let _ = fs::mkdir(LOG_DIR, 0o755);
+        let size = match fs::read_file_via_fs(LOG_PATH, MAX_LOG_SIZE * 2) {
+            Ok(Some(data)) => data.len(),
+            _ => 0,
+        };
+        Self {
+            size,
+            pending: String::new(),
+        }
+    }
+
+    /// 記録を行にして溜める（本文が複数行なら行ごとに同じヘッダを付ける）
+    fn record(&mut self, rec: &Record) {
+        let service = sanitize_service(rec.service);
+        let mut lines = rec.message.lines().peekable();
+        if lines.peek().is_none() {
+            self.push_line(rec, &service, "");
+        }
+        for line in lines {
+            self.push_line(rec, &service, line.trim_end_matches('\r'));
+        }
+    }
+
+    fn push_line(&mut self, rec: &Record, service: &str, message: &str) {
+        self.pending.push_str(&format!(
+            "[{:>5}.{:03}] {:<5} {}: {}\n",
+            rec.timestamp_ms / 1000,
+            rec.timestamp_ms % 1000,
+            rec.level.as_str(),
+            service,
+            message
+        ));
+    }
+
+    /// 溜めた行を書き出す
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let text = core::mem::take(&mut self.pending);
+        if self.size > 0 && self.size + text.len() > MAX_LOG_SIZE {
+            rotate();
+            self.size = 0;
+        }
+        let fd = io::open(LOG_PATH, io::O_WRONLY | io::O_CREAT | io::O_APPEND);
+        if fd < 0 {
+            println!("[LOGD] Failed to open {}", LOG_PATH);
+            return;
+        }
+        if write_all(fd as u64, text.as_bytes()) {
+            self.size += text.len();
+        } else {
+            println!("[LOGD] Failed to write {}", LOG_PATH);
+        }
+        let _ = io::close(fd as u64);
+    }
+
+    /// 条件に合う新しい記録を古い順に、応答に収まるだけ返す
+    fn query(&mut self, query: &Query) -> String {
+        self.flush();
+        let room = log::MAX_RESPONSE - log::RESPONSE_HEADER;
+        let limit = if query.limit == 0 {
+            usize::MAX
+        } else {
+            query.limit
+        };
+        let service = query.service.map(short_name);
+
+        let mut files = Vec::new();
+        for n in (1..=ROTATE_KEEP).rev() {
+            files.push(rotated_path(n));
+        }
+        files.push(LOG_PATH.to_string());
+        let mut matches = Vec::new();
+        for path in &files {
+            let Ok(Some(data)) = fs::read_file_via_fs(path, MAX_LOG_SIZE * 2) else {
+                continue;
+            };
+            let text = String::from_utf8_lossy(&data);
+            for line in text.lines() {
+                let Some((level, name)) = parse_line(line) else {
+                    continue;
+                };
+                if level >= query.min_level && service.is_none_or(|s| s == short_name(name)) {
+                    matches.push(line.to_string());
+                }
+            }
+        }
+
+        let mut picked = Vec::new();
+        let mut used = 0;
+        for line in matches.iter().rev().take(limit) {
+            if used + line.len() + 1 > room {
+                break;
+            }
+            used += line.len() + 1;
+            picked.push(line.as_str());
+        }
+        let mut out = String::with_capacity(used);
+        for line in picked.iter().rev() {
+            out.push_str(line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// 行の区切りに使う文字を含まないサービス名にする
+fn sanitize_service(name: &str) -> String {
+    if name.is_empty() {
+        return "unknown".to_string();
+    }
+    name.chars()
+        .map(|c| if c.is_whitespace() || c == ':' { '_' } else { c })
+        .collect()
+}
+
+/// "audit" と "audit.service" を同じに扱う
+fn short_name(name: &str) -> &str {
+    name.strip_suffix(".service").unwrap_or(name)
+}
+
+/// 行を読む → (レベル, サービス名)
+fn parse_line(line: &str) -> Option<(Level, &str)> {
+    let rest = line.strip_prefix('[')?.split_once("] ")?.1;
+    let (level, rest) = rest.split_once(' ')?;
+    let (service, _) = rest.trim_start().split_once(": ")?;
+    Some((Level::parse(level)?, service))
+}
+
+fn write_all(fd: u64, mut data: &[u8]) -> bool {
+    while !data.is_empty() {
+        let n = io::write(fd, data);
+        if (n as i64) <= 0 {
+            return false;
+        }
+        data = &data[(n as usize).min(data.len())..];
+    }
+    true
+}
+
+fn rotated_path(n: usize) -> String {
+    format!("{}.{}", LOG_PATH, n)
+}
+
+/// `from` の中身で `to` を置き換える（fs.service に rename が無いのでコピーする）
+fn copy_file(from: &str, to: &str) {
+    let data = match fs::read_file_via_fs(from, MAX_LOG_SIZE * 2) {
+        Ok(Some(data)) => data,
+        _ => return,
+    };
+    let fd = io::open(to, io::O_WRONLY | io::O_CREAT | io::O_TRUNC);
+    if fd < 0 {
+        println!("[LOGD] Failed to open {} for rotation", to);
+        return;
+    }
+    if !write_all(fd as u64, &data) {
+        println!("[LOGD] Failed to write {} for rotation", to);
+    }
+    let _ = io::close(fd as u64);
+}
+
+/// messages.log → messages.log.1 → .. → messages.log.ROTATE_KEEP と世代を送り、messages.log を空にする
+fn rotate() {
+    for n in (1..ROTATE_KEEP).rev() {
+        copy_file(&rotated_path(n), &rotated_path(n + 1));
+    }
+    copy_file(LOG_PATH, &rotated_path(1));
+    let fd = io::open(LOG_PATH, io::O_WRONLY | io::O_CREAT | io::O_TRUNC);
+    if fd >= 0 {
+        let _ = io::close(fd as u64);
+    }
+    println!("[LOGD] Rotated {}", LOG_PATH);
+}
+
+/// 1 通のメッセージを処理する
+fn handle(log: &mut MessageLog, sender: u64, msg: &[u8]) {
+    if msg.len() < 8 {
+        return;
+    }
+    match u64::from_le_bytes(msg[..8].try_into().unwrap_or([0; 8])) {
+        log::OP_WRITE => match Record::decode(msg) {
+            Some(rec) => log.record(&rec),
+            None => println!("[LOGD] Dropping malformed record from TID={}", sender),
+        },
+        log::OP_QUERY => {
+            let (status, text) = match Query::decode(msg) {
+                Some(query) => (0, log.query(&query)),
+                None => (-22, String::new()), // EINVAL
+            };
+            let mut reply = Vec::with_capacity(log::RESPONSE_HEADER + text.len());
+            reply.extend_from_slice(&(status as i64).to_le_bytes());
+            reply.extend_from_slice(&(text.len() as u64).to_le_bytes());
+            reply.extend_from_slice(text.as_bytes());
+            let _ = ipc::ipc_send(sender, &reply);
+        }
+        _ => {}
+    }
+}
+
+fn main() {
+    println!("[LOGD] Log service started");
+
+    let mut log = MessageLog::open();
+    log.record(&Record::now("logd", Level::Info, "started"));
+    log.flush();
+
+    let mut buf = vec![0u8; MAX_REQUEST];
+    loop {
+        // 届くまで眠り、届いている分をまとめて処理してから書き出す
+        let (sender, len) = ipc::ipc_recv_wait(&mut buf);
+        if sender != 0 {
+            handle(&mut log, sender, &buf[..len as usize]);
+        }
+        loop {
+            let (sender, len) = ipc::ipc_recv(&mut buf);
+            if sender == 0 && len == 0 {
+                break;
+            }
+            if sender != 0 {
+                handle(&mut log, sender, &buf[..len as usize]);
+            }
+        }
+        log.flush();
+    }
+}
@@ -0,0 +1,115 @@
+//! 監査ログ（audit_read システムコール）のユーザー側ラッパー
+//!
+//! レコードの配置はカーネルの `audit::AuditWireRecord` と同じ。
+//! 読み出しには CAP_AUDIT_READ が必要。
+
+use super::sys::{syscall4, SyscallNumber};
+
+/// 本文の最大長
+pub const AUDIT_MSG_LEN: usize = 160;
+
+/// 種別（カーネルの `AuditEventKind` の並び順と同じ番号）
+pub const KIND_DENY: u32 = 0;
+pub const KIND_FAULT: u32 = 1;
+pub const KIND_REVOKE: u32 = 2;
+pub const KIND_QUARANTINE: u32 = 3;
+pub const KIND_RESTART: u32 = 4;
+pub const KIND_POLICY: u32 = 5;
+pub const KIND_USERCOPY: u32 = 6;
+pub const KIND_DEVICE: u32 = 7;
+pub const KIND_EXEC: u32 = 8;
+pub const KIND_IPC: u32 = 9;
+pub const KIND_MEMORY: u32 = 10;
+
+const KIND_NAMES: [&str; 11] = [
+    "deny",
+    "fault",
+    "revoke",
+    "quarantine",
+    "restart",
+    "policy",
+    "usercopy",
+    "device",
+    "exec",
+    "ipc",
+    "memory",
+];
+
+/// 全種別を読むときの種別マスク
+pub const ALL_KINDS: u64 = 0;
+
+/// 監査レコード 1 件
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AuditRecord {
+    pub seq: u64,
+    /// 起動からのティック数（100 回 = 1 秒）
+    pub ticks: u64,
+    /// 0 はプロセス外（起動処理や割り込み）
+    pub pid: u64,
+    pub tid: u64,
+    pub uid: u32,
+    pub kind: u32,
+    pub len: u32,
+    _pad: u32,
+    msg: [u8; AUDIT_MSG_LEN],
+}
+
+impl AuditRecord {
+    pub const fn empty() -> Self {
+        Self {
+            seq: 0,
+            ticks: 0,
+            pid: 0,
+            tid: 0,
+            uid: 0,
+            kind: 0,
+            len: 0,
+            _pad: 0,
+            msg: [0; AUDIT_MSG_LEN],
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        let len = (self.len as usize).min(AUDIT_MSG_LEN);
+        core::str::from_utf8(&self.msg[..len]).unwrap_or("<invalid-audit-utf8>")
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        kind_name(self.kind)
+    }
+}
+
+/// 種別の表示名
+pub fn kind_name(kind: u32) -> &'static str {
+    KIND_NAMES.get(kind as usize).copied().unwrap_or("?")
+}
+
+/// 表示名（`kind_name` の逆）から種別へ
+pub fn parse_kind(name: &str) -> Option<u32> {
+    KIND_NAMES.iter().position(|n| *n == name).map(|i| i as u32)
+}
+
+/// 種別の並びから種別マスクを作る
+pub fn kind_mask(kinds: &[u32]) -> u64 {
+    kinds.iter().fold(0, |mask, kind| mask | (1 << kind))
+}
+
+/// 通し番号が `since` より後の記録を `out` に読み出し、件数を返す
+///
+/// `mask` は `kind_mask` で作った種別マスク（`ALL_KINDS` なら全種別）。
+/// 通し番号が `since + 1` から飛んでいれば、その間の記録はリングから溢れている。
+pub fn read(out: &mut [AuditRecord], since: u64, mask: u64) -> Result<usize, u64> {
+    let ret = syscall4(
+        SyscallNumber::AuditRead as u64,
+        out.as_mut_ptr() as u64,
+        out.len() as u64,
+        since,
+        mask,
+    );
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ret as usize)
+    }
+}
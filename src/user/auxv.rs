@@ -0,0 +1,51 @@
+//! 補助ベクタ（auxv）
+//!
+//! crt0 の `_start` が初期スタックを `__swiftlib_init_auxv` に渡し、
+//! カーネルが積んだ auxv の位置を記録しておく。
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+pub const AT_NULL: u64 = 0;
+pub const AT_PHDR: u64 = 3;
+pub const AT_PHENT: u64 = 4;
+pub const AT_PHNUM: u64 = 5;
+pub const AT_PAGESZ: u64 = 6;
+pub const AT_ENTRY: u64 = 9;
+pub const AT_RANDOM: u64 = 25;
+pub const AT_EXECFN: u64 = 31;
+/// vDSO の ELF ヘッダ
+pub const AT_SYSINFO_EHDR: u64 = 33;
+
+/// auxv の先頭（0 = 未記録）
+static AUXV: AtomicUsize = AtomicUsize::new(0);
+
+/// 初期スタック（argc, argv[], NULL, envp[], NULL, auxv[]）から auxv の先頭を記録する
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __swiftlib_init_auxv(sp: *const u64) {
+    if sp.is_null() {
+        return;
+    }
+    let argc = *sp as usize;
+    let mut p = sp.add(1 + argc + 1);
+    while *p != 0 {
+        p = p.add(1);
+    }
+    AUXV.store(p.add(1) as usize, Ordering::Relaxed);
+}
+
+/// auxv からキーに対応する値を取り出す
+pub fn getauxval(key: u64) -> Option<u64> {
+    let mut p = AUXV.load(Ordering::Relaxed) as *const u64;
+    if p.is_null() || key == AT_NULL {
+        return None;
+    }
+    unsafe {
+        loop {
+            match *p {
+                AT_NULL => return None,
+                k if k == key => return Some(*p.add(1)),
+                _ => p = p.add(2),
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! プロセスケーパビリティ関連のシステムコール（ユーザー側）
+//!
+//! カーネルの `task::capability` と同じビット割り当て・バイナリ表現を使う。
+
+use super::sys::{syscall2, SyscallNumber};
+
+/// DMA 用の仮想→物理アドレス変換
+pub const CAP_DMA: u64 = 1 << 0;
+/// サービスの起動・代理 exec
+pub const CAP_SPAWN_SERVICE: u64 = 1 << 1;
+/// キーボード/マウス入力の注入・タップ
+pub const CAP_INPUT_INJECT: u64 = 1 << 2;
+/// 任意プロセスへのシグナル送信
+pub const CAP_KILL_ANY: u64 = 1 << 3;
+/// 物理ページのマップ・共有ページ
+pub const CAP_SHARED_MEMORY: u64 = 1 << 4;
+/// 他プロセスのケーパビリティ設定
+pub const CAP_SET_CAPS: u64 = 1 << 5;
+/// カーネル設定（コアダンプの出力先など）の変更
+pub const CAP_SYS_CONFIG: u64 = 1 << 6;
+/// 子以外・権限の強いプロセスへの ptrace アタッチ
+pub const CAP_SYS_PTRACE: u64 = 1 << 7;
+/// 監査ログの読み出し
+pub const CAP_AUDIT_READ: u64 = 1 << 8;
+/// トレースポイント・プロファイラの操作と読み出し
+pub const CAP_PERFMON: u64 = 1 << 9;
+/// ディスク上のファイルの作成・変更
+pub const CAP_FS_WRITE: u64 = 1 << 10;
+
+/// フラグの名前（マニフェストなどで使う）
+pub const FLAG_NAMES: &[(&str, u64)] = &[
+    ("CAP_DMA", CAP_DMA),
+    ("CAP_SPAWN_SERVICE", CAP_SPAWN_SERVICE),
+    ("CAP_INPUT_INJECT", CAP_INPUT_INJECT),
+    ("CAP_KILL_ANY", CAP_KILL_ANY),
+    ("CAP_SHARED_MEMORY", CAP_SHARED_MEMORY),
+    ("CAP_SET_CAPS", CAP_SET_CAPS),
+    ("CAP_SYS_CONFIG", CAP_SYS_CONFIG),
+    ("CAP_SYS_PTRACE", CAP_SYS_PTRACE),
+    ("CAP_AUDIT_READ", CAP_AUDIT_READ),
+    ("CAP_PERFMON", CAP_PERFMON),
+    ("CAP_FS_WRITE", CAP_FS_WRITE),
+];
+
+/// 名前からフラグを引く
+pub fn flag_by_name(name: &str) -> Option<u64> {
+    FLAG_NAMES.iter().find(|(n, _)| *n == name).map(|&(_, f)| f)
+}
+
+/// 保持できるポート範囲/MMIO 範囲の最大数
+pub const MAX_CAP_RANGES: usize = 4;
+
+const WORDS: usize = 1 + 1 + MAX_CAP_RANGES * 2 + 1 + MAX_CAP_RANGES * 2;
+const MMIO_BASE: usize = 2 + MAX_CAP_RANGES * 2;
+
+/// ケーパビリティ集合
+///
+/// 範囲は閉区間 `(start, end)`。`const` で組み立てられるので
+/// サービスマニフェストの定数としてそのまま書ける。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapabilitySet {
+    pub flags: u64,
+    pub ports: [(u64, u64); MAX_CAP_RANGES],
+    pub port_count: usize,
+    pub mmio: [(u64, u64); MAX_CAP_RANGES],
+    pub mmio_count: usize,
+}
+
+impl CapabilitySet {
+    /// 何も持たない集合
+    pub const fn empty() -> Self {
+        Self {
+            flags: 0,
+            ports: [(0, 0); MAX_CAP_RANGES],
+            port_count: 0,
+            mmio: [(0, 0); MAX_CAP_RANGES],
+            mmio_count: 0,
+        }
+    }
+
+    /// フラグを追加
+    pub const fn with(mut self, flag: u64) -> Self {
+        self.flags |= flag;
+        self
+    }
+
+    /// I/O ポート範囲 `[start, end]` を追加（上限を超えた分は無視）
+    pub const fn with_ports(mut self, start: u16, end: u16) -> Self {
+        if self.port_count < MAX_CAP_RANGES {
+            self.ports[self.port_count] = (start as u64, end as u64);
+            self.port_count += 1;
+        }
+        self
+    }
+
+    /// 物理 MMIO 範囲 `[start, end]` を追加（上限を超えた分は無視）
+    pub const fn with_mmio(mut self, start: u64, end: u64) -> Self {
+        if self.mmio_count < MAX_CAP_RANGES {
+            self.mmio[self.mmio_count] = (start, end);
+            self.mmio_count += 1;
+        }
+        self
+    }
+
+    pub fn has(&self, flag: u64) -> bool {
+        flag != 0 && (self.flags & flag) == flag
+    }
+
+    pub(crate) fn to_words(self) -> [u64; WORDS] {
+        let mut w = [0u64; WORDS];
+        w[0] = self.flags;
+        w[1] = self.port_count as u64;
+        for (i, (s, e)) in self.ports[..self.port_count].iter().enumerate() {
+            w[2 + i * 2] = *s;
+            w[3 + i * 2] = *e;
+        }
+        w[MMIO_BASE] = self.mmio_count as u64;
+        for (i, (s, e)) in self.mmio[..self.mmio_count].iter().enumerate() {
+            w[MMIO_BASE + 1 + i * 2] = *s;
+            w[MMIO_BASE + 2 + i * 2] = *e;
+        }
+        w
+    }
+
+    fn from_words(w: &[u64; WORDS]) -> Self {
+        let mut set = Self::empty();
+        set.flags = w[0];
+        set.port_count = (w[1] as usize).min(MAX_CAP_RANGES);
+        for i in 0..set.port_count {
+            set.ports[i] = (w[2 + i * 2], w[3 + i * 2]);
+        }
+        set.mmio_count = (w[MMIO_BASE] as usize).min(MAX_CAP_RANGES);
+        for i in 0..set.mmio_count {
+            set.mmio[i] = (w[MMIO_BASE + 1 + i * 2], w[MMIO_BASE + 2 + i * 2]);
+        }
+        set
+    }
+}
+
+/// プロセスのケーパビリティを取得する（`pid == 0` で自プロセス）
+pub fn get_capabilities(pid: u64) -> Result<CapabilitySet, u64> {
+    let mut words = [0u64; WORDS];
+    let ret = syscall2(
+        SyscallNumber::GetCapabilities as u64,
+        pid,
+        words.as_mut_ptr() as u64,
+    );
+    if (ret as i64) < 0 {
+        return Err(ret);
+    }
+    Ok(CapabilitySet::from_words(&words))
+}
+
+/// スレッドが属するプロセスの ID とケーパビリティを取得する
+///
+/// IPC の送信元（`ipc_recv` が返すスレッド ID）の権限を確かめるのに使う。
+pub fn get_thread_capabilities(tid: u64) -> Result<(u64, CapabilitySet), u64> {
+    let mut words = [0u64; WORDS];
+    let ret = syscall2(
+        SyscallNumber::GetThreadCapabilities as u64,
+        tid,
+        words.as_mut_ptr() as u64,
+    );
+    if (ret as i64) < 0 {
+        return Err(ret);
+    }
+    Ok((ret, CapabilitySet::from_words(&words)))
+}
+
+/// プロセスのケーパビリティを設定する（`pid == 0` で自プロセス）
+///
+/// 自分が持っていない権限は付与できない。他プロセスへの設定には CAP_SET_CAPS が必要
+/// （core.service の集合は本人以外には変えられない）。
+pub fn set_capabilities(pid: u64, caps: &CapabilitySet) -> Result<(), u64> {
+    let words = caps.to_words();
+    let ret = syscall2(
+        SyscallNumber::SetCapabilities as u64,
+        pid,
+        words.as_ptr() as u64,
+    );
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
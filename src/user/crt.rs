@@ -9,9 +9,13 @@ global_asm!(
     ".section .text",
     ".global _start",
     "_start:",
-    // カーネルによってスタック上に argc, argv, envp が構築されている。
+    // カーネルによってスタック上に argc, argv, envp, auxv が構築されている。
     // RSP は argc を指している。
 
+    // auxv の位置を記録する (RSP は 16 バイト境界)
+    "mov rdi, rsp",
+    "call __swiftlib_init_auxv",
+
     // argc を取得 (RDI)
     "pop rdi",
 
@@ -35,5 +39,7 @@ extern "C" {
     #[allow(unused)]
     fn main(argc: i32, argv: *const *const u8) -> i32;
     fn _exit(code: i32) -> !;
+    #[allow(unused)]
+    fn __swiftlib_init_auxv(sp: *const u64);
 }
 
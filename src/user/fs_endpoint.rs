@@ -0,0 +1,69 @@
+//! fs.service の引き継ぎ（fs_endpoint システムコール）のユーザー側ラッパー
+//!
+//! fs.service 専用。状態を復元し終えたら `register` でカーネルの要求の送り先になり、
+//! オープン中のハンドルなどが変わるたびに `save` でカーネルに預ける。落ちた後に
+//! 起動した fs.service は `load` で預けられた状態を拾う。
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::sys::{syscall3, SyscallNumber};
+
+const FS_ENDPOINT_REGISTER: u64 = 0;
+const FS_ENDPOINT_QUERY: u64 = 1;
+const FS_ENDPOINT_SAVE: u64 = 2;
+const FS_ENDPOINT_LOAD: u64 = 3;
+const FS_ENDPOINT_HOLDS: u64 = 4;
+const FS_ENDPOINT_IS_SUCCESSOR: u64 = 5;
+
+/// 預けられる状態の最大長（カーネルの FS_STATE_MAX と同じ）
+pub const STATE_MAX: usize = 4096;
+
+fn check(ret: u64) -> Result<u64, u64> {
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
+fn fs_endpoint(op: u64, arg1: u64, arg2: u64) -> Result<u64, u64> {
+    check(syscall3(SyscallNumber::FsEndpoint as u64, op, arg1, arg2))
+}
+
+/// 自分をカーネルの要求の送り先にする
+pub fn register() -> Result<(), u64> {
+    fs_endpoint(FS_ENDPOINT_REGISTER, 0, 0).map(|_| ())
+}
+
+/// いま登録されている fs.service のスレッド ID
+pub fn query() -> Option<u64> {
+    fs_endpoint(FS_ENDPOINT_QUERY, 0, 0)
+        .ok()
+        .filter(|&tid| tid != 0)
+}
+
+/// 状態をカーネルに預ける（`STATE_MAX` バイトまで）
+pub fn save(state: &[u8]) -> Result<(), u64> {
+    fs_endpoint(FS_ENDPOINT_SAVE, state.as_ptr() as u64, state.len() as u64).map(|_| ())
+}
+
+/// 預けられた状態（無ければ空）
+pub fn load() -> Result<Vec<u8>, u64> {
+    let mut buf = vec![0u8; STATE_MAX];
+    let len = fs_endpoint(FS_ENDPOINT_LOAD, buf.as_mut_ptr() as u64, buf.len() as u64)?;
+    buf.truncate(len as usize);
+    Ok(buf)
+}
+
+/// プロセス `pid`（0 ならどれかのプロセス）がハンドル `handle` をカーネルの FD テーブルに
+/// 持っているか（登録したエンドポイントだけが呼べる）
+pub fn holds(pid: u64, handle: u64) -> bool {
+    fs_endpoint(FS_ENDPOINT_HOLDS, handle, pid) == Ok(1)
+}
+
+/// スレッド `tid` が状態を引き継げる fs.service（core.service が起動した別のプロセス）か
+/// （登録したエンドポイントだけが呼べる）
+pub fn is_successor(tid: u64) -> bool {
+    fs_endpoint(FS_ENDPOINT_IS_SUCCESSOR, tid, 0) == Ok(1)
+}
@@ -2,7 +2,7 @@
 
 use super::sys::{syscall1, SyscallNumber, EINVAL, EPERM};
 
-/// raw スキャンコードを入力キューへ注入する（Service/Core専用）
+/// raw スキャンコードを入力キューへ注入する（CAP_INPUT_INJECT 専用）
 #[inline]
 pub fn inject_scancode(scancode: u8) -> Result<(), u64> {
     let ret = syscall1(SyscallNumber::KeyboardInject as u64, scancode as u64);
@@ -13,7 +13,7 @@ pub fn inject_scancode(scancode: u8) -> Result<(), u64> {
     }
 }
 
-/// 4バイトマウスパケットを入力キューへ注入する（Service/Core専用）
+/// 4バイトマウスパケットを入力キューへ注入する（CAP_INPUT_INJECT 専用）
 ///
 /// `buttons`: bit0=Left, bit1=Right, bit2=Middle
 #[inline]
@@ -54,9 +54,51 @@ pub mod mmio;
 /// ファイルシステムIPC定数
 pub mod fs_consts;
 
-/// 特権システムコール（Service権限専用）
+/// 特権システムコール（ケーパビリティ保持プロセス専用）
 pub mod privileged;
 
+/// プロセスケーパビリティ
+pub mod capability;
+
+/// シグナル（sigaction / sigaltstack / sigqueue / signalfd）
+pub mod signal;
+
+/// 補助ベクタ（auxv）
+pub mod auxv;
+
+/// vDSO（clock_gettime / gettimeofday / getcpu の高速経路）
+pub mod vdso;
+
+/// リソース上限とコアダンプ設定
+pub mod resource;
+
+/// プロセストレース（デバッガ用）
+pub mod ptrace;
+
+/// カーネルログ（dmesg 用）
+pub mod syslog;
+
+/// 監査ログ（audit.service 用）
+pub mod audit;
+
+/// syscall トレース（strace 用）
+pub mod strace;
+
+/// トレースポイントとプロファイラ（perf 用）
+pub mod perf;
+
+/// カーネルウォッチドッグへのハートビート
+pub mod watchdog;
+
+/// core.service のサービス管理プロトコル
+pub mod service;
+
+/// fs.service の引き継ぎ
+pub mod fs_endpoint;
+
+/// 構造化ログ（logd.service）
+pub mod log;
+
 #[cfg(not(feature = "std-support"))]
 use core::panic::PanicInfo;
 #[cfg(not(feature = "std-support"))]
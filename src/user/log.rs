@@ -0,0 +1,347 @@
+//! 構造化ログ（logd.service）のプロトコルとクライアント
+//!
+//! 記録はサービス名・レベル・時刻（起動からのミリ秒）・本文からなり、logd.service が
+//! /var/log に書き出す。記録は `HEADER_SIZE` バイトのヘッダ（OP・レベル・時刻・
+//! サービス名の長さ・サービス名）の後に本文が続き、応答は無い。
+//!
+//! 問い合わせはヘッダと同じ形（時刻の代わりに件数）で送り、応答は
+//! `status: i64`（0 か負の errno）と `len: u64` の後に、条件に合う新しい記録が
+//! ファイルと同じ行の形で古い順に続く。
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::{io, ipc, task, time};
+
+/// 記録を書く（応答なし）
+pub const OP_WRITE: u64 = 1;
+/// 記録を問い合わせる
+pub const OP_QUERY: u64 = 2;
+
+/// サービス名の最大長
+pub const MAX_SERVICE: usize = 48;
+/// ヘッダの大きさ
+pub const HEADER_SIZE: usize = 32 + MAX_SERVICE;
+/// 本文の最大長（これより長い本文は切り詰める）
+pub const MAX_MESSAGE: usize = 1024;
+/// 応答ヘッダの大きさ
+pub const RESPONSE_HEADER: usize = 16;
+/// 応答の最大の大きさ（IPC メッセージの上限に収まる）
+pub const MAX_RESPONSE: usize = 4096;
+
+/// logd.service のプロセス名
+const LOGD_NAME: &str = "logd.service";
+/// 応答を待つ時間
+const REPLY_TIMEOUT_MS: u64 = 5000;
+const REPLY_POLL_MS: u64 = 10;
+
+const ESRCH: i64 = -3;
+const EIO: i64 = -5;
+const EINVAL: i64 = -22;
+const ETIMEDOUT: i64 = -110;
+
+/// 見つけた logd.service のスレッド ID（0 なら未発見）
+static LOGD: AtomicU64 = AtomicU64::new(0);
+
+/// 記録の重要度（値は `syslog::LEVEL_*` と同じ）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace = 0,
+    Debug = 1,
+    Info = 2,
+    Warn = 3,
+    Error = 4,
+}
+
+impl Level {
+    pub fn from_u64(v: u64) -> Option<Self> {
+        Some(match v {
+            0 => Self::Trace,
+            1 => Self::Debug,
+            2 => Self::Info,
+            3 => Self::Warn,
+            4 => Self::Error,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        }
+    }
+
+    /// "warn" や "WARN" を読む（"warning"・"err" も受け付ける）
+    pub fn parse(s: &str) -> Option<Self> {
+        let levels = [
+            Self::Trace,
+            Self::Debug,
+            Self::Info,
+            Self::Warn,
+            Self::Error,
+        ];
+        if let Some(level) = levels.iter().find(|l| l.as_str().eq_ignore_ascii_case(s)) {
+            return Some(*level);
+        }
+        if s.eq_ignore_ascii_case("warning") {
+            Some(Self::Warn)
+        } else if s.eq_ignore_ascii_case("err") {
+            Some(Self::Error)
+        } else {
+            None
+        }
+    }
+}
+
+/// 1 件の記録
+#[derive(Debug, Clone, Copy)]
+pub struct Record<'a> {
+    pub service: &'a str,
+    pub level: Level,
+    /// 起動からのミリ秒
+    pub timestamp_ms: u64,
+    pub message: &'a str,
+}
+
+impl<'a> Record<'a> {
+    /// 今の時刻で記録を作る
+    pub fn now(service: &'a str, level: Level, message: &'a str) -> Self {
+        Self {
+            service,
+            level,
+            timestamp_ms: now_ms(),
+            message,
+        }
+    }
+
+    /// OP_WRITE のメッセージにする（サービス名が長すぎれば None）
+    pub fn encode(&self) -> Option<Vec<u8>> {
+        let message = truncate(self.message, MAX_MESSAGE);
+        let mut out = Vec::with_capacity(HEADER_SIZE + message.len());
+        out.extend_from_slice(&encode_header(
+            OP_WRITE,
+            self.level as u64,
+            self.timestamp_ms,
+            self.service,
+        )?);
+        out.extend_from_slice(message.as_bytes());
+        Some(out)
+    }
+
+    /// OP_WRITE のメッセージを読む
+    pub fn decode(bytes: &'a [u8]) -> Option<Self> {
+        let (op, level, timestamp_ms, service) = decode_header(bytes)?;
+        if op != OP_WRITE {
+            return None;
+        }
+        let message = &bytes[HEADER_SIZE..];
+        let message = &message[..message.len().min(MAX_MESSAGE)];
+        Some(Self {
+            service,
+            level: Level::from_u64(level)?,
+            timestamp_ms,
+            message: core::str::from_utf8(message).ok()?,
+        })
+    }
+}
+
+/// 問い合わせの条件
+#[derive(Debug, Clone, Copy)]
+pub struct Query<'a> {
+    /// None ならすべてのサービス
+    pub service: Option<&'a str>,
+    /// これ以上の重要度の記録だけ
+    pub min_level: Level,
+    /// 返す件数の上限（新しいものから）
+    pub limit: usize,
+}
+
+impl<'a> Query<'a> {
+    pub fn encode(&self) -> Option<[u8; HEADER_SIZE]> {
+        encode_header(
+            OP_QUERY,
+            self.min_level as u64,
+            self.limit as u64,
+            self.service.unwrap_or(""),
+        )
+    }
+
+    pub fn decode(bytes: &'a [u8]) -> Option<Self> {
+        let (op, level, limit, service) = decode_header(bytes)?;
+        if op != OP_QUERY {
+            return None;
+        }
+        Some(Self {
+            service: (!service.is_empty()).then_some(service),
+            min_level: Level::from_u64(level)?,
+            limit: limit as usize,
+        })
+    }
+}
+
+fn encode_header(op: u64, level: u64, arg: u64, service: &str) -> Option<[u8; HEADER_SIZE]> {
+    if service.len() > MAX_SERVICE {
+        return None;
+    }
+    let mut out = [0u8; HEADER_SIZE];
+    out[..8].copy_from_slice(&op.to_le_bytes());
+    out[8..16].copy_from_slice(&level.to_le_bytes());
+    out[16..24].copy_from_slice(&arg.to_le_bytes());
+    out[24..32].copy_from_slice(&(service.len() as u64).to_le_bytes());
+    out[32..32 + service.len()].copy_from_slice(service.as_bytes());
+    Some(out)
+}
+
+/// ヘッダを読む → (OP, レベル, 時刻か件数, サービス名)
+fn decode_header(bytes: &[u8]) -> Option<(u64, u64, u64, &str)> {
+    if bytes.len() < HEADER_SIZE {
+        return None;
+    }
+    let word = |i: usize| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap_or([0; 8]));
+    let len = word(3) as usize;
+    if len > MAX_SERVICE {
+        return None;
+    }
+    let service = core::str::from_utf8(&bytes[32..32 + len]).ok()?;
+    Some((word(0), word(1), word(2), service))
+}
+
+/// 文字の途中で切らないよう `max` バイト以内に切り詰める
+fn truncate(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// 起動からのミリ秒（1 tick = 10ms）
+pub fn now_ms() -> u64 {
+    time::get_ticks() * 10
+}
+
+/// logd.service のスレッド ID（`refresh` なら探し直す）
+fn logd(refresh: bool) -> Option<u64> {
+    let cached = LOGD.load(Ordering::Relaxed);
+    if cached != 0 && !refresh {
+        return Some(cached);
+    }
+    let tid = task::find_process_by_name(LOGD_NAME)?;
+    LOGD.store(tid, Ordering::Relaxed);
+    Some(tid)
+}
+
+/// 記録を logd.service に送る
+///
+/// logd.service が見つからなければ ESRCH、受信箱が一杯などで送れなければ EIO。
+pub fn send(record: &Record) -> Result<(), i64> {
+    let msg = record.encode().ok_or(EINVAL)?;
+    let tid = logd(false).ok_or(ESRCH)?;
+    if ipc::ipc_send(tid, &msg) == 0 {
+        return Ok(());
+    }
+    // logd.service が起動し直したかもしれない
+    let retry = logd(true).ok_or(ESRCH)?;
+    if retry != tid && ipc::ipc_send(retry, &msg) == 0 {
+        return Ok(());
+    }
+    Err(EIO)
+}
+
+/// 条件に合う新しい記録を、ファイルと同じ行の形で古い順に返す
+pub fn query(service: Option<&str>, min_level: Level, limit: usize) -> Result<String, i64> {
+    let request = Query {
+        service,
+        min_level,
+        limit,
+    }
+    .encode()
+    .ok_or(EINVAL)?;
+    let tid = logd(true).ok_or(ESRCH)?;
+    if ipc::ipc_send(tid, &request) != 0 {
+        return Err(EIO);
+    }
+    let mut buf = alloc::vec![0u8; MAX_RESPONSE];
+    let mut waited = 0;
+    loop {
+        let (sender, len) = ipc::ipc_recv(&mut buf);
+        if sender == tid && (len as usize) >= RESPONSE_HEADER {
+            let status = i64::from_le_bytes(buf[..8].try_into().unwrap_or([0; 8]));
+            if status < 0 {
+                return Err(status);
+            }
+            let text_len = u64::from_le_bytes(buf[8..16].try_into().unwrap_or([0; 8])) as usize;
+            let end = (RESPONSE_HEADER + text_len).min(len as usize);
+            return Ok(String::from_utf8_lossy(&buf[RESPONSE_HEADER..end]).into_owned());
+        }
+        if sender != 0 {
+            // logd.service 以外からのメッセージは捨てる
+            continue;
+        }
+        if waited >= REPLY_TIMEOUT_MS {
+            return Err(ETIMEDOUT);
+        }
+        time::sleep_ms(REPLY_POLL_MS);
+        waited += REPLY_POLL_MS;
+    }
+}
+
+/// サービスのロガー
+///
+/// ```ignore
+/// static LOG: Logger = Logger::new("audit");
+/// LOG.info("started");
+/// ```
+///
+/// logd.service に送れないとき（起動前など）は標準出力に書く。
+pub struct Logger {
+    service: &'static str,
+}
+
+impl Logger {
+    pub const fn new(service: &'static str) -> Self {
+        Self { service }
+    }
+
+    pub fn log(&self, level: Level, message: &str) {
+        if send(&Record::now(self.service, level, message)).is_err() {
+            let mut line = String::with_capacity(message.len() + 32);
+            line.push('[');
+            line.push_str(self.service);
+            line.push_str("] ");
+            line.push_str(level.as_str());
+            line.push_str(": ");
+            line.push_str(message);
+            line.push('\n');
+            io::print(&line);
+        }
+    }
+
+    pub fn trace(&self, message: &str) {
+        self.log(Level::Trace, message);
+    }
+
+    pub fn debug(&self, message: &str) {
+        self.log(Level::Debug, message);
+    }
+
+    pub fn info(&self, message: &str) {
+        self.log(Level::Info, message);
+    }
+
+    pub fn warn(&self, message: &str) {
+        self.log(Level::Warn, message);
+    }
+
+    pub fn error(&self, message: &str) {
+        self.log(Level::Error, message);
+    }
+}
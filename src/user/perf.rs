@@ -0,0 +1,120 @@
+//! トレースポイントとプロファイラ（perf システムコール）のユーザー側ラッパー
+//!
+//! 記録の配置はカーネルの `trace::TraceRecord` と同じ。状態の取得と読み出しは誰でもでき、
+//! 有効化・停止には CAP_PERFMON が必要（起動オプション `trace=` / `profile` でも有効にできる）。
+//! プロファイラのサンプルは `PROFILE_PATH` を読むと folded 形式で得られる。
+
+use super::sys::{syscall3, SyscallNumber};
+
+const PERF_SET_EVENTS: u64 = 0;
+const PERF_READ_EVENTS: u64 = 1;
+const PERF_PROFILE_START: u64 = 2;
+const PERF_PROFILE_STOP: u64 = 3;
+const PERF_STATUS: u64 = 4;
+
+/// 読むたびに溜まったサンプルを取り出して folded 形式で返す仮想ファイル
+pub const PROFILE_PATH: &str = "/proc/profile";
+
+/// トレースポイントの種類（カーネルの `trace::Tracepoint` と同じ番号）
+pub const EVENT_SCHED: u32 = 0;
+pub const EVENT_IPC_SEND: u32 = 1;
+pub const EVENT_IPC_RECV: u32 = 2;
+pub const EVENT_FAULT: u32 = 3;
+pub const EVENT_IRQ: u32 = 4;
+pub const EVENT_SYSCALL: u32 = 5;
+/// カーネル側で溢れて捨てた記録（`args[0]` に件数）
+pub const EVENT_LOST: u32 = 0xff;
+
+const EVENT_NAMES: [&str; 6] = ["sched", "ipc_send", "ipc_recv", "fault", "irq", "syscall"];
+
+/// 全種類のマスク
+pub const ALL_EVENTS: u32 = (1 << EVENT_NAMES.len()) - 1;
+
+/// トレース記録 1 件
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TraceRecord {
+    /// 起動からのナノ秒
+    pub time_ns: u64,
+    pub pid: u64,
+    pub tid: u64,
+    pub id: u32,
+    pub cpu: u32,
+    pub args: [u64; 3],
+}
+
+impl TraceRecord {
+    pub const fn empty() -> Self {
+        Self {
+            time_ns: 0,
+            pid: 0,
+            tid: 0,
+            id: 0,
+            cpu: 0,
+            args: [0; 3],
+        }
+    }
+}
+
+/// 種類の表示名
+pub fn event_name(id: u32) -> &'static str {
+    if id == EVENT_LOST {
+        return "lost";
+    }
+    EVENT_NAMES.get(id as usize).copied().unwrap_or("?")
+}
+
+/// 表示名（`event_name` の逆）から種類へ
+pub fn parse_event(name: &str) -> Option<u32> {
+    EVENT_NAMES
+        .iter()
+        .position(|n| *n == name)
+        .map(|i| i as u32)
+}
+
+/// 現在の状態
+pub struct Status {
+    /// 有効なトレースポイントのマスク
+    pub events: u32,
+    /// プロファイラが動いているか
+    pub profiling: bool,
+}
+
+fn check(ret: u64) -> Result<u64, u64> {
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
+fn perf(op: u64, arg1: u64, arg2: u64) -> Result<u64, u64> {
+    check(syscall3(SyscallNumber::Perf as u64, op, arg1, arg2))
+}
+
+/// 有効にするトレースポイントを設定し、それまでのマスクを返す
+pub fn set_events(mask: u32) -> Result<u32, u64> {
+    perf(PERF_SET_EVENTS, mask as u64, 0).map(|m| m as u32)
+}
+
+/// 溜まった記録を `out` に読み出し、件数を返す（無ければ 0）
+pub fn read_events(out: &mut [TraceRecord]) -> Result<usize, u64> {
+    perf(PERF_READ_EVENTS, out.as_mut_ptr() as u64, out.len() as u64).map(|n| n as usize)
+}
+
+/// プロファイラを動かす（`interval` ティックに 1 回。1 ティック = 10ms）
+pub fn profile_start(interval: u64) -> Result<(), u64> {
+    perf(PERF_PROFILE_START, interval, 0).map(|_| ())
+}
+
+/// プロファイラを止める
+pub fn profile_stop() -> Result<(), u64> {
+    perf(PERF_PROFILE_STOP, 0, 0).map(|_| ())
+}
+
+pub fn status() -> Result<Status, u64> {
+    perf(PERF_STATUS, 0, 0).map(|s| Status {
+        events: s as u32,
+        profiling: s & (1 << 32) != 0,
+    })
+}
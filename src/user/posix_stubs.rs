@@ -3,7 +3,7 @@
 //! Rust std (build-std) がリンク時に要求する C ライブラリ関数を実装する。
 //! 各関数は最小限の実装か、成功を返すスタブ。
 
-use crate::sys::{syscall1, syscall2, syscall3, syscall4, syscall6, SyscallNumber};
+use crate::sys::{syscall1, syscall2, syscall3, syscall4, syscall5, syscall6, SyscallNumber};
 
 // errno
 static mut ERRNO_VAL: i32 = 0;
@@ -195,18 +195,61 @@ pub unsafe extern "C" fn pthread_getattr_np(
     pthread_attr_init(attr)
 }
 
+// libc の struct sigaction (musl x86-64):
+// sa_handler [+0] u64, sa_mask [+8] 128 bytes, sa_flags [+136] i32, sa_restorer [+144] u64
+const LIBC_SA_MASK: usize = 8;
+const LIBC_SA_FLAGS: usize = 136;
+const LIBC_SA_RESTORER: usize = 144;
+
+/// sigaction: libc のレイアウトをカーネルの struct sigaction に詰め替える
+///
+/// ハンドラの戻り先はカーネルが vDSO のトランポリンに固定するので、sa_restorer はそのまま渡す。
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn sigaction(
-    _signum: i32,
-    _act: *const u8,
-    _oldact: *mut u8,
-) -> i32 {
-    0 // 成功
+pub unsafe extern "C" fn sigaction(signum: i32, act: *const u8, oldact: *mut u8) -> i32 {
+    use crate::signal::SigAction;
+
+    let new = if act.is_null() {
+        None
+    } else {
+        let handler = (act as *const u64).read_unaligned();
+        let mask = (act.add(LIBC_SA_MASK) as *const u64).read_unaligned();
+        let flags = (act.add(LIBC_SA_FLAGS) as *const i32).read_unaligned() as u32 as u64;
+        let restorer = (act.add(LIBC_SA_RESTORER) as *const u64).read_unaligned();
+        Some(SigAction {
+            handler,
+            flags,
+            restorer,
+            mask,
+        })
+    };
+    match crate::signal::sigaction(signum, new.as_ref()) {
+        Ok(old) => {
+            if !oldact.is_null() {
+                core::ptr::write_bytes(oldact, 0, LIBC_SA_RESTORER + 8);
+                (oldact as *mut u64).write_unaligned(old.handler);
+                (oldact.add(LIBC_SA_MASK) as *mut u64).write_unaligned(old.mask);
+                (oldact.add(LIBC_SA_FLAGS) as *mut i32).write_unaligned(old.flags as i32);
+                (oldact.add(LIBC_SA_RESTORER) as *mut u64).write_unaligned(old.restorer);
+            }
+            0
+        }
+        Err(e) => {
+            set_errno(errno_from_neg_ret(e as i64));
+            -1
+        }
+    }
 }
 
+/// sigaltstack: stack_t のレイアウトはカーネルと同じなのでそのまま渡す
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn sigaltstack(_ss: *const u8, _oss: *mut u8) -> i32 {
-    0
+pub unsafe extern "C" fn sigaltstack(ss: *const u8, oss: *mut u8) -> i32 {
+    let ret = syscall2(SyscallNumber::Sigaltstack as u64, ss as u64, oss as u64) as i64;
+    if ret < 0 {
+        set_errno(errno_from_neg_ret(ret));
+        -1
+    } else {
+        0
+    }
 }
 
 /// nanosleep(req, rem) - 簡易実装 (yield で代用)
@@ -219,10 +262,15 @@ pub unsafe extern "C" fn nanosleep(_req: *const u8, _rem: *mut u8) -> i32 {
     0
 }
 
-/// pause() - シグナル待ち (実装なし: すぐリターン)
+/// pause() - シグナルハンドラが走るまで待つ
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn pause() -> i32 {
-    -1 // EINTR
+    const EINTR: i32 = 4;
+    if let Ok(mask) = crate::signal::sigprocmask(crate::signal::SIG_BLOCK, None) {
+        crate::signal::sigsuspend(mask);
+    }
+    set_errno(EINTR);
+    -1
 }
 
 #[unsafe(no_mangle)]
@@ -267,13 +315,31 @@ pub unsafe extern "C" fn socketpair(
 }
 
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn getauxval(_type_: u64) -> u64 {
-    0
+pub unsafe extern "C" fn getauxval(type_: u64) -> u64 {
+    match crate::auxv::getauxval(type_) {
+        Some(value) => value,
+        None => {
+            set_errno(2); // ENOENT
+            0
+        }
+    }
 }
 
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn waitpid(_pid: i32, _status: *mut i32, _options: i32) -> i32 {
-    -1
+pub unsafe extern "C" fn waitpid(pid: i32, status: *mut i32, options: i32) -> i32 {
+    let ret = syscall4(
+        SyscallNumber::Wait as u64,
+        pid as i64 as u64,
+        status as u64,
+        options as u64,
+        0,
+    ) as i64;
+    if ret < 0 {
+        set_errno(errno_from_neg_ret(ret));
+        -1
+    } else {
+        ret as i32
+    }
 }
 
 #[unsafe(no_mangle)]
@@ -433,7 +499,22 @@ pub unsafe extern "C" fn setsid() -> i32 { 1 }
 pub unsafe extern "C" fn execvp(_file: *const u8, _argv: *const *const u8) -> i32 { -1 }
 
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn waitid(_which: i32, _id: u32, _infop: *mut u8, _options: i32) -> i32 { -1 }
+pub unsafe extern "C" fn waitid(which: i32, id: u32, infop: *mut u8, options: i32) -> i32 {
+    let ret = syscall5(
+        SyscallNumber::Waitid as u64,
+        which as u64,
+        id as u64,
+        infop as u64,
+        options as u64,
+        0,
+    ) as i64;
+    if ret < 0 {
+        set_errno(errno_from_neg_ret(ret));
+        -1
+    } else {
+        0
+    }
+}
 
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn poll(fds: *mut u8, nfds: u64, timeout: i32) -> i32 {
@@ -500,17 +581,20 @@ pub unsafe extern "C" fn posix_memalign(
 }
 
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn clock_gettime(_clk_id: i32, tp: *mut u8) -> i32 {
-    // timespec: { tv_sec: i64, tv_nsec: i64 }
-    // タイマーティック (1ティック = 1ms) から計算
-    let ticks = syscall1(SyscallNumber::GetTicks as u64, 0);
-    let sec = (ticks / 1000) as i64;
-    let nsec = ((ticks % 1000) * 1_000_000) as i64;
-    if !tp.is_null() {
-        core::ptr::write(tp as *mut i64, sec);
-        core::ptr::write((tp as *mut i64).add(1), nsec);
+pub unsafe extern "C" fn clock_gettime(clk_id: i32, tp: *mut u8) -> i32 {
+    // timespec: { tv_sec: i64, tv_nsec: i64 }（vDSO があればシステムコールを使わない）
+    match crate::time::clock_gettime(clk_id) {
+        Ok(ts) => {
+            if !tp.is_null() {
+                core::ptr::write(tp as *mut crate::time::Timespec, ts);
+            }
+            0
+        }
+        Err(e) => {
+            set_errno(errno_from_neg_ret(e as i64));
+            -1
+        }
     }
-    0
 }
 
 // ── ファイル I/O（std がリンク時に要求する基本 POSIX 関数）────────────────
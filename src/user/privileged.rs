@@ -1,10 +1,10 @@
-//! 特権システムコール（Service権限プロセス専用）
+//! 特権システムコール（ケーパビリティ保持プロセス専用）
 
 use super::sys::{syscall2, syscall3, syscall4, SyscallNumber};
 
 /// 物理ページ配列をターゲットプロセスのアドレス空間にマップ
 ///
-/// **Service権限専用**: PrivilegeLevel::Service以外から呼び出すとEPERMを返す
+/// **CAP_SHARED_MEMORY 専用**: CAP_SHARED_MEMORY を持たないプロセスから呼び出すとEPERMを返す
 ///
 /// # Arguments
 /// * `target_thread_id` - マップ先のスレッドID
@@ -34,7 +34,7 @@ pub unsafe fn map_physical_pages(
 
 /// 仮想アドレスから物理アドレスを取得
 ///
-/// **Service権限専用**: PrivilegeLevel::Service以外から呼び出すとEPERMを返す
+/// **CAP_DMA 専用**: CAP_DMA を持たないプロセスから呼び出すとEPERMを返す
 ///
 /// # Arguments
 /// * `virt_addr` - 変換したい仮想アドレス
@@ -53,7 +53,7 @@ pub fn get_physical_addr(virt_addr: u64, target_thread_id: u64) -> u64 {
 
 /// 共有用物理ページを割り当て、自プロセスにマップして物理アドレスを返す
 ///
-/// **Service権限専用**: PrivilegeLevel::Service以外から呼び出すとEPERMを返す
+/// **CAP_SHARED_MEMORY 専用**: CAP_SHARED_MEMORY を持たないプロセスから呼び出すとEPERMを返す
 ///
 /// # Arguments
 /// * `page_count` - 割り当てるページ数（最大128）
@@ -95,7 +95,7 @@ pub unsafe fn alloc_shared_pages(
 
 /// 物理ページをアンマップして解放
 ///
-/// **Service権限専用**: PrivilegeLevel::Service以外から呼び出すとEPERMを返す
+/// **CAP_SHARED_MEMORY 専用**: CAP_SHARED_MEMORY を持たないプロセスから呼び出すとEPERMを返す
 ///
 /// # Arguments
 /// * `virt_addr` - アンマップする仮想アドレス（ページ境界）
@@ -116,7 +116,7 @@ pub fn unmap_pages(virt_addr: u64, page_count: u64, deallocate: bool) -> u64 {
 
 /// IPC経由で物理ページをターゲットプロセスへ送信
 ///
-/// **Service権限専用**: PrivilegeLevel::Service以外から呼び出すとEPERMを返す
+/// **CAP_SHARED_MEMORY 専用**: CAP_SHARED_MEMORY を持たないプロセスから呼び出すとEPERMを返す
 ///
 /// # Arguments
 /// * `dest_thread_id` - 送信先スレッドID
@@ -139,3 +139,21 @@ pub unsafe fn ipc_send_pages(dest_thread_id: u64, phys_pages: &[u64], map_start:
         map_start,
     )
 }
+
+/// システムを再起動する
+///
+/// **CAP_SYS_CONFIG 専用**: 成功すれば戻らない
+///
+/// # Returns
+/// エラー時: 負のエラーコード（u64としてキャスト）
+pub fn reboot() -> u64 {
+    const LINUX_REBOOT_MAGIC1: u64 = 0xfee1_dead;
+    const LINUX_REBOOT_MAGIC2: u64 = 672_274_793;
+    const LINUX_REBOOT_CMD_RESTART: u64 = 0x0123_4567;
+    syscall3(
+        SyscallNumber::Reboot as u64,
+        LINUX_REBOOT_MAGIC1,
+        LINUX_REBOOT_MAGIC2,
+        LINUX_REBOOT_CMD_RESTART,
+    )
+}
@@ -1,6 +1,7 @@
 //! プロセス管理関連のシステムコール
 
-use super::sys::{syscall2, SyscallNumber};
+use super::capability::CapabilitySet;
+use super::sys::{syscall2, syscall3, syscall4, SyscallNumber};
 
 /// 実行可能ファイルを起動する
 /// パスから新しいプロセスを起動し、そのPIDを返す
@@ -66,6 +67,92 @@ pub fn exec_with_args(path: &str, args: &[&str]) -> Result<u64, ()> {
     }
 }
 
+/// 引数と環境変数（"KEY=VALUE"）付きで実行可能ファイルを起動する
+/// 失敗したら負の errno を返す
+pub fn exec_with_env(path: &str, args: &[&str], env: &[&str]) -> Result<u64, i64> {
+    let mut path_buf = [0u8; 256];
+    let path_bytes = path.as_bytes();
+    if path_bytes.is_empty() || path_bytes.len() >= 255 {
+        return Err(-22);
+    }
+    path_buf[..path_bytes.len()].copy_from_slice(path_bytes);
+    path_buf[path_bytes.len()] = 0;
+
+    let mut args_buf = [0u8; 512];
+    let mut env_buf = [0u8; 1024];
+    let args_ptr = fill_nul_separated(&mut args_buf, args)?;
+    let env_ptr = fill_nul_separated(&mut env_buf, env)?;
+
+    let result = syscall3(
+        SyscallNumber::ExecWithEnv as u64,
+        path_buf.as_ptr() as u64,
+        args_ptr,
+        env_ptr,
+    );
+    if (result as i64) < 0 {
+        Err(result as i64)
+    } else {
+        Ok(result)
+    }
+}
+
+/// ケーパビリティを指定して実行可能ファイルを起動する（CAP_SET_CAPS が必要）
+///
+/// `caps` は子の最初の命令が走る前から適用される。自分が持っていない権限は与えられない。
+pub fn exec_with_capabilities(
+    path: &str,
+    args: &[&str],
+    env: &[&str],
+    caps: &CapabilitySet,
+) -> Result<u64, i64> {
+    let mut path_buf = [0u8; 256];
+    let path_bytes = path.as_bytes();
+    if path_bytes.is_empty() || path_bytes.len() >= 255 {
+        return Err(-22);
+    }
+    path_buf[..path_bytes.len()].copy_from_slice(path_bytes);
+    path_buf[path_bytes.len()] = 0;
+
+    let mut args_buf = [0u8; 512];
+    let mut env_buf = [0u8; 1024];
+    let args_ptr = fill_nul_separated(&mut args_buf, args)?;
+    let env_ptr = fill_nul_separated(&mut env_buf, env)?;
+    let words = caps.to_words();
+
+    let result = syscall4(
+        SyscallNumber::ExecWithCaps as u64,
+        path_buf.as_ptr() as u64,
+        args_ptr,
+        env_ptr,
+        words.as_ptr() as u64,
+    );
+    if (result as i64) < 0 {
+        Err(result as i64)
+    } else {
+        Ok(result)
+    }
+}
+
+/// "a\0b\0\0" 形式で `buf` に詰め、渡すポインタを返す（空なら 0）
+fn fill_nul_separated(buf: &mut [u8], items: &[&str]) -> Result<u64, i64> {
+    if items.is_empty() {
+        return Ok(0);
+    }
+    let mut pos = 0usize;
+    for item in items {
+        let b = item.as_bytes();
+        if pos + b.len() + 2 > buf.len() {
+            return Err(-7); // E2BIG
+        }
+        buf[pos..pos + b.len()].copy_from_slice(b);
+        pos += b.len();
+        buf[pos] = 0;
+        pos += 1;
+    }
+    buf[pos] = 0;
+    Ok(buf.as_ptr() as u64)
+}
+
 /// メモリ上の ELF データから新プロセスを起動する
 pub fn exec_from_buffer(elf_data: &[u8]) -> Result<u64, ()> {
     use super::sys::syscall2;
@@ -234,3 +321,35 @@ pub fn exec_via_fs_stream(path: &str, args: &[&str]) -> Result<u64, i64> {
     );
     if (res as i64) < 0 { Err(res as i64) } else { Ok(res) }
 }
+
+/// execve: 現在のプロセスを `path` のプログラムで置き換える
+///
+/// args: argv[1..]（argv[0] = path は自動で設定、最大 15 個）。
+/// 成功すると戻らない。失敗したら負の errno を返す。
+pub fn execve(path: &str, args: &[&str]) -> i64 {
+    const MAX_ARGS: usize = 16;
+    let mut strings = [0u8; 768];
+    let mut argv = [0u64; MAX_ARGS + 1];
+    if args.len() + 1 > MAX_ARGS {
+        return -7; // E2BIG
+    }
+
+    let mut pos = 0usize;
+    for (i, s) in core::iter::once(&path).chain(args.iter()).enumerate() {
+        let b = s.as_bytes();
+        if pos + b.len() + 1 > strings.len() {
+            return -7;
+        }
+        strings[pos..pos + b.len()].copy_from_slice(b);
+        strings[pos + b.len()] = 0;
+        argv[i] = strings[pos..].as_ptr() as u64;
+        pos += b.len() + 1;
+    }
+
+    syscall3(
+        SyscallNumber::Execve as u64,
+        argv[0],
+        argv.as_ptr() as u64,
+        0,
+    ) as i64
+}
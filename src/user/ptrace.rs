@@ -0,0 +1,157 @@
+//! プロセストレース（ptrace）のユーザー側ラッパー
+//!
+//! リクエスト番号とレジスタの並びは Linux x86-64 と同じ。
+//! PEEK 系は raw syscall の規約（結果を `data` の指す先へ書く）を隠して値を返す。
+
+use super::sys::{syscall4, SyscallNumber};
+
+pub const PTRACE_TRACEME: u64 = 0;
+pub const PTRACE_PEEKTEXT: u64 = 1;
+pub const PTRACE_PEEKDATA: u64 = 2;
+pub const PTRACE_PEEKUSER: u64 = 3;
+pub const PTRACE_POKETEXT: u64 = 4;
+pub const PTRACE_POKEDATA: u64 = 5;
+pub const PTRACE_POKEUSER: u64 = 6;
+pub const PTRACE_CONT: u64 = 7;
+pub const PTRACE_KILL: u64 = 8;
+pub const PTRACE_SINGLESTEP: u64 = 9;
+pub const PTRACE_GETREGS: u64 = 12;
+pub const PTRACE_SETREGS: u64 = 13;
+pub const PTRACE_ATTACH: u64 = 16;
+pub const PTRACE_DETACH: u64 = 17;
+pub const PTRACE_SYSCALL: u64 = 24;
+pub const PTRACE_SETOPTIONS: u64 = 0x4200;
+pub const PTRACE_GETSIGINFO: u64 = 0x4202;
+
+/// syscall 停止の wait status を SIGTRAP | 0x80 にする
+pub const PTRACE_O_TRACESYSGOOD: u64 = 0x1;
+/// トレーサーが終了したらトレーシーを SIGKILL する
+pub const PTRACE_O_EXITKILL: u64 = 0x10_0000;
+
+/// struct user_regs_struct（Linux x86-64 と同じ並び）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UserRegs {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub orig_rax: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub eflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+    pub fs_base: u64,
+    pub gs_base: u64,
+    pub ds: u64,
+    pub es: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+fn check(ret: u64) -> Result<u64, u64> {
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
+fn request(req: u64, pid: u64, addr: u64, data: u64) -> Result<u64, u64> {
+    check(syscall4(SyscallNumber::Ptrace as u64, req, pid, addr, data))
+}
+
+/// 親プロセスにトレースさせる（このあとの execve で SIGTRAP 停止する）
+pub fn traceme() -> Result<(), u64> {
+    request(PTRACE_TRACEME, 0, 0, 0).map(|_| ())
+}
+
+/// 対象にアタッチし SIGSTOP で止める（子以外には CAP_SYS_PTRACE が必要）
+pub fn attach(pid: u64) -> Result<(), u64> {
+    request(PTRACE_ATTACH, pid, 0, 0).map(|_| ())
+}
+
+/// トレースを外して再開させる
+pub fn detach(pid: u64, sig: u32) -> Result<(), u64> {
+    request(PTRACE_DETACH, pid, 0, sig as u64).map(|_| ())
+}
+
+/// 再開させる（`sig` が非 0 ならそのシグナルを配送させる）
+pub fn cont(pid: u64, sig: u32) -> Result<(), u64> {
+    request(PTRACE_CONT, pid, 0, sig as u64).map(|_| ())
+}
+
+/// 次の syscall 入口/出口まで再開させる
+pub fn syscall(pid: u64, sig: u32) -> Result<(), u64> {
+    request(PTRACE_SYSCALL, pid, 0, sig as u64).map(|_| ())
+}
+
+/// 1 命令だけ実行させる
+pub fn singlestep(pid: u64, sig: u32) -> Result<(), u64> {
+    request(PTRACE_SINGLESTEP, pid, 0, sig as u64).map(|_| ())
+}
+
+/// トレーシーを SIGKILL で終了させる
+pub fn kill(pid: u64) -> Result<(), u64> {
+    request(PTRACE_KILL, pid, 0, 0).map(|_| ())
+}
+
+/// PTRACE_O_* を設定する
+pub fn set_options(pid: u64, options: u64) -> Result<(), u64> {
+    request(PTRACE_SETOPTIONS, pid, 0, options).map(|_| ())
+}
+
+/// トレーシーのメモリから 1 ワード読む
+pub fn peek_data(pid: u64, addr: u64) -> Result<u64, u64> {
+    let mut word = 0u64;
+    request(PTRACE_PEEKDATA, pid, addr, &mut word as *mut u64 as u64)?;
+    Ok(word)
+}
+
+/// トレーシーのメモリへ 1 ワード書く（テキストにも書ける）
+pub fn poke_data(pid: u64, addr: u64, word: u64) -> Result<(), u64> {
+    request(PTRACE_POKEDATA, pid, addr, word).map(|_| ())
+}
+
+/// user_regs_struct 内のオフセット `offset` の値を読む
+pub fn peek_user(pid: u64, offset: u64) -> Result<u64, u64> {
+    let mut word = 0u64;
+    request(PTRACE_PEEKUSER, pid, offset, &mut word as *mut u64 as u64)?;
+    Ok(word)
+}
+
+/// user_regs_struct 内のオフセット `offset` に書く
+pub fn poke_user(pid: u64, offset: u64, word: u64) -> Result<(), u64> {
+    request(PTRACE_POKEUSER, pid, offset, word).map(|_| ())
+}
+
+/// 全レジスタを読む
+pub fn get_regs(pid: u64) -> Result<UserRegs, u64> {
+    let mut regs = UserRegs::default();
+    request(PTRACE_GETREGS, pid, 0, &mut regs as *mut UserRegs as u64)?;
+    Ok(regs)
+}
+
+/// 全レジスタを書く（CS/SS と特権に関わる RFLAGS ビットは無視される）
+pub fn set_regs(pid: u64, regs: &UserRegs) -> Result<(), u64> {
+    request(PTRACE_SETREGS, pid, 0, regs as *const UserRegs as u64).map(|_| ())
+}
+
+/// 停止の原因になった siginfo を読む（siginfo_t 128 バイト）
+pub fn get_siginfo(pid: u64) -> Result<[u8; 128], u64> {
+    let mut buf = [0u8; 128];
+    request(PTRACE_GETSIGINFO, pid, 0, buf.as_mut_ptr() as u64)?;
+    Ok(buf)
+}
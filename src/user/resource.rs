@@ -0,0 +1,92 @@
+//! リソース上限（getrlimit / setrlimit / prlimit64）とコアダンプ設定（ユーザー側）
+
+use super::sys::{syscall1, syscall2, syscall4, SyscallNumber};
+
+/// 上限なし
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+pub const RLIMIT_CPU: u32 = 0;
+pub const RLIMIT_FSIZE: u32 = 1;
+pub const RLIMIT_DATA: u32 = 2;
+pub const RLIMIT_STACK: u32 = 3;
+/// コアダンプの最大サイズ（0 ならダンプしない）
+pub const RLIMIT_CORE: u32 = 4;
+pub const RLIMIT_NOFILE: u32 = 7;
+
+/// struct rlimit
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RLimit {
+    pub cur: u64,
+    pub max: u64,
+}
+
+fn check(ret: u64) -> Result<u64, u64> {
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
+/// 自分のリソース上限を取得
+pub fn getrlimit(resource: u32) -> Result<RLimit, u64> {
+    let mut limit = RLimit { cur: 0, max: 0 };
+    check(syscall2(
+        SyscallNumber::Getrlimit as u64,
+        resource as u64,
+        &mut limit as *mut RLimit as u64,
+    ))?;
+    Ok(limit)
+}
+
+/// 自分のリソース上限を設定（ハードリミットは下げることしかできない）
+pub fn setrlimit(resource: u32, limit: RLimit) -> Result<(), u64> {
+    check(syscall2(
+        SyscallNumber::Setrlimit as u64,
+        resource as u64,
+        &limit as *const RLimit as u64,
+    ))
+    .map(|_| ())
+}
+
+/// 他プロセスのリソース上限を読み書きする（pid 0 は自分、他人は CAP_KILL_ANY が必要）
+///
+/// `new` が Some なら設定し、設定前の値を返す。
+pub fn prlimit(pid: u64, resource: u32, new: Option<RLimit>) -> Result<RLimit, u64> {
+    let mut old = RLimit { cur: 0, max: 0 };
+    let new_ptr = new.as_ref().map(|l| l as *const RLimit as u64).unwrap_or(0);
+    check(syscall4(
+        SyscallNumber::Prlimit64 as u64,
+        pid,
+        resource as u64,
+        new_ptr,
+        &mut old as *mut RLimit as u64,
+    ))?;
+    Ok(old)
+}
+
+/// コアダンプの出力先ディレクトリを `buf` に読み出し、その部分を返す
+pub fn core_dump_dir(buf: &mut [u8]) -> Result<&str, u64> {
+    let len = check(syscall2(
+        SyscallNumber::GetCoreDumpDir as u64,
+        buf.as_mut_ptr() as u64,
+        buf.len() as u64,
+    ))? as usize;
+    core::str::from_utf8(&buf[..len]).map_err(|_| super::sys::EINVAL)
+}
+
+/// コアダンプの出力先ディレクトリを設定する（CAP_SYS_CONFIG が必要）
+pub fn set_core_dump_dir(path: &str) -> Result<(), u64> {
+    let mut path_buf = [0u8; 256];
+    let bytes = path.as_bytes();
+    if bytes.len() >= path_buf.len() {
+        return Err(super::sys::EINVAL);
+    }
+    path_buf[..bytes.len()].copy_from_slice(bytes);
+    check(syscall1(
+        SyscallNumber::SetCoreDumpDir as u64,
+        path_buf.as_ptr() as u64,
+    ))
+    .map(|_| ())
+}
@@ -0,0 +1,300 @@
+//! core.service のサービス管理 IPC プロトコルとクライアント
+//!
+//! 要求は `REQUEST_SIZE` バイト固定（`REQUEST_MAGIC`・OP・名前の長さ・名前）、応答は
+//! `status: i64`（0 か負の errno）と `len: u64` の後に `len` バイトのペイロードが続く。
+//! 一覧と状態のペイロードは `ServiceStatus::SIZE` バイトのレコードの列、ログは UTF-8 の行。
+//! core.service には監視しているサービスの出力も届くので、先頭のマジックと大きさが
+//! 合わないメッセージは要求として扱わない。
+//!
+//! 問い合わせ（list・status・logs）は誰でもできる。stop には CAP_KILL_ANY、
+//! それ以外の操作（start・restart・enable・disable）には CAP_SPAWN_SERVICE が要る。
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{ipc, task, time};
+
+/// サービスの一覧
+pub const OP_LIST: u64 = 1;
+/// 1 つのサービスの状態
+pub const OP_STATUS: u64 = 2;
+/// 起動する
+pub const OP_START: u64 = 3;
+/// 止める（終わるまで起動し直さない）
+pub const OP_STOP: u64 = 4;
+/// 止めてから起動し直す
+pub const OP_RESTART: u64 = 5;
+/// 次回の起動から自動起動する
+pub const OP_ENABLE: u64 = 6;
+/// 次回の起動から自動起動しない
+pub const OP_DISABLE: u64 = 7;
+/// core.service が記録したサービスの出来事
+pub const OP_LOGS: u64 = 8;
+
+/// 要求の先頭に置く印
+pub const REQUEST_MAGIC: [u8; 8] = *b"SVCREQ\0\x01";
+/// サービス名の最大長
+pub const MAX_NAME: usize = 48;
+/// 要求の大きさ
+pub const REQUEST_SIZE: usize = 24 + MAX_NAME;
+/// 応答ヘッダの大きさ
+pub const RESPONSE_HEADER: usize = 16;
+/// 応答の最大の大きさ（IPC メッセージの上限に収まる）
+pub const MAX_RESPONSE: usize = 4096;
+
+/// 応答を待つ時間
+const REPLY_TIMEOUT_MS: u64 = 5000;
+const REPLY_POLL_MS: u64 = 10;
+
+const ENOENT: i64 = -2;
+const ESRCH: i64 = -3;
+const EIO: i64 = -5;
+const EINVAL: i64 = -22;
+const ETIMEDOUT: i64 = -110;
+
+/// サービスの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceState {
+    /// 依存が準備完了になるのを待っている
+    Pending = 0,
+    /// 動いている
+    Running = 1,
+    /// 再起動を待っている
+    BackingOff = 2,
+    /// 止めている途中
+    Stopping = 3,
+    /// 止まっている
+    Stopped = 4,
+    /// 起動できない・落ちすぎて諦めた
+    Failed = 5,
+}
+
+impl ServiceState {
+    pub fn from_u64(v: u64) -> Option<Self> {
+        Some(match v {
+            0 => Self::Pending,
+            1 => Self::Running,
+            2 => Self::BackingOff,
+            3 => Self::Stopping,
+            4 => Self::Stopped,
+            5 => Self::Failed,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::BackingOff => "backoff",
+            Self::Stopping => "stopping",
+            Self::Stopped => "stopped",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// 1 つのサービスの状態（一覧・状態の応答）
+#[derive(Debug, Clone, Copy)]
+pub struct ServiceStatus {
+    name: [u8; MAX_NAME],
+    pub state: ServiceState,
+    /// 動いていなければ 0
+    pub pid: u64,
+    /// 今の起動からの経過時間（動いていなければ 0）
+    pub uptime_ms: u64,
+    /// 起動し直した回数
+    pub restarts: u64,
+    /// 最後に終了したときの終了コード
+    pub last_exit: Option<i32>,
+    /// 準備完了になっている
+    pub ready: bool,
+    /// 自動起動する
+    pub enabled: bool,
+}
+
+impl ServiceStatus {
+    /// レコードの大きさ
+    pub const SIZE: usize = MAX_NAME + 6 * 8;
+
+    pub fn new(name: &str, state: ServiceState) -> Self {
+        let mut buf = [0u8; MAX_NAME];
+        let len = name.len().min(MAX_NAME);
+        buf[..len].copy_from_slice(&name.as_bytes()[..len]);
+        Self {
+            name: buf,
+            state,
+            pid: 0,
+            uptime_ms: 0,
+            restarts: 0,
+            last_exit: None,
+            ready: false,
+            enabled: false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|&b| b == 0).unwrap_or(MAX_NAME);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[..MAX_NAME].copy_from_slice(&self.name);
+        let flags =
+            self.ready as u64 | (self.enabled as u64) << 1 | (self.last_exit.is_some() as u64) << 2;
+        let words = [
+            self.state as u64,
+            self.pid,
+            self.uptime_ms,
+            self.restarts,
+            self.last_exit.unwrap_or(0) as i64 as u64,
+            flags,
+        ];
+        for (i, w) in words.iter().enumerate() {
+            let at = MAX_NAME + i * 8;
+            out[at..at + 8].copy_from_slice(&w.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::SIZE {
+            return None;
+        }
+        let word = |i: usize| {
+            let at = MAX_NAME + i * 8;
+            u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap_or([0; 8]))
+        };
+        let mut name = [0u8; MAX_NAME];
+        name.copy_from_slice(&bytes[..MAX_NAME]);
+        let flags = word(5);
+        Some(Self {
+            name,
+            state: ServiceState::from_u64(word(0))?,
+            pid: word(1),
+            uptime_ms: word(2),
+            restarts: word(3),
+            last_exit: (flags & 4 != 0).then_some(word(4) as i64 as i32),
+            ready: flags & 1 != 0,
+            enabled: flags & 2 != 0,
+        })
+    }
+}
+
+/// 要求を組み立てる（名前が長すぎれば None）
+pub fn encode_request(op: u64, name: &str) -> Option<[u8; REQUEST_SIZE]> {
+    if name.len() > MAX_NAME {
+        return None;
+    }
+    let mut out = [0u8; REQUEST_SIZE];
+    out[..8].copy_from_slice(&REQUEST_MAGIC);
+    out[8..16].copy_from_slice(&op.to_le_bytes());
+    out[16..24].copy_from_slice(&(name.len() as u64).to_le_bytes());
+    out[24..24 + name.len()].copy_from_slice(name.as_bytes());
+    Some(out)
+}
+
+/// 管理の要求として組み立てられたメッセージか
+pub fn is_request(bytes: &[u8]) -> bool {
+    bytes.len() == REQUEST_SIZE && bytes[..8] == REQUEST_MAGIC
+}
+
+/// 要求を読む → (OP, 名前)
+pub fn decode_request(bytes: &[u8]) -> Option<(u64, &str)> {
+    if !is_request(bytes) {
+        return None;
+    }
+    let op = u64::from_le_bytes(bytes[8..16].try_into().ok()?);
+    let len = u64::from_le_bytes(bytes[16..24].try_into().ok()?) as usize;
+    if len > MAX_NAME {
+        return None;
+    }
+    let name = core::str::from_utf8(&bytes[24..24 + len]).ok()?;
+    Some((op, name))
+}
+
+/// 応答を組み立てる（ペイロードは `MAX_RESPONSE` に収まるよう切り詰める）
+pub fn encode_response(status: i64, payload: &[u8]) -> Vec<u8> {
+    let len = payload.len().min(MAX_RESPONSE - RESPONSE_HEADER);
+    let mut out = Vec::with_capacity(RESPONSE_HEADER + len);
+    out.extend_from_slice(&status.to_le_bytes());
+    out.extend_from_slice(&(len as u64).to_le_bytes());
+    out.extend_from_slice(&payload[..len]);
+    out
+}
+
+/// core.service に要求を送り、応答のペイロードを返す
+///
+/// 失敗したら負の errno（core.service が見つからなければ ESRCH、
+/// 応答が来なければ ETIMEDOUT）。
+pub fn call(op: u64, name: &str) -> Result<Vec<u8>, i64> {
+    let request = encode_request(op, name).ok_or(EINVAL)?;
+    let core = task::find_process_by_name("core.service").ok_or(ESRCH)?;
+    if ipc::ipc_send(core, &request) != 0 {
+        return Err(EIO);
+    }
+    let mut buf = alloc::vec![0u8; MAX_RESPONSE];
+    let mut waited = 0;
+    loop {
+        let (sender, len) = ipc::ipc_recv(&mut buf);
+        if sender == core && (len as usize) >= RESPONSE_HEADER {
+            let status = i64::from_le_bytes(buf[..8].try_into().unwrap_or([0; 8]));
+            if status < 0 {
+                return Err(status);
+            }
+            let payload_len = u64::from_le_bytes(buf[8..16].try_into().unwrap_or([0; 8])) as usize;
+            let end = (RESPONSE_HEADER + payload_len).min(len as usize);
+            return Ok(buf[RESPONSE_HEADER..end].to_vec());
+        }
+        if sender != 0 {
+            // core.service 以外からのメッセージは捨てる
+            continue;
+        }
+        if waited >= REPLY_TIMEOUT_MS {
+            return Err(ETIMEDOUT);
+        }
+        time::sleep_ms(REPLY_POLL_MS);
+        waited += REPLY_POLL_MS;
+    }
+}
+
+/// すべてのサービスの状態
+pub fn list() -> Result<Vec<ServiceStatus>, i64> {
+    let payload = call(OP_LIST, "")?;
+    Ok(payload
+        .chunks_exact(ServiceStatus::SIZE)
+        .filter_map(ServiceStatus::from_bytes)
+        .collect())
+}
+
+/// 1 つのサービスの状態
+pub fn status(name: &str) -> Result<ServiceStatus, i64> {
+    let payload = call(OP_STATUS, name)?;
+    ServiceStatus::from_bytes(&payload).ok_or(ENOENT)
+}
+
+pub fn start(name: &str) -> Result<(), i64> {
+    call(OP_START, name).map(|_| ())
+}
+
+pub fn stop(name: &str) -> Result<(), i64> {
+    call(OP_STOP, name).map(|_| ())
+}
+
+pub fn restart(name: &str) -> Result<(), i64> {
+    call(OP_RESTART, name).map(|_| ())
+}
+
+pub fn enable(name: &str) -> Result<(), i64> {
+    call(OP_ENABLE, name).map(|_| ())
+}
+
+pub fn disable(name: &str) -> Result<(), i64> {
+    call(OP_DISABLE, name).map(|_| ())
+}
+
+/// core.service が記録したサービスの出来事（古い順）
+pub fn logs(name: &str) -> Result<String, i64> {
+    call(OP_LOGS, name).map(|payload| String::from_utf8_lossy(&payload).into_owned())
+}
@@ -0,0 +1,333 @@
+//! シグナル関連のシステムコール（ユーザー側）
+//!
+//! カーネルの `syscall::signal` と同じレイアウト（Linux x86-64 互換）を使う。
+//! ハンドラは `handler(sig, &SigInfo, ucontext)` の形で呼ばれ、
+//! 戻り先は常にカーネルの vDSO のトランポリン（sa_restorer は使われない）。
+
+use super::sys::{syscall2, syscall3, syscall4, SyscallNumber};
+
+pub const SIGHUP: i32 = 1;
+pub const SIGINT: i32 = 2;
+pub const SIGQUIT: i32 = 3;
+pub const SIGILL: i32 = 4;
+pub const SIGTRAP: i32 = 5;
+pub const SIGBUS: i32 = 7;
+pub const SIGFPE: i32 = 8;
+pub const SIGKILL: i32 = 9;
+pub const SIGUSR1: i32 = 10;
+pub const SIGSEGV: i32 = 11;
+pub const SIGUSR2: i32 = 12;
+pub const SIGPIPE: i32 = 13;
+pub const SIGALRM: i32 = 14;
+pub const SIGTERM: i32 = 15;
+pub const SIGCHLD: i32 = 17;
+pub const SIGCONT: i32 = 18;
+pub const SIGSTOP: i32 = 19;
+pub const SIGTSTP: i32 = 20;
+/// リアルタイムシグナルの範囲
+pub const SIGRTMIN: i32 = 32;
+pub const SIGRTMAX: i32 = 64;
+
+/// 既定動作
+pub const SIG_DFL: u64 = 0;
+/// 無視
+pub const SIG_IGN: u64 = 1;
+
+pub const SA_NOCLDSTOP: u64 = 0x00000001;
+/// ハンドラに siginfo / ucontext を渡す
+pub const SA_SIGINFO: u64 = 0x00000004;
+pub const SA_RESTORER: u64 = 0x04000000;
+/// 代替シグナルスタック上で実行する
+pub const SA_ONSTACK: u64 = 0x08000000;
+pub const SA_NODEFER: u64 = 0x40000000;
+pub const SA_RESETHAND: u64 = 0x80000000;
+
+pub const SIG_BLOCK: u64 = 0;
+pub const SIG_UNBLOCK: u64 = 1;
+pub const SIG_SETMASK: u64 = 2;
+
+pub const SI_USER: i32 = 0;
+pub const SI_KERNEL: i32 = 0x80;
+pub const SI_QUEUE: i32 = -1;
+pub const SI_TKILL: i32 = -6;
+pub const SEGV_MAPERR: i32 = 1;
+pub const SEGV_ACCERR: i32 = 2;
+
+pub const SS_ONSTACK: i32 = 1;
+pub const SS_DISABLE: i32 = 2;
+pub const MINSIGSTKSZ: usize = 2048;
+pub const SIGSTKSZ: usize = 8192;
+
+/// signalfd: ノンブロッキング
+pub const SFD_NONBLOCK: u64 = 0x800;
+/// signalfd: exec 時にクローズ
+pub const SFD_CLOEXEC: u64 = 0x80000;
+
+/// シグナル番号 → マスクのビット
+pub const fn sig_bit(sig: i32) -> u64 {
+    if sig >= 1 && sig <= 64 {
+        1u64 << (sig - 1)
+    } else {
+        0
+    }
+}
+
+/// カーネルの struct sigaction
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigAction {
+    pub handler: u64,
+    pub flags: u64,
+    pub restorer: u64,
+    pub mask: u64,
+}
+
+impl SigAction {
+    /// ハンドラ関数から作る
+    pub fn new(handler: SigHandler, flags: u64, mask: u64) -> Self {
+        Self {
+            handler: handler as usize as u64,
+            flags,
+            restorer: 0,
+            mask,
+        }
+    }
+
+    /// SIG_DFL / SIG_IGN
+    pub const fn disposition(handler: u64) -> Self {
+        Self {
+            handler,
+            flags: 0,
+            restorer: 0,
+            mask: 0,
+        }
+    }
+}
+
+/// シグナルハンドラ: `(sig, siginfo, ucontext)`
+pub type SigHandler = extern "C" fn(i32, *const SigInfo, *mut u8);
+
+/// siginfo_t（128 バイト）
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SigInfo {
+    pub signo: i32,
+    pub errno: i32,
+    pub code: i32,
+    _pad: i32,
+    fields: [u64; 14],
+}
+
+impl SigInfo {
+    /// 障害アドレス（SIGSEGV など）
+    pub fn addr(&self) -> u64 {
+        self.fields[0]
+    }
+
+    /// 送信元 PID（SIGCHLD では子の PID）
+    pub fn pid(&self) -> u32 {
+        self.fields[0] as u32
+    }
+
+    /// sigqueue で渡された値
+    pub fn value(&self) -> u64 {
+        self.fields[1]
+    }
+
+    /// SIGCHLD の終了コード / 停止シグナル
+    pub fn status(&self) -> i32 {
+        self.fields[1] as i32
+    }
+
+    pub const fn zeroed() -> Self {
+        Self {
+            signo: 0,
+            errno: 0,
+            code: 0,
+            _pad: 0,
+            fields: [0; 14],
+        }
+    }
+}
+
+/// signalfd から読み出すレコード（struct signalfd_siginfo, 128 バイト）
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalfdSiginfo {
+    pub signo: u32,
+    pub errno: i32,
+    pub code: i32,
+    pub pid: u32,
+    pub uid: u32,
+    pub fd: i32,
+    pub tid: u32,
+    pub band: u32,
+    pub overrun: u32,
+    pub trapno: u32,
+    pub status: i32,
+    pub int: i32,
+    pub ptr: u64,
+    pub utime: u64,
+    pub stime: u64,
+    pub addr: u64,
+    _pad: [u8; 48],
+}
+
+impl SignalfdSiginfo {
+    pub const fn zeroed() -> Self {
+        Self {
+            signo: 0,
+            errno: 0,
+            code: 0,
+            pid: 0,
+            uid: 0,
+            fd: 0,
+            tid: 0,
+            band: 0,
+            overrun: 0,
+            trapno: 0,
+            status: 0,
+            int: 0,
+            ptr: 0,
+            utime: 0,
+            stime: 0,
+            addr: 0,
+            _pad: [0; 48],
+        }
+    }
+}
+
+/// 代替シグナルスタック（stack_t）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackT {
+    pub sp: u64,
+    pub flags: i32,
+    _pad: i32,
+    pub size: u64,
+}
+
+impl StackT {
+    pub const fn new(sp: u64, size: u64) -> Self {
+        Self {
+            sp,
+            flags: 0,
+            _pad: 0,
+            size,
+        }
+    }
+
+    pub const fn disabled() -> Self {
+        Self {
+            sp: 0,
+            flags: SS_DISABLE,
+            _pad: 0,
+            size: 0,
+        }
+    }
+}
+
+fn check(ret: u64) -> Result<u64, u64> {
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
+/// シグナルを送る
+pub fn kill(pid: i64, sig: i32) -> Result<(), u64> {
+    check(syscall2(SyscallNumber::Kill as u64, pid as u64, sig as u64)).map(|_| ())
+}
+
+/// シグナルのアクションを設定し、以前のアクションを返す
+pub fn sigaction(sig: i32, action: Option<&SigAction>) -> Result<SigAction, u64> {
+    let mut old = SigAction::disposition(SIG_DFL);
+    let new_ptr = action.map(|a| a as *const SigAction as u64).unwrap_or(0);
+    check(syscall3(
+        SyscallNumber::RtSigaction as u64,
+        sig as u64,
+        new_ptr,
+        &mut old as *mut SigAction as u64,
+    ))?;
+    Ok(old)
+}
+
+/// シグナルマスクを変更し、以前のマスクを返す（`set` が None なら取得のみ）
+pub fn sigprocmask(how: u64, set: Option<u64>) -> Result<u64, u64> {
+    let mut old = 0u64;
+    let set_ptr = set.as_ref().map(|s| s as *const u64 as u64).unwrap_or(0);
+    check(syscall3(
+        SyscallNumber::RtSigprocmask as u64,
+        how,
+        set_ptr,
+        &mut old as *mut u64 as u64,
+    ))?;
+    Ok(old)
+}
+
+/// 代替シグナルスタックを設定し、以前の設定を返す（`stack` が None なら取得のみ）
+pub fn sigaltstack(stack: Option<&StackT>) -> Result<StackT, u64> {
+    let mut old = StackT::disabled();
+    let new_ptr = stack.map(|s| s as *const StackT as u64).unwrap_or(0);
+    check(syscall2(
+        SyscallNumber::Sigaltstack as u64,
+        new_ptr,
+        &mut old as *mut StackT as u64,
+    ))?;
+    Ok(old)
+}
+
+/// 値付きでシグナルを送る（リアルタイムシグナルは送った回数だけ届く）
+pub fn sigqueue(pid: u64, sig: i32, value: u64) -> Result<(), u64> {
+    let mut info = SigInfo::zeroed();
+    info.signo = sig;
+    info.code = SI_QUEUE;
+    info.fields[1] = value;
+    check(syscall3(
+        SyscallNumber::RtSigqueueinfo as u64,
+        pid,
+        sig as u64,
+        &info as *const SigInfo as u64,
+    ))
+    .map(|_| ())
+}
+
+/// `set` のシグナルが届くまで待つ（`timeout_ms` が None なら無期限）
+///
+/// 対象シグナルはあらかじめ sigprocmask でブロックしておくこと。
+/// タイムアウト時は EAGAIN を返す。
+pub fn sigtimedwait(set: u64, timeout_ms: Option<u64>) -> Result<SigInfo, u64> {
+    let mut info = SigInfo::zeroed();
+    let ts = timeout_ms.map(|ms| [(ms / 1000) as i64, ((ms % 1000) * 1_000_000) as i64]);
+    let ts_ptr = ts.as_ref().map(|t| t.as_ptr() as u64).unwrap_or(0);
+    check(syscall4(
+        SyscallNumber::RtSigtimedwait as u64,
+        &set as *const u64 as u64,
+        &mut info as *mut SigInfo as u64,
+        ts_ptr,
+        8,
+    ))?;
+    Ok(info)
+}
+
+/// マスクを一時的に `mask` に置き換えてシグナルハンドラが走るまで眠る
+pub fn sigsuspend(mask: u64) {
+    let _ = syscall2(
+        SyscallNumber::RtSigsuspend as u64,
+        &mask as *const u64 as u64,
+        8,
+    );
+}
+
+/// signalfd を作成する（`fd` に既存の signalfd を渡すとマスクを更新する）
+pub fn signalfd(fd: i32, mask: u64, flags: u64) -> Result<i32, u64> {
+    check(syscall4(
+        SyscallNumber::Signalfd4 as u64,
+        fd as i64 as u64,
+        &mask as *const u64 as u64,
+        8,
+        flags,
+    ))
+    .map(|fd| fd as i32)
+}
@@ -0,0 +1,268 @@
+//! syscall トレース（SyscallTrace システムコール）のユーザー側ラッパー
+//!
+//! イベントの配置はカーネルの `syscall::strace::TraceEvent` と同じ。
+//! 対象にできるのは自分の子（権限が自分以下）だけで、それ以外は CAP_SYS_PTRACE が必要。
+
+use super::sys::{syscall3, SyscallNumber};
+
+const STRACE_ATTACH: u64 = 0;
+const STRACE_DETACH: u64 = 1;
+const STRACE_READ: u64 = 2;
+const STRACE_ATTACH_NEXT_CHILD: u64 = 3;
+const STRACE_COUNT: u64 = 4;
+
+/// 対象が fork した子も対象にする
+pub const STRACE_FOLLOW_FORK: u64 = 1 << 0;
+
+/// syscall の入口（引数と、パスや write のバッファの先頭）
+pub const TRACE_ENTER: u32 = 0;
+/// syscall の出口（戻り値・所要時間と、read のバッファの先頭）
+pub const TRACE_EXIT: u32 = 1;
+/// 対象プロセスの終了（`ret` に終了コード）
+pub const TRACE_PROC_EXIT: u32 = 2;
+/// カーネル側で溢れて捨てたイベント（`ret` に件数）
+pub const TRACE_LOST: u32 = 3;
+
+/// イベントに写し取られるデータの最大長
+pub const TRACE_DATA_LEN: usize = 64;
+
+/// トレースイベント 1 件
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TraceEvent {
+    pub kind: u32,
+    pub data_len: u32,
+    /// 入口と出口で同じ番号
+    pub seq: u64,
+    pub pid: u64,
+    pub tid: u64,
+    pub nr: u64,
+    pub args: [u64; 6],
+    pub ret: u64,
+    /// 起動からのティック数（100 回 = 1 秒）
+    pub ticks: u64,
+    /// 出口イベントの所要時間（計測できなければ 0）
+    pub duration_ns: u64,
+    data: [u8; TRACE_DATA_LEN],
+}
+
+impl TraceEvent {
+    pub const fn empty() -> Self {
+        Self {
+            kind: 0,
+            data_len: 0,
+            seq: 0,
+            pid: 0,
+            tid: 0,
+            nr: 0,
+            args: [0; 6],
+            ret: 0,
+            ticks: 0,
+            duration_ns: 0,
+            data: [0; TRACE_DATA_LEN],
+        }
+    }
+
+    /// 写し取られたデータ（パスやバッファの先頭）
+    pub fn data(&self) -> &[u8] {
+        &self.data[..(self.data_len as usize).min(TRACE_DATA_LEN)]
+    }
+}
+
+fn check(ret: u64) -> Result<u64, u64> {
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
+fn trace(op: u64, arg1: u64, arg2: u64) -> Result<u64, u64> {
+    check(syscall3(SyscallNumber::SyscallTrace as u64, op, arg1, arg2))
+}
+
+/// 動いているプロセスにアタッチする
+pub fn attach(pid: u64, flags: u64) -> Result<(), u64> {
+    trace(STRACE_ATTACH, pid, flags).map(|_| ())
+}
+
+/// 次に fork する自分の子を対象にする（fork の前に呼ぶ）
+pub fn attach_next_child(flags: u64) -> Result<(), u64> {
+    trace(STRACE_ATTACH_NEXT_CHILD, flags, 0).map(|_| ())
+}
+
+/// 対象から外れる（`pid` が 0 なら全部）
+pub fn detach(pid: u64) -> Result<(), u64> {
+    trace(STRACE_DETACH, pid, 0).map(|_| ())
+}
+
+/// 溜まったイベントを `out` に読み出し、件数を返す（無ければ 0）
+pub fn read(out: &mut [TraceEvent]) -> Result<usize, u64> {
+    trace(STRACE_READ, out.as_mut_ptr() as u64, out.len() as u64).map(|n| n as usize)
+}
+
+/// 残っている対象の数
+pub fn target_count() -> Result<usize, u64> {
+    trace(STRACE_COUNT, 0, 0).map(|n| n as usize)
+}
+
+const SYSCALL_NAMES: &[(SyscallNumber, &str)] = &[
+    (SyscallNumber::Read, "read"),
+    (SyscallNumber::Write, "write"),
+    (SyscallNumber::Open, "open"),
+    (SyscallNumber::Close, "close"),
+    (SyscallNumber::Stat, "stat"),
+    (SyscallNumber::Fstat, "fstat"),
+    (SyscallNumber::Lstat, "lstat"),
+    (SyscallNumber::Poll, "poll"),
+    (SyscallNumber::Lseek, "lseek"),
+    (SyscallNumber::Mmap, "mmap"),
+    (SyscallNumber::Mprotect, "mprotect"),
+    (SyscallNumber::Munmap, "munmap"),
+    (SyscallNumber::Brk, "brk"),
+    (SyscallNumber::RtSigaction, "rt_sigaction"),
+    (SyscallNumber::RtSigprocmask, "rt_sigprocmask"),
+    (SyscallNumber::RtSigreturn, "rt_sigreturn"),
+    (SyscallNumber::Ioctl, "ioctl"),
+    (SyscallNumber::Readv, "readv"),
+    (SyscallNumber::Writev, "writev"),
+    (SyscallNumber::Access, "access"),
+    (SyscallNumber::Pipe, "pipe"),
+    (SyscallNumber::Select, "select"),
+    (SyscallNumber::Dup, "dup"),
+    (SyscallNumber::Dup2, "dup2"),
+    (SyscallNumber::Nanosleep, "nanosleep"),
+    (SyscallNumber::GetPid, "getpid"),
+    (SyscallNumber::Clone, "clone"),
+    (SyscallNumber::Fork, "fork"),
+    (SyscallNumber::Execve, "execve"),
+    (SyscallNumber::Exit, "exit"),
+    (SyscallNumber::Wait, "wait4"),
+    (SyscallNumber::Kill, "kill"),
+    (SyscallNumber::Uname, "uname"),
+    (SyscallNumber::Fcntl, "fcntl"),
+    (SyscallNumber::Fsync, "fsync"),
+    (SyscallNumber::Fdatasync, "fdatasync"),
+    (SyscallNumber::Truncate, "truncate"),
+    (SyscallNumber::Ftruncate, "ftruncate"),
+    (SyscallNumber::Getcwd, "getcwd"),
+    (SyscallNumber::Unlink, "unlink"),
+    (SyscallNumber::Readlink, "readlink"),
+    (SyscallNumber::Getrlimit, "getrlimit"),
+    (SyscallNumber::Ptrace, "ptrace"),
+    (SyscallNumber::Getuid, "getuid"),
+    (SyscallNumber::Syslog, "syslog"),
+    (SyscallNumber::Getgid, "getgid"),
+    (SyscallNumber::Geteuid, "geteuid"),
+    (SyscallNumber::Getegid, "getegid"),
+    (SyscallNumber::Setpgid, "setpgid"),
+    (SyscallNumber::GetPpid, "getppid"),
+    (SyscallNumber::Setsid, "setsid"),
+    (SyscallNumber::Getpgid, "getpgid"),
+    (SyscallNumber::Getsid, "getsid"),
+    (SyscallNumber::RtSigtimedwait, "rt_sigtimedwait"),
+    (SyscallNumber::RtSigqueueinfo, "rt_sigqueueinfo"),
+    (SyscallNumber::RtSigsuspend, "rt_sigsuspend"),
+    (SyscallNumber::Sigaltstack, "sigaltstack"),
+    (SyscallNumber::Statfs, "statfs"),
+    (SyscallNumber::ArchPrctl, "arch_prctl"),
+    (SyscallNumber::Setrlimit, "setrlimit"),
+    (SyscallNumber::GetTid, "gettid"),
+    (SyscallNumber::Tkill, "tkill"),
+    (SyscallNumber::Futex, "futex"),
+    (SyscallNumber::Getdents64, "getdents64"),
+    (SyscallNumber::SetTidAddress, "set_tid_address"),
+    (SyscallNumber::ClockGettime, "clock_gettime"),
+    (SyscallNumber::ExitGroup, "exit_group"),
+    (SyscallNumber::Tgkill, "tgkill"),
+    (SyscallNumber::Waitid, "waitid"),
+    (SyscallNumber::Openat, "openat"),
+    (SyscallNumber::Newfstatat, "newfstatat"),
+    (SyscallNumber::Unlinkat, "unlinkat"),
+    (SyscallNumber::Readlinkat, "readlinkat"),
+    (SyscallNumber::Faccessat, "faccessat"),
+    (SyscallNumber::Pselect6, "pselect6"),
+    (SyscallNumber::Ppoll, "ppoll"),
+    (SyscallNumber::SetRobustList, "set_robust_list"),
+    (SyscallNumber::Signalfd, "signalfd"),
+    (SyscallNumber::Signalfd4, "signalfd4"),
+    (SyscallNumber::Pipe2, "pipe2"),
+    (SyscallNumber::Prlimit64, "prlimit64"),
+    (SyscallNumber::Getrandom, "getrandom"),
+    (SyscallNumber::Reboot, "reboot"),
+    (SyscallNumber::Yield, "yield"),
+    (SyscallNumber::GetTicks, "get_ticks"),
+    (SyscallNumber::IpcSend, "ipc_send"),
+    (SyscallNumber::IpcRecv, "ipc_recv"),
+    (SyscallNumber::Exec, "exec"),
+    (SyscallNumber::Sleep, "sleep"),
+    (SyscallNumber::FindProcessByName, "find_process_by_name"),
+    (SyscallNumber::Log, "log"),
+    (SyscallNumber::PortIn, "port_in"),
+    (SyscallNumber::PortOut, "port_out"),
+    (SyscallNumber::Mkdir, "mkdir"),
+    (SyscallNumber::Rmdir, "rmdir"),
+    (SyscallNumber::Readdir, "readdir"),
+    (SyscallNumber::Chdir, "chdir"),
+    (SyscallNumber::KeyboardRead, "keyboard_read"),
+    (SyscallNumber::GetThreadPrivilege, "get_thread_privilege"),
+    (SyscallNumber::GetFramebufferInfo, "get_framebuffer_info"),
+    (SyscallNumber::MapFramebuffer, "map_framebuffer"),
+    (SyscallNumber::ExecFromBuffer, "exec_from_buffer"),
+    (SyscallNumber::SetConsoleCursor, "set_console_cursor"),
+    (SyscallNumber::GetConsoleCursor, "get_console_cursor"),
+    (SyscallNumber::IpcRecvWait, "ipc_recv_wait"),
+    (SyscallNumber::KeyboardReadTap, "keyboard_read_tap"),
+    (SyscallNumber::MouseRead, "mouse_read"),
+    (SyscallNumber::MapPhysicalRange, "map_physical_range"),
+    (SyscallNumber::VirtToPhys, "virt_to_phys"),
+    (SyscallNumber::PortInWords, "port_in_words"),
+    (SyscallNumber::PortOutWords, "port_out_words"),
+    (SyscallNumber::KeyboardInject, "keyboard_inject"),
+    (SyscallNumber::MouseInject, "mouse_inject"),
+    (SyscallNumber::ExecFromBufferNamed, "exec_from_buffer_named"),
+    (
+        SyscallNumber::ExecFromBufferNamedArgs,
+        "exec_from_buffer_named_args",
+    ),
+    (
+        SyscallNumber::ExecFromBufferNamedArgsWithRequester,
+        "exec_from_buffer_named_args_with_requester",
+    ),
+    (SyscallNumber::ExecFromFsStream, "exec_from_fs_stream"),
+    (SyscallNumber::MapPhysicalPages, "map_physical_pages"),
+    (SyscallNumber::GetPhysicalAddr, "get_physical_addr"),
+    (SyscallNumber::AllocSharedPages, "alloc_shared_pages"),
+    (SyscallNumber::UnmapPages, "unmap_pages"),
+    (SyscallNumber::IpcSendPages, "ipc_send_pages"),
+    (SyscallNumber::MouseReadWait, "mouse_read_wait"),
+    (SyscallNumber::ListProcesses, "list_processes"),
+    (SyscallNumber::GetCapabilities, "get_capabilities"),
+    (SyscallNumber::SetCapabilities, "set_capabilities"),
+    (SyscallNumber::GetCoreDumpDir, "get_core_dump_dir"),
+    (SyscallNumber::SetCoreDumpDir, "set_core_dump_dir"),
+    (SyscallNumber::AuditRead, "audit_read"),
+    (SyscallNumber::SyscallTrace, "syscall_trace"),
+    (SyscallNumber::Perf, "perf"),
+    (SyscallNumber::Watchdog, "watchdog"),
+    (SyscallNumber::ExecWithEnv, "exec_with_env"),
+    (SyscallNumber::FsEndpoint, "fs_endpoint"),
+    (SyscallNumber::ExecWithCaps, "exec_with_caps"),
+    (SyscallNumber::GetThreadCapabilities, "get_thread_capabilities"),
+];
+
+/// syscall 番号の名前（知らない番号は None）
+pub fn syscall_name(nr: u64) -> Option<&'static str> {
+    SYSCALL_NAMES
+        .iter()
+        .find(|(n, _)| *n as u64 == nr)
+        .map(|(_, name)| *name)
+}
+
+/// 名前から syscall 番号へ（`syscall_name` の逆）
+pub fn syscall_number(name: &str) -> Option<u64> {
+    SYSCALL_NAMES
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(nr, _)| *nr as u64)
+}
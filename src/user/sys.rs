@@ -44,10 +44,14 @@ pub enum SyscallNumber {
     RtSigprocmask = 14,
     /// Fork
     Fork = 57,
+    /// execve (現在のプロセスのイメージを置き換える)
+    Execve = 59,
     /// プロセス終了
     Exit = 60,
-    /// Wait
+    /// Wait (wait4)
     Wait = 61,
+    /// waitid
+    Waitid = 247,
     /// 現在のプロセスIDを取得
     GetPid = 39,
     /// 現在のスレッドIDを取得
@@ -70,10 +74,104 @@ pub enum SyscallNumber {
     Tkill = 200,
     /// tgkill (スレッドグループ+スレッドID宛てシグナル)
     Tgkill = 234,
+    /// rt_sigtimedwait
+    RtSigtimedwait = 128,
+    /// rt_sigqueueinfo
+    RtSigqueueinfo = 129,
+    /// rt_sigsuspend
+    RtSigsuspend = 130,
+    /// sigaltstack
+    Sigaltstack = 131,
+    /// signalfd4
+    Signalfd4 = 289,
     /// getcwd
     Getcwd = 79,
     /// unlink (ファイル削除)
     Unlink = 87,
+    /// getrlimit
+    Getrlimit = 97,
+    /// setrlimit
+    Setrlimit = 160,
+    /// prlimit64
+    Prlimit64 = 302,
+    /// ptrace
+    Ptrace = 101,
+    /// syslog (カーネルログの読み出しと制御)
+    Syslog = 103,
+    /// ベクタ読み込み
+    Readv = 19,
+    /// mprotect
+    Mprotect = 10,
+    /// access
+    Access = 21,
+    /// pipe
+    Pipe = 22,
+    /// dup
+    Dup = 32,
+    /// dup2
+    Dup2 = 33,
+    /// nanosleep
+    Nanosleep = 35,
+    /// uname
+    Uname = 63,
+    /// fsync
+    Fsync = 74,
+    /// fdatasync
+    Fdatasync = 75,
+    /// truncate
+    Truncate = 76,
+    /// ftruncate
+    Ftruncate = 77,
+    /// readlink
+    Readlink = 89,
+    /// getuid
+    Getuid = 102,
+    /// getgid
+    Getgid = 104,
+    /// geteuid
+    Geteuid = 107,
+    /// getegid
+    Getegid = 108,
+    /// setpgid
+    Setpgid = 109,
+    /// getppid
+    GetPpid = 110,
+    /// setsid
+    Setsid = 112,
+    /// getpgid
+    Getpgid = 121,
+    /// getsid
+    Getsid = 124,
+    /// statfs
+    Statfs = 137,
+    /// getdents64
+    Getdents64 = 217,
+    /// set_tid_address
+    SetTidAddress = 218,
+    /// openat
+    Openat = 257,
+    /// newfstatat (fstatat)
+    Newfstatat = 262,
+    /// unlinkat
+    Unlinkat = 263,
+    /// readlinkat
+    Readlinkat = 267,
+    /// faccessat
+    Faccessat = 269,
+    /// pselect6
+    Pselect6 = 270,
+    /// ppoll
+    Ppoll = 271,
+    /// set_robust_list
+    SetRobustList = 273,
+    /// signalfd
+    Signalfd = 282,
+    /// pipe2
+    Pipe2 = 293,
+    /// getrandom
+    Getrandom = 318,
+    /// reboot（再起動のみ。CAP_SYS_CONFIG 専用）
+    Reboot = 169,
 
     // mochiOS独自syscall (Linux未使用番号帯を使用: 512+)
     /// スケジューラへ譲る
@@ -144,20 +242,44 @@ pub enum SyscallNumber {
     ExecFromBufferNamedArgsWithRequester = 544,
     /// FS 経由のストリーム exec（マップ書き込みを試行）
     ExecFromFsStream = 545,
-    /// 物理ページ配列をターゲットプロセスのアドレス空間にマップ（Service権限専用）
+    /// 物理ページ配列をターゲットプロセスのアドレス空間にマップ（CAP_SHARED_MEMORY 専用）
     MapPhysicalPages = 546,
-    /// 仮想アドレスから物理アドレスを取得（Service権限専用）
+    /// 仮想アドレスから物理アドレスを取得（CAP_DMA 専用）
     GetPhysicalAddr = 547,
-    /// 共有用物理ページを割り当て、自プロセスにマップして物理アドレスを返す（Service権限専用）
+    /// 共有用物理ページを割り当て、自プロセスにマップして物理アドレスを返す（CAP_SHARED_MEMORY 専用）
     AllocSharedPages = 548,
-    /// 物理ページをアンマップして解放（Service権限専用）
+    /// 物理ページをアンマップして解放（CAP_SHARED_MEMORY 専用）
     UnmapPages = 549,
-    /// IPC経由で物理ページをターゲットプロセスへ送信（Service権限専用）
+    /// IPC経由で物理ページをターゲットプロセスへ送信（CAP_SHARED_MEMORY 専用）
     IpcSendPages = 550,
     /// PS/2 マウスの3バイトパケットを読み取る（ブロッキング版）
     MouseReadWait = 551,
     /// プロセス一覧を取得（ユーザーバッファへ書き込む）
     ListProcesses = 552,
+    /// プロセスのケーパビリティを取得 (pid, out_ptr)
+    GetCapabilities = 553,
+    /// プロセスのケーパビリティを設定 (pid, in_ptr)（呼び出し元の部分集合のみ）
+    SetCapabilities = 554,
+    /// コアダンプの出力先ディレクトリを取得 (buf, len)
+    GetCoreDumpDir = 555,
+    /// コアダンプの出力先ディレクトリを設定 (path_ptr)（CAP_SYS_CONFIG 専用）
+    SetCoreDumpDir = 556,
+    /// 監査ログを読み出す (buf, count, since_seq, kind_mask)（CAP_AUDIT_READ 専用）
+    AuditRead = 557,
+    /// syscall トレース (op, arg1, arg2)（操作は strace モジュールを参照）
+    SyscallTrace = 558,
+    /// トレースポイントとプロファイラの操作 (op, arg1, arg2)（CAP_PERFMON 専用）
+    Perf = 559,
+    /// ウォッチドッグの操作 (op, arg1, arg2, arg3)（操作は watchdog モジュールを参照）
+    Watchdog = 560,
+    /// 環境変数付きで実行可能ファイルを実行 (path_ptr, args_ptr, env_ptr)
+    ExecWithEnv = 561,
+    /// fs.service のエンドポイント登録と状態の引き継ぎ (op, ptr, len)
+    FsEndpoint = 562,
+    /// ケーパビリティを指定して実行可能ファイルを実行 (path_ptr, args_ptr, env_ptr, caps_ptr)（CAP_SET_CAPS 専用）
+    ExecWithCaps = 563,
+    /// スレッドが属するプロセスのケーパビリティを取得 (tid, out_ptr) → プロセスID
+    GetThreadCapabilities = 564,
     /// 重力が存在するか
     CheckGravityExist = 999,
 }
@@ -0,0 +1,141 @@
+//! カーネルログ（syslog システムコール）のユーザー側ラッパー
+//!
+//! アクション番号は Linux の syslog(2) と同じ。読み出される各行は
+//! `レベル,通し番号,ティック,CPU,PID;本文` で、本文が複数行なら続きの行は空白で始まる。
+
+use super::sys::{syscall3, SyscallNumber};
+
+pub const SYSLOG_ACTION_READ: u64 = 2;
+pub const SYSLOG_ACTION_READ_ALL: u64 = 3;
+pub const SYSLOG_ACTION_READ_CLEAR: u64 = 4;
+pub const SYSLOG_ACTION_CLEAR: u64 = 5;
+pub const SYSLOG_ACTION_CONSOLE_OFF: u64 = 6;
+pub const SYSLOG_ACTION_CONSOLE_ON: u64 = 7;
+pub const SYSLOG_ACTION_CONSOLE_LEVEL: u64 = 8;
+pub const SYSLOG_ACTION_SIZE_UNREAD: u64 = 9;
+pub const SYSLOG_ACTION_SIZE_BUFFER: u64 = 10;
+
+/// ログレベル（カーネルの util::log::LogLevel と同じ値）
+pub const LEVEL_TRACE: u8 = 0;
+pub const LEVEL_DEBUG: u8 = 1;
+pub const LEVEL_INFO: u8 = 2;
+pub const LEVEL_WARN: u8 = 3;
+pub const LEVEL_ERROR: u8 = 4;
+
+fn check(ret: u64) -> Result<u64, u64> {
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
+/// 生の syslog システムコール
+pub fn syslog(action: u64, buf: &mut [u8]) -> Result<usize, u64> {
+    check(syscall3(
+        SyscallNumber::Syslog as u64,
+        action,
+        buf.as_mut_ptr() as u64,
+        buf.len() as u64,
+    ))
+    .map(|n| n as usize)
+}
+
+/// 残っているログを `buf` に読み出す（入りきらなければ新しい方を優先）
+pub fn read_all(buf: &mut [u8]) -> Result<usize, u64> {
+    syslog(SYSLOG_ACTION_READ_ALL, buf)
+}
+
+/// 残っているログを読み出してから消す（CAP_SYS_CONFIG が必要）
+pub fn read_clear(buf: &mut [u8]) -> Result<usize, u64> {
+    syslog(SYSLOG_ACTION_READ_CLEAR, buf)
+}
+
+/// 未読のログを読み出して既読にする（溜まっていなければ 0）
+pub fn read_unread(buf: &mut [u8]) -> Result<usize, u64> {
+    syslog(SYSLOG_ACTION_READ, buf)
+}
+
+/// ログを消す（CAP_SYS_CONFIG が必要）
+pub fn clear() -> Result<(), u64> {
+    syslog(SYSLOG_ACTION_CLEAR, &mut []).map(|_| ())
+}
+
+/// シリアルへの出力を有効・無効にする（CAP_SYS_CONFIG が必要）
+pub fn set_console(enabled: bool) -> Result<(), u64> {
+    let action = if enabled {
+        SYSLOG_ACTION_CONSOLE_ON
+    } else {
+        SYSLOG_ACTION_CONSOLE_OFF
+    };
+    syslog(action, &mut []).map(|_| ())
+}
+
+/// カーネルのログレベルを設定する（CAP_SYS_CONFIG が必要）
+pub fn set_level(level: u8) -> Result<(), u64> {
+    check(syscall3(
+        SyscallNumber::Syslog as u64,
+        SYSLOG_ACTION_CONSOLE_LEVEL,
+        0,
+        level as u64,
+    ))
+    .map(|_| ())
+}
+
+/// リングバッファの大きさ（バイト）
+pub fn buffer_size() -> Result<usize, u64> {
+    syslog(SYSLOG_ACTION_SIZE_BUFFER, &mut [])
+}
+
+/// レベルの表示名
+pub fn level_name(level: u8) -> &'static str {
+    match level {
+        LEVEL_TRACE => "trace",
+        LEVEL_DEBUG => "debug",
+        LEVEL_INFO => "info",
+        LEVEL_WARN => "warn",
+        LEVEL_ERROR => "err",
+        _ => "?",
+    }
+}
+
+/// 表示名（`level_name` の逆。数字も受け付ける）からレベルへ
+pub fn parse_level(name: &str) -> Option<u8> {
+    match name {
+        "trace" | "0" => Some(LEVEL_TRACE),
+        "debug" | "1" => Some(LEVEL_DEBUG),
+        "info" | "2" => Some(LEVEL_INFO),
+        "warn" | "3" => Some(LEVEL_WARN),
+        "err" | "error" | "4" => Some(LEVEL_ERROR),
+        _ => None,
+    }
+}
+
+/// ログの 1 件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KmsgRecord<'a> {
+    pub level: u8,
+    pub seq: u64,
+    /// 起動からのティック数（100 回 = 1 秒）
+    pub ticks: u64,
+    pub cpu: u32,
+    /// 0 はプロセス外（起動処理や割り込み）
+    pub pid: u64,
+    /// 本文の 1 行目
+    pub text: &'a str,
+}
+
+/// 読み出した 1 行を解析する（続きの行や壊れた行は None）
+pub fn parse_line(line: &str) -> Option<KmsgRecord<'_>> {
+    let (head, text) = line.split_once(';')?;
+    let mut fields = head.split(',');
+    let record = KmsgRecord {
+        level: fields.next()?.parse().ok()?,
+        seq: fields.next()?.parse().ok()?,
+        ticks: fields.next()?.parse().ok()?,
+        cpu: fields.next()?.parse().ok()?,
+        pid: fields.next()?.parse().ok()?,
+        text,
+    };
+    fields.next().is_none().then_some(record)
+}
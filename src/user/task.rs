@@ -34,12 +34,88 @@ pub fn fork() -> i64 {
 
 /// 子プロセスの終了を待つ
 pub fn wait(pid: i64) -> (i64, i32) {
-    let ret = syscall1(SyscallNumber::Wait as u64, pid as u64);
-    if (ret as i64) < 0 {
-        (-1, 0)
-    } else {
-        (ret as i64, 0)
+    match wait4(pid, 0) {
+        Ok(r) => (r.pid, r.status),
+        Err(_) => (-1, 0),
+    }
+}
+
+/// wait4 のオプション: 対象がいなければすぐ戻る
+pub const WNOHANG: u64 = 0x1;
+/// wait4 のオプション: 停止した子も報告する
+pub const WUNTRACED: u64 = 0x2;
+/// wait4 のオプション: SIGCONT で再開した子も報告する
+pub const WCONTINUED: u64 = 0x8;
+
+/// 子プロセスの CPU 使用時間（struct rusage の ru_utime / ru_stime）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rusage {
+    pub utime_sec: u64,
+    pub utime_usec: u64,
+    pub stime_sec: u64,
+    pub stime_usec: u64,
+}
+
+/// wait4 の結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitResult {
+    /// 状態が変化した子の PID（WNOHANG で対象なしなら 0）
+    pub pid: i64,
+    /// 生の wait status
+    pub status: i32,
+    pub rusage: Rusage,
+}
+
+impl WaitResult {
+    /// 正常終了したか
+    pub fn exited(&self) -> bool {
+        self.status & 0x7f == 0
+    }
+    /// 終了コード
+    pub fn exit_status(&self) -> i32 {
+        (self.status >> 8) & 0xff
+    }
+    /// 停止したか
+    pub fn stopped(&self) -> bool {
+        self.status & 0xff == 0x7f
+    }
+    /// 停止させたシグナル
+    pub fn stop_signal(&self) -> i32 {
+        (self.status >> 8) & 0xff
+    }
+    /// 再開したか
+    pub fn continued(&self) -> bool {
+        self.status == 0xffff
+    }
+}
+
+/// 子プロセスの状態変化を待つ（wait4）
+///
+/// `pid`: >0 = 指定PID, -1 = 任意の子, 0 = 同じプロセスグループ, < -1 = グループ -pid
+pub fn wait4(pid: i64, options: u64) -> Result<WaitResult, i64> {
+    use super::sys::syscall4;
+    let mut status: i32 = 0;
+    let mut rusage = [0u64; 18];
+    let ret = syscall4(
+        SyscallNumber::Wait as u64,
+        pid as u64,
+        &mut status as *mut i32 as u64,
+        options,
+        rusage.as_mut_ptr() as u64,
+    ) as i64;
+    if ret < 0 {
+        return Err(ret);
     }
+    Ok(WaitResult {
+        pid: ret,
+        status,
+        rusage: Rusage {
+            utime_sec: rusage[0],
+            utime_usec: rusage[1],
+            stime_sec: rusage[2],
+            stime_usec: rusage[3],
+        },
+    })
 }
 
 /// 子プロセスの終了を非ブロッキングで確認する（WNOHANG）
@@ -68,10 +144,9 @@ pub enum WaitNonblockingStatus {
 
 /// 子プロセスの終了を非ブロッキングで確認する（WNOHANG, 詳細版）
 pub fn wait_nonblocking_status(pid: i64) -> WaitNonblockingStatus {
-    use super::sys::syscall3;
-    const WNOHANG: u64 = 0x1;
+    use super::sys::syscall4;
     const ECHILD: i64 = -10;
-    let ret = syscall3(SyscallNumber::Wait as u64, pid as u64, 0, WNOHANG);
+    let ret = syscall4(SyscallNumber::Wait as u64, pid as u64, 0, WNOHANG, 0);
     let ret_i64 = ret as i64;
     if ret_i64 > 0 {
         WaitNonblockingStatus::Exited(ret_i64)
@@ -1,6 +1,26 @@
 //! 時刻系システムコール（ユーザー側）
 
-use super::sys::{syscall0, syscall1, SyscallNumber};
+use super::sys::{syscall0, syscall1, syscall2, SyscallNumber};
+use super::vdso;
+
+pub const CLOCK_REALTIME: i32 = 0;
+pub const CLOCK_MONOTONIC: i32 = 1;
+
+/// struct timespec
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timespec {
+    pub sec: i64,
+    pub nsec: i64,
+}
+
+/// struct timeval
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timeval {
+    pub sec: i64,
+    pub usec: i64,
+}
 
 /// タイマーティック数を取得
 pub fn get_ticks() -> u64 {
@@ -11,3 +31,39 @@ pub fn get_ticks() -> u64 {
 pub fn sleep_ms(ms: u64) {
     syscall1(SyscallNumber::Sleep as u64, ms);
 }
+
+/// 時刻を取得（vDSO があればシステムコールを使わない）
+pub fn clock_gettime(clk: i32) -> Result<Timespec, u64> {
+    let mut ts = Timespec::default();
+    let ret = match vdso::clock_gettime(clk, &mut ts) {
+        Some(ret) => ret as u64,
+        None => syscall2(
+            SyscallNumber::ClockGettime as u64,
+            clk as u64,
+            &mut ts as *mut Timespec as u64,
+        ),
+    };
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ts)
+    }
+}
+
+/// 現在時刻をマイクロ秒精度で取得
+pub fn gettimeofday() -> Result<Timeval, u64> {
+    let mut tv = Timeval::default();
+    if vdso::gettimeofday(&mut tv).is_some() {
+        return Ok(tv);
+    }
+    let ts = clock_gettime(CLOCK_REALTIME)?;
+    Ok(Timeval {
+        sec: ts.sec,
+        usec: ts.nsec / 1000,
+    })
+}
+
+/// 実行中の CPU 番号（vDSO がなければ None）
+pub fn getcpu() -> Option<u32> {
+    vdso::getcpu()
+}
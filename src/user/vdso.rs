@@ -0,0 +1,122 @@
+//! vDSO（カーネルが全プロセスへマップする共有ページ）
+//!
+//! AT_SYSINFO_EHDR の ELF イメージから `__vdso_*` シンボルを引き、
+//! システムコールを経由せずに時刻や CPU 番号を読む。
+//! vDSO が見つからなければ各関数は None を返すので、呼び出し側でシステムコールへ戻す。
+
+use super::auxv::{getauxval, AT_SYSINFO_EHDR};
+use super::time::{Timespec, Timeval};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const DT_NULL: u64 = 0;
+const DT_HASH: u64 = 4;
+const DT_STRTAB: u64 = 5;
+const DT_SYMTAB: u64 = 6;
+const SYM_SIZE: u64 = 24;
+
+/// シンボル未解決
+const UNRESOLVED: u64 = 0;
+/// シンボルなし
+const MISSING: u64 = 1;
+
+static CLOCK_GETTIME: AtomicU64 = AtomicU64::new(UNRESOLVED);
+static GETTIMEOFDAY: AtomicU64 = AtomicU64::new(UNRESOLVED);
+static GETCPU: AtomicU64 = AtomicU64::new(UNRESOLVED);
+
+unsafe fn read<T: Copy>(addr: u64) -> T {
+    (addr as *const T).read_unaligned()
+}
+
+/// vDSO の動的シンボル表から `name` のアドレスを探す
+pub fn lookup(name: &str) -> Option<u64> {
+    let ehdr = getauxval(AT_SYSINFO_EHDR).filter(|&v| v != 0)?;
+    unsafe {
+        let phoff: u64 = read(ehdr + 32);
+        let phentsize: u16 = read(ehdr + 54);
+        let phnum: u16 = read(ehdr + 56);
+
+        let mut load_bias = None;
+        let mut dynamic = None;
+        for i in 0..phnum as u64 {
+            let ph = ehdr + phoff + i * phentsize as u64;
+            let p_offset: u64 = read(ph + 8);
+            let p_vaddr: u64 = read(ph + 16);
+            match read::<u32>(ph) {
+                PT_LOAD => load_bias = Some((ehdr + p_offset).wrapping_sub(p_vaddr)),
+                PT_DYNAMIC => dynamic = Some(ehdr + p_offset),
+                _ => {}
+            }
+        }
+        let (bias, mut dyn_entry) = (load_bias?, dynamic?);
+
+        let (mut hash, mut strtab, mut symtab) = (0, 0, 0);
+        loop {
+            let tag: u64 = read(dyn_entry);
+            let value: u64 = read(dyn_entry + 8);
+            match tag {
+                DT_NULL => break,
+                DT_HASH => hash = bias + value,
+                DT_STRTAB => strtab = bias + value,
+                DT_SYMTAB => symtab = bias + value,
+                _ => {}
+            }
+            dyn_entry += 16;
+        }
+        if hash == 0 || strtab == 0 || symtab == 0 {
+            return None;
+        }
+
+        let nsym: u32 = read(hash + 4);
+        for i in 1..nsym as u64 {
+            let sym = symtab + i * SYM_SIZE;
+            let st_name: u32 = read(sym);
+            let st_shndx: u16 = read(sym + 6);
+            if st_shndx == 0 {
+                continue;
+            }
+            let sym_name = core::ffi::CStr::from_ptr((strtab + st_name as u64) as *const _);
+            if sym_name.to_bytes() == name.as_bytes() {
+                return Some(bias + read::<u64>(sym + 8));
+            }
+        }
+    }
+    None
+}
+
+fn resolve(slot: &AtomicU64, name: &str) -> Option<u64> {
+    match slot.load(Ordering::Relaxed) {
+        UNRESOLVED => {
+            let addr = lookup(name);
+            slot.store(addr.unwrap_or(MISSING), Ordering::Relaxed);
+            addr
+        }
+        MISSING => None,
+        addr => Some(addr),
+    }
+}
+
+/// `__vdso_clock_gettime` を呼ぶ（戻り値はシステムコールと同じく 0 か負の errno）
+pub fn clock_gettime(clk: i32, ts: &mut Timespec) -> Option<i64> {
+    let addr = resolve(&CLOCK_GETTIME, "__vdso_clock_gettime")?;
+    let f: extern "C" fn(i32, *mut Timespec) -> i64 = unsafe { core::mem::transmute(addr) };
+    Some(f(clk, ts))
+}
+
+/// `__vdso_gettimeofday` を呼ぶ
+pub fn gettimeofday(tv: &mut Timeval) -> Option<i64> {
+    let addr = resolve(&GETTIMEOFDAY, "__vdso_gettimeofday")?;
+    let f: extern "C" fn(*mut Timeval, *mut u8) -> i64 = unsafe { core::mem::transmute(addr) };
+    Some(f(tv, core::ptr::null_mut()))
+}
+
+/// `__vdso_getcpu` で現在の CPU 番号を得る
+pub fn getcpu() -> Option<u32> {
+    let addr = resolve(&GETCPU, "__vdso_getcpu")?;
+    let f: extern "C" fn(*mut u32, *mut u32, *mut u8) -> i64 =
+        unsafe { core::mem::transmute(addr) };
+    let mut cpu = 0u32;
+    f(&mut cpu, core::ptr::null_mut(), core::ptr::null_mut());
+    Some(cpu)
+}
@@ -0,0 +1,67 @@
+//! カーネルウォッチドッグ（watchdog システムコール）のユーザー側ラッパー
+//!
+//! 登録したプロセスは `timeout_ms` ごとに `heartbeat` を呼ぶ。止まったら登録時の
+//! `Action` が取られる。自分以外の登録・解除には CAP_SPAWN_SERVICE が必要。
+
+use super::sys::{syscall4, SyscallNumber};
+
+const WATCHDOG_HEARTBEAT: u64 = 0;
+const WATCHDOG_REGISTER: u64 = 1;
+const WATCHDOG_UNREGISTER: u64 = 2;
+const WATCHDOG_STATUS: u64 = 3;
+
+/// タイムアウトの下限（ミリ秒）
+pub const MIN_TIMEOUT_MS: u64 = 100;
+
+/// タイムアウトしたときの対処（カーネルの `watchdog::Action` と同じ番号）
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// 殺す（core.service なら再起動する）
+    Restart = 0,
+    /// スレッドの状態を書き出すだけ
+    Dump = 1,
+    /// 殺して縮退モードに入る
+    SafeMode = 2,
+}
+
+/// 現在の状態
+pub struct Status {
+    /// 縮退モード中か（core.service は必須サービスだけを起動する）
+    pub safe_mode: bool,
+    /// ウォッチドッグが core.service を再起動した回数
+    pub core_restarts: u32,
+}
+
+fn check(ret: u64) -> Result<u64, u64> {
+    if (ret as i64) < 0 {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
+fn watchdog(op: u64, arg1: u64, arg2: u64, arg3: u64) -> Result<u64, u64> {
+    check(syscall4(SyscallNumber::Watchdog as u64, op, arg1, arg2, arg3))
+}
+
+/// 生きていることを知らせる
+pub fn heartbeat() -> Result<(), u64> {
+    watchdog(WATCHDOG_HEARTBEAT, 0, 0, 0).map(|_| ())
+}
+
+/// `pid`（0 なら自分）を見張ってもらう
+pub fn register(pid: u64, timeout_ms: u64, action: Action) -> Result<(), u64> {
+    watchdog(WATCHDOG_REGISTER, pid, timeout_ms, action as u64).map(|_| ())
+}
+
+/// `pid`（0 なら自分）の見張りをやめてもらう
+pub fn unregister(pid: u64) -> Result<(), u64> {
+    watchdog(WATCHDOG_UNREGISTER, pid, 0, 0).map(|_| ())
+}
+
+pub fn status() -> Result<Status, u64> {
+    watchdog(WATCHDOG_STATUS, 0, 0, 0).map(|s| Status {
+        safe_mode: s & 1 != 0,
+        core_restarts: (s >> 32) as u32,
+    })
+}
@@ -0,0 +1,161 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::{self, Write};
+use swiftlib::{io, syslog};
+
+/// 一度に読み出すバッファ（カーネルのリングバッファより大きければよい）
+const BUF_SIZE: usize = 192 * 1024;
+
+struct Stdout;
+
+impl Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        io::print(s);
+        Ok(())
+    }
+}
+
+fn usage() -> i32 {
+    io::print(
+        "Usage: dmesg [-c | -C] [-r] [-l LEVEL] [-n LEVEL] [-D | -E]\n\
+         \x20 -c        print and clear the kernel log\n\
+         \x20 -C        clear the kernel log\n\
+         \x20 -r        print raw records\n\
+         \x20 -l LEVEL  print only messages at LEVEL or above\n\
+         \x20 -n LEVEL  set the kernel log level\n\
+         \x20 -D / -E   disable / enable kernel messages on the console\n\
+         LEVEL: trace, debug, info, warn, err (or 0-4)\n",
+    );
+    1
+}
+
+fn arg<'a>(argv: *const *const u8, i: i32) -> Option<&'a str> {
+    unsafe {
+        let arg_ptr = *argv.offset(i as isize);
+        if arg_ptr.is_null() {
+            return None;
+        }
+        let mut len = 0;
+        while *arg_ptr.offset(len) != 0 {
+            len += 1;
+        }
+        core::str::from_utf8(core::slice::from_raw_parts(arg_ptr, len as usize)).ok()
+    }
+}
+
+fn fail(what: &str, err: u64) -> i32 {
+    let _ = writeln!(Stdout, "dmesg: {}: error {}", what, err as i64);
+    1
+}
+
+fn print_record(line: &str, min_level: u8, raw: bool, shown: &mut bool) {
+    // 続きの行は直前の行が表示されたときだけ出す
+    if let Some(rest) = line.strip_prefix(' ') {
+        if *shown && raw {
+            let _ = writeln!(Stdout, "{}", line);
+        } else if *shown {
+            let _ = writeln!(Stdout, "    {}", rest);
+        }
+        return;
+    }
+    let Some(rec) = syslog::parse_line(line) else {
+        *shown = false;
+        return;
+    };
+    *shown = rec.level >= min_level;
+    if !*shown {
+        return;
+    }
+    if raw {
+        let _ = writeln!(Stdout, "{}", line);
+        return;
+    }
+    let _ = write!(
+        Stdout,
+        "[{:>5}.{:02}] {:<5} ",
+        rec.ticks / 100,
+        rec.ticks % 100,
+        syslog::level_name(rec.level)
+    );
+    if rec.pid != 0 {
+        let _ = write!(Stdout, "cpu{} pid {}: ", rec.cpu, rec.pid);
+    } else {
+        let _ = write!(Stdout, "cpu{} kernel: ", rec.cpu);
+    }
+    let _ = writeln!(Stdout, "{}", rec.text);
+}
+
+#[no_mangle]
+pub extern "C" fn main(argc: i32, argv: *const *const u8) -> i32 {
+    let mut clear_after = false;
+    let mut clear_only = false;
+    let mut raw = false;
+    let mut min_level = syslog::LEVEL_TRACE;
+    let mut set_level = None;
+    let mut console = None;
+
+    let mut i = 1;
+    while i < argc {
+        let Some(a) = arg(argv, i) else {
+            return usage();
+        };
+        match a {
+            "-c" => clear_after = true,
+            "-C" => clear_only = true,
+            "-r" => raw = true,
+            "-D" => console = Some(false),
+            "-E" => console = Some(true),
+            "-l" | "-n" => {
+                i += 1;
+                let Some(level) = arg(argv, i).and_then(syslog::parse_level) else {
+                    return usage();
+                };
+                if a == "-l" {
+                    min_level = level;
+                } else {
+                    set_level = Some(level);
+                }
+            }
+            _ => return usage(),
+        }
+        i += 1;
+    }
+
+    if let Some(level) = set_level {
+        if let Err(e) = syslog::set_level(level) {
+            return fail("set level", e);
+        }
+    }
+    if let Some(enabled) = console {
+        if let Err(e) = syslog::set_console(enabled) {
+            return fail("console", e);
+        }
+    }
+    if clear_only {
+        return match syslog::clear() {
+            Ok(()) => 0,
+            Err(e) => fail("clear", e),
+        };
+    }
+    if set_level.is_some() || console.is_some() {
+        return 0;
+    }
+
+    let mut buf = [0u8; BUF_SIZE];
+    let result = if clear_after {
+        syslog::read_clear(&mut buf)
+    } else {
+        syslog::read_all(&mut buf)
+    };
+    let len = match result {
+        Ok(n) => n,
+        Err(e) => return fail("read", e),
+    };
+    let text = core::str::from_utf8(&buf[..len]).unwrap_or("");
+    let mut shown = false;
+    for line in text.lines() {
+        print_record(line, min_level, raw, &mut shown);
+    }
+    0
+}
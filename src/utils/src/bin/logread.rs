@@ -0,0 +1,95 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+use core::fmt::{self, Write};
+use swiftlib::io;
+use swiftlib::log::{self, Level};
+
+/// 件数を指定しなかったときに表示する数
+const DEFAULT_LIMIT: usize = 50;
+
+struct Stdout;
+
+impl Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        io::print(s);
+        Ok(())
+    }
+}
+
+fn usage() -> i32 {
+    io::print(
+        "Usage: logread [-s SERVICE] [-l LEVEL] [-n COUNT]\n\
+         \x20 -s SERVICE  show only records from SERVICE (with or without .service)\n\
+         \x20 -l LEVEL    show only records at LEVEL or above\n\
+         \x20 -n COUNT    show the newest COUNT records (default 50, 0 for all that fit)\n\
+         LEVEL: trace, debug, info, warn, error\n\
+         Records are read from /var/log/messages.log and its rotated files.\n",
+    );
+    1
+}
+
+fn arg<'a>(argv: *const *const u8, i: i32) -> Option<&'a str> {
+    unsafe {
+        let arg_ptr = *argv.offset(i as isize);
+        if arg_ptr.is_null() {
+            return None;
+        }
+        let mut len = 0;
+        while *arg_ptr.offset(len) != 0 {
+            len += 1;
+        }
+        core::str::from_utf8(core::slice::from_raw_parts(arg_ptr, len as usize)).ok()
+    }
+}
+
+fn fail(errno: i64) -> i32 {
+    let reason = match errno {
+        -3 => "logd.service is not running",
+        -5 => "I/O error",
+        -22 => "invalid request",
+        -110 => "logd.service did not reply",
+        _ => "error",
+    };
+    let _ = writeln!(Stdout, "logread: {} ({})", reason, errno);
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn main(argc: i32, argv: *const *const u8) -> i32 {
+    let mut service = None;
+    let mut min_level = Level::Trace;
+    let mut limit = DEFAULT_LIMIT;
+
+    let mut i = 1;
+    while i < argc {
+        let Some(opt) = arg(argv, i) else {
+            return usage();
+        };
+        let Some(value) = arg(argv, i + 1).filter(|_| i + 1 < argc) else {
+            return usage();
+        };
+        match opt {
+            "-s" if value.len() <= log::MAX_SERVICE => service = Some(value),
+            "-l" => match Level::parse(value) {
+                Some(level) => min_level = level,
+                None => return usage(),
+            },
+            "-n" => match value.parse() {
+                Ok(n) => limit = n,
+                Err(_) => return usage(),
+            },
+            _ => return usage(),
+        }
+        i += 2;
+    }
+
+    match log::query(service, min_level, limit) {
+        Ok(text) => {
+            io::print(&text);
+            0
+        }
+        Err(errno) => fail(errno),
+    }
+}
@@ -0,0 +1,340 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use swiftlib::perf::{self, TraceRecord};
+use swiftlib::{io, strace, time};
+
+/// `perf trace` で一度に読む記録数
+const BATCH: usize = 64;
+/// `perf trace` で記録が無いときの待ち時間
+const TRACE_POLL_MS: u64 = 100;
+/// `perf record` で `/proc/profile` を読む間隔（カーネルのリングが溢れない程度）
+const RECORD_POLL_MS: u64 = 1000;
+
+struct Stdout;
+
+impl Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        io::print(s);
+        Ok(())
+    }
+}
+
+fn usage() -> i32 {
+    io::print(
+        "Usage: perf record [-F TICKS] [-o FILE] [SECONDS]\n\
+         \x20      perf report [-o FILE]\n\
+         \x20      perf trace [-e NAME[,NAME...]] [SECONDS]\n\
+         \x20      perf status\n\
+         \x20 record    sample call stacks for SECONDS (default 10) and print folded stacks\n\
+         \x20 report    print the samples collected so far (e.g. with the `profile` boot option)\n\
+         \x20 trace     print tracepoint records for SECONDS (default 5)\n\
+         \x20 -F TICKS  take a sample every TICKS timer ticks (1 tick = 10ms)\n\
+         \x20 -o FILE   write the folded stacks to FILE instead of stdout\n\
+         \x20 -e NAMES  enable the listed tracepoints while tracing\n\
+         NAME: sched, ipc_send, ipc_recv, fault, irq, syscall\n\
+         Starting the profiler or enabling tracepoints requires CAP_PERFMON;\n\
+         otherwise boot with `profile` or `trace=NAME,...`.\n\
+         Turn folded stacks into a flame graph with flamegraph.pl on the host.\n",
+    );
+    1
+}
+
+fn arg<'a>(argv: *const *const u8, i: i32) -> Option<&'a str> {
+    unsafe {
+        let arg_ptr = *argv.offset(i as isize);
+        if arg_ptr.is_null() {
+            return None;
+        }
+        let mut len = 0;
+        while *arg_ptr.offset(len) != 0 {
+            len += 1;
+        }
+        core::str::from_utf8(core::slice::from_raw_parts(arg_ptr, len as usize)).ok()
+    }
+}
+
+fn fail(what: &str, err: u64) -> i32 {
+    let _ = writeln!(Stdout, "perf: {}: error {}", what, err as i64);
+    1
+}
+
+/// `/proc/profile` を読み、folded 形式の各行を `stacks` に足し込む
+fn collect_profile(stacks: &mut BTreeMap<String, u64>) -> Result<(), u64> {
+    let fd = io::open(perf::PROFILE_PATH, io::O_RDONLY);
+    if fd < 0 {
+        return Err(fd as u64);
+    }
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = io::read(fd as u64, &mut buf);
+        if n == 0 || (n as i64) < 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n as usize]);
+    }
+    let _ = io::close(fd as u64);
+
+    let text = core::str::from_utf8(&data).unwrap_or("");
+    for line in text.lines() {
+        let Some((stack, count)) = line.rsplit_once(' ') else {
+            continue;
+        };
+        let Ok(count) = count.parse::<u64>() else {
+            continue;
+        };
+        *stacks.entry(String::from(stack)).or_insert(0) += count;
+    }
+    Ok(())
+}
+
+fn write_all(fd: u64, mut data: &[u8]) -> bool {
+    while !data.is_empty() {
+        let n = io::write(fd, data);
+        if (n as i64) <= 0 {
+            return false;
+        }
+        data = &data[(n as usize).min(data.len())..];
+    }
+    true
+}
+
+/// まとめたスタックを folded 形式で書き出す
+fn emit_folded(stacks: &BTreeMap<String, u64>, output: Option<&str>) -> i32 {
+    let mut text = String::new();
+    for (stack, count) in stacks {
+        let _ = writeln!(text, "{} {}", stack, count);
+    }
+    let Some(path) = output else {
+        io::print(&text);
+        return 0;
+    };
+    let fd = io::open(path, io::O_WRONLY | io::O_CREAT | io::O_TRUNC);
+    if fd < 0 {
+        return fail(path, fd as u64);
+    }
+    let ok = write_all(fd as u64, text.as_bytes());
+    let _ = io::close(fd as u64);
+    if !ok {
+        return fail(path, 0);
+    }
+    let total: u64 = stacks.values().sum();
+    let _ = writeln!(
+        Stdout,
+        "perf: wrote {} sample(s) in {} stack(s) to {}",
+        total,
+        stacks.len(),
+        path
+    );
+    0
+}
+
+fn record(interval: u64, seconds: u64, output: Option<&str>) -> i32 {
+    let status = match perf::status() {
+        Ok(s) => s,
+        Err(e) => return fail("status", e),
+    };
+    // 起動オプションなどで既に動いていれば、止めずにそのまま集める
+    let started = !status.profiling;
+    if started {
+        if let Err(e) = perf::profile_start(interval) {
+            return fail("start profiler (CAP_PERFMON required)", e);
+        }
+    }
+    let mut stacks = BTreeMap::new();
+    // 開始前に溜まっていたサンプルは捨てる
+    let _ = collect_profile(&mut BTreeMap::new());
+    let mut elapsed_ms = 0;
+    while elapsed_ms < seconds * 1000 {
+        time::sleep_ms(RECORD_POLL_MS);
+        elapsed_ms += RECORD_POLL_MS;
+        if let Err(e) = collect_profile(&mut stacks) {
+            return fail(perf::PROFILE_PATH, e);
+        }
+    }
+    if started {
+        let _ = perf::profile_stop();
+    }
+    if let Err(e) = collect_profile(&mut stacks) {
+        return fail(perf::PROFILE_PATH, e);
+    }
+    emit_folded(&stacks, output)
+}
+
+fn report(output: Option<&str>) -> i32 {
+    let mut stacks = BTreeMap::new();
+    if let Err(e) = collect_profile(&mut stacks) {
+        return fail(perf::PROFILE_PATH, e);
+    }
+    if stacks.is_empty() {
+        io::print("perf: no samples (start the profiler with `perf record` or boot with `profile`)\n");
+        return 1;
+    }
+    emit_folded(&stacks, output)
+}
+
+fn print_record(rec: &TraceRecord) {
+    if rec.id == perf::EVENT_LOST {
+        let _ = writeln!(
+            Stdout,
+            "--- lost {} record(s) on cpu{} ---",
+            rec.args[0], rec.cpu
+        );
+        return;
+    }
+    let _ = write!(
+        Stdout,
+        "[{:>5}.{:06}] cpu{} pid {} tid {} {}: ",
+        rec.time_ns / 1_000_000_000,
+        rec.time_ns % 1_000_000_000 / 1000,
+        rec.cpu,
+        rec.pid,
+        rec.tid,
+        perf::event_name(rec.id)
+    );
+    let [a0, a1, a2] = rec.args;
+    let _ = match rec.id {
+        perf::EVENT_SCHED => writeln!(Stdout, "{} -> {} (pid {})", a0, a1, a2),
+        perf::EVENT_IPC_SEND => writeln!(Stdout, "to tid {} len {}", a0, a1),
+        perf::EVENT_IPC_RECV => writeln!(Stdout, "from tid {} len {}", a0, a1),
+        perf::EVENT_FAULT => writeln!(Stdout, "addr {:#x} error {:#x} rip {:#x}", a0, a1, a2),
+        perf::EVENT_IRQ => writeln!(Stdout, "vector {}", a0),
+        perf::EVENT_SYSCALL => match strace::syscall_name(a0) {
+            Some(name) => writeln!(Stdout, "{}() = {} <{} ns>", name, a1 as i64, a2),
+            None => writeln!(Stdout, "syscall_{}() = {} <{} ns>", a0, a1 as i64, a2),
+        },
+        _ => writeln!(Stdout, "{:#x} {:#x} {:#x}", a0, a1, a2),
+    };
+}
+
+fn trace(mask: Option<u32>, seconds: u64) -> i32 {
+    let previous = match mask {
+        Some(mask) => match perf::set_events(mask) {
+            Ok(previous) => Some(previous),
+            Err(e) => return fail("enable tracepoints (CAP_PERFMON required)", e),
+        },
+        None => {
+            match perf::status() {
+                Ok(s) if s.events != 0 => {}
+                Ok(_) => {
+                    io::print("perf: no tracepoints enabled (use -e or boot with `trace=NAME,...`)\n");
+                    return 1;
+                }
+                Err(e) => return fail("status", e),
+            }
+            None
+        }
+    };
+
+    let mut records = [TraceRecord::empty(); BATCH];
+    let mut elapsed_ms = 0;
+    while elapsed_ms < seconds * 1000 {
+        let n = match perf::read_events(&mut records) {
+            Ok(n) => n,
+            Err(e) => return fail("read", e),
+        };
+        for rec in &records[..n] {
+            print_record(rec);
+        }
+        if n == 0 {
+            time::sleep_ms(TRACE_POLL_MS);
+            elapsed_ms += TRACE_POLL_MS;
+        }
+    }
+    if let Some(previous) = previous {
+        let _ = perf::set_events(previous);
+    }
+    0
+}
+
+fn status() -> i32 {
+    let status = match perf::status() {
+        Ok(s) => s,
+        Err(e) => return fail("status", e),
+    };
+    io::print("tracepoints:");
+    if status.events == 0 {
+        io::print(" (none)");
+    }
+    for id in 0..32 {
+        if status.events & (1 << id) != 0 {
+            let _ = write!(Stdout, " {}", perf::event_name(id));
+        }
+    }
+    let _ = writeln!(
+        Stdout,
+        "\nprofiler: {}",
+        if status.profiling {
+            "running"
+        } else {
+            "stopped"
+        }
+    );
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn main(argc: i32, argv: *const *const u8) -> i32 {
+    let Some(command) = arg(argv, 1).filter(|_| argc > 1) else {
+        return usage();
+    };
+    let mut interval = 1;
+    let mut output = None;
+    let mut mask = None;
+    let mut seconds = None;
+
+    let mut i = 2;
+    while i < argc {
+        let Some(a) = arg(argv, i) else {
+            return usage();
+        };
+        match a {
+            "-F" | "-o" | "-e" => {
+                i += 1;
+                let Some(value) = arg(argv, i) else {
+                    return usage();
+                };
+                match a {
+                    "-F" => match value.parse::<u64>() {
+                        Ok(n) if n > 0 => interval = n,
+                        _ => return usage(),
+                    },
+                    "-o" => output = Some(value),
+                    _ => {
+                        let mut bits = 0;
+                        for name in value.split(',') {
+                            match perf::parse_event(name) {
+                                Some(id) => bits |= 1 << id,
+                                None if name == "all" => bits |= perf::ALL_EVENTS,
+                                None => {
+                                    let _ = writeln!(Stdout, "perf: unknown tracepoint '{}'", name);
+                                    return 1;
+                                }
+                            }
+                        }
+                        mask = Some(bits);
+                    }
+                }
+            }
+            _ => match a.parse::<u64>() {
+                Ok(n) if seconds.is_none() => seconds = Some(n),
+                _ => return usage(),
+            },
+        }
+        i += 1;
+    }
+
+    match command {
+        "record" => record(interval, seconds.unwrap_or(10), output),
+        "report" => report(output),
+        "trace" => trace(mask, seconds.unwrap_or(5)),
+        "status" => status(),
+        _ => usage(),
+    }
+}
@@ -58,6 +58,7 @@ pub extern "C" fn main(_argc: i32, _argv: *const *const u8) -> i32 {
             2 => "Blocked",
             3 => "Sleeping",
             4 => "Terminated",
+            5 => "Stopped",
             _ => "Unknown",
         };
         print_number(pid);
@@ -0,0 +1,514 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use swiftlib::strace::{self, TraceEvent};
+use swiftlib::{io, process, task, time};
+
+/// 一度に読み出すイベント数
+const BATCH: usize = 32;
+/// イベントが無いときの待ち時間
+const POLL_MS: u64 = 10;
+/// openat などの AT_FDCWD
+const AT_FDCWD: i64 = -100;
+
+struct Stdout;
+
+impl Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        io::print(s);
+        Ok(())
+    }
+}
+
+fn usage() -> i32 {
+    io::print(
+        "Usage: strace [-f] [-T] [-e NAME[,NAME...]] (-p PID | CMD [ARGS...])\n\
+         \x20 -f        also trace children created by fork\n\
+         \x20 -T        show the time spent in each syscall\n\
+         \x20 -e NAMES  trace only the listed syscalls\n\
+         \x20 -p PID    attach to a running process (requires CAP_SYS_PTRACE\n\
+         \x20           unless PID is a child of strace)\n",
+    );
+    1
+}
+
+fn arg<'a>(argv: *const *const u8, i: i32) -> Option<&'a str> {
+    unsafe {
+        let arg_ptr = *argv.offset(i as isize);
+        if arg_ptr.is_null() {
+            return None;
+        }
+        let mut len = 0;
+        while *arg_ptr.offset(len) != 0 {
+            len += 1;
+        }
+        core::str::from_utf8(core::slice::from_raw_parts(arg_ptr, len as usize)).ok()
+    }
+}
+
+fn fail(what: &str, err: u64) -> i32 {
+    let _ = writeln!(Stdout, "strace: {}: error {}", what, err as i64);
+    1
+}
+
+/// 引数の表示のしかた
+#[derive(Clone, Copy, PartialEq)]
+enum Arg {
+    Int,
+    Hex,
+    Oct,
+    Fd,
+    /// AT_FDCWD を名前で出す fd
+    DirFd,
+    /// 入口で写し取ったパス
+    Path,
+    /// write の入力バッファ（入口で写し取る）
+    InBuf,
+    /// read の出力バッファ（出口で写し取る）
+    OutBuf,
+    OpenFlags,
+}
+
+/// syscall ごとの引数の並び（知らないものは 16 進で 3 個）
+fn signature(name: &str) -> &'static [Arg] {
+    use Arg::*;
+    match name {
+        "read" => &[Fd, OutBuf, Int],
+        "write" => &[Fd, InBuf, Int],
+        "open" => &[Path, OpenFlags, Oct],
+        "openat" => &[DirFd, Path, OpenFlags, Oct],
+        "close" | "fsync" | "fdatasync" | "dup" => &[Fd],
+        "dup2" => &[Fd, Fd],
+        "stat" | "lstat" | "statfs" => &[Path, Hex],
+        "fstat" => &[Fd, Hex],
+        "newfstatat" => &[DirFd, Path, Hex, Hex],
+        "access" => &[Path, Oct],
+        "faccessat" => &[DirFd, Path, Oct, Hex],
+        "mkdir" => &[Path, Oct],
+        "rmdir" | "chdir" | "unlink" => &[Path],
+        "unlinkat" => &[DirFd, Path, Hex],
+        "readlink" => &[Path, Hex, Int],
+        "readlinkat" => &[DirFd, Path, Hex, Int],
+        "truncate" => &[Path, Int],
+        "ftruncate" => &[Fd, Int],
+        "lseek" => &[Fd, Int, Int],
+        "ioctl" | "fcntl" => &[Fd, Hex, Hex],
+        "getdents64" | "readdir" => &[Fd, Hex, Int],
+        "mmap" => &[Hex, Int, Hex, Hex, Fd, Int],
+        "munmap" | "mprotect" => &[Hex, Int, Hex],
+        "brk" => &[Hex],
+        "execve" => &[Path, Hex, Hex],
+        "exec" | "exec_from_fs_stream" => &[Path],
+        "exit" | "exit_group" => &[Int],
+        "kill" | "tkill" => &[Int, Int],
+        "tgkill" => &[Int, Int, Int],
+        "wait4" => &[Int, Hex, Hex, Hex],
+        "getpid" | "gettid" | "getppid" | "getuid" | "getgid" | "geteuid" | "getegid" | "fork"
+        | "yield" | "get_ticks" | "setsid" => &[],
+        "sleep" => &[Int],
+        "ipc_send" => &[Int, Hex, Int],
+        "ipc_recv" | "ipc_recv_wait" => &[Hex, Hex, Int],
+        _ => &[Hex, Hex, Hex],
+    }
+}
+
+/// 戻り値がアドレスの syscall
+fn returns_address(name: &str) -> bool {
+    matches!(name, "mmap" | "brk")
+}
+
+const OPEN_FLAGS: &[(u64, &str)] = &[
+    (0o100, "O_CREAT"),
+    (0o200, "O_EXCL"),
+    (0o400, "O_NOCTTY"),
+    (0o1000, "O_TRUNC"),
+    (0o2000, "O_APPEND"),
+    (0o4000, "O_NONBLOCK"),
+    (0o200000, "O_DIRECTORY"),
+    (0o2000000, "O_CLOEXEC"),
+];
+
+fn write_open_flags(out: &mut impl Write, flags: u64) -> fmt::Result {
+    out.write_str(match flags & 3 {
+        0 => "O_RDONLY",
+        1 => "O_WRONLY",
+        2 => "O_RDWR",
+        _ => "O_ACCMODE",
+    })?;
+    let mut rest = flags & !3;
+    for &(bit, name) in OPEN_FLAGS {
+        if rest & bit != 0 {
+            write!(out, "|{}", name)?;
+            rest &= !bit;
+        }
+    }
+    if rest != 0 {
+        write!(out, "|{:#o}", rest)?;
+    }
+    Ok(())
+}
+
+/// バイト列を C 文字列風にエスケープして引用符で囲む
+fn write_quoted(out: &mut impl Write, data: &[u8], truncated: bool) -> fmt::Result {
+    out.write_char('"')?;
+    for &b in data {
+        match b {
+            b'"' => out.write_str("\\\"")?,
+            b'\\' => out.write_str("\\\\")?,
+            b'\n' => out.write_str("\\n")?,
+            b'\r' => out.write_str("\\r")?,
+            b'\t' => out.write_str("\\t")?,
+            0x20..=0x7e => out.write_char(b as char)?,
+            _ => write!(out, "\\x{:02x}", b)?,
+        }
+    }
+    out.write_char('"')?;
+    if truncated {
+        out.write_str("...")?;
+    }
+    Ok(())
+}
+
+const ERRNO_NAMES: &[(i64, &str)] = &[
+    (1, "EPERM"),
+    (2, "ENOENT"),
+    (3, "ESRCH"),
+    (4, "EINTR"),
+    (5, "EIO"),
+    (6, "ENXIO"),
+    (7, "E2BIG"),
+    (9, "EBADF"),
+    (10, "ECHILD"),
+    (11, "EAGAIN"),
+    (12, "ENOMEM"),
+    (13, "EACCES"),
+    (14, "EFAULT"),
+    (16, "EBUSY"),
+    (17, "EEXIST"),
+    (20, "ENOTDIR"),
+    (21, "EISDIR"),
+    (22, "EINVAL"),
+    (24, "EMFILE"),
+    (25, "ENOTTY"),
+    (28, "ENOSPC"),
+    (32, "EPIPE"),
+    (34, "ERANGE"),
+    (36, "ENAMETOOLONG"),
+    (38, "ENOSYS"),
+    (39, "ENOTEMPTY"),
+    (61, "ENODATA"),
+    (95, "ENOTSUP"),
+    (110, "ETIMEDOUT"),
+];
+
+fn write_ret(out: &mut impl Write, name: &str, ret: u64) -> fmt::Result {
+    let value = ret as i64;
+    if (-4095..0).contains(&value) {
+        let errno = -value;
+        match ERRNO_NAMES.iter().find(|(n, _)| *n == errno) {
+            Some((_, e)) => write!(out, "-1 {}", e),
+            None => write!(out, "-1 errno {}", errno),
+        }
+    } else if returns_address(name) {
+        write!(out, "{:#x}", ret)
+    } else {
+        write!(out, "{}", value)
+    }
+}
+
+fn name_of(nr: u64) -> Option<&'static str> {
+    strace::syscall_name(nr)
+}
+
+/// 引数を表示する（`exit` は read の出力バッファを写し取った出口イベント）
+fn write_args(out: &mut impl Write, enter: &TraceEvent, exit: Option<&TraceEvent>) -> fmt::Result {
+    let name = name_of(enter.nr).unwrap_or("");
+    for (i, kind) in signature(name).iter().enumerate() {
+        if i > 0 {
+            out.write_str(", ")?;
+        }
+        let value = enter.args[i];
+        match kind {
+            Arg::Int => write!(out, "{}", value as i64)?,
+            Arg::Hex => write!(out, "{:#x}", value)?,
+            Arg::Oct => write!(out, "{:#o}", value)?,
+            Arg::Fd => write!(out, "{}", value as i64)?,
+            Arg::DirFd if value as i64 == AT_FDCWD => out.write_str("AT_FDCWD")?,
+            Arg::DirFd => write!(out, "{}", value as i64)?,
+            Arg::Path if enter.data_len > 0 => write_quoted(out, enter.data(), false)?,
+            Arg::InBuf if enter.data_len > 0 => {
+                let truncated = enter.args[i + 1] > enter.data_len as u64;
+                write_quoted(out, enter.data(), truncated)?
+            }
+            Arg::OutBuf => match exit {
+                Some(exit) if exit.data_len > 0 => {
+                    write_quoted(out, exit.data(), (exit.ret as i64) > exit.data_len as i64)?
+                }
+                _ => write!(out, "{:#x}", value)?,
+            },
+            Arg::Path | Arg::InBuf => write!(out, "{:#x}", value)?,
+            Arg::OpenFlags => write_open_flags(out, value)?,
+        }
+    }
+    Ok(())
+}
+
+struct Tracer {
+    /// 複数プロセスを追うときは行頭に pid を付ける
+    show_pid: bool,
+    show_time: bool,
+    /// 表示する syscall 番号（空なら全部）
+    filter: Vec<u64>,
+    /// 出口を待っている入口（次のイベントが対応する出口なら 1 行にまとめる）
+    held: Option<TraceEvent>,
+    /// `<unfinished ...>` を出して出口を待っている入口
+    unfinished: Vec<TraceEvent>,
+}
+
+impl Tracer {
+    fn wanted(&self, nr: u64) -> bool {
+        self.filter.is_empty() || self.filter.contains(&nr)
+    }
+
+    fn prefix(&self, pid: u64) {
+        if self.show_pid {
+            let _ = write!(Stdout, "[pid {:>4}] ", pid);
+        }
+    }
+
+    fn write_name(&self, nr: u64) {
+        let _ = match name_of(nr) {
+            Some(name) => Stdout.write_str(name),
+            None => write!(Stdout, "syscall_{}", nr),
+        };
+    }
+
+    fn write_result(&self, exit: &TraceEvent) {
+        io::print(" = ");
+        let _ = write_ret(&mut Stdout, name_of(exit.nr).unwrap_or(""), exit.ret);
+        if self.show_time {
+            let ns = exit.duration_ns;
+            let _ = write!(
+                Stdout,
+                " <{}.{:06}>",
+                ns / 1_000_000_000,
+                ns % 1_000_000_000 / 1000
+            );
+        }
+        io::print("\n");
+    }
+
+    /// 保留中の入口を `<unfinished ...>` として出す
+    fn flush_held(&mut self) {
+        let Some(enter) = self.held.take() else {
+            return;
+        };
+        self.prefix(enter.pid);
+        self.write_name(enter.nr);
+        io::print("(");
+        let _ = write_args(&mut Stdout, &enter, None);
+        io::print(" <unfinished ...>\n");
+        self.unfinished.push(enter);
+    }
+
+    fn handle(&mut self, ev: &TraceEvent) {
+        match ev.kind {
+            strace::TRACE_ENTER => {
+                self.flush_held();
+                if self.wanted(ev.nr) {
+                    self.held = Some(*ev);
+                }
+            }
+            strace::TRACE_EXIT => {
+                if !self.wanted(ev.nr) {
+                    return;
+                }
+                if let Some(enter) = self.held.take() {
+                    if enter.tid == ev.tid && enter.seq == ev.seq {
+                        self.prefix(enter.pid);
+                        self.write_name(enter.nr);
+                        io::print("(");
+                        let _ = write_args(&mut Stdout, &enter, Some(ev));
+                        io::print(")");
+                        self.write_result(ev);
+                        return;
+                    }
+                    self.held = Some(enter);
+                    self.flush_held();
+                }
+                let pos = self
+                    .unfinished
+                    .iter()
+                    .position(|e| e.tid == ev.tid && e.seq == ev.seq);
+                if let Some(pos) = pos {
+                    self.unfinished.swap_remove(pos);
+                }
+                self.prefix(ev.pid);
+                io::print("<... ");
+                self.write_name(ev.nr);
+                io::print(" resumed>");
+                if ev.nr == swiftlib::sys::SyscallNumber::Read as u64 && ev.data_len > 0 {
+                    io::print(" ");
+                    let _ =
+                        write_quoted(&mut Stdout, ev.data(), (ev.ret as i64) > ev.data_len as i64);
+                }
+                io::print(")");
+                self.write_result(ev);
+            }
+            strace::TRACE_PROC_EXIT => {
+                // exit / exit_group は戻ってこない
+                if let Some(enter) = self.held.take() {
+                    if enter.pid == ev.pid {
+                        self.prefix(enter.pid);
+                        self.write_name(enter.nr);
+                        io::print("(");
+                        let _ = write_args(&mut Stdout, &enter, None);
+                        io::print(") = ?\n");
+                    } else {
+                        self.held = Some(enter);
+                        self.flush_held();
+                    }
+                }
+                self.unfinished.retain(|e| e.pid != ev.pid);
+                self.prefix(ev.pid);
+                let _ = writeln!(Stdout, "+++ exited with {} +++", ev.ret as i64);
+            }
+            strace::TRACE_LOST => {
+                self.flush_held();
+                let _ = writeln!(Stdout, "--- lost {} event(s) ---", ev.ret);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 次の子を対象にしてから fork し、子で `program` を execve する
+fn launch(program: &str, args: &[&str], flags: u64) -> Result<u64, u64> {
+    strace::attach_next_child(flags)?;
+    let pid = task::fork();
+    if pid < 0 {
+        let _ = strace::detach(0);
+        return Err(pid as u64);
+    }
+    if pid == 0 {
+        let err = process::execve(program, args);
+        let _ = writeln!(Stdout, "strace: execve {} failed ({})", program, err);
+        task::exit(127);
+    }
+    Ok(pid as u64)
+}
+
+#[no_mangle]
+pub extern "C" fn main(argc: i32, argv: *const *const u8) -> i32 {
+    let mut follow = false;
+    let mut show_time = false;
+    let mut filter = Vec::new();
+    let mut attach_pid = None;
+
+    let mut i = 1;
+    while i < argc {
+        let Some(a) = arg(argv, i) else {
+            return usage();
+        };
+        match a {
+            "-f" => follow = true,
+            "-T" => show_time = true,
+            "-e" => {
+                i += 1;
+                let Some(names) = arg(argv, i) else {
+                    return usage();
+                };
+                for name in names.split(',') {
+                    match strace::syscall_number(name) {
+                        Some(nr) => filter.push(nr),
+                        None => {
+                            let _ = writeln!(Stdout, "strace: unknown syscall '{}'", name);
+                            return 1;
+                        }
+                    }
+                }
+            }
+            "-p" => {
+                i += 1;
+                let Some(pid) = arg(argv, i).and_then(|p| p.parse::<u64>().ok()) else {
+                    return usage();
+                };
+                attach_pid = Some(pid);
+            }
+            _ if a.starts_with('-') => return usage(),
+            _ => break,
+        }
+        i += 1;
+    }
+
+    let flags = if follow {
+        strace::STRACE_FOLLOW_FORK
+    } else {
+        0
+    };
+    let child = match attach_pid {
+        Some(pid) => {
+            if i < argc {
+                return usage();
+            }
+            if let Err(e) = strace::attach(pid, flags) {
+                return fail("attach", e);
+            }
+            None
+        }
+        None => {
+            let Some(program) = arg(argv, i) else {
+                return usage();
+            };
+            let mut args = Vec::new();
+            for j in i + 1..argc {
+                let Some(a) = arg(argv, j) else {
+                    return usage();
+                };
+                args.push(a);
+            }
+            match launch(program, &args, flags) {
+                Ok(pid) => Some(pid),
+                Err(e) => return fail("launch", e),
+            }
+        }
+    };
+
+    let mut tracer = Tracer {
+        show_pid: follow,
+        show_time,
+        filter,
+        held: None,
+        unfinished: Vec::new(),
+    };
+    let mut events = [TraceEvent::empty(); BATCH];
+    loop {
+        // 対象が居なくなってから読めば、残りのイベントは全部積まれている
+        let done = strace::target_count().unwrap_or(0) == 0;
+        let n = match strace::read(&mut events) {
+            Ok(n) => n,
+            Err(e) => return fail("read", e),
+        };
+        for ev in &events[..n] {
+            tracer.handle(ev);
+        }
+        if n > 0 {
+            continue;
+        }
+        // 止まっている syscall の入口は待たずに出す
+        tracer.flush_held();
+        if done {
+            break;
+        }
+        time::sleep_ms(POLL_MS);
+    }
+
+    match child.map(|pid| task::wait4(pid as i64, 0)) {
+        Some(Ok(r)) if r.exited() => r.exit_status(),
+        Some(_) => 1,
+        None => 0,
+    }
+}
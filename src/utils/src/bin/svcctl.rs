@@ -0,0 +1,166 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+use core::fmt::{self, Write};
+use swiftlib::io;
+use swiftlib::service::{self, ServiceStatus};
+
+struct Stdout;
+
+impl Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        io::print(s);
+        Ok(())
+    }
+}
+
+fn usage() -> i32 {
+    io::print(
+        "Usage: svcctl list\n\
+         \x20      svcctl status NAME\n\
+         \x20      svcctl start|stop|restart NAME\n\
+         \x20      svcctl enable|disable NAME\n\
+         \x20      svcctl logs NAME\n\
+         \x20 list      show every service known to core.service\n\
+         \x20 status    show the PID, uptime, restart count and last exit code\n\
+         \x20 start     start a stopped service (after its dependencies are ready)\n\
+         \x20 stop      send SIGTERM, then SIGKILL if it does not exit within 3s\n\
+         \x20 restart   stop and start again\n\
+         \x20 enable    start the service automatically from the next boot\n\
+         \x20 disable   do not start the service automatically from the next boot\n\
+         \x20 logs      show what core.service recorded about the service\n\
+         NAME may be given with or without the .service suffix.\n",
+    );
+    1
+}
+
+fn arg<'a>(argv: *const *const u8, i: i32) -> Option<&'a str> {
+    unsafe {
+        let arg_ptr = *argv.offset(i as isize);
+        if arg_ptr.is_null() {
+            return None;
+        }
+        let mut len = 0;
+        while *arg_ptr.offset(len) != 0 {
+            len += 1;
+        }
+        core::str::from_utf8(core::slice::from_raw_parts(arg_ptr, len as usize)).ok()
+    }
+}
+
+fn fail(what: &str, errno: i64) -> i32 {
+    let reason = match errno {
+        -1 => "permission denied",
+        -2 => "no such service",
+        -3 => "core.service is not running",
+        -5 => "I/O error",
+        -22 => "invalid request",
+        -110 => "core.service did not reply",
+        _ => "error",
+    };
+    let _ = writeln!(Stdout, "svcctl: {}: {} ({})", what, reason, errno);
+    1
+}
+
+/// "1h02m03s" の形
+fn write_uptime(out: &mut Stdout, ms: u64) {
+    let secs = ms / 1000;
+    let _ = match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => write!(out, "{}s", s),
+        (0, m, s) => write!(out, "{}m{:02}s", m, s),
+        (h, m, s) => write!(out, "{}h{:02}m{:02}s", h, m, s),
+    };
+}
+
+fn print_row(status: &ServiceStatus) {
+    let mut out = Stdout;
+    let _ = write!(
+        out,
+        "{:<20} {:<9} {:<5} ",
+        status.name(),
+        status.state.as_str(),
+        if status.enabled { "yes" } else { "no" }
+    );
+    if status.pid != 0 {
+        let _ = write!(out, "{:<6} ", status.pid);
+        write_uptime(&mut out, status.uptime_ms);
+    } else {
+        let _ = write!(out, "{:<6} -", "-");
+    }
+    let _ = writeln!(out);
+}
+
+fn print_status(status: &ServiceStatus) {
+    let mut out = Stdout;
+    let _ = writeln!(out, "{}", status.name());
+    let _ = writeln!(out, "  state:     {}", status.state.as_str());
+    let _ = writeln!(
+        out,
+        "  enabled:   {}",
+        if status.enabled { "yes" } else { "no" }
+    );
+    if status.pid != 0 {
+        let _ = writeln!(out, "  pid:       {}", status.pid);
+        let _ = write!(out, "  uptime:    ");
+        write_uptime(&mut out, status.uptime_ms);
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "  ready:     {}",
+            if status.ready { "yes" } else { "no" }
+        );
+    }
+    let _ = writeln!(out, "  restarts:  {}", status.restarts);
+    match status.last_exit {
+        Some(code) => {
+            let _ = writeln!(out, "  last exit: {}", code);
+        }
+        None => {
+            let _ = writeln!(out, "  last exit: -");
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn main(argc: i32, argv: *const *const u8) -> i32 {
+    let Some(command) = arg(argv, 1).filter(|_| argc > 1) else {
+        return usage();
+    };
+    if command == "list" {
+        return match service::list() {
+            Ok(services) => {
+                let _ = writeln!(
+                    Stdout,
+                    "{:<20} {:<9} {:<5} {:<6} UPTIME",
+                    "SERVICE", "STATE", "AUTO", "PID"
+                );
+                for status in &services {
+                    print_row(status);
+                }
+                0
+            }
+            Err(errno) => fail("list", errno),
+        };
+    }
+
+    let Some(name) = arg(argv, 2).filter(|_| argc == 3) else {
+        return usage();
+    };
+    let result = match command {
+        "status" => service::status(name).map(|status| print_status(&status)),
+        "start" => service::start(name),
+        "stop" => service::stop(name),
+        "restart" => service::restart(name),
+        "enable" => service::enable(name),
+        "disable" => service::disable(name),
+        "logs" => service::logs(name).map(|text| {
+            io::print(&text);
+        }),
+        _ => return usage(),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(errno) => fail(name, errno),
+    }
+}